@@ -0,0 +1,467 @@
+use crate::release_notes::GitHubReleaseNotes;
+use crate::score::Adjustment;
+use crate::version::ResolvedVersions;
+use crate::{UpgradeError, UpgradeRequest, UpgradeWorker};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Issues opened this long after a release are compared with as long before
+const ISSUE_WINDOW_DAYS: i64 = 14;
+
+/// How widely a target version has been adopted, in
+/// [`crate::UpgradeResponse::adoption`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AdoptionSignals {
+    /// Days since the target was published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_age_days: Option<i64>,
+    /// Recent downloads of the package, as [`crate::registry::Registry::downloads`]
+    /// counts them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<u64>,
+    /// Share of the package's downloads going to the target version, as a
+    /// stand-in for the dependents already on it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_share: Option<f64>,
+    /// Issues opened in the two weeks after the target's release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issues_after_release: Option<u64>,
+    /// Issues opened in as long a period before it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issues_before_release: Option<u64>,
+}
+
+impl AdoptionSignals {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Factor the popularity signal of the compatibility score is scaled
+    /// by: fresh, little used or issue-ridden releases lower it,
+    /// established ones raise it.
+    pub fn multiplier(&self) -> f64 {
+        self.adjustments()
+            .iter()
+            .map(|adjustment| adjustment.multiplier)
+            .product()
+    }
+
+    /// The adjustments making up [`Self::multiplier`], one per signal that
+    /// moves it.
+    pub fn adjustments(&self) -> Vec<Adjustment> {
+        let mut adjustments = Vec::new();
+        match self.target_age_days {
+            Some(age) if age < 3 => adjustments.push(Adjustment::new(
+                "target_age",
+                0.85,
+                "registry",
+                format!(
+                    "the target was published {} day{} ago",
+                    age,
+                    if age == 1 { "" } else { "s" }
+                ),
+            )),
+            Some(age) if age < 14 => adjustments.push(Adjustment::new(
+                "target_age",
+                0.95,
+                "registry",
+                format!("the target was published {} days ago", age),
+            )),
+            Some(age) if age >= 90 => adjustments.push(Adjustment::new(
+                "target_age",
+                1.05,
+                "registry",
+                format!("the target has been out for {} days", age),
+            )),
+            _ => {}
+        }
+        match self.target_share {
+            Some(share) if share >= 0.2 => adjustments.push(Adjustment::new(
+                "target_share",
+                1.1,
+                "registry",
+                format!("{:.0}% of downloads go to the target", share * 100.0),
+            )),
+            Some(share) if share < 0.01 && self.target_age_days.is_some_and(|age| age >= 30) => {
+                adjustments.push(Adjustment::new(
+                    "target_share",
+                    0.9,
+                    "registry",
+                    format!(
+                        "only {:.1}% of downloads go to a target out for a month",
+                        share * 100.0
+                    ),
+                ))
+            }
+            _ => {}
+        }
+        if let Some(downloads) = self.downloads.filter(|downloads| *downloads < 1000) {
+            adjustments.push(Adjustment::new(
+                "downloads",
+                0.95,
+                "registry",
+                format!("the package has only {} recent downloads", downloads),
+            ));
+        }
+        if let (Some(after), Some(before)) = (self.issues_after_release, self.issues_before_release)
+        {
+            if after >= 5 && after > 2 * before {
+                adjustments.push(Adjustment::new(
+                    "issue_spike",
+                    0.85,
+                    "issue_tracker",
+                    format!(
+                        "{} issues were opened after the release against {} before",
+                        after, before
+                    ),
+                ));
+            }
+        }
+        adjustments
+    }
+}
+
+/// A period of time, from its start up to its end.
+pub type Window = (DateTime<Utc>, DateTime<Utc>);
+
+/// The period before a release, and the period after it issues are counted
+/// in; `None` for a release less than a day old.
+pub fn issue_windows(published: DateTime<Utc>, now: DateTime<Utc>) -> Option<(Window, Window)> {
+    let end = now.min(published + chrono::Duration::days(ISSUE_WINDOW_DAYS));
+    let length = end - published;
+    (length >= chrono::Duration::days(1))
+        .then(|| ((published - length, published), (published, end)))
+}
+
+/// Counts of the issues opened against a package's repository.
+#[async_trait]
+pub trait IssueActivity: Send + Sync {
+    /// Issues opened between `from` and `to`; `None` when the package's
+    /// repository is not known.
+    async fn issues_opened(
+        &self,
+        ecosystem: &str,
+        package: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Option<u64>, UpgradeError>;
+}
+
+/// Counts issues with GitHub's issue search, in the repository and with
+/// the token of a release notes source.
+pub struct GitHubIssueActivity {
+    github: Arc<GitHubReleaseNotes>,
+}
+
+impl GitHubIssueActivity {
+    pub fn new(github: Arc<GitHubReleaseNotes>) -> Self {
+        Self { github }
+    }
+}
+
+#[async_trait]
+impl IssueActivity for GitHubIssueActivity {
+    async fn issues_opened(
+        &self,
+        ecosystem: &str,
+        package: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Option<u64>, UpgradeError> {
+        let Some(repository) = self.github.repository(ecosystem, package).await? else {
+            return Ok(None);
+        };
+        let format = "%Y-%m-%dT%H:%M:%SZ";
+        let url = format!(
+            "{}/search/issues?q=repo:{}+is:issue+created:{}..{}&per_page=1",
+            self.github.api_url(),
+            repository,
+            from.format(format),
+            to.format(format)
+        );
+        let Some(body) = self.github.get(&url, "application/vnd.github+json").await? else {
+            return Ok(None);
+        };
+        let results: serde_json::Value =
+            serde_json::from_str(&body).unwrap_or(serde_json::Value::Null);
+        Ok(results["total_count"].as_u64())
+    }
+}
+
+/// Canned issue counts, useful for tests.
+#[derive(Debug, Clone, Default)]
+pub struct StaticIssueActivity {
+    issues: HashMap<(String, String), Vec<DateTime<Utc>>>,
+}
+
+impl StaticIssueActivity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an issue opened at `opened`.
+    pub fn with_issue(mut self, ecosystem: &str, package: &str, opened: DateTime<Utc>) -> Self {
+        self.issues
+            .entry((ecosystem.to_string(), package.to_string()))
+            .or_default()
+            .push(opened);
+        self
+    }
+}
+
+#[async_trait]
+impl IssueActivity for StaticIssueActivity {
+    async fn issues_opened(
+        &self,
+        ecosystem: &str,
+        package: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Option<u64>, UpgradeError> {
+        Ok(self
+            .issues
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .map(|opened| opened.iter().filter(|at| **at >= from && **at < to).count() as u64))
+    }
+}
+
+type CacheKey = (String, String, String);
+
+/// Adoption signals per package version, kept for `ttl` so repeated
+/// upgrades to the same version do not query registries again.
+pub struct AdoptionCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, (Instant, AdoptionSignals)>>,
+}
+
+impl AdoptionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<AdoptionSignals> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(key)
+            .filter(|(fetched, _)| fetched.elapsed() < self.ttl)
+            .map(|(_, signals)| signals.clone())
+    }
+
+    fn insert(&self, key: CacheKey, signals: AdoptionSignals) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (fetched, _)| fetched.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), signals));
+    }
+}
+
+impl Default for AdoptionCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(3600))
+    }
+}
+
+impl UpgradeWorker {
+    /// Gathers the adoption signals of the target version the registry and
+    /// issue tracker publish. Signals that cannot be fetched are left out,
+    /// with a warning, and the rest are cached.
+    pub(crate) async fn adoption_signals(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        warnings: &mut Vec<String>,
+    ) -> Option<AdoptionSignals> {
+        let key = (
+            request.ecosystem.clone(),
+            request.package_name.clone(),
+            versions.target.to_string(),
+        );
+        if let Some(signals) = self.adoption_cache.get(&key) {
+            return (!signals.is_empty()).then_some(signals);
+        }
+
+        let mut signals = AdoptionSignals::default();
+        let mut failures = Vec::new();
+        let registry = self
+            .registry
+            .as_ref()
+            .filter(|registry| registry.supports(&request.ecosystem));
+        let scheme = self.version_scheme(request);
+        let is_target = |version: &str| scheme.parse(version).is_ok_and(|v| v == versions.target);
+        let now = Utc::now();
+
+        let mut published = None;
+        if let Some(registry) = registry {
+            match self.registry_releases(request).await {
+                Ok(releases) => {
+                    published = releases
+                        .unwrap_or_default()
+                        .iter()
+                        .find(|release| is_target(&release.version))
+                        .and_then(|release| release.published_at.as_deref())
+                        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                        .map(|at| at.with_timezone(&Utc));
+                    signals.target_age_days = published.map(|at| (now - at).num_days());
+                }
+                Err(e) => failures.push(e.message),
+            }
+            match registry
+                .downloads(&request.ecosystem, &request.package_name)
+                .await
+            {
+                Ok(downloads) => signals.downloads = downloads,
+                Err(e) => failures.push(e.message),
+            }
+            match registry
+                .version_downloads(&request.ecosystem, &request.package_name)
+                .await
+            {
+                Ok(downloads) => {
+                    let total: u64 = downloads.values().sum();
+                    let target: u64 = downloads
+                        .iter()
+                        .filter(|(version, _)| is_target(version))
+                        .map(|(_, downloads)| downloads)
+                        .sum();
+                    signals.target_share = (total > 0).then(|| target as f64 / total as f64);
+                }
+                Err(e) => failures.push(e.message),
+            }
+        }
+
+        if let (Some(issues), Some((before, after))) = (
+            &self.issue_activity,
+            published.and_then(|at| issue_windows(at, now)),
+        ) {
+            let count = |(from, to): Window| {
+                issues.issues_opened(&request.ecosystem, &request.package_name, from, to)
+            };
+            match (count(before).await, count(after).await) {
+                (Ok(before), Ok(after)) => {
+                    signals.issues_before_release = before;
+                    signals.issues_after_release = after;
+                }
+                (Err(e), _) | (_, Err(e)) => failures.push(e.message),
+            }
+        }
+
+        if failures.is_empty() {
+            self.adoption_cache.insert(key, signals.clone());
+        } else {
+            warnings.push(format!(
+                "Adoption signals incomplete: {}",
+                failures.join("; ")
+            ));
+        }
+        (!signals.is_empty()).then_some(signals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+
+    #[test]
+    fn test_multiplier() {
+        let established = AdoptionSignals {
+            target_age_days: Some(200),
+            downloads: Some(5_000_000),
+            target_share: Some(0.4),
+            ..Default::default()
+        };
+        assert!((established.multiplier() - 1.05 * 1.1).abs() < 1e-9);
+
+        let troubled = AdoptionSignals {
+            target_age_days: Some(5),
+            issues_before_release: Some(3),
+            issues_after_release: Some(12),
+            ..Default::default()
+        };
+        assert!((troubled.multiplier() - 0.95 * 0.85).abs() < 1e-9);
+        assert_eq!(AdoptionSignals::default().multiplier(), 1.0);
+
+        let published = DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let (before, after) =
+            issue_windows(published, published + chrono::Duration::days(5)).unwrap();
+        assert_eq!(before.0, published - chrono::Duration::days(5));
+        assert_eq!(after.1, published + chrono::Duration::days(5));
+        assert!(issue_windows(published, published + chrono::Duration::hours(6)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_adoption_signals_scale_compatibility() {
+        let days_ago = |days: i64| Utc::now() - chrono::Duration::days(days);
+        let release = |version: &str, published: DateTime<Utc>| ReleaseInfo {
+            published_at: Some(published.to_rfc3339()),
+            ..ReleaseInfo::new(version)
+        };
+        let registry = StaticRegistry::new()
+            .with_releases(
+                "npm",
+                "left-pad",
+                vec![
+                    release("1.2.0", days_ago(400)),
+                    release("1.3.0", days_ago(300)),
+                    release("1.4.0", days_ago(2)),
+                ],
+            )
+            .with_downloads("npm", "left-pad", 2_000_000)
+            .with_version_downloads("npm", "left-pad", "1.2.0", 200_000)
+            .with_version_downloads("npm", "left-pad", "1.3.0", 1_790_000)
+            .with_version_downloads("npm", "left-pad", "1.4.0", 10_000);
+        let mut issues = StaticIssueActivity::new();
+        for hours in 1..=6 {
+            issues = issues.with_issue(
+                "npm",
+                "left-pad",
+                Utc::now() - chrono::Duration::hours(hours),
+            );
+        }
+        let worker = UpgradeWorker::new(None)
+            .with_registry(Arc::new(registry))
+            .with_issue_activity(Arc::new(issues));
+        let request = |target: &str| UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "left-pad".to_string(),
+            current_version: "1.2.0".to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"left-pad": "1.2.0"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let established = worker.process_upgrade(request("1.3.0")).await.unwrap();
+        let adoption = established.adoption.unwrap();
+        assert_eq!(adoption.target_age_days, Some(300));
+        assert!((adoption.target_share.unwrap() - 0.895).abs() < 1e-9);
+
+        let fresh = worker.process_upgrade(request("1.4.0")).await.unwrap();
+        let adoption = fresh.adoption.unwrap();
+        assert_eq!(adoption.issues_after_release, Some(6));
+        assert_eq!(adoption.issues_before_release, Some(0));
+        assert!(fresh.compatibility_score < established.compatibility_score);
+        let popularity = &fresh.score_breakdown.factors[3];
+        let adjustments: Vec<&str> = popularity
+            .adjustments
+            .iter()
+            .map(|adjustment| adjustment.name.as_str())
+            .collect();
+        assert_eq!(adjustments, ["target_age", "issue_spike"]);
+        assert!(fresh
+            .score_breakdown
+            .sources
+            .contains(&"issue_tracker".to_string()));
+    }
+}
@@ -0,0 +1,940 @@
+mod cache;
+mod epss;
+mod ghsa;
+mod minimal;
+mod npm_audit;
+mod transitive;
+
+pub use cache::{AdvisoryCache, AdvisoryCacheConfig};
+pub use epss::{parse_scores, EpssClient, EpssScore, ExploitScores, StaticExploitScores};
+pub use ghsa::{ghsa_ecosystem, GhsaClient, GhsaConfig};
+pub use minimal::{candidate_versions, SecurityUpgrade, SecurityUpgradeRequest};
+pub use npm_audit::NpmAuditClient;
+pub use transitive::{lockfile_ecosystem, native_packages, resolved_packages};
+
+use crate::version::ResolvedVersions;
+use crate::{ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Severity rating of an advisory, as GitHub and CVSS v3 grade them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Parses the labels databases use (`MODERATE`, `high`, ...).
+    pub fn parse(label: &str) -> Option<Self> {
+        match label.to_ascii_lowercase().as_str() {
+            "low" => Some(Severity::Low),
+            "moderate" | "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+
+    /// The CVSS v3 rating of a base score; `None` for 0.0.
+    pub fn from_cvss(score: f64) -> Option<Self> {
+        match score {
+            s if s >= 9.0 => Some(Severity::Critical),
+            s if s >= 7.0 => Some(Severity::High),
+            s if s >= 4.0 => Some(Severity::Medium),
+            s if s > 0.0 => Some(Severity::Low),
+            _ => None,
+        }
+    }
+}
+
+/// A known vulnerability of the current or the target version, in
+/// [`crate::RiskAssessment::security_issues`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityIssue {
+    /// Advisory ID (`GHSA-...`, `RUSTSEC-...`, `npm:1523`); empty for
+    /// issues recorded as plain text.
+    pub id: String,
+    /// The same advisory in other databases, e.g. its CVE.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Database the advisory came from: `osv`, `ghsa` or `npm`.
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    /// CVSS base score, when the database publishes one.
+    #[serde(default)]
+    pub cvss_score: Option<f64>,
+    /// Exploit probability of the advisory's CVE, when scored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epss: Option<EpssScore>,
+    #[serde(default)]
+    pub summary: String,
+    /// Dependency the advisory concerns when it is not the upgraded package,
+    /// such as a transitive dependency the upgrade pulls in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Version of the package the advisory was found for.
+    #[serde(default)]
+    pub version: String,
+    /// Affected version ranges.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub affected: Vec<String>,
+    /// Versions releasing the fix.
+    #[serde(default)]
+    pub fixed_in: Vec<String>,
+    #[serde(default)]
+    pub references: Vec<String>,
+    /// Only the current version is affected: the upgrade fixes the issue.
+    #[serde(default)]
+    pub fixed_by_upgrade: bool,
+}
+
+impl From<String> for SecurityIssue {
+    /// An issue recorded as plain text, as security issues used to be.
+    fn from(summary: String) -> Self {
+        Self {
+            summary,
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for SecurityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.id.is_empty() {
+            return write!(f, "{}", self.summary);
+        }
+        write!(f, "{}", self.id)?;
+        if !self.aliases.is_empty() {
+            write!(f, " ({})", self.aliases.join(", "))?;
+        }
+        match &self.package {
+            Some(package) => write!(f, " affects {} {}", package, self.version)?,
+            None => write!(f, " affects {}", self.version)?,
+        }
+        if self.fixed_by_upgrade {
+            write!(f, ", fixed by the upgrade")?;
+        }
+        if !self.summary.is_empty() {
+            write!(f, ": {}", self.summary)?;
+        }
+        let mut details = Vec::new();
+        if let Some(severity) = self.severity {
+            details.push(format!("{:?}", severity).to_lowercase());
+        }
+        if let Some(score) = self.cvss_score {
+            details.push(format!("CVSS {:.1}", score));
+        }
+        if let Some(epss) = self.epss {
+            details.push(format!("EPSS {:.1}%", epss.probability * 100.0));
+        }
+        if !self.affected.is_empty() {
+            details.push(format!("affected: {}", self.affected.join("; ")));
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads security issues both as objects and as the plain strings older
+/// workers produced.
+pub fn security_issues_compat<'de, D>(deserializer: D) -> Result<Vec<SecurityIssue>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<serde_json::Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|issue| match issue {
+            serde_json::Value::String(summary) => Ok(SecurityIssue::from(summary)),
+            issue => serde_json::from_value(issue).map_err(serde::de::Error::custom),
+        })
+        .collect()
+}
+
+/// A published security advisory affecting a package.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Advisory {
+    /// Identifier in the database it came from (`GHSA-...`, `RUSTSEC-...`).
+    pub id: String,
+    /// The same advisory in other databases, e.g. its CVE.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Affected version ranges, e.g. `>=4.0.0, <4.17.21`.
+    #[serde(default)]
+    pub affected: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cvss_score: Option<f64>,
+    /// Versions releasing the fix.
+    #[serde(default)]
+    pub fixed_in: Vec<String>,
+    #[serde(default)]
+    pub references: Vec<String>,
+}
+
+impl Advisory {
+    fn is_same(&self, other: &Advisory) -> bool {
+        let ids = |advisory: &Advisory| {
+            std::iter::once(advisory.id.clone())
+                .chain(advisory.aliases.iter().cloned())
+                .collect::<HashSet<_>>()
+        };
+        !ids(self).is_disjoint(&ids(other))
+    }
+
+    // Completes this advisory with what another database knows of it
+    fn merge(&mut self, other: Advisory) {
+        let extend = |known: &mut Vec<String>, found: Vec<String>| {
+            for item in found {
+                if !known.contains(&item) {
+                    known.push(item);
+                }
+            }
+        };
+        let mut ids = vec![other.id];
+        ids.extend(other.aliases);
+        ids.retain(|id| *id != self.id);
+        extend(&mut self.aliases, ids);
+        self.summary = self.summary.take().or(other.summary);
+        self.severity = self.severity.or(other.severity);
+        self.cvss_score = self.cvss_score.or(other.cvss_score);
+        extend(&mut self.fixed_in, other.fixed_in);
+        extend(&mut self.references, other.references);
+    }
+
+    fn issue(&self, source: &str, version: &str, fixed_by_upgrade: bool) -> SecurityIssue {
+        SecurityIssue {
+            id: self.id.clone(),
+            aliases: self.aliases.clone(),
+            source: source.to_string(),
+            severity: self
+                .severity
+                .or_else(|| self.cvss_score.and_then(Severity::from_cvss)),
+            cvss_score: self.cvss_score,
+            epss: None,
+            summary: self.summary.clone().unwrap_or_default(),
+            package: None,
+            version: version.to_string(),
+            affected: self.affected.clone(),
+            fixed_in: self.fixed_in.clone(),
+            references: self.references.clone(),
+            fixed_by_upgrade,
+        }
+    }
+}
+
+/// When cached advisories were fetched, in
+/// [`crate::RiskAssessment::advisory_freshness`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Freshness {
+    /// Database the advisories came from.
+    pub source: String,
+    /// When the oldest of the advisories used was fetched.
+    pub fetched_at: DateTime<Utc>,
+    /// The database could not be reached to renew advisories older than
+    /// the cache allows.
+    pub stale: bool,
+}
+
+/// Outcome of [`UpgradeWorker::refresh_advisories`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdvisoryRefresh {
+    /// Package versions whose advisories were fetched again.
+    pub refreshed: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Source of security advisories for published package versions.
+#[async_trait]
+pub trait AdvisoryDatabase: Send + Sync {
+    /// Short name recorded as the source of its advisories.
+    fn name(&self) -> &'static str;
+
+    /// Whether this database covers the given ecosystem.
+    fn supports(&self, ecosystem: &str) -> bool;
+
+    /// Advisories affecting each `(package, version)` query, in query order.
+    async fn advisories(
+        &self,
+        ecosystem: &str,
+        queries: &[(&str, &str)],
+    ) -> Result<Vec<Vec<Advisory>>, UpgradeError>;
+
+    /// When the advisories last returned for `queries` were fetched; `None`
+    /// for databases queried live.
+    fn freshness(&self, _ecosystem: &str, _queries: &[(&str, &str)]) -> Option<Freshness> {
+        None
+    }
+
+    /// Fetches again the advisories kept locally and returns how many
+    /// package versions were renewed; databases queried live have none.
+    async fn refresh(&self) -> Result<usize, UpgradeError> {
+        Ok(0)
+    }
+}
+
+/// Ecosystem name OSV uses for a SpecCursor ecosystem.
+pub fn osv_ecosystem(ecosystem: &str) -> Option<&'static str> {
+    match ecosystem {
+        "cargo" => Some("crates.io"),
+        "npm" | "pnpm" => Some("npm"),
+        "pip" | "pipenv" => Some("PyPI"),
+        "maven" | "gradle" => Some("Maven"),
+        "nuget" => Some("NuGet"),
+        "rubygems" => Some("RubyGems"),
+        "composer" => Some("Packagist"),
+        "go" => Some("Go"),
+        "hex" => Some("Hex"),
+        "pubspec" => Some("Pub"),
+        "conan" => Some("ConanCenter"),
+        "swiftpm" => Some("SwiftURL"),
+        "github-actions" => Some("GitHub Actions"),
+        _ => None,
+    }
+}
+
+/// Client for the OSV.dev API: one batch query for all versions, then the
+/// full record of each advisory found, for its summary and ranges.
+pub struct OsvClient {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl Default for OsvClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OsvClient {
+    pub fn new() -> Self {
+        Self::with_api_url("https://api.osv.dev")
+    }
+
+    pub fn with_api_url(api_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<serde_json::Value, UpgradeError> {
+        let response = request.send().await.map_err(network_error)?;
+        if !response.status().is_success() {
+            return Err(UpgradeError {
+                message: format!("OSV request failed with status {}", response.status()),
+                error_type: ErrorType::Network,
+            });
+        }
+        response.json().await.map_err(network_error)
+    }
+}
+
+#[async_trait]
+impl AdvisoryDatabase for OsvClient {
+    fn name(&self) -> &'static str {
+        "osv"
+    }
+
+    fn supports(&self, ecosystem: &str) -> bool {
+        osv_ecosystem(ecosystem).is_some()
+    }
+
+    async fn advisories(
+        &self,
+        ecosystem: &str,
+        queries: &[(&str, &str)],
+    ) -> Result<Vec<Vec<Advisory>>, UpgradeError> {
+        let Some(osv) = osv_ecosystem(ecosystem) else {
+            return Ok(vec![Vec::new(); queries.len()]);
+        };
+        let body = json!({
+            "queries": queries
+                .iter()
+                .map(|(package, version)| json!({
+                    "package": {"name": package, "ecosystem": osv},
+                    "version": version,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        let batch = self
+            .send(
+                self.client
+                    .post(format!("{}/v1/querybatch", self.api_url))
+                    .json(&body),
+            )
+            .await?;
+
+        // The batch only names the advisories
+        let mut records: HashMap<String, serde_json::Value> = HashMap::new();
+        let mut found = Vec::new();
+        for (i, (package, _)) in queries.iter().enumerate() {
+            let ids: Vec<String> = batch["results"][i]["vulns"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|vuln| Some(vuln["id"].as_str()?.to_string()))
+                .collect();
+            for id in &ids {
+                if !records.contains_key(id) {
+                    let url = format!("{}/v1/vulns/{}", self.api_url, id);
+                    records.insert(id.clone(), self.send(self.client.get(url)).await?);
+                }
+            }
+            found.push(
+                ids.iter()
+                    .map(|id| parse_osv_record(&records[id], osv, package))
+                    .collect(),
+            );
+        }
+        Ok(found)
+    }
+}
+
+/// An OSV record as an advisory, keeping the ranges that apply to `package`.
+pub fn parse_osv_record(record: &serde_json::Value, ecosystem: &str, package: &str) -> Advisory {
+    let strings = |value: &serde_json::Value| -> Vec<String> {
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| Some(item.as_str()?.to_string()))
+            .collect()
+    };
+    let ranges: Vec<&[serde_json::Value]> = record["affected"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|affected| {
+            affected["package"]["ecosystem"] == ecosystem
+                && affected["package"]["name"]
+                    .as_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(package))
+        })
+        .flat_map(|affected| affected["ranges"].as_array().into_iter().flatten())
+        .filter(|range| matches!(range["type"].as_str(), Some("SEMVER" | "ECOSYSTEM")))
+        .map(|range| range["events"].as_array().map_or(&[][..], Vec::as_slice))
+        .collect();
+    Advisory {
+        id: record["id"].as_str().unwrap_or_default().to_string(),
+        aliases: strings(&record["aliases"]),
+        summary: record["summary"]
+            .as_str()
+            .filter(|summary| !summary.is_empty())
+            .map(str::to_string),
+        affected: ranges
+            .iter()
+            .flat_map(|events| osv_ranges(events))
+            .collect(),
+        // Records imported from GitHub carry its rating
+        severity: record["database_specific"]["severity"]
+            .as_str()
+            .and_then(Severity::parse),
+        cvss_score: None,
+        fixed_in: ranges
+            .iter()
+            .flat_map(|events| events.iter())
+            .filter_map(|event| Some(event["fixed"].as_str()?.to_string()))
+            .collect(),
+        references: record["references"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|reference| Some(reference["url"].as_str()?.to_string()))
+            .collect(),
+    }
+}
+
+// `introduced`/`fixed`/`last_affected` events as ranges
+fn osv_ranges(events: &[serde_json::Value]) -> Vec<String> {
+    let bounded = |start: Option<&str>, end: String| match start {
+        None | Some("0") => end,
+        Some(start) => format!(">={}, {}", start, end),
+    };
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for event in events {
+        if let Some(version) = event["introduced"].as_str() {
+            start = Some(version);
+        } else if let Some(version) = event["fixed"].as_str() {
+            ranges.push(bounded(start.take(), format!("<{}", version)));
+        } else if let Some(version) = event["last_affected"].as_str() {
+            ranges.push(bounded(start.take(), format!("<={}", version)));
+        }
+    }
+    match start {
+        Some("0") => ranges.push("*".to_string()),
+        Some(start) => ranges.push(format!(">={}", start)),
+        None => {}
+    }
+    ranges
+}
+
+fn network_error(e: reqwest::Error) -> UpgradeError {
+    UpgradeError {
+        message: format!("OSV request failed: {}", e),
+        error_type: ErrorType::Network,
+    }
+}
+
+/// In-memory advisories, useful for tests and for callers that pre-fetch them.
+#[derive(Debug, Clone, Default)]
+pub struct StaticAdvisories {
+    advisories: HashMap<(String, String, String), Vec<Advisory>>,
+}
+
+impl StaticAdvisories {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_advisory(
+        mut self,
+        ecosystem: &str,
+        package: &str,
+        versions: &[&str],
+        advisory: Advisory,
+    ) -> Self {
+        for version in versions {
+            self.advisories
+                .entry((
+                    ecosystem.to_string(),
+                    package.to_string(),
+                    version.to_string(),
+                ))
+                .or_default()
+                .push(advisory.clone());
+        }
+        self
+    }
+}
+
+#[async_trait]
+impl AdvisoryDatabase for StaticAdvisories {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+
+    fn supports(&self, ecosystem: &str) -> bool {
+        self.advisories
+            .keys()
+            .any(|(known, _, _)| known == ecosystem)
+    }
+
+    async fn advisories(
+        &self,
+        ecosystem: &str,
+        queries: &[(&str, &str)],
+    ) -> Result<Vec<Vec<Advisory>>, UpgradeError> {
+        Ok(queries
+            .iter()
+            .map(|(package, version)| {
+                self.advisories
+                    .get(&(
+                        ecosystem.to_string(),
+                        package.to_string(),
+                        version.to_string(),
+                    ))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+}
+
+impl UpgradeWorker {
+    /// Renews the advisories every cached database keeps, as the
+    /// background refresh does.
+    pub async fn refresh_advisories(&self) -> AdvisoryRefresh {
+        let mut outcome = AdvisoryRefresh::default();
+        for database in &self.advisories {
+            match database.refresh().await {
+                Ok(refreshed) => outcome.refreshed += refreshed,
+                Err(e) => outcome.warnings.push(e.message),
+            }
+        }
+        outcome
+    }
+
+    /// Advisories of each `(package, version)` query from every database
+    /// covering `ecosystem`, merged by ID and alias, with the database that
+    /// reported them first. Databases that cannot be reached are reported as
+    /// warnings prefixed with `check`; `None` when none could be.
+    async fn merged_advisories(
+        &self,
+        ecosystem: &str,
+        queries: &[(&str, &str)],
+        check: &str,
+        warnings: &mut Vec<String>,
+    ) -> Option<Vec<Vec<(&'static str, Advisory)>>> {
+        let mut known: Vec<Vec<(&'static str, Advisory)>> = vec![Vec::new(); queries.len()];
+        let mut answered = false;
+        for database in &self.advisories {
+            if !database.supports(ecosystem) {
+                continue;
+            }
+            let found = match database.advisories(ecosystem, queries).await {
+                Ok(found) => found,
+                Err(e) => {
+                    warnings.push(format!("{} skipped: {}", check, e.message));
+                    continue;
+                }
+            };
+            answered = true;
+            for (known, found) in known.iter_mut().zip(found) {
+                for advisory in found {
+                    match known.iter_mut().find(|(_, other)| other.is_same(&advisory)) {
+                        Some((_, other)) => other.merge(advisory),
+                        None => known.push((database.name(), advisory)),
+                    }
+                }
+            }
+        }
+        answered.then_some(known)
+    }
+
+    /// Lists the advisories affecting the current and target versions in
+    /// `security_issues`; an advisory against the target makes the upgrade
+    /// critical. Databases that cannot be reached are reported as warnings.
+    pub(crate) async fn check_advisories(
+        &self,
+        request: &UpgradeRequest,
+        target_request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        let queries = [
+            (request.package_name.as_str(), versions.current.as_str()),
+            (
+                target_request.package_name.as_str(),
+                versions.target.as_str(),
+            ),
+        ];
+        // Advisories of each version, with the database that reported them first
+        let mut current: Vec<(&str, Advisory)> = Vec::new();
+        let mut target: Vec<(&str, Advisory)> = Vec::new();
+        for database in &self.advisories {
+            if !database.supports(&request.ecosystem) {
+                continue;
+            }
+            let found = match database.advisories(&request.ecosystem, &queries).await {
+                Ok(found) => found,
+                Err(e) => {
+                    warnings.push(format!("Advisory check skipped: {}", e.message));
+                    continue;
+                }
+            };
+            if let Some(freshness) = database.freshness(&request.ecosystem, &queries) {
+                if freshness.stale {
+                    warnings.push(format!(
+                        "Advisories from {} date from {} and may be out of date",
+                        freshness.source,
+                        freshness.fetched_at.to_rfc3339()
+                    ));
+                }
+                risk.advisory_freshness.push(freshness);
+            }
+            for (known, found) in [&mut current, &mut target].into_iter().zip(found) {
+                for advisory in found {
+                    match known.iter_mut().find(|(_, other)| other.is_same(&advisory)) {
+                        Some((_, other)) => other.merge(advisory),
+                        None => known.push((database.name(), advisory)),
+                    }
+                }
+            }
+        }
+
+        let mut issues: Vec<SecurityIssue> = target
+            .iter()
+            .map(|(source, advisory)| advisory.issue(source, queries[1].1, false))
+            .collect();
+        for (source, advisory) in &current {
+            if !target.iter().any(|(_, other)| other.is_same(advisory)) {
+                issues.push(advisory.issue(source, queries[0].1, true));
+            }
+        }
+        self.score_exploits(&mut issues, warnings).await;
+
+        // Advisories unlikely to be exploited can be rated below critical
+        for issue in issues.iter().filter(|issue| !issue.fixed_by_upgrade) {
+            let level = match (self.config.epss_critical_threshold, issue.epss) {
+                (Some(threshold), Some(epss)) if epss.probability < threshold => {
+                    risk.explanations.push(format!(
+                        "{} has a {:.1}% exploit probability (EPSS), below the {:.1}% threshold",
+                        issue.id,
+                        epss.probability * 100.0,
+                        threshold * 100.0
+                    ));
+                    RiskLevel::High
+                }
+                _ => RiskLevel::Critical,
+            };
+            risk.risk_level = risk.risk_level.max(level);
+        }
+        risk.security_issues.extend(issues);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WorkerConfig;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_osv_record() {
+        let record = json!({
+            "id": "GHSA-35jh-r3h4-6jhm",
+            "summary": "Command Injection in lodash",
+            "aliases": ["CVE-2021-23337"],
+            "database_specific": {"severity": "HIGH"},
+            "references": [{"type": "ADVISORY", "url": "https://nvd.nist.gov/vuln/detail/CVE-2021-23337"}],
+            "affected": [
+                {
+                    "package": {"ecosystem": "npm", "name": "lodash"},
+                    "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"fixed": "4.17.21"}]}]
+                },
+                {
+                    "package": {"ecosystem": "npm", "name": "lodash-es"},
+                    "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"fixed": "4.17.21"}]}]
+                },
+                {
+                    "package": {"ecosystem": "npm", "name": "lodash"},
+                    "ranges": [
+                        {"type": "ECOSYSTEM", "events": [
+                            {"introduced": "5.0.0"}, {"last_affected": "5.0.2"},
+                            {"introduced": "6.0.0"}
+                        ]},
+                        {"type": "GIT", "events": [{"introduced": "0"}, {"fixed": "c4847eb"}]}
+                    ]
+                }
+            ]
+        });
+        assert_eq!(
+            parse_osv_record(&record, "npm", "lodash"),
+            Advisory {
+                id: "GHSA-35jh-r3h4-6jhm".to_string(),
+                aliases: vec!["CVE-2021-23337".to_string()],
+                summary: Some("Command Injection in lodash".to_string()),
+                affected: vec![
+                    "<4.17.21".to_string(),
+                    ">=5.0.0, <=5.0.2".to_string(),
+                    ">=6.0.0".to_string(),
+                ],
+                severity: Some(Severity::High),
+                cvss_score: None,
+                fixed_in: vec!["4.17.21".to_string()],
+                references: vec!["https://nvd.nist.gov/vuln/detail/CVE-2021-23337".to_string()],
+            }
+        );
+        assert_eq!(osv_ecosystem("cargo"), Some("crates.io"));
+        assert_eq!(osv_ecosystem("lean"), None);
+    }
+
+    #[tokio::test]
+    async fn test_advisories_in_risk_assessment() {
+        let advisory = |id: &str, alias: &str, affected: &str| Advisory {
+            id: id.to_string(),
+            aliases: vec![alias.to_string()],
+            summary: Some("Prototype Pollution in lodash".to_string()),
+            affected: vec![affected.to_string()],
+            ..Default::default()
+        };
+        let osv = StaticAdvisories::new()
+            .with_advisory(
+                "npm",
+                "lodash",
+                &["4.17.15"],
+                advisory("GHSA-p6mc-m468-83gw", "CVE-2020-8203", "<4.17.19"),
+            )
+            .with_advisory(
+                "npm",
+                "lodash",
+                &["4.17.15", "4.17.20"],
+                advisory("GHSA-35jh-r3h4-6jhm", "CVE-2021-23337", "<4.17.21"),
+            );
+        // Another database reporting the same advisory under its CVE
+        let nvd = StaticAdvisories::new().with_advisory(
+            "npm",
+            "lodash",
+            &["4.17.20"],
+            Advisory {
+                cvss_score: Some(7.2),
+                fixed_in: vec!["4.17.21".to_string()],
+                ..advisory("CVE-2021-23337", "GHSA-35jh-r3h4-6jhm", "<4.17.21")
+            },
+        );
+        let worker = UpgradeWorker::new(None)
+            .with_advisory_database(Arc::new(osv))
+            .with_advisory_database(Arc::new(nvd));
+        let request = UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.15".to_string(),
+            target_version: "4.17.20".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "4.17.15"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request.clone()).await.unwrap();
+        let risk = response.risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::Critical);
+        assert_eq!(
+            risk.security_issues,
+            [
+                SecurityIssue {
+                    id: "GHSA-35jh-r3h4-6jhm".to_string(),
+                    aliases: vec!["CVE-2021-23337".to_string()],
+                    source: "static".to_string(),
+                    severity: Some(Severity::High),
+                    cvss_score: Some(7.2),
+                    epss: None,
+                    summary: "Prototype Pollution in lodash".to_string(),
+                    package: None,
+                    version: "4.17.20".to_string(),
+                    affected: vec!["<4.17.21".to_string()],
+                    fixed_in: vec!["4.17.21".to_string()],
+                    references: Vec::new(),
+                    fixed_by_upgrade: false,
+                },
+                SecurityIssue {
+                    id: "GHSA-p6mc-m468-83gw".to_string(),
+                    aliases: vec!["CVE-2020-8203".to_string()],
+                    source: "static".to_string(),
+                    summary: "Prototype Pollution in lodash".to_string(),
+                    version: "4.17.15".to_string(),
+                    affected: vec!["<4.17.19".to_string()],
+                    fixed_by_upgrade: true,
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            risk.security_issues[1].to_string(),
+            "GHSA-p6mc-m468-83gw (CVE-2020-8203) affects 4.17.15, fixed by the upgrade: \
+             Prototype Pollution in lodash (affected: <4.17.19)"
+        );
+
+        let fixed = UpgradeRequest {
+            target_version: "4.17.21".to_string(),
+            ..request
+        };
+        let risk = worker.process_upgrade(fixed).await.unwrap().risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::Low);
+        assert_eq!(risk.security_issues.len(), 2);
+        assert!(risk
+            .security_issues
+            .iter()
+            .all(|issue| issue.fixed_by_upgrade));
+
+        // Assessments of older workers listed issues as text
+        let legacy: RiskAssessment = serde_json::from_value(json!({
+            "risk_level": "Critical",
+            "breaking_changes": false,
+            "security_issues": ["Known security vulnerability detected"],
+            "performance_impact": "None",
+            "version_jump": "Patch",
+            "explanations": []
+        }))
+        .unwrap();
+        assert_eq!(
+            legacy.security_issues,
+            [SecurityIssue::from(
+                "Known security vulnerability detected".to_string()
+            )]
+        );
+        assert_eq!(Severity::from_cvss(9.8), Some(Severity::Critical));
+        assert_eq!(Severity::parse("MODERATE"), Some(Severity::Medium));
+    }
+
+    #[tokio::test]
+    async fn test_exploit_scores_weight_risk() {
+        let advisories = StaticAdvisories::new().with_advisory(
+            "npm",
+            "lodash",
+            &["4.17.20"],
+            Advisory {
+                id: "GHSA-35jh-r3h4-6jhm".to_string(),
+                aliases: vec!["CVE-2021-23337".to_string()],
+                ..Default::default()
+            },
+        );
+        let scores = StaticExploitScores::new().with_score("CVE-2021-23337", 0.00625, 0.78645);
+        let request = UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.19".to_string(),
+            target_version: "4.17.20".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "4.17.19"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+        let worker = |threshold: Option<f64>| {
+            UpgradeWorker::new(Some(WorkerConfig {
+                epss_critical_threshold: threshold,
+                ..WorkerConfig::default()
+            }))
+            .with_advisory_database(Arc::new(advisories.clone()))
+            .with_exploit_scores(Arc::new(scores.clone()))
+        };
+
+        let risk = worker(None)
+            .process_upgrade(request.clone())
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::Critical);
+        assert_eq!(
+            risk.security_issues[0].epss,
+            Some(EpssScore {
+                probability: 0.00625,
+                percentile: 0.78645,
+            })
+        );
+        assert!(risk.security_issues[0].to_string().contains("EPSS 0.6%"));
+
+        let risk = worker(Some(0.1))
+            .process_upgrade(request)
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::High);
+        assert!(risk.explanations.contains(
+            &"GHSA-35jh-r3h4-6jhm has a 0.6% exploit probability (EPSS), below the 10.0% threshold"
+                .to_string()
+        ));
+    }
+}
@@ -0,0 +1,355 @@
+use super::{Advisory, AdvisoryDatabase, Freshness};
+use crate::{ErrorType, UpgradeError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How an [`AdvisoryCache`] keeps and renews advisories.
+#[derive(Debug, Clone)]
+pub struct AdvisoryCacheConfig {
+    /// Directory the cache is saved to, so that it survives restarts; kept in
+    /// memory only when unset.
+    pub directory: Option<PathBuf>,
+    /// How long advisories are served without asking the database again.
+    /// Older ones are still served, marked stale, while it cannot be reached.
+    pub ttl: Duration,
+    /// How often [`AdvisoryCache::spawn_refresh`] renews cached advisories.
+    pub refresh_interval: Duration,
+}
+
+impl Default for AdvisoryCacheConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            ttl: Duration::from_secs(6 * 3600),
+            refresh_interval: Duration::from_secs(3600),
+        }
+    }
+}
+
+// (ecosystem, package, version)
+type Key = (String, String, String);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    ecosystem: String,
+    package: String,
+    version: String,
+    fetched_at: DateTime<Utc>,
+    advisories: Vec<Advisory>,
+}
+
+impl Entry {
+    fn key(&self) -> Key {
+        (
+            self.ecosystem.clone(),
+            self.package.clone(),
+            self.version.clone(),
+        )
+    }
+}
+
+/// Keeps the advisories another database reports for each package version,
+/// so that jobs do not query it again until they are older than the TTL.
+pub struct AdvisoryCache {
+    database: Arc<dyn AdvisoryDatabase>,
+    config: AdvisoryCacheConfig,
+    entries: Mutex<HashMap<Key, Entry>>,
+}
+
+impl AdvisoryCache {
+    /// Caches `database`, starting from the advisories saved in
+    /// `config.directory` by an earlier run, if any.
+    pub fn new(database: Arc<dyn AdvisoryDatabase>, config: AdvisoryCacheConfig) -> Self {
+        let cache = Self {
+            database,
+            config,
+            entries: Mutex::new(HashMap::new()),
+        };
+        // An unreadable cache file is refetched rather than fatal
+        let saved: Vec<Entry> = cache
+            .path()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        cache
+            .lock()
+            .extend(saved.into_iter().map(|entry| (entry.key(), entry)));
+        cache
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Key, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        let directory = self.config.directory.as_ref()?;
+        Some(directory.join(format!("{}-advisories.json", self.database.name())))
+    }
+
+    fn is_fresh(&self, entry: &Entry, now: DateTime<Utc>) -> bool {
+        (now - entry.fetched_at)
+            .to_std()
+            .map_or(true, |age| age < self.config.ttl)
+    }
+
+    // Writes the cache through a temporary file so that readers never see
+    // half of it
+    fn save(&self) -> Result<(), UpgradeError> {
+        let Some(path) = self.path() else {
+            return Ok(());
+        };
+        let entries: Vec<Entry> = self.lock().values().cloned().collect();
+        let content = serde_json::to_vec(&entries).map_err(|e| UpgradeError {
+            message: format!("Failed to serialize the advisory cache: {}", e),
+            error_type: ErrorType::Internal,
+        })?;
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let temporary = path.with_extension("json.tmp");
+            std::fs::write(&temporary, content)?;
+            std::fs::rename(&temporary, &path)
+        };
+        write().map_err(|e| UpgradeError {
+            message: format!(
+                "Failed to write the advisory cache to {}: {}",
+                path.display(),
+                e
+            ),
+            error_type: ErrorType::Internal,
+        })
+    }
+
+    // Asks the database about `queries` and caches its answers
+    async fn fetch(&self, ecosystem: &str, queries: &[(&str, &str)]) -> Result<(), UpgradeError> {
+        let found = self.database.advisories(ecosystem, queries).await?;
+        let fetched_at = Utc::now();
+        let mut entries = self.lock();
+        for ((package, version), advisories) in queries.iter().zip(found) {
+            let entry = Entry {
+                ecosystem: ecosystem.to_string(),
+                package: package.to_string(),
+                version: version.to_string(),
+                fetched_at,
+                advisories,
+            };
+            entries.insert(entry.key(), entry);
+        }
+        drop(entries);
+        self.save()
+    }
+
+    /// Refreshes the cache every `refresh_interval` in the background. A
+    /// failed refresh keeps the advisories already cached, which are
+    /// reported stale once older than the TTL.
+    pub fn spawn_refresh(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(cache.config.refresh_interval);
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let _ = cache.refresh().await;
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl AdvisoryDatabase for AdvisoryCache {
+    fn name(&self) -> &'static str {
+        self.database.name()
+    }
+
+    fn supports(&self, ecosystem: &str) -> bool {
+        self.database.supports(ecosystem)
+    }
+
+    async fn advisories(
+        &self,
+        ecosystem: &str,
+        queries: &[(&str, &str)],
+    ) -> Result<Vec<Vec<Advisory>>, UpgradeError> {
+        let key = |(package, version): &(&str, &str)| {
+            (
+                ecosystem.to_string(),
+                package.to_string(),
+                version.to_string(),
+            )
+        };
+        let now = Utc::now();
+        let missing: Vec<(&str, &str)> = {
+            let entries = self.lock();
+            queries
+                .iter()
+                .filter(|query| {
+                    !entries
+                        .get(&key(query))
+                        .is_some_and(|entry| self.is_fresh(entry, now))
+                })
+                .copied()
+                .collect()
+        };
+        if !missing.is_empty() {
+            if let Err(e) = self.fetch(ecosystem, &missing).await {
+                // Stale advisories beat none while the database is down
+                let entries = self.lock();
+                if !missing
+                    .iter()
+                    .all(|query| entries.contains_key(&key(query)))
+                {
+                    return Err(e);
+                }
+            }
+        }
+
+        let entries = self.lock();
+        Ok(queries
+            .iter()
+            .map(|query| {
+                entries
+                    .get(&key(query))
+                    .map(|entry| entry.advisories.clone())
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    /// Asks the database again about every cached package version, one
+    /// request per ecosystem, and returns how many were renewed.
+    async fn refresh(&self) -> Result<usize, UpgradeError> {
+        let mut keys: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for (ecosystem, package, version) in self.lock().keys().cloned() {
+            keys.entry(ecosystem).or_default().push((package, version));
+        }
+        let mut refreshed = 0;
+        let mut failures = Vec::new();
+        for (ecosystem, keys) in &keys {
+            let queries: Vec<(&str, &str)> = keys
+                .iter()
+                .map(|(package, version)| (package.as_str(), version.as_str()))
+                .collect();
+            match self.fetch(ecosystem, &queries).await {
+                Ok(()) => refreshed += queries.len(),
+                Err(e) => failures.push(format!("{}: {}", ecosystem, e.message)),
+            }
+        }
+        if !failures.is_empty() {
+            return Err(UpgradeError {
+                message: format!(
+                    "Failed to refresh {} advisories for {}",
+                    self.database.name(),
+                    failures.join("; ")
+                ),
+                error_type: ErrorType::Network,
+            });
+        }
+        Ok(refreshed)
+    }
+
+    fn freshness(&self, ecosystem: &str, queries: &[(&str, &str)]) -> Option<Freshness> {
+        let now = Utc::now();
+        let entries = self.lock();
+        let cached: Vec<&Entry> = queries
+            .iter()
+            .filter_map(|(package, version)| {
+                entries.get(&(
+                    ecosystem.to_string(),
+                    package.to_string(),
+                    version.to_string(),
+                ))
+            })
+            .collect();
+        let oldest = cached.iter().min_by_key(|entry| entry.fetched_at)?;
+        Some(Freshness {
+            source: self.database.name().to_string(),
+            fetched_at: oldest.fetched_at,
+            stale: !self.is_fresh(oldest, now),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advisories::StaticAdvisories;
+
+    // A database that cannot be reached
+    struct Unreachable;
+
+    #[async_trait]
+    impl AdvisoryDatabase for Unreachable {
+        fn name(&self) -> &'static str {
+            "static"
+        }
+
+        fn supports(&self, _ecosystem: &str) -> bool {
+            true
+        }
+
+        async fn advisories(
+            &self,
+            _ecosystem: &str,
+            _queries: &[(&str, &str)],
+        ) -> Result<Vec<Vec<Advisory>>, UpgradeError> {
+            Err(UpgradeError {
+                message: "connection refused".to_string(),
+                error_type: ErrorType::Network,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_advisory_cache() {
+        let directory = tempfile::tempdir().unwrap();
+        let config = AdvisoryCacheConfig {
+            directory: Some(directory.path().to_path_buf()),
+            ..AdvisoryCacheConfig::default()
+        };
+        let advisory = Advisory {
+            id: "GHSA-35jh-r3h4-6jhm".to_string(),
+            ..Default::default()
+        };
+        let database =
+            StaticAdvisories::new().with_advisory("npm", "lodash", &["4.17.20"], advisory.clone());
+        let queries = [("lodash", "4.17.20"), ("lodash", "4.17.21")];
+
+        let cache = AdvisoryCache::new(Arc::new(database), config.clone());
+        assert!(cache.freshness("npm", &queries).is_none());
+        let found = cache.advisories("npm", &queries).await.unwrap();
+        assert_eq!(found, [vec![advisory.clone()], Vec::new()]);
+        assert!(!cache.freshness("npm", &queries).unwrap().stale);
+        assert_eq!(cache.refresh().await.unwrap(), 2);
+
+        // A restarted worker answers from disk while the database is down
+        let restarted = AdvisoryCache::new(Arc::new(Unreachable), config.clone());
+        let found = restarted.advisories("npm", &queries).await.unwrap();
+        assert_eq!(found[0], vec![advisory.clone()]);
+        assert!(restarted.refresh().await.is_err());
+        assert!(restarted
+            .advisories("npm", &[("lodash", "4.17.15")])
+            .await
+            .is_err());
+
+        // Past the TTL the advisories are still served, as stale
+        let expired = AdvisoryCache::new(
+            Arc::new(Unreachable),
+            AdvisoryCacheConfig {
+                ttl: Duration::ZERO,
+                ..config
+            },
+        );
+        let found = expired.advisories("npm", &queries).await.unwrap();
+        assert_eq!(found[0], [advisory]);
+        let freshness = expired.freshness("npm", &queries).unwrap();
+        assert_eq!(freshness.source, "static");
+        assert!(freshness.stale);
+    }
+}
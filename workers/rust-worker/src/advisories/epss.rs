@@ -0,0 +1,217 @@
+use super::SecurityIssue;
+use crate::{ErrorType, UpgradeError, UpgradeWorker};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// CVEs per request; the API accepts a comma-separated list up to 2000
+// characters long
+const BATCH_SIZE: usize = 100;
+
+/// Probability that a CVE is exploited in the wild within the next 30 days,
+/// from FIRST's Exploit Prediction Scoring System.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EpssScore {
+    /// Between 0 and 1.
+    pub probability: f64,
+    /// Share of all scored CVEs with a lower probability.
+    pub percentile: f64,
+}
+
+/// Source of EPSS scores.
+#[async_trait]
+pub trait ExploitScores: Send + Sync {
+    /// Scores of the given CVE IDs; CVEs without a score are left out.
+    async fn scores(&self, cves: &[&str]) -> Result<HashMap<String, EpssScore>, UpgradeError>;
+}
+
+/// Client for the EPSS API of FIRST.
+pub struct EpssClient {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl Default for EpssClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EpssClient {
+    pub fn new() -> Self {
+        Self::with_api_url("https://api.first.org/data/v1")
+    }
+
+    pub fn with_api_url(api_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ExploitScores for EpssClient {
+    async fn scores(&self, cves: &[&str]) -> Result<HashMap<String, EpssScore>, UpgradeError> {
+        let network_error = |e: reqwest::Error| UpgradeError {
+            message: format!("EPSS request failed: {}", e),
+            error_type: ErrorType::Network,
+        };
+        let mut scores = HashMap::new();
+        for batch in cves.chunks(BATCH_SIZE) {
+            let response = self
+                .client
+                .get(format!("{}/epss", self.api_url))
+                .query(&[("cve", batch.join(","))])
+                .send()
+                .await
+                .map_err(network_error)?;
+            if !response.status().is_success() {
+                return Err(UpgradeError {
+                    message: format!("EPSS request failed with status {}", response.status()),
+                    error_type: ErrorType::Network,
+                });
+            }
+            let body: serde_json::Value = response.json().await.map_err(network_error)?;
+            scores.extend(parse_scores(&body));
+        }
+        Ok(scores)
+    }
+}
+
+/// Scores of an EPSS API response, whose figures are strings.
+pub fn parse_scores(body: &serde_json::Value) -> HashMap<String, EpssScore> {
+    let number = |value: &serde_json::Value| match value {
+        serde_json::Value::String(text) => text.parse().ok(),
+        value => value.as_f64(),
+    };
+    body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            Some((
+                entry["cve"].as_str()?.to_string(),
+                EpssScore {
+                    probability: number(&entry["epss"])?,
+                    percentile: number(&entry["percentile"])?,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// In-memory EPSS scores, for tests and air-gapped deployments.
+#[derive(Debug, Clone, Default)]
+pub struct StaticExploitScores {
+    scores: HashMap<String, EpssScore>,
+}
+
+impl StaticExploitScores {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_score(mut self, cve: &str, probability: f64, percentile: f64) -> Self {
+        self.scores.insert(
+            cve.to_string(),
+            EpssScore {
+                probability,
+                percentile,
+            },
+        );
+        self
+    }
+}
+
+#[async_trait]
+impl ExploitScores for StaticExploitScores {
+    async fn scores(&self, cves: &[&str]) -> Result<HashMap<String, EpssScore>, UpgradeError> {
+        Ok(cves
+            .iter()
+            .filter_map(|cve| Some((cve.to_string(), *self.scores.get(*cve)?)))
+            .collect())
+    }
+}
+
+// The CVE an issue is known as, by ID or alias
+fn cve(issue: &SecurityIssue) -> Option<&str> {
+    std::iter::once(&issue.id)
+        .chain(&issue.aliases)
+        .map(String::as_str)
+        .find(|id| id.starts_with("CVE-"))
+}
+
+impl UpgradeWorker {
+    /// Records the EPSS score of each issue that has a CVE. Issues are left
+    /// unscored when no source is configured or it cannot be reached.
+    pub(crate) async fn score_exploits(
+        &self,
+        issues: &mut [SecurityIssue],
+        warnings: &mut Vec<String>,
+    ) {
+        let Some(source) = &self.exploit_scores else {
+            return;
+        };
+        let mut cves: Vec<&str> = issues.iter().filter_map(cve).collect();
+        cves.sort();
+        cves.dedup();
+        if cves.is_empty() {
+            return;
+        }
+        let scores = match source.scores(&cves).await {
+            Ok(scores) => scores,
+            Err(e) => {
+                warnings.push(format!("Exploit scoring skipped: {}", e.message));
+                return;
+            }
+        };
+        for issue in issues {
+            issue.epss = cve(issue).and_then(|cve| scores.get(cve)).copied();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_scores() {
+        let body = json!({
+            "status": "OK",
+            "total": 2,
+            "data": [
+                {"cve": "CVE-2021-44228", "epss": "0.944010000", "percentile": "0.999890000", "date": "2024-03-01"},
+                {"cve": "CVE-2021-23337", "epss": "0.006250000", "percentile": "0.786450000", "date": "2024-03-01"},
+                {"cve": "CVE-2099-0001"}
+            ]
+        });
+        let scores = parse_scores(&body);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(
+            scores["CVE-2021-44228"],
+            EpssScore {
+                probability: 0.94401,
+                percentile: 0.99989,
+            }
+        );
+        assert_eq!(
+            cve(&SecurityIssue {
+                id: "GHSA-35jh-r3h4-6jhm".to_string(),
+                aliases: vec!["CVE-2021-23337".to_string()],
+                ..Default::default()
+            }),
+            Some("CVE-2021-23337")
+        );
+    }
+}
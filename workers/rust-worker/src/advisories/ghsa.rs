@@ -0,0 +1,344 @@
+use super::{Advisory, AdvisoryDatabase, Severity};
+use crate::credentials::Secret;
+use crate::scm::{Auth, Client};
+use crate::version::{scheme_for, VersionScheme};
+use crate::{ErrorType, UpgradeError};
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+// Pages of 100 vulnerabilities; packages rarely have more than a few
+const MAX_PAGES: usize = 10;
+
+const QUERY: &str =
+    "query($ecosystem: SecurityAdvisoryEcosystem!, $package: String!, $after: String) {
+  securityVulnerabilities(ecosystem: $ecosystem, package: $package, first: 100, after: $after) {
+    nodes {
+      vulnerableVersionRange
+      firstPatchedVersion { identifier }
+      package { name }
+      advisory {
+        ghsaId summary severity withdrawnAt permalink
+        cvss { score }
+        identifiers { type value }
+        references { url }
+      }
+    }
+    pageInfo { hasNextPage endCursor }
+  }
+}";
+
+/// Access to the GitHub Advisory Database.
+#[derive(Debug, Clone)]
+pub struct GhsaConfig {
+    /// Token for the GraphQL API, which refuses anonymous requests; it needs
+    /// no scopes.
+    pub token: Secret,
+    /// GraphQL endpoint of a GitHub Enterprise Server instance
+    /// (`https://github.example.com/api/graphql`); api.github.com when
+    /// unset.
+    pub api_url: Option<String>,
+    /// How long the advisories of a package are reused, to stay within the
+    /// API's rate limits.
+    pub cache_ttl: Duration,
+}
+
+// Advisories of each (ecosystem, package), with when they were fetched
+type Cache = HashMap<(String, String), (Instant, Vec<Advisory>)>;
+
+/// Client for the GitHub Advisory Database, through the vulnerable package
+/// ranges of the GraphQL security advisory API. All advisories of a package
+/// are fetched at once and cached; versions are matched locally.
+pub struct GhsaClient {
+    config: GhsaConfig,
+    timeout: Duration,
+    cache: Mutex<Cache>,
+}
+
+/// Ecosystem name GitHub uses for a SpecCursor ecosystem.
+pub fn ghsa_ecosystem(ecosystem: &str) -> Option<&'static str> {
+    match ecosystem {
+        "npm" | "pnpm" => Some("NPM"),
+        "pip" | "pipenv" => Some("PIP"),
+        "maven" | "gradle" => Some("MAVEN"),
+        "composer" => Some("COMPOSER"),
+        "rubygems" => Some("RUBYGEMS"),
+        "nuget" => Some("NUGET"),
+        "go" => Some("GO"),
+        "cargo" => Some("RUST"),
+        "hex" => Some("ERLANG"),
+        "pubspec" => Some("PUB"),
+        "swiftpm" => Some("SWIFT"),
+        "github-actions" => Some("ACTIONS"),
+        _ => None,
+    }
+}
+
+impl GhsaClient {
+    pub fn new(config: GhsaConfig, timeout: Duration) -> Self {
+        Self {
+            config,
+            timeout,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cached(&self, key: &(String, String)) -> Option<Vec<Advisory>> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .get(key)
+            .filter(|(fetched, _)| fetched.elapsed() < self.config.cache_ttl)
+            .map(|(_, advisories)| advisories.clone())
+    }
+
+    // Every advisory of `package`, with its vulnerable ranges
+    async fn package_advisories(
+        &self,
+        ecosystem: &'static str,
+        package: &str,
+    ) -> Result<Vec<Advisory>, UpgradeError> {
+        let key = (ecosystem.to_string(), package.to_string());
+        if let Some(advisories) = self.cached(&key) {
+            return Ok(advisories);
+        }
+
+        let client = Client::new(
+            "GitHub",
+            Auth::Bearer(self.config.token.read()?),
+            self.timeout,
+        );
+        let url = self.config.api_url.as_deref().unwrap_or(GRAPHQL_URL);
+        let mut nodes = Vec::new();
+        let mut after = serde_json::Value::Null;
+        for _ in 0..MAX_PAGES {
+            let payload = json!({
+                "query": QUERY,
+                "variables": {"ecosystem": ecosystem, "package": package, "after": after},
+            });
+            let body = client.create(url, &payload).await?;
+            if !body["errors"][0].is_null() {
+                return Err(client.error(url, reqwest::StatusCode::OK, &body));
+            }
+            let page = &body["data"]["securityVulnerabilities"];
+            nodes.extend(page["nodes"].as_array().into_iter().flatten().cloned());
+            if page["pageInfo"]["hasNextPage"] != true {
+                break;
+            }
+            after = page["pageInfo"]["endCursor"].clone();
+        }
+
+        let advisories = parse_vulnerabilities(&nodes, package);
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, (Instant::now(), advisories.clone()));
+        Ok(advisories)
+    }
+}
+
+/// `securityVulnerabilities` nodes as one advisory per GHSA ID, with the
+/// ranges of `package` it affects. Withdrawn advisories are left out.
+pub fn parse_vulnerabilities(nodes: &[serde_json::Value], package: &str) -> Vec<Advisory> {
+    let mut advisories: Vec<Advisory> = Vec::new();
+    for node in nodes {
+        let advisory = &node["advisory"];
+        let (Some(id), Some(range)) = (
+            advisory["ghsaId"].as_str(),
+            node["vulnerableVersionRange"].as_str(),
+        ) else {
+            continue;
+        };
+        if !advisory["withdrawnAt"].is_null()
+            || node["package"]["name"]
+                .as_str()
+                .is_some_and(|name| !name.eq_ignore_ascii_case(package))
+        {
+            continue;
+        }
+        let fixed_in: Vec<String> = node["firstPatchedVersion"]["identifier"]
+            .as_str()
+            .map(str::to_string)
+            .into_iter()
+            .collect();
+        if let Some(known) = advisories.iter_mut().find(|known| known.id == id) {
+            known.affected.push(range.to_string());
+            for version in fixed_in {
+                if !known.fixed_in.contains(&version) {
+                    known.fixed_in.push(version);
+                }
+            }
+            continue;
+        }
+        advisories.push(Advisory {
+            id: id.to_string(),
+            aliases: advisory["identifiers"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|identifier| identifier["type"] != "GHSA")
+                .filter_map(|identifier| Some(identifier["value"].as_str()?.to_string()))
+                .collect(),
+            summary: advisory["summary"].as_str().map(str::to_string),
+            affected: vec![range.to_string()],
+            severity: advisory["severity"].as_str().and_then(Severity::parse),
+            // GitHub reports 0.0 when there is no score
+            cvss_score: advisory["cvss"]["score"]
+                .as_f64()
+                .filter(|score| *score > 0.0),
+            fixed_in,
+            references: advisory["permalink"]
+                .as_str()
+                .into_iter()
+                .chain(
+                    advisory["references"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|reference| reference["url"].as_str()),
+                )
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+    advisories
+}
+
+/// Whether `version` lies in a GitHub vulnerable range such as
+/// `>= 4.0.0, < 4.17.21`. Ranges that do not parse match nothing.
+pub fn in_range(scheme: &dyn VersionScheme, range: &str, version: &str) -> bool {
+    let Ok(version) = scheme.parse(version) else {
+        return false;
+    };
+    range.split(',').all(|constraint| {
+        let constraint = constraint.trim();
+        let operator = [">=", "<=", ">", "<", "="]
+            .into_iter()
+            .find(|operator| constraint.starts_with(operator));
+        let Some(bound) =
+            operator.and_then(|operator| scheme.parse(constraint[operator.len()..].trim()).ok())
+        else {
+            return false;
+        };
+        match operator {
+            Some(">=") => version >= bound,
+            Some("<=") => version <= bound,
+            Some(">") => version > bound,
+            Some("<") => version < bound,
+            _ => version == bound,
+        }
+    })
+}
+
+#[async_trait]
+impl AdvisoryDatabase for GhsaClient {
+    fn name(&self) -> &'static str {
+        "ghsa"
+    }
+
+    fn supports(&self, ecosystem: &str) -> bool {
+        ghsa_ecosystem(ecosystem).is_some()
+    }
+
+    async fn advisories(
+        &self,
+        ecosystem: &str,
+        queries: &[(&str, &str)],
+    ) -> Result<Vec<Vec<Advisory>>, UpgradeError> {
+        let Some(ghsa) = ghsa_ecosystem(ecosystem) else {
+            return Err(UpgradeError {
+                message: format!(
+                    "The GitHub Advisory Database does not cover '{}'",
+                    ecosystem
+                ),
+                error_type: ErrorType::Validation,
+            });
+        };
+        let scheme = scheme_for(ecosystem);
+        let mut found = Vec::new();
+        for (package, version) in queries {
+            let advisories = self.package_advisories(ghsa, package).await?;
+            found.push(
+                advisories
+                    .into_iter()
+                    .filter(|advisory| {
+                        advisory
+                            .affected
+                            .iter()
+                            .any(|range| in_range(scheme, range, version))
+                    })
+                    .collect(),
+            );
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::SemanticScheme;
+
+    #[test]
+    fn test_parse_vulnerabilities() {
+        let node = |id: &str, range: &str, withdrawn: Option<&str>| {
+            json!({
+                "vulnerableVersionRange": range,
+                "package": {"name": "lodash"},
+                "firstPatchedVersion": {"identifier": "4.17.12"},
+                "advisory": {
+                    "ghsaId": id,
+                    "summary": "Prototype Pollution in lodash",
+                    "severity": "CRITICAL",
+                    "permalink": "https://github.com/advisories/GHSA-jf85-cpcp-j695",
+                    "cvss": {"score": 9.1},
+                    "withdrawnAt": withdrawn,
+                    "identifiers": [
+                        {"type": "GHSA", "value": id},
+                        {"type": "CVE", "value": "CVE-2019-10744"}
+                    ]
+                }
+            })
+        };
+        let nodes = [
+            node("GHSA-jf85-cpcp-j695", "< 4.17.12", None),
+            node("GHSA-jf85-cpcp-j695", ">= 5.0.0, < 5.0.1", None),
+            node(
+                "GHSA-xxxx-xxxx-xxxx",
+                "< 4.17.12",
+                Some("2021-01-01T00:00:00Z"),
+            ),
+        ];
+        let advisories = parse_vulnerabilities(&nodes, "lodash");
+        assert_eq!(
+            advisories,
+            [Advisory {
+                id: "GHSA-jf85-cpcp-j695".to_string(),
+                aliases: vec!["CVE-2019-10744".to_string()],
+                summary: Some("Prototype Pollution in lodash".to_string()),
+                affected: vec!["< 4.17.12".to_string(), ">= 5.0.0, < 5.0.1".to_string()],
+                severity: Some(Severity::Critical),
+                cvss_score: Some(9.1),
+                fixed_in: vec!["4.17.12".to_string()],
+                references: vec!["https://github.com/advisories/GHSA-jf85-cpcp-j695".to_string()],
+            }]
+        );
+
+        let affects = |version: &str| {
+            advisories[0]
+                .affected
+                .iter()
+                .any(|range| in_range(&SemanticScheme, range, version))
+        };
+        assert!(affects("4.17.11"));
+        assert!(!affects("4.17.12"));
+        assert!(affects("5.0.0"));
+        assert!(!affects("5.0.1"));
+        assert!(in_range(&SemanticScheme, "= 1.2.3", "1.2.3"));
+        assert!(!in_range(&SemanticScheme, "<= not-a-version", "1.2.3"));
+        assert_eq!(ghsa_ecosystem("cargo"), Some("RUST"));
+    }
+}
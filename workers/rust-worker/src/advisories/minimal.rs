@@ -0,0 +1,283 @@
+use super::SecurityIssue;
+use crate::version::{ParsedVersion, VersionJump};
+use crate::{ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker};
+use serde::{Deserialize, Serialize};
+
+// Candidate versions whose advisories are looked up per request
+const BATCH_SIZE: usize = 20;
+
+/// A package version to move off known advisories, for
+/// `POST /upgrade/security`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityUpgradeRequest {
+    pub ecosystem: String,
+    pub package_name: String,
+    pub current_version: String,
+}
+
+/// The smallest upgrade clear of every known advisory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityUpgrade {
+    pub package_name: String,
+    pub current_version: String,
+    /// Version to upgrade to.
+    pub version: String,
+    pub jump: VersionJump,
+    /// Advisories affecting the current version that the upgrade fixes.
+    pub fixes: Vec<SecurityIssue>,
+    pub warnings: Vec<String>,
+}
+
+// Patch releases first, then minor, then major ones
+fn preference(jump: VersionJump) -> u8 {
+    match jump {
+        VersionJump::Patch | VersionJump::Prerelease => 0,
+        VersionJump::Minor => 1,
+        VersionJump::Major => 2,
+        VersionJump::None | VersionJump::Downgrade => 3,
+    }
+}
+
+/// Stable releases newer than `current`, in the order they are tried: by
+/// size of the jump, then lowest version first.
+pub fn candidate_versions(
+    published: &[ParsedVersion],
+    current: &ParsedVersion,
+) -> Vec<ParsedVersion> {
+    let mut candidates: Vec<ParsedVersion> = published
+        .iter()
+        .filter(|version| *version > current && !version.is_prerelease())
+        .cloned()
+        .collect();
+    candidates.sort_by(|a, b| {
+        let rank = |version| preference(VersionJump::classify(current, version));
+        rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+    });
+    candidates.dedup();
+    candidates
+}
+
+impl UpgradeWorker {
+    /// Picks the smallest upgrade of a package version that no known
+    /// advisory affects, preferring patch releases over minor and major
+    /// ones.
+    pub async fn minimal_security_upgrade(
+        &self,
+        request: &SecurityUpgradeRequest,
+    ) -> Result<SecurityUpgrade, UpgradeError> {
+        let lookup = UpgradeRequest {
+            ecosystem: request.ecosystem.clone(),
+            package_name: request.package_name.clone(),
+            current_version: request.current_version.clone(),
+            target_version: request.current_version.clone(),
+            ..Default::default()
+        };
+        if request.package_name.is_empty() {
+            return Err(UpgradeError {
+                message: "Package name cannot be empty".to_string(),
+                error_type: ErrorType::Validation,
+            });
+        }
+        let current = self
+            .version_scheme(&lookup)
+            .parse(&request.current_version)
+            .map_err(|e| UpgradeError {
+                message: format!(
+                    "Invalid current version '{}': {}",
+                    request.current_version, e
+                ),
+                error_type: ErrorType::Validation,
+            })?;
+        if !self.has_registry_for(&lookup) {
+            return Err(UpgradeError {
+                message: format!(
+                    "Finding a secure version of '{}' requires a registry",
+                    request.package_name
+                ),
+                error_type: ErrorType::Validation,
+            });
+        }
+        if !self
+            .advisories
+            .iter()
+            .any(|database| database.supports(&request.ecosystem))
+        {
+            return Err(UpgradeError {
+                message: format!(
+                    "No advisory database covers the {} ecosystem",
+                    request.ecosystem
+                ),
+                error_type: ErrorType::Validation,
+            });
+        }
+
+        let published = self.published_versions(&lookup).await?;
+        let candidates = candidate_versions(&published, &current);
+        let current_version = current.to_string();
+        let mut warnings = Vec::new();
+        let mut fixes = Vec::new();
+        let unreachable = || UpgradeError {
+            message: format!(
+                "No advisory database covering {} could be reached",
+                request.ecosystem
+            ),
+            error_type: ErrorType::Network,
+        };
+
+        // The current version is looked up with the first batch
+        let mut batches = candidates.chunks(BATCH_SIZE).peekable();
+        let mut first = true;
+        while first || batches.peek().is_some() {
+            let batch = batches.next().unwrap_or_default();
+            let versions: Vec<String> = batch.iter().map(ParsedVersion::to_string).collect();
+            let mut queries: Vec<(&str, &str)> = versions
+                .iter()
+                .map(|version| (request.package_name.as_str(), version.as_str()))
+                .collect();
+            if first {
+                queries.insert(0, (&request.package_name, &current_version));
+            }
+            let mut found = self
+                .merged_advisories(
+                    &request.ecosystem,
+                    &queries,
+                    "Security upgrade advisory lookup",
+                    &mut warnings,
+                )
+                .await
+                .ok_or_else(unreachable)?;
+
+            if first {
+                first = false;
+                fixes = found
+                    .remove(0)
+                    .into_iter()
+                    .map(|(source, advisory)| advisory.issue(source, &current_version, true))
+                    .collect();
+                if fixes.is_empty() {
+                    return Err(UpgradeError {
+                        message: format!(
+                            "No known advisory affects {} {}",
+                            request.package_name, current_version
+                        ),
+                        error_type: ErrorType::Validation,
+                    });
+                }
+            }
+
+            if let Some((version, _)) = batch
+                .iter()
+                .zip(&found)
+                .find(|(_, advisories)| advisories.is_empty())
+            {
+                return Ok(SecurityUpgrade {
+                    package_name: request.package_name.clone(),
+                    current_version,
+                    version: version.to_string(),
+                    jump: VersionJump::classify(&current, version),
+                    fixes,
+                    warnings,
+                });
+            }
+        }
+
+        Err(UpgradeError {
+            message: format!(
+                "No published version of '{}' is clear of the advisories affecting {}",
+                request.package_name, current_version
+            ),
+            error_type: ErrorType::Validation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advisories::{Advisory, StaticAdvisories};
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::sync::Arc;
+
+    fn advisory(id: &str) -> Advisory {
+        Advisory {
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_minimal_security_upgrade() {
+        let releases = ["4.17.19", "4.17.20", "4.17.21", "4.18.0", "4.18.1", "5.0.0"];
+        let registry = StaticRegistry::new().with_releases(
+            "npm",
+            "lodash",
+            releases.iter().map(|v| ReleaseInfo::new(v)).collect(),
+        );
+        let advisories = StaticAdvisories::new()
+            .with_advisory(
+                "npm",
+                "lodash",
+                &["4.17.19", "4.17.20"],
+                advisory("GHSA-p6mc-m468-83gw"),
+            )
+            .with_advisory(
+                "npm",
+                "lodash",
+                &["4.17.19", "4.17.20", "4.17.21"],
+                advisory("GHSA-35jh-r3h4-6jhm"),
+            )
+            .with_advisory(
+                "npm",
+                "lodash",
+                &["4.18.0"],
+                advisory("GHSA-29mw-wpgm-hmr9"),
+            );
+        let worker = UpgradeWorker::new(None)
+            .with_registry(Arc::new(registry))
+            .with_advisory_database(Arc::new(advisories));
+        let request = |version: &str| SecurityUpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: version.to_string(),
+        };
+
+        // No patch release is clear, and 4.18.0 has an advisory of its own
+        let upgrade = worker
+            .minimal_security_upgrade(&request("4.17.20"))
+            .await
+            .unwrap();
+        assert_eq!(upgrade.version, "4.18.1");
+        assert_eq!(upgrade.jump, VersionJump::Minor);
+        let fixed: Vec<&str> = upgrade
+            .fixes
+            .iter()
+            .map(|issue| issue.id.as_str())
+            .collect();
+        assert_eq!(fixed, ["GHSA-p6mc-m468-83gw", "GHSA-35jh-r3h4-6jhm"]);
+        assert!(upgrade.fixes.iter().all(|issue| issue.fixed_by_upgrade));
+
+        let err = worker
+            .minimal_security_upgrade(&request("5.0.0"))
+            .await
+            .unwrap_err();
+        assert!(err
+            .message
+            .contains("No known advisory affects lodash 5.0.0"));
+    }
+
+    #[test]
+    fn test_candidate_versions() {
+        let parse = |versions: &[&str]| -> Vec<ParsedVersion> {
+            versions
+                .iter()
+                .map(|v| SemanticScheme.parse(v).unwrap())
+                .collect()
+        };
+        let published = parse(&["1.2.3", "2.0.0", "1.3.0", "1.2.5", "1.2.4", "1.3.0-rc.1"]);
+        assert_eq!(
+            candidate_versions(&published, &parse(&["1.2.3"])[0]),
+            parse(&["1.2.4", "1.2.5", "1.3.0", "2.0.0"])
+        );
+    }
+}
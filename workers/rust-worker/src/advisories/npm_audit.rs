@@ -0,0 +1,184 @@
+use super::{Advisory, AdvisoryDatabase, Severity};
+use crate::version::VersionSpec;
+use crate::{ErrorType, UpgradeError};
+use async_trait::async_trait;
+use std::collections::{BTreeSet, HashMap};
+
+/// Client for the bulk advisory endpoint `npm audit` uses. One request
+/// covers every version of a package that is asked about; the vulnerable
+/// ranges are then matched locally with npm semantics.
+pub struct NpmAuditClient {
+    client: reqwest::Client,
+    registry_url: String,
+}
+
+impl Default for NpmAuditClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NpmAuditClient {
+    pub fn new() -> Self {
+        Self::with_registry_url("https://registry.npmjs.org")
+    }
+
+    /// Uses a registry mirroring the npm audit API, such as a company proxy.
+    pub fn with_registry_url(registry_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            registry_url: registry_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+/// Advisories the bulk endpoint returned for `package`. The GHSA ID is
+/// used when the advisory links to one, so that other databases reporting
+/// it are recognised.
+pub fn parse_bulk_advisories(body: &serde_json::Value, package: &str) -> Vec<Advisory> {
+    body[package]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|advisory| {
+            let npm_id = format!("npm:{}", advisory["id"].as_u64()?);
+            let ghsa = advisory["url"]
+                .as_str()
+                .and_then(|url| url.rsplit('/').next())
+                .filter(|id| id.starts_with("GHSA-"));
+            let (id, aliases) = match ghsa {
+                Some(ghsa) => (ghsa.to_string(), vec![npm_id]),
+                None => (npm_id, Vec::new()),
+            };
+            Some(Advisory {
+                id,
+                aliases,
+                summary: advisory["title"].as_str().map(str::to_string),
+                affected: vec![advisory["vulnerable_versions"].as_str()?.to_string()],
+                severity: advisory["severity"].as_str().and_then(Severity::parse),
+                cvss_score: advisory["cvss"]["score"]
+                    .as_f64()
+                    .filter(|score| *score > 0.0),
+                fixed_in: Vec::new(),
+                references: advisory["url"]
+                    .as_str()
+                    .map(str::to_string)
+                    .into_iter()
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Whether `version` is in one of the npm ranges the advisory affects.
+pub fn affects(advisory: &Advisory, version: &str) -> bool {
+    let Ok(VersionSpec::Exact(version)) = VersionSpec::parse("npm", version) else {
+        return false;
+    };
+    advisory
+        .affected
+        .iter()
+        .any(|range| VersionSpec::parse("npm", range).is_ok_and(|range| range.matches(&version)))
+}
+
+#[async_trait]
+impl AdvisoryDatabase for NpmAuditClient {
+    fn name(&self) -> &'static str {
+        "npm"
+    }
+
+    fn supports(&self, ecosystem: &str) -> bool {
+        matches!(ecosystem, "npm" | "pnpm")
+    }
+
+    async fn advisories(
+        &self,
+        _ecosystem: &str,
+        queries: &[(&str, &str)],
+    ) -> Result<Vec<Vec<Advisory>>, UpgradeError> {
+        let mut versions: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+        for (package, version) in queries {
+            versions.entry(package).or_default().insert(version);
+        }
+        let url = format!("{}/-/npm/v1/security/advisories/bulk", self.registry_url);
+        let network_error = |message: String| UpgradeError {
+            message: format!("npm audit request to {} failed: {}", url, message),
+            error_type: ErrorType::Network,
+        };
+        let response = self
+            .client
+            .post(&url)
+            .json(&versions)
+            .send()
+            .await
+            .map_err(|e| network_error(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(network_error(format!("status {}", response.status())));
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| network_error(e.to_string()))?;
+
+        Ok(queries
+            .iter()
+            .map(|(package, version)| {
+                parse_bulk_advisories(&body, package)
+                    .into_iter()
+                    .filter(|advisory| affects(advisory, version))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bulk_advisories() {
+        let body = json!({
+            "lodash": [
+                {
+                    "id": 1523,
+                    "url": "https://github.com/advisories/GHSA-p6mc-m468-83gw",
+                    "title": "Prototype Pollution in lodash",
+                    "severity": "high",
+                    "cvss": {"score": 7.4, "vectorString": "CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:H/A:N"},
+                    "vulnerable_versions": ">=3.7.0 <4.17.19"
+                },
+                {
+                    "id": 1673,
+                    "url": "https://npmjs.com/advisories/1673",
+                    "title": "Command Injection in lodash",
+                    "severity": "high",
+                    "vulnerable_versions": "<4.17.21"
+                }
+            ]
+        });
+        let advisories = parse_bulk_advisories(&body, "lodash");
+        assert_eq!(advisories[0].id, "GHSA-p6mc-m468-83gw");
+        assert_eq!(advisories[0].aliases, ["npm:1523"]);
+        assert_eq!(advisories[0].severity, Some(Severity::High));
+        assert_eq!(advisories[0].cvss_score, Some(7.4));
+        assert_eq!(advisories[1].id, "npm:1673");
+        assert_eq!(advisories[1].affected, ["<4.17.21"]);
+
+        // 4.17.20 fixes the first advisory but not the second
+        assert!(affects(&advisories[0], "4.17.15"));
+        assert!(!affects(&advisories[0], "4.17.20"));
+        assert!(affects(&advisories[1], "4.17.20"));
+        assert!(!affects(&advisories[1], "4.17.21"));
+        assert!(parse_bulk_advisories(&body, "react").is_empty());
+    }
+}
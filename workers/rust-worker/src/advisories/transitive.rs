@@ -0,0 +1,460 @@
+use crate::{Change, RiskAssessment, RiskLevel, UpgradeRequest, UpgradeWorker};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `(name, version)` pairs pinned by a lockfile.
+type Packages = BTreeSet<(String, String)>;
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Ecosystem whose advisories cover the packages a lockfile pins.
+pub fn lockfile_ecosystem(path: &str) -> Option<&'static str> {
+    match file_name(path) {
+        "Cargo.lock" => Some("cargo"),
+        "package-lock.json" | "npm-shrinkwrap.json" | "yarn.lock" => Some("npm"),
+        "poetry.lock" => Some("pip"),
+        "Gemfile.lock" => Some("rubygems"),
+        _ => None,
+    }
+}
+
+/// Every package a lockfile resolves, as `(name, version)`; `None` when the
+/// lockfile is empty or not in a format that is understood.
+pub fn resolved_packages(path: &str, content: &str) -> Option<Packages> {
+    if content.trim().is_empty() {
+        return None;
+    }
+    match file_name(path) {
+        "Cargo.lock" | "poetry.lock" => toml_packages(content, file_name(path) == "Cargo.lock"),
+        "package-lock.json" | "npm-shrinkwrap.json" => npm_packages(content),
+        "yarn.lock" => Some(yarn_packages(content)),
+        "Gemfile.lock" => Some(gemfile_packages(content)),
+        _ => None,
+    }
+}
+
+// `[[package]]` tables; Cargo workspace members have no source and are
+// left out
+fn toml_packages(content: &str, registry_only: bool) -> Option<Packages> {
+    let lockfile: toml::Value = toml::from_str(content).ok()?;
+    Some(
+        lockfile
+            .get("package")?
+            .as_array()?
+            .iter()
+            .filter(|package| !registry_only || package.get("source").is_some())
+            .filter_map(|package| {
+                Some((
+                    package.get("name")?.as_str()?.to_string(),
+                    package.get("version")?.as_str()?.to_string(),
+                ))
+            })
+            .collect(),
+    )
+}
+
+// `packages` keyed by install path (lockfile v2 and v3), or the nested
+// `dependencies` of v1
+fn npm_packages(content: &str) -> Option<Packages> {
+    let lockfile: serde_json::Value = serde_json::from_str(content).ok()?;
+    let mut packages = Packages::new();
+    if let Some(installed) = lockfile["packages"].as_object() {
+        for (path, package) in installed {
+            let Some(name) = path
+                .rsplit("node_modules/")
+                .next()
+                .filter(|_| !path.is_empty())
+            else {
+                continue;
+            };
+            if package["link"] == true {
+                continue;
+            }
+            if let Some(version) = package["version"].as_str() {
+                packages.insert((name.to_string(), version.to_string()));
+            }
+        }
+        return Some(packages);
+    }
+
+    let mut pending = vec![&lockfile["dependencies"]];
+    while let Some(dependencies) = pending.pop() {
+        for (name, package) in dependencies.as_object().into_iter().flatten() {
+            if let Some(version) = package["version"].as_str() {
+                packages.insert((name.clone(), version.to_string()));
+            }
+            pending.push(&package["dependencies"]);
+        }
+    }
+    Some(packages)
+}
+
+// Entries of Yarn classic (`version "1.2.3"`) and Berry (`version: 1.2.3`)
+// lockfiles, named after the first of their descriptors
+fn yarn_packages(content: &str) -> Packages {
+    let mut packages = Packages::new();
+    let mut name: Option<String> = None;
+    for line in content.lines() {
+        if !line.starts_with(' ') && line.ends_with(':') {
+            let descriptor = line.split(", ").next().unwrap_or(line);
+            let descriptor = descriptor.trim_end_matches(':').trim_matches('"');
+            name = descriptor
+                .rfind('@')
+                .filter(|&at| at > 0 && !descriptor[at..].starts_with("@workspace:"))
+                .map(|at| descriptor[..at].to_string());
+            continue;
+        }
+        let entry = line.trim_start();
+        if let (Some(package), Some(version)) = (
+            &name,
+            entry
+                .strip_prefix("version ")
+                .or_else(|| entry.strip_prefix("version:")),
+        ) {
+            let version = version.trim().trim_matches('"');
+            packages.insert((package.clone(), version.to_string()));
+            name = None;
+        }
+    }
+    packages
+}
+
+// `name (version)` lines of the GEM specs; platform suffixes
+// (`-x86_64-linux`) are dropped
+fn gemfile_packages(content: &str) -> Packages {
+    let mut packages = Packages::new();
+    let mut in_gems = false;
+    for line in content.lines() {
+        if !line.starts_with(' ') {
+            in_gems = line == "GEM";
+            continue;
+        }
+        let Some(spec) = line
+            .strip_prefix("    ")
+            .filter(|spec| !spec.starts_with(' '))
+        else {
+            continue;
+        };
+        if let (true, Some((name, version))) = (in_gems, spec.split_once(" (")) {
+            let version = version.trim_end_matches(')');
+            let version = version.split('-').next().unwrap_or(version);
+            packages.insert((name.to_string(), version.to_string()));
+        }
+    }
+    packages
+}
+
+/// Packages pinned after the upgrade that were not before, at a new version
+/// or not at all.
+pub fn introduced(before: &Packages, after: &Packages) -> Packages {
+    after.difference(before).cloned().collect()
+}
+
+/// Names of the packages a lockfile pins that compile native code: npm
+/// entries with install scripts (`node-gyp` builds), `-sys` crates and the
+/// `cc` and `cmake` build helpers. Other lockfiles do not record it.
+pub fn native_packages(path: &str, content: &str) -> BTreeSet<String> {
+    match file_name(path) {
+        "Cargo.lock" => toml_packages(content, true)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| name.ends_with("-sys") || name == "cc" || name == "cmake")
+            .collect(),
+        "package-lock.json" | "npm-shrinkwrap.json" => {
+            let lockfile: serde_json::Value = serde_json::from_str(content).unwrap_or_default();
+            lockfile["packages"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(_, package)| package["hasInstallScript"] == true)
+                .filter_map(|(path, _)| Some(path.rsplit("node_modules/").next()?.to_string()))
+                .filter(|name| !name.is_empty())
+                .collect()
+        }
+        _ => BTreeSet::new(),
+    }
+}
+
+impl UpgradeWorker {
+    /// Looks up the packages the regenerated lockfiles newly resolve and
+    /// lists the advisories affecting them that did not affect the versions
+    /// pinned before. Such an advisory makes the upgrade high risk.
+    pub(crate) async fn check_transitive_advisories(
+        &self,
+        request: &UpgradeRequest,
+        changes: &[Change],
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        // Packages introduced per ecosystem, with the versions they replace
+        let mut introduced_packages: BTreeMap<&str, BTreeMap<(String, String), Vec<String>>> =
+            BTreeMap::new();
+        for change in changes {
+            if !change.metadata.contains_key("regenerated") {
+                continue;
+            }
+            let path = change.file_path.as_str();
+            let (Some(ecosystem), Some(before), Some(after)) = (
+                lockfile_ecosystem(path),
+                request
+                    .manifests
+                    .get(path)
+                    .and_then(|content| resolved_packages(path, content)),
+                resolved_packages(path, &change.content),
+            ) else {
+                continue;
+            };
+            let found = introduced_packages.entry(ecosystem).or_default();
+            for (name, version) in introduced(&before, &after) {
+                if name == request.package_name
+                    || request.replacement_package.as_deref() == Some(name.as_str())
+                {
+                    continue;
+                }
+                let previous = before
+                    .iter()
+                    .filter(|(other, _)| *other == name)
+                    .map(|(_, version)| version.clone())
+                    .collect();
+                found.insert((name, version), previous);
+            }
+        }
+
+        let mut issues = Vec::new();
+        for (ecosystem, packages) in introduced_packages {
+            let mut queries: BTreeSet<(&str, &str)> = BTreeSet::new();
+            for ((name, version), previous) in &packages {
+                queries.insert((name, version));
+                queries.extend(previous.iter().map(|old| (name.as_str(), old.as_str())));
+            }
+            let queries: Vec<(&str, &str)> = queries.into_iter().collect();
+            if queries.is_empty() {
+                continue;
+            }
+
+            let known = self
+                .merged_advisories(ecosystem, &queries, "Transitive advisory check", warnings)
+                .await
+                .unwrap_or_default();
+            let advisories_of = |name: &str, version: &str| {
+                queries
+                    .iter()
+                    .position(|query| *query == (name, version))
+                    .and_then(|i| known.get(i))
+                    .map_or(&[][..], Vec::as_slice)
+            };
+
+            for ((name, version), previous) in &packages {
+                for (source, advisory) in advisories_of(name, version) {
+                    let already_affected = previous.iter().any(|old| {
+                        advisories_of(name, old)
+                            .iter()
+                            .any(|(_, other)| other.is_same(advisory))
+                    });
+                    if already_affected {
+                        continue;
+                    }
+                    let mut issue = advisory.issue(source, version, false);
+                    issue.package = Some(name.clone());
+                    issues.push(issue);
+                    risk.risk_level = risk.risk_level.max(RiskLevel::High);
+                    risk.explanations.push(format!(
+                        "The upgrade pulls in {} {}, affected by {}",
+                        name, version, advisory.id
+                    ));
+                }
+            }
+        }
+        self.score_exploits(&mut issues, warnings).await;
+        risk.security_issues.extend(issues);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advisories::{Advisory, StaticAdvisories};
+    use crate::{ChangeType, VersionJump};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn pairs(packages: &[(&str, &str)]) -> Packages {
+        packages
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolved_packages() {
+        let cargo = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.190"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(
+            resolved_packages("crates/Cargo.lock", cargo),
+            Some(pairs(&[("serde", "1.0.190")]))
+        );
+
+        let npm = r#"{
+  "lockfileVersion": 3,
+  "packages": {
+    "": {"name": "app", "version": "1.0.0"},
+    "node_modules/express": {"version": "4.18.2"},
+    "node_modules/express/node_modules/qs": {"version": "6.11.0"},
+    "node_modules/@types/node": {"version": "20.8.0"},
+    "node_modules/shared": {"resolved": "packages/shared", "link": true}
+  }
+}"#;
+        assert_eq!(
+            resolved_packages("package-lock.json", npm),
+            Some(pairs(&[
+                ("@types/node", "20.8.0"),
+                ("express", "4.18.2"),
+                ("qs", "6.11.0"),
+            ]))
+        );
+        let npm_v1 = r#"{"dependencies": {"express": {"version": "4.17.1", "dependencies": {"qs": {"version": "6.7.0"}}}}}"#;
+        assert_eq!(
+            resolved_packages("npm-shrinkwrap.json", npm_v1),
+            Some(pairs(&[("express", "4.17.1"), ("qs", "6.7.0")]))
+        );
+
+        let yarn = r#"# yarn lockfile v1
+
+"@babel/core@^7.0.0", "@babel/core@^7.1.0":
+  version "7.23.2"
+  resolved "https://registry.yarnpkg.com/@babel/core/-/core-7.23.2.tgz"
+
+"app@workspace:.":
+  version: 0.0.0-use.local
+
+"minimist@npm:^1.2.0":
+  version: 1.2.8
+"#;
+        assert_eq!(
+            resolved_packages("web/yarn.lock", yarn),
+            Some(pairs(&[("@babel/core", "7.23.2"), ("minimist", "1.2.8")]))
+        );
+
+        let gemfile = "GEM\n  remote: https://rubygems.org/\n  specs:\n    nokogiri (1.15.4-x86_64-linux)\n      racc (~> 1.4)\n    racc (1.7.1)\n\nPLATFORMS\n  x86_64-linux\n";
+        assert_eq!(
+            resolved_packages("Gemfile.lock", gemfile),
+            Some(pairs(&[("nokogiri", "1.15.4"), ("racc", "1.7.1")]))
+        );
+
+        assert_eq!(resolved_packages("package-lock.json", ""), None);
+        assert_eq!(resolved_packages("go.sum", "x v1.0.0 h1:abc="), None);
+    }
+
+    #[test]
+    fn test_introduced() {
+        let before = pairs(&[("qs", "6.7.0"), ("express", "4.17.1")]);
+        let after = pairs(&[("qs", "6.11.0"), ("express", "4.17.1"), ("ms", "2.1.3")]);
+        assert_eq!(
+            introduced(&before, &after),
+            pairs(&[("ms", "2.1.3"), ("qs", "6.11.0")])
+        );
+    }
+
+    #[test]
+    fn test_native_packages() {
+        let npm = r#"{"packages": {
+            "": {"hasInstallScript": true},
+            "node_modules/bcrypt": {"version": "5.1.1", "hasInstallScript": true},
+            "node_modules/ms": {"version": "2.1.3"}
+        }}"#;
+        assert_eq!(
+            native_packages("package-lock.json", npm),
+            BTreeSet::from(["bcrypt".to_string()])
+        );
+        let cargo = r#"
+[[package]]
+name = "openssl-sys"
+version = "0.9.96"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.190"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(
+            native_packages("Cargo.lock", cargo),
+            BTreeSet::from(["openssl-sys".to_string()])
+        );
+        assert!(native_packages("yarn.lock", "").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_transitive_advisories() {
+        let lockfile = |express: &str, qs: &str| {
+            format!(
+                r#"{{"lockfileVersion": 3, "packages": {{
+                    "node_modules/express": {{"version": "{}"}},
+                    "node_modules/qs": {{"version": "{}"}},
+                    "node_modules/ms": {{"version": "2.1.3"}}
+                }}}}"#,
+                express, qs
+            )
+        };
+        let advisory = |id: &str| Advisory {
+            id: id.to_string(),
+            summary: Some("Prototype Pollution in qs".to_string()),
+            ..Default::default()
+        };
+        // One advisory already affected the old qs, the other is new
+        let database = StaticAdvisories::new()
+            .with_advisory("npm", "qs", &["6.7.0", "6.10.0"], advisory("GHSA-old"))
+            .with_advisory("npm", "qs", &["6.10.0"], advisory("GHSA-new"))
+            .with_advisory("npm", "ms", &["2.1.3"], advisory("GHSA-ms"))
+            .with_advisory("npm", "express", &["4.18.0"], advisory("GHSA-direct"));
+        let worker = UpgradeWorker::new(None).with_advisory_database(Arc::new(database));
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "express".to_string(),
+            manifests: HashMap::from([(
+                "package-lock.json".to_string(),
+                lockfile("4.17.1", "6.7.0"),
+            )]),
+            ..Default::default()
+        };
+        let changes = [Change {
+            file_path: "package-lock.json".to_string(),
+            change_type: ChangeType::Modify,
+            content: lockfile("4.18.0", "6.10.0"),
+            metadata: HashMap::from([("regenerated".to_string(), serde_json::json!(true))]),
+        }];
+        let mut risk = RiskAssessment {
+            version_jump: VersionJump::Patch,
+            ..Default::default()
+        };
+        let mut warnings = Vec::new();
+        worker
+            .check_transitive_advisories(&request, &changes, &mut risk, &mut warnings)
+            .await;
+
+        assert_eq!(risk.risk_level, RiskLevel::High);
+        let issues: Vec<String> = risk.security_issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            issues,
+            ["GHSA-new affects qs 6.10.0: Prototype Pollution in qs"]
+        );
+        assert_eq!(
+            risk.explanations,
+            ["The upgrade pulls in qs 6.10.0, affected by GHSA-new"]
+        );
+        assert!(warnings.is_empty());
+    }
+}
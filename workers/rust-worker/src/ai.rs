@@ -0,0 +1,542 @@
+use crate::credentials::Secret;
+use crate::lockfile::is_safe_path;
+use crate::release_notes::ReleaseNotes;
+use crate::verification::{StepStatus, Verification};
+use crate::version::ResolvedVersions;
+use crate::{rename, Change, ChangeType, ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Highest confidence a suggested change is given: suggestions are never
+/// as certain as edits the worker derives itself.
+pub const MAX_CONFIDENCE: f64 = 0.99;
+
+/// How suggested fixes are used when [`crate::WorkerConfig::ai`] is set.
+#[derive(Debug, Clone)]
+pub struct AiConfig {
+    /// Add suggestions to the changes, which are then written, committed
+    /// and proposed like any other edit, instead of only returning them in
+    /// `UpgradeResponse::suggested_changes`.
+    pub apply: bool,
+    /// Source files sent to the provider at most.
+    pub max_files: usize,
+    /// Suggestions the provider is less confident in are dropped.
+    pub min_confidence: f64,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        Self {
+            apply: false,
+            max_files: 8,
+            min_confidence: 0.5,
+        }
+    }
+}
+
+/// What a provider is asked to fix: the upgrade, the failing build and the
+/// files fixes may edit.
+#[derive(Debug, Clone, Serialize)]
+pub struct FixRequest {
+    pub ecosystem: String,
+    pub package: String,
+    pub current_version: String,
+    pub target_version: String,
+    pub failed_command: String,
+    pub build_log: String,
+    pub release_notes: Option<ReleaseNotes>,
+    /// Content of each file, by repository path.
+    pub files: BTreeMap<String, String>,
+}
+
+/// A fix proposed by a provider: the whole new content of one file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixSuggestion {
+    pub file_path: String,
+    pub content: String,
+    pub confidence: f64,
+    #[serde(default)]
+    pub rationale: String,
+}
+
+/// A language model proposing source edits for builds an upgrade breaks.
+#[async_trait]
+pub trait FixProvider: Send + Sync {
+    /// Name recorded on suggested changes (`anthropic:claude-sonnet-4-5`).
+    fn name(&self) -> String;
+
+    async fn suggest_fixes(&self, request: &FixRequest)
+        -> Result<Vec<FixSuggestion>, UpgradeError>;
+}
+
+const SYSTEM_PROMPT: &str = "You fix source code broken by dependency upgrades. \
+Only edit the files you are given. Reply with a JSON object and nothing else: \
+{\"fixes\": [{\"file_path\": \"<path>\", \"content\": \"<complete new file content>\", \
+\"confidence\": <0 to 1>, \"rationale\": \"<one sentence>\"}]}. \
+Reply with {\"fixes\": []} when no edit of the given files fixes the build.";
+
+/// The user prompt describing `request`.
+pub fn prompt(request: &FixRequest) -> String {
+    let mut prompt = format!(
+        "Upgrading the {} package `{}` from {} to {} makes `{}` fail:\n\n```\n{}\n```\n",
+        request.ecosystem,
+        request.package,
+        request.current_version,
+        request.target_version,
+        request.failed_command,
+        request.build_log
+    );
+    if let Some(notes) = &request.release_notes {
+        for (title, entries) in [
+            ("Breaking changes", &notes.breaking),
+            ("Migration notes", &notes.migration),
+        ] {
+            if !entries.is_empty() {
+                prompt.push_str(&format!("\n{} from the release notes:\n", title));
+            }
+            for note in entries {
+                prompt.push_str(&format!("- {} ({})\n", note.text, note.version));
+            }
+        }
+    }
+    for (path, content) in &request.files {
+        prompt.push_str(&format!("\nFile `{}`:\n```\n{}\n```\n", path, content));
+    }
+    prompt
+}
+
+/// Suggestions in a provider's reply, which may wrap the JSON object in
+/// prose or a code fence.
+pub fn parse_suggestions(reply: &str) -> Result<Vec<FixSuggestion>, String> {
+    #[derive(Deserialize)]
+    struct Reply {
+        fixes: Vec<FixSuggestion>,
+    }
+    let (Some(start), Some(end)) = (reply.find('{'), reply.rfind('}')) else {
+        return Err("the reply holds no JSON object".to_string());
+    };
+    if end < start {
+        return Err("the reply holds no JSON object".to_string());
+    }
+    serde_json::from_str::<Reply>(&reply[start..=end])
+        .map(|reply| reply.fixes)
+        .map_err(|e| format!("the reply is not a list of fixes: {}", e))
+}
+
+fn provider_error(provider: &str, message: String) -> UpgradeError {
+    UpgradeError {
+        message: format!("{} request failed: {}", provider, message),
+        error_type: ErrorType::Network,
+    }
+}
+
+fn http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(concat!(
+            "speccursor-rust-worker/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Anthropic's Messages API.
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Secret,
+    model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: Secret, model: &str, timeout: Duration) -> Self {
+        Self {
+            client: http_client(timeout),
+            api_url: "https://api.anthropic.com".to_string(),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl FixProvider for AnthropicProvider {
+    fn name(&self) -> String {
+        format!("anthropic:{}", self.model)
+    }
+
+    async fn suggest_fixes(
+        &self,
+        request: &FixRequest,
+    ) -> Result<Vec<FixSuggestion>, UpgradeError> {
+        let body = json!({
+            "model": self.model,
+            "max_tokens": 16384,
+            "system": SYSTEM_PROMPT,
+            "messages": [{"role": "user", "content": prompt(request)}],
+        });
+        let response = self
+            .client
+            .post(format!("{}/v1/messages", self.api_url))
+            .header("x-api-key", self.api_key.read()?)
+            .header("anthropic-version", "2023-06-01")
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| provider_error("Anthropic", e.to_string()))?;
+        let reply: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| provider_error("Anthropic", e.to_string()))?;
+        let text = reply["content"][0]["text"].as_str().unwrap_or_default();
+        parse_suggestions(text).map_err(|e| provider_error("Anthropic", e))
+    }
+}
+
+/// OpenAI's Chat Completions API, or a self-hosted server speaking it
+/// (vLLM, Ollama, llama.cpp).
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Option<Secret>,
+    model: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(api_key: Option<Secret>, model: &str, timeout: Duration) -> Self {
+        Self {
+            client: http_client(timeout),
+            api_url: "https://api.openai.com/v1".to_string(),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+
+    /// Sends requests to a self-hosted server (`http://localhost:11434/v1`).
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl FixProvider for OpenAiProvider {
+    fn name(&self) -> String {
+        format!("openai:{}", self.model)
+    }
+
+    async fn suggest_fixes(
+        &self,
+        request: &FixRequest,
+    ) -> Result<Vec<FixSuggestion>, UpgradeError> {
+        let body = json!({
+            "model": self.model,
+            "messages": [
+                {"role": "system", "content": SYSTEM_PROMPT},
+                {"role": "user", "content": prompt(request)},
+            ],
+        });
+        let mut http = self
+            .client
+            .post(format!("{}/chat/completions", self.api_url))
+            .json(&body);
+        if let Some(api_key) = &self.api_key {
+            http = http.bearer_auth(api_key.read()?);
+        }
+        let response = http
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| provider_error("OpenAI", e.to_string()))?;
+        let reply: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| provider_error("OpenAI", e.to_string()))?;
+        let text = reply["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap_or_default();
+        parse_suggestions(text).map_err(|e| provider_error("OpenAI", e))
+    }
+}
+
+/// Fixed suggestions, for tests and dry runs.
+#[derive(Default)]
+pub struct StaticFixProvider {
+    suggestions: Vec<FixSuggestion>,
+}
+
+impl StaticFixProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_suggestion(mut self, suggestion: FixSuggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+}
+
+#[async_trait]
+impl FixProvider for StaticFixProvider {
+    fn name(&self) -> String {
+        "static".to_string()
+    }
+
+    async fn suggest_fixes(
+        &self,
+        _request: &FixRequest,
+    ) -> Result<Vec<FixSuggestion>, UpgradeError> {
+        Ok(self.suggestions.clone())
+    }
+}
+
+impl UpgradeWorker {
+    /// Asks the fix provider for edits making a failed verification pass,
+    /// sending the files the build log names, then those importing the
+    /// package. The suggestions are added to `changes` only when
+    /// [`AiConfig::apply`] is set and are returned otherwise; either way
+    /// they carry `metadata.confidence` below 1 and `metadata.suggested_by`.
+    pub(crate) async fn suggest_fixes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        verification: Option<&Verification>,
+        release_notes: Option<&ReleaseNotes>,
+        changes: &mut Vec<Change>,
+        warnings: &mut Vec<String>,
+    ) -> Vec<Change> {
+        let (Some(config), Some(provider)) = (&self.config.ai, &self.fix_provider) else {
+            return Vec::new();
+        };
+        let Some(failed) = verification
+            .into_iter()
+            .flat_map(|verification| &verification.steps)
+            .find(|step| step.status == StepStatus::Failed)
+        else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<&str> = request
+            .manifests
+            .keys()
+            .map(String::as_str)
+            .filter(|path| failed.log.contains(path))
+            .collect();
+        paths.sort_unstable();
+        for path in rename::affected_imports(request) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths.truncate(config.max_files);
+        let current = |path: &str| {
+            changes
+                .iter()
+                .find(|change| change.file_path == path)
+                .map(|change| change.content.clone())
+                .or_else(|| request.manifests.get(path).cloned())
+        };
+        let fix_request = FixRequest {
+            ecosystem: request.ecosystem.clone(),
+            package: request.package_name.clone(),
+            current_version: versions.current.to_string(),
+            target_version: versions.target.to_string(),
+            failed_command: failed.command.clone(),
+            build_log: failed.log.clone(),
+            release_notes: release_notes.cloned(),
+            files: paths
+                .iter()
+                .filter_map(|path| Some((path.to_string(), current(path)?)))
+                .collect(),
+        };
+
+        let suggestions = match provider.suggest_fixes(&fix_request).await {
+            Ok(suggestions) => suggestions,
+            Err(e) => {
+                warnings.push(format!("Fix suggestions skipped: {}", e.message));
+                return Vec::new();
+            }
+        };
+        let name = provider.name();
+        let mut suggested = Vec::new();
+        for suggestion in suggestions {
+            // Only the files sent may be edited
+            if !fix_request.files.contains_key(&suggestion.file_path)
+                || !is_safe_path(&suggestion.file_path)
+                || suggestion.confidence < config.min_confidence
+            {
+                continue;
+            }
+            let mut change = Change {
+                file_path: suggestion.file_path,
+                change_type: ChangeType::Modify,
+                content: suggestion.content,
+                metadata: Default::default(),
+            };
+            change.metadata.insert(
+                "confidence".to_string(),
+                json!(suggestion.confidence.min(MAX_CONFIDENCE)),
+            );
+            change
+                .metadata
+                .insert("suggested_by".to_string(), json!(name));
+            change
+                .metadata
+                .insert("rationale".to_string(), json!(suggestion.rationale));
+            suggested.push(change);
+        }
+        if suggested.is_empty() {
+            return Vec::new();
+        }
+
+        if !config.apply {
+            warnings.push(format!(
+                "{} suggested {} unverified fix{} in suggested_changes",
+                name,
+                suggested.len(),
+                if suggested.len() == 1 { "" } else { "es" }
+            ));
+            return suggested;
+        }
+        warnings.push(format!(
+            "Applied {} unverified fix{} suggested by {}",
+            suggested.len(),
+            if suggested.len() == 1 { "" } else { "es" },
+            name
+        ));
+        for fix in suggested {
+            match changes
+                .iter_mut()
+                .find(|change| change.file_path == fix.file_path)
+            {
+                Some(change) => {
+                    change.content = fix.content;
+                    change.metadata.extend(fix.metadata);
+                }
+                None => changes.push(fix),
+            }
+        }
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::VerificationConfig;
+    use crate::WorkerConfig;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_prompt_and_reply() {
+        let request = FixRequest {
+            ecosystem: "cargo".to_string(),
+            package: "tokio".to_string(),
+            current_version: "1.30.0".to_string(),
+            target_version: "1.40.0".to_string(),
+            failed_command: "cargo check --all-targets".to_string(),
+            build_log: "error[E0425]: cannot find function `sleep_ms` in module `time`\n --> src/main.rs:2:17".to_string(),
+            release_notes: None,
+            files: BTreeMap::from([(
+                "src/main.rs".to_string(),
+                "fn main() {\n    tokio::time::sleep_ms(10);\n}".to_string(),
+            )]),
+        };
+        let prompt = prompt(&request);
+        assert!(prompt.starts_with(
+            "Upgrading the cargo package `tokio` from 1.30.0 to 1.40.0 makes `cargo check --all-targets` fail:"
+        ));
+        assert!(prompt.contains("\nFile `src/main.rs`:\n```\nfn main() {"));
+
+        let reply = "Here is the fix:\n```json\n{\"fixes\": [{\"file_path\": \"src/main.rs\", \
+                     \"content\": \"fn main() {}\", \"confidence\": 0.8}]}\n```";
+        let suggestions = parse_suggestions(reply).unwrap();
+        assert_eq!(suggestions[0].file_path, "src/main.rs");
+        assert_eq!(suggestions[0].confidence, 0.8);
+        assert!(parse_suggestions("I cannot help with that").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_suggested_fixes_applied_only_when_allowed() {
+        let root = tempfile::tempdir().unwrap();
+        let provider = StaticFixProvider::new()
+            .with_suggestion(FixSuggestion {
+                file_path: "apps/web/src/index.js".to_string(),
+                content: "import _ from 'lodash-es';".to_string(),
+                confidence: 1.0,
+                rationale: "lodash 5 is published as ES modules".to_string(),
+            })
+            .with_suggestion(FixSuggestion {
+                file_path: "../outside.js".to_string(),
+                content: String::new(),
+                confidence: 0.9,
+                rationale: String::new(),
+            });
+        let provider: Arc<dyn FixProvider> = Arc::new(provider);
+        let upgrade = |workspace: &str, apply: bool| {
+            let path = root.path().join(workspace);
+            std::fs::create_dir(&path).unwrap();
+            crate::repo::tests::source_repository(&path);
+            let worker = UpgradeWorker::new(Some(WorkerConfig {
+                local_roots: vec![root.path().to_path_buf()],
+                verification: Some(VerificationConfig {
+                    commands: HashMap::from([(
+                        "npm".to_string(),
+                        "grep -q lodash-es apps/web/src/index.js".to_string(),
+                    )]),
+                    ..VerificationConfig::default()
+                }),
+                ai: Some(AiConfig {
+                    apply,
+                    ..AiConfig::default()
+                }),
+                sandbox_enabled: false,
+                ..WorkerConfig::default()
+            }))
+            .with_fix_provider(provider.clone());
+            let request = UpgradeRequest {
+                repository: "acme/app".to_string(),
+                ecosystem: "npm".to_string(),
+                package_name: "lodash".to_string(),
+                current_version: "4.17.20".to_string(),
+                target_version: "5.0.0".to_string(),
+                local_path: Some(path.to_string_lossy().to_string()),
+                ..Default::default()
+            };
+            (worker, request, path)
+        };
+
+        let (worker, request, path) = upgrade("suggested", false);
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert_eq!(response.suggested_changes.len(), 1);
+        let fix = &response.suggested_changes[0];
+        assert_eq!(fix.metadata["confidence"], MAX_CONFIDENCE);
+        assert_eq!(fix.metadata["suggested_by"], "static");
+        assert!(response
+            .changes
+            .iter()
+            .all(|change| change.file_path != "apps/web/src/index.js"));
+        assert_eq!(
+            std::fs::read_to_string(path.join("apps/web/src/index.js")).unwrap(),
+            "import _ from 'lodash';"
+        );
+
+        let (worker, request, path) = upgrade("applied", true);
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert!(response.suggested_changes.is_empty());
+        assert!(response
+            .changes
+            .iter()
+            .any(|change| change.file_path == "apps/web/src/index.js"));
+        assert_eq!(
+            std::fs::read_to_string(path.join("apps/web/src/index.js")).unwrap(),
+            "import _ from 'lodash-es';"
+        );
+    }
+}
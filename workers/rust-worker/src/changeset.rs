@@ -0,0 +1,111 @@
+use crate::{Change, ChangeType, UpgradeRequest};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Hex-encoded SHA-256 of a file's content.
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// The state each file touched by `changes` must be in before they are
+/// applied: the hash of the content supplied with the request, or `None`
+/// for files an `Add` expects to create. Files the request did not supply
+/// carry no precondition.
+pub fn original_hashes(
+    request: &UpgradeRequest,
+    changes: &[Change],
+) -> BTreeMap<String, Option<String>> {
+    let mut hashes = BTreeMap::new();
+    for change in changes {
+        match request.manifests.get(&change.file_path) {
+            Some(original) => {
+                hashes.insert(change.file_path.clone(), Some(content_hash(original)));
+            }
+            None if matches!(change.change_type, ChangeType::Add) => {
+                hashes.insert(change.file_path.clone(), None);
+            }
+            None => {}
+        }
+    }
+    hashes
+}
+
+/// The change set undoing `changes`: edited files get their original
+/// content back, added files are deleted and deleted files restored. Content
+/// is always whole files, whatever format `changes` were returned in. Files
+/// the request did not supply cannot be restored and are left out.
+pub fn generate_rollback(request: &UpgradeRequest, changes: &[Change]) -> Vec<Change> {
+    let mut seen = HashSet::new();
+    let mut rollback = Vec::new();
+    for change in changes {
+        if !seen.insert(change.file_path.as_str()) {
+            continue;
+        }
+        let original = request.manifests.get(&change.file_path);
+        let (change_type, content) = match (&change.change_type, original) {
+            (ChangeType::Add, None) => (ChangeType::Delete, String::new()),
+            (ChangeType::Delete, Some(original)) => (ChangeType::Add, original.clone()),
+            (ChangeType::BaselineBump, Some(original)) => {
+                (ChangeType::BaselineBump, original.clone())
+            }
+            (_, Some(original)) => (ChangeType::Modify, original.clone()),
+            (_, None) => continue,
+        };
+        rollback.push(Change {
+            file_path: change.file_path.clone(),
+            change_type,
+            content,
+            metadata: HashMap::new(),
+        });
+    }
+    rollback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, change_type: ChangeType, content: &str) -> Change {
+        Change {
+            file_path: path.to_string(),
+            change_type,
+            content: content.to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_hashes_and_rollback() {
+        let request = UpgradeRequest {
+            manifests: HashMap::from([
+                ("package.json".to_string(), "{}".to_string()),
+                (".eslintrc.json".to_string(), "{\"rules\": {}}".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let changes = vec![
+            change("package.json", ChangeType::Modify, "{\"a\": 1}"),
+            change("eslint.config.mjs", ChangeType::Add, "export default [];"),
+            change(".eslintrc.json", ChangeType::Delete, ""),
+            change("yarn.lock", ChangeType::Modify, ""),
+        ];
+
+        let hashes = original_hashes(&request, &changes);
+        assert_eq!(
+            hashes["package.json"].as_deref(),
+            Some("44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a")
+        );
+        assert_eq!(hashes["eslint.config.mjs"], None);
+        assert!(hashes[".eslintrc.json"].is_some());
+        assert!(!hashes.contains_key("yarn.lock"));
+
+        let rollback = generate_rollback(&request, &changes);
+        assert_eq!(rollback.len(), 3);
+        assert!(matches!(rollback[0].change_type, ChangeType::Modify));
+        assert_eq!(rollback[0].content, "{}");
+        assert!(matches!(rollback[1].change_type, ChangeType::Delete));
+        assert_eq!(rollback[1].file_path, "eslint.config.mjs");
+        assert!(matches!(rollback[2].change_type, ChangeType::Add));
+        assert_eq!(rollback[2].content, "{\"rules\": {}}");
+    }
+}
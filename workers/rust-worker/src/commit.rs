@@ -0,0 +1,516 @@
+use crate::ecosystems::gitlink;
+use crate::repo::{write_changes, Checkout};
+use crate::signing::{sign_commit, SigningConfig};
+use crate::version::ResolvedVersions;
+use crate::{
+    Change, ChangeType, DependencyKind, ErrorType, RiskAssessment, UpgradeError, UpgradeRequest,
+    UpgradeWorker,
+};
+use git2::{IndexEntry, IndexTime, Oid, Reference, Repository, Signature};
+use std::path::Path;
+
+/// How upgrades are committed to a branch of the checkout when
+/// [`crate::WorkerConfig::commit`] is set.
+///
+/// Templates may use `{package}`, `{current}`, `{target}`, `{ecosystem}` and
+/// `{repository}`.
+#[derive(Debug, Clone)]
+pub struct CommitConfig {
+    /// Name of the branch created for the upgrade.
+    pub branch_template: String,
+    /// Commit message; a multi-line template adds a body.
+    pub message_template: String,
+    /// Prefix the subject with a Conventional Commits type and scope
+    /// (`chore(deps): bump ...`), marking breaking upgrades with `!` and a
+    /// `BREAKING CHANGE` footer.
+    pub conventional: bool,
+    pub author_name: String,
+    pub author_email: String,
+    /// Sign commits with a GPG or SSH key; unsigned when unset.
+    pub signing: Option<SigningConfig>,
+}
+
+impl Default for CommitConfig {
+    fn default() -> Self {
+        Self {
+            branch_template: "speccursor/{package}-{target}".to_string(),
+            message_template: "Bump {package} from {current} to {target}".to_string(),
+            conventional: false,
+            author_name: "speccursor".to_string(),
+            author_email: "speccursor@users.noreply.github.com".to_string(),
+            signing: None,
+        }
+    }
+}
+
+/// Branch and commit an upgrade was committed as.
+#[derive(Debug, Clone)]
+pub struct UpgradeCommit {
+    pub branch: String,
+    pub sha: String,
+    pub message: String,
+}
+
+fn commit_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Internal,
+    }
+}
+
+fn render(template: &str, request: &UpgradeRequest, versions: &ResolvedVersions) -> String {
+    template
+        .replace("{package}", &request.package_name)
+        .replace("{current}", &versions.current.to_string())
+        .replace("{target}", &versions.target.to_string())
+        .replace("{ecosystem}", &request.ecosystem)
+        .replace("{repository}", &request.repository)
+}
+
+/// The rendered branch template, with characters git does not allow in ref
+/// names (`@`, `:`, spaces, `..`) replaced so that scoped or
+/// `group:artifact` package names still give a valid branch.
+pub fn branch_name(
+    template: &str,
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+) -> Result<String, UpgradeError> {
+    let rendered: String = render(template, request, versions)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let branch = rendered
+        .split('/')
+        .map(|component| {
+            component
+                .replace("..", ".")
+                .trim_matches(['-', '.'])
+                .trim_end_matches(".lock")
+                .to_string()
+        })
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+        Ok(branch)
+    } else {
+        Err(UpgradeError {
+            message: format!("Branch template '{}' gives no valid branch name", template),
+            error_type: ErrorType::Validation,
+        })
+    }
+}
+
+/// The rendered message template, as a Conventional Commit when
+/// `config.conventional` is set.
+pub fn commit_message(
+    config: &CommitConfig,
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+    risk_assessment: &RiskAssessment,
+) -> String {
+    let message = render(&config.message_template, request, versions);
+    if !config.conventional {
+        return message;
+    }
+
+    let scope = if request.dependency_kind == Some(DependencyKind::Dev) {
+        "deps-dev"
+    } else {
+        "deps"
+    };
+    let breaking = if risk_assessment.breaking_changes {
+        "!"
+    } else {
+        ""
+    };
+    let mut chars = message.chars();
+    let subject: String = chars
+        .next()
+        .map(|first| first.to_lowercase().chain(chars).collect())
+        .unwrap_or_default();
+    let mut message = format!("chore({}){}: {}", scope, breaking, subject);
+    if risk_assessment.breaking_changes {
+        message = format!(
+            "{}\n\nBREAKING CHANGE: {} {} is not compatible with {}",
+            message.trim_end(),
+            request.package_name,
+            versions.target,
+            versions.current
+        );
+    }
+    message
+}
+
+/// Git trailers linking an upgrade commit to the job that made it, for
+/// audit tooling reading them with `git interpret-trailers --parse`.
+pub fn trailers(
+    job_id: &str,
+    request: &UpgradeRequest,
+    risk_assessment: &RiskAssessment,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("Speccursor-Job-Id", job_id.to_string()),
+        (
+            "Speccursor-Risk-Level",
+            format!("{:?}", risk_assessment.risk_level).to_lowercase(),
+        ),
+        ("Speccursor-Package", request.package_name.clone()),
+    ]
+}
+
+// `message` with `trailers` as its last paragraph
+fn append_trailers(message: &str, trailers: &[(&str, String)]) -> String {
+    let mut message = format!("{}\n", message.trim_end());
+    for (i, (key, value)) in trailers.iter().enumerate() {
+        let separator = if i == 0 { "\n" } else { "" };
+        // Trailer values are single lines
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        message.push_str(&format!("{}{}: {}\n", separator, key, value));
+    }
+    message
+}
+
+/// Index entry for `path` with `mode` (`0o100644` for files, `0o160000`
+/// for submodules) pointing at object `id`.
+pub(crate) fn index_entry(path: &str, mode: u32, id: Oid) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id,
+        flags: path.len().min(0xfff) as u16,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+/// Writes `changes` to the working tree of `checkout` and commits them on a
+/// new `branch` on top of the checked out commit, which becomes `HEAD`.
+/// Lockfiles still waiting for their refresh command are left out and
+/// submodule commits are staged as gitlinks.
+pub fn commit_changes(
+    checkout: &Checkout,
+    branch: &str,
+    message: &str,
+    config: &CommitConfig,
+    changes: &[Change],
+) -> Result<String, UpgradeError> {
+    let git_error = |e: git2::Error| commit_error(format!("Failed to commit to {}: {}", branch, e));
+
+    let repo = Repository::open(&checkout.path).map_err(git_error)?;
+    let base = repo
+        .revparse_single(&checkout.commit)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(git_error)?;
+    // Start from the full tree: a sparse working tree lacks most files
+    let mut index = repo.index().map_err(git_error)?;
+    index
+        .read_tree(&base.tree().map_err(git_error)?)
+        .map_err(git_error)?;
+
+    write_changes(&checkout.path, changes)?;
+    for change in changes
+        .iter()
+        .filter(|change| !change.metadata.contains_key("lockfile_refresh"))
+    {
+        let path = Path::new(&change.file_path);
+        if let Some(commit) = gitlink(change) {
+            let id = Oid::from_str(commit).map_err(git_error)?;
+            index
+                .add(&index_entry(&change.file_path, 0o160000, id))
+                .map_err(git_error)?;
+        } else if matches!(change.change_type, ChangeType::Delete) {
+            index.remove_path(path).map_err(git_error)?;
+        } else {
+            index.add_path(path).map_err(git_error)?;
+        }
+    }
+    index.write().map_err(git_error)?;
+
+    let tree = repo
+        .find_tree(index.write_tree().map_err(git_error)?)
+        .map_err(git_error)?;
+    let signature = Signature::now(&config.author_name, &config.author_email).map_err(git_error)?;
+    let reference = format!("refs/heads/{}", branch);
+    let commit = match &config.signing {
+        None => repo
+            .commit(
+                Some(&reference),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &[&base],
+            )
+            .map_err(git_error)?,
+        Some(signing) => {
+            let buffer = repo
+                .commit_create_buffer(&signature, &signature, message, &tree, &[&base])
+                .map_err(git_error)?;
+            let payload = buffer
+                .as_str()
+                .ok_or_else(|| commit_error("Commit message is not UTF-8".to_string()))?;
+            let gpgsig = sign_commit(signing, payload)?;
+            let commit = repo
+                .commit_signed(payload, &gpgsig, None)
+                .map_err(git_error)?;
+            repo.reference(&reference, commit, true, "speccursor: signed upgrade")
+                .map_err(git_error)?;
+            commit
+        }
+    };
+    repo.set_head(&reference).map_err(git_error)?;
+    Ok(commit.to_string())
+}
+
+impl UpgradeWorker {
+    /// Commits the generated changes to a new branch when commits are
+    /// enabled, which needs the repository to be checked out.
+    pub(crate) fn commit_upgrade(
+        &self,
+        checkout: Option<&Checkout>,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk_assessment: &RiskAssessment,
+        changes: &[Change],
+        warnings: &mut Vec<String>,
+    ) -> Result<Option<UpgradeCommit>, UpgradeError> {
+        let Some(config) = &self.config.commit else {
+            return Ok(None);
+        };
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        let Some(checkout) = checkout else {
+            warnings.push("Changes were not committed: no repository checkout".to_string());
+            return Ok(None);
+        };
+        if checkout.commit.is_empty() {
+            warnings.push(format!(
+                "Changes were not committed: {} is not a git repository",
+                checkout.path.display()
+            ));
+            return Ok(None);
+        }
+
+        let branch = branch_name(&config.branch_template, request, versions)?;
+        let message = append_trailers(
+            &commit_message(config, request, versions, risk_assessment),
+            &trailers(
+                request.job_id.as_deref().unwrap_or_default(),
+                request,
+                risk_assessment,
+            ),
+        );
+        let sha = commit_changes(checkout, &branch, &message, config, changes)?;
+        Ok(Some(UpgradeCommit {
+            branch,
+            sha,
+            message,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{tests::source_repository, CheckoutConfig};
+    use crate::version::{SemanticScheme, VersionJump, VersionScheme};
+    use crate::{RiskLevel, WorkerConfig};
+
+    fn versions(current: &str, target: &str) -> ResolvedVersions {
+        ResolvedVersions {
+            current: SemanticScheme.parse(current).unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        }
+    }
+
+    fn risk(breaking_changes: bool) -> RiskAssessment {
+        RiskAssessment {
+            risk_level: RiskLevel::Low,
+            breaking_changes,
+            version_jump: VersionJump::Major,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_branch_and_message_templates() {
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "@types/node".to_string(),
+            dependency_kind: Some(DependencyKind::Dev),
+            ..Default::default()
+        };
+        let versions = versions("18.0.0", "20.11.5");
+        let config = CommitConfig::default();
+
+        assert_eq!(
+            branch_name(&config.branch_template, &request, &versions).unwrap(),
+            "speccursor/types/node-20.11.5"
+        );
+        let maven = UpgradeRequest {
+            package_name: "org.slf4j:slf4j-api".to_string(),
+            ..request.clone()
+        };
+        assert_eq!(
+            branch_name("deps/{ecosystem}/{package}", &maven, &versions).unwrap(),
+            "deps/npm/org.slf4j-slf4j-api"
+        );
+        assert!(branch_name("{ecosystem}/..", &UpgradeRequest::default(), &versions).is_err());
+
+        assert_eq!(
+            commit_message(&config, &request, &versions, &risk(false)),
+            "Bump @types/node from 18.0.0 to 20.11.5"
+        );
+        let conventional = CommitConfig {
+            conventional: true,
+            ..config
+        };
+        assert_eq!(
+            commit_message(&conventional, &request, &versions, &risk(false)),
+            "chore(deps-dev): bump @types/node from 18.0.0 to 20.11.5"
+        );
+        assert_eq!(
+            commit_message(&conventional, &maven, &versions, &risk(true)),
+            "chore(deps-dev)!: bump org.slf4j:slf4j-api from 18.0.0 to 20.11.5\n\n\
+             BREAKING CHANGE: org.slf4j:slf4j-api 20.11.5 is not compatible with 18.0.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_committed_to_branch() {
+        let source = tempfile::tempdir().unwrap();
+        let base = source_repository(source.path());
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            local_roots: vec![source.path().to_path_buf()],
+            commit: Some(CommitConfig::default()),
+            ..WorkerConfig::default()
+        }));
+        let request = UpgradeRequest {
+            repository: "acme/app".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.20".to_string(),
+            target_version: "4.17.21".to_string(),
+            job_id: Some("job-42".to_string()),
+            local_path: Some(source.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert_eq!(
+            response.branch.as_deref(),
+            Some("speccursor/lodash-4.17.21")
+        );
+        let sha = response.commit_sha.unwrap();
+
+        let repo = Repository::open(source.path()).unwrap();
+        let head = repo.head().unwrap();
+        assert_eq!(head.name(), Some("refs/heads/speccursor/lodash-4.17.21"));
+        let commit = head.peel_to_commit().unwrap();
+        assert_eq!(commit.id().to_string(), sha);
+        assert_eq!(commit.parent_id(0).unwrap().to_string(), base);
+        assert_eq!(
+            commit.message(),
+            Some(
+                "Bump lodash from 4.17.20 to 4.17.21\n\n\
+                 Speccursor-Job-Id: job-42\n\
+                 Speccursor-Risk-Level: low\n\
+                 Speccursor-Package: lodash\n"
+            )
+        );
+
+        // Files outside a sparse checkout are kept in the commit
+        let checkouts = tempfile::tempdir().unwrap();
+        let sparse = crate::repo::checkout(
+            &source.path().to_string_lossy(),
+            &CheckoutConfig {
+                depth: 0,
+                reference: Some(base),
+                sparse: true,
+                directory: Some(checkouts.path().to_path_buf()),
+                ..CheckoutConfig::default()
+            },
+            &crate::credentials::GitCredentials::Default,
+        )
+        .unwrap();
+        let sha = commit_changes(
+            &sparse,
+            "speccursor/lodash-4.17.21",
+            "Bump lodash",
+            &CommitConfig::default(),
+            &response.changes,
+        )
+        .unwrap();
+        let repo = Repository::open(&sparse.path).unwrap();
+        let tree = repo
+            .find_commit(Oid::from_str(&sha).unwrap())
+            .unwrap()
+            .tree()
+            .unwrap();
+        assert!(tree.get_path(Path::new("apps/web/src/index.js")).is_ok());
+        let manifest = tree
+            .get_path(Path::new("package.json"))
+            .unwrap()
+            .to_object(&repo)
+            .unwrap();
+        let content = std::str::from_utf8(manifest.as_blob().unwrap().content()).unwrap();
+        assert!(content.contains("^4.17.21"));
+    }
+
+    #[tokio::test]
+    async fn test_submodule_committed_as_gitlink() {
+        let root = tempfile::tempdir().unwrap();
+        source_repository(root.path());
+        std::fs::write(
+            root.path().join(".gitmodules"),
+            "[submodule \"libfoo\"]\n\tpath = third_party/libfoo\n\turl = https://github.com/foo/libfoo.git\n",
+        )
+        .unwrap();
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            local_roots: vec![root.path().to_path_buf()],
+            commit: Some(CommitConfig::default()),
+            ..WorkerConfig::default()
+        }));
+        let commit = "9fceb02d0ae598e95dc970b74767f19372d61af8";
+        let request = UpgradeRequest {
+            repository: "acme/app".to_string(),
+            ecosystem: "git-submodule".to_string(),
+            package_name: "third_party/libfoo".to_string(),
+            current_version: "v1.2.0".to_string(),
+            target_version: "v1.3.0".to_string(),
+            metadata: std::collections::HashMap::from([(
+                crate::ecosystems::SUBMODULE_COMMIT_METADATA_KEY.to_string(),
+                serde_json::json!(commit),
+            )]),
+            local_path: Some(root.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        let repo = Repository::open(root.path()).unwrap();
+        let tree = repo
+            .find_commit(Oid::from_str(&response.commit_sha.unwrap()).unwrap())
+            .unwrap()
+            .tree()
+            .unwrap();
+        let entry = tree.get_path(Path::new("third_party/libfoo")).unwrap();
+        assert_eq!(entry.filemode(), 0o160000);
+        assert_eq!(entry.id().to_string(), commit);
+        assert!(!root.path().join("third_party/libfoo").exists());
+    }
+}
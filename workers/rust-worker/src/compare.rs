@@ -0,0 +1,141 @@
+use crate::version::{self, ParsedVersion, VersionJump};
+use crate::{ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionComparisonRequest {
+    pub ecosystem: String,
+    /// Needed to compute the release distance from registry metadata.
+    #[serde(default)]
+    pub package_name: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionComparison {
+    pub scheme: String,
+    pub ordering: VersionOrdering,
+    pub jump: VersionJump,
+    /// Number of published releases after `from` up to and including `to`.
+    pub distance: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionOrdering {
+    Less,
+    Equal,
+    Greater,
+}
+
+impl From<Ordering> for VersionOrdering {
+    fn from(ordering: Ordering) -> Self {
+        match ordering {
+            Ordering::Less => VersionOrdering::Less,
+            Ordering::Equal => VersionOrdering::Equal,
+            Ordering::Greater => VersionOrdering::Greater,
+        }
+    }
+}
+
+impl UpgradeWorker {
+    pub async fn compare_versions(
+        &self,
+        request: &VersionComparisonRequest,
+    ) -> Result<VersionComparison, UpgradeError> {
+        let scheme = version::detect_scheme(&request.ecosystem, &[&request.from, &request.to]);
+        let parse = |field: &str, raw: &str| {
+            scheme.parse(raw).map_err(|e| UpgradeError {
+                message: format!("Invalid {} version '{}': {}", field, raw, e),
+                error_type: ErrorType::Validation,
+            })
+        };
+        let from = parse("from", &request.from)?;
+        let to = parse("to", &request.to)?;
+
+        let distance = match &request.package_name {
+            Some(package_name) => {
+                let lookup = UpgradeRequest {
+                    ecosystem: request.ecosystem.clone(),
+                    package_name: package_name.clone(),
+                    ..Default::default()
+                };
+                if self.has_registry_for(&lookup) {
+                    let published = self.published_versions(&lookup).await?;
+                    Some(release_distance(&published, &from, &to))
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        Ok(VersionComparison {
+            scheme: scheme.name().to_string(),
+            ordering: from.cmp(&to).into(),
+            jump: VersionJump::classify(&from, &to),
+            distance,
+        })
+    }
+}
+
+fn release_distance(
+    published: &[ParsedVersion],
+    from: &ParsedVersion,
+    to: &ParsedVersion,
+) -> usize {
+    let (low, high) = if from <= to { (from, to) } else { (to, from) };
+    published
+        .iter()
+        .filter(|version| *version > low && *version <= high)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_compare_versions() {
+        let registry = StaticRegistry::new().with_releases(
+            "pip",
+            "django",
+            ["4.2", "4.2.1", "5.0a1", "5.0", "5.0.1"]
+                .iter()
+                .map(|v| ReleaseInfo::new(v))
+                .collect(),
+        );
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+
+        let comparison = worker
+            .compare_versions(&VersionComparisonRequest {
+                ecosystem: "pip".to_string(),
+                package_name: Some("django".to_string()),
+                from: "4.2".to_string(),
+                to: "5.0".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(comparison.scheme, "pep440");
+        assert_eq!(comparison.ordering, VersionOrdering::Less);
+        assert_eq!(comparison.jump, VersionJump::Major);
+        assert_eq!(comparison.distance, Some(3));
+
+        let without_package = worker
+            .compare_versions(&VersionComparisonRequest {
+                ecosystem: "npm".to_string(),
+                package_name: None,
+                from: "2.0.0".to_string(),
+                to: "1.9.0".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(without_package.ordering, VersionOrdering::Greater);
+        assert_eq!(without_package.jump, VersionJump::Downgrade);
+        assert_eq!(without_package.distance, None);
+    }
+}
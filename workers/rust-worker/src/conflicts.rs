@@ -0,0 +1,228 @@
+use crate::commit::index_entry;
+use crate::ecosystems::gitlink;
+use crate::repo::{self, Checkout};
+use crate::{Change, ChangeType, ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker};
+use git2::{Index, Oid, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A file whose edits no longer apply to the head of the default branch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub file_path: String,
+    pub reason: String,
+}
+
+// `base` with `changes` applied, built in memory
+fn edited_tree<'r>(
+    repo: &'r Repository,
+    base: &Tree,
+    changes: &[Change],
+) -> Result<Tree<'r>, git2::Error> {
+    let mut index = Index::new()?;
+    index.read_tree(base)?;
+    for change in changes
+        .iter()
+        .filter(|change| !change.metadata.contains_key("lockfile_refresh"))
+    {
+        let path = &change.file_path;
+        if let Some(commit) = gitlink(change) {
+            index.add(&index_entry(path, 0o160000, Oid::from_str(commit)?))?;
+        } else if matches!(change.change_type, ChangeType::Delete) {
+            index.remove_path(Path::new(path))?;
+        } else {
+            let blob = repo.blob(change.content.as_bytes())?;
+            index.add(&index_entry(path, 0o100644, blob))?;
+        }
+    }
+    repo.find_tree(index.write_tree_to(repo)?)
+}
+
+/// Merges the edits of `changes`, made against commit `base` of the
+/// repository at `path`, into commit `head` and returns the files that
+/// conflict.
+pub fn find_conflicts(
+    path: &Path,
+    base: &str,
+    head: &str,
+    changes: &[Change],
+) -> Result<Vec<Conflict>, git2::Error> {
+    if base == head {
+        return Ok(Vec::new());
+    }
+    let repo = Repository::open(path)?;
+    let base = repo.revparse_single(base)?.peel_to_commit()?.tree()?;
+    let head = repo.revparse_single(head)?.peel_to_commit()?.tree()?;
+    let edited = edited_tree(&repo, &base, changes)?;
+
+    let merged = repo.merge_trees(&base, &head, &edited, None)?;
+    let mut conflicts = Vec::new();
+    for conflict in merged.conflicts()? {
+        let conflict = conflict?;
+        let reason = match (&conflict.our, &conflict.their) {
+            (None, _) => "deleted on the default branch",
+            (_, None) => "changed on the default branch, but the upgrade deletes it",
+            _ => "changed on the default branch where the upgrade edits it",
+        };
+        let entry = [&conflict.their, &conflict.our, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .next();
+        if let Some(entry) = entry {
+            conflicts.push(Conflict {
+                file_path: String::from_utf8_lossy(&entry.path).to_string(),
+                reason: reason.to_string(),
+            });
+        }
+    }
+    Ok(conflicts)
+}
+
+impl UpgradeWorker {
+    /// Checks that the changes still apply to the head of the default
+    /// branch when the repository was checked out. A check that cannot run
+    /// is reported as a warning.
+    pub(crate) async fn check_conflicts(
+        &self,
+        checkout: Option<&Checkout>,
+        request: &UpgradeRequest,
+        changes: &[Change],
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<Conflict>, UpgradeError> {
+        let Some(checkout) = checkout.filter(|checkout| !checkout.commit.is_empty()) else {
+            return Ok(Vec::new());
+        };
+        if changes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let timeout = Duration::from_secs(self.config.max_execution_time);
+        let provider = self
+            .config
+            .scm
+            .as_ref()
+            .map(|scm| crate::scm::provider_for(scm, timeout));
+        let credentials = self
+            .git_credentials(&request.repository, provider.as_deref())
+            .await?;
+
+        let path: PathBuf = checkout.path.clone();
+        let base = checkout.commit.clone();
+        let edits = changes.to_vec();
+        let task = tokio::task::spawn_blocking(move || {
+            let Some(head) = repo::fetch_default_branch(&path, &credentials)? else {
+                return Ok(None);
+            };
+            let conflicts =
+                find_conflicts(&path, &base, &head, &edits).map_err(|e| UpgradeError {
+                    message: format!("Failed to merge into {}: {}", head, e),
+                    error_type: ErrorType::Internal,
+                })?;
+            Ok::<_, UpgradeError>(Some((head, conflicts)))
+        });
+        let checked = match tokio::time::timeout(timeout, task).await {
+            Ok(Ok(checked)) => checked,
+            Ok(Err(e)) => Err(UpgradeError {
+                message: format!("Conflict check task failed: {}", e),
+                error_type: ErrorType::Internal,
+            }),
+            Err(_) => Err(UpgradeError {
+                message: format!("timed out after {}s", timeout.as_secs()),
+                error_type: ErrorType::Network,
+            }),
+        };
+
+        match checked {
+            Ok(Some((head, conflicts))) => {
+                if !conflicts.is_empty() {
+                    let paths: Vec<&str> = conflicts
+                        .iter()
+                        .map(|conflict| conflict.file_path.as_str())
+                        .collect();
+                    warnings.push(format!(
+                        "The changes conflict with the default branch at {}: {}",
+                        &head[..head.len().min(12)],
+                        paths.join(", ")
+                    ));
+                }
+                Ok(conflicts)
+            }
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => {
+                warnings.push(format!("Conflict check skipped: {}", e.message));
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::tests::source_repository;
+    use crate::WorkerConfig;
+    use git2::Signature;
+
+    // Commits `content` to `path` on `main` of the repository at `root`
+    fn advance_main(root: &Path, path: &str, content: &str) {
+        let repo = Repository::open(root).unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        let mut index = Index::new().unwrap();
+        index.read_tree(&parent.tree().unwrap()).unwrap();
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        index.add(&index_entry(path, 0o100644, blob)).unwrap();
+        let tree = repo.find_tree(index.write_tree_to(&repo).unwrap()).unwrap();
+        let author = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &author, &author, "Drift", &tree, &[&parent])
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_with_default_branch() {
+        let source = tempfile::tempdir().unwrap();
+        source_repository(source.path());
+        // A workspace cloned from `source`, whose `main` then moves on
+        let root = tempfile::tempdir().unwrap();
+        let workspace = root.path().join("app");
+        Repository::clone(&source.path().to_string_lossy(), &workspace).unwrap();
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            local_roots: vec![root.path().to_path_buf()],
+            ..WorkerConfig::default()
+        }));
+        let request = UpgradeRequest {
+            repository: "acme/app".to_string(),
+            local_path: Some(workspace.to_string_lossy().to_string()),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.20".to_string(),
+            target_version: "4.17.21".to_string(),
+            ..Default::default()
+        };
+
+        // Drift elsewhere merges cleanly
+        advance_main(source.path(), "apps/web/src/index.js", "import 'lodash';");
+        let response = worker.process_upgrade(request.clone()).await.unwrap();
+        assert!(response.conflicts.is_empty());
+        assert!(response.warnings.is_empty());
+        // Undo the edit the upgrade made to the workspace
+        Repository::open(&workspace)
+            .unwrap()
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+
+        advance_main(
+            source.path(),
+            "package.json",
+            r#"{"dependencies": {"lodash": "^4.17.20", "react": "^18.2.0"}}"#,
+        );
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert_eq!(
+            response.conflicts,
+            [Conflict {
+                file_path: "package.json".to_string(),
+                reason: "changed on the default branch where the upgrade edits it".to_string(),
+            }]
+        );
+        assert!(response.warnings[0].contains("conflict with the default branch"));
+    }
+}
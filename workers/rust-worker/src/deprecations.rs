@@ -0,0 +1,304 @@
+use crate::release_notes::ReleaseNotes;
+use crate::version::ResolvedVersions;
+use crate::{rename, RiskAssessment, RiskLevel, UpgradeRequest, UpgradeWorker};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Where a deprecation was declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeprecationSource {
+    /// A registry marker: npm `deprecate`, a crates.io maintenance badge.
+    Registry,
+    /// A deprecation listed in the release notes.
+    ReleaseNotes,
+}
+
+/// A deprecation the target version, or a version before it, declares; in
+/// [`crate::RiskAssessment::deprecations`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deprecation {
+    pub source: DeprecationSource,
+    /// Version declaring it; `None` for markers on the whole package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub message: String,
+    /// Supplied source files that use the API deprecated, by the names the
+    /// release notes quote.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub used_in: Vec<String>,
+}
+
+fn code_span_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"`([^`]+)`").expect("valid pattern"))
+}
+
+/// API names a deprecation note quotes: the last path segment of each code
+/// span (`Client#query` and `db.query()` both name `query`). Names shorter
+/// than three characters match too much to be told apart.
+pub fn quoted_names(note: &str) -> Vec<&str> {
+    let mut names: Vec<&str> = code_span_pattern()
+        .captures_iter(note)
+        .filter_map(|caps| {
+            let span = caps.get(1)?.as_str();
+            let path = span.split(['(', '<', ' ']).next()?;
+            path.rsplit(['.', ':', '#', '/'])
+                .find(|segment| !segment.is_empty())
+        })
+        .filter(|name| {
+            name.len() >= 3
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        })
+        .collect();
+    names.dedup();
+    names
+}
+
+/// Files among `files` using any of the names a note quotes.
+fn files_using<'a>(note: &str, files: &[&'a str], request: &UpgradeRequest) -> Vec<&'a str> {
+    let names = quoted_names(note);
+    if names.is_empty() {
+        return Vec::new();
+    }
+    let pattern = format!(
+        r"\b(?:{})\b",
+        names
+            .iter()
+            .map(|name| regex::escape(name))
+            .collect::<Vec<_>>()
+            .join("|")
+    );
+    let pattern = Regex::new(&pattern).expect("valid pattern");
+    files
+        .iter()
+        .filter(|path| {
+            request
+                .manifests
+                .get(**path)
+                .is_some_and(|content| pattern.is_match(content))
+        })
+        .copied()
+        .collect()
+}
+
+impl UpgradeWorker {
+    /// Records the deprecations registries and release notes declare up to
+    /// the target. Registry markers always warn and raise the risk to
+    /// medium; release notes deprecations do unless the supplied files that
+    /// import the package show the deprecated API is not used.
+    pub(crate) async fn check_deprecations(
+        &self,
+        request: &UpgradeRequest,
+        target_request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        release_notes: Option<&ReleaseNotes>,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        let package = &target_request.package_name;
+        let mut deprecations = Vec::new();
+
+        if let Some(registry) = self
+            .registry
+            .as_ref()
+            .filter(|registry| registry.supports(&target_request.ecosystem))
+        {
+            match registry
+                .deprecation(&target_request.ecosystem, package)
+                .await
+            {
+                Ok(Some(message)) => deprecations.push(Deprecation {
+                    source: DeprecationSource::Registry,
+                    version: None,
+                    message,
+                    used_in: Vec::new(),
+                }),
+                Ok(None) => {}
+                Err(e) => warnings.push(format!("Deprecation check skipped: {}", e.message)),
+            }
+        }
+        let scheme = self.version_scheme(target_request);
+        if let Ok(Some(releases)) = self.registry_releases(target_request).await {
+            let target = releases.iter().find(|release| {
+                scheme
+                    .parse(&release.version)
+                    .is_ok_and(|version| version == versions.target)
+            });
+            if let Some(message) = target.and_then(|release| release.deprecated.clone()) {
+                deprecations.push(Deprecation {
+                    source: DeprecationSource::Registry,
+                    version: Some(versions.target.to_string()),
+                    message,
+                    used_in: Vec::new(),
+                });
+            }
+        }
+
+        let importing = rename::affected_imports(request);
+        let mut unused = 0;
+        for note in release_notes.iter().flat_map(|notes| &notes.deprecations) {
+            let used_in = files_using(&note.text, &importing, request);
+            if !importing.is_empty() && used_in.is_empty() {
+                unused += 1;
+            }
+            deprecations.push(Deprecation {
+                source: DeprecationSource::ReleaseNotes,
+                version: Some(note.version.clone()),
+                message: note.text.clone(),
+                used_in: used_in.into_iter().map(str::to_string).collect(),
+            });
+        }
+
+        let mut flagged = 0;
+        for deprecation in &deprecations {
+            let warning = match (deprecation.source, &deprecation.version) {
+                (DeprecationSource::Registry, None) => {
+                    format!("{} is deprecated: {}", package, deprecation.message)
+                }
+                (DeprecationSource::Registry, Some(version)) => {
+                    format!(
+                        "{} {} is deprecated: {}",
+                        package, version, deprecation.message
+                    )
+                }
+                (DeprecationSource::ReleaseNotes, _) if !deprecation.used_in.is_empty() => {
+                    format!(
+                        "{} {} deprecates {}, used in {}",
+                        package,
+                        deprecation.version.as_deref().unwrap_or_default(),
+                        deprecation.message,
+                        deprecation.used_in.join(", ")
+                    )
+                }
+                (DeprecationSource::ReleaseNotes, _) if importing.is_empty() => format!(
+                    "{} {} deprecates {}",
+                    package,
+                    deprecation.version.as_deref().unwrap_or_default(),
+                    deprecation.message
+                ),
+                (DeprecationSource::ReleaseNotes, _) => continue,
+            };
+            warnings.push(warning);
+            flagged += 1;
+        }
+
+        if flagged > 0 {
+            risk.risk_level = risk.risk_level.max(RiskLevel::Medium);
+            risk.explanations.push(format!(
+                "{} deprecation{} of {} up to {} may affect the repository",
+                flagged,
+                if flagged == 1 { "" } else { "s" },
+                package,
+                versions.target
+            ));
+        }
+        if unused > 0 {
+            risk.explanations.push(format!(
+                "{} deprecation{} of {} only concern{} APIs the repository does not use",
+                unused,
+                if unused == 1 { "" } else { "s" },
+                package,
+                if unused == 1 { "s" } else { "" }
+            ));
+        }
+        risk.deprecations = deprecations;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use crate::release_notes::{Changelog, Release, StaticReleaseNotes};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_quoted_names() {
+        assert_eq!(
+            quoted_names("`Client#query` in favour of `Client#execute`"),
+            ["query", "execute"]
+        );
+        assert_eq!(quoted_names("`db.connect(url)` and `fs::rm`"), ["connect"]);
+        assert!(quoted_names("Node 16 support").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_deprecations_in_risk_assessment() {
+        let changelog = Changelog {
+            source: "https://github.com/acme/db/releases".to_string(),
+            releases: vec![
+                Release {
+                    version: "1.5.0".to_string(),
+                    body: "### Deprecated\n- `Client#query`, use `Client#run`\n\
+                           - `Pool.drain()`\n"
+                        .to_string(),
+                },
+                Release {
+                    version: "1.6.0".to_string(),
+                    body: "- Faster queries\n".to_string(),
+                },
+            ],
+        };
+        let registry = StaticRegistry::new().with_releases(
+            "npm",
+            "acme-db",
+            vec![
+                ReleaseInfo::new("1.4.0"),
+                ReleaseInfo::new("1.5.0"),
+                ReleaseInfo {
+                    deprecated: Some("Use 1.6.1, which fixes pooling".to_string()),
+                    ..ReleaseInfo::new("1.6.0")
+                },
+            ],
+        );
+        let worker = UpgradeWorker::new(None)
+            .with_registry(Arc::new(registry))
+            .with_release_notes(Arc::new(
+                StaticReleaseNotes::new().with_changelog("npm", "acme-db", changelog),
+            ));
+        let request = |target: &str| UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "acme-db".to_string(),
+            current_version: "1.4.0".to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([
+                (
+                    "package.json".to_string(),
+                    r#"{"dependencies": {"acme-db": "1.4.0"}}"#.to_string(),
+                ),
+                (
+                    "src/db.ts".to_string(),
+                    "import { Client } from 'acme-db';\nawait client.query(sql);\n".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request("1.5.0")).await.unwrap();
+        let risk = response.risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::Medium);
+        assert_eq!(risk.deprecations.len(), 2);
+        assert_eq!(risk.deprecations[0].used_in, ["src/db.ts"]);
+        assert!(risk.deprecations[1].used_in.is_empty());
+        assert_eq!(
+            response.warnings,
+            ["acme-db 1.5.0 deprecates `Client#query`, use `Client#run`, used in src/db.ts"]
+        );
+        assert!(risk.explanations.contains(
+            &"1 deprecation of acme-db only concerns APIs the repository does not use".to_string()
+        ));
+
+        // A deprecated target version is flagged, not refused
+        let response = worker.process_upgrade(request("1.6.0")).await.unwrap();
+        assert_eq!(response.risk_assessment.deprecations.len(), 3);
+        assert!(response
+            .warnings
+            .contains(&"acme-db 1.6.0 is deprecated: Use 1.6.1, which fixes pooling".to_string()));
+    }
+}
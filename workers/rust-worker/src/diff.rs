@@ -0,0 +1,226 @@
+use crate::{Change, ChangeFormat, ChangeType, UpgradeRequest};
+use serde_json::{json, Value};
+use similar::TextDiff;
+
+const CONTEXT_LINES: usize = 3;
+
+/// Unified diff turning `original` into `updated`, with `a/`/`b/` path
+/// prefixes so it applies with `git apply` or `patch -p1`. A missing side
+/// is written as `/dev/null`.
+pub fn unified_diff(path: &str, original: Option<&str>, updated: Option<&str>) -> String {
+    let old_header = original.map_or("/dev/null".to_string(), |_| format!("a/{}", path));
+    let new_header = updated.map_or("/dev/null".to_string(), |_| format!("b/{}", path));
+    TextDiff::from_lines(original.unwrap_or_default(), updated.unwrap_or_default())
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&old_header, &new_header)
+        .to_string()
+}
+
+/// RFC 6902 operations turning `original` into `updated`. Arrays that
+/// change length are replaced whole.
+pub fn json_patch(original: &Value, updated: &Value) -> Vec<Value> {
+    let mut operations = Vec::new();
+    diff_values("", original, updated, &mut operations);
+    operations
+}
+
+fn diff_values(pointer: &str, original: &Value, updated: &Value, operations: &mut Vec<Value>) {
+    match (original, updated) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, value) in before {
+                let path = format!("{}/{}", pointer, escape(key));
+                match after.get(key) {
+                    Some(new) => diff_values(&path, value, new, operations),
+                    None => operations.push(json!({"op": "remove", "path": path})),
+                }
+            }
+            for (key, value) in after.iter().filter(|(key, _)| !before.contains_key(*key)) {
+                let path = format!("{}/{}", pointer, escape(key));
+                operations.push(json!({"op": "add", "path": path, "value": value}));
+            }
+        }
+        (Value::Array(before), Value::Array(after)) if before.len() == after.len() => {
+            for (i, (value, new)) in before.iter().zip(after).enumerate() {
+                diff_values(&format!("{}/{}", pointer, i), value, new, operations);
+            }
+        }
+        _ if original != updated => {
+            operations.push(json!({"op": "replace", "path": pointer, "value": updated}));
+        }
+        _ => {}
+    }
+}
+
+// JSON Pointer escaping (RFC 6901)
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Parses JSON, TOML and YAML manifests, chosen by file name, into a
+/// common tree. Other files and unparseable content yield `None`.
+fn parse_structured(path: &str, content: &str) -> Option<Value> {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    match file_name
+        .rsplit_once('.')
+        .map_or("", |(_, extension)| extension)
+    {
+        _ if matches!(file_name, "Pipfile" | "Cargo.lock" | "poetry.lock") => {
+            toml::from_str(content).ok()
+        }
+        "json" | "lock" => serde_json::from_str(content).ok(),
+        "toml" => toml::from_str(content).ok(),
+        "yaml" | "yml" => serde_yaml::from_str(content).ok(),
+        _ => None,
+    }
+}
+
+/// Rewrites change contents into the request's `change_format`, diffing
+/// against the supplied manifests. Lockfiles still awaiting a refresh have
+/// no content yet and are left as they are.
+pub fn apply_change_format(request: &UpgradeRequest, changes: &mut [Change]) {
+    let changes = changes
+        .iter_mut()
+        .filter(|change| !change.metadata.contains_key("lockfile_refresh"));
+    match request.change_format {
+        ChangeFormat::Content | ChangeFormat::FormatPatch => {}
+        ChangeFormat::UnifiedDiff => changes.for_each(|change| to_unified_diff(request, change)),
+        ChangeFormat::JsonPatch => changes.for_each(|change| add_json_patch(request, change)),
+    }
+}
+
+fn add_json_patch(request: &UpgradeRequest, change: &mut Change) {
+    if matches!(change.change_type, ChangeType::Add | ChangeType::Delete) {
+        return;
+    }
+    let Some(original) = request.manifests.get(&change.file_path) else {
+        return;
+    };
+    let (Some(original), Some(updated)) = (
+        parse_structured(&change.file_path, original),
+        parse_structured(&change.file_path, &change.content),
+    ) else {
+        return;
+    };
+    change.metadata.insert(
+        "json_patch".to_string(),
+        Value::Array(json_patch(&original, &updated)),
+    );
+}
+
+fn to_unified_diff(request: &UpgradeRequest, change: &mut Change) {
+    let original = match change.change_type {
+        ChangeType::Add => None,
+        _ => request.manifests.get(&change.file_path).map(String::as_str),
+    };
+    let updated = match change.change_type {
+        ChangeType::Delete => None,
+        _ => Some(change.content.as_str()),
+    };
+    change.content = unified_diff(&change.file_path, original, updated);
+    change
+        .metadata
+        .insert("format".to_string(), json!("unified_diff"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let original = "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nserde = \"1.0.100\"\ntokio = \"1.30\"\n";
+        let updated = original.replace("1.0.100", "1.0.195");
+        let request = UpgradeRequest {
+            manifests: HashMap::from([("Cargo.toml".to_string(), original.to_string())]),
+            change_format: ChangeFormat::UnifiedDiff,
+            ..Default::default()
+        };
+        let mut changes = vec![
+            Change {
+                file_path: "Cargo.toml".to_string(),
+                change_type: ChangeType::Modify,
+                content: updated,
+                metadata: HashMap::new(),
+            },
+            Change {
+                file_path: "Cargo.lock".to_string(),
+                change_type: ChangeType::Modify,
+                content: String::new(),
+                metadata: HashMap::from([(
+                    "lockfile_refresh".to_string(),
+                    serde_json::json!(true),
+                )]),
+            },
+        ];
+
+        apply_change_format(&request, &mut changes);
+        assert_eq!(
+            changes[0].content,
+            "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -4,5 +4,5 @@\n edition = \"2021\"\n \n [dependencies]\n-serde = \"1.0.100\"\n+serde = \"1.0.195\"\n tokio = \"1.30\"\n"
+        );
+        assert_eq!(changes[0].metadata["format"], "unified_diff");
+        assert!(changes[1].content.is_empty());
+
+        let added = unified_diff(".eslintrc.json", None, Some("{}\n"));
+        assert!(added.starts_with("--- /dev/null\n+++ b/.eslintrc.json\n@@ -0,0 +1 @@\n+{}\n"));
+    }
+
+    #[test]
+    fn test_json_patch_operations() {
+        let original =
+            r#"{"dependencies": {"react": "^18.2.0", "a/b": "1.0.0"}, "files": ["dist"]}"#;
+        let updated = r#"{"dependencies": {"react": "^18.3.1", "zod": "^3.22.0"}, "files": ["dist", "types"]}"#;
+        assert_eq!(
+            json_patch(
+                &serde_json::from_str(original).unwrap(),
+                &serde_json::from_str(updated).unwrap()
+            ),
+            vec![
+                json!({"op": "remove", "path": "/dependencies/a~1b"}),
+                json!({"op": "replace", "path": "/dependencies/react", "value": "^18.3.1"}),
+                json!({"op": "add", "path": "/dependencies/zod", "value": "^3.22.0"}),
+                json!({"op": "replace", "path": "/files", "value": ["dist", "types"]}),
+            ]
+        );
+
+        let manifests = [
+            (
+                "Cargo.toml",
+                "[dependencies]\nserde = { version = \"1.0.100\", features = [\"derive\"] }\n",
+            ),
+            ("pnpm-workspace.yaml", "catalog:\n  react: ^18.2.0\n"),
+        ];
+        let request = UpgradeRequest {
+            manifests: manifests
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect(),
+            change_format: ChangeFormat::JsonPatch,
+            ..Default::default()
+        };
+        let mut changes: Vec<Change> = manifests
+            .iter()
+            .map(|(path, content)| Change {
+                file_path: path.to_string(),
+                change_type: ChangeType::Modify,
+                content: content
+                    .replace("1.0.100", "1.0.195")
+                    .replace("18.2.0", "18.3.1"),
+                metadata: HashMap::new(),
+            })
+            .collect();
+        apply_change_format(&request, &mut changes);
+
+        assert!(changes[0].content.contains("1.0.195"));
+        assert_eq!(
+            changes[0].metadata["json_patch"],
+            json!([{"op": "replace", "path": "/dependencies/serde/version", "value": "1.0.195"}])
+        );
+        assert_eq!(
+            changes[1].metadata["json_patch"],
+            json!([{"op": "replace", "path": "/catalog/react", "value": "^18.3.1"}])
+        );
+    }
+}
@@ -0,0 +1,424 @@
+mod cargo;
+mod cocoapods;
+mod composer;
+mod conan;
+mod conda;
+mod deno;
+mod docker;
+mod git_submodule;
+mod github_actions;
+mod go;
+mod gradle;
+mod helm;
+mod hex;
+mod lean;
+mod maven;
+mod npm;
+mod nuget;
+mod os_packages;
+mod pip;
+mod pipenv;
+mod pnpm;
+mod pubspec;
+mod rubygems;
+mod swiftpm;
+mod terraform;
+mod vcpkg;
+mod yaml;
+
+pub use cargo::Cargo;
+pub use cocoapods::CocoaPods;
+pub use composer::Composer;
+pub use conan::Conan;
+pub use conda::Conda;
+pub use deno::Deno;
+pub use docker::{base_images, Docker};
+pub use git_submodule::{
+    gitlink, GitSubmodule, GITLINK_METADATA_KEY, SUBMODULE_COMMIT_METADATA_KEY,
+    SUBMODULE_URL_METADATA_KEY,
+};
+pub use github_actions::{
+    GitHubActions, GITHUB_ACTIONS_PIN_METADATA_KEY, GITHUB_ACTIONS_SHA_METADATA_KEY,
+};
+pub use go::Go;
+pub use gradle::Gradle;
+pub use helm::Helm;
+pub use hex::Hex;
+pub use lean::Lean;
+pub use maven::Maven;
+pub use npm::{Npm, NPM_EXCLUDE_WORKSPACES_METADATA_KEY};
+pub use nuget::NuGet;
+pub use os_packages::OsPackages;
+pub use pip::Pip;
+pub use pipenv::Pipenv;
+pub use pubspec::Pub;
+pub use rubygems::RubyGems;
+pub use swiftpm::SwiftPm;
+pub use terraform::Terraform;
+pub use vcpkg::{Vcpkg, VCPKG_BASELINE_METADATA_KEY};
+
+use crate::version::ResolvedVersions;
+use crate::{
+    Change, ChangeType, DependencyKind, ErrorType, RiskAssessment, UpgradeError, UpgradeRequest,
+};
+use std::collections::HashMap;
+use std::ops::Range;
+use toml_edit::{DocumentMut, Item, Value};
+
+/// Manifest handling for one package ecosystem: locating the files that
+/// declare a package and rewriting them for the target version.
+pub trait Ecosystem: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Multiplier applied to the base compatibility score.
+    fn compatibility(&self, request: &UpgradeRequest) -> f64;
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError>;
+
+    /// Whether a supplied manifest declares the package, which tells an
+    /// upgrade already applied from one aimed at a package the repository
+    /// does not use when no change is generated.
+    fn declares(&self, request: &UpgradeRequest) -> bool {
+        request.manifests.values().any(|content| {
+            request
+                .package_name
+                .split(':')
+                .all(|part| mentions(content, part))
+        })
+    }
+
+    /// Adjusts the generic risk assessment with ecosystem-specific rules.
+    fn assess_risk(
+        &self,
+        _request: &UpgradeRequest,
+        _versions: &ResolvedVersions,
+        _assessment: &mut RiskAssessment,
+    ) {
+    }
+}
+
+/// Returns the manifest handler for an ecosystem, if one is registered.
+pub fn ecosystem_for(name: &str) -> Option<&'static dyn Ecosystem> {
+    match name {
+        "cargo" => Some(&Cargo),
+        "cocoapods" => Some(&CocoaPods),
+        "composer" => Some(&Composer),
+        "conan" => Some(&Conan),
+        "conda" => Some(&Conda),
+        "deno" => Some(&Deno),
+        "docker" => Some(&Docker),
+        "git-submodule" => Some(&GitSubmodule),
+        "github-actions" => Some(&GitHubActions),
+        "go" => Some(&Go),
+        "gradle" => Some(&Gradle),
+        "helm" => Some(&Helm),
+        "hex" => Some(&Hex),
+        "lean" => Some(&Lean),
+        "maven" => Some(&Maven),
+        "npm" => Some(&Npm),
+        "nuget" => Some(&NuGet),
+        "os-packages" => Some(&OsPackages),
+        "pip" => Some(&Pip),
+        "pipenv" => Some(&Pipenv),
+        "pub" => Some(&Pub),
+        "rubygems" => Some(&RubyGems),
+        "swiftpm" => Some(&SwiftPm),
+        "terraform" => Some(&Terraform),
+        "vcpkg" => Some(&Vcpkg),
+        _ => None,
+    }
+}
+
+/// Repository files supplied with the request whose file name is one of
+/// `names`, in path order.
+fn manifests_named<'a>(request: &'a UpgradeRequest, names: &[&str]) -> Vec<(&'a str, &'a str)> {
+    manifests_matching(request, |file_name| names.contains(&file_name))
+}
+
+/// Repository files supplied with the request whose file name satisfies
+/// `predicate`, in path order.
+fn manifests_matching(
+    request: &UpgradeRequest,
+    predicate: impl Fn(&str) -> bool,
+) -> Vec<(&str, &str)> {
+    let mut files: Vec<(&str, &str)> = request
+        .manifests
+        .iter()
+        .filter(|(path, _)| predicate(path.rsplit('/').next().unwrap_or(path)))
+        .map(|(path, content)| (path.as_str(), content.as_str()))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Whether `name` appears in `content` as a whole name rather than part of
+/// a longer one (`lodash` in `lodash-es`), ignoring case.
+fn mentions(content: &str, name: &str) -> bool {
+    let content = content.to_lowercase();
+    let name = name.to_lowercase();
+    let part_of_name =
+        |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || "-_.".contains(c));
+    !name.is_empty()
+        && content.match_indices(&name).any(|(start, _)| {
+            !part_of_name(content[..start].chars().next_back())
+                && !part_of_name(content[start + name.len()..].chars().next())
+        })
+}
+
+/// Splits JVM `group:artifact` coordinates.
+fn coordinates<'a>(
+    ecosystem: &str,
+    package_name: &'a str,
+) -> Result<(&'a str, &'a str), UpgradeError> {
+    match package_name.split_once(':') {
+        Some((group, artifact)) if !group.is_empty() && !artifact.is_empty() => {
+            Ok((group, artifact))
+        }
+        _ => Err(UpgradeError {
+            message: format!(
+                "{} packages must be named 'group:artifact', got '{}'",
+                ecosystem, package_name
+            ),
+            error_type: ErrorType::Validation,
+        }),
+    }
+}
+
+// Byte range between the braces of a top-level `"section": { ... }` object.
+fn object_body(content: &str, section: &str) -> Option<Range<usize>> {
+    let key = format!("\"{}\"", section);
+    let mut search_from = 0;
+    while let Some(found) = content[search_from..].find(&key) {
+        let after_key = search_from + found + key.len();
+        let rest = content[after_key..].trim_start();
+        if let Some(rest) = rest.strip_prefix(':') {
+            let rest = rest.trim_start();
+            if rest.starts_with('{') {
+                let start = content.len() - rest.len() + 1;
+                return Some(start..start + closing_brace(&content[start..])?);
+            }
+        }
+        search_from = after_key;
+    }
+    None
+}
+
+// Offset of the `}` closing an object whose body starts `text`, skipping
+// nested objects and braces inside strings.
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string && depth == 0 => return Some(i),
+            '}' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Matches a relative path against a workspace glob (`packages/*`,
+/// `crates/**`): `*` and `?` within one component, `**` across any number.
+pub(crate) fn glob_match(pattern: &str, path: &str) -> bool {
+    fn components(pattern: &[&str], path: &[&str]) -> bool {
+        match (pattern.first(), path.first()) {
+            (None, None) => true,
+            (Some(&"**"), _) => {
+                components(&pattern[1..], path)
+                    || (!path.is_empty() && components(pattern, &path[1..]))
+            }
+            (Some(expected), Some(actual)) => {
+                wildcard(expected.as_bytes(), actual.as_bytes())
+                    && components(&pattern[1..], &path[1..])
+            }
+            _ => false,
+        }
+    }
+    fn wildcard(pattern: &[u8], text: &[u8]) -> bool {
+        match (pattern.first(), text.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => {
+                wildcard(&pattern[1..], text) || (!text.is_empty() && wildcard(pattern, &text[1..]))
+            }
+            (Some(b'?'), Some(_)) => wildcard(&pattern[1..], &text[1..]),
+            (Some(expected), Some(actual)) => {
+                expected == actual && wildcard(&pattern[1..], &text[1..])
+            }
+            _ => false,
+        }
+    }
+
+    fn split(value: &str) -> Vec<&str> {
+        value
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect()
+    }
+
+    components(&split(pattern), &split(path))
+}
+
+/// Rejects a dependency kind the ecosystem has no manifest section for.
+fn unsupported_kind(ecosystem: &str, kind: Option<DependencyKind>) -> UpgradeError {
+    UpgradeError {
+        message: format!(
+            "{} manifests have no section for {:?} dependencies",
+            ecosystem,
+            kind.unwrap_or(DependencyKind::Normal)
+        ),
+        error_type: ErrorType::Validation,
+    }
+}
+
+fn parse_toml(path: &str, content: &str) -> Result<DocumentMut, UpgradeError> {
+    content.parse::<DocumentMut>().map_err(|e| UpgradeError {
+        message: format!("Failed to parse {}: {}", path, e),
+        error_type: ErrorType::Validation,
+    })
+}
+
+// Replaces a string value while keeping its surrounding whitespace and comments.
+fn set_string(item: &mut Item, new: &str) {
+    if let Some(value) = item.as_value_mut() {
+        set_value(value, new);
+    }
+}
+
+fn set_value(value: &mut Value, new: &str) {
+    let decor = value.decor().clone();
+    *value = Value::from(new);
+    *value.decor_mut() = decor;
+}
+
+/// Manifests are edited, never invented: an upgrade without the file
+/// declaring the package fails validation.
+fn manifest_required(file: &str) -> UpgradeError {
+    UpgradeError {
+        message: format!(
+            "Manifest content required: supply {} in `manifests` or enable checkouts",
+            file
+        ),
+        error_type: ErrorType::Validation,
+    }
+}
+
+fn modified(file_path: &str, content: String) -> Change {
+    Change {
+        file_path: file_path.to_string(),
+        change_type: ChangeType::Modify,
+        content,
+        metadata: HashMap::new(),
+    }
+}
+
+/// A lockfile that has to be regenerated by the package manager; its new
+/// content is unknown until `steps` run. Each step is an argument vector
+/// run without a shell, so names in it stay single arguments.
+pub(crate) fn lockfile_refresh(file_path: &str, steps: &[&[&str]]) -> Change {
+    let command = steps
+        .iter()
+        .map(|argv| argv.join(" "))
+        .collect::<Vec<_>>()
+        .join(" && ");
+    let mut change = modified(file_path, String::new());
+    change
+        .metadata
+        .insert("lockfile_refresh".to_string(), serde_json::json!(true));
+    change
+        .metadata
+        .insert("command".to_string(), serde_json::json!(command));
+    change
+        .metadata
+        .insert("argv".to_string(), serde_json::json!(steps));
+    change
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentions() {
+        assert!(mentions(r#"{"dependencies": {"lodash": "^4"}}"#, "lodash"));
+        assert!(!mentions(
+            r#"{"dependencies": {"lodash-es": "^4"}}"#,
+            "lodash"
+        ));
+        assert!(mentions("Django==4.2\n", "django"));
+        assert!(!mentions("source = \"aws/vpc-endpoints\"", "aws/vpc"));
+    }
+
+    #[test]
+    fn test_manifests_are_never_invented() {
+        for name in [
+            "cargo",
+            "cocoapods",
+            "composer",
+            "conan",
+            "conda",
+            "deno",
+            "docker",
+            "git-submodule",
+            "github-actions",
+            "go",
+            "gradle",
+            "helm",
+            "hex",
+            "lean",
+            "maven",
+            "npm",
+            "nuget",
+            "os-packages",
+            "pip",
+            "pipenv",
+            "pub",
+            "rubygems",
+            "swiftpm",
+            "terraform",
+            "vcpkg",
+        ] {
+            let scheme = crate::version::scheme_for(name);
+            let request = UpgradeRequest {
+                ecosystem: name.to_string(),
+                package_name: "org.example:example".to_string(),
+                current_version: "1.0.0".to_string(),
+                target_version: "2.0.0".to_string(),
+                ..Default::default()
+            };
+            let versions = ResolvedVersions {
+                current: scheme.parse("1.0.0").unwrap(),
+                target: scheme.parse("2.0.0").unwrap(),
+            };
+            let err = ecosystem_for(name)
+                .unwrap()
+                .generate_changes(&request, &versions)
+                .unwrap_err();
+            assert!(matches!(err.error_type, ErrorType::Validation), "{}", name);
+            assert!(
+                err.message.starts_with("Manifest content required"),
+                "{}: {}",
+                name,
+                err.message
+            );
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("crates/*", "crates/core"));
+        assert!(!glob_match("crates/*", "crates/core/nested"));
+        assert!(glob_match("crates/**", "crates/core/nested"));
+        assert!(glob_match("plugins/plugin-?", "plugins/plugin-a"));
+        assert!(!glob_match("tools/gen", "tools"));
+    }
+}
@@ -0,0 +1,765 @@
+use super::{
+    glob_match, lockfile_refresh, manifest_required, manifests_named, modified, parse_toml,
+    set_string, unsupported_kind, Ecosystem,
+};
+use crate::rewrite::{cargo_tables, rewrite_requirement};
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use std::collections::BTreeMap;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Rust crates: dependency entries in every supplied `Cargo.toml`, edited
+/// with `toml_edit` so formatting, comments, features and other keys are
+/// kept. Covers target-specific tables, `[workspace.dependencies]` and
+/// `[patch]` overrides; members inheriting with `workspace = true` follow
+/// their workspace root. Git dependencies move with their version tag, while
+/// path, branch and rev pins are reported as needing manual intervention.
+pub struct Cargo;
+
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+impl Ecosystem for Cargo {
+    fn name(&self) -> &'static str {
+        "cargo"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.9
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let tables = cargo_tables(request.dependency_kind);
+        if tables.is_empty() {
+            return Err(unsupported_kind("cargo", request.dependency_kind));
+        }
+
+        let manifests = manifests_named(request, &["Cargo.toml"]);
+        if manifests.is_empty() {
+            return Err(manifest_required("Cargo.toml"));
+        }
+
+        let documents = manifests
+            .into_iter()
+            .map(|(path, content)| Ok((path, content, parse_toml(path, content)?)))
+            .collect::<Result<Vec<_>, UpgradeError>>()?;
+        let workspaces: Vec<Workspace> = documents
+            .iter()
+            .filter_map(|(path, _, doc)| Workspace::declared_in(path, doc))
+            .collect();
+
+        // Member manifests declaring the package, keyed by workspace root
+        let mut members: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        let mut changes = Vec::new();
+        let mut blockers = Vec::new();
+        for (path, content, mut doc) in documents {
+            let root = workspace_for(&workspaces, path).map(|workspace| workspace.manifest);
+            if let Some(root) = root.filter(|root| *root != path) {
+                if usage(&doc, &request.package_name) != Usage::None {
+                    members.entry(root).or_default().push(path);
+                }
+            }
+
+            let edits = match &request.replacement_package {
+                Some(replacement) => {
+                    let mut edits = ManifestEdits::default();
+                    for_each_dependency_table(&mut doc, tables, &mut |dependencies, section| {
+                        rename_dependencies(
+                            dependencies,
+                            section,
+                            &request.package_name,
+                            replacement,
+                            &versions.target,
+                            &mut edits,
+                        )
+                    });
+                    edits
+                }
+                None => update_manifest(&mut doc, tables, &request.package_name, &versions.target),
+            };
+            blockers.extend(
+                edits
+                    .blockers
+                    .into_iter()
+                    .map(|blocker| format!("{}: {}", path, blocker)),
+            );
+            let updated = doc.to_string();
+            if updated != content {
+                let mut change = modified(path, updated);
+                change
+                    .metadata
+                    .insert("locations".to_string(), serde_json::json!(edits.locations));
+                if let Some(root) = root.filter(|root| *root != path) {
+                    change
+                        .metadata
+                        .insert("workspace_root".to_string(), serde_json::json!(root));
+                }
+                changes.push(change);
+            }
+        }
+        if !blockers.is_empty() {
+            return Err(UpgradeError {
+                message: format!(
+                    "Manual intervention required to upgrade {}: {}",
+                    request.package_name,
+                    blockers.join("; ")
+                ),
+                error_type: ErrorType::Unsupported,
+            });
+        }
+
+        for change in &mut changes {
+            if let Some(members) = members.get(change.file_path.as_str()) {
+                change
+                    .metadata
+                    .insert("workspace_members".to_string(), serde_json::json!(members));
+            }
+        }
+
+        if !changes.is_empty() {
+            // The replacement is not in the lockfile yet, so let Cargo add it
+            let target = versions.target.to_string();
+            let argv: &[&str] = match &request.replacement_package {
+                Some(_) => &["cargo", "update", "--workspace"],
+                None => &[
+                    "cargo",
+                    "update",
+                    "-p",
+                    &request.package_name,
+                    "--precise",
+                    &target,
+                ],
+            };
+            for (path, _) in manifests_named(request, &["Cargo.lock"]) {
+                changes.push(lockfile_refresh(path, &[argv]));
+            }
+        }
+        Ok(changes)
+    }
+
+    fn declares(&self, request: &UpgradeRequest) -> bool {
+        manifests_named(request, &["Cargo.toml"])
+            .into_iter()
+            .filter_map(|(path, content)| parse_toml(path, content).ok())
+            .any(|doc| usage(&doc, &request.package_name) != Usage::None)
+    }
+
+    /// Members inheriting the package from a workspace root that was not
+    /// supplied keep resolving the root's version, which is not upgraded.
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        _versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        let documents: Vec<(&str, DocumentMut)> = manifests_named(request, &["Cargo.toml"])
+            .into_iter()
+            .filter_map(|(path, content)| Some((path, content.parse().ok()?)))
+            .collect();
+        let workspaces: Vec<Workspace> = documents
+            .iter()
+            .filter_map(|(path, doc)| Workspace::declared_in(path, doc))
+            .collect();
+
+        for (path, doc) in &documents {
+            if usage(doc, &request.package_name) == Usage::Inherited
+                && workspace_for(&workspaces, path).is_none()
+            {
+                assessment.risk_level = assessment.risk_level.max(RiskLevel::Medium);
+                assessment.explanations.push(format!(
+                    "{} inherits {} from a workspace root that was not supplied; its version is not upgraded",
+                    path, request.package_name
+                ));
+            }
+        }
+    }
+}
+
+/// A `[workspace]` declared in a root manifest.
+struct Workspace<'a> {
+    manifest: &'a str,
+    directory: &'a str,
+    members: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl<'a> Workspace<'a> {
+    fn declared_in(manifest: &'a str, doc: &DocumentMut) -> Option<Self> {
+        let workspace = doc.get("workspace")?;
+        let patterns = |key: &str| -> Vec<String> {
+            workspace
+                .get(key)
+                .and_then(Item::as_array)
+                .map(|patterns| {
+                    patterns
+                        .iter()
+                        .filter_map(|pattern| pattern.as_str())
+                        .map(|pattern| pattern.trim_start_matches("./").trim_end_matches('/'))
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Some(Self {
+            manifest,
+            directory: manifest.trim_end_matches("Cargo.toml"),
+            members: patterns("members"),
+            exclude: patterns("exclude"),
+        })
+    }
+
+    fn contains(&self, manifest: &str) -> bool {
+        if manifest == self.manifest {
+            return true;
+        }
+        let Some(relative) = manifest
+            .trim_end_matches("Cargo.toml")
+            .strip_prefix(self.directory)
+        else {
+            return false;
+        };
+        let relative = relative.trim_end_matches('/');
+        let matches = |pattern: &String| glob_match(pattern, relative);
+        self.members.iter().any(matches) && !self.exclude.iter().any(matches)
+    }
+}
+
+// The innermost supplied workspace listing `manifest`, as Cargo resolves it.
+fn workspace_for<'w, 'a>(
+    workspaces: &'w [Workspace<'a>],
+    manifest: &str,
+) -> Option<&'w Workspace<'a>> {
+    workspaces
+        .iter()
+        .filter(|workspace| workspace.contains(manifest))
+        .max_by_key(|workspace| workspace.directory.len())
+}
+
+#[derive(Debug, PartialEq)]
+enum Usage {
+    None,
+    Declared,
+    Inherited,
+}
+
+// How a manifest depends on `package`: not at all, with its own requirement
+// or only through `workspace = true` entries.
+fn usage(doc: &DocumentMut, package: &str) -> Usage {
+    let mut tables: Vec<&dyn TableLike> = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|table| doc.get(table)?.as_table_like())
+        .collect();
+    if let Some(targets) = doc.get("target").and_then(Item::as_table_like) {
+        for (_, platform) in targets.iter() {
+            tables.extend(
+                DEPENDENCY_TABLES
+                    .iter()
+                    .filter_map(|table| platform.get(table)?.as_table_like()),
+            );
+        }
+    }
+    if let Some(dependencies) = doc
+        .get("workspace")
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(Item::as_table_like)
+    {
+        tables.push(dependencies);
+    }
+
+    let mut found = Usage::None;
+    for (key, entry) in tables.iter().flat_map(|table| table.iter()) {
+        let renamed_from = entry.get("package").and_then(Item::as_str);
+        if renamed_from.unwrap_or(key) != package {
+            continue;
+        }
+        if entry.get("workspace").and_then(Item::as_bool) != Some(true) {
+            return Usage::Declared;
+        }
+        found = Usage::Inherited;
+    }
+    found
+}
+
+// Calls `edit` with each dependency table among `tables`, including
+// target-specific ones, and `[workspace.dependencies]`, naming its section.
+fn for_each_dependency_table(
+    doc: &mut DocumentMut,
+    tables: &[&str],
+    edit: &mut dyn FnMut(&mut dyn TableLike, &str),
+) {
+    for table in tables {
+        if let Some(dependencies) = doc.get_mut(table).and_then(Item::as_table_like_mut) {
+            edit(dependencies, table);
+        }
+    }
+
+    // [target.'cfg(unix)'.dependencies] and friends
+    if let Some(targets) = doc.get_mut("target").and_then(Item::as_table_like_mut) {
+        for (platform_key, platform) in targets.iter_mut() {
+            for table in tables {
+                if let Some(dependencies) =
+                    platform.get_mut(table).and_then(Item::as_table_like_mut)
+                {
+                    edit(
+                        dependencies,
+                        &format!("target.'{}'.{}", platform_key.get(), table),
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(dependencies) = doc
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("dependencies"))
+        .and_then(Item::as_table_like_mut)
+    {
+        edit(dependencies, "workspace.dependencies");
+    }
+}
+
+/// Entries an edit moved (`[target.'cfg(windows)'.dependencies] winapi`) and
+/// those it could not, with the reason.
+#[derive(Default)]
+struct ManifestEdits {
+    locations: Vec<String>,
+    blockers: Vec<String>,
+}
+
+// Shared `[workspace.dependencies]` entries and `[patch]` overrides are
+// edited whatever the kind.
+fn update_manifest(
+    doc: &mut DocumentMut,
+    tables: &[&str],
+    package: &str,
+    target: &ParsedVersion,
+) -> ManifestEdits {
+    let mut edits = ManifestEdits::default();
+    for_each_dependency_table(doc, tables, &mut |dependencies, section| {
+        update_dependencies(dependencies, section, package, target, &mut edits)
+    });
+
+    // [patch.crates-io] and patches of other sources
+    if let Some(patches) = doc.get_mut("patch").and_then(Item::as_table_like_mut) {
+        for (source, dependencies) in patches.iter_mut() {
+            if let Some(dependencies) = dependencies.as_table_like_mut() {
+                let section = format!("patch.{}", source.get());
+                update_dependencies(dependencies, &section, package, target, &mut edits);
+            }
+        }
+    }
+    edits
+}
+
+/// Replaces entries for `package` with `replacement` at the target version.
+/// Aliased entries (`cli = { package = "structopt" }`) keep their key; a
+/// table already declaring the replacement has it moved to the target and
+/// the old entry dropped.
+fn rename_dependencies(
+    dependencies: &mut dyn TableLike,
+    section: &str,
+    package: &str,
+    replacement: &str,
+    target: &ParsedVersion,
+    edits: &mut ManifestEdits,
+) {
+    let keys: Vec<String> = dependencies
+        .iter()
+        .filter(|(key, entry)| {
+            entry.get("package").and_then(Item::as_str).unwrap_or(key) == package
+        })
+        .map(|(key, _)| key.to_string())
+        .collect();
+
+    for key in keys {
+        edits.locations.push(format!("[{}] {}", section, key));
+        if let Some(aliased) = dependencies
+            .get_mut(&key)
+            .filter(|entry| entry.get("package").is_some())
+        {
+            if let Some(name) = aliased.get_mut("package") {
+                set_string(name, replacement);
+            }
+            set_requirement(aliased, target);
+            continue;
+        }
+        let Some(mut entry) = dependencies.remove(&key) else {
+            continue;
+        };
+
+        match dependencies.get_mut(replacement) {
+            Some(existing) => {
+                set_requirement(existing, target);
+            }
+            None => {
+                set_requirement(&mut entry, target);
+                dependencies.insert(replacement, entry);
+            }
+        }
+    }
+}
+
+/// Rewrites the version requirement of entries for `package`, including
+/// renamed ones (`alias = { package = "...", version = "..." }`), and the
+/// `tag` of git entries tagged with a version. Entries inheriting from the
+/// workspace are left alone; path entries and git entries pinned to a
+/// branch, rev or other tag are reported as blockers.
+fn update_dependencies(
+    dependencies: &mut dyn TableLike,
+    section: &str,
+    package: &str,
+    target: &ParsedVersion,
+    edits: &mut ManifestEdits,
+) {
+    for (key, entry) in dependencies.iter_mut() {
+        let renamed_from = entry.get("package").and_then(Item::as_str);
+        if renamed_from.unwrap_or(key.get()) != package {
+            continue;
+        }
+
+        let location = format!("[{}] {}", section, key.get());
+        // A `version` next to `path` is what gets published, so it still moves
+        if let Some(path) = entry
+            .get("path")
+            .and_then(Item::as_str)
+            .filter(|_| entry.get("version").is_none())
+        {
+            edits
+                .blockers
+                .push(format!("{} points at the local path {}", location, path));
+            continue;
+        }
+        let mut moved_tag = false;
+        if entry.get("git").is_some() {
+            let Some(tag) = entry.get_mut("tag").filter(|tag| tag.is_str()) else {
+                let pin = match entry.get("rev").and_then(Item::as_str) {
+                    Some(rev) => format!("pins git rev {}", rev),
+                    None => "tracks a git branch".to_string(),
+                };
+                edits.blockers.push(format!("{} {}", location, pin));
+                continue;
+            };
+            let written = tag.as_str().unwrap_or_default().to_string();
+            match version_tag(&written, target) {
+                Some(moved) => {
+                    set_string(tag, &moved);
+                    moved_tag = true;
+                }
+                None => {
+                    edits.blockers.push(format!(
+                        "{} pins git tag {}, which does not name a version",
+                        location, written
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        if set_requirement(entry, target) || moved_tag {
+            edits.locations.push(location);
+        }
+    }
+}
+
+// Returns whether the entry carried a requirement to rewrite.
+fn set_requirement(entry: &mut Item, target: &ParsedVersion) -> bool {
+    let requirement = if entry.is_str() {
+        Some(entry)
+    } else {
+        entry.get_mut("version").filter(|version| version.is_str())
+    };
+    let Some(requirement) = requirement else {
+        return false;
+    };
+    let written = requirement.as_str().unwrap_or_default().to_string();
+    set_string(requirement, &rewrite_requirement("cargo", &written, target));
+    true
+}
+
+// `v1.2.3` or `1.2.3` tags move to the target, keeping the `v` prefix.
+fn version_tag(written: &str, target: &ParsedVersion) -> Option<String> {
+    let (prefix, version) = match written.strip_prefix('v') {
+        Some(version) => ("v", version),
+        None => ("", written),
+    };
+    semver::Version::parse(version).ok()?;
+    Some(format!("{}{}", prefix, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use crate::DependencyKind;
+    use std::collections::HashMap;
+
+    const MANIFEST: &str = r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1.0.100", features = ["derive"], optional = true } # serialization
+tokio = "~1.30"
+json = { package = "serde_json", version = "=1.0.100" }
+local = { path = "../local" }
+
+[dependencies.regex]
+version = "1.9"
+default-features = false
+
+[target.'cfg(unix)'.dependencies]
+serde = "1.0.100"
+
+[dev-dependencies]
+tokio = { workspace = true }
+"#;
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        upgrade_kind(package, target, None)
+    }
+
+    fn upgrade_kind(package: &str, target: &str, kind: Option<DependencyKind>) -> Vec<Change> {
+        let request = UpgradeRequest {
+            dependency_kind: kind,
+            ecosystem: "cargo".to_string(),
+            package_name: package.to_string(),
+            current_version: "1.0.0".to_string(),
+            manifests: HashMap::from([
+                ("crates/app/Cargo.toml".to_string(), MANIFEST.to_string()),
+                ("Cargo.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        Cargo.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_manifest_required() {
+        let request = UpgradeRequest {
+            ecosystem: "cargo".to_string(),
+            package_name: "serde".to_string(),
+            current_version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+        let error = Cargo
+            .generate_changes(
+                &request,
+                &ResolvedVersions {
+                    current: SemanticScheme.parse("1.0.0").unwrap(),
+                    target: SemanticScheme.parse("1.0.195").unwrap(),
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(error.error_type, crate::ErrorType::Validation));
+        assert!(error.message.contains("Manifest content required"));
+        assert!(error.message.contains("Cargo.toml"));
+    }
+
+    #[test]
+    fn test_manifest_entries() {
+        let serde = upgrade("serde", "1.0.195");
+        assert_eq!(serde.len(), 2);
+        let manifest = &serde[0].content;
+        assert!(manifest.contains(
+            r#"serde = { version = "1.0.195", features = ["derive"], optional = true } # serialization"#
+        ));
+        assert!(manifest.contains("[target.'cfg(unix)'.dependencies]\nserde = \"1.0.195\"\n"));
+        assert_eq!(
+            serde[0].metadata["locations"],
+            serde_json::json!([
+                "[dependencies] serde",
+                "[target.'cfg(unix)'.dependencies] serde"
+            ])
+        );
+        assert_eq!(
+            manifest.replace("1.0.195", "1.0.100"),
+            MANIFEST,
+            "only the requirement strings change"
+        );
+        assert_eq!(serde[1].file_path, "Cargo.lock");
+        assert_eq!(
+            serde[1].metadata["command"],
+            "cargo update -p serde --precise 1.0.195"
+        );
+        assert_eq!(
+            serde[1].metadata["argv"],
+            serde_json::json!([["cargo", "update", "-p", "serde", "--precise", "1.0.195"]])
+        );
+
+        let tokio = upgrade("tokio", "1.36.0");
+        assert!(tokio[0].content.contains("tokio = \"~1.36.0\"\n"));
+        assert!(tokio[0].content.contains("tokio = { workspace = true }"));
+        assert_eq!(
+            tokio[0].metadata["locations"],
+            serde_json::json!(["[dependencies] tokio"])
+        );
+
+        let renamed = upgrade("serde_json", "1.0.114");
+        assert!(renamed[0]
+            .content
+            .contains(r#"json = { package = "serde_json", version = "=1.0.114" }"#));
+
+        let regex = upgrade("regex", "1.10.3");
+        assert!(regex[0]
+            .content
+            .contains("[dependencies.regex]\nversion = \"1.10.3\"\ndefault-features = false\n"));
+    }
+
+    #[test]
+    fn test_overrides_and_sources() {
+        let manifest = r#"[dependencies]
+tracing = { git = "https://github.com/tokio-rs/tracing", tag = "tracing-0.1.40" }
+hyper = { git = "https://github.com/hyperium/hyper", tag = "v0.14.27", version = "0.14.27" }
+local = { path = "../local" }
+shared = { path = "../shared", version = "0.3.1" }
+pinned = { git = "https://github.com/acme/pinned", rev = "9f1c2e7" }
+
+[patch.crates-io]
+serde = { git = "https://github.com/serde-rs/serde", tag = "v1.0.100" }
+"#;
+        let upgrade = |package: &str, target: &str| {
+            let request = UpgradeRequest {
+                ecosystem: "cargo".to_string(),
+                package_name: package.to_string(),
+                manifests: HashMap::from([("Cargo.toml".to_string(), manifest.to_string())]),
+                ..Default::default()
+            };
+            let versions = ResolvedVersions {
+                current: SemanticScheme.parse("0.1.0").unwrap(),
+                target: SemanticScheme.parse(target).unwrap(),
+            };
+            Cargo.generate_changes(&request, &versions)
+        };
+
+        let hyper = upgrade("hyper", "0.14.28").unwrap();
+        assert!(hyper[0]
+            .content
+            .contains(r#"tag = "v0.14.28", version = "0.14.28" }"#));
+        let serde = upgrade("serde", "1.0.195").unwrap();
+        assert!(serde[0]
+            .content
+            .contains("[patch.crates-io]\nserde = { git = \"https://github.com/serde-rs/serde\", tag = \"v1.0.195\" }"));
+        let shared = upgrade("shared", "0.4.0").unwrap();
+        assert!(shared[0]
+            .content
+            .contains(r#"shared = { path = "../shared", version = "0.4.0" }"#));
+
+        for (package, reason) in [
+            (
+                "local",
+                "[dependencies] local points at the local path ../local",
+            ),
+            ("pinned", "[dependencies] pinned pins git rev 9f1c2e7"),
+            (
+                "tracing",
+                "pins git tag tracing-0.1.40, which does not name a version",
+            ),
+        ] {
+            let err = upgrade(package, "2.0.0").unwrap_err();
+            assert!(matches!(err.error_type, ErrorType::Unsupported));
+            assert!(err.message.contains(reason), "{}", err.message);
+        }
+    }
+
+    #[test]
+    fn test_dependency_kind_selects_tables() {
+        let normal = upgrade_kind("tokio", "1.36.0", Some(DependencyKind::Normal));
+        assert!(normal[0].content.contains("tokio = \"~1.36.0\"\n"));
+
+        assert!(upgrade_kind("serde", "1.0.195", Some(DependencyKind::Build)).is_empty());
+        let dev = upgrade_kind("serde", "1.0.195", Some(DependencyKind::Dev));
+        assert!(dev.is_empty());
+    }
+
+    #[test]
+    fn test_workspace_members() {
+        let root = "[workspace]\nmembers = [\"crates/*\", \"tools/gen\"]\nexclude = [\"crates/legacy\"]\n\n[workspace.dependencies]\nserde = { version = \"1.0.100\", features = [\"derive\"] }\n";
+        let inherits = "[package]\nname = \"core\"\n\n[dependencies]\nserde = { workspace = true, features = [\"rc\"] }\n";
+        let pins = "[package]\nname = \"gen\"\n\n[build-dependencies]\nserde = \"=1.0.100\"\n";
+        let request = UpgradeRequest {
+            ecosystem: "cargo".to_string(),
+            package_name: "serde".to_string(),
+            current_version: "1.0.100".to_string(),
+            manifests: HashMap::from([
+                ("Cargo.toml".to_string(), root.to_string()),
+                ("crates/core/Cargo.toml".to_string(), inherits.to_string()),
+                ("crates/legacy/Cargo.toml".to_string(), inherits.to_string()),
+                ("tools/gen/Cargo.toml".to_string(), pins.to_string()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.0.100").unwrap(),
+            target: SemanticScheme.parse("1.0.195").unwrap(),
+        };
+        let changes = Cargo.generate_changes(&request, &versions).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].file_path, "Cargo.toml");
+        assert!(changes[0]
+            .content
+            .contains(r#"serde = { version = "1.0.195", features = ["derive"] }"#));
+        assert_eq!(
+            changes[0].metadata["workspace_members"],
+            serde_json::json!(["crates/core/Cargo.toml", "tools/gen/Cargo.toml"])
+        );
+        assert_eq!(changes[1].file_path, "tools/gen/Cargo.toml");
+        assert!(changes[1].content.contains("serde = \"=1.0.195\""));
+        assert_eq!(changes[1].metadata["workspace_root"], "Cargo.toml");
+
+        // The excluded crate inherits from a workspace that is not supplied
+        let mut assessment = RiskAssessment {
+            version_jump: crate::version::VersionJump::Patch,
+            ..Default::default()
+        };
+        Cargo.assess_risk(&request, &versions, &mut assessment);
+        assert_eq!(assessment.risk_level, RiskLevel::Medium);
+        assert!(assessment.explanations[0].starts_with("crates/legacy/Cargo.toml inherits serde"));
+    }
+
+    #[test]
+    fn test_replacement_package() {
+        let manifest = r#"[dependencies]
+structopt = { version = "0.3.26", default-features = false }
+anyhow = "1.0"
+cli = { package = "structopt", version = "0.3" }
+
+[dev-dependencies]
+clap = "4.0"
+structopt = "0.3.26"
+"#;
+        let request = UpgradeRequest {
+            ecosystem: "cargo".to_string(),
+            package_name: "structopt".to_string(),
+            replacement_package: Some("clap".to_string()),
+            manifests: HashMap::from([
+                ("Cargo.toml".to_string(), manifest.to_string()),
+                ("Cargo.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("0.3.26").unwrap(),
+            target: SemanticScheme.parse("4.5.1").unwrap(),
+        };
+        let changes = Cargo.generate_changes(&request, &versions).unwrap();
+        assert_eq!(
+            changes[0].content,
+            r#"[dependencies]
+anyhow = "1.0"
+cli = { package = "clap", version = "4.5.1" }
+clap = { version = "4.5.1", default-features = false }
+
+[dev-dependencies]
+clap = "4.5.1"
+"#
+        );
+        assert_eq!(changes[1].metadata["command"], "cargo update --workspace");
+    }
+}
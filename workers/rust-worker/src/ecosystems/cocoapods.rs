@@ -0,0 +1,166 @@
+use super::rubygems::update_declarations;
+use super::{lockfile_refresh, manifest_required, manifests_named, modified, Ecosystem};
+use crate::version::{ResolvedVersions, VersionSpec};
+use crate::{Change, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use regex::Regex;
+
+/// iOS/macOS projects: `pod` declarations in a `Podfile` (subspecs such as
+/// `Firebase/Analytics` count as the `Firebase` pod), followed by a
+/// `Podfile.lock` refresh.
+pub struct CocoaPods;
+
+fn declaration(pod: &str) -> Regex {
+    Regex::new(&format!(
+        r#"(?m)^\s*pod\s+['"]{}(?:/[^'"]*)?['"]"#,
+        regex::escape(pod)
+    ))
+    .expect("valid pattern")
+}
+
+/// The first version constraint written for the pod in any Podfile.
+fn declared_constraint(request: &UpgradeRequest) -> Option<String> {
+    let constraint = Regex::new(&format!(
+        r#"{}\s*,\s*['"]([^'"]+)['"]"#,
+        declaration(&request.package_name).as_str()
+    ))
+    .expect("valid pattern");
+    manifests_named(request, &["Podfile"])
+        .into_iter()
+        .find_map(|(_, content)| Some(constraint.captures(content)?[1].to_string()))
+}
+
+impl Ecosystem for CocoaPods {
+    fn name(&self) -> &'static str {
+        "cocoapods"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.8
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let podfiles = manifests_named(request, &["Podfile"]);
+        if podfiles.is_empty() {
+            return Err(manifest_required("Podfile"));
+        }
+
+        let declaration = declaration(&request.package_name);
+        let mut changes = Vec::new();
+        for (path, content) in podfiles {
+            let updated = update_declarations(content, &declaration, &versions.target);
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+            changes.push(lockfile_refresh(
+                &format!("{}.lock", path),
+                &[&["pod", "update", &request.package_name]],
+            ));
+        }
+        Ok(changes)
+    }
+
+    /// A target outside the Podfile's `~>` constraint is a deliberate move
+    /// past the range the project declared compatible.
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        let Some(constraint) = declared_constraint(request) else {
+            return;
+        };
+        if !constraint.trim_start().starts_with("~>") {
+            return;
+        }
+        let Ok(spec) = VersionSpec::parse("cocoapods", &constraint) else {
+            return;
+        };
+        if !spec.matches(&versions.target) {
+            assessment.breaking_changes = true;
+            assessment.risk_level = assessment.risk_level.max(RiskLevel::High);
+            assessment.explanations.push(format!(
+                "Target {} is outside the Podfile constraint '{}'",
+                versions.target, constraint
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{RubyGemsScheme, VersionJump, VersionScheme};
+    use std::collections::HashMap;
+
+    const PODFILE: &str = r#"platform :ios, '15.0'
+target 'App' do
+  pod 'Alamofire', '~> 5.6'
+  pod 'Firebase/Analytics', '~> 10.0'
+  pod 'SnapKit'
+end
+"#;
+
+    fn request(package: &str, target: &str) -> (UpgradeRequest, ResolvedVersions) {
+        let request = UpgradeRequest {
+            ecosystem: "cocoapods".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([("ios/Podfile".to_string(), PODFILE.to_string())]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: RubyGemsScheme.parse("5.6.0").unwrap(),
+            target: RubyGemsScheme.parse(target).unwrap(),
+        };
+        (request, versions)
+    }
+
+    #[test]
+    fn test_podfile_declarations() {
+        let (alamofire, versions) = request("Alamofire", "5.8.1");
+        let changes = CocoaPods.generate_changes(&alamofire, &versions).unwrap();
+        assert!(changes[0].content.contains("pod 'Alamofire', '~> 5.8'"));
+        assert_eq!(changes[1].file_path, "ios/Podfile.lock");
+        assert_eq!(
+            changes[1].metadata["argv"],
+            serde_json::json!([["pod", "update", "Alamofire"]])
+        );
+
+        let (firebase, versions) = request("Firebase", "10.19.0");
+        let changes = CocoaPods.generate_changes(&firebase, &versions).unwrap();
+        assert!(changes[0]
+            .content
+            .contains("pod 'Firebase/Analytics', '~> 10.19'"));
+
+        let (snapkit, versions) = request("SnapKit", "5.6.0");
+        assert!(CocoaPods
+            .generate_changes(&snapkit, &versions)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_pessimistic_constraint_risk() {
+        let assess = |target: &str| {
+            let (request, versions) = request("Alamofire", target);
+            let mut assessment = RiskAssessment {
+                version_jump: VersionJump::Minor,
+                ..Default::default()
+            };
+            CocoaPods.assess_risk(&request, &versions, &mut assessment);
+            assessment
+        };
+
+        assert!(!assess("5.8.1").breaking_changes);
+        let outside = assess("6.0.0");
+        assert!(outside.breaking_changes);
+        assert_eq!(outside.risk_level, RiskLevel::High);
+        assert_eq!(outside.explanations.len(), 1);
+    }
+}
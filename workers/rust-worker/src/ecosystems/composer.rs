@@ -0,0 +1,183 @@
+use super::{
+    lockfile_refresh, manifest_required, manifests_named, modified, object_body, unsupported_kind,
+    Ecosystem,
+};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, DependencyKind, UpgradeError, UpgradeRequest};
+use std::ops::Range;
+
+/// PHP projects: `require`/`require-dev` constraints in `composer.json`,
+/// with a `composer.lock` refresh when the project commits its lockfile.
+pub struct Composer;
+
+const SECTIONS: &[&str] = &["require", "require-dev"];
+
+fn sections(kind: Option<DependencyKind>) -> &'static [&'static str] {
+    match kind {
+        None => SECTIONS,
+        Some(DependencyKind::Normal) => &["require"],
+        Some(DependencyKind::Dev) => &["require-dev"],
+        Some(_) => &[],
+    }
+}
+
+impl Ecosystem for Composer {
+    fn name(&self) -> &'static str {
+        "composer"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let sections = sections(request.dependency_kind);
+        if sections.is_empty() {
+            return Err(unsupported_kind("composer", request.dependency_kind));
+        }
+
+        let manifests = manifests_named(request, &["composer.json"]);
+        if manifests.is_empty() {
+            return Err(manifest_required("composer.json"));
+        }
+
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let updated =
+                update_manifest(content, sections, &request.package_name, &versions.target);
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+
+            let lock_path = format!("{}composer.lock", path.trim_end_matches("composer.json"));
+            if request.manifests.contains_key(&lock_path) {
+                changes.push(lockfile_refresh(
+                    &lock_path,
+                    &[&[
+                        "composer",
+                        "update",
+                        &request.package_name,
+                        "--with-dependencies",
+                    ]],
+                ));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Rewrites the package's constraint inside `sections`, editing the text in
+/// place so key order and formatting are kept.
+fn update_manifest(
+    content: &str,
+    sections: &[&str],
+    package: &str,
+    target: &ParsedVersion,
+) -> String {
+    let mut edits: Vec<(Range<usize>, String)> = sections
+        .iter()
+        .filter_map(|section| object_body(content, section))
+        .filter_map(|body| {
+            let key = format!("\"{}\"", package);
+            let key_end = body.start + content[body.clone()].find(&key)? + key.len();
+            let value_start = key_end + content[key_end..].find('"')? + 1;
+            let value_end = value_start + content[value_start..].find('"')?;
+            let written = &content[value_start..value_end];
+            Some((
+                value_start..value_end,
+                rewrite_requirement("composer", written, target),
+            ))
+        })
+        .collect();
+
+    let mut updated = content.to_string();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, constraint) in edits {
+        updated.replace_range(range, &constraint);
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const MANIFEST: &str = r#"{
+    "name": "acme/app",
+    "require": {
+        "php": "^8.1",
+        "monolog/monolog": "~2.9",
+        "guzzlehttp/guzzle": "7.5.0"
+    },
+    "require-dev": {
+        "phpunit/phpunit": "^9.6"
+    },
+    "suggest": {
+        "phpunit/phpunit": "for tests"
+    }
+}
+"#;
+
+    fn upgrade(package: &str, target: &str, with_lock: bool) -> Vec<Change> {
+        let mut manifests = HashMap::from([("composer.json".to_string(), MANIFEST.to_string())]);
+        if with_lock {
+            manifests.insert("composer.lock".to_string(), "{}".to_string());
+        }
+        let request = UpgradeRequest {
+            ecosystem: "composer".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests,
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        Composer.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_preserves_constraint_style() {
+        let monolog = upgrade("monolog/monolog", "3.5.0", false);
+        assert_eq!(monolog.len(), 1);
+        assert!(monolog[0].content.contains(r#""monolog/monolog": "~3.5","#));
+
+        let guzzle = upgrade("guzzlehttp/guzzle", "7.8.1", false);
+        assert!(guzzle[0]
+            .content
+            .contains(r#""guzzlehttp/guzzle": "7.8.1""#));
+
+        let phpunit = upgrade("phpunit/phpunit", "10.5.9", false);
+        assert!(phpunit[0]
+            .content
+            .contains(r#""phpunit/phpunit": "^10.5.9""#));
+        assert!(phpunit[0]
+            .content
+            .contains(r#""phpunit/phpunit": "for tests""#));
+    }
+
+    #[test]
+    fn test_lockfile_refresh() {
+        let changes = upgrade("monolog/monolog", "3.5.0", true);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[1].file_path, "composer.lock");
+        assert_eq!(
+            changes[1].metadata["argv"],
+            serde_json::json!([[
+                "composer",
+                "update",
+                "monolog/monolog",
+                "--with-dependencies"
+            ]])
+        );
+    }
+}
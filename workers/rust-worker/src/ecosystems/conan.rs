@@ -0,0 +1,142 @@
+use super::{manifest_required, manifests_named, modified, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+
+/// C/C++ projects: `name/version` references in `conanfile.txt` sections and
+/// `conanfile.py` (`requires = ...`, `self.requires(...)`), including Conan 2
+/// version ranges (`boost/[>=1.80 <2]`). User/channel and revision suffixes
+/// are kept.
+pub struct Conan;
+
+impl Ecosystem for Conan {
+    fn name(&self) -> &'static str {
+        "conan"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.75
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let files = manifests_named(request, &["conanfile.txt", "conanfile.py"]);
+        if files.is_empty() {
+            return Err(manifest_required("conanfile.txt"));
+        }
+
+        let reference = Regex::new(&format!(
+            r#"(?m)((?:^|[\s"'(,])\s*{}/)(\[[^\]]*\]|[^\s"'@#,)\]]+)"#,
+            regex::escape(&request.package_name)
+        ))
+        .expect("valid pattern");
+
+        let mut changes = Vec::new();
+        for (path, content) in files {
+            let updated = reference
+                .replace_all(content, |caps: &Captures| {
+                    format!(
+                        "{}{}",
+                        &caps[1],
+                        rewrite_version(&caps[2], &versions.target)
+                    )
+                })
+                .into_owned();
+            if updated != content {
+                changes.push(modified(path, updated));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Pinned versions become the target. Ranges stay ranges: single comparators
+/// keep their operator, compound ranges are replaced by `>=target <next major`,
+/// and options such as `include_prerelease` are kept.
+fn rewrite_version(written: &str, target: &ParsedVersion) -> String {
+    let Some(range) = written.strip_prefix('[').and_then(|w| w.strip_suffix(']')) else {
+        return target.to_string();
+    };
+
+    let (constraint, options) = match range.split_once(',') {
+        Some((constraint, options)) => (constraint.trim(), format!(",{}", options)),
+        None => (range.trim(), String::new()),
+    };
+    let rewritten = if constraint.split_whitespace().count() > 1 {
+        format!(">={} <{}", target, target.major() + 1)
+    } else {
+        rewrite_requirement("conan", constraint, target)
+    };
+    format!("[{}{}]", rewritten, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{ConanScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    fn upgrade(files: &[(&str, &str)], package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "conan".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: ConanScheme.parse("1.0").unwrap(),
+            target: ConanScheme.parse(target).unwrap(),
+        };
+        Conan.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_conanfile_txt() {
+        let conanfile = "[requires]\nzlib/1.2.13\nboost/[>=1.80 <1.84]\nfmt/[^10.0, include_prerelease]\nopenssl/3.1.2@corp/stable#a1b2c3\nzlib-ng/2.1.3\n\n[tool_requires]\ncmake/3.27.1\n";
+        let files = [("conanfile.txt", conanfile)];
+
+        let zlib = upgrade(&files, "zlib", "1.3");
+        assert!(zlib[0].content.contains("\nzlib/1.3\n"));
+        assert!(zlib[0].content.contains("zlib-ng/2.1.3"));
+
+        let boost = upgrade(&files, "boost", "1.84.0");
+        assert!(boost[0].content.contains("boost/[>=1.84.0 <2]"));
+
+        let fmt = upgrade(&files, "fmt", "10.2.1");
+        assert!(fmt[0].content.contains("fmt/[^10.2.1, include_prerelease]"));
+
+        let openssl = upgrade(&files, "openssl", "3.2.0");
+        assert!(openssl[0]
+            .content
+            .contains("openssl/3.2.0@corp/stable#a1b2c3"));
+    }
+
+    #[test]
+    fn test_conanfile_py() {
+        let recipe = r#"class App(ConanFile):
+    requires = "spdlog/1.12.0", "catch2/3.4.0"
+
+    def build_requirements(self):
+        self.tool_requires("cmake/[>=3.20]")
+"#;
+        let files = [("conanfile.py", recipe)];
+
+        let spdlog = upgrade(&files, "spdlog", "1.13.0");
+        assert!(spdlog[0]
+            .content
+            .contains(r#"requires = "spdlog/1.13.0", "catch2/3.4.0""#));
+
+        let cmake = upgrade(&files, "cmake", "3.28.1");
+        assert!(cmake[0]
+            .content
+            .contains(r#"self.tool_requires("cmake/[>=3.28.1]")"#));
+    }
+}
@@ -0,0 +1,158 @@
+use super::pip::{normalize_name, rewrite_pep508};
+use super::{manifest_required, manifests_named, modified, yaml, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Conda environments: match specs in the `dependencies` list of
+/// `environment.yml` (`numpy=1.26`, `conda-forge::scipy>=1.11`), and PEP 508
+/// requirements in its nested `pip:` list.
+pub struct Conda;
+
+const ENVIRONMENT_FILES: &[&str] = &["environment.yml", "environment.yaml"];
+
+// `[channel::]name[ version[ build]]`, where the version may start with an
+// operator or follow a space
+fn match_spec_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^((?:[^:\s]+::)?([A-Za-z0-9_][A-Za-z0-9_.-]*)(?:\[[^\]]*\])?)(\s*)(.*)$")
+            .expect("valid pattern")
+    })
+}
+
+impl Ecosystem for Conda {
+    fn name(&self) -> &'static str {
+        "conda"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.8
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let manifests = manifests_named(request, ENVIRONMENT_FILES);
+        if manifests.is_empty() {
+            return Err(manifest_required(ENVIRONMENT_FILES[0]));
+        }
+
+        let name = normalize_name(&request.package_name);
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let edits = yaml::scalars(content)
+                .iter()
+                .filter_map(|entry| {
+                    let updated = if entry.is_at(&["dependencies", "*"]) {
+                        rewrite_match_spec(&entry.value, &name, &versions.target)
+                    } else if entry.is_at(&["dependencies", "*", "pip", "*"]) {
+                        rewrite_pep508(&entry.value, &name, &versions.target)
+                    } else {
+                        None
+                    };
+                    updated.map(|updated| entry.set(&updated))
+                })
+                .collect();
+            let updated = yaml::apply(content, edits);
+            if updated != content {
+                changes.push(modified(path, updated));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Rewrites the version of a conda match spec naming `name`. Build strings
+/// (`=py311h64a7726_0`) belong to the old release and are dropped; unpinned
+/// specs are left alone.
+fn rewrite_match_spec(spec: &str, name: &str, target: &ParsedVersion) -> Option<String> {
+    let caps = match_spec_pattern().captures(spec)?;
+    if normalize_name(&caps[2]) != name || caps[4].is_empty() {
+        return None;
+    }
+
+    let constraint = caps[4].trim();
+    // `=1.26=build` and `1.26 build` both carry a build after the version
+    let version = match constraint
+        .strip_prefix('=')
+        .filter(|rest| !rest.starts_with('='))
+    {
+        Some(rest) => format!("={}", rest.split('=').next().unwrap_or(rest)),
+        None => constraint
+            .split_whitespace()
+            .next()
+            .unwrap_or(constraint)
+            .to_string(),
+    };
+    Some(format!(
+        "{}{}{}",
+        &caps[1],
+        &caps[3],
+        rewrite_requirement("conda", &version, target)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{Pep440Scheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const ENVIRONMENT: &str = r#"name: analysis
+channels:
+  - conda-forge
+dependencies:
+  - python=3.11
+  - numpy=1.24.3=py311h64a7726_0   # pinned build
+  - conda-forge::scipy>=1.10
+  - pandas 2.0.*
+  - matplotlib
+  - pip
+  - pip:
+      - requests==2.31.0
+"#;
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "conda".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([("environment.yml".to_string(), ENVIRONMENT.to_string())]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: Pep440Scheme.parse("1.0").unwrap(),
+            target: Pep440Scheme.parse(target).unwrap(),
+        };
+        Conda.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_environment_dependencies() {
+        let numpy = upgrade("numpy", "1.26.4");
+        assert_eq!(
+            numpy[0].content,
+            ENVIRONMENT.replace("numpy=1.24.3=py311h64a7726_0", "numpy=1.26.4")
+        );
+
+        let python = upgrade("python", "3.12.2");
+        assert!(python[0].content.contains("  - python=3.12\n"));
+
+        let scipy = upgrade("scipy", "1.12.0");
+        assert!(scipy[0]
+            .content
+            .contains("  - conda-forge::scipy>=1.12.0\n"));
+
+        let pandas = upgrade("pandas", "2.2.1");
+        assert!(pandas[0].content.contains("  - pandas 2.2.*\n"));
+
+        let requests = upgrade("requests", "2.32.3");
+        assert!(requests[0].content.contains("      - requests==2.32.3\n"));
+
+        assert!(upgrade("matplotlib", "3.8.3").is_empty());
+    }
+}
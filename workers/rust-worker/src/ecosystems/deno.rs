@@ -0,0 +1,185 @@
+use super::{lockfile_refresh, manifest_required, manifests_named, modified, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Deno projects: `imports` (and import map `scopes`) in `deno.json`,
+/// `deno.jsonc` and import map files, where the version is part of the
+/// specifier: `jsr:@std/assert@^1.0.0`, `npm:chalk@5.3.0` or
+/// `https://deno.land/x/oak@v12.6.1/`.
+pub struct Deno;
+
+const CONFIG_FILES: &[&str] = &["deno.json", "deno.jsonc"];
+
+fn import_map_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#""importMap"\s*:\s*"([^"]+)""#).expect("valid pattern"))
+}
+
+impl Ecosystem for Deno {
+    fn name(&self) -> &'static str {
+        "deno"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.8
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let files = import_files(request);
+        if files.is_empty() {
+            return Err(manifest_required("deno.json"));
+        }
+
+        // Registry specifiers carry a requirement; URLs pin an exact version
+        let specifier = Regex::new(&format!(
+            r#""((?:jsr:|npm:)/?{package}@|https?://[^"\s]*/{package}@)([^/"\s]+)([^"]*)""#,
+            package = regex::escape(&request.package_name)
+        ))
+        .expect("valid pattern");
+
+        let mut changes = Vec::new();
+        for (path, content) in files {
+            let updated = specifier
+                .replace_all(content, |caps: &Captures| {
+                    let version = if caps[1].starts_with("http") {
+                        url_version(&caps[2], &versions.target)
+                    } else {
+                        rewrite_requirement("deno", &caps[2], &versions.target)
+                    };
+                    format!(r#""{}{}{}""#, &caps[1], version, &caps[3])
+                })
+                .into_owned();
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+        }
+
+        // Lockfiles sit next to the config, wherever its import map lives
+        if !changes.is_empty() {
+            for (path, _) in manifests_named(request, CONFIG_FILES) {
+                let lock_path = format!("{}deno.lock", directory(path));
+                if request.manifests.contains_key(&lock_path) {
+                    changes.push(lockfile_refresh(&lock_path, &[&["deno", "install"]]));
+                }
+            }
+        }
+        Ok(changes)
+    }
+}
+
+fn directory(path: &str) -> &str {
+    &path[..path.rfind('/').map_or(0, |i| i + 1)]
+}
+
+// Keeps a `v` prefix (`oak@v12.6.1`) when the URL used one
+fn url_version(written: &str, target: &ParsedVersion) -> String {
+    let target = target.as_str().trim_start_matches('v');
+    if written.starts_with('v') {
+        format!("v{}", target)
+    } else {
+        target.to_string()
+    }
+}
+
+/// Deno config files plus the import maps they reference through
+/// `importMap` or that use the conventional `import_map.json` name.
+fn import_files(request: &UpgradeRequest) -> Vec<(&str, &str)> {
+    let configs = manifests_named(request, CONFIG_FILES);
+    let mut files = configs.clone();
+    files.extend(manifests_named(request, &["import_map.json"]));
+    for (path, content) in configs {
+        let Some(caps) = import_map_pattern().captures(content) else {
+            continue;
+        };
+        let referenced = caps[1].trim_start_matches("./");
+        let map_path = format!("{}{}", directory(path), referenced);
+        if let Some((path, content)) = request.manifests.get_key_value(&map_path) {
+            files.push((path.as_str(), content.as_str()));
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const DENO_JSON: &str = r#"{
+  "importMap": "./maps/imports.json",
+  "imports": {
+    "@std/assert": "jsr:@std/assert@^1.0.0",
+    "chalk": "npm:chalk@5.3.0",
+    "oak/": "https://deno.land/x/oak@v12.6.1/",
+    "oak_sessions/": "https://deno.land/x/oak_sessions@v4.1.9/"
+  }
+}
+"#;
+
+    const IMPORT_MAP: &str = r#"{
+  "imports": { "preact": "https://esm.sh/preact@10.19.2" },
+  "scopes": {
+    "./legacy/": { "@std/assert": "jsr:@std/assert@0.220.0/assert-equals" }
+  }
+}
+"#;
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "deno".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                ("deno.json".to_string(), DENO_JSON.to_string()),
+                ("maps/imports.json".to_string(), IMPORT_MAP.to_string()),
+                ("deno.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        Deno.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_registry_specifiers() {
+        let assert = upgrade("@std/assert", "1.0.6");
+        assert_eq!(assert.len(), 3);
+        assert!(assert[0].content.contains(r#""jsr:@std/assert@^1.0.6""#));
+        assert!(assert[1]
+            .content
+            .contains(r#""jsr:@std/assert@1.0.6/assert-equals""#));
+        assert_eq!(assert[2].file_path, "deno.lock");
+
+        let chalk = upgrade("chalk", "5.4.1");
+        assert!(chalk[0].content.contains(r#""npm:chalk@5.4.1""#));
+    }
+
+    #[test]
+    fn test_versioned_urls() {
+        let oak = upgrade("oak", "v17.1.3");
+        assert!(oak[0]
+            .content
+            .contains(r#""https://deno.land/x/oak@v17.1.3/""#));
+        assert!(oak[0].content.contains("oak_sessions@v4.1.9"));
+
+        let preact = upgrade("preact", "10.24.0");
+        assert_eq!(preact[0].file_path, "maps/imports.json");
+        assert_eq!(preact[1].file_path, "deno.lock");
+        assert!(preact[0]
+            .content
+            .contains(r#""https://esm.sh/preact@10.24.0""#));
+    }
+}
@@ -0,0 +1,172 @@
+use super::os_packages::is_dockerfile;
+use super::{manifest_required, manifests_matching, modified, Ecosystem};
+use crate::version::ResolvedVersions;
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Base images of Dockerfile `FROM` instructions (`FROM node:18.19.0-alpine
+/// AS build`). The tag's variant suffix is kept and a digest pinning the
+/// old image is dropped.
+pub struct Docker;
+
+fn from_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"(?im)^(\s*FROM\s+(?:--\S+\s+)*)(\S+)").expect("valid pattern"))
+}
+
+// Docker Hub images can be named with or without their implicit prefixes
+fn normalize(name: &str) -> &str {
+    let name = name.strip_prefix("docker.io/").unwrap_or(name);
+    name.strip_prefix("library/").unwrap_or(name)
+}
+
+/// Splits an image reference into its name and tag, leaving out any digest.
+fn split_reference(reference: &str) -> (&str, Option<&str>) {
+    let reference = reference.split('@').next().unwrap_or(reference);
+    // A colon before the last slash separates a registry port
+    match reference.rfind(':') {
+        Some(colon) if !reference[colon..].contains('/') => {
+            (&reference[..colon], Some(&reference[colon + 1..]))
+        }
+        _ => (reference, None),
+    }
+}
+
+/// The image `reference` upgraded to `versions.target`, if it is the
+/// requested image at the current version.
+fn upgraded_reference(
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+    reference: &str,
+) -> Option<(String, String)> {
+    let (name, tag) = split_reference(reference);
+    if normalize(name) != normalize(&request.package_name) {
+        return None;
+    }
+    let current = versions.current.to_string();
+    let variant = tag?.strip_prefix(current.as_str())?;
+    if !variant.is_empty() && !variant.starts_with('-') {
+        return None;
+    }
+    Some((
+        format!("{}:{}", name, tag?),
+        format!("{}:{}{}", name, versions.target, variant),
+    ))
+}
+
+/// `(current, target)` references of the base images the upgrade bumps, in
+/// the order Dockerfiles name them; the bare image when none is supplied.
+pub fn base_images(request: &UpgradeRequest, versions: &ResolvedVersions) -> Vec<(String, String)> {
+    let mut images: Vec<(String, String)> = Vec::new();
+    for (_, content) in manifests_matching(request, is_dockerfile) {
+        for caps in from_pattern().captures_iter(content) {
+            if let Some(pair) = upgraded_reference(request, versions, &caps[2]) {
+                if !images.contains(&pair) {
+                    images.push(pair);
+                }
+            }
+        }
+    }
+    if images.is_empty() {
+        images.push((
+            format!("{}:{}", request.package_name, versions.current),
+            format!("{}:{}", request.package_name, versions.target),
+        ));
+    }
+    images
+}
+
+impl Ecosystem for Docker {
+    fn name(&self) -> &'static str {
+        "docker"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let dockerfiles = manifests_matching(request, is_dockerfile);
+        if dockerfiles.is_empty() {
+            return Err(manifest_required("Dockerfile"));
+        }
+
+        let mut changes = Vec::new();
+        for (path, content) in dockerfiles {
+            let updated = from_pattern().replace_all(content, |caps: &Captures| {
+                match upgraded_reference(request, versions, &caps[2]) {
+                    Some((_, target)) => format!("{}{}", &caps[1], target),
+                    None => caps[0].to_string(),
+                }
+            });
+            if updated != content {
+                changes.push(modified(path, updated.into_owned()));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{ConanScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const DOCKERFILE: &str = "ARG NODE_VERSION=18
+FROM --platform=$BUILDPLATFORM node:18.19.0-alpine AS build
+RUN npm ci
+FROM node:18.19.0-alpine@sha256:435dcad253bb5b7f347ebc69c8cc52de7c912eb7241098b920f2fc2d7843183d
+FROM node:18.19.0
+FROM node:18.19.01
+FROM registry.example.com:5000/node:18.19.0
+";
+
+    #[test]
+    fn test_base_image_bumps() {
+        let request = UpgradeRequest {
+            ecosystem: "docker".to_string(),
+            package_name: "node".to_string(),
+            manifests: HashMap::from([("Dockerfile".to_string(), DOCKERFILE.to_string())]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: ConanScheme.parse("18.19.0").unwrap(),
+            target: ConanScheme.parse("20.11.0").unwrap(),
+        };
+
+        let changes = Docker.generate_changes(&request, &versions).unwrap();
+        assert_eq!(
+            changes[0].content,
+            "ARG NODE_VERSION=18
+FROM --platform=$BUILDPLATFORM node:20.11.0-alpine AS build
+RUN npm ci
+FROM node:20.11.0-alpine
+FROM node:20.11.0
+FROM node:18.19.01
+FROM registry.example.com:5000/node:18.19.0
+"
+        );
+        assert_eq!(
+            base_images(&request, &versions),
+            [
+                (
+                    "node:18.19.0-alpine".to_string(),
+                    "node:20.11.0-alpine".to_string()
+                ),
+                ("node:18.19.0".to_string(), "node:20.11.0".to_string()),
+            ]
+        );
+        assert_eq!(
+            split_reference("registry.example.com:5000/node"),
+            ("registry.example.com:5000/node", None)
+        );
+        assert_eq!(normalize("docker.io/library/node"), "node");
+    }
+}
@@ -0,0 +1,224 @@
+use super::{manifest_required, modified, Ecosystem};
+use crate::version::ResolvedVersions;
+use crate::{Change, ErrorType, UpgradeError, UpgradeRequest};
+use serde_json::json;
+
+/// Request metadata carrying the commit the target tag points at, which the
+/// submodule is pinned to.
+pub const SUBMODULE_COMMIT_METADATA_KEY: &str = "submodule_commit";
+
+/// Request metadata carrying the submodule's new URL, for projects that
+/// moved.
+pub const SUBMODULE_URL_METADATA_KEY: &str = "submodule_url";
+
+/// Change metadata marking a submodule commit (gitlink) rather than a file;
+/// its value is the commit.
+pub const GITLINK_METADATA_KEY: &str = "gitlink";
+
+/// Git submodules, named by their `.gitmodules` name or path and versioned
+/// by the tags of the submodule repository. The gitlink is moved to the
+/// commit of the target tag and `.gitmodules` follows a changed URL.
+pub struct GitSubmodule;
+
+impl Ecosystem for GitSubmodule {
+    fn name(&self) -> &'static str {
+        "git-submodule"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.8
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        _versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let gitmodules = request
+            .manifests
+            .get(".gitmodules")
+            .ok_or_else(|| manifest_required(".gitmodules"))?;
+        let commit = request
+            .metadata
+            .get(SUBMODULE_COMMIT_METADATA_KEY)
+            .and_then(|value| value.as_str())
+            .map(str::to_ascii_lowercase)
+            .ok_or_else(|| {
+                validation(format!(
+                    "Submodules are pinned by commit; supply the commit of the target tag in '{}'",
+                    SUBMODULE_COMMIT_METADATA_KEY
+                ))
+            })?;
+        if !(commit.len() == 40 && commit.chars().all(|c| c.is_ascii_hexdigit())) {
+            return Err(validation(format!(
+                "'{}' must be a full 40-character commit SHA, got '{}'",
+                SUBMODULE_COMMIT_METADATA_KEY, commit
+            )));
+        }
+        let url = request
+            .metadata
+            .get(SUBMODULE_URL_METADATA_KEY)
+            .and_then(|value| value.as_str());
+
+        let mut changes = Vec::new();
+        let submodule = submodules(gitmodules)
+            .into_iter()
+            .find(|submodule| {
+                submodule.name == request.package_name
+                    || submodule.path == Some(request.package_name.as_str())
+            })
+            .ok_or_else(|| {
+                validation(format!(
+                    ".gitmodules declares no submodule '{}'",
+                    request.package_name
+                ))
+            })?;
+        if let (Some(url), Some((current, range))) = (url, submodule.url) {
+            if url != current {
+                let mut updated = gitmodules.clone();
+                updated.replace_range(range, url);
+                changes.push(modified(".gitmodules", updated));
+            }
+        }
+        let path = submodule.path.unwrap_or(submodule.name);
+
+        let mut gitlink = modified(path, format!("Subproject commit {}\n", commit));
+        gitlink
+            .metadata
+            .insert(GITLINK_METADATA_KEY.to_string(), json!(commit));
+        changes.insert(0, gitlink);
+        Ok(changes)
+    }
+}
+
+/// The commit a gitlink change points the submodule at.
+pub fn gitlink(change: &Change) -> Option<&str> {
+    change.metadata.get(GITLINK_METADATA_KEY)?.as_str()
+}
+
+fn validation(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Validation,
+    }
+}
+
+struct Submodule<'a> {
+    name: &'a str,
+    path: Option<&'a str>,
+    /// The URL and its byte range in `.gitmodules`.
+    url: Option<(&'a str, std::ops::Range<usize>)>,
+}
+
+// `[submodule "name"]` sections of `.gitmodules`
+fn submodules(content: &str) -> Vec<Submodule<'_>> {
+    let mut submodules: Vec<Submodule> = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        if let Some(section) = trimmed.strip_prefix('[') {
+            if let Some(name) = section
+                .strip_prefix("submodule")
+                .map(str::trim_start)
+                .and_then(|rest| rest.strip_prefix('"'))
+                .and_then(|rest| rest.strip_suffix("\"]"))
+            {
+                submodules.push(Submodule {
+                    name,
+                    path: None,
+                    url: None,
+                });
+            }
+            continue;
+        }
+        let (Some(submodule), Some((key, value))) =
+            (submodules.last_mut(), trimmed.split_once('='))
+        else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "path" => submodule.path = Some(value),
+            "url" => {
+                let at = start + line.find(value).unwrap_or(0);
+                submodule.url = Some((value, at..at + value.len()));
+            }
+            _ => {}
+        }
+    }
+    submodules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const GITMODULES: &str = r#"[submodule "libfoo"]
+	path = third_party/libfoo
+	url = https://github.com/foo/libfoo.git
+[submodule "docs-theme"]
+	path = docs/theme
+	url = ../theme.git
+"#;
+
+    const COMMIT: &str = "9fceb02d0ae598e95dc970b74767f19372d61af8";
+
+    fn upgrade(package: &str, metadata: &[(&str, &str)]) -> Result<Vec<Change>, UpgradeError> {
+        let request = UpgradeRequest {
+            ecosystem: "git-submodule".to_string(),
+            package_name: package.to_string(),
+            metadata: metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), json!(value)))
+                .collect(),
+            manifests: HashMap::from([(".gitmodules".to_string(), GITMODULES.to_string())]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("v1.2.0").unwrap(),
+            target: SemanticScheme.parse("v1.3.0").unwrap(),
+        };
+        GitSubmodule.generate_changes(&request, &versions)
+    }
+
+    #[test]
+    fn test_submodule_bump() {
+        let changes = upgrade("libfoo", &[(SUBMODULE_COMMIT_METADATA_KEY, COMMIT)]).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].file_path, "third_party/libfoo");
+        assert_eq!(gitlink(&changes[0]), Some(COMMIT));
+        assert_eq!(
+            changes[0].content,
+            format!("Subproject commit {}\n", COMMIT)
+        );
+
+        // Submodules are found by path too, and follow a new URL
+        let moved = upgrade(
+            "docs/theme",
+            &[
+                (SUBMODULE_COMMIT_METADATA_KEY, COMMIT),
+                (
+                    SUBMODULE_URL_METADATA_KEY,
+                    "https://github.com/acme/theme.git",
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(moved[0].file_path, "docs/theme");
+        assert_eq!(moved[1].file_path, ".gitmodules");
+        assert_eq!(
+            moved[1].content,
+            GITMODULES.replace("../theme.git", "https://github.com/acme/theme.git")
+        );
+
+        for metadata in [vec![], vec![(SUBMODULE_COMMIT_METADATA_KEY, "v1.3.0")]] {
+            let err = upgrade("libfoo", &metadata).unwrap_err();
+            assert!(matches!(err.error_type, ErrorType::Validation));
+        }
+        assert!(upgrade("missing", &[(SUBMODULE_COMMIT_METADATA_KEY, COMMIT)]).is_err());
+    }
+}
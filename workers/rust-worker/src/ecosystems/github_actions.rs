@@ -0,0 +1,269 @@
+use super::{manifest_required, modified, Ecosystem};
+use crate::version::ResolvedVersions;
+use crate::{Change, ErrorType, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+
+/// Request metadata carrying the commit SHA the target release tag points at.
+/// Required to update SHA-pinned `uses:` references.
+pub const GITHUB_ACTIONS_SHA_METADATA_KEY: &str = "action_commit_sha";
+
+/// Request metadata flag (`true`) asking for tag pins to be converted to SHA
+/// pins, with the tag kept as a trailing comment.
+pub const GITHUB_ACTIONS_PIN_METADATA_KEY: &str = "pin_to_sha";
+
+/// GitHub Actions workflows: `uses: owner/repo[/path]@ref` steps under
+/// `.github/workflows/`. Tag refs keep their precision (`v3` -> `v4`); SHA
+/// refs are replaced with the target commit.
+pub struct GitHubActions;
+
+impl Ecosystem for GitHubActions {
+    fn name(&self) -> &'static str {
+        "github-actions"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let commit = request
+            .metadata
+            .get(GITHUB_ACTIONS_SHA_METADATA_KEY)
+            .and_then(|value| value.as_str())
+            .map(str::to_ascii_lowercase);
+        if let Some(commit) = &commit {
+            if !is_commit_sha(commit) {
+                return Err(validation(format!(
+                    "'{}' must be a full 40-character commit SHA, got '{}'",
+                    GITHUB_ACTIONS_SHA_METADATA_KEY, commit
+                )));
+            }
+        }
+        let pin = request
+            .metadata
+            .get(GITHUB_ACTIONS_PIN_METADATA_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        if pin && commit.is_none() {
+            return Err(validation(format!(
+                "Pinning to a SHA requires the target commit in '{}'",
+                GITHUB_ACTIONS_SHA_METADATA_KEY
+            )));
+        }
+
+        let tag = versions.target.as_str().trim_start_matches('v');
+        let workflows = workflow_files(request);
+        if workflows.is_empty() {
+            return Err(manifest_required(".github/workflows/*.yml"));
+        }
+
+        // `owner/repo` also covers sub-path actions like `github/codeql-action/init`
+        let step = Regex::new(&format!(
+            r#"(?m)(uses:\s*["']?)((?i:{})(?:/[^@\s"']*)?)@([^\s"'#]+)(["']?)([ \t]*#[^\n]*)?"#,
+            regex::escape(&request.package_name)
+        ))
+        .expect("valid pattern");
+
+        let mut changes = Vec::new();
+        for (path, content) in workflows {
+            let mut missing_commit = false;
+            let updated = step
+                .replace_all(content, |caps: &Captures| {
+                    let reference = &caps[3];
+                    let comment = caps.get(5).map_or("", |m| m.as_str());
+                    let (new_reference, new_comment) = match &commit {
+                        Some(commit) if is_commit_sha(reference) => {
+                            (commit.clone(), version_comment(comment, tag))
+                        }
+                        None if is_commit_sha(reference) => {
+                            missing_commit = true;
+                            return caps[0].to_string();
+                        }
+                        // The comment records the exact release, not the moving tag
+                        Some(commit) if pin => {
+                            let prefix = if reference.starts_with('v') { "v" } else { "" };
+                            (commit.clone(), format!(" # {}{}", prefix, tag))
+                        }
+                        _ => (retag(reference, tag), comment.to_string()),
+                    };
+                    format!(
+                        "{}{}@{}{}{}",
+                        &caps[1], &caps[2], new_reference, &caps[4], new_comment
+                    )
+                })
+                .into_owned();
+            if missing_commit {
+                return Err(validation(format!(
+                    "{} pins {} by commit SHA; supply the target commit in '{}'",
+                    path, request.package_name, GITHUB_ACTIONS_SHA_METADATA_KEY
+                )));
+            }
+            if updated != content {
+                changes.push(modified(path, updated));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+fn validation(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Validation,
+    }
+}
+
+fn workflow_files(request: &UpgradeRequest) -> Vec<(&str, &str)> {
+    let mut files: Vec<(&str, &str)> = request
+        .manifests
+        .iter()
+        .filter(|(path, _)| {
+            let in_workflows =
+                path.starts_with(".github/workflows/") || path.contains("/.github/workflows/");
+            in_workflows && (path.ends_with(".yml") || path.ends_with(".yaml"))
+        })
+        .map(|(path, content)| (path.as_str(), content.as_str()))
+        .collect();
+    files.sort();
+    files
+}
+
+fn is_commit_sha(reference: &str) -> bool {
+    reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Moves a tag ref to `tag`, keeping its `v` prefix and number of
+/// components (`v3` -> `v4`, `v3.1` -> `v4.2`). Branch refs are left alone.
+fn retag(reference: &str, tag: &str) -> String {
+    let (prefix, version) = match reference.strip_prefix('v') {
+        Some(version) => ("v", version),
+        None => ("", reference),
+    };
+    if version.is_empty() || !version.split('.').all(|c| c.parse::<u64>().is_ok()) {
+        return reference.to_string();
+    }
+    let precision = version.split('.').count();
+    let components: Vec<&str> = tag
+        .split(['-', '+'])
+        .next()
+        .unwrap_or(tag)
+        .split('.')
+        .collect();
+    if precision >= components.len() {
+        return format!("{}{}", prefix, tag);
+    }
+    format!("{}{}", prefix, components[..precision].join("."))
+}
+
+// SHA pins conventionally note the tag they correspond to (`# v4.1.1`)
+fn version_comment(comment: &str, tag: &str) -> String {
+    match comment.split_once('#') {
+        Some((leading, text))
+            if text.trim().is_empty()
+                || text
+                    .trim()
+                    .starts_with(|c: char| c == 'v' || c.is_ascii_digit()) =>
+        {
+            format!("{}# {}", leading, retag(text.trim(), tag))
+        }
+        Some(_) => comment.to_string(),
+        None => format!(" # v{}", tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    const CI: &str = r#"jobs:
+  build:
+    steps:
+      - uses: actions/checkout@v3
+      - uses: "actions/setup-node@v3.8.1"
+      - uses: github/codeql-action/init@v2
+      - uses: actions/cache@88522ab9f39a2ea568f7027eddc7d8d8bc9d59c8 # v3.3.1
+      - uses: actions/checkout-extra@v1
+      - uses: ./local-action
+"#;
+
+    fn request(package: &str, metadata: &[(&str, serde_json::Value)]) -> UpgradeRequest {
+        UpgradeRequest {
+            ecosystem: "github-actions".to_string(),
+            package_name: package.to_string(),
+            metadata: metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+            manifests: HashMap::from([
+                (".github/workflows/ci.yml".to_string(), CI.to_string()),
+                ("docs/example.yml".to_string(), CI.to_string()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn upgrade(request: &UpgradeRequest, target: &str) -> Result<Vec<Change>, UpgradeError> {
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("v3.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        GitHubActions.generate_changes(request, &versions)
+    }
+
+    const SHA: &str = "b4ffde65f46336ab88eb53be808477a3936bae11";
+
+    #[test]
+    fn test_tag_refs() {
+        let checkout = upgrade(&request("actions/checkout", &[]), "v4.1.1").unwrap();
+        assert_eq!(checkout.len(), 1);
+        assert_eq!(checkout[0].file_path, ".github/workflows/ci.yml");
+        assert!(checkout[0].content.contains("actions/checkout@v4\n"));
+        assert!(checkout[0].content.contains("actions/checkout-extra@v1"));
+
+        let node = upgrade(&request("actions/setup-node", &[]), "v4.0.2").unwrap();
+        assert!(node[0].content.contains(r#""actions/setup-node@v4.0.2""#));
+
+        let codeql = upgrade(&request("github/codeql-action", &[]), "v3.24.0").unwrap();
+        assert!(codeql[0].content.contains("github/codeql-action/init@v3\n"));
+    }
+
+    #[test]
+    fn test_sha_pins() {
+        let without_commit = upgrade(&request("actions/cache", &[]), "v4.0.0");
+        assert!(matches!(
+            without_commit,
+            Err(UpgradeError {
+                error_type: ErrorType::Validation,
+                ..
+            })
+        ));
+
+        let cache = request(
+            "actions/cache",
+            &[(GITHUB_ACTIONS_SHA_METADATA_KEY, json!(SHA))],
+        );
+        let changes = upgrade(&cache, "v4.0.0").unwrap();
+        assert!(changes[0]
+            .content
+            .contains(&format!("actions/cache@{} # v4.0.0", SHA)));
+
+        let pinned = request(
+            "actions/checkout",
+            &[
+                (GITHUB_ACTIONS_SHA_METADATA_KEY, json!(SHA)),
+                (GITHUB_ACTIONS_PIN_METADATA_KEY, json!(true)),
+            ],
+        );
+        let changes = upgrade(&pinned, "v4.1.1").unwrap();
+        assert!(changes[0]
+            .content
+            .contains(&format!("actions/checkout@{} # v4.1.1\n", SHA)));
+    }
+}
@@ -0,0 +1,386 @@
+use super::{
+    lockfile_refresh, manifest_required, manifests_matching, manifests_named, modified, Ecosystem,
+};
+use crate::version::ResolvedVersions;
+use crate::{Change, RiskAssessment, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Go modules: `require` directives in `go.mod` (single-line and blocks),
+/// `replace` directives pinning the same module, a `go.sum` refresh, and
+/// import path rewrites in `.go` files when the major version suffix
+/// (`/v2`, or `.v3` for gopkg.in) changes.
+pub struct Go;
+
+fn directive_line_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(\s*(?:require\s+)?)(\S+)(\s+)(\S+)(.*)$").expect("valid pattern")
+    })
+}
+
+impl Ecosystem for Go {
+    fn name(&self) -> &'static str {
+        "go"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.95
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let old_path = request.package_name.as_str();
+        let new_path = target_path(old_path, versions.target.major());
+        let version = format!("v{}", versions.target.as_str().trim_start_matches('v'));
+
+        let go_mods = manifests_named(request, &["go.mod"]);
+        if go_mods.is_empty() {
+            return Err(manifest_required("go.mod"));
+        }
+
+        let mut changes = Vec::new();
+        for (path, content) in go_mods {
+            let updated = update_go_mod(content, old_path, &new_path, &version);
+            if updated == content {
+                continue;
+            }
+            let mut change = modified(path, updated);
+            if let Some(replacement) = replacement(content, old_path) {
+                change
+                    .metadata
+                    .insert("replaced_by".to_string(), serde_json::json!(replacement));
+            }
+            changes.push(change);
+            let module = format!("{}@{}", new_path, version);
+            changes.push(lockfile_refresh(
+                &format!("{}go.sum", path.trim_end_matches("go.mod")),
+                &[&["go", "get", &module], &["go", "mod", "tidy"]],
+            ));
+        }
+
+        // A new major version is a different module path for every importer
+        if new_path != old_path {
+            for (path, content) in manifests_matching(request, |name| name.ends_with(".go")) {
+                let updated = rewrite_imports(content, old_path, &new_path);
+                if updated != content {
+                    changes.push(modified(path, updated));
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        let old_path = request.package_name.as_str();
+        let new_path = target_path(old_path, versions.target.major());
+        if new_path != old_path {
+            assessment.breaking_changes = true;
+            assessment.explanations.push(format!(
+                "The module path changes from {} to {}; every import must be updated",
+                old_path, new_path
+            ));
+        }
+
+        for (path, content) in manifests_named(request, &["go.mod"]) {
+            if let Some(replacement) = replacement(content, old_path) {
+                assessment.explanations.push(format!(
+                    "{} replaces {} with {}, so the required version is not what gets built",
+                    path, old_path, replacement
+                ));
+            }
+        }
+    }
+}
+
+/// Splits the major version suffix off a module path:
+/// `github.com/a/b/v2` -> (`github.com/a/b`, 2), `gopkg.in/yaml.v3` ->
+/// (`gopkg.in/yaml`, 3). Paths without a suffix are major 0 or 1.
+fn split_major(path: &str) -> (&str, Option<u64>) {
+    let separator = if path.starts_with("gopkg.in/") {
+        ".v"
+    } else {
+        "/v"
+    };
+    match path.rsplit_once(separator) {
+        Some((base, major)) => match major.parse::<u64>() {
+            Ok(major) if major >= 2 || separator == ".v" => (base, Some(major)),
+            _ => (path, None),
+        },
+        None => (path, None),
+    }
+}
+
+fn target_path(path: &str, major: u64) -> String {
+    let (base, current) = split_major(path);
+    if path.starts_with("gopkg.in/") {
+        return match current {
+            Some(_) => format!("{}.v{}", base, major),
+            None => path.to_string(),
+        };
+    }
+    if major >= 2 {
+        format!("{}/v{}", base, major)
+    } else {
+        base.to_string()
+    }
+}
+
+fn update_go_mod(content: &str, old_path: &str, new_path: &str, version: &str) -> String {
+    let mut block: Option<&str> = None;
+    let mut updated = String::with_capacity(content.len());
+
+    for line in content.split_inclusive('\n') {
+        let (text, ending) = match line.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (line, ""),
+        };
+        let trimmed = text.trim_start();
+
+        if block.is_some() && trimmed.starts_with(')') {
+            block = None;
+        } else if let Some((directive, rest)) = trimmed.split_once(char::is_whitespace) {
+            if rest.trim() == "(" {
+                block = Some(directive);
+                updated.push_str(line);
+                continue;
+            }
+        }
+
+        let directive = match block {
+            Some(directive) => directive,
+            None => trimmed.split_whitespace().next().unwrap_or(""),
+        };
+        let rewritten = match directive {
+            "require" => rewrite_require(text, old_path, new_path, version),
+            "replace" => rewrite_replace(text, old_path, new_path, version),
+            _ => None,
+        };
+        match rewritten {
+            Some(rewritten) => {
+                updated.push_str(&rewritten);
+                updated.push_str(ending);
+            }
+            None => updated.push_str(line),
+        }
+    }
+    updated
+}
+
+fn rewrite_require(line: &str, old_path: &str, new_path: &str, version: &str) -> Option<String> {
+    let caps = directive_line_pattern().captures(line)?;
+    if &caps[2] != old_path {
+        return None;
+    }
+    // Modules without a go.mod keep their path past v1 and are marked instead
+    let (path, version) = if caps[4].ends_with("+incompatible") && split_major(old_path).1.is_none()
+    {
+        (old_path.to_string(), format!("{}+incompatible", version))
+    } else {
+        (new_path.to_string(), version.to_string())
+    };
+    Some(format!(
+        "{}{}{}{}{}",
+        &caps[1], path, &caps[3], version, &caps[5]
+    ))
+}
+
+/// Same-module replacements (`path => path v1.2.3`) pin the version that is
+/// actually built, so they move with the requirement.
+fn rewrite_replace(line: &str, old_path: &str, new_path: &str, version: &str) -> Option<String> {
+    let (left, right) = line.split_once("=>")?;
+    let right_tokens: Vec<&str> = right.split_whitespace().collect();
+    let replaced = left.split_whitespace().find(|token| *token != "replace") == Some(old_path);
+    if !replaced || right_tokens.len() != 2 || right_tokens[0] != old_path {
+        return None;
+    }
+    let left = left.replacen(old_path, new_path, 1);
+    Some(format!(
+        "{}=>{}",
+        left,
+        right
+            .replacen(right_tokens[1], version, 1)
+            .replacen(old_path, new_path, 1)
+    ))
+}
+
+/// Where a `replace` directive redirects the module: a local directory or
+/// another module (`path version`). Same-module version pins are not
+/// reported.
+fn replacement(content: &str, old_path: &str) -> Option<String> {
+    let mut in_block = false;
+    for line in content.lines() {
+        let text = line.split("//").next().unwrap_or(line).trim();
+        let directive = if in_block {
+            if text.starts_with(')') {
+                in_block = false;
+                continue;
+            }
+            text
+        } else if let Some(rest) = text.strip_prefix("replace") {
+            if rest.trim() == "(" {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+
+        let Some((left, right)) = directive.split_once("=>") else {
+            continue;
+        };
+        if left.split_whitespace().next() != Some(old_path) {
+            continue;
+        }
+        let right = right.split_whitespace().collect::<Vec<_>>();
+        if right.first() != Some(&old_path) {
+            return Some(right.join(" "));
+        }
+    }
+    None
+}
+
+// Rewrites `"old/path"` and `"old/path/sub"` import strings, leaving other
+// major versions of the same module alone.
+fn rewrite_imports(content: &str, old_path: &str, new_path: &str) -> String {
+    let import =
+        Regex::new(&format!(r#""{}(/[^"]*)?""#, regex::escape(old_path))).expect("valid pattern");
+    let other_major = Regex::new(r"^/v\d+(/|$)").expect("valid pattern");
+    import
+        .replace_all(content, |caps: &Captures| {
+            let subpackage = caps.get(1).map_or("", |m| m.as_str());
+            if other_major.is_match(subpackage) {
+                return caps[0].to_string();
+            }
+            format!(r#""{}{}""#, new_path, subpackage)
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const GO_MOD: &str = "module example.com/app
+
+go 1.21
+
+require github.com/spf13/cobra v1.7.0
+
+require (
+\tgithub.com/go-chi/chi v1.5.4
+\tgithub.com/go-chi/chi/v5 v5.0.10
+\tgithub.com/docker/docker v20.10.24+incompatible // indirect
+\tgopkg.in/yaml.v2 v2.4.0
+\tgithub.com/pkg/errors v0.9.1
+)
+
+replace github.com/go-chi/chi => github.com/go-chi/chi v1.5.5
+
+replace (
+\tgithub.com/pkg/errors => ../errors
+)
+";
+
+    const MAIN_GO: &str = r#"package main
+
+import (
+	"github.com/go-chi/chi"
+	"github.com/go-chi/chi/middleware"
+	v5 "github.com/go-chi/chi/v5"
+)
+"#;
+
+    fn request(package: &str) -> UpgradeRequest {
+        UpgradeRequest {
+            ecosystem: "go".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                ("go.mod".to_string(), GO_MOD.to_string()),
+                ("cmd/main.go".to_string(), MAIN_GO.to_string()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn versions(current: &str, target: &str) -> ResolvedVersions {
+        ResolvedVersions {
+            current: SemanticScheme.parse(current).unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        }
+    }
+
+    fn upgrade(package: &str, current: &str, target: &str) -> Vec<Change> {
+        Go.generate_changes(&request(package), &versions(current, target))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_require_directives() {
+        let cobra = upgrade("github.com/spf13/cobra", "v1.7.0", "v1.8.0");
+        assert_eq!(cobra.len(), 2);
+        assert!(cobra[0]
+            .content
+            .contains("require github.com/spf13/cobra v1.8.0\n"));
+        assert_eq!(cobra[1].file_path, "go.sum");
+        assert_eq!(
+            cobra[1].metadata["argv"],
+            serde_json::json!([
+                ["go", "get", "github.com/spf13/cobra@v1.8.0"],
+                ["go", "mod", "tidy"]
+            ])
+        );
+
+        let docker = upgrade("github.com/docker/docker", "20.10.24", "24.0.7");
+        assert!(docker[0]
+            .content
+            .contains("\tgithub.com/docker/docker v24.0.7+incompatible // indirect\n"));
+
+        let yaml = upgrade("gopkg.in/yaml.v2", "v2.4.0", "v3.0.1");
+        assert!(yaml[0].content.contains("\tgopkg.in/yaml.v3 v3.0.1\n"));
+
+        let errors = upgrade("github.com/pkg/errors", "v0.9.1", "v0.9.2");
+        assert_eq!(errors[0].metadata["replaced_by"], "../errors");
+    }
+
+    #[test]
+    fn test_major_version_path() {
+        let chi = upgrade("github.com/go-chi/chi", "v1.5.4", "v2.0.0");
+        let go_mod = &chi[0].content;
+        assert!(go_mod.contains("\tgithub.com/go-chi/chi/v2 v2.0.0\n"));
+        assert!(go_mod.contains("\tgithub.com/go-chi/chi/v5 v5.0.10\n"));
+        assert!(go_mod
+            .contains("replace github.com/go-chi/chi/v2 => github.com/go-chi/chi/v2 v2.0.0\n"));
+
+        let main = chi.iter().find(|c| c.file_path == "cmd/main.go").unwrap();
+        assert!(main.content.contains("\t\"github.com/go-chi/chi/v2\"\n"));
+        assert!(main
+            .content
+            .contains("\"github.com/go-chi/chi/v2/middleware\""));
+        assert!(main.content.contains("v5 \"github.com/go-chi/chi/v5\""));
+
+        let mut assessment = RiskAssessment {
+            risk_level: crate::RiskLevel::High,
+            version_jump: crate::version::VersionJump::Major,
+            ..Default::default()
+        };
+        Go.assess_risk(
+            &request("github.com/go-chi/chi"),
+            &versions("v1.5.4", "v2.0.0"),
+            &mut assessment,
+        );
+        assert!(assessment.breaking_changes);
+        assert!(assessment.explanations[0].contains("github.com/go-chi/chi/v2"));
+    }
+}
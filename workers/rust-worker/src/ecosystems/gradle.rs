@@ -0,0 +1,275 @@
+use super::{
+    coordinates, manifest_required, manifests_named, modified, parse_toml, set_string, Ecosystem,
+};
+use crate::version::{MavenScheme, ResolvedVersions, VersionScheme};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::Regex;
+use toml_edit::Item;
+
+/// Gradle builds: dependency coordinates in `build.gradle` and
+/// `build.gradle.kts`, and version catalogs (`libs.versions.toml`).
+/// Packages are named `group:artifact`.
+pub struct Gradle;
+
+const BUILD_SCRIPTS: &[&str] = &["build.gradle", "build.gradle.kts"];
+const VERSION_CATALOG: &str = "libs.versions.toml";
+
+// Rich version constraints in catalogs (`{ strictly = "1.0" }`)
+const RICH_VERSION_KEYS: &[&str] = &["strictly", "require", "prefer"];
+
+impl Ecosystem for Gradle {
+    fn name(&self) -> &'static str {
+        "gradle"
+    }
+
+    fn compatibility(&self, request: &UpgradeRequest) -> f64 {
+        match MavenScheme.parse(&request.target_version) {
+            Ok(target) if target.is_prerelease() => 0.7,
+            _ => 0.85,
+        }
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let (group, artifact) = coordinates("Gradle", &request.package_name)?;
+        let target = versions.target.as_str();
+
+        let mut files = manifests_named(request, BUILD_SCRIPTS);
+        files.extend(manifests_named(request, &[VERSION_CATALOG]));
+        if files.is_empty() {
+            return Err(manifest_required("build.gradle.kts"));
+        }
+
+        let mut changes = Vec::new();
+        for (path, content) in files {
+            let updated = if path.ends_with(VERSION_CATALOG) {
+                update_catalog(path, content, group, artifact, target)?
+            } else {
+                update_build_script(content, group, artifact, target)
+            };
+            if updated != content {
+                changes.push(modified(path, updated));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Rewrites `"group:artifact:version"` strings and map notation
+/// (`group: 'g', name: 'a', version: 'v'`). Interpolated versions
+/// (`"g:a:$junitVersion"`) update the variable's assignment instead.
+fn update_build_script(content: &str, group: &str, artifact: &str, target: &str) -> String {
+    let coordinate = Regex::new(&format!(
+        r#"(["']{}:{}:)([^"':@]+)"#,
+        regex::escape(group),
+        regex::escape(artifact)
+    ))
+    .expect("valid pattern");
+    let map_notation = Regex::new(&format!(
+        r#"(group\s*[:=]\s*["']{}["']\s*,\s*name\s*[:=]\s*["']{}["']\s*,\s*version\s*[:=]\s*["'])([^"']+)"#,
+        regex::escape(group),
+        regex::escape(artifact)
+    ))
+    .expect("valid pattern");
+
+    let mut variables = Vec::new();
+    let mut updated = content.to_string();
+    for pattern in [&coordinate, &map_notation] {
+        updated = pattern
+            .replace_all(&updated, |caps: &regex::Captures| {
+                let written = &caps[2];
+                match interpolated_variable(written) {
+                    Some(variable) => {
+                        variables.push(variable.to_string());
+                        format!("{}{}", &caps[1], written)
+                    }
+                    None => format!("{}{}", &caps[1], target),
+                }
+            })
+            .into_owned();
+    }
+
+    for variable in variables {
+        let assignment = Regex::new(&format!(
+            r#"(\b{}\s*=\s*["'])([^"']+)(["'])"#,
+            regex::escape(&variable)
+        ))
+        .expect("valid pattern");
+        updated = assignment
+            .replace_all(&updated, |caps: &regex::Captures| {
+                format!("{}{}{}", &caps[1], target, &caps[3])
+            })
+            .into_owned();
+    }
+    updated
+}
+
+fn interpolated_variable(written: &str) -> Option<&str> {
+    let variable = written.strip_prefix('$')?;
+    Some(
+        variable
+            .strip_prefix('{')
+            .and_then(|v| v.strip_suffix('}'))
+            .unwrap_or(variable),
+    )
+}
+
+/// Updates `[libraries]` entries for the package, following `version.ref`
+/// into `[versions]`.
+fn update_catalog(
+    path: &str,
+    content: &str,
+    group: &str,
+    artifact: &str,
+    target: &str,
+) -> Result<String, UpgradeError> {
+    let mut doc = parse_toml(path, content)?;
+    let module = format!("{}:{}", group, artifact);
+
+    let mut refs = Vec::new();
+    if let Some(libraries) = doc.get_mut("libraries").and_then(Item::as_table_like_mut) {
+        for (_, library) in libraries.iter_mut() {
+            if let Some(notation) = library.as_str() {
+                if notation
+                    .strip_prefix(&module)
+                    .is_some_and(|rest| rest.starts_with(':'))
+                {
+                    set_string(library, &format!("{}:{}", module, target));
+                }
+                continue;
+            }
+
+            let Some(table) = library.as_table_like_mut() else {
+                continue;
+            };
+            let declared = table
+                .get("module")
+                .and_then(Item::as_str)
+                .map(str::to_string)
+                .or_else(|| {
+                    let group = table.get("group")?.as_str()?;
+                    let name = table.get("name")?.as_str()?;
+                    Some(format!("{}:{}", group, name))
+                });
+            if declared.as_deref() != Some(module.as_str()) {
+                continue;
+            }
+
+            match table.get_mut("version") {
+                Some(version) if version.is_str() => set_string(version, target),
+                Some(version) => {
+                    if let Some(reference) = version.get("ref").and_then(Item::as_str) {
+                        refs.push(reference.to_string());
+                    } else {
+                        set_rich_version(version, target);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let Some(versions) = doc.get_mut("versions").and_then(Item::as_table_like_mut) {
+        for reference in refs {
+            match versions.get_mut(&reference) {
+                Some(version) if version.is_str() => set_string(version, target),
+                Some(version) => set_rich_version(version, target),
+                None => {}
+            }
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+fn set_rich_version(version: &mut Item, target: &str) {
+    for key in RICH_VERSION_KEYS {
+        if let Some(item) = version.get_mut(key) {
+            set_string(item, target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn upgrade(files: &[(&str, &str)], package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "gradle".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: MavenScheme.parse("1.0").unwrap(),
+            target: MavenScheme.parse(target).unwrap(),
+        };
+        Gradle.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_build_script_coordinates() {
+        let script = r#"
+def okhttpVersion = "4.11.0"
+dependencies {
+    implementation("com.squareup.okhttp3:okhttp:$okhttpVersion")
+    implementation 'com.google.guava:guava:31.1-jre'
+    testImplementation group: 'junit', name: 'junit', version: '4.12'
+}
+"#;
+        let files = [("app/build.gradle", script)];
+
+        let okhttp = upgrade(&files, "com.squareup.okhttp3:okhttp", "4.12.0");
+        assert!(okhttp[0]
+            .content
+            .contains(r#"def okhttpVersion = "4.12.0""#));
+        assert!(okhttp[0].content.contains("okhttp:$okhttpVersion"));
+
+        let guava = upgrade(&files, "com.google.guava:guava", "33.0.0-jre");
+        assert!(guava[0]
+            .content
+            .contains("'com.google.guava:guava:33.0.0-jre'"));
+
+        let junit = upgrade(&files, "junit:junit", "4.13.2");
+        assert!(junit[0].content.contains("version: '4.13.2'"));
+    }
+
+    #[test]
+    fn test_version_catalog() {
+        let catalog = r#"[versions]
+kotlin = "1.9.10" # compiler
+room = { strictly = "2.5.2" }
+
+[libraries]
+kotlin-stdlib = { module = "org.jetbrains.kotlin:kotlin-stdlib", version.ref = "kotlin" }
+room-runtime = { group = "androidx.room", name = "room-runtime", version.ref = "room" }
+coil = "io.coil-kt:coil:2.4.0"
+"#;
+        let files = [("gradle/libs.versions.toml", catalog)];
+
+        let kotlin = upgrade(&files, "org.jetbrains.kotlin:kotlin-stdlib", "1.9.22");
+        assert_eq!(kotlin[0].file_path, "gradle/libs.versions.toml");
+        assert!(kotlin[0]
+            .content
+            .contains(r#"kotlin = "1.9.22" # compiler"#));
+
+        let room = upgrade(&files, "androidx.room:room-runtime", "2.6.1");
+        assert!(room[0].content.contains(r#"room = { strictly = "2.6.1" }"#));
+
+        let coil = upgrade(&files, "io.coil-kt:coil", "2.5.0");
+        assert!(coil[0]
+            .content
+            .contains(r#"coil = "io.coil-kt:coil:2.5.0""#));
+
+        assert!(upgrade(&files, "io.ktor:ktor-client", "2.3.7").is_empty());
+    }
+}
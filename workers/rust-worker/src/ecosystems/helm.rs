@@ -0,0 +1,136 @@
+use super::{lockfile_refresh, manifest_required, manifests_named, modified, yaml, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::ResolvedVersions;
+use crate::{Change, UpgradeError, UpgradeRequest};
+
+/// Helm charts: subchart entries in the `dependencies` list of `Chart.yaml`
+/// (or `requirements.yaml` for `apiVersion: v1` charts), matched by `name`,
+/// followed by a `Chart.lock`/`requirements.lock` refresh.
+pub struct Helm;
+
+const MANIFESTS: &[(&str, &str)] = &[
+    ("Chart.yaml", "Chart.lock"),
+    ("requirements.yaml", "requirements.lock"),
+];
+
+impl Ecosystem for Helm {
+    fn name(&self) -> &'static str {
+        "helm"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let names: Vec<&str> = MANIFESTS.iter().map(|(manifest, _)| *manifest).collect();
+        let manifests = manifests_named(request, &names);
+        if manifests.is_empty() {
+            return Err(manifest_required("Chart.yaml"));
+        }
+
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let scalars = yaml::scalars(content);
+            let subcharts: Vec<&String> = scalars
+                .iter()
+                .filter(|entry| {
+                    entry.is_at(&["dependencies", "*", "name"])
+                        && entry.value == request.package_name
+                })
+                .map(|entry| &entry.path[1])
+                .collect();
+            let edits = scalars
+                .iter()
+                .filter(|entry| {
+                    entry.is_at(&["dependencies", "*", "version"])
+                        && subcharts.contains(&&entry.path[1])
+                })
+                .map(|entry| {
+                    entry.set(&rewrite_requirement("helm", &entry.value, &versions.target))
+                })
+                .collect();
+            let updated = yaml::apply(content, edits);
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+
+            let (directory, file_name) = match path.rsplit_once('/') {
+                Some((directory, file_name)) => (&path[..directory.len() + 1], file_name),
+                None => ("", path),
+            };
+            let lock = MANIFESTS
+                .iter()
+                .find(|(manifest, _)| *manifest == file_name)
+                .map(|(_, lock)| format!("{}{}", directory, lock));
+            if let Some(lock_path) = lock.filter(|lock| request.manifests.contains_key(lock)) {
+                changes.push(lockfile_refresh(
+                    &lock_path,
+                    &[&["helm", "dependency", "update"]],
+                ));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const CHART: &str = r#"apiVersion: v2
+name: platform
+version: 1.4.0
+dependencies:
+  # Cache
+  - name: redis
+    version: "~17.3.0"
+    repository: https://charts.bitnami.com/bitnami
+    condition: redis.enabled
+  - name: postgresql
+    version: 12.1.2
+    repository: oci://registry-1.docker.io/bitnamicharts
+"#;
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "helm".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                ("charts/platform/Chart.yaml".to_string(), CHART.to_string()),
+                ("charts/platform/Chart.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        Helm.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_chart_dependencies() {
+        let redis = upgrade("redis", "18.6.1");
+        assert_eq!(
+            redis[0].content,
+            CHART.replace("\"~17.3.0\"", "\"~18.6.1\"")
+        );
+        assert_eq!(redis[1].file_path, "charts/platform/Chart.lock");
+        assert_eq!(redis[1].metadata["command"], "helm dependency update");
+
+        let postgresql = upgrade("postgresql", "13.2.24");
+        assert!(postgresql[0].content.contains("    version: 13.2.24\n"));
+        // The chart's own version is not a dependency
+        assert!(postgresql[0].content.contains("version: 1.4.0\n"));
+
+        assert!(upgrade("mysql", "9.0.0").is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use super::{lockfile_refresh, manifest_required, manifests_named, modified, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::ResolvedVersions;
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+
+/// Elixir projects: dependency tuples in `mix.exs`
+/// (`{:phoenix, "~> 1.6", only: :dev}`), followed by a `mix.lock` refresh.
+pub struct Hex;
+
+impl Ecosystem for Hex {
+    fn name(&self) -> &'static str {
+        "hex"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let manifests = manifests_named(request, &["mix.exs"]);
+        if manifests.is_empty() {
+            return Err(manifest_required("mix.exs"));
+        }
+
+        let dependency = Regex::new(&format!(
+            r#"(\{{\s*:{}\s*,\s*")([^"]*)(")"#,
+            regex::escape(&request.package_name)
+        ))
+        .expect("valid pattern");
+
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let updated = dependency
+                .replace_all(content, |caps: &Captures| {
+                    format!(
+                        "{}{}{}",
+                        &caps[1],
+                        rewrite_requirement("hex", &caps[2], &versions.target),
+                        &caps[3]
+                    )
+                })
+                .into_owned();
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+            changes.push(lockfile_refresh(
+                &format!("{}mix.lock", path.trim_end_matches("mix.exs")),
+                &[&["mix", "deps.update", &request.package_name]],
+            ));
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const MIX: &str = r#"defp deps do
+    [
+      {:phoenix, "~> 1.6"},
+      {:ecto_sql, "~> 3.10.1"},
+      {:jason, ">= 1.0.0 and < 2.0.0"},
+      {:credo, "~> 1.7", only: [:dev, :test], runtime: false},
+      {:my_lib, git: "https://github.com/acme/my_lib.git"}
+    ]
+  end
+"#;
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "hex".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([("apps/web/mix.exs".to_string(), MIX.to_string())]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        Hex.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_dependency_tuples() {
+        let phoenix = upgrade("phoenix", "1.7.10");
+        assert!(phoenix[0].content.contains(r#"{:phoenix, "~> 1.7"}"#));
+        assert_eq!(phoenix[1].file_path, "apps/web/mix.lock");
+        assert_eq!(
+            phoenix[1].metadata["argv"],
+            serde_json::json!([["mix", "deps.update", "phoenix"]])
+        );
+
+        let ecto = upgrade("ecto_sql", "3.11.1");
+        assert!(ecto[0].content.contains(r#"{:ecto_sql, "~> 3.11.1"}"#));
+
+        let jason = upgrade("jason", "2.1.0");
+        assert!(jason[0].content.contains(r#"{:jason, "~> 2.1"}"#));
+
+        // Already covered by the written requirement
+        assert!(upgrade("credo", "1.7.3").is_empty());
+        let credo = upgrade("credo", "1.8.0");
+        assert!(credo[0]
+            .content
+            .contains(r#"{:credo, "~> 1.8", only: [:dev, :test], runtime: false}"#));
+
+        assert!(upgrade("my_lib", "1.0.0").is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+use super::{
+    lockfile_refresh, manifest_required, manifests_named, modified, parse_toml, set_string,
+    Ecosystem,
+};
+use crate::version::{ParsedVersion, ResolvedVersions, VersionJump};
+use crate::{Change, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+use toml_edit::Item;
+
+/// Lean 4 projects: the `lean-toolchain` pin (package `lean4`) and Lake
+/// dependency revs in `lakefile.lean` / `lakefile.toml`, e.g. mathlib tags.
+pub struct Lean;
+
+const TOOLCHAIN_FILE: &str = "lean-toolchain";
+const LAKE_MANIFEST: &str = "lake-manifest.json";
+
+fn require_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"(?m)^require\b").expect("valid pattern"))
+}
+
+// `@ "v4.9.0"` or `@ git "v4.9.0"`; `@[attr]` does not match
+fn rev_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"(@\s*(?:git\s+)?")([^"]+)(")"#).expect("valid pattern"))
+}
+
+fn is_toolchain(package: &str) -> bool {
+    matches!(package, "lean" | "lean4" | "leanprover/lean4")
+}
+
+impl Ecosystem for Lean {
+    fn name(&self) -> &'static str {
+        "lean"
+    }
+
+    fn compatibility(&self, request: &UpgradeRequest) -> f64 {
+        if is_toolchain(&request.package_name) {
+            0.6
+        } else {
+            0.75
+        }
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        if is_toolchain(&request.package_name) {
+            return update_toolchain(request, &versions.target);
+        }
+
+        let lakefiles = manifests_named(request, &["lakefile.lean", "lakefile.toml"]);
+        if lakefiles.is_empty() {
+            return Err(manifest_required("lakefile.toml"));
+        }
+
+        let mut changes = Vec::new();
+        for (path, content) in lakefiles {
+            let updated = if path.ends_with(".toml") {
+                update_lakefile_toml(path, content, &request.package_name, &versions.target)?
+            } else {
+                update_lakefile_lean(content, &request.package_name, &versions.target)
+            };
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+
+            let manifest = format!("{}{}", path.trim_end_matches(|c| c != '/'), LAKE_MANIFEST);
+            if request.manifests.contains_key(&manifest) {
+                changes.push(lockfile_refresh(
+                    &manifest,
+                    &[&["lake", "update", &request.package_name]],
+                ));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Lean makes no compatibility promises between toolchain releases, so
+    /// anything beyond a patch bump is treated as breaking.
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        if is_toolchain(&request.package_name) {
+            if !matches!(
+                assessment.version_jump,
+                VersionJump::None | VersionJump::Patch
+            ) {
+                assessment.breaking_changes = true;
+                assessment.risk_level = assessment.risk_level.max(RiskLevel::High);
+                assessment.explanations.push(format!(
+                    "Lean toolchain {} -> {} may break proofs, tactics and dependencies built for the old toolchain",
+                    versions.current, versions.target
+                ));
+            }
+        } else if !manifests_named(request, &[TOOLCHAIN_FILE]).is_empty() {
+            assessment.explanations.push(format!(
+                "{} {} may require a matching lean-toolchain",
+                request.package_name, versions.target
+            ));
+        }
+    }
+}
+
+// Keeps the written rev style: `v4.9.0` or a bare `4.9.0`
+fn rev(prefix: &str, target: &ParsedVersion) -> String {
+    format!("{}{}", prefix, target.as_str().trim_start_matches('v'))
+}
+
+fn written_prefix(written: &str) -> &'static str {
+    if written.starts_with(|c: char| c.is_ascii_digit()) {
+        ""
+    } else {
+        "v"
+    }
+}
+
+fn update_toolchain(
+    request: &UpgradeRequest,
+    target: &ParsedVersion,
+) -> Result<Vec<Change>, UpgradeError> {
+    let files = manifests_named(request, &[TOOLCHAIN_FILE]);
+    if files.is_empty() {
+        return Err(manifest_required(TOOLCHAIN_FILE));
+    }
+
+    Ok(files
+        .into_iter()
+        .filter_map(|(path, content)| {
+            let trimmed = content.trim_end();
+            let (origin, written) = trimmed.rsplit_once(':')?;
+            let updated = format!(
+                "{}:{}{}",
+                origin,
+                rev(written_prefix(written), target),
+                &content[trimmed.len()..]
+            );
+            (updated != content).then(|| modified(path, updated))
+        })
+        .collect())
+}
+
+/// Rewrites the rev of `require <name>` statements, both the
+/// `require name from git "url" @ "rev"` and the
+/// `require "scope" / "name" @ git "rev"` forms.
+fn update_lakefile_lean(content: &str, package: &str, target: &ParsedVersion) -> String {
+    let name = Regex::new(&format!(
+        r#"^require\s+(?:"[^"]*"\s*/\s*)?"?{}"?(\s|$)"#,
+        regex::escape(package)
+    ))
+    .expect("valid pattern");
+
+    let starts: Vec<usize> = require_pattern()
+        .find_iter(content)
+        .map(|m| m.start())
+        .collect();
+    let mut updated = content[..starts.first().copied().unwrap_or(content.len())].to_string();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(content.len());
+        let statement = &content[start..end];
+        if name.is_match(statement) {
+            updated.push_str(&rev_pattern().replace(statement, |caps: &Captures| {
+                format!(
+                    "{}{}{}",
+                    &caps[1],
+                    rev(written_prefix(&caps[2]), target),
+                    &caps[3]
+                )
+            }));
+        } else {
+            updated.push_str(statement);
+        }
+    }
+    updated
+}
+
+fn update_lakefile_toml(
+    path: &str,
+    content: &str,
+    package: &str,
+    target: &ParsedVersion,
+) -> Result<String, UpgradeError> {
+    let mut doc = parse_toml(path, content)?;
+    if let Some(requires) = doc
+        .get_mut("require")
+        .and_then(Item::as_array_of_tables_mut)
+    {
+        for require in requires.iter_mut() {
+            if require.get("name").and_then(Item::as_str) != Some(package) {
+                continue;
+            }
+            for key in ["rev", "version"] {
+                if let Some(item) = require.get_mut(key) {
+                    let written = item.as_str().unwrap_or_default().to_string();
+                    set_string(item, &rev(written_prefix(&written), target));
+                }
+            }
+        }
+    }
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const LAKEFILE_LEAN: &str = r#"import Lake
+open Lake DSL
+
+require mathlib from git
+  "https://github.com/leanprover-community/mathlib4" @ "v4.8.0"
+
+require "leanprover-community" / "batteries" @ git "v4.8.0"
+
+@[default_target]
+lean_lib Spec
+"#;
+
+    const LAKEFILE_TOML: &str = r#"name = "spec"
+
+[[require]]
+name = "mathlib"
+scope = "leanprover-community"
+rev = "v4.8.0"  # keep in sync with lean-toolchain
+"#;
+
+    fn request(package: &str, lakefile: (&str, &str)) -> UpgradeRequest {
+        UpgradeRequest {
+            ecosystem: "lean".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                (lakefile.0.to_string(), lakefile.1.to_string()),
+                (LAKE_MANIFEST.to_string(), String::new()),
+                (
+                    TOOLCHAIN_FILE.to_string(),
+                    "leanprover/lean4:v4.8.0\n".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn versions(target: &str) -> ResolvedVersions {
+        ResolvedVersions {
+            current: SemanticScheme.parse("v4.8.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        }
+    }
+
+    fn upgrade(package: &str, lakefile: (&str, &str), target: &str) -> Vec<Change> {
+        Lean.generate_changes(&request(package, lakefile), &versions(target))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_lakefile_revs() {
+        let mathlib = upgrade("mathlib", ("lakefile.lean", LAKEFILE_LEAN), "v4.9.0");
+        assert_eq!(mathlib.len(), 2);
+        assert!(mathlib[0].content.contains(r#"mathlib4" @ "v4.9.0""#));
+        assert!(mathlib[0].content.contains(r#"@ git "v4.8.0""#));
+        assert!(mathlib[0].content.contains("@[default_target]"));
+        assert_eq!(
+            mathlib[1].metadata["argv"],
+            serde_json::json!([["lake", "update", "mathlib"]])
+        );
+
+        let batteries = upgrade("batteries", ("lakefile.lean", LAKEFILE_LEAN), "v4.9.0");
+        assert!(batteries[0]
+            .content
+            .contains(r#""batteries" @ git "v4.9.0""#));
+
+        let toml = upgrade("mathlib", ("lakefile.toml", LAKEFILE_TOML), "v4.9.0");
+        assert!(toml[0]
+            .content
+            .contains(r#"rev = "v4.9.0"  # keep in sync with lean-toolchain"#));
+    }
+
+    #[test]
+    fn test_toolchain_jump() {
+        let toolchain = upgrade("lean4", ("lakefile.toml", LAKEFILE_TOML), "v4.9.0");
+        assert_eq!(toolchain.len(), 1);
+        assert_eq!(toolchain[0].content, "leanprover/lean4:v4.9.0\n");
+
+        let mut assessment = RiskAssessment {
+            risk_level: RiskLevel::Medium,
+            version_jump: VersionJump::Minor,
+            ..Default::default()
+        };
+        Lean.assess_risk(
+            &request("lean4", ("lakefile.toml", LAKEFILE_TOML)),
+            &versions("v4.9.0"),
+            &mut assessment,
+        );
+        assert!(assessment.breaking_changes);
+        assert_eq!(assessment.risk_level, RiskLevel::High);
+    }
+}
@@ -0,0 +1,197 @@
+use super::{coordinates, manifest_required, manifests_named, modified, Ecosystem};
+use crate::version::{MavenScheme, ResolvedVersions, VersionScheme};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Maven projects: `<dependency>` entries in `pom.xml`, including
+/// `<dependencyManagement>` and versions indirected through `<properties>`.
+/// Packages are named `groupId:artifactId`.
+pub struct Maven;
+
+fn dependency_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"(?s)<dependency>(.*?)</dependency>").expect("valid pattern"))
+}
+
+fn properties_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"(?s)<properties>(.*?)</properties>").expect("valid pattern"))
+}
+
+impl Ecosystem for Maven {
+    fn name(&self) -> &'static str {
+        "maven"
+    }
+
+    fn compatibility(&self, request: &UpgradeRequest) -> f64 {
+        // Qualified targets (-SNAPSHOT, -M1, -rc-2) are less likely to be drop-in
+        match MavenScheme.parse(&request.target_version) {
+            Ok(target) if target.is_prerelease() => 0.7,
+            _ => 0.85,
+        }
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let (group_id, artifact_id) = coordinates("Maven", &request.package_name)?;
+        let target = versions.target.as_str();
+        let poms = manifests_named(request, &["pom.xml"]);
+
+        if poms.is_empty() {
+            return Err(manifest_required("pom.xml"));
+        }
+
+        // Versions written as `${name}` are updated where the property is defined
+        let mut properties = Vec::new();
+        let mut updated: Vec<(&str, String)> = poms
+            .iter()
+            .map(|(path, content)| {
+                let edits = dependency_edits(content, group_id, artifact_id, &mut properties);
+                (*path, apply_edits(content, edits, target))
+            })
+            .collect();
+        for (_, content) in updated.iter_mut() {
+            let edits = property_edits(content, &properties);
+            *content = apply_edits(content, edits, target);
+        }
+
+        Ok(poms
+            .iter()
+            .zip(updated)
+            .filter(|((_, original), (_, content))| original != content)
+            .map(|(_, (path, content))| modified(path, content))
+            .collect())
+    }
+}
+
+// Byte range of the text inside the first `<tag>...</tag>` in `body`.
+fn tag_text(body: &str, tag: &str) -> Option<Range<usize>> {
+    let open = format!("<{}>", tag);
+    let start = body.find(&open)? + open.len();
+    let end = start + body[start..].find(&format!("</{}>", tag))?;
+    Some(start..end)
+}
+
+fn dependency_edits(
+    content: &str,
+    group_id: &str,
+    artifact_id: &str,
+    properties: &mut Vec<String>,
+) -> Vec<Range<usize>> {
+    let mut edits = Vec::new();
+    for block in dependency_pattern().captures_iter(content) {
+        let body = block.get(1).expect("dependency body");
+        let text =
+            |tag: &str| tag_text(body.as_str(), tag).map(|range| body.as_str()[range].trim());
+        if text("groupId") != Some(group_id) || text("artifactId") != Some(artifact_id) {
+            continue;
+        }
+
+        let Some(version) = tag_text(body.as_str(), "version") else {
+            continue;
+        };
+        let written = body.as_str()[version.clone()].trim();
+        match written.strip_prefix("${").and_then(|v| v.strip_suffix('}')) {
+            Some(property) => properties.push(property.to_string()),
+            None => edits.push(body.start() + version.start..body.start() + version.end),
+        }
+    }
+    edits
+}
+
+fn property_edits(content: &str, properties: &[String]) -> Vec<Range<usize>> {
+    properties_pattern()
+        .captures_iter(content)
+        .flat_map(|block| {
+            let body = block.get(1).expect("properties body");
+            properties.iter().filter_map(move |property| {
+                tag_text(body.as_str(), property)
+                    .map(|range| body.start() + range.start..body.start() + range.end)
+            })
+        })
+        .collect()
+}
+
+fn apply_edits(content: &str, mut edits: Vec<Range<usize>>, replacement: &str) -> String {
+    let mut content = content.to_string();
+    edits.sort_by_key(|range| std::cmp::Reverse(range.start));
+    edits.dedup();
+    for range in edits {
+        content.replace_range(range, replacement);
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const POM: &str = r#"<project>
+  <properties>
+    <junit.version>5.9.3</junit.version>
+  </properties>
+  <dependencyManagement>
+    <dependencies>
+      <dependency>
+        <groupId>com.google.guava</groupId>
+        <artifactId>guava</artifactId>
+        <version>31.1-jre</version>
+      </dependency>
+    </dependencies>
+  </dependencyManagement>
+  <dependencies>
+    <dependency>
+      <groupId>org.junit.jupiter</groupId>
+      <artifactId>junit-jupiter</artifactId>
+      <version>${junit.version}</version>
+      <scope>test</scope>
+    </dependency>
+  </dependencies>
+</project>
+"#;
+
+    fn upgrade(package: &str, current: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "maven".to_string(),
+            package_name: package.to_string(),
+            current_version: current.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([("pom.xml".to_string(), POM.to_string())]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: MavenScheme.parse(current).unwrap(),
+            target: MavenScheme.parse(target).unwrap(),
+        };
+        Maven.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_updates_managed_and_property_versions() {
+        let guava = upgrade("com.google.guava:guava", "31.1-jre", "33.0.0-jre");
+        assert_eq!(guava.len(), 1);
+        assert!(guava[0].content.contains("<version>33.0.0-jre</version>"));
+        assert!(guava[0]
+            .content
+            .contains("<junit.version>5.9.3</junit.version>"));
+
+        let junit = upgrade("org.junit.jupiter:junit-jupiter", "5.9.3", "5.10.1");
+        assert!(junit[0]
+            .content
+            .contains("<junit.version>5.10.1</junit.version>"));
+        assert!(junit[0]
+            .content
+            .contains("<version>${junit.version}</version>"));
+
+        assert!(upgrade("org.slf4j:slf4j-api", "2.0.9", "2.0.12").is_empty());
+        assert!(coordinates("Maven", "guava").is_err());
+    }
+}
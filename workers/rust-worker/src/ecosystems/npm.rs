@@ -0,0 +1,818 @@
+use super::pnpm::pnpm_catalog_changes;
+use super::{
+    glob_match, lockfile_refresh, manifest_required, manifests_named, mentions, modified,
+    object_body, unsupported_kind, Ecosystem,
+};
+use crate::rewrite::{npm_sections, rewrite_requirement};
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// npm packages: entries in `dependencies`, `devDependencies`,
+/// `peerDependencies`, `optionalDependencies` and `overrides` of every
+/// supplied `package.json`, edited in place so key order and indentation
+/// are kept. Also covers pnpm catalogs, Yarn Berry `resolutions` entries
+/// pinning the package, and a refresh of whichever lockfile the project uses.
+pub struct Npm;
+
+/// Request metadata listing workspace packages, by `name` or directory, to
+/// leave out of a monorepo upgrade.
+pub const NPM_EXCLUDE_WORKSPACES_METADATA_KEY: &str = "exclude_workspaces";
+
+fn resolution_entry_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"("([^"]+)"\s*:\s*")([^"]*)(")"#).expect("valid pattern"))
+}
+
+impl Ecosystem for Npm {
+    fn name(&self) -> &'static str {
+        "npm"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        1.0
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let sections = npm_sections(request.dependency_kind);
+        if sections.is_empty() {
+            return Err(unsupported_kind("npm", request.dependency_kind));
+        }
+
+        let manifests = manifests_named(request, &["package.json"]);
+        let catalog_changes = pnpm_catalog_changes(request, versions);
+        if manifests.is_empty() && catalog_changes.is_none() {
+            return Err(manifest_required("package.json"));
+        }
+
+        let berry = yarn_berry_manifests(request);
+        let roots = workspace_roots(&manifests);
+        let excluded = excluded_workspaces(request);
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let workspace = workspace_package(&roots, path, content);
+            if workspace
+                .as_ref()
+                .is_some_and(|workspace| workspace.is_excluded(&excluded))
+            {
+                continue;
+            }
+
+            let mut updated = match &request.replacement_package {
+                Some(replacement) => rename_dependency(
+                    content,
+                    sections,
+                    &request.package_name,
+                    replacement,
+                    &versions.target,
+                ),
+                None => update_manifest(content, sections, &request.package_name, &versions.target),
+            };
+
+            let mut pinned = Vec::new();
+            if berry.contains(&(path, content)) {
+                updated = update_resolutions(&updated, &request.package_name, |key, written| {
+                    let rewritten = rewrite_range(written, &versions.target)?;
+                    pinned.push(key.to_string());
+                    Some(rewritten)
+                });
+            }
+            if updated == content {
+                continue;
+            }
+            let locations = edited_sections(content, &updated, sections)
+                .map(|section| format!("{}.{}", section, request.package_name))
+                .collect::<Vec<_>>();
+            let mut change = modified(path, updated);
+            change
+                .metadata
+                .insert("locations".to_string(), serde_json::json!(locations));
+            if let Some(workspace) = workspace {
+                change
+                    .metadata
+                    .insert("workspace".to_string(), serde_json::json!(workspace.name));
+            }
+            if !pinned.is_empty() {
+                change
+                    .metadata
+                    .insert("resolutions".to_string(), serde_json::json!(pinned));
+            }
+            changes.push(change);
+        }
+        changes.extend(catalog_changes.unwrap_or_default());
+
+        // One refresh per lockfile, found next to or above each edited file
+        let mut lockfiles: Vec<String> = changes
+            .iter()
+            .filter_map(|change| nearest_lockfile(request, &change.file_path))
+            .collect();
+        lockfiles.sort();
+        lockfiles.dedup();
+        for lock_path in lockfiles {
+            let argv = lockfile_command(request, &lock_path);
+            changes.push(lockfile_refresh(&lock_path, &[argv]));
+        }
+        Ok(changes)
+    }
+
+    fn declares(&self, request: &UpgradeRequest) -> bool {
+        let sections = npm_sections(request.dependency_kind);
+        let declared = manifests_named(request, &["package.json"])
+            .into_iter()
+            .filter_map(|(_, content)| serde_json::from_str::<serde_json::Value>(content).ok())
+            .any(|manifest| {
+                sections
+                    .iter()
+                    .chain(&["overrides", "resolutions"])
+                    .any(|section| manifest[*section].get(&request.package_name).is_some())
+            });
+        declared
+            || manifests_named(request, &["pnpm-workspace.yaml"])
+                .into_iter()
+                .any(|(_, content)| mentions(content, &request.package_name))
+    }
+
+    /// Resolutions that cannot be moved to the target (patches, portals,
+    /// aliases to other packages) keep overriding it.
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        let manifests = manifests_named(request, &["package.json"]);
+        let roots = workspace_roots(&manifests);
+        let excluded = excluded_workspaces(request);
+        for (path, content) in &manifests {
+            let Some(workspace) = workspace_package(&roots, path, content) else {
+                continue;
+            };
+            if workspace.is_excluded(&excluded)
+                && update_manifest(
+                    content,
+                    npm_sections(request.dependency_kind),
+                    &request.package_name,
+                    &versions.target,
+                ) != *content
+            {
+                assessment.explanations.push(format!(
+                    "Workspace {} opted out and keeps its current {} requirement",
+                    workspace.name, request.package_name
+                ));
+            }
+        }
+
+        for (path, content) in yarn_berry_manifests(request) {
+            update_resolutions(content, &request.package_name, |key, written| {
+                if rewrite_range(written, &versions.target).is_none() {
+                    assessment.risk_level = assessment.risk_level.max(RiskLevel::Medium);
+                    assessment.explanations.push(format!(
+                        "{} resolution \"{}\": \"{}\" overrides {} and was left unchanged",
+                        path, key, written, versions.target
+                    ));
+                }
+                None
+            });
+        }
+    }
+}
+
+/// A package of an npm, Yarn or Bun workspace.
+struct WorkspacePackage {
+    name: String,
+    directory: String,
+}
+
+impl WorkspacePackage {
+    fn is_excluded(&self, excluded: &[&str]) -> bool {
+        excluded
+            .iter()
+            .any(|entry| *entry == self.name || entry.trim_end_matches('/') == self.directory)
+    }
+}
+
+/// Directories of root manifests declaring `workspaces`, with their globs
+/// (either the array itself or its `packages` field).
+fn workspace_roots<'a>(manifests: &[(&'a str, &str)]) -> Vec<(&'a str, Vec<String>)> {
+    manifests
+        .iter()
+        .filter_map(|(path, content)| {
+            let manifest: serde_json::Value = serde_json::from_str(content).ok()?;
+            let globs = match &manifest["workspaces"] {
+                serde_json::Value::Array(globs) => globs,
+                workspaces => workspaces["packages"].as_array()?,
+            };
+            let globs = globs
+                .iter()
+                .filter_map(|glob| glob.as_str())
+                .map(|glob| {
+                    glob.trim_start_matches("./")
+                        .trim_end_matches('/')
+                        .to_string()
+                })
+                .collect();
+            Some((path.trim_end_matches("package.json"), globs))
+        })
+        .collect()
+}
+
+/// The workspace package `path` belongs to: matched by a glob of the
+/// closest root and not excluded by a `!` pattern.
+fn workspace_package(
+    roots: &[(&str, Vec<String>)],
+    path: &str,
+    content: &str,
+) -> Option<WorkspacePackage> {
+    let package_directory = path.trim_end_matches("package.json");
+    let directory = roots
+        .iter()
+        .filter(|(root, _)| package_directory != *root)
+        .filter_map(|(root, globs)| {
+            let relative = package_directory.strip_prefix(root)?.trim_end_matches('/');
+            let included = globs
+                .iter()
+                .any(|glob| !glob.starts_with('!') && glob_match(glob, relative));
+            let negated = globs
+                .iter()
+                .filter_map(|glob| glob.strip_prefix('!'))
+                .any(|glob| glob_match(glob, relative));
+            (included && !negated).then_some((root.len(), relative))
+        })
+        .max_by_key(|(root_length, _)| *root_length)?
+        .1
+        .to_string();
+    let name = serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|manifest| manifest["name"].as_str().map(str::to_string))
+        .unwrap_or_else(|| directory.clone());
+    Some(WorkspacePackage { name, directory })
+}
+
+fn excluded_workspaces(request: &UpgradeRequest) -> Vec<&str> {
+    request
+        .metadata
+        .get(NPM_EXCLUDE_WORKSPACES_METADATA_KEY)
+        .and_then(|value| value.as_array())
+        .map(|entries| entries.iter().filter_map(|entry| entry.as_str()).collect())
+        .unwrap_or_default()
+}
+
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+];
+
+/// The lockfile governing `path`: the first one found in its directory or
+/// the closest ancestor, as workspace lockfiles live at the root.
+fn nearest_lockfile(request: &UpgradeRequest, path: &str) -> Option<String> {
+    let mut directory = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+    loop {
+        if let Some(lockfile) = LOCKFILES
+            .iter()
+            .map(|name| format!("{}{}", directory, name))
+            .find(|candidate| request.manifests.contains_key(candidate))
+        {
+            return Some(lockfile);
+        }
+        if directory.is_empty() {
+            return None;
+        }
+        let parent = directory.trim_end_matches('/');
+        directory = &parent[..parent.rfind('/').map_or(0, |i| i + 1)];
+    }
+}
+
+// Lockfile-only updates where the package manager has them
+fn lockfile_command(request: &UpgradeRequest, lock_path: &str) -> &'static [&'static str] {
+    let directory = &lock_path[..lock_path.rfind('/').map_or(0, |i| i + 1)];
+    match &lock_path[directory.len()..] {
+        "pnpm-lock.yaml" => &["pnpm", "install", "--lockfile-only", "--ignore-scripts"],
+        "yarn.lock" => {
+            let berry = request
+                .manifests
+                .get_key_value(&format!("{}package.json", directory))
+                .is_some_and(|(path, content)| is_yarn_berry(request, path, content));
+            if berry {
+                &["yarn", "install", "--mode=update-lockfile"]
+            } else {
+                &["yarn", "install", "--ignore-scripts"]
+            }
+        }
+        _ => &["npm", "install", "--package-lock-only", "--ignore-scripts"],
+    }
+}
+
+/// Root `package.json` files of Yarn Berry projects: next to a
+/// `.yarnrc.yml`, or declaring `"packageManager": "yarn@2+"`.
+fn yarn_berry_manifests(request: &UpgradeRequest) -> Vec<(&str, &str)> {
+    manifests_named(request, &["package.json"])
+        .into_iter()
+        .filter(|(path, content)| is_yarn_berry(request, path, content))
+        .collect()
+}
+
+fn is_yarn_berry(request: &UpgradeRequest, path: &str, content: &str) -> bool {
+    let directory = path.trim_end_matches("package.json");
+    if request
+        .manifests
+        .contains_key(&format!("{}.yarnrc.yml", directory))
+    {
+        return true;
+    }
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|manifest| {
+            let version = manifest["packageManager"].as_str()?.strip_prefix("yarn@")?;
+            version.split('.').next()?.parse::<u64>().ok()
+        })
+        .is_some_and(|major| major >= 2)
+}
+
+/// Whether a resolution key (`lodash`, `**/lodash`, `parent/lodash`,
+/// `lodash@npm:^4.0.0`) targets the package.
+fn resolution_targets(key: &str, package: &str) -> bool {
+    let Some(start) = key.rfind(package) else {
+        return false;
+    };
+    let before = &key[..start];
+    let after = &key[start + package.len()..];
+    (before.is_empty() || before.ends_with('/')) && (after.is_empty() || after.starts_with('@'))
+}
+
+/// Calls `rewrite` for every resolution entry targeting the package and
+/// substitutes the values it returns, editing the text in place.
+fn update_resolutions(
+    content: &str,
+    package: &str,
+    mut rewrite: impl FnMut(&str, &str) -> Option<String>,
+) -> String {
+    let Some(body) = object_body(content, "resolutions") else {
+        return content.to_string();
+    };
+    let edited =
+        resolution_entry_pattern().replace_all(&content[body.clone()], |caps: &Captures| {
+            if !resolution_targets(&caps[2], package) {
+                return caps[0].to_string();
+            }
+            match rewrite(&caps[2], &caps[3]) {
+                Some(value) => format!("{}{}{}", &caps[1], value, &caps[4]),
+                None => caps[0].to_string(),
+            }
+        });
+    format!(
+        "{}{}{}",
+        &content[..body.start],
+        edited,
+        &content[body.end..]
+    )
+}
+
+// Sections among `sections`, `overrides` and `resolutions` whose body
+// differs between the two versions of a manifest.
+fn edited_sections<'a>(
+    content: &'a str,
+    updated: &'a str,
+    sections: &'a [&'a str],
+) -> impl Iterator<Item = &'a str> {
+    let body = |text: &'a str, section: &str| object_body(text, section).map(|body| &text[body]);
+    sections
+        .iter()
+        .chain(&["overrides", "resolutions"])
+        .copied()
+        .filter(move |section| body(content, section) != body(updated, section))
+}
+
+/// Rewrites the package's entries in `sections` and `overrides`. Entries
+/// that are not version ranges (`catalog:`, `workspace:`, tags, URLs) are
+/// left alone, as are nested `overrides` keyed by other packages' ranges.
+fn update_manifest(
+    content: &str,
+    sections: &[&str],
+    package: &str,
+    target: &ParsedVersion,
+) -> String {
+    let entry = Regex::new(&format!(
+        r#"("{}"\s*:\s*")([^"]*)(")"#,
+        regex::escape(package)
+    ))
+    .expect("valid pattern");
+
+    let mut updated = content.to_string();
+    for section in sections.iter().chain(&["overrides"]) {
+        let Some(body) = object_body(&updated, section) else {
+            continue;
+        };
+        let edited = entry.replace_all(
+            &updated[body.clone()],
+            |caps: &Captures| match rewrite_range(&caps[2], target) {
+                Some(range) => format!("{}{}{}", &caps[1], range, &caps[3]),
+                None => caps[0].to_string(),
+            },
+        );
+        updated = format!(
+            "{}{}{}",
+            &updated[..body.start],
+            edited,
+            &updated[body.end..]
+        );
+    }
+    updated
+}
+
+/// Replaces the package's entries in `sections` with `replacement` at the
+/// target version, keeping their position. A section already declaring the
+/// replacement has it moved to the target and the old entry dropped.
+fn rename_dependency(
+    content: &str,
+    sections: &[&str],
+    package: &str,
+    replacement: &str,
+    target: &ParsedVersion,
+) -> String {
+    let entry = |name: &str| {
+        Regex::new(&format!(r#""{}"\s*:\s*"([^"]*)""#, regex::escape(name))).expect("valid pattern")
+    };
+    let (old_entry, new_entry) = (entry(package), entry(replacement));
+    let requirement =
+        |written: &str| rewrite_range(written, target).unwrap_or_else(|| format!("^{}", target));
+
+    let mut updated = content.to_string();
+    for section in sections {
+        let Some(body) = object_body(&updated, section) else {
+            continue;
+        };
+        let text = &updated[body.clone()];
+        let Some(old) = old_entry.captures(text) else {
+            continue;
+        };
+        let matched = old.get(0).expect("whole match");
+
+        let edited = match new_entry.captures(text) {
+            None => format!(
+                "{}\"{}\": \"{}\"{}",
+                &text[..matched.start()],
+                replacement,
+                requirement(&old[1]),
+                &text[matched.end()..]
+            ),
+            Some(existing) => {
+                let value = existing.get(1).expect("value group");
+                let mut text = format!(
+                    "{}{}{}",
+                    &text[..value.start()],
+                    requirement(value.as_str()),
+                    &text[value.end()..]
+                );
+                let matched = old_entry.find(&text).expect("old entry still present");
+                // Drop the entry with its separating comma
+                let after = &text[matched.end()..];
+                let removal = match after.trim_start().strip_prefix(',') {
+                    Some(rest) => text[..matched.start()].trim_end().len()..text.len() - rest.len(),
+                    None => {
+                        let before = text[..matched.start()].trim_end();
+                        before.strip_suffix(',').unwrap_or(before).len()..matched.end()
+                    }
+                };
+                text.replace_range(removal, "");
+                text
+            }
+        };
+        updated = format!(
+            "{}{}{}",
+            &updated[..body.start],
+            edited,
+            &updated[body.end..]
+        );
+    }
+    updated
+}
+
+// Versions and ranges, optionally with the `npm:` protocol; anything else
+// (`patch:`, `portal:`, `npm:other@1.0.0` aliases, git URLs) cannot be
+// moved to the target.
+fn rewrite_range(written: &str, target: &ParsedVersion) -> Option<String> {
+    let (protocol, range) = match written.strip_prefix("npm:") {
+        Some(range) => ("npm:", range),
+        None => ("", written),
+    };
+    let range_like = range
+        .trim_start_matches(['^', '~', '>', '<', '=', ' '])
+        .starts_with(|c: char| c.is_ascii_digit());
+    range_like.then(|| format!("{}{}", protocol, rewrite_requirement("npm", range, target)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionJump, VersionScheme};
+    use crate::DependencyKind;
+    use std::collections::HashMap;
+
+    const PACKAGE_JSON: &str = r#"{
+  "name": "app",
+  "packageManager": "yarn@4.1.0",
+  "dependencies": {
+    "lodash": "^4.17.20"
+  },
+  "resolutions": {
+    "**/lodash": "npm:4.17.20",
+    "left-pad": "patch:left-pad@npm%3A1.3.0#./.yarn/patches/left-pad.patch",
+    "lodash-es": "4.17.20"
+  }
+}
+"#;
+
+    fn request(package: &str) -> UpgradeRequest {
+        UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                ("package.json".to_string(), PACKAGE_JSON.to_string()),
+                ("yarn.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn versions(target: &str) -> ResolvedVersions {
+        ResolvedVersions {
+            current: SemanticScheme.parse("1.3.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_required() {
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+        let error = Npm
+            .generate_changes(&request, &versions("1.4.0"))
+            .unwrap_err();
+        assert!(matches!(error.error_type, crate::ErrorType::Validation));
+        assert!(error.message.contains("Manifest content required"));
+        assert!(error.message.contains("package.json"));
+    }
+
+    #[test]
+    fn test_manifest_edit_preserves_format() {
+        let manifest = r#"{
+    "name": "web",
+    "dependencies": {
+        "react": "^18.2.0",
+        "react-dom": "catalog:"
+    },
+    "devDependencies": {
+        "@types/react": "~18.2.0",
+        "react": "18.2.0"
+    },
+    "optionalDependencies": {
+        "react": "^18.2.0"
+    },
+    "overrides": {
+        "react-dom": {
+            "react": "$react"
+        },
+        "react": "18.2.0"
+    }
+}
+"#;
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "react".to_string(),
+            manifests: HashMap::from([("apps/web/package.json".to_string(), manifest.to_string())]),
+            ..Default::default()
+        };
+        let changes = Npm.generate_changes(&request, &versions("18.3.1")).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].content,
+            manifest
+                .replace(r#""react": "^18.2.0""#, r#""react": "^18.3.1""#)
+                .replace(r#""react": "18.2.0""#, r#""react": "18.3.1""#)
+        );
+        assert!(changes[0].content.contains(r#""react": "$react""#));
+        assert_eq!(
+            changes[0].metadata["locations"],
+            serde_json::json!([
+                "dependencies.react",
+                "devDependencies.react",
+                "optionalDependencies.react",
+                "overrides.react"
+            ])
+        );
+
+        let dev_only = UpgradeRequest {
+            dependency_kind: Some(DependencyKind::Dev),
+            ..request.clone()
+        };
+        let changes = Npm
+            .generate_changes(&dev_only, &versions("18.3.1"))
+            .unwrap();
+        assert!(changes[0].content.contains(r#""react": "^18.2.0""#));
+        assert!(changes[0].content.contains(
+            r#""devDependencies": {
+        "@types/react": "~18.2.0",
+        "react": "18.3.1""#
+        ));
+
+        let build = UpgradeRequest {
+            dependency_kind: Some(DependencyKind::Build),
+            ..request
+        };
+        let err = Npm
+            .generate_changes(&build, &versions("18.3.1"))
+            .unwrap_err();
+        assert!(matches!(err.error_type, crate::ErrorType::Validation));
+    }
+
+    #[test]
+    fn test_lockfile_detection() {
+        let request = UpgradeRequest {
+            manifests: HashMap::from([
+                ("package-lock.json".to_string(), String::new()),
+                ("tools/pnpm-lock.yaml".to_string(), String::new()),
+                ("legacy/yarn.lock".to_string(), String::new()),
+                ("legacy/package.json".to_string(), "{}".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let lockfile = |path: &str| nearest_lockfile(&request, path);
+        assert_eq!(
+            lockfile("packages/web/package.json").as_deref(),
+            Some("package-lock.json")
+        );
+        assert_eq!(
+            lockfile("tools/lint/package.json").as_deref(),
+            Some("tools/pnpm-lock.yaml")
+        );
+        assert_eq!(
+            lockfile_command(&request, "legacy/yarn.lock"),
+            ["yarn", "install", "--ignore-scripts"]
+        );
+        assert_eq!(
+            lockfile_command(&request, "tools/pnpm-lock.yaml"),
+            ["pnpm", "install", "--lockfile-only", "--ignore-scripts"]
+        );
+        // Every planned refresh is one the worker may run
+        for path in [
+            "package-lock.json",
+            "tools/pnpm-lock.yaml",
+            "legacy/yarn.lock",
+        ] {
+            assert!(crate::lockfile::is_lockfile_tool(
+                lockfile_command(&request, path)[0]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_resolutions_follow_upgrade() {
+        let changes = Npm
+            .generate_changes(&request("lodash"), &versions("4.17.21"))
+            .unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0]
+            .content
+            .contains(r#""**/lodash": "npm:4.17.21","#));
+        assert!(changes[0].content.contains(r#""lodash-es": "4.17.20""#));
+        assert!(changes[0].content.contains(r#""lodash": "^4.17.21""#));
+        assert_eq!(changes[1].file_path, "yarn.lock");
+
+        assert!(resolution_targets("lodash@npm:^4.0.0", "lodash"));
+        assert!(resolution_targets("parent/@types/node", "@types/node"));
+        assert!(!resolution_targets("lodash-es", "lodash"));
+    }
+
+    #[test]
+    fn test_unmovable_resolution_is_flagged() {
+        let changes = Npm
+            .generate_changes(&request("left-pad"), &versions("1.4.0"))
+            .unwrap();
+        assert!(changes.is_empty());
+
+        let mut assessment = RiskAssessment {
+            version_jump: VersionJump::Minor,
+            ..Default::default()
+        };
+        Npm.assess_risk(&request("left-pad"), &versions("1.4.0"), &mut assessment);
+        assert_eq!(assessment.risk_level, RiskLevel::Medium);
+        assert!(assessment.explanations[0].contains("left unchanged"));
+    }
+
+    #[test]
+    fn test_workspace_packages() {
+        let root = r#"{"name": "monorepo", "private": true, "workspaces": {"packages": ["packages/*", "apps/**", "!packages/legacy"]}, "devDependencies": {"lodash": "^4.17.20"}}"#;
+        let package = |name: &str| {
+            format!(
+                r#"{{"name": "{}", "dependencies": {{"lodash": "^4.17.20"}}}}"#,
+                name
+            )
+        };
+        let mut request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            manifests: HashMap::from([
+                ("package.json".to_string(), root.to_string()),
+                ("package-lock.json".to_string(), String::new()),
+                ("packages/ui/package.json".to_string(), package("@acme/ui")),
+                (
+                    "packages/legacy/package.json".to_string(),
+                    package("legacy"),
+                ),
+                ("apps/web/site/package.json".to_string(), package("site")),
+            ]),
+            metadata: HashMap::from([(
+                NPM_EXCLUDE_WORKSPACES_METADATA_KEY.to_string(),
+                serde_json::json!(["apps/web/site"]),
+            )]),
+            ..Default::default()
+        };
+
+        let changes = Npm
+            .generate_changes(&request, &versions("4.17.21"))
+            .unwrap();
+        let paths: Vec<&str> = changes.iter().map(|c| c.file_path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "package.json",
+                "packages/legacy/package.json",
+                "packages/ui/package.json",
+                "package-lock.json"
+            ]
+        );
+        assert!(!changes[0].metadata.contains_key("workspace"));
+        // Negated globs take the directory out of the workspace
+        assert!(!changes[1].metadata.contains_key("workspace"));
+        assert_eq!(changes[2].metadata["workspace"], "@acme/ui");
+        assert!(changes[2].content.contains(r#""lodash": "^4.17.21""#));
+
+        let mut assessment = RiskAssessment {
+            version_jump: VersionJump::Patch,
+            ..Default::default()
+        };
+        Npm.assess_risk(&request, &versions("4.17.21"), &mut assessment);
+        assert_eq!(
+            assessment.explanations,
+            ["Workspace site opted out and keeps its current lodash requirement"]
+        );
+
+        request.metadata.insert(
+            NPM_EXCLUDE_WORKSPACES_METADATA_KEY.to_string(),
+            serde_json::json!(["@acme/ui", "site"]),
+        );
+        let changes = Npm
+            .generate_changes(&request, &versions("4.17.21"))
+            .unwrap();
+        assert!(changes
+            .iter()
+            .all(|c| !c.metadata.contains_key("workspace")));
+    }
+
+    #[test]
+    fn test_rename_dependency() {
+        let manifest = r#"{
+  "dependencies": {
+    "babel-core": "^6.26.3",
+    "lodash": "^4.17.21"
+  },
+  "devDependencies": {
+    "@babel/core": "^7.0.0",
+    "babel-core": "6.26.3"
+  }
+}
+"#;
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "babel-core".to_string(),
+            replacement_package: Some("@babel/core".to_string()),
+            manifests: HashMap::from([("package.json".to_string(), manifest.to_string())]),
+            ..Default::default()
+        };
+        let changes = Npm.generate_changes(&request, &versions("7.24.0")).unwrap();
+        assert_eq!(
+            changes[0].content,
+            r#"{
+  "dependencies": {
+    "@babel/core": "^7.24.0",
+    "lodash": "^4.17.21"
+  },
+  "devDependencies": {
+    "@babel/core": "^7.24.0"
+  }
+}
+"#
+        );
+    }
+}
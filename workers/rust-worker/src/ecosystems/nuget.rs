@@ -0,0 +1,200 @@
+use super::{manifest_required, manifests_matching, modified, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::ResolvedVersions;
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// .NET projects: `<PackageReference>` items in project files and
+/// `<PackageVersion>` items in `Directory.Packages.props` (central package
+/// management). Package ids are matched case-insensitively.
+pub struct NuGet;
+
+const PROJECT_EXTENSIONS: &[&str] = &[".csproj", ".fsproj", ".vbproj"];
+const CENTRAL_PACKAGES: &str = "Directory.Packages.props";
+
+fn item_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?s)<(PackageReference|PackageVersion)\b([^>]*?)(/>|>(.*?)</(?:PackageReference|PackageVersion)>)")
+            .expect("valid pattern")
+    })
+}
+
+fn attribute_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"\b(Include|Update|Version|VersionOverride)\s*=\s*"([^"]*)""#)
+            .expect("valid pattern")
+    })
+}
+
+fn version_element_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"(?s)(<Version>)(.*?)(</Version>)").expect("valid pattern"))
+}
+
+impl Ecosystem for NuGet {
+    fn name(&self) -> &'static str {
+        "nuget"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let files = manifests_matching(request, |file_name| {
+            file_name == CENTRAL_PACKAGES
+                || PROJECT_EXTENSIONS
+                    .iter()
+                    .any(|extension| file_name.ends_with(extension))
+        });
+
+        if files.is_empty() {
+            return Err(manifest_required("*.csproj"));
+        }
+
+        Ok(files
+            .into_iter()
+            .filter_map(|(path, content)| {
+                let updated = update_items(content, &request.package_name, versions);
+                (updated != content).then(|| modified(path, updated))
+            })
+            .collect())
+    }
+}
+
+fn update_items(content: &str, package: &str, versions: &ResolvedVersions) -> String {
+    item_pattern()
+        .replace_all(content, |item: &Captures| {
+            let whole = item[0].to_string();
+            let attributes = &item[2];
+            let names_package = attribute_pattern().captures_iter(attributes).any(|attr| {
+                matches!(&attr[1], "Include" | "Update") && attr[2].eq_ignore_ascii_case(package)
+            });
+            if !names_package {
+                return whole;
+            }
+
+            let updated_attributes = attribute_pattern()
+                .replace_all(attributes, |attr: &Captures| {
+                    if matches!(&attr[1], "Version" | "VersionOverride") {
+                        attr[0].replace(
+                            &format!("\"{}\"", &attr[2]),
+                            &format!("\"{}\"", rewrite(&attr[2], versions)),
+                        )
+                    } else {
+                        attr[0].to_string()
+                    }
+                })
+                .into_owned();
+            let body = match item.get(4) {
+                Some(body) => version_element_pattern()
+                    .replace_all(body.as_str(), |element: &Captures| {
+                        format!(
+                            "{}{}{}",
+                            &element[1],
+                            rewrite(&element[2], versions),
+                            &element[3]
+                        )
+                    })
+                    .into_owned(),
+                None => String::new(),
+            };
+
+            match item.get(4) {
+                Some(_) => format!(
+                    "<{}{}>{}</{}>",
+                    &item[1], updated_attributes, body, &item[1]
+                ),
+                None => format!("<{}{}/>", &item[1], updated_attributes),
+            }
+        })
+        .into_owned()
+}
+
+// Exact pins (`[1.2.3]`) stay pinned; floating versions keep their precision
+fn rewrite(written: &str, versions: &ResolvedVersions) -> String {
+    let written = written.trim();
+    match written.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(pinned) if !pinned.contains(',') => format!("[{}]", versions.target),
+        _ => rewrite_requirement("nuget", written, &versions.target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{NuGetScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    fn upgrade(files: &[(&str, &str)], package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "nuget".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: NuGetScheme.parse("1.0").unwrap(),
+            target: NuGetScheme.parse(target).unwrap(),
+        };
+        NuGet.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_project_references() {
+        let project = r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup>
+    <PackageReference Include="Newtonsoft.Json" Version="13.0.1" />
+    <PackageReference Include="Serilog" Version="3.0.*" />
+    <PackageReference Include="Polly">
+      <Version>[7.2.3]</Version>
+    </PackageReference>
+  </ItemGroup>
+</Project>
+"#;
+        let files = [("src/App/App.csproj", project)];
+
+        let json = upgrade(&files, "newtonsoft.json", "13.0.3");
+        assert!(json[0]
+            .content
+            .contains(r#"<PackageReference Include="Newtonsoft.Json" Version="13.0.3" />"#));
+
+        let serilog = upgrade(&files, "Serilog", "3.1.1");
+        assert!(serilog[0].content.contains(r#"Version="3.1.*""#));
+
+        let polly = upgrade(&files, "Polly", "8.2.0");
+        assert!(polly[0].content.contains("<Version>[8.2.0]</Version>"));
+        assert!(polly[0].content.contains("</PackageReference>"));
+    }
+
+    #[test]
+    fn test_central_package_management() {
+        let props = r#"<Project>
+  <ItemGroup>
+    <PackageVersion Include="xunit" Version="2.5.0" />
+  </ItemGroup>
+</Project>
+"#;
+        let project = r#"<ItemGroup><PackageReference Include="xunit" /></ItemGroup>"#;
+        let files = [
+            ("Directory.Packages.props", props),
+            ("tests/Tests.csproj", project),
+        ];
+
+        let changes = upgrade(&files, "xunit", "2.6.6");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].file_path, "Directory.Packages.props");
+        assert!(changes[0].content.contains(r#"Version="2.6.6""#));
+    }
+}
@@ -0,0 +1,162 @@
+use super::{manifest_required, manifests_matching, modified, Ecosystem};
+use crate::version::ResolvedVersions;
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Distribution packages pinned in Dockerfiles: `apt-get install pkg=ver`
+/// (Debian/Ubuntu) and `apk add pkg=ver` or `pkg~ver` (Alpine).
+pub struct OsPackages;
+
+// Start of an install command; its arguments run until the next `&&`, `;`,
+// `|` or the end of the (backslash-continued) instruction.
+fn install_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"\b(?:(?:apt-get|apt|aptitude)\s+(?:-[^\s]+\s+)*install|apk\s+(?:-[^\s]+\s+)*add)\b",
+        )
+        .expect("valid pattern")
+    })
+}
+
+pub(super) fn is_dockerfile(file_name: &str) -> bool {
+    matches!(file_name, "Dockerfile" | "Containerfile")
+        || file_name.starts_with("Dockerfile.")
+        || file_name.ends_with(".Dockerfile")
+        || file_name.ends_with(".dockerfile")
+}
+
+impl Ecosystem for OsPackages {
+    fn name(&self) -> &'static str {
+        "os-packages"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.8
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let dockerfiles = manifests_matching(request, is_dockerfile);
+        if dockerfiles.is_empty() {
+            return Err(manifest_required("Dockerfile"));
+        }
+
+        // `pkg=ver`, `pkg:arch=ver` and apk's fuzzy `pkg~ver`
+        let pin = Regex::new(&format!(
+            r"(\s{}(?::[\w-]+)?(?:=|~))([^\s;&|\\]+)",
+            regex::escape(&request.package_name)
+        ))
+        .expect("valid pattern");
+
+        let mut changes = Vec::new();
+        for (path, content) in dockerfiles {
+            let mut updated = String::with_capacity(content.len());
+            let mut copied = 0;
+            for command in install_pattern().find_iter(content) {
+                if command.start() < copied {
+                    continue;
+                }
+                let end = command.end() + arguments_len(&content[command.end()..]);
+                updated.push_str(&content[copied..command.end()]);
+                updated.push_str(
+                    &pin.replace_all(&content[command.end()..end], |caps: &Captures| {
+                        format!("{}{}", &caps[1], versions.target)
+                    }),
+                );
+                copied = end;
+            }
+            updated.push_str(&content[copied..]);
+
+            if updated != content {
+                changes.push(modified(path, updated));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+fn arguments_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'&' | b';' | b'|' => return i,
+            b'\n' if i == 0 || bytes[i - 1] != b'\\' => return i,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{DebianScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const DOCKERFILE: &str = "FROM debian:bookworm-slim
+ENV curl=7.88.1-10+deb12u4
+RUN apt-get update \\
+ && apt-get install -y --no-install-recommends \\
+      ca-certificates=20230311 \\
+      curl=7.88.1-10+deb12u4 \\
+      libcurl4:amd64=7.88.1-10+deb12u4 \\
+ && rm -rf /var/lib/apt/lists/*
+";
+
+    const ALPINE: &str = "FROM alpine:3.19
+RUN apk add --no-cache curl=8.5.0-r0 openssl~3.1
+";
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "os-packages".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                ("Dockerfile".to_string(), DOCKERFILE.to_string()),
+                ("docker/alpine.Dockerfile".to_string(), ALPINE.to_string()),
+                (
+                    "docker/README.md".to_string(),
+                    "apk add curl=8.5.0-r0".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: DebianScheme.parse("7.88.1-10+deb12u4").unwrap(),
+            target: DebianScheme.parse(target).unwrap(),
+        };
+        OsPackages.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_apt_pins() {
+        let curl = upgrade("curl", "7.88.1-10+deb12u5");
+        assert_eq!(curl.len(), 2);
+        let dockerfile = &curl[0].content;
+        assert!(dockerfile.contains("      curl=7.88.1-10+deb12u5 \\\n"));
+        assert!(dockerfile.contains("ENV curl=7.88.1-10+deb12u4\n"));
+        assert!(dockerfile.contains("libcurl4:amd64=7.88.1-10+deb12u4"));
+
+        let libcurl = upgrade("libcurl4", "7.88.1-10+deb12u5");
+        assert!(libcurl[0]
+            .content
+            .contains("libcurl4:amd64=7.88.1-10+deb12u5"));
+    }
+
+    #[test]
+    fn test_apk_pins() {
+        let curl = upgrade("curl", "8.5.0-r1");
+        assert_eq!(curl[1].file_path, "docker/alpine.Dockerfile");
+        assert!(curl[1].content.contains("curl=8.5.0-r1 openssl~3.1\n"));
+
+        let openssl = upgrade("openssl", "3.1.4-r5");
+        assert_eq!(openssl.len(), 1);
+        assert!(openssl[0].content.contains("openssl~3.1.4-r5\n"));
+    }
+}
@@ -0,0 +1,421 @@
+use super::{
+    lockfile_refresh, manifest_required, manifests_matching, manifests_named, modified, parse_toml,
+    set_string, set_value, Ecosystem,
+};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::Regex;
+use std::sync::OnceLock;
+use toml_edit::{Array, DocumentMut, Item, TableLike};
+
+/// Python projects described by `pyproject.toml`: Poetry dependency tables
+/// (`[tool.poetry.dependencies]`, dev and group tables) and PEP 621
+/// `[project]` dependency arrays. Edits go through `toml_edit` so comments
+/// and layout are kept; `poetry.lock` is regenerated when present.
+/// pip-tools inputs (`requirements*.in`) are edited line by line and their
+/// compiled `.txt` files are regenerated with `pip-compile`.
+pub struct Pip;
+
+const PYPROJECT: &str = "pyproject.toml";
+
+// PEP 508: name, optional extras, version specifier, optional markers
+fn pep508_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(\s*[A-Za-z0-9][A-Za-z0-9._-]*\s*(?:\[[^\]]*\])?\s*)([^;@]*?)(\s*(?:;.*)?)$")
+            .expect("valid pattern")
+    })
+}
+
+/// PEP 503 normalized project name: case-insensitive, with runs of `-`, `_`
+/// and `.` equivalent.
+pub(super) fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.trim().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+impl Ecosystem for Pip {
+    fn name(&self) -> &'static str {
+        "pip"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let manifests = manifests_named(request, &[PYPROJECT]);
+        let inputs = manifests_matching(request, |file_name| {
+            file_name.ends_with(".in") && file_name != "MANIFEST.in"
+        });
+        let inputs: Vec<_> = inputs
+            .into_iter()
+            .filter(|(path, _)| {
+                let file_name = path.rsplit('/').next().unwrap_or(path);
+                file_name.starts_with("requirements")
+                    || request.manifests.contains_key(&compiled_path(path))
+            })
+            .collect();
+        if manifests.is_empty() && inputs.is_empty() {
+            return Err(manifest_required(PYPROJECT));
+        }
+
+        let name = normalize_name(&request.package_name);
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let mut doc = parse_toml(path, content)?;
+            update_poetry(&mut doc, &name, &versions.target);
+            update_pep621(&mut doc, &name, &versions.target);
+
+            let updated = doc.to_string();
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+
+            let lock_path = format!("{}poetry.lock", path.trim_end_matches(PYPROJECT));
+            if request.manifests.contains_key(&lock_path) {
+                changes.push(lockfile_refresh(
+                    &lock_path,
+                    &[&["poetry", "update", &request.package_name, "--lock"]],
+                ));
+            }
+        }
+
+        for (path, content) in inputs {
+            let updated = update_requirements_file(content, &name, &versions.target);
+            if updated != content {
+                changes.push(modified(path, updated));
+            }
+
+            // The compiled file pins transitive dependencies too, so it is
+            // refreshed whenever it pins the package, edited input or not
+            let output = compiled_path(path);
+            let Some(compiled) = request.manifests.get(&output) else {
+                continue;
+            };
+            if !pins_package(compiled, &name) {
+                continue;
+            }
+            let file_name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+            let pin = format!("{}=={}", request.package_name, versions.target);
+            let (output_name, input_name) = (file_name(&output), file_name(path));
+            let mut argv = vec!["pip-compile", "--quiet"];
+            if compiled.contains("--hash=") {
+                argv.push("--generate-hashes");
+            }
+            argv.extend([
+                "--upgrade-package",
+                &pin,
+                "--output-file",
+                &output_name,
+                &input_name,
+            ]);
+            changes.push(lockfile_refresh(&output, &[&argv]));
+        }
+        Ok(changes)
+    }
+}
+
+/// `requirements.in` compiles to `requirements.txt` next to it.
+fn compiled_path(input: &str) -> String {
+    format!("{}.txt", input.trim_end_matches(".in"))
+}
+
+/// Rewrites the requirement lines of a pip requirements file naming `name`.
+/// Options (`-r`, `-c`, `-e`, ...), comments and unpinned lines are kept.
+fn update_requirements_file(content: &str, name: &str, target: &ParsedVersion) -> String {
+    content
+        .split('\n')
+        .map(|line| {
+            if line.trim_start().starts_with(['-', '#']) {
+                return line.to_string();
+            }
+            // pip only treats `#` as a comment after whitespace
+            let (requirement, comment) = match line.find(" #") {
+                Some(at) => line.split_at(at),
+                None => (line, ""),
+            };
+            match rewrite_pep508(requirement, name, target) {
+                Some(updated) => format!("{}{}", updated, comment),
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether a compiled requirements file pins `name` (`name==1.2.3 \`).
+fn pins_package(compiled: &str, name: &str) -> bool {
+    compiled.lines().any(|line| {
+        line.split_once("==").is_some_and(|(declared, _)| {
+            let declared = declared.split('[').next().unwrap_or_default();
+            !declared.trim_start().starts_with(['-', '#']) && normalize_name(declared) == name
+        })
+    })
+}
+
+fn update_poetry(doc: &mut DocumentMut, name: &str, target: &ParsedVersion) {
+    let Some(poetry) = doc
+        .get_mut("tool")
+        .and_then(|tool| tool.get_mut("poetry"))
+        .and_then(Item::as_table_like_mut)
+    else {
+        return;
+    };
+
+    for table in ["dependencies", "dev-dependencies"] {
+        if let Some(dependencies) = poetry.get_mut(table).and_then(Item::as_table_like_mut) {
+            update_poetry_table(dependencies, name, target);
+        }
+    }
+    if let Some(groups) = poetry.get_mut("group").and_then(Item::as_table_like_mut) {
+        for (_, group) in groups.iter_mut() {
+            if let Some(dependencies) = group
+                .get_mut("dependencies")
+                .and_then(Item::as_table_like_mut)
+            {
+                update_poetry_table(dependencies, name, target);
+            }
+        }
+    }
+}
+
+// Entries are either a constraint string or a table with a `version` key;
+// `*` and git/path dependencies are left alone.
+fn update_poetry_table(dependencies: &mut dyn TableLike, name: &str, target: &ParsedVersion) {
+    for (key, entry) in dependencies.iter_mut() {
+        if normalize_name(key.get()) != name {
+            continue;
+        }
+        let constraint = if entry.is_str() {
+            Some(entry)
+        } else {
+            entry.get_mut("version").filter(|version| version.is_str())
+        };
+        if let Some(constraint) = constraint {
+            let written = constraint.as_str().unwrap_or_default().to_string();
+            if written.trim() != "*" {
+                set_string(constraint, &rewrite_requirement("poetry", &written, target));
+            }
+        }
+    }
+}
+
+fn update_pep621(doc: &mut DocumentMut, name: &str, target: &ParsedVersion) {
+    let Some(project) = doc.get_mut("project").and_then(Item::as_table_like_mut) else {
+        return;
+    };
+
+    if let Some(dependencies) = project.get_mut("dependencies").and_then(Item::as_array_mut) {
+        update_requirements(dependencies, name, target);
+    }
+    if let Some(optional) = project
+        .get_mut("optional-dependencies")
+        .and_then(Item::as_table_like_mut)
+    {
+        for (_, extra) in optional.iter_mut() {
+            if let Some(dependencies) = extra.as_array_mut() {
+                update_requirements(dependencies, name, target);
+            }
+        }
+    }
+}
+
+fn update_requirements(requirements: &mut Array, name: &str, target: &ParsedVersion) {
+    for value in requirements.iter_mut() {
+        let Some(requirement) = value.as_str() else {
+            continue;
+        };
+        if let Some(updated) = rewrite_pep508(requirement, name, target) {
+            set_value(value, &updated);
+        }
+    }
+}
+
+/// Rewrites the specifier of a PEP 508 requirement string naming `name`,
+/// keeping extras and environment markers. Unpinned requirements and direct
+/// URL references are left alone.
+pub(super) fn rewrite_pep508(
+    requirement: &str,
+    name: &str,
+    target: &ParsedVersion,
+) -> Option<String> {
+    let caps = pep508_pattern().captures(requirement)?;
+    let declared = caps[1].split('[').next().unwrap_or_default();
+    let specifier = caps[2].trim();
+    if normalize_name(declared) != name || specifier.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}{}{}",
+        &caps[1],
+        rewrite_requirement("pip", specifier, target),
+        &caps[3]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{Pep440Scheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const PYPROJECT_TOML: &str = r#"[project]
+name = "app"
+dependencies = [
+    "requests[socks]>=2.28,<3 ; python_version >= '3.8'",  # http
+    "Django~=4.2.1",
+    "attrs",
+]
+
+[project.optional-dependencies]
+test = ["pytest==7.4.*"]
+
+[tool.poetry.dependencies]
+python = "^3.9"
+typing_extensions = "^4.7"  # backports
+pydantic = { version = "~1.10.2", extras = ["email"] }
+
+[tool.poetry.group.dev.dependencies]
+black = "*"
+PyTest = ">=7.0,<8.0"
+"#;
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "pip".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([
+                (PYPROJECT.to_string(), PYPROJECT_TOML.to_string()),
+                ("poetry.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: Pep440Scheme.parse("1.0").unwrap(),
+            target: Pep440Scheme.parse(target).unwrap(),
+        };
+        Pip.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Typing_Extensions"), "typing-extensions");
+        assert_eq!(normalize_name("zope.interface"), "zope-interface");
+        assert_eq!(normalize_name("a-_.b"), "a-b");
+    }
+
+    #[test]
+    fn test_pep621_dependencies() {
+        let requests = upgrade("requests", "3.1.0");
+        assert!(requests[0]
+            .content
+            .contains(r#""requests[socks]~=3.1 ; python_version >= '3.8'",  # http"#));
+        assert_eq!(requests[1].file_path, "poetry.lock");
+        assert_eq!(
+            requests[1].metadata["argv"],
+            serde_json::json!([["poetry", "update", "requests", "--lock"]])
+        );
+
+        let django = upgrade("django", "5.0.2");
+        assert!(django[0].content.contains(r#""Django~=5.0.2","#));
+
+        assert!(upgrade("attrs", "23.2.0").is_empty());
+    }
+
+    #[test]
+    fn test_poetry_dependencies() {
+        let typing = upgrade("typing-extensions", "4.9.0");
+        assert!(typing[0]
+            .content
+            .contains(r#"typing_extensions = "^4.9.0"  # backports"#));
+
+        let pydantic = upgrade("pydantic", "2.5.3");
+        assert!(pydantic[0]
+            .content
+            .contains(r#"pydantic = { version = "~2.5.3", extras = ["email"] }"#));
+
+        let pytest = upgrade("pytest", "8.0.0");
+        assert!(pytest[0].content.contains(r#""pytest==8.0.*""#));
+        assert!(pytest[0].content.contains(r#"PyTest = "^8.0.0""#));
+
+        assert!(upgrade("black", "24.1.0").is_empty());
+    }
+
+    #[test]
+    fn test_pip_compile_requirements() {
+        let compiled = "#\n# This file is autogenerated by pip-compile\n#\n\
+                        certifi==2023.7.22 \\\n    --hash=sha256:abc\n    # via requests\n\
+                        requests==2.31.0 \\\n    --hash=sha256:def\n    # via -r requirements.in\n";
+        let request = |package: &str| UpgradeRequest {
+            ecosystem: "pip".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                (
+                    "api/requirements.in".to_string(),
+                    "-c constraints.txt\nrequests>=2.28  # http\nflask\n".to_string(),
+                ),
+                ("api/requirements.txt".to_string(), compiled.to_string()),
+                ("MANIFEST.in".to_string(), "include README.md\n".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let versions = |target: &str| ResolvedVersions {
+            current: Pep440Scheme.parse("1.0").unwrap(),
+            target: Pep440Scheme.parse(target).unwrap(),
+        };
+
+        let requests = Pip
+            .generate_changes(&request("requests"), &versions("2.32.3"))
+            .unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].file_path, "api/requirements.in");
+        assert_eq!(
+            requests[0].content,
+            "-c constraints.txt\nrequests>=2.32.3  # http\nflask\n"
+        );
+        assert_eq!(requests[1].file_path, "api/requirements.txt");
+        assert_eq!(
+            requests[1].metadata["argv"],
+            serde_json::json!([[
+                "pip-compile",
+                "--quiet",
+                "--generate-hashes",
+                "--upgrade-package",
+                "requests==2.32.3",
+                "--output-file",
+                "requirements.txt",
+                "requirements.in"
+            ]])
+        );
+
+        // Transitive pins only need the compiled file refreshed
+        let certifi = Pip
+            .generate_changes(&request("certifi"), &versions("2024.2.2"))
+            .unwrap();
+        assert_eq!(certifi.len(), 1);
+        assert_eq!(certifi[0].file_path, "api/requirements.txt");
+        assert!(certifi[0].metadata.contains_key("lockfile_refresh"));
+
+        assert!(Pip
+            .generate_changes(&request("flask"), &versions("3.0.0"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use super::pip::normalize_name;
+use super::{
+    lockfile_refresh, manifest_required, manifests_named, modified, parse_toml, set_string,
+    unsupported_kind, Ecosystem,
+};
+use crate::rewrite::rewrite_requirement;
+use crate::version::ResolvedVersions;
+use crate::{Change, DependencyKind, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use toml_edit::{DocumentMut, Item};
+
+/// Pipenv projects: `Pipfile` entries under `[packages]`, `[dev-packages]`
+/// and custom categories, followed by a `Pipfile.lock` refresh.
+pub struct Pipenv;
+
+const PIPFILE: &str = "Pipfile";
+
+// Top-level Pipfile tables that are not package categories
+const NON_CATEGORIES: &[&str] = &["source", "requires", "scripts", "pipenv"];
+
+impl Ecosystem for Pipenv {
+    fn name(&self) -> &'static str {
+        "pipenv"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        // Custom categories only take part when no kind was requested
+        let only_category = match request.dependency_kind {
+            None => None,
+            Some(DependencyKind::Normal) => Some("packages"),
+            Some(DependencyKind::Dev) => Some("dev-packages"),
+            Some(_) => return Err(unsupported_kind("pipenv", request.dependency_kind)),
+        };
+
+        let manifests = manifests_named(request, &[PIPFILE]);
+        if manifests.is_empty() {
+            return Err(manifest_required(PIPFILE));
+        }
+
+        let name = normalize_name(&request.package_name);
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let mut doc = parse_toml(path, content)?;
+            let mut categories = categories_declaring(&doc, &name);
+            if let Some(only) = only_category {
+                categories.retain(|category| category == only);
+            }
+            for category in &categories {
+                let Some(entry) = doc
+                    .get_mut(category)
+                    .and_then(Item::as_table_like_mut)
+                    .and_then(|table| {
+                        table
+                            .iter_mut()
+                            .find(|(key, _)| normalize_name(key.get()) == name)
+                            .map(|(_, entry)| entry)
+                    })
+                else {
+                    continue;
+                };
+                // `"*"` and VCS/path entries carry no version to move
+                let constraint = if entry.is_str() {
+                    Some(entry)
+                } else {
+                    entry.get_mut("version").filter(|version| version.is_str())
+                };
+                if let Some(constraint) = constraint {
+                    let written = constraint.as_str().unwrap_or_default().to_string();
+                    if written.trim() != "*" {
+                        set_string(
+                            constraint,
+                            &rewrite_requirement("pip", &written, &versions.target),
+                        );
+                    }
+                }
+            }
+
+            let updated = doc.to_string();
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+
+            let lock_path = format!("{}.lock", path);
+            if request.manifests.contains_key(&lock_path) {
+                let dev_only = categories.iter().all(|category| category == "dev-packages");
+                let argv: &[&str] = if dev_only {
+                    &["pipenv", "upgrade", "--dev", &request.package_name]
+                } else {
+                    &["pipenv", "upgrade", &request.package_name]
+                };
+                changes.push(lockfile_refresh(&lock_path, &[argv]));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Development-only dependencies do not ship, so their upgrades are
+    /// capped at medium risk unless a security issue was found.
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        _versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        let name = normalize_name(&request.package_name);
+        let mut categories: Vec<String> = manifests_named(request, &[PIPFILE])
+            .into_iter()
+            .filter_map(|(_, content)| content.parse::<DocumentMut>().ok())
+            .flat_map(|doc| categories_declaring(&doc, &name))
+            .collect();
+        categories.sort();
+        categories.dedup();
+        if categories.is_empty() {
+            return;
+        }
+
+        if categories.iter().any(|category| category == "packages") {
+            assessment.explanations.push(format!(
+                "{} is a runtime dependency ([packages])",
+                request.package_name
+            ));
+        } else {
+            if assessment.risk_level < RiskLevel::Critical {
+                assessment.risk_level = assessment.risk_level.min(RiskLevel::Medium);
+            }
+            assessment.explanations.push(format!(
+                "{} is only a development dependency ([{}])",
+                request.package_name,
+                categories.join("], [")
+            ));
+        }
+    }
+}
+
+/// Package categories (`packages`, `dev-packages`, custom) declaring `name`.
+fn categories_declaring(doc: &DocumentMut, name: &str) -> Vec<String> {
+    doc.iter()
+        .filter(|(category, _)| !NON_CATEGORIES.contains(category))
+        .filter(|(_, table)| {
+            table
+                .as_table_like()
+                .is_some_and(|table| table.iter().any(|(key, _)| normalize_name(key) == name))
+        })
+        .map(|(category, _)| category.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{Pep440Scheme, VersionJump, VersionScheme};
+    use std::collections::HashMap;
+
+    const PIPFILE_TOML: &str = r#"[[source]]
+url = "https://pypi.org/simple"
+verify_ssl = true
+name = "pypi"
+
+[packages]
+requests = "==2.31.0"
+Django = {version = "~=4.2", extras = ["bcrypt"]}
+flask = "*"
+
+[dev-packages]
+pytest = ">=7.0,<8"  # test runner
+
+[requires]
+python_version = "3.11"
+"#;
+
+    fn request(package: &str) -> UpgradeRequest {
+        UpgradeRequest {
+            ecosystem: "pipenv".to_string(),
+            package_name: package.to_string(),
+            manifests: HashMap::from([
+                (PIPFILE.to_string(), PIPFILE_TOML.to_string()),
+                ("Pipfile.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn versions(target: &str) -> ResolvedVersions {
+        ResolvedVersions {
+            current: Pep440Scheme.parse("1.0").unwrap(),
+            target: Pep440Scheme.parse(target).unwrap(),
+        }
+    }
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        Pipenv
+            .generate_changes(&request(package), &versions(target))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pipfile_entries() {
+        let requests = upgrade("requests", "2.32.3");
+        assert!(requests[0].content.contains(r#"requests = "==2.32.3""#));
+        assert_eq!(requests[1].file_path, "Pipfile.lock");
+        assert_eq!(requests[1].metadata["command"], "pipenv upgrade requests");
+
+        let django = upgrade("django", "5.0.1");
+        assert!(django[0]
+            .content
+            .contains(r#"Django = {version = "~=5.0", extras = ["bcrypt"]}"#));
+
+        let pytest = upgrade("pytest", "8.1.0");
+        assert!(pytest[0]
+            .content
+            .contains(r#"pytest = "~=8.1"  # test runner"#));
+        assert_eq!(pytest[1].metadata["command"], "pipenv upgrade --dev pytest");
+
+        assert!(upgrade("flask", "3.0.0").is_empty());
+    }
+
+    #[test]
+    fn test_dev_packages_risk() {
+        let assess = |package: &str| {
+            let mut assessment = RiskAssessment {
+                risk_level: RiskLevel::High,
+                breaking_changes: true,
+                version_jump: VersionJump::Major,
+                ..Default::default()
+            };
+            Pipenv.assess_risk(&request(package), &versions("9.0"), &mut assessment);
+            assessment
+        };
+
+        let pytest = assess("pytest");
+        assert_eq!(pytest.risk_level, RiskLevel::Medium);
+        assert!(pytest.explanations[0].contains("[dev-packages]"));
+
+        let requests = assess("requests");
+        assert_eq!(requests.risk_level, RiskLevel::High);
+        assert!(requests.explanations[0].contains("[packages]"));
+    }
+}
@@ -0,0 +1,192 @@
+use super::{manifests_named, modified, yaml};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, UpgradeRequest};
+use std::collections::BTreeMap;
+
+const WORKSPACE_FILE: &str = "pnpm-workspace.yaml";
+const DEFAULT_CATALOG: &str = "default";
+
+const DEPENDENCY_TABLES: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// Workspace packages referencing the package through pnpm catalogs
+/// (`"react": "catalog:"` or `"catalog:react17"`), grouped by catalog name.
+fn catalog_references(request: &UpgradeRequest) -> BTreeMap<String, Vec<String>> {
+    let mut references: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (path, content) in manifests_named(request, &["package.json"]) {
+        let Ok(manifest) = serde_json::from_str::<serde_json::Value>(content) else {
+            continue;
+        };
+        let catalogs = DEPENDENCY_TABLES.iter().filter_map(|table| {
+            let specifier = manifest[*table][&request.package_name].as_str()?;
+            let catalog = specifier.strip_prefix("catalog:")?.trim();
+            Some(if catalog.is_empty() {
+                DEFAULT_CATALOG
+            } else {
+                catalog
+            })
+        });
+        let workspace_package = manifest["name"].as_str().unwrap_or(path);
+        for catalog in catalogs {
+            let packages = references.entry(catalog.to_string()).or_default();
+            if !packages.iter().any(|known| known == workspace_package) {
+                packages.push(workspace_package.to_string());
+            }
+        }
+    }
+    references
+}
+
+/// Edits for npm packages resolved through pnpm catalogs: the catalog entry
+/// in `pnpm-workspace.yaml` is updated once instead of every `package.json`,
+/// and the change lists the workspace packages it affects. Returns `None`
+/// when no workspace package uses a catalog for the package.
+pub(super) fn pnpm_catalog_changes(
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+) -> Option<Vec<Change>> {
+    let workspace = manifests_named(request, &[WORKSPACE_FILE]);
+    let (path, content) = workspace.first()?;
+    let references = catalog_references(request);
+    if references.is_empty() {
+        return None;
+    }
+
+    let mut updated = content.to_string();
+    let mut catalogs = Vec::new();
+    let mut affected = Vec::new();
+    for (catalog, packages) in &references {
+        let edited = update_catalog(&updated, catalog, &request.package_name, &versions.target);
+        if edited != updated {
+            updated = edited;
+            catalogs.push(catalog.clone());
+            affected.extend(packages.iter().cloned());
+        }
+    }
+    if updated == *content {
+        return Some(Vec::new());
+    }
+
+    affected.sort();
+    affected.dedup();
+    let mut change = modified(path, updated);
+    change
+        .metadata
+        .insert("catalogs".to_string(), serde_json::json!(catalogs));
+    change
+        .metadata
+        .insert("affected_packages".to_string(), serde_json::json!(affected));
+    Some(vec![change])
+}
+
+/// Rewrites the package's entry in the named catalog: the top-level
+/// `catalog:` map for the default catalog, or `catalogs.<name>`.
+fn update_catalog(content: &str, catalog: &str, package: &str, target: &ParsedVersion) -> String {
+    let edits = yaml::scalars(content)
+        .iter()
+        .filter(|entry| {
+            if catalog == DEFAULT_CATALOG && entry.is_at(&["catalog", package]) {
+                return true;
+            }
+            entry.is_at(&["catalogs", catalog, package])
+        })
+        .map(|entry| entry.set(&rewrite_requirement("npm", &entry.value, target)))
+        .collect();
+    yaml::apply(content, edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const WORKSPACE: &str = r#"packages:
+  - "packages/*"
+
+catalog:
+  react: ^18.2.0
+  "@types/node": "~20.11.0"  # LTS
+
+catalogs:
+  react17:
+    react: ^17.0.2
+"#;
+
+    fn request(package: &str, manifests: &[(&str, &str)]) -> UpgradeRequest {
+        let mut files: HashMap<String, String> = manifests
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.to_string()))
+            .collect();
+        files.insert(WORKSPACE_FILE.to_string(), WORKSPACE.to_string());
+        UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: package.to_string(),
+            manifests: files,
+            ..Default::default()
+        }
+    }
+
+    fn versions(target: &str) -> ResolvedVersions {
+        ResolvedVersions {
+            current: SemanticScheme.parse("18.2.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_catalog_entries() {
+        let react = request(
+            "react",
+            &[
+                (
+                    "packages/web/package.json",
+                    r#"{"name": "@acme/web", "dependencies": {"react": "catalog:"}}"#,
+                ),
+                (
+                    "packages/docs/package.json",
+                    r#"{"name": "@acme/docs", "devDependencies": {"react": "catalog:default"}}"#,
+                ),
+                (
+                    "packages/legacy/package.json",
+                    r#"{"name": "@acme/legacy", "dependencies": {"react": "catalog:react17"}}"#,
+                ),
+            ],
+        );
+        let changes = pnpm_catalog_changes(&react, &versions("18.3.1")).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].content.contains("catalog:\n  react: ^18.3.1\n"));
+        assert!(changes[0].content.contains("    react: ^18.3.1\n"));
+        assert_eq!(
+            changes[0].metadata["affected_packages"],
+            serde_json::json!(["@acme/docs", "@acme/legacy", "@acme/web"])
+        );
+
+        let types = request(
+            "@types/node",
+            &[(
+                "package.json",
+                r#"{"devDependencies": {"@types/node": "catalog:"}}"#,
+            )],
+        );
+        let changes = pnpm_catalog_changes(&types, &versions("20.14.0")).unwrap();
+        assert!(changes[0]
+            .content
+            .contains(r#""@types/node": "~20.14.0"  # LTS"#));
+        assert!(changes[0].content.contains("  react: ^18.2.0\n"));
+    }
+
+    #[test]
+    fn test_without_catalog_reference() {
+        let direct = request(
+            "react",
+            &[("package.json", r#"{"dependencies": {"react": "^18.2.0"}}"#)],
+        );
+        assert!(pnpm_catalog_changes(&direct, &versions("18.3.1")).is_none());
+    }
+}
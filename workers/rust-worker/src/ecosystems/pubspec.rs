@@ -0,0 +1,144 @@
+use super::{
+    lockfile_refresh, manifest_required, manifests_named, modified, unsupported_kind, yaml,
+    Ecosystem,
+};
+use crate::rewrite::rewrite_requirement;
+use crate::version::ResolvedVersions;
+use crate::{Change, DependencyKind, UpgradeError, UpgradeRequest};
+
+/// Dart and Flutter packages: `pubspec.yaml` entries under `dependencies`,
+/// `dev_dependencies` and `dependency_overrides`, either as a constraint
+/// (`http: ^1.1.0`) or a hosted entry with a `version` key, followed by a
+/// `pubspec.lock` refresh.
+pub struct Pub;
+
+const PUBSPEC: &str = "pubspec.yaml";
+
+impl Ecosystem for Pub {
+    fn name(&self) -> &'static str {
+        "pub"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let sections: &[&str] = match request.dependency_kind {
+            None => &["dependencies", "dev_dependencies", "dependency_overrides"],
+            Some(DependencyKind::Normal) => &["dependencies"],
+            Some(DependencyKind::Dev) => &["dev_dependencies"],
+            Some(_) => return Err(unsupported_kind("pub", request.dependency_kind)),
+        };
+
+        let manifests = manifests_named(request, &[PUBSPEC]);
+        if manifests.is_empty() {
+            return Err(manifest_required(PUBSPEC));
+        }
+
+        let package = request.package_name.as_str();
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let scalars = yaml::scalars(content);
+            // `any` and sdk/git/path entries carry no version to move
+            let edits = scalars
+                .iter()
+                .filter(|entry| {
+                    sections.iter().any(|section| {
+                        entry.is_at(&[section, package])
+                            || entry.is_at(&[section, package, "version"])
+                    })
+                })
+                .filter(|entry| entry.value != "any")
+                .map(|entry| entry.set(&rewrite_requirement("pub", &entry.value, &versions.target)))
+                .collect();
+            let updated = yaml::apply(content, edits);
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+
+            let lock_path = format!("{}pubspec.lock", path.trim_end_matches(PUBSPEC));
+            if request.manifests.contains_key(&lock_path) {
+                let flutter = scalars
+                    .iter()
+                    .any(|entry| entry.is_at(&["dependencies", "flutter", "sdk"]));
+                let tool = if flutter { "flutter" } else { "dart" };
+                changes.push(lockfile_refresh(
+                    &lock_path,
+                    &[&[tool, "pub", "upgrade", package]],
+                ));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const PUBSPEC_YAML: &str = r#"name: app
+environment:
+  sdk: '>=3.0.0 <4.0.0'
+
+dependencies:
+  flutter:
+    sdk: flutter
+  http: ^0.13.6   # networking
+  provider:
+    hosted: https://pub.example.com
+    version: '6.0.5'
+  collection: any
+
+dev_dependencies:
+  lints: ^2.1.0
+"#;
+
+    fn upgrade(package: &str, target: &str, kind: Option<DependencyKind>) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "pub".to_string(),
+            package_name: package.to_string(),
+            dependency_kind: kind,
+            manifests: HashMap::from([
+                ("app/pubspec.yaml".to_string(), PUBSPEC_YAML.to_string()),
+                ("app/pubspec.lock".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("0.1.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        Pub.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_pubspec_dependencies() {
+        let http = upgrade("http", "1.2.0", None);
+        assert_eq!(
+            http[0].content,
+            PUBSPEC_YAML.replace("^0.13.6   # networking", "^1.2.0   # networking")
+        );
+        assert_eq!(http[1].file_path, "app/pubspec.lock");
+        assert_eq!(
+            http[1].metadata["argv"],
+            serde_json::json!([["flutter", "pub", "upgrade", "http"]])
+        );
+
+        let provider = upgrade("provider", "6.1.1", None);
+        assert!(provider[0].content.contains("    version: '6.1.1'\n"));
+
+        assert!(upgrade("collection", "1.18.0", None).is_empty());
+        assert!(upgrade("lints", "3.0.0", Some(DependencyKind::Normal)).is_empty());
+        assert!(upgrade("lints", "3.0.0", Some(DependencyKind::Dev))[0]
+            .content
+            .contains("  lints: ^3.0.0\n"));
+    }
+}
@@ -0,0 +1,190 @@
+use super::{lockfile_refresh, manifest_required, manifests_matching, modified, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Ruby projects: `gem` declarations in a `Gemfile` and `add_*dependency`
+/// calls in `*.gemspec` files. Gemfile edits are followed by a
+/// `Gemfile.lock` refresh.
+pub struct RubyGems;
+
+fn argument_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"^\s*,\s*(['"])([^'"]*)['"]"#).expect("valid pattern"))
+}
+
+impl Ecosystem for RubyGems {
+    fn name(&self) -> &'static str {
+        "rubygems"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.85
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let files = manifests_matching(request, |file_name| {
+            file_name == "Gemfile" || file_name.ends_with(".gemspec")
+        });
+        if files.is_empty() {
+            return Err(manifest_required("Gemfile"));
+        }
+
+        let name = regex::escape(&request.package_name);
+        let gemfile_declaration =
+            Regex::new(&format!(r#"(?m)^\s*gem\s*\(?\s*['"]{}['"]"#, name)).expect("valid pattern");
+        let gemspec_declaration = Regex::new(&format!(
+            r#"\.add_(?:runtime_|development_)?dependency\s*\(?\s*['"]{}['"]"#,
+            name
+        ))
+        .expect("valid pattern");
+
+        let mut changes = Vec::new();
+        for (path, content) in files {
+            let is_gemfile = path.rsplit('/').next() == Some("Gemfile");
+            let declaration = if is_gemfile {
+                &gemfile_declaration
+            } else {
+                &gemspec_declaration
+            };
+            let updated = update_declarations(content, declaration, &versions.target);
+            if updated == content {
+                continue;
+            }
+            changes.push(modified(path, updated));
+
+            if is_gemfile {
+                changes.push(lockfile_refresh(
+                    &format!("{}.lock", path),
+                    &[&["bundle", "update", "--conservative", &request.package_name]],
+                ));
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Rewrites the version constraint arguments that follow each declaration.
+/// A single constraint keeps its style; several (`'>= 1.0', '< 2'`) are
+/// collapsed into one pessimistic constraint.
+pub(super) fn update_declarations(
+    content: &str,
+    declaration: &Regex,
+    target: &ParsedVersion,
+) -> String {
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    for found in declaration.find_iter(content) {
+        let mut constraints = Vec::new();
+        let mut position = found.end();
+        while let Some(argument) = argument_pattern().captures(&content[position..]) {
+            let value = argument.get(2).expect("argument value");
+            if !value
+                .as_str()
+                .trim_start()
+                .starts_with(|c: char| c.is_ascii_digit() || "~<>=!".contains(c))
+            {
+                break;
+            }
+            let value = position + value.start()..position + value.end();
+            constraints.push((position..position + argument[0].len(), value));
+            position += argument[0].len();
+        }
+
+        let Some((_, first_value)) = constraints.first() else {
+            continue;
+        };
+        let written: Vec<&str> = constraints
+            .iter()
+            .map(|(_, value)| &content[value.clone()])
+            .collect();
+        edits.push((
+            first_value.clone(),
+            rewrite_requirement("rubygems", &written.join(", "), target),
+        ));
+        for (argument, _) in constraints.iter().skip(1) {
+            edits.push((argument.clone(), String::new()));
+        }
+    }
+
+    let mut updated = content.to_string();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, replacement) in edits {
+        updated.replace_range(range, &replacement);
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{RubyGemsScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    fn upgrade(files: &[(&str, &str)], package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "rubygems".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: RubyGemsScheme.parse("1.0").unwrap(),
+            target: RubyGemsScheme.parse(target).unwrap(),
+        };
+        RubyGems.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_gemfile_declarations() {
+        let gemfile = r#"source "https://rubygems.org"
+gem "rails", "~> 7.0.4"
+gem 'pg', '>= 0.18', '< 2.0', require: false
+gem "puma"
+"#;
+        let files = [("Gemfile", gemfile)];
+
+        let rails = upgrade(&files, "rails", "7.1.2");
+        assert_eq!(rails.len(), 2);
+        assert!(rails[0].content.contains(r#"gem "rails", "~> 7.1.2""#));
+        assert_eq!(rails[1].file_path, "Gemfile.lock");
+        assert_eq!(
+            rails[1].metadata["argv"],
+            serde_json::json!([["bundle", "update", "--conservative", "rails"]])
+        );
+
+        let pg = upgrade(&files, "pg", "2.1.0");
+        assert!(pg[0].content.contains("gem 'pg', '~> 2.1', require: false"));
+
+        assert!(upgrade(&files, "puma", "6.4.2").is_empty());
+    }
+
+    #[test]
+    fn test_gemspec_dependencies() {
+        let gemspec = r#"Gem::Specification.new do |spec|
+  spec.add_dependency "rack", "~> 2.2"
+  spec.add_development_dependency("rspec", "~> 3.12")
+end
+"#;
+        let files = [("acme.gemspec", gemspec)];
+
+        let rack = upgrade(&files, "rack", "3.0.8");
+        assert_eq!(rack.len(), 1);
+        assert!(rack[0]
+            .content
+            .contains(r#"spec.add_dependency "rack", "~> 3.0""#));
+
+        let rspec = upgrade(&files, "rspec", "3.13.0");
+        assert!(rspec[0].content.contains(r#"("rspec", "~> 3.13")"#));
+    }
+}
@@ -0,0 +1,259 @@
+use super::{manifest_required, manifests_named, modified, Ecosystem};
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{Change, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Swift packages: `.package(url:...)` declarations in `Package.swift` and
+/// the matching pin in `Package.resolved`. Packages are identified like
+/// SwiftPM does, by the last path component of the repository URL.
+pub struct SwiftPm;
+
+fn range_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#""([^"]+)"\s*(?:\.\.<|\.\.\.)\s*"([^"]+)""#).expect("valid pattern")
+    })
+}
+
+fn version_argument_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"((?:from|exact):\s*|\.exact\(\s*)"([^"]+)""#).expect("valid pattern")
+    })
+}
+
+fn url_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"url:\s*"([^"]+)""#).expect("valid pattern"))
+}
+
+impl Ecosystem for SwiftPm {
+    fn name(&self) -> &'static str {
+        "swiftpm"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.8
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let identity = identity(&request.package_name);
+        let manifests = manifests_named(request, &["Package.swift"]);
+        if manifests.is_empty() {
+            return Err(manifest_required("Package.swift"));
+        }
+
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let updated = update_manifest(content, &identity, &versions.target);
+            if updated != content {
+                changes.push(modified(path, updated));
+            }
+        }
+        for (path, content) in manifests_named(request, &["Package.resolved"]) {
+            let updated = update_resolved(content, &identity, versions.target.as_str());
+            if updated != content {
+                let mut change = modified(path, updated);
+                // The pinned revision still points at the old tag
+                change.metadata.insert(
+                    "command".to_string(),
+                    serde_json::json!(format!("swift package update {}", identity)),
+                );
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// SwiftPM package identity: `https://github.com/apple/swift-nio.git` is
+/// `swift-nio`.
+fn identity(package: &str) -> String {
+    let last = package
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(package);
+    last.trim_end_matches(".git").to_ascii_lowercase()
+}
+
+fn update_manifest(content: &str, identity: &str, target: &ParsedVersion) -> String {
+    let mut updated = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find(".package(") {
+        let arguments_start = start + ".package(".len();
+        let Some(arguments_len) = balanced_len(&rest[arguments_start..]) else {
+            break;
+        };
+        let arguments = &rest[arguments_start..arguments_start + arguments_len];
+        let matches = url_pattern()
+            .captures(arguments)
+            .is_some_and(|url| self::identity(&url[1]) == identity);
+
+        updated.push_str(&rest[..arguments_start]);
+        if matches {
+            updated.push_str(&rewrite_requirement(arguments, target));
+        } else {
+            updated.push_str(arguments);
+        }
+        rest = &rest[arguments_start + arguments_len..];
+    }
+    updated.push_str(rest);
+    updated
+}
+
+// Length of the text up to (not including) the parenthesis closing the
+// call whose arguments start at `text`.
+fn balanced_len(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string && depth == 0 => return Some(i),
+            ')' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `from:`/`exact:`/`.upToNextMajor(from:)` get the target; ranges become
+/// `"target"..<"next major"`. Branch and revision pins are left alone.
+fn rewrite_requirement(arguments: &str, target: &ParsedVersion) -> String {
+    if range_pattern().is_match(arguments) {
+        let upper = format!("{}.0.0", target.major() + 1);
+        return range_pattern()
+            .replace(arguments, format!(r#""{}"..<"{}""#, target, upper).as_str())
+            .into_owned();
+    }
+    version_argument_pattern()
+        .replace_all(arguments, |caps: &Captures| {
+            format!(r#"{}"{}""#, &caps[1], target)
+        })
+        .into_owned()
+}
+
+/// Updates the pin's `version` in `Package.resolved` (format v1 keys pins by
+/// `repositoryURL`, v2 and later by `identity`).
+fn update_resolved(content: &str, identity: &str, target: &str) -> String {
+    let key = Regex::new(r#""(identity|package|repositoryURL|location)"\s*:\s*"([^"]+)""#)
+        .expect("valid pattern");
+    let version = Regex::new(r#"("version"\s*:\s*")([^"]*)(")"#).expect("valid pattern");
+
+    let Some(pin) = key
+        .captures_iter(content)
+        .find(|caps| self::identity(&caps[2]) == identity)
+    else {
+        return content.to_string();
+    };
+    let pin_start = pin.get(0).expect("pin key").end();
+    let Some(field) = version.captures(&content[pin_start..]) else {
+        return content.to_string();
+    };
+    let value = field.get(2).expect("version value");
+
+    format!(
+        "{}{}{}",
+        &content[..pin_start + value.start()],
+        target,
+        &content[pin_start + value.end()..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const MANIFEST: &str = r#"let package = Package(
+    name: "App",
+    dependencies: [
+        .package(url: "https://github.com/apple/swift-nio.git", from: "2.58.0"),
+        .package(url: "https://github.com/vapor/vapor.git", .upToNextMinor(from: "4.77.0")),
+        .package(url: "https://github.com/pointfreeco/swift-snapshot-testing", "1.10.0"..<"2.0.0"),
+        .package(url: "https://github.com/apple/swift-log.git", exact: "1.5.2"),
+        .package(url: "https://github.com/acme/internal.git", branch: "main"),
+    ]
+)
+"#;
+
+    const RESOLVED: &str = r#"{
+  "pins" : [
+    {
+      "identity" : "swift-log",
+      "kind" : "remoteSourceControl",
+      "location" : "https://github.com/apple/swift-log.git",
+      "state" : {
+        "revision" : "532d8b529501fb73a2455b179e0bbb6d49b652ed",
+        "version" : "1.5.2"
+      }
+    },
+    {
+      "identity" : "swift-nio",
+      "kind" : "remoteSourceControl",
+      "location" : "https://github.com/apple/swift-nio.git",
+      "state" : {
+        "revision" : "cf281631ff10ec6111f2761052aa81896a83a007",
+        "version" : "2.58.0"
+      }
+    }
+  ],
+  "version" : 2
+}
+"#;
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "swiftpm".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([
+                ("Package.swift".to_string(), MANIFEST.to_string()),
+                ("Package.resolved".to_string(), RESOLVED.to_string()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        SwiftPm.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_manifest_and_resolved() {
+        let nio = upgrade("https://github.com/apple/swift-nio.git", "2.62.0");
+        assert_eq!(nio.len(), 2);
+        assert!(nio[0]
+            .content
+            .contains(r#"swift-nio.git", from: "2.62.0")"#));
+        assert!(nio[1].content.contains(r#""version" : "2.62.0""#));
+        assert!(nio[1].content.contains(r#""version" : "1.5.2""#));
+        assert!(nio[1].content.contains(r#""version" : 2"#));
+        assert_eq!(nio[1].metadata["command"], "swift package update swift-nio");
+    }
+
+    #[test]
+    fn test_requirement_forms() {
+        let vapor = upgrade("vapor", "4.89.0");
+        assert!(vapor[0]
+            .content
+            .contains(r#".upToNextMinor(from: "4.89.0")"#));
+
+        let snapshot = upgrade("swift-snapshot-testing", "1.15.1");
+        assert!(snapshot[0].content.contains(r#""1.15.1"..<"2.0.0""#));
+
+        let log = upgrade("Swift-Log", "1.5.4");
+        assert!(log[0].content.contains(r#"exact: "1.5.4""#));
+
+        assert!(upgrade("internal", "1.0.0").is_empty());
+    }
+}
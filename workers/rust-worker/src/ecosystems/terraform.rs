@@ -0,0 +1,358 @@
+use super::{lockfile_refresh, manifest_required, manifests_matching, modified, Ecosystem};
+use crate::rewrite::rewrite_requirement;
+use crate::version::{ParsedVersion, ResolvedVersions, VersionJump};
+use crate::{Change, RiskAssessment, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Terraform configurations: provider constraints in `required_providers`
+/// and module `version`/`?ref=` pins across `.tf` files. Providers are named
+/// by source (`hashicorp/aws`) and modules by registry or git source.
+pub struct Terraform;
+
+const LOCK_FILE: &str = ".terraform.lock.hcl";
+
+fn provider_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"(?m)^\s*([A-Za-z][\w-]*)\s*=\s*\{([^{}]*)\}"#).expect("valid pattern")
+    })
+}
+
+fn attribute_pattern(name: &str) -> Regex {
+    Regex::new(&format!(r#"(\b{}\s*=\s*")([^"]*)(")"#, name)).expect("valid pattern")
+}
+
+impl Ecosystem for Terraform {
+    fn name(&self) -> &'static str {
+        "terraform"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.8
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let files = manifests_matching(request, |file_name| file_name.ends_with(".tf"));
+        if files.is_empty() {
+            return Err(manifest_required("*.tf"));
+        }
+
+        let mut changes = Vec::new();
+        let mut lock_directories = Vec::new();
+        for (path, content) in files {
+            let with_providers = update_providers(content, &request.package_name, &versions.target);
+            let updated = update_modules(&with_providers, &request.package_name, &versions.target);
+            if updated == content {
+                continue;
+            }
+            if with_providers != content {
+                let directory = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+                if !lock_directories.contains(&directory) {
+                    lock_directories.push(directory);
+                }
+            }
+            changes.push(modified(path, updated));
+        }
+
+        // Provider upgrades invalidate the recorded hashes
+        for directory in lock_directories {
+            let lock_path = format!("{}{}", directory, LOCK_FILE);
+            if request.manifests.contains_key(&lock_path) {
+                changes.push(lockfile_refresh(
+                    &lock_path,
+                    &[
+                        &["terraform", "init", "-upgrade"],
+                        &["terraform", "providers", "lock"],
+                    ],
+                ));
+            }
+        }
+        Ok(changes)
+    }
+
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        let is_provider = manifests_matching(request, |file_name| file_name.ends_with(".tf"))
+            .iter()
+            .any(|(_, content)| provider_matches(content, &request.package_name));
+        if is_provider && assessment.version_jump == VersionJump::Major {
+            assessment.explanations.push(format!(
+                "Provider major upgrade to {} may change resource schemas and require state migration",
+                versions.target
+            ));
+        }
+    }
+}
+
+// Provider sources may carry the registry host (`registry.terraform.io/hashicorp/aws`)
+fn same_source(written: &str, package: &str) -> bool {
+    let written = written.to_ascii_lowercase();
+    let package = package.to_ascii_lowercase();
+    written == package || written.ends_with(&format!("/{}", package))
+}
+
+fn provider_matches(content: &str, package: &str) -> bool {
+    provider_pattern().captures_iter(content).any(|entry| {
+        attribute_pattern("source")
+            .captures(&entry[2])
+            .is_some_and(|source| same_source(&source[2], package))
+    })
+}
+
+fn update_providers(content: &str, package: &str, target: &ParsedVersion) -> String {
+    let source = attribute_pattern("source");
+    let version = attribute_pattern("version");
+    provider_pattern()
+        .replace_all(content, |entry: &Captures| {
+            let matches = source
+                .captures(&entry[2])
+                .is_some_and(|source| same_source(&source[2], package));
+            if !matches {
+                return entry[0].to_string();
+            }
+            version
+                .replace(&entry[0], |field: &Captures| {
+                    format!(
+                        "{}{}{}",
+                        &field[1],
+                        rewrite_requirement("terraform", &field[2], target),
+                        &field[3]
+                    )
+                })
+                .into_owned()
+        })
+        .into_owned()
+}
+
+/// Registry modules get their `version` argument rewritten; git sources get
+/// their `?ref=` tag replaced, keeping a `v` prefix if one was used.
+fn update_modules(content: &str, package: &str, target: &ParsedVersion) -> String {
+    let module_start = Regex::new(r#"module\s+"[^"]*"\s*\{"#).expect("valid pattern");
+    let source = attribute_pattern("source");
+    let version = attribute_pattern("version");
+    let git_ref = Regex::new(r"(\?ref=)(v?)[^&]*").expect("valid pattern");
+
+    let mut updated = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = module_start.find(rest) {
+        let body_start = start.end();
+        let Some(body_len) = block_len(&rest[body_start..]) else {
+            break;
+        };
+        let body = &rest[body_start..body_start + body_len];
+        updated.push_str(&rest[..body_start]);
+
+        let written_source = source.captures(body).map(|caps| caps[2].to_string());
+        let new_body = match written_source {
+            Some(written) if written == package => version
+                .replace(body, |field: &Captures| {
+                    format!(
+                        "{}{}{}",
+                        &field[1],
+                        rewrite_requirement("terraform", &field[2], target),
+                        &field[3]
+                    )
+                })
+                .into_owned(),
+            Some(written) if written.contains("?ref=") && git_source_matches(&written, package) => {
+                let new_source = git_ref
+                    .replace(&written, |caps: &Captures| {
+                        format!("{}{}{}", &caps[1], &caps[2], target)
+                    })
+                    .into_owned();
+                body.replacen(&written, &new_source, 1)
+            }
+            _ => body.to_string(),
+        };
+        updated.push_str(&new_body);
+        rest = &rest[body_start + body_len..];
+    }
+    updated.push_str(rest);
+    updated
+}
+
+// `git::https://github.com/org/repo.git//modules/x?ref=v1` names `org/repo`
+// (or `github.com/org/repo`); anything else in the address is not compared.
+fn git_source_matches(written: &str, package: &str) -> bool {
+    let source = written.strip_prefix("git::").unwrap_or(written);
+    let source = source.split('?').next().unwrap_or(source);
+    let (address, separators) = match source.split_once("://") {
+        Some((_, rest)) => (rest, &['/'][..]),
+        None => (source, &['/', ':'][..]),
+    };
+    let address = address
+        .split_once("//")
+        .map_or(address, |(repository, _)| repository);
+    let address = match address.find('@') {
+        Some(at) if !address[..at].contains('/') => &address[at + 1..],
+        _ => address,
+    };
+    let Some((host, path)) = address.split_once(separators) else {
+        return false;
+    };
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    path.eq_ignore_ascii_case(package) || format!("{}/{}", host, path).eq_ignore_ascii_case(package)
+}
+
+// Length of a block body up to (not including) its closing brace.
+fn block_len(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string && depth == 0 => return Some(i),
+            '}' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use crate::RiskLevel;
+    use std::collections::HashMap;
+
+    const VERSIONS_TF: &str = r#"terraform {
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+    random = {
+      source  = "registry.terraform.io/hashicorp/random"
+      version = ">= 3.1, < 4.0"
+    }
+  }
+}
+"#;
+
+    const MAIN_TF: &str = r#"module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.2"
+
+  tags = {
+    Team = "platform"
+  }
+}
+
+module "labels" {
+  source = "git::https://github.com/cloudposse/terraform-null-label.git?ref=v0.25.0"
+}
+"#;
+
+    fn request(package: &str, target: &str) -> (UpgradeRequest, ResolvedVersions) {
+        let request = UpgradeRequest {
+            ecosystem: "terraform".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([
+                ("infra/versions.tf".to_string(), VERSIONS_TF.to_string()),
+                ("infra/main.tf".to_string(), MAIN_TF.to_string()),
+                ("infra/.terraform.lock.hcl".to_string(), String::new()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("5.0.0").unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        (request, versions)
+    }
+
+    fn upgrade(package: &str, target: &str) -> Vec<Change> {
+        let (request, versions) = request(package, target);
+        Terraform.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_provider_constraints() {
+        let aws = upgrade("hashicorp/aws", "6.2.0");
+        assert_eq!(aws.len(), 2);
+        assert!(aws[0].content.contains(r#"version = "~> 6.2""#));
+        assert!(aws[0].content.contains(r#"version = ">= 3.1, < 4.0""#));
+        assert_eq!(aws[1].file_path, "infra/.terraform.lock.hcl");
+
+        let random = upgrade("hashicorp/random", "4.1.0");
+        assert!(random[0].content.contains(r#"version = "~> 4.1""#));
+    }
+
+    #[test]
+    fn test_module_pins() {
+        let vpc = upgrade("terraform-aws-modules/vpc/aws", "5.5.1");
+        assert_eq!(vpc.len(), 1);
+        assert!(vpc[0].content.contains(r#"version = "5.5.1""#));
+        assert!(vpc[0].content.contains(r#"Team = "platform""#));
+
+        let labels = upgrade("cloudposse/terraform-null-label", "0.25.1");
+        assert!(labels[0]
+            .content
+            .contains("terraform-null-label.git?ref=v0.25.1"));
+    }
+
+    #[test]
+    fn test_module_sources_match_exactly() {
+        let (mut request, versions) = request("terraform-aws-modules/vpc/aws", "5.5.1");
+        request.manifests = HashMap::from([(
+            "main.tf".to_string(),
+            r#"module "endpoints" {
+  source  = "terraform-aws-modules/vpc/aws-endpoints"
+  version = "5.1.2"
+}
+
+module "label" {
+  source = "git::https://github.com/cloudposse/terraform-null-label-extra.git?ref=v0.25.0"
+}
+"#
+            .to_string(),
+        )]);
+        assert!(Terraform
+            .generate_changes(&request, &versions)
+            .unwrap()
+            .is_empty());
+        request.package_name = "cloudposse/terraform-null-label".to_string();
+        assert!(Terraform
+            .generate_changes(&request, &versions)
+            .unwrap()
+            .is_empty());
+
+        assert!(git_source_matches(
+            "git::https://github.com/cloudposse/terraform-null-label.git//modules/x?ref=v1",
+            "github.com/cloudposse/terraform-null-label"
+        ));
+        assert!(git_source_matches(
+            "git@github.com:cloudposse/terraform-null-label.git?ref=v1",
+            "cloudposse/terraform-null-label"
+        ));
+        assert!(!git_source_matches(
+            "git::https://github.com/cloudposse/terraform-null-label.git?ref=v1",
+            "terraform-null-label"
+        ));
+    }
+
+    #[test]
+    fn test_provider_major_risk() {
+        let (request, versions) = request("hashicorp/aws", "6.0.0");
+        let mut assessment = RiskAssessment {
+            risk_level: RiskLevel::High,
+            breaking_changes: true,
+            version_jump: VersionJump::Major,
+            ..Default::default()
+        };
+        Terraform.assess_risk(&request, &versions, &mut assessment);
+        assert_eq!(assessment.explanations.len(), 1);
+    }
+}
@@ -0,0 +1,197 @@
+use super::{manifest_required, manifests_named, modified, Ecosystem};
+use crate::version::ResolvedVersions;
+use crate::{Change, ChangeType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Request metadata carrying the vcpkg registry commit to move
+/// `builtin-baseline` to.
+pub const VCPKG_BASELINE_METADATA_KEY: &str = "vcpkg_baseline";
+
+/// vcpkg manifest mode: `version>=` constraints in `vcpkg.json`
+/// dependencies, `overrides` pins, and `builtin-baseline` bumps.
+pub struct Vcpkg;
+
+fn version_field_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"("(?:version>=|version|version-semver|version-date|version-string)"\s*:\s*")([^"]*)(")"#)
+            .expect("valid pattern")
+    })
+}
+
+fn baseline_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"("builtin-baseline"\s*:\s*")([^"]*)(")"#).expect("valid pattern")
+    })
+}
+
+fn requested_baseline(request: &UpgradeRequest) -> Option<&str> {
+    request
+        .metadata
+        .get(VCPKG_BASELINE_METADATA_KEY)
+        .and_then(|value| value.as_str())
+}
+
+impl Ecosystem for Vcpkg {
+    fn name(&self) -> &'static str {
+        "vcpkg"
+    }
+
+    fn compatibility(&self, _request: &UpgradeRequest) -> f64 {
+        0.75
+    }
+
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let manifests = manifests_named(request, &["vcpkg.json"]);
+        if manifests.is_empty() {
+            return Err(manifest_required("vcpkg.json"));
+        }
+
+        // Objects naming the port: dependency entries and overrides
+        let entry = Regex::new(&format!(
+            r#"\{{[^{{}}]*"name"\s*:\s*"{}"[^{{}}]*\}}"#,
+            regex::escape(&request.package_name)
+        ))
+        .expect("valid pattern");
+
+        let mut changes = Vec::new();
+        for (path, content) in manifests {
+            let mut updated = entry
+                .replace_all(content, |object: &Captures| {
+                    version_field_pattern()
+                        .replace_all(&object[0], |field: &Captures| {
+                            format!("{}{}{}", &field[1], versions.target, &field[3])
+                        })
+                        .into_owned()
+                })
+                .into_owned();
+
+            let mut previous_baseline = None;
+            if let Some(baseline) = requested_baseline(request) {
+                updated = baseline_pattern()
+                    .replace(&updated, |field: &Captures| {
+                        if &field[2] != baseline {
+                            previous_baseline = Some(field[2].to_string());
+                        }
+                        format!("{}{}{}", &field[1], baseline, &field[3])
+                    })
+                    .into_owned();
+            }
+
+            if updated == content {
+                continue;
+            }
+            let mut change = modified(path, updated);
+            if let Some(previous) = previous_baseline {
+                change.change_type = ChangeType::BaselineBump;
+                change
+                    .metadata
+                    .insert("previous_baseline".to_string(), serde_json::json!(previous));
+            }
+            changes.push(change);
+        }
+        Ok(changes)
+    }
+
+    /// Baseline bumps move every unpinned port, not just the requested one.
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        _versions: &ResolvedVersions,
+        assessment: &mut RiskAssessment,
+    ) {
+        if let Some(baseline) = requested_baseline(request) {
+            assessment.risk_level = assessment.risk_level.max(RiskLevel::Medium);
+            assessment.explanations.push(format!(
+                "Moving builtin-baseline to {} also upgrades every port without an override",
+                baseline
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{ConanScheme, VersionScheme};
+    use std::collections::HashMap;
+
+    const MANIFEST: &str = r#"{
+  "name": "app",
+  "dependencies": [
+    "zlib",
+    { "name": "boost-asio", "version>=": "1.83.0" },
+    { "name": "fmt", "features": ["std"] }
+  ],
+  "builtin-baseline": "3426db05b996481ca31e95fff3734cf23e0f51bc",
+  "overrides": [
+    { "name": "fmt", "version": "10.1.1" }
+  ]
+}
+"#;
+
+    fn upgrade(package: &str, target: &str, baseline: Option<&str>) -> Vec<Change> {
+        let mut metadata = HashMap::new();
+        if let Some(baseline) = baseline {
+            metadata.insert(
+                VCPKG_BASELINE_METADATA_KEY.to_string(),
+                serde_json::json!(baseline),
+            );
+        }
+        let request = UpgradeRequest {
+            ecosystem: "vcpkg".to_string(),
+            package_name: package.to_string(),
+            target_version: target.to_string(),
+            metadata,
+            manifests: HashMap::from([("vcpkg.json".to_string(), MANIFEST.to_string())]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: ConanScheme.parse("1.0").unwrap(),
+            target: ConanScheme.parse(target).unwrap(),
+        };
+        Vcpkg.generate_changes(&request, &versions).unwrap()
+    }
+
+    #[test]
+    fn test_constraints_and_overrides() {
+        let asio = upgrade("boost-asio", "1.84.0", None);
+        assert!(asio[0]
+            .content
+            .contains(r#"{ "name": "boost-asio", "version>=": "1.84.0" }"#));
+        assert!(matches!(asio[0].change_type, ChangeType::Modify));
+
+        let fmt = upgrade("fmt", "10.2.1", None);
+        assert!(fmt[0]
+            .content
+            .contains(r#"{ "name": "fmt", "version": "10.2.1" }"#));
+        assert!(fmt[0].content.contains(r#""features": ["std"]"#));
+
+        // zlib only follows the baseline
+        assert!(upgrade("zlib", "1.3.1", None).is_empty());
+    }
+
+    #[test]
+    fn test_baseline_bump() {
+        let zlib = upgrade(
+            "zlib",
+            "1.3.1",
+            Some("c8696863d371ab7f46e213d8f5ca923c4aef2a00"),
+        );
+        assert_eq!(zlib.len(), 1);
+        assert!(matches!(zlib[0].change_type, ChangeType::BaselineBump));
+        assert!(zlib[0]
+            .content
+            .contains(r#""builtin-baseline": "c8696863d371ab7f46e213d8f5ca923c4aef2a00""#));
+        assert_eq!(
+            zlib[0].metadata["previous_baseline"],
+            "3426db05b996481ca31e95fff3734cf23e0f51bc"
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use std::ops::Range;
+
+/// A scalar value in a block-style YAML document, addressed by the mapping
+/// keys and sequence indices leading to it (`dependencies`, `0`, `version`).
+///
+/// Edits replace only the value's bytes, so comments, anchors, quoting and
+/// indentation elsewhere in the file survive untouched. Flow collections
+/// (`{...}`, `[...]`), aliases and block scalars are not reported.
+#[derive(Debug)]
+pub(super) struct Scalar {
+    pub path: Vec<String>,
+    pub value: String,
+    /// Byte range of the value, inside any quotes.
+    span: Range<usize>,
+    quoted: bool,
+}
+
+/// A replacement of one scalar's bytes, produced by [`Scalar::set`].
+pub(super) type Edit = (Range<usize>, String);
+
+impl Scalar {
+    /// Whether the scalar sits at `pattern`, where `*` matches any key or
+    /// index.
+    pub fn is_at(&self, pattern: &[&str]) -> bool {
+        self.path.len() == pattern.len()
+            && self
+                .path
+                .iter()
+                .zip(pattern)
+                .all(|(segment, expected)| *expected == "*" || segment == expected)
+    }
+
+    /// Replaces the value, quoting it when a previously plain scalar would
+    /// otherwise change meaning (`>=1.0` starts a folded block).
+    pub fn set(&self, value: &str) -> Edit {
+        let text = if !self.quoted && needs_quotes(value) {
+            format!("\"{}\"", value)
+        } else {
+            value.to_string()
+        };
+        (self.span.clone(), text)
+    }
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        || value.contains(": ")
+        || value.contains(" #")
+}
+
+/// Applies `edits` to `content`, leaving every other byte as it was.
+pub(super) fn apply(content: &str, mut edits: Vec<Edit>) -> String {
+    edits.sort_by_key(|(span, _)| span.start);
+    let mut updated = String::with_capacity(content.len());
+    let mut copied = 0;
+    for (span, text) in edits {
+        updated.push_str(&content[copied..span.start]);
+        updated.push_str(&text);
+        copied = span.end;
+    }
+    updated.push_str(&content[copied..]);
+    updated
+}
+
+struct Frame {
+    indent: usize,
+    segment: String,
+    sequence: bool,
+    items: usize,
+}
+
+/// Every scalar in a block-style YAML document, in document order. Only the
+/// first document of a stream is read.
+pub(super) fn scalars(content: &str) -> Vec<Scalar> {
+    let mut scalars = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut root_items = 0;
+    // Indent of the key or item owning a `|`/`>` block being skipped
+    let mut block_owner: Option<usize> = None;
+    let mut seen_content = false;
+
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let indent = text.len() - text.trim_start_matches(' ').len();
+        let mut rest = &text[indent..];
+
+        if let Some(owner) = block_owner {
+            if rest.is_empty() || indent > owner {
+                continue;
+            }
+            block_owner = None;
+        }
+        if rest.is_empty() || rest.starts_with('#') || rest.starts_with('%') {
+            continue;
+        }
+        if rest.starts_with("---") || rest.starts_with("...") {
+            if seen_content {
+                break;
+            }
+            continue;
+        }
+        seen_content = true;
+
+        let is_item = rest == "-" || rest.starts_with("- ");
+        while let Some(frame) = frames.last() {
+            let closed =
+                frame.indent > indent || (frame.indent == indent && (frame.sequence || !is_item));
+            if !closed {
+                break;
+            }
+            frames.pop();
+        }
+
+        // Sequence items, possibly several on one line (`- - a`)
+        let mut column = indent;
+        while rest == "-" || rest.starts_with("- ") {
+            let index = match frames.last_mut() {
+                Some(frame) => &mut frame.items,
+                None => &mut root_items,
+            };
+            *index += 1;
+            let segment = (*index - 1).to_string();
+            frames.push(Frame {
+                indent: column,
+                segment,
+                sequence: true,
+                items: 0,
+            });
+            let after = rest[1..].trim_start_matches(' ');
+            column += rest.len() - after.len();
+            rest = after;
+        }
+        if rest.is_empty() || rest.starts_with('#') {
+            continue;
+        }
+
+        let mut path: Vec<String> = frames.iter().map(|frame| frame.segment.clone()).collect();
+        let value_at = match split_key(rest) {
+            Some((key, value_at)) => {
+                path.push(key.clone());
+                frames.push(Frame {
+                    indent: column,
+                    segment: key,
+                    sequence: false,
+                    items: 0,
+                });
+                value_at
+            }
+            None => 0,
+        };
+
+        match parse_value(&rest[value_at..]) {
+            Value::Scalar(span, quoted) => {
+                let base = start + column + value_at;
+                scalars.push(Scalar {
+                    path,
+                    value: content[base + span.start..base + span.end].to_string(),
+                    span: base + span.start..base + span.end,
+                    quoted,
+                });
+            }
+            Value::Block => block_owner = Some(column),
+            Value::Other => {}
+        }
+    }
+    scalars
+}
+
+/// Splits `key: value`, returning the unquoted key and the offset of the
+/// text after the colon.
+fn split_key(text: &str) -> Option<(String, usize)> {
+    let (key, after_key) = if let Some(quote) = text.chars().next().filter(|c| "\"'".contains(*c)) {
+        let end = text[1..].find(quote)? + 1;
+        (text[1..end].to_string(), end + 1)
+    } else {
+        if text.starts_with(['[', '{']) {
+            return None;
+        }
+        let colon = text
+            .match_indices(':')
+            .map(|(i, _)| i)
+            .find(|&i| text[i + 1..].is_empty() || text[i + 1..].starts_with([' ', '\t']))?;
+        if text[..colon].contains(" #") {
+            return None;
+        }
+        (text[..colon].trim_end().to_string(), colon)
+    };
+
+    let colon = after_key + text[after_key..].len() - text[after_key..].trim_start().len();
+    if !text[colon..].starts_with(':') {
+        return None;
+    }
+    Some((key, colon + 1))
+}
+
+enum Value {
+    Scalar(Range<usize>, bool),
+    Block,
+    Other,
+}
+
+/// Locates the scalar in the value part of a line, skipping anchors and
+/// tags and stopping at a trailing comment.
+fn parse_value(text: &str) -> Value {
+    let mut at = text.len() - text.trim_start().len();
+    while text[at..].starts_with(['&', '!']) {
+        let token = text[at..].find([' ', '\t']).unwrap_or(text.len() - at);
+        at += token;
+        at += text[at..].len() - text[at..].trim_start().len();
+    }
+    let value = &text[at..];
+
+    match value.chars().next() {
+        None | Some('#') | Some('*') | Some('[') | Some('{') => Value::Other,
+        Some('|') | Some('>') => Value::Block,
+        Some(quote @ ('"' | '\'')) => {
+            let mut escaped = false;
+            let end = value[1..].char_indices().find_map(|(i, c)| match c {
+                _ if escaped => {
+                    escaped = false;
+                    None
+                }
+                '\\' if quote == '"' => {
+                    escaped = true;
+                    None
+                }
+                c if c == quote => Some(i + 1),
+                _ => None,
+            });
+            match end {
+                Some(end) => Value::Scalar(at + 1..at + end, true),
+                None => Value::Other,
+            }
+        }
+        Some(_) => {
+            let end = value.find(" #").unwrap_or(value.len());
+            let plain = value[..end].trim_end();
+            Value::Scalar(at..at + plain.len(), false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"# Chart dependencies
+name: app
+defaults: &defaults
+  version: "1.0.0"   # pinned
+dependencies:
+- name: redis
+  version: ~17.3.0
+  condition: redis.enabled
+- name: 'postgresql'
+  version: 12.1.2 # database
+  tags:
+    - db
+description: |
+  name: not-a-key
+other:
+  <<: *defaults
+"#;
+
+    #[test]
+    fn test_scalars_and_edits() {
+        let scalars = scalars(DOCUMENT);
+        let find = |pattern: &[&str]| {
+            scalars
+                .iter()
+                .find(|scalar| scalar.is_at(pattern))
+                .unwrap_or_else(|| panic!("no scalar at {:?}", pattern))
+        };
+
+        assert_eq!(find(&["defaults", "version"]).value, "1.0.0");
+        assert_eq!(find(&["dependencies", "0", "version"]).value, "~17.3.0");
+        assert_eq!(find(&["dependencies", "1", "name"]).value, "postgresql");
+        assert_eq!(find(&["dependencies", "1", "version"]).value, "12.1.2");
+        assert_eq!(find(&["dependencies", "1", "tags", "0"]).value, "db");
+        assert!(!scalars.iter().any(|scalar| scalar.value == "not-a-key"));
+        assert!(!scalars.iter().any(|scalar| scalar.is_at(&["other", "<<"])));
+
+        let edits = vec![
+            find(&["dependencies", "0", "version"]).set(">=18.0.0"),
+            find(&["dependencies", "1", "version"]).set("13.0.0"),
+            find(&["defaults", "version"]).set("2.0.0"),
+        ];
+        let updated = apply(DOCUMENT, edits);
+        assert_eq!(
+            updated,
+            DOCUMENT
+                .replace("\"1.0.0\"   # pinned", "\"2.0.0\"   # pinned")
+                .replace("~17.3.0", "\">=18.0.0\"")
+                .replace("12.1.2 # database", "13.0.0 # database")
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::rewrite::cargo_tables;
+use crate::version::ResolvedVersions;
+use crate::{RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker};
+use toml_edit::{DocumentMut, Item};
+
+/// Features a manifest enables on the upgraded crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureSelection {
+    pub manifest: String,
+    pub features: Vec<String>,
+}
+
+/// Reads the `features = [...]` lists of every entry for the package in the
+/// supplied `Cargo.toml` files, including target-specific tables,
+/// `[workspace.dependencies]` and renamed entries.
+pub fn declared_features(request: &UpgradeRequest) -> Vec<FeatureSelection> {
+    let mut selections: Vec<FeatureSelection> = request
+        .manifests
+        .iter()
+        .filter(|(path, _)| path.rsplit('/').next() == Some("Cargo.toml"))
+        .filter_map(|(path, content)| {
+            let doc = content.parse::<DocumentMut>().ok()?;
+            let mut features: Vec<String> = dependency_tables(&doc)
+                .into_iter()
+                .flat_map(|table| table.iter())
+                .filter(|(key, entry)| {
+                    entry.get("package").and_then(Item::as_str).unwrap_or(key)
+                        == request.package_name
+                })
+                .filter_map(|(_, entry)| entry.get("features")?.as_array())
+                .flat_map(|features| features.iter().filter_map(|f| f.as_str()))
+                .map(str::to_string)
+                .collect();
+            features.sort();
+            features.dedup();
+            (!features.is_empty()).then(|| FeatureSelection {
+                manifest: path.clone(),
+                features,
+            })
+        })
+        .collect();
+    selections.sort_by(|a, b| a.manifest.cmp(&b.manifest));
+    selections
+}
+
+fn dependency_tables(doc: &DocumentMut) -> Vec<&dyn toml_edit::TableLike> {
+    let tables = cargo_tables(None);
+    let mut found: Vec<&dyn toml_edit::TableLike> = tables
+        .iter()
+        .filter_map(|table| doc.get(table)?.as_table_like())
+        .collect();
+    if let Some(targets) = doc.get("target").and_then(Item::as_table_like) {
+        for (_, platform) in targets.iter() {
+            found.extend(
+                tables
+                    .iter()
+                    .filter_map(|table| platform.get(table)?.as_table_like()),
+            );
+        }
+    }
+    if let Some(workspace) = doc
+        .get("workspace")
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(Item::as_table_like)
+    {
+        found.push(workspace);
+    }
+    found
+}
+
+impl UpgradeWorker {
+    /// Flags enabled features the target release no longer publishes. Only
+    /// features the current release lists are checked, since implicit
+    /// features of optional dependencies are not published by the registry.
+    pub(crate) async fn check_features(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+    ) -> Result<(), UpgradeError> {
+        let selections = declared_features(request);
+        if selections.is_empty() {
+            return Ok(());
+        }
+
+        let releases = self.registry_releases(request).await?.unwrap_or_default();
+        let published = |version: &str| {
+            releases
+                .iter()
+                .find(|release| release.version == version)
+                .and_then(|release| release.features.clone())
+        };
+        let (Some(current), Some(target)) = (
+            published(versions.current.as_str()),
+            published(versions.target.as_str()),
+        ) else {
+            return Ok(());
+        };
+
+        for selection in selections {
+            for feature in selection.features {
+                if current.contains(&feature) && !target.contains(&feature) {
+                    risk.risk_level = risk.risk_level.max(RiskLevel::High);
+                    risk.breaking_changes = true;
+                    risk.explanations.push(format!(
+                        "{} {} removes or renames feature \"{}\" enabled in {}",
+                        request.package_name, versions.target, feature, selection.manifest
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    const MANIFEST: &str = r#"[package]
+name = "app"
+
+[dependencies]
+config = { version = "0.13.4", features = ["toml", "ini"] }
+
+[target.'cfg(windows)'.dependencies]
+config = { version = "0.13.4", default-features = false, features = ["json5"] }
+"#;
+
+    fn request() -> UpgradeRequest {
+        UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "cargo".to_string(),
+            package_name: "config".to_string(),
+            current_version: "0.13.4".to_string(),
+            target_version: "0.14.0".to_string(),
+            manifests: HashMap::from([("Cargo.toml".to_string(), MANIFEST.to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_declared_features() {
+        assert_eq!(
+            declared_features(&request()),
+            vec![FeatureSelection {
+                manifest: "Cargo.toml".to_string(),
+                features: vec!["ini".to_string(), "json5".to_string(), "toml".to_string()],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_removed_feature_is_breaking() {
+        let release = |version: &str, features: &[&str]| ReleaseInfo {
+            features: Some(features.iter().map(|f| f.to_string()).collect()),
+            ..ReleaseInfo::new(version)
+        };
+        let registry = StaticRegistry::new().with_releases(
+            "cargo",
+            "config",
+            vec![
+                release("0.13.4", &["default", "toml", "ini", "json5"]),
+                release("0.14.0", &["default", "toml", "json5", "ron"]),
+            ],
+        );
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+
+        let response = worker.process_upgrade(request()).await.unwrap();
+        assert!(response.risk_assessment.breaking_changes);
+        assert_eq!(response.risk_assessment.risk_level, RiskLevel::High);
+        assert_eq!(
+            response.risk_assessment.explanations,
+            ["config 0.14.0 removes or renames feature \"ini\" enabled in Cargo.toml"]
+        );
+        // The rewritten entries keep their feature lists
+        assert!(response.changes[0]
+            .content
+            .contains(r#"config = { version = "0.14.0", features = ["toml", "ini"] }"#));
+    }
+}
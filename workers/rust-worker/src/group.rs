@@ -0,0 +1,500 @@
+use crate::version::{self, VersionSpec};
+use crate::{
+    ChangeFormat, ErrorType, PerformanceImpact, RecommendedAction, RiskLevel, UpgradeError,
+    UpgradeRequest, UpgradeResponse, UpgradeWorker,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Upgrades evaluated together, e.g. a framework and its plugins, in the
+/// order they are processed. Groups are only evaluated: nothing is written,
+/// committed or proposed for them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupUpgradeRequest {
+    pub upgrades: Vec<UpgradeRequest>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionKind {
+    /// The target of one upgrade declares a peer range the target of
+    /// another falls outside of.
+    PeerConflict,
+    /// The targets declare the same peer dependency, which the group does
+    /// not upgrade.
+    SharedPeer,
+    /// The upgrades edit the same file, each from its original content.
+    SharedFile,
+}
+
+/// Something that only goes wrong when the upgrades are applied together.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub kind: InteractionKind,
+    pub packages: Vec<String>,
+    pub detail: String,
+}
+
+/// Risk of the group as a whole: the worst of its upgrades, raised by the
+/// interactions between them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupRiskAssessment {
+    pub risk_level: RiskLevel,
+    pub breaking_changes: bool,
+    pub performance_impact: PerformanceImpact,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interactions: Vec<Interaction>,
+    /// Human-readable reasons for the assessed risk level.
+    pub explanations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupUpgradeResponse {
+    /// One response per upgrade, in request order.
+    pub upgrades: Vec<UpgradeResponse>,
+    pub risk_assessment: GroupRiskAssessment,
+    /// The most restrictive action of the upgrades, and never an automatic
+    /// merge of a group above low risk.
+    pub recommended_action: RecommendedAction,
+    /// The lowest compatibility score of the upgrades.
+    pub compatibility_score: f64,
+}
+
+/// A member of the group as the interaction checks see it.
+struct Member<'a> {
+    ecosystem: &'a str,
+    package: &'a str,
+    version: &'a str,
+    peers: BTreeMap<String, String>,
+    files: HashSet<&'a str>,
+}
+
+impl UpgradeWorker {
+    /// Processes each upgrade of the group and assesses their combined
+    /// risk. The first upgrade that fails fails the group. Settings that
+    /// would write, commit or propose the upgrades are refused, as each
+    /// would act before the risk of the group is known.
+    pub async fn process_group(
+        &self,
+        request: GroupUpgradeRequest,
+    ) -> Result<GroupUpgradeResponse, UpgradeError> {
+        let invalid = |message: String| UpgradeError {
+            message,
+            error_type: ErrorType::Validation,
+        };
+        if request.upgrades.is_empty() {
+            return Err(invalid("A group needs at least one upgrade".to_string()));
+        }
+        if self.config.commit.is_some() || self.config.scm.is_some() {
+            return Err(invalid(
+                "Groups cannot be committed or proposed: process them on a worker \
+                 without commit and SCM settings"
+                    .to_string(),
+            ));
+        }
+        let writes = |upgrade: &UpgradeRequest| {
+            upgrade.local_path.is_some()
+                || upgrade.change_format == ChangeFormat::FormatPatch
+                    && self.config.patch_directory.is_some()
+        };
+        if let Some(upgrade) = request.upgrades.iter().find(|upgrade| writes(upgrade)) {
+            return Err(invalid(format!(
+                "Upgrade of '{}' would be written before the group is assessed: \
+                 groups accept neither local workspaces nor written patches",
+                upgrade.package_name
+            )));
+        }
+
+        let mut upgrades = Vec::with_capacity(request.upgrades.len());
+        let mut peers = Vec::with_capacity(request.upgrades.len());
+        for upgrade in request.upgrades.iter().cloned() {
+            let package = upgrade.package_name.clone();
+            let response =
+                self.process_upgrade(upgrade.clone())
+                    .await
+                    .map_err(|e| UpgradeError {
+                        message: format!("Upgrade of '{}' failed: {}", package, e.message),
+                        error_type: e.error_type,
+                    })?;
+            peers.push(
+                self.target_peers(&upgrade, &response.resolved_version)
+                    .await,
+            );
+            upgrades.push(response);
+        }
+
+        let members: Vec<Member<'_>> = request
+            .upgrades
+            .iter()
+            .zip(&upgrades)
+            .zip(peers)
+            .map(|((upgrade, response), peers)| Member {
+                ecosystem: &upgrade.ecosystem,
+                package: target_package(upgrade),
+                version: &response.resolved_version,
+                peers,
+                files: response
+                    .changes
+                    .iter()
+                    .map(|change| change.file_path.as_str())
+                    .collect(),
+            })
+            .collect();
+        let risk_assessment = assess_group(&members, &upgrades, interactions(&members));
+
+        let mut recommended_action = upgrades
+            .iter()
+            .map(|upgrade| upgrade.recommended_action)
+            .max_by_key(|action| restrictiveness(*action))
+            .unwrap_or_default();
+        if recommended_action == RecommendedAction::AutoMerge
+            && risk_assessment.risk_level > RiskLevel::Low
+        {
+            recommended_action = RecommendedAction::ReviewRequired;
+        }
+        let compatibility_score = upgrades
+            .iter()
+            .map(|upgrade| upgrade.compatibility_score)
+            .fold(1.0, f64::min);
+
+        Ok(GroupUpgradeResponse {
+            upgrades,
+            risk_assessment,
+            recommended_action,
+            compatibility_score,
+        })
+    }
+
+    /// Peer dependencies the resolved target declares; empty when the
+    /// registry does not know them.
+    async fn target_peers(
+        &self,
+        request: &UpgradeRequest,
+        resolved: &str,
+    ) -> BTreeMap<String, String> {
+        let lookup = UpgradeRequest {
+            ecosystem: request.ecosystem.clone(),
+            package_name: target_package(request).to_string(),
+            ..Default::default()
+        };
+        let releases = self
+            .registry_releases(&lookup)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        releases
+            .into_iter()
+            .find(|release| release.version == resolved)
+            .and_then(|release| release.peer_dependencies)
+            .unwrap_or_default()
+    }
+}
+
+/// The package the upgrade ends on, the replacement of renames.
+fn target_package(request: &UpgradeRequest) -> &str {
+    request
+        .replacement_package
+        .as_deref()
+        .unwrap_or(&request.package_name)
+}
+
+fn restrictiveness(action: RecommendedAction) -> u8 {
+    match action {
+        RecommendedAction::AutoMerge => 0,
+        RecommendedAction::SecurityFastTrack => 1,
+        RecommendedAction::ReviewRequired => 2,
+        RecommendedAction::Block => 3,
+    }
+}
+
+fn interactions(members: &[Member<'_>]) -> Vec<Interaction> {
+    let mut interactions = Vec::new();
+    let in_group = |ecosystem: &str, package: &str| {
+        members
+            .iter()
+            .find(|member| member.ecosystem == ecosystem && member.package == package)
+    };
+
+    for member in members {
+        for (peer, range) in &member.peers {
+            let Some(other) = in_group(member.ecosystem, peer) else {
+                continue;
+            };
+            let (Ok(spec), Ok(version)) = (
+                VersionSpec::parse(member.ecosystem, range),
+                version::scheme_for(member.ecosystem).parse(other.version),
+            ) else {
+                continue;
+            };
+            if !spec.matches(&version) {
+                interactions.push(Interaction {
+                    kind: InteractionKind::PeerConflict,
+                    packages: vec![member.package.to_string(), other.package.to_string()],
+                    detail: format!(
+                        "{} {} requires {} {}, which the group upgrades to {}",
+                        member.package, member.version, peer, range, other.version
+                    ),
+                });
+            }
+        }
+    }
+
+    for (i, first) in members.iter().enumerate() {
+        for second in &members[i + 1..] {
+            if first.ecosystem == second.ecosystem {
+                for (peer, range) in &first.peers {
+                    let Some(other_range) = second.peers.get(peer) else {
+                        continue;
+                    };
+                    if in_group(first.ecosystem, peer).is_some() {
+                        continue;
+                    }
+                    interactions.push(Interaction {
+                        kind: InteractionKind::SharedPeer,
+                        packages: vec![first.package.to_string(), second.package.to_string()],
+                        detail: format!(
+                            "{} and {} both declare {} as a peer ({} and {}): its version must satisfy both",
+                            first.package, second.package, peer, range, other_range
+                        ),
+                    });
+                }
+            }
+
+            let mut shared: Vec<&str> = first.files.intersection(&second.files).copied().collect();
+            shared.sort_unstable();
+            for file in shared {
+                interactions.push(Interaction {
+                    kind: InteractionKind::SharedFile,
+                    packages: vec![first.package.to_string(), second.package.to_string()],
+                    detail: format!(
+                        "{} and {} both edit {}: apply them as one change",
+                        first.package, second.package, file
+                    ),
+                });
+            }
+        }
+    }
+    interactions
+}
+
+fn assess_group(
+    members: &[Member<'_>],
+    upgrades: &[UpgradeResponse],
+    interactions: Vec<Interaction>,
+) -> GroupRiskAssessment {
+    let mut assessment = GroupRiskAssessment {
+        risk_level: RiskLevel::Low,
+        breaking_changes: false,
+        performance_impact: PerformanceImpact::None,
+        interactions: Vec::new(),
+        explanations: Vec::new(),
+    };
+    for upgrade in upgrades {
+        let risk = &upgrade.risk_assessment;
+        assessment.risk_level = assessment.risk_level.max(risk.risk_level);
+        assessment.breaking_changes |= risk.breaking_changes;
+        assessment.performance_impact = assessment.performance_impact.max(risk.performance_impact);
+    }
+    if assessment.risk_level > RiskLevel::Low {
+        let riskiest: Vec<&str> = members
+            .iter()
+            .zip(upgrades)
+            .filter(|(_, upgrade)| upgrade.risk_assessment.risk_level == assessment.risk_level)
+            .map(|(member, _)| member.package)
+            .collect();
+        assessment.explanations.push(format!(
+            "{:?} risk of {}",
+            assessment.risk_level,
+            riskiest.join(", ")
+        ));
+    }
+
+    for interaction in &interactions {
+        if interaction.kind == InteractionKind::PeerConflict {
+            assessment.risk_level = assessment.risk_level.max(RiskLevel::High);
+        }
+        assessment.explanations.push(interaction.detail.clone());
+    }
+    assessment.interactions = interactions;
+    assessment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit::CommitConfig;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use crate::WorkerConfig;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn release(version: &str, peers: &[(&str, &str)]) -> ReleaseInfo {
+        ReleaseInfo {
+            peer_dependencies: Some(
+                peers
+                    .iter()
+                    .map(|(name, range)| (name.to_string(), range.to_string()))
+                    .collect(),
+            ),
+            ..ReleaseInfo::new(version)
+        }
+    }
+
+    fn upgrade(package: &str, current: &str, target: &str) -> UpgradeRequest {
+        UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: package.to_string(),
+            current_version: current.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"react": "^16.14.0", "react-dom": "^17.0.2", "react-router": "^5.3.0"}}"#
+                    .to_string(),
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn registry() -> Arc<StaticRegistry> {
+        Arc::new(
+            StaticRegistry::new()
+                .with_releases("npm", "react", vec![ReleaseInfo::new("17.0.2")])
+                .with_releases(
+                    "npm",
+                    "react-dom",
+                    vec![release("18.2.0", &[("react", "^18.2.0")])],
+                )
+                .with_releases(
+                    "npm",
+                    "react-router",
+                    vec![release("5.3.4", &[("react", ">=15")])],
+                ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_group_risk_and_interactions() {
+        let worker = UpgradeWorker::new(None).with_registry(registry());
+
+        let group = worker
+            .process_group(GroupUpgradeRequest {
+                upgrades: vec![
+                    upgrade("react", "16.14.0", "17.0.2"),
+                    upgrade("react-dom", "17.0.2", "18.2.0"),
+                ],
+            })
+            .await
+            .unwrap();
+        assert_eq!(group.upgrades.len(), 2);
+        let risk = &group.risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::High);
+        assert!(risk.breaking_changes);
+        assert_eq!(risk.interactions[0].kind, InteractionKind::PeerConflict);
+        assert_eq!(
+            risk.interactions[0].detail,
+            "react-dom 18.2.0 requires react ^18.2.0, which the group upgrades to 17.0.2"
+        );
+        assert!(risk.explanations.contains(
+            &"react and react-dom both edit package.json: apply them as one change".to_string()
+        ));
+        assert_eq!(group.recommended_action, RecommendedAction::ReviewRequired);
+
+        let group = worker
+            .process_group(GroupUpgradeRequest {
+                upgrades: vec![
+                    upgrade("react-dom", "17.0.2", "18.2.0"),
+                    upgrade("react-router", "5.3.0", "5.3.4"),
+                ],
+            })
+            .await
+            .unwrap();
+        let shared: Vec<&Interaction> = group
+            .risk_assessment
+            .interactions
+            .iter()
+            .filter(|interaction| interaction.kind == InteractionKind::SharedPeer)
+            .collect();
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].packages, ["react-dom", "react-router"]);
+        assert_eq!(
+            group.risk_assessment.explanations[0],
+            "High risk of react-dom"
+        );
+
+        assert!(worker
+            .process_group(GroupUpgradeRequest {
+                upgrades: Vec::new()
+            })
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_high_risk_group_is_not_committed() {
+        let root = tempfile::tempdir().unwrap();
+        let base = crate::repo::tests::source_repository(root.path());
+        let conflicting = || {
+            vec![
+                upgrade("react", "16.14.0", "17.0.2"),
+                upgrade("react-dom", "17.0.2", "18.2.0"),
+            ]
+        };
+
+        let committing = UpgradeWorker::new(Some(WorkerConfig {
+            commit: Some(CommitConfig::default()),
+            ..WorkerConfig::default()
+        }))
+        .with_registry(registry());
+        let err = committing
+            .process_group(GroupUpgradeRequest {
+                upgrades: conflicting(),
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+
+        let local = UpgradeWorker::new(Some(WorkerConfig {
+            local_roots: vec![root.path().to_path_buf()],
+            ..WorkerConfig::default()
+        }))
+        .with_registry(registry());
+        let upgrades = conflicting()
+            .into_iter()
+            .map(|upgrade| UpgradeRequest {
+                local_path: Some(root.path().to_string_lossy().to_string()),
+                ..upgrade
+            })
+            .collect();
+        let err = local
+            .process_group(GroupUpgradeRequest { upgrades })
+            .await
+            .unwrap_err();
+        assert!(err
+            .message
+            .starts_with("Upgrade of 'react' would be written"));
+
+        let repo = git2::Repository::open(root.path()).unwrap();
+        assert_eq!(repo.head().unwrap().name(), Some("refs/heads/main"));
+        assert_eq!(repo.head().unwrap().target().unwrap().to_string(), base);
+        assert_eq!(repo.branches(None).unwrap().count(), 1);
+        assert_eq!(
+            std::fs::read_to_string(root.path().join("package.json")).unwrap(),
+            r#"{"dependencies": {"lodash": "^4.17.20"}}"#
+        );
+
+        // Evaluated, the group is only reported
+        let group = local
+            .process_group(GroupUpgradeRequest {
+                upgrades: conflicting(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(group.risk_assessment.risk_level, RiskLevel::High);
+        assert!(group
+            .upgrades
+            .iter()
+            .all(|upgrade| upgrade.commit_sha.is_none() && upgrade.pull_request.is_none()));
+    }
+}
@@ -0,0 +1,339 @@
+use crate::advisories::Severity;
+use crate::ecosystems::base_images;
+use crate::version::ResolvedVersions;
+use crate::{ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Known vulnerabilities of a container image, by severity.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageScan {
+    pub image: String,
+    pub critical: usize,
+    pub high: usize,
+    pub medium: usize,
+    pub low: usize,
+    /// Vulnerabilities without a rating.
+    pub unknown: usize,
+    /// IDs of the vulnerabilities found (`CVE-...`), sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vulnerabilities: Vec<String>,
+}
+
+impl ImageScan {
+    /// An image's scan from its vulnerabilities and their ratings; a
+    /// vulnerability found in several packages counts once, at its highest
+    /// rating.
+    pub fn new(image: &str, found: impl IntoIterator<Item = (String, Option<Severity>)>) -> Self {
+        let mut ratings: BTreeMap<String, Option<Severity>> = BTreeMap::new();
+        for (id, severity) in found {
+            let rating = ratings.entry(id).or_insert(severity);
+            *rating = (*rating).max(severity);
+        }
+        let count = |severity: Option<Severity>| {
+            ratings
+                .values()
+                .filter(|rating| **rating == severity)
+                .count()
+        };
+        Self {
+            image: image.to_string(),
+            critical: count(Some(Severity::Critical)),
+            high: count(Some(Severity::High)),
+            medium: count(Some(Severity::Medium)),
+            low: count(Some(Severity::Low)),
+            unknown: count(None),
+            vulnerabilities: ratings.into_keys().collect(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.critical + self.high + self.medium + self.low + self.unknown
+    }
+}
+
+/// Vulnerabilities of the current and target base images, in
+/// [`crate::RiskAssessment::image_scans`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageScanComparison {
+    pub current: ImageScan,
+    pub target: ImageScan,
+    /// Vulnerabilities of the current image the target no longer has.
+    pub fixed: Vec<String>,
+    /// Vulnerabilities of the target image the current one did not have.
+    pub introduced: Vec<String>,
+}
+
+impl ImageScanComparison {
+    pub fn new(current: ImageScan, target: ImageScan) -> Self {
+        let missing_from = |scan: &ImageScan, other: &ImageScan| {
+            scan.vulnerabilities
+                .iter()
+                .filter(|id| other.vulnerabilities.binary_search(id).is_err())
+                .cloned()
+                .collect()
+        };
+        Self {
+            fixed: missing_from(&current, &target),
+            introduced: missing_from(&target, &current),
+            current,
+            target,
+        }
+    }
+}
+
+/// Lookup of the known vulnerabilities of container images.
+#[async_trait]
+pub trait ImageScanner: Send + Sync {
+    async fn scan(&self, image: &str) -> Result<ImageScan, UpgradeError>;
+}
+
+/// Scans images with the Trivy CLI, against a Trivy server's vulnerability
+/// database when one is configured.
+pub struct TrivyScanner {
+    server: Option<String>,
+    timeout: Duration,
+}
+
+impl TrivyScanner {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            server: None,
+            timeout,
+        }
+    }
+
+    /// Scans in client mode against the Trivy server at `url`.
+    pub fn with_server(mut self, url: &str) -> Self {
+        self.server = Some(url.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl ImageScanner for TrivyScanner {
+    async fn scan(&self, image: &str) -> Result<ImageScan, UpgradeError> {
+        let scan_error = |message: String| UpgradeError {
+            message: format!("Failed to scan {}: {}", image, message),
+            error_type: ErrorType::Internal,
+        };
+        let mut command = Command::new("trivy");
+        command.args(["image", "--quiet", "--format", "json", "--scanners", "vuln"]);
+        if let Some(server) = &self.server {
+            command.args(["--server", server]);
+        }
+        let output = command.arg(image).kill_on_drop(true).output();
+        let output = tokio::time::timeout(self.timeout, output)
+            .await
+            .map_err(|_| scan_error(format!("timed out after {}s", self.timeout.as_secs())))?
+            .map_err(|e| scan_error(e.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(scan_error(
+                stderr.lines().last().unwrap_or("trivy failed").to_string(),
+            ));
+        }
+        let report: serde_json::Value =
+            serde_json::from_slice(&output.stdout).map_err(|e| scan_error(e.to_string()))?;
+        Ok(parse_trivy_report(image, &report))
+    }
+}
+
+/// The vulnerabilities of a `trivy image --format json` report.
+pub fn parse_trivy_report(image: &str, report: &serde_json::Value) -> ImageScan {
+    let found = report["Results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|result| result["Vulnerabilities"].as_array().into_iter().flatten())
+        .filter_map(|vulnerability| {
+            Some((
+                vulnerability["VulnerabilityID"].as_str()?.to_string(),
+                vulnerability["Severity"].as_str().and_then(Severity::parse),
+            ))
+        });
+    ImageScan::new(image, found)
+}
+
+/// In-memory image scans, useful for tests and for callers that pre-scan
+/// images.
+#[derive(Debug, Clone, Default)]
+pub struct StaticImageScanner {
+    scans: HashMap<String, ImageScan>,
+}
+
+impl StaticImageScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_scan(mut self, scan: ImageScan) -> Self {
+        self.scans.insert(scan.image.clone(), scan);
+        self
+    }
+}
+
+#[async_trait]
+impl ImageScanner for StaticImageScanner {
+    async fn scan(&self, image: &str) -> Result<ImageScan, UpgradeError> {
+        self.scans.get(image).cloned().ok_or_else(|| UpgradeError {
+            message: format!("No scan of {}", image),
+            error_type: ErrorType::Internal,
+        })
+    }
+}
+
+impl UpgradeWorker {
+    /// Compares the vulnerabilities of the base images a Docker upgrade
+    /// moves between. More critical or high vulnerabilities in a target
+    /// image make the upgrade high risk.
+    pub(crate) async fn check_image_vulnerabilities(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        let Some(scanner) = &self.image_scanner else {
+            return;
+        };
+        if request.ecosystem != "docker" {
+            return;
+        }
+
+        for (current, target) in base_images(request, versions) {
+            let scans = match (scanner.scan(&current).await, scanner.scan(&target).await) {
+                (Ok(current), Ok(target)) => ImageScanComparison::new(current, target),
+                (Err(e), _) | (_, Err(e)) => {
+                    warnings.push(format!("Image scan skipped: {}", e.message));
+                    continue;
+                }
+            };
+            let (before, after) = (&scans.current, &scans.target);
+            if after.critical > before.critical || after.high > before.high {
+                risk.risk_level = risk.risk_level.max(RiskLevel::High);
+                risk.explanations.push(format!(
+                    "{} has {} critical and {} high vulnerabilities, up from {} and {} in {}",
+                    target, after.critical, after.high, before.critical, before.high, current
+                ));
+            } else if after.total() < before.total() {
+                risk.explanations.push(format!(
+                    "{} fixes {} of the {} vulnerabilities of {}",
+                    target,
+                    scans.fixed.len(),
+                    before.total(),
+                    current
+                ));
+            }
+            risk.image_scans.push(scans);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_trivy_report() {
+        let report = json!({
+            "SchemaVersion": 2,
+            "ArtifactName": "node:18.19.0-alpine",
+            "Results": [
+                {"Target": "node:18.19.0-alpine (alpine 3.19.0)", "Vulnerabilities": [
+                    {"VulnerabilityID": "CVE-2023-5678", "PkgName": "libcrypto3", "Severity": "MEDIUM"},
+                    {"VulnerabilityID": "CVE-2023-5678", "PkgName": "libssl3", "Severity": "HIGH"},
+                    {"VulnerabilityID": "CVE-2024-0727", "PkgName": "libssl3", "Severity": "UNKNOWN"}
+                ]},
+                {"Target": "Node.js", "Class": "lang-pkgs"}
+            ]
+        });
+        let scan = parse_trivy_report("node:18.19.0-alpine", &report);
+        assert_eq!(scan.high, 1);
+        assert_eq!(scan.medium, 0);
+        assert_eq!(scan.unknown, 1);
+        assert_eq!(scan.vulnerabilities, ["CVE-2023-5678", "CVE-2024-0727"]);
+    }
+
+    #[tokio::test]
+    async fn test_image_scans_in_risk_assessment() {
+        let scan = |image: &str, found: &[(&str, Severity)]| {
+            ImageScan::new(
+                image,
+                found
+                    .iter()
+                    .map(|(id, severity)| (id.to_string(), Some(*severity))),
+            )
+        };
+        let scanner = StaticImageScanner::new()
+            .with_scan(scan(
+                "node:18.19.0-alpine",
+                &[
+                    ("CVE-2023-5678", Severity::Medium),
+                    ("CVE-2024-0727", Severity::Low),
+                ],
+            ))
+            .with_scan(scan(
+                "node:20.11.0-alpine",
+                &[("CVE-2024-0727", Severity::Low)],
+            ));
+        let worker = UpgradeWorker::new(None).with_image_scanner(Arc::new(scanner));
+        let request = UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "docker".to_string(),
+            package_name: "node".to_string(),
+            current_version: "18.19.0".to_string(),
+            target_version: "20.11.0".to_string(),
+            manifests: HashMap::from([(
+                "Dockerfile".to_string(),
+                "FROM node:18.19.0-alpine\n".to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request.clone()).await.unwrap();
+        assert_eq!(response.changes[0].content, "FROM node:20.11.0-alpine\n");
+        let risk = response.risk_assessment;
+        assert_eq!(risk.image_scans[0].fixed, ["CVE-2023-5678"]);
+        assert!(risk.image_scans[0].introduced.is_empty());
+        assert!(risk.explanations.contains(
+            &"node:20.11.0-alpine fixes 1 of the 2 vulnerabilities of node:18.19.0-alpine"
+                .to_string()
+        ));
+
+        let worse = StaticImageScanner::new()
+            .with_scan(scan("node:18.19.0-alpine", &[]))
+            .with_scan(scan(
+                "node:20.11.0-alpine",
+                &[("CVE-2024-21626", Severity::High)],
+            ));
+        let worker = UpgradeWorker::new(None).with_image_scanner(Arc::new(worse));
+        let risk = worker
+            .process_upgrade(request.clone())
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert!(risk.risk_level >= RiskLevel::High);
+        assert!(risk.explanations.contains(
+            &"node:20.11.0-alpine has 0 critical and 1 high vulnerabilities, \
+              up from 0 and 0 in node:18.19.0-alpine"
+                .to_string()
+        ));
+        assert_eq!(risk.image_scans[0].introduced, ["CVE-2024-21626"]);
+
+        // Images that cannot be scanned are reported, not fatal
+        let worker =
+            UpgradeWorker::new(None).with_image_scanner(Arc::new(StaticImageScanner::new()));
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert!(response.risk_assessment.image_scans.is_empty());
+        assert!(response
+            .warnings
+            .contains(&"Image scan skipped: No scan of node:18.19.0-alpine".to_string()));
+    }
+}
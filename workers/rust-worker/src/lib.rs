@@ -1,9 +1,54 @@
+pub mod adoption;
+pub mod advisories;
+pub mod ai;
+pub mod changeset;
+pub mod commit;
+pub mod compare;
+pub mod conflicts;
+pub mod credentials;
+pub mod deprecations;
+pub mod diff;
+pub mod ecosystems;
+pub mod features;
+pub mod group;
+pub mod image_scan;
+pub mod license;
+pub mod lockfile;
+pub mod migrations;
+pub mod msrv;
+pub mod outcomes;
+pub mod patch;
+pub mod planner;
+pub mod policy;
+pub mod provenance;
+pub mod registry;
+pub mod release_notes;
+pub mod rename;
+pub mod repo;
+pub mod rewrite;
+pub mod risk;
+pub mod sandbox;
+pub mod sbom;
+pub mod scm;
+pub mod scope;
+pub mod score;
+pub mod semver_checks;
+pub mod signing;
+pub mod supply_chain;
+pub mod usages;
+pub mod verification;
+pub mod version;
+
+use planner::UpgradePlan;
+use registry::{Registry, ReleaseInfo};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt;
+use std::sync::Arc;
+use version::{ParsedVersion, ResolvedVersions, VersionJump, VersionScheme, VersionSpec};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UpgradeRequest {
     pub repository: String,
     pub ecosystem: String,
@@ -11,6 +56,69 @@ pub struct UpgradeRequest {
     pub current_version: String,
     pub target_version: String,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Contents of repository files relevant to the upgrade, keyed by
+    /// repository-relative path (e.g. `Cargo.toml`).
+    #[serde(default)]
+    pub manifests: HashMap<String, String>,
+    /// Restricts edits to one kind of dependency declaration; every kind is
+    /// edited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dependency_kind: Option<DependencyKind>,
+    /// How the content of returned changes is represented.
+    #[serde(default)]
+    pub change_format: ChangeFormat,
+    /// Package replacing `package_name` when the upgrade is a rename
+    /// (`babel-core` to `@babel/core`); `target_version` then refers to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement_package: Option<String>,
+    /// Working tree already on the worker's filesystem (a CI checkout or a
+    /// mounted volume) to read and edit in place instead of cloning
+    /// `repository`. It must lie under one of [`WorkerConfig::local_roots`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_path: Option<String>,
+    /// Directory of one component of a monorepo (`services/payments/`):
+    /// only manifests under it are upgraded and risk is assessed for it
+    /// alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_scope: Option<String>,
+    /// Identifier of the job the request belongs to in the caller's
+    /// scheduler, recorded on upgrade commits; the changeset ID otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// Benchmark command (`cargo bench`, `npm run bench`) run before and
+    /// after the upgrade when verification is enabled; regressions it shows
+    /// raise the performance impact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmark_command: Option<String>,
+}
+
+/// Representation of `Change.content` in responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeFormat {
+    /// The complete new file content.
+    #[default]
+    Content,
+    /// A unified diff against the file supplied in `manifests`.
+    UnifiedDiff,
+    /// The complete new file content, plus an RFC 6902 patch against the
+    /// supplied JSON, TOML or YAML file in `metadata.json_patch`.
+    JsonPatch,
+    /// The complete new file content, plus the change set as a series of
+    /// `git format-patch` mails in `UpgradeResponse::patches`.
+    FormatPatch,
+}
+
+/// How a package is depended on, mapping to manifest sections such as
+/// `devDependencies` or `[build-dependencies]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Normal,
+    Dev,
+    Peer,
+    Optional,
+    Build,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,7 +127,73 @@ pub struct UpgradeResponse {
     pub message: String,
     pub changes: Vec<Change>,
     pub compatibility_score: f64,
+    /// Gating decision derived from the risk assessment, the advisories and
+    /// the verification.
+    #[serde(default)]
+    pub recommended_action: RecommendedAction,
+    /// The factors `compatibility_score` was computed from.
+    #[serde(default)]
+    pub score_breakdown: score::ScoreBreakdown,
     pub risk_assessment: RiskAssessment,
+    pub resolved_version: String,
+    /// Versioning scheme the versions were interpreted with (`semver`, `calver`, ...).
+    pub version_scheme: String,
+    pub warnings: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<UpgradePlan>,
+    /// Workspace packages whose manifests were changed, in monorepos.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    /// Identifies the changes as one unit to apply or roll back together.
+    #[serde(default)]
+    pub changeset_id: String,
+    /// SHA-256 each touched file must have before the changes are applied,
+    /// `null` for files that must not exist yet.
+    #[serde(default)]
+    pub original_hashes: BTreeMap<String, Option<String>>,
+    /// The repository already declares the target, so `changes` is empty.
+    #[serde(default)]
+    pub no_change: bool,
+    /// Commit of the checked out repository the changes were generated from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_commit: Option<String>,
+    /// Branch the changes were committed to, when commits are enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// SHA of the commit holding the changes on `branch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+    /// Pull request (GitLab merge request) opened for `branch`, when a
+    /// provider is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<scm::PullRequest>,
+    /// The changes as `git format-patch` mails, one per logical step, when
+    /// requested with [`ChangeFormat::FormatPatch`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patches: Vec<patch::Patch>,
+    /// Files whose edits no longer apply to the head of the default branch,
+    /// which moved on since the checked out commit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<conflicts::Conflict>,
+    /// Breaking changes, deprecations and migration notes the releases up
+    /// to the target published, when a release notes source is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<release_notes::ReleaseNotes>,
+    /// Adoption of the target version the compatibility score was scaled
+    /// by, when the registry or issue tracker publish any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adoption: Option<adoption::AdoptionSignals>,
+    /// Outcome of building and testing the upgraded checkout, when
+    /// verification is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<verification::Verification>,
+    /// Fixes a language model suggested for a failed verification, left
+    /// out of `changes` unless [`ai::AiConfig::apply`] is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggested_changes: Vec<Change>,
+    /// Policy rules the upgrade matched, when a policy is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<policy::PolicyDecision>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,32 +209,124 @@ pub enum ChangeType {
     Add,
     Modify,
     Delete,
+    /// Moves a registry baseline (vcpkg `builtin-baseline`), which shifts
+    /// every dependency that is not pinned explicitly.
+    BaselineBump,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RiskAssessment {
     pub risk_level: RiskLevel,
     pub breaking_changes: bool,
-    pub security_issues: Vec<String>,
+    /// Known vulnerabilities of the current and target versions. Plain
+    /// strings, the earlier format, are still read.
+    #[serde(deserialize_with = "advisories::security_issues_compat")]
+    pub security_issues: Vec<advisories::SecurityIssue>,
+    /// Signs of a malicious package: typosquatting names, brand-new packages
+    /// and added install scripts.
+    #[serde(default)]
+    pub supply_chain_flags: Vec<supply_chain::SupplyChainFlag>,
+    /// Signature and provenance checks of the target version; empty when no
+    /// verifier is configured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<provenance::ProvenanceCheck>,
+    /// Licenses of the current and target versions, when the registry
+    /// publishes them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<license::LicenseCheck>,
+    /// When cached advisory databases fetched the advisories checked; empty
+    /// when every database was queried live.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub advisory_freshness: Vec<advisories::Freshness>,
+    /// Vulnerabilities of the current and target base images of Docker
+    /// upgrades, when an image scanner is configured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub image_scans: Vec<image_scan::ImageScanComparison>,
+    /// Breaking changes of the target's public API found by diffing it
+    /// against the current version; absent when cargo upgrades are not
+    /// diffed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_breaks: Option<Vec<semver_checks::ApiBreak>>,
+    /// Deprecations declared up to the target, by registries and release
+    /// notes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deprecations: Vec<deprecations::Deprecation>,
+    /// Lines of the repository using APIs the target removes or changes, by
+    /// the API diff and release notes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_usages: Vec<usages::ApiUsage>,
     pub performance_impact: PerformanceImpact,
+    pub version_jump: VersionJump,
+    /// Human-readable reasons for the assessed risk level.
+    pub explanations: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum RiskLevel {
+    #[default]
     Low,
     Medium,
     High,
     Critical,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum PerformanceImpact {
+    #[default]
     None,
     Low,
     Medium,
     High,
 }
 
+/// What the orchestrator should do with an upgrade, in
+/// `UpgradeResponse::recommended_action`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecommendedAction {
+    /// Low risk, not breaking, and verified when verification ran.
+    AutoMerge,
+    #[default]
+    ReviewRequired,
+    /// Blocked by policy, critical risk or a failed verification.
+    Block,
+    /// Fixes advisories of the current version: review and merge first.
+    SecurityFastTrack,
+}
+
+impl RecommendedAction {
+    pub fn recommend(
+        risk: &RiskAssessment,
+        verification: Option<&verification::Verification>,
+        changes: &[Change],
+        blocked: bool,
+    ) -> Self {
+        let failed = verification.is_some_and(|verification| !verification.passed);
+        if blocked || failed || risk.risk_level == RiskLevel::Critical {
+            return Self::Block;
+        }
+        let fixes_advisories = risk
+            .security_issues
+            .iter()
+            .any(|issue| issue.fixed_by_upgrade);
+        if fixes_advisories {
+            return Self::SecurityFastTrack;
+        }
+        // Suggested fixes are never as certain as the worker's own edits
+        let unverified = changes.iter().any(|change| {
+            change
+                .metadata
+                .get("confidence")
+                .and_then(serde_json::Value::as_f64)
+                .is_some_and(|confidence| confidence < 1.0)
+        });
+        if risk.risk_level == RiskLevel::Low && !risk.breaking_changes && !unverified {
+            Self::AutoMerge
+        } else {
+            Self::ReviewRequired
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct UpgradeError {
     pub message: String,
@@ -74,7 +340,11 @@ pub enum ErrorType {
     Security,
     Performance,
     Network,
+    Registry,
     Internal,
+    /// The package is declared in a form that needs manual intervention,
+    /// e.g. a git branch or local path override.
+    Unsupported,
 }
 
 impl fmt::Display for UpgradeError {
@@ -85,16 +355,103 @@ impl fmt::Display for UpgradeError {
 
 impl Error for UpgradeError {}
 
+#[derive(Clone)]
 pub struct UpgradeWorker {
     config: WorkerConfig,
+    registry: Option<Arc<dyn Registry>>,
+    advisories: Vec<Arc<dyn advisories::AdvisoryDatabase>>,
+    provenance: Vec<Arc<dyn provenance::ProvenanceVerifier>>,
+    exploit_scores: Option<Arc<dyn advisories::ExploitScores>>,
+    image_scanner: Option<Arc<dyn image_scan::ImageScanner>>,
+    api_diffs: Vec<Arc<dyn semver_checks::ApiDiff>>,
+    release_notes: Option<Arc<dyn release_notes::ReleaseNotesSource>>,
+    issue_activity: Option<Arc<dyn adoption::IssueActivity>>,
+    adoption_cache: Arc<adoption::AdoptionCache>,
+    fix_provider: Option<Arc<dyn ai::FixProvider>>,
+    risk_rules: Vec<Arc<dyn risk::RiskRule>>,
+    policy_file: Option<Arc<policy::PolicyFile>>,
+    outcomes: Option<Arc<dyn outcomes::OutcomeStore>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct WorkerConfig {
     pub max_execution_time: u64,
     pub memory_limit: u64,
+    /// Run the commands of checked out repositories in `sandbox`, and
+    /// refuse them without one.
     pub sandbox_enabled: bool,
+    /// Container builds, tests, codemods and lockfile refreshes run in.
+    pub sandbox: Option<sandbox::SandboxConfig>,
     pub log_level: String,
+    pub downgrade_policy: DowngradePolicy,
+    pub prerelease_policy: PrereleasePolicy,
+    /// Run lockfile refresh commands in a temporary checkout so that
+    /// changes carry the regenerated lockfile instead of a placeholder.
+    pub regenerate_lockfiles: bool,
+    /// Clone `request.repository` and generate changes from its files
+    /// rather than only the manifests supplied with the request.
+    pub checkout: Option<repo::CheckoutConfig>,
+    /// Commit the changes to a new branch of the checkout.
+    pub commit: Option<commit::CommitConfig>,
+    /// Push committed branches to GitHub, GitLab or Bitbucket and open pull
+    /// requests for them.
+    pub scm: Option<scm::ScmConfig>,
+    /// Directory `format_patch` patches are also written to, one
+    /// subdirectory per change set.
+    pub patch_directory: Option<std::path::PathBuf>,
+    /// Directories `UpgradeRequest::local_path` may lie under; local
+    /// workspaces are refused when empty.
+    pub local_roots: Vec<std::path::PathBuf>,
+    /// Treat target versions whose signature or provenance cannot be
+    /// verified as high risk.
+    pub require_provenance: bool,
+    /// Licenses target versions may be published under; only license
+    /// changes are reported when unset.
+    pub license_policy: Option<license::LicensePolicy>,
+    /// EPSS probability below which an advisory of the target version makes
+    /// the upgrade high rather than critical risk. Every advisory is
+    /// critical when unset, or when its CVE has no score.
+    pub epss_critical_threshold: Option<f64>,
+    /// Build and test a copy of the checkout with the changes applied,
+    /// reporting the outcome in `UpgradeResponse::verification`.
+    pub verification: Option<verification::VerificationConfig>,
+    /// Run the codemods of the major releases an upgrade crosses on a copy
+    /// of the checkout and add their edits to the changes.
+    pub codemods: Option<migrations::CodemodConfig>,
+    /// Ask the fix provider for source edits when verification fails.
+    pub ai: Option<ai::AiConfig>,
+    /// Rules adjusting risk levels and blocking upgrades once they are
+    /// scored.
+    pub policy: Option<policy::Policy>,
+    /// Policy file read in place of `policy`, and read again when it
+    /// changes.
+    pub policy_file: Option<std::path::PathBuf>,
+    /// Weights of the signals the compatibility score combines, unless the
+    /// policy sets its own.
+    pub score_weights: score::ScoreWeights,
+}
+
+/// Request metadata key overriding [`WorkerConfig::prerelease_policy`].
+pub const PRERELEASE_POLICY_METADATA_KEY: &str = "prerelease_policy";
+
+/// How alpha/beta/rc targets are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrereleasePolicy {
+    /// Upgrade to the pre-release as requested.
+    Accept,
+    /// Replace the pre-release with the nearest stable release.
+    Skip,
+    /// Fail validation.
+    Reject,
+}
+
+/// What to do when the requested target is older than the current version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DowngradePolicy {
+    Reject,
+    Warn,
+    Allow,
 }
 
 impl Default for WorkerConfig {
@@ -103,41 +460,558 @@ impl Default for WorkerConfig {
             max_execution_time: 300,
             memory_limit: 1024 * 1024 * 1024, // 1GB
             sandbox_enabled: true,
+            sandbox: None,
             log_level: "info".to_string(),
+            downgrade_policy: DowngradePolicy::Warn,
+            prerelease_policy: PrereleasePolicy::Accept,
+            regenerate_lockfiles: false,
+            checkout: None,
+            commit: None,
+            scm: None,
+            patch_directory: None,
+            local_roots: Vec::new(),
+            require_provenance: false,
+            license_policy: None,
+            epss_critical_threshold: None,
+            verification: None,
+            codemods: None,
+            ai: None,
+            policy: None,
+            policy_file: None,
+            score_weights: score::ScoreWeights::default(),
         }
     }
 }
 
 impl UpgradeWorker {
     pub fn new(config: Option<WorkerConfig>) -> Self {
+        let config = config.unwrap_or_default();
+        let policy_file = config
+            .policy_file
+            .clone()
+            .map(|path| Arc::new(policy::PolicyFile::new(path)));
         Self {
-            config: config.unwrap_or_default(),
+            config,
+            registry: None,
+            advisories: Vec::new(),
+            provenance: Vec::new(),
+            exploit_scores: None,
+            image_scanner: None,
+            api_diffs: Vec::new(),
+            release_notes: None,
+            issue_activity: None,
+            adoption_cache: Arc::default(),
+            fix_provider: None,
+            risk_rules: risk::builtin_rules(),
+            policy_file,
+            outcomes: None,
         }
     }
 
-    pub async fn process_upgrade(&self, request: UpgradeRequest) -> Result<UpgradeResponse, UpgradeError> {
+    pub fn with_registry(mut self, registry: Arc<dyn Registry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Adds a database of security advisories to check both versions, and
+    /// the dependencies regenerated lockfiles pull in, against; advisories
+    /// found in several are reported once.
+    pub fn with_advisory_database(
+        mut self,
+        database: Arc<dyn advisories::AdvisoryDatabase>,
+    ) -> Self {
+        self.advisories.push(database);
+        self
+    }
+
+    /// Adds a verifier of the signatures and provenance of target versions.
+    /// Nothing is verified, or recorded, without one.
+    pub fn with_provenance_verifier(
+        mut self,
+        verifier: Arc<dyn provenance::ProvenanceVerifier>,
+    ) -> Self {
+        self.provenance.push(verifier);
+        self
+    }
+
+    /// Sets the source of EPSS scores recorded for advisories with a CVE.
+    pub fn with_exploit_scores(mut self, scores: Arc<dyn advisories::ExploitScores>) -> Self {
+        self.exploit_scores = Some(scores);
+        self
+    }
+
+    /// Sets the scanner comparing the vulnerabilities of the base images
+    /// Docker upgrades move between.
+    pub fn with_image_scanner(mut self, scanner: Arc<dyn image_scan::ImageScanner>) -> Self {
+        self.image_scanner = Some(scanner);
+        self
+    }
+
+    /// Adds an API diff deciding whether upgrades of its ecosystem are
+    /// breaking, in place of their version numbers; the first one
+    /// supporting an ecosystem is used.
+    pub fn with_api_diff(mut self, diff: Arc<dyn semver_checks::ApiDiff>) -> Self {
+        self.api_diffs.push(diff);
+        self
+    }
+
+    /// Sets where the release notes digested into responses, pull request
+    /// descriptions and risk levels are read.
+    pub fn with_release_notes(
+        mut self,
+        source: Arc<dyn release_notes::ReleaseNotesSource>,
+    ) -> Self {
+        self.release_notes = Some(source);
+        self
+    }
+
+    /// Sets where issue counts come from, to spot targets whose release
+    /// was followed by a spike of new issues.
+    pub fn with_issue_activity(mut self, issues: Arc<dyn adoption::IssueActivity>) -> Self {
+        self.issue_activity = Some(issues);
+        self
+    }
+
+    /// Sets the language model suggesting fixes for builds the upgrade
+    /// breaks; it is only asked when [`WorkerConfig::ai`] is set.
+    pub fn with_fix_provider(mut self, provider: Arc<dyn ai::FixProvider>) -> Self {
+        self.fix_provider = Some(provider);
+        self
+    }
+
+    /// Adds a rule run after the built-in ones when assessing risk.
+    pub fn with_risk_rule(mut self, rule: Arc<dyn risk::RiskRule>) -> Self {
+        self.risk_rules.push(rule);
+        self
+    }
+
+    /// Sets where reported upgrade outcomes are kept, and raises the risk of
+    /// packages whose earlier upgrades were reverted or broke production.
+    pub fn with_outcome_store(mut self, store: Arc<dyn outcomes::OutcomeStore>) -> Self {
+        self.risk_rules
+            .push(Arc::new(outcomes::OutcomeRule::new(store.clone())));
+        self.outcomes = Some(store);
+        self
+    }
+
+    pub async fn process_upgrade(
+        &self,
+        mut request: UpgradeRequest,
+    ) -> Result<UpgradeResponse, UpgradeError> {
         // Validate input
-        self.validate_request(&request)?;
+        let (current_spec, target_spec) = self.validate_request(&request)?;
+
+        // Read the repository itself when checkouts are enabled
+        let checkout = self.load_repository(&mut request).await?;
+
+        // Resolve requirements to concrete versions
+        let versions = self
+            .resolve_versions(&request, &current_spec, &target_spec)
+            .await?;
+        let mut warnings = Vec::new();
+        let target_request = rename::target_request(&request);
+
+        // Apply pre-release policy
+        let versions = self
+            .apply_prerelease_policy(&target_request, versions, &mut warnings)
+            .await?;
+
+        // Apply downgrade policy; versions of a replacement are not comparable
+        if request.replacement_package.is_none() {
+            if let Some(warning) = self.check_downgrade(&request, &versions)? {
+                warnings.push(warning);
+            }
+        }
+
+        // Make sure the target can actually be installed
+        self.check_target_published(&target_request, &versions)
+            .await?;
+
+        // Check compatibility, weighed by how widely the target is adopted
+        let adoption = self
+            .adoption_signals(&target_request, &versions, &mut warnings)
+            .await;
+
+        // Generate changes, unless there is nothing in the repository to edit
+        // and the target is the exact version already in use
+        let already_applied = request.manifests.is_empty()
+            && request.replacement_package.is_none()
+            && matches!(current_spec, VersionSpec::Exact(_))
+            && versions.current == versions.target;
+        let mut changes = if already_applied {
+            Vec::new()
+        } else {
+            self.generate_changes(&request, &versions)?
+        };
+        // Nothing to edit is only an upgrade already applied when a
+        // manifest declares the package
+        if changes.is_empty() && !already_applied && !self.declares(&request) {
+            return Err(UpgradeError {
+                message: format!(
+                    "Package '{}' not found in the supplied manifests",
+                    request.package_name
+                ),
+                error_type: ErrorType::Validation,
+            });
+        }
+        scope::retain_changes(&request, &mut changes)?;
+        let no_change = changes.is_empty();
+        if !no_change {
+            self.run_codemods(
+                checkout.as_ref(),
+                &request,
+                &versions,
+                &mut changes,
+                &mut warnings,
+            )
+            .await?;
+        }
+        if self.config.regenerate_lockfiles {
+            warnings.extend(self.regenerate_lockfiles(&request, &mut changes).await?);
+        }
+
+        // Assess risk, for the scoped component alone
+        let scoped_request = scope::scoped_request(&request);
+        let mut risk_assessment = self.assess_risk(&scoped_request, &versions, &changes)?;
+        self.check_api_breaks(&request, &versions, &mut risk_assessment, &mut warnings)
+            .await;
+        let release_notes = self
+            .check_release_notes(&request, &versions, &mut risk_assessment, &mut warnings)
+            .await;
+        self.check_deprecations(
+            &request,
+            &target_request,
+            &versions,
+            release_notes.as_ref(),
+            &mut risk_assessment,
+            &mut warnings,
+        )
+        .await;
+        usages::check_api_usages(
+            &scoped_request,
+            &versions,
+            release_notes.as_ref(),
+            &mut risk_assessment,
+        );
+        self.check_advisories(
+            &request,
+            &target_request,
+            &versions,
+            &mut risk_assessment,
+            &mut warnings,
+        )
+        .await;
+        self.check_transitive_advisories(&request, &changes, &mut risk_assessment, &mut warnings)
+            .await;
+        self.check_supply_chain(
+            &request,
+            &target_request,
+            &versions,
+            &mut risk_assessment,
+            &mut warnings,
+        )
+        .await;
+        self.check_provenance(
+            &target_request,
+            &versions,
+            &mut risk_assessment,
+            &mut warnings,
+        )
+        .await;
+        self.check_license(
+            &request,
+            &target_request,
+            &versions,
+            &mut risk_assessment,
+            &mut warnings,
+        )
+        .await;
+        self.check_image_vulnerabilities(&request, &versions, &mut risk_assessment, &mut warnings)
+            .await;
+
+        // Flag toolchain requirement bumps and dropped features
+        if request.ecosystem == "cargo" {
+            self.check_msrv(&target_request, &versions, &mut risk_assessment)
+                .await?;
+            if request.replacement_package.is_none() {
+                self.check_features(&scoped_request, &versions, &mut risk_assessment)
+                    .await?;
+            }
+        }
+
+        // Build and test the upgraded checkout
+        let verification = self
+            .verify_upgrade(
+                checkout.as_ref(),
+                &request,
+                &versions,
+                &changes,
+                &mut risk_assessment,
+                &mut warnings,
+            )
+            .await?;
+        let mut suggested_changes = self
+            .suggest_fixes(
+                &request,
+                &versions,
+                verification.as_ref(),
+                release_notes.as_ref(),
+                &mut changes,
+                &mut warnings,
+            )
+            .await;
 
-        // Check compatibility
-        let compatibility_score = self.assess_compatibility(&request)?;
+        // Score the upgrade from every signal gathered
+        let policy = match &self.policy_file {
+            Some(file) => Some(file.current(&mut warnings)?),
+            None => self.config.policy.clone().map(Arc::new),
+        };
+        let weights = policy
+            .as_ref()
+            .and_then(|policy| policy.weights)
+            .unwrap_or(self.config.score_weights);
+        let score_breakdown = self.assess_compatibility(
+            &request,
+            &weights,
+            &risk_assessment,
+            adoption.as_ref(),
+            verification.as_ref(),
+        )?;
+        let compatibility_score = score_breakdown.score;
 
-        // Generate changes
-        let changes = self.generate_changes(&request)?;
+        // Propose intermediate steps when planning was requested
+        let plan = if planner::planning_requested(&request) {
+            Some(self.plan_upgrade(&request, &versions).await?)
+        } else {
+            None
+        };
 
-        // Assess risk
-        let risk_assessment = self.assess_risk(&request, &changes)?;
+        // Apply the policy to the scored upgrade
+        let policy = policy.map(|policy| policy.evaluate(&request, &mut risk_assessment));
+        let blocked = policy.as_ref().is_some_and(|decision| decision.blocked);
+        if blocked {
+            warnings.push(
+                "Blocked by policy: the changes were neither written nor committed".to_string(),
+            );
+        }
+
+        // Make sure the edits still apply to the default branch
+        let conflicts = self
+            .check_conflicts(checkout.as_ref(), &request, &changes, &mut warnings)
+            .await?;
+
+        // Edit a local workspace in place
+        if let Some(checkout) = checkout
+            .as_ref()
+            .filter(|checkout| checkout.local && !blocked)
+        {
+            repo::write_changes(&checkout.path, &changes)?;
+        }
+
+        // Commit the edits while they are still whole files
+        let changeset_id = uuid::Uuid::new_v4().to_string();
+        request.job_id.get_or_insert_with(|| changeset_id.clone());
+        let commit = if blocked {
+            None
+        } else {
+            self.commit_upgrade(
+                checkout.as_ref(),
+                &request,
+                &versions,
+                &risk_assessment,
+                &changes,
+                &mut warnings,
+            )?
+        };
+        let pull_request = if blocked {
+            None
+        } else if conflicts.is_empty() {
+            let description = scm::description(
+                &request,
+                &versions,
+                &risk_assessment,
+                release_notes.as_ref(),
+                &changes,
+            );
+            self.open_pull_request(
+                checkout.as_ref().zip(commit.as_ref()),
+                &request,
+                &risk_assessment,
+                description,
+                &changes,
+                &mut warnings,
+            )
+            .await?
+        } else {
+            if self.config.scm.is_some() {
+                warnings.push(
+                    "No pull request opened: the changes conflict with the default branch"
+                        .to_string(),
+                );
+            }
+            None
+        };
+
+        let patches = if request.change_format == ChangeFormat::FormatPatch {
+            self.format_patches(
+                &request,
+                &versions,
+                &risk_assessment,
+                &changes,
+                &changeset_id,
+            )?
+        } else {
+            Vec::new()
+        };
+
+        let recommended_action = RecommendedAction::recommend(
+            &risk_assessment,
+            verification.as_ref(),
+            &changes,
+            blocked,
+        );
+        let original_hashes = changeset::original_hashes(&request, &changes);
+        diff::apply_change_format(&request, &mut changes);
+        diff::apply_change_format(&request, &mut suggested_changes);
+
+        let mut workspaces: Vec<String> = changes
+            .iter()
+            .filter_map(|change| change.metadata.get("workspace")?.as_str())
+            .map(str::to_string)
+            .collect();
+        workspaces.sort();
+        workspaces.dedup();
+
+        let message = if no_change {
+            format!(
+                "No changes needed: '{}' is already at {}",
+                request.package_name, versions.target
+            )
+        } else if warnings.is_empty() {
+            "Upgrade processed successfully".to_string()
+        } else {
+            "Upgrade processed with warnings".to_string()
+        };
 
         Ok(UpgradeResponse {
             success: true,
-            message: "Upgrade processed successfully".to_string(),
+            message,
             changes,
             compatibility_score,
+            recommended_action,
+            score_breakdown,
             risk_assessment,
+            resolved_version: versions.target.to_string(),
+            version_scheme: self.version_scheme(&request).name().to_string(),
+            warnings,
+            plan,
+            workspaces,
+            changeset_id,
+            original_hashes,
+            no_change,
+            base_commit: checkout
+                .map(|checkout| checkout.commit)
+                .filter(|commit| !commit.is_empty()),
+            branch: commit.as_ref().map(|commit| commit.branch.clone()),
+            commit_sha: commit.map(|commit| commit.sha),
+            pull_request,
+            patches,
+            conflicts,
+            release_notes,
+            adoption,
+            verification,
+            suggested_changes,
+            policy,
         })
     }
 
-    fn validate_request(&self, request: &UpgradeRequest) -> Result<(), UpgradeError> {
+    fn prerelease_policy(
+        &self,
+        request: &UpgradeRequest,
+    ) -> Result<PrereleasePolicy, UpgradeError> {
+        match request.metadata.get(PRERELEASE_POLICY_METADATA_KEY) {
+            Some(value) => serde_json::from_value(value.clone()).map_err(|_| UpgradeError {
+                message: format!(
+                    "Invalid {}: {} (expected \"accept\", \"skip\" or \"reject\")",
+                    PRERELEASE_POLICY_METADATA_KEY, value
+                ),
+                error_type: ErrorType::Validation,
+            }),
+            None => Ok(self.config.prerelease_policy),
+        }
+    }
+
+    async fn apply_prerelease_policy(
+        &self,
+        request: &UpgradeRequest,
+        versions: ResolvedVersions,
+        warnings: &mut Vec<String>,
+    ) -> Result<ResolvedVersions, UpgradeError> {
+        if !versions.target.is_prerelease() {
+            return Ok(versions);
+        }
+
+        match self.prerelease_policy(request)? {
+            PrereleasePolicy::Accept => Ok(versions),
+            PrereleasePolicy::Reject => Err(UpgradeError {
+                message: format!(
+                    "Target version {} of '{}' is a pre-release; pre-releases are rejected by policy",
+                    versions.target, request.package_name
+                ),
+                error_type: ErrorType::Validation,
+            }),
+            PrereleasePolicy::Skip => {
+                let published = self.published_versions(request).await?;
+                let stable = nearest_stable(&published, &versions.target).ok_or_else(|| {
+                    UpgradeError {
+                        message: format!(
+                            "Target version {} of '{}' is a pre-release and no stable release was found to use instead",
+                            versions.target, request.package_name
+                        ),
+                        error_type: ErrorType::Validation,
+                    }
+                })?;
+
+                warnings.push(format!(
+                    "Pre-release target {} replaced with stable release {}",
+                    versions.target, stable
+                ));
+                Ok(ResolvedVersions {
+                    current: versions.current,
+                    target: stable,
+                })
+            }
+        }
+    }
+
+    fn check_downgrade(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Option<String>, UpgradeError> {
+        if VersionJump::classify(&versions.current, &versions.target) != VersionJump::Downgrade {
+            return Ok(None);
+        }
+
+        let description = format!(
+            "Target version {} of '{}' is lower than current version {}",
+            versions.target, request.package_name, versions.current
+        );
+
+        match self.config.downgrade_policy {
+            DowngradePolicy::Reject => Err(UpgradeError {
+                message: format!("{}; downgrades are rejected by policy", description),
+                error_type: ErrorType::Validation,
+            }),
+            DowngradePolicy::Warn => Ok(Some(format!("{}; manual approval required", description))),
+            DowngradePolicy::Allow => Ok(None),
+        }
+    }
+
+    fn validate_request(
+        &self,
+        request: &UpgradeRequest,
+    ) -> Result<(VersionSpec, VersionSpec), UpgradeError> {
         if request.repository.is_empty() {
             return Err(UpgradeError {
                 message: "Repository cannot be empty".to_string(),
@@ -152,141 +1026,376 @@ impl UpgradeWorker {
             });
         }
 
-        if !self.is_valid_version(&request.current_version) {
+        rename::validate(request)?;
+
+        // Names become arguments of package manager commands
+        for name in [&request.package_name]
+            .into_iter()
+            .chain(&request.replacement_package)
+        {
+            if !is_valid_package_name(name) {
+                return Err(UpgradeError {
+                    message: format!("Invalid package name '{}'", name),
+                    error_type: ErrorType::Validation,
+                });
+            }
+        }
+
+        scope::prefix(request)?;
+
+        let current = self.parse_spec(request, "current", &request.current_version)?;
+        let target = self.parse_spec(request, "target", &request.target_version)?;
+
+        if let VersionSpec::Latest { .. } | VersionSpec::Tag(_) = current {
             return Err(UpgradeError {
-                message: format!("Invalid current version: {}", request.current_version),
+                message: format!(
+                    "Current version must be a version or requirement, not '{}'",
+                    request.current_version
+                ),
                 error_type: ErrorType::Validation,
             });
         }
 
-        if !self.is_valid_version(&request.target_version) {
+        Ok((current, target))
+    }
+
+    /// Versioning scheme for the request: the ecosystem's own, unless both
+    /// versions are calendar versions.
+    fn version_scheme(&self, request: &UpgradeRequest) -> &'static dyn VersionScheme {
+        version::detect_scheme(
+            &request.ecosystem,
+            &[&request.current_version, &request.target_version],
+        )
+    }
+
+    fn parse_spec(
+        &self,
+        request: &UpgradeRequest,
+        field: &str,
+        version: &str,
+    ) -> Result<VersionSpec, UpgradeError> {
+        VersionSpec::parse_with(&request.ecosystem, self.version_scheme(request), version).map_err(
+            |e| UpgradeError {
+                message: format!("Invalid {} version '{}': {}", field, version, e),
+                error_type: ErrorType::Validation,
+            },
+        )
+    }
+
+    async fn resolve_versions(
+        &self,
+        request: &UpgradeRequest,
+        current: &VersionSpec,
+        target: &VersionSpec,
+    ) -> Result<ResolvedVersions, UpgradeError> {
+        let target_request = rename::target_request(request);
+        let target = &match self.resolve_dist_tag(&target_request, target).await? {
+            Some(tagged) => VersionSpec::Exact(tagged),
+            None => target.clone(),
+        };
+
+        if let (VersionSpec::Exact(current), VersionSpec::Exact(target)) = (current, target) {
+            return Ok(ResolvedVersions {
+                current: current.clone(),
+                target: target.clone(),
+            });
+        }
+
+        if !self.has_registry_for(request) {
             return Err(UpgradeError {
-                message: format!("Invalid target version: {}", request.target_version),
+                message: format!(
+                    "Resolving version requirements for '{}' requires a registry",
+                    request.package_name
+                ),
                 error_type: ErrorType::Validation,
             });
         }
 
-        Ok(())
+        let published = self.published_versions(request).await?;
+        let published_targets = match &request.replacement_package {
+            Some(_) => self.published_versions(&target_request).await?,
+            None => published.clone(),
+        };
+
+        let resolve =
+            |field: &str, spec: &VersionSpec, published: &[ParsedVersion], package: &str| {
+                spec.resolve(published).ok_or_else(|| UpgradeError {
+                    message: format!(
+                        "No published version of '{}' satisfies {} requirement '{}'",
+                        package, field, spec
+                    ),
+                    error_type: ErrorType::Validation,
+                })
+            };
+
+        Ok(ResolvedVersions {
+            current: resolve("current", current, &published, &request.package_name)?,
+            target: resolve(
+                "target",
+                target,
+                &published_targets,
+                &target_request.package_name,
+            )?,
+        })
     }
 
-    fn is_valid_version(&self, version: &str) -> bool {
-        // Basic semantic version validation
-        let parts: Vec<&str> = version.split('.').collect();
-        if parts.len() < 2 || parts.len() > 3 {
-            return false;
+    /// Looks up npm dist-tag targets in the packument. `latest` and `next`
+    /// fall back to the newest published release when the tag is missing.
+    async fn resolve_dist_tag(
+        &self,
+        request: &UpgradeRequest,
+        target: &VersionSpec,
+    ) -> Result<Option<ParsedVersion>, UpgradeError> {
+        let tag = match target.dist_tag() {
+            Some(tag) if request.ecosystem == "npm" => tag,
+            _ => return Ok(None),
+        };
+        let registry = match &self.registry {
+            Some(registry) if registry.supports(&request.ecosystem) => registry,
+            _ => return Ok(None),
+        };
+
+        let tags = registry
+            .dist_tags(&request.ecosystem, &request.package_name)
+            .await?;
+        match tags.get(tag) {
+            Some(version) => self
+                .version_scheme(request)
+                .parse(version)
+                .map(Some)
+                .map_err(|e| UpgradeError {
+                    message: format!(
+                        "Dist-tag '{}' of '{}' points at invalid version '{}': {}",
+                        tag, request.package_name, version, e
+                    ),
+                    error_type: ErrorType::Registry,
+                }),
+            None if matches!(target, VersionSpec::Tag(_)) => Err(UpgradeError {
+                message: format!(
+                    "Package '{}' has no dist-tag '{}'",
+                    request.package_name, tag
+                ),
+                error_type: ErrorType::Validation,
+            }),
+            None => Ok(None),
         }
+    }
 
-        for part in parts {
-            if part.is_empty() {
-                return false;
-            }
-            if !part.chars().all(|c| c.is_alphanumeric() || c == '-') {
-                return false;
-            }
+    fn has_registry_for(&self, request: &UpgradeRequest) -> bool {
+        self.registry
+            .as_ref()
+            .map(|registry| registry.supports(&request.ecosystem))
+            .unwrap_or(false)
+    }
+
+    /// All registry releases of the requested package, or `None` when no
+    /// registry is configured for the ecosystem.
+    async fn registry_releases(
+        &self,
+        request: &UpgradeRequest,
+    ) -> Result<Option<Vec<ReleaseInfo>>, UpgradeError> {
+        match &self.registry {
+            Some(registry) if registry.supports(&request.ecosystem) => registry
+                .releases(&request.ecosystem, &request.package_name)
+                .await
+                .map(Some),
+            _ => Ok(None),
         }
+    }
 
-        true
+    /// Non-yanked releases of the requested package that parse under the
+    /// ecosystem's version scheme. Empty when no registry is configured.
+    async fn published_versions(
+        &self,
+        request: &UpgradeRequest,
+    ) -> Result<Vec<ParsedVersion>, UpgradeError> {
+        let scheme = self.version_scheme(request);
+        Ok(self
+            .registry_releases(request)
+            .await?
+            .unwrap_or_default()
+            .iter()
+            .filter(|release| !release.yanked)
+            .filter_map(|release| scheme.parse(&release.version).ok())
+            .collect())
     }
 
-    fn assess_compatibility(&self, request: &UpgradeRequest) -> Result<f64, UpgradeError> {
-        // Simulate compatibility assessment
-        let base_score = 0.8;
-        
-        // Adjust based on ecosystem
-        let ecosystem_multiplier = match request.ecosystem.as_str() {
-            "npm" => 1.0,
-            "cargo" => 0.9,
-            "pip" => 0.85,
-            "go" => 0.95,
-            _ => 0.7,
+    async fn check_target_published(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<(), UpgradeError> {
+        let releases = match self.registry_releases(request).await? {
+            Some(releases) => releases,
+            None => return Ok(()),
+        };
+
+        let scheme = self.version_scheme(request);
+        let release = releases.iter().find(|release| {
+            scheme
+                .parse(&release.version)
+                .map(|v| v == versions.target)
+                .unwrap_or(false)
+        });
+
+        let problem = match release {
+            None => "has not been published or was removed from the registry".to_string(),
+            Some(release) if release.yanked => "has been yanked".to_string(),
+            Some(_) => return Ok(()),
         };
 
-        let final_score = base_score * ecosystem_multiplier;
-        Ok(final_score.min(1.0))
+        Err(UpgradeError {
+            message: format!(
+                "Target version {} of '{}' {}",
+                versions.target, request.package_name, problem
+            ),
+            error_type: ErrorType::Registry,
+        })
     }
 
-    fn generate_changes(&self, request: &UpgradeRequest) -> Result<Vec<Change>, UpgradeError> {
-        let mut changes = Vec::new();
+    fn assess_compatibility(
+        &self,
+        request: &UpgradeRequest,
+        weights: &score::ScoreWeights,
+        risk: &RiskAssessment,
+        adoption: Option<&adoption::AdoptionSignals>,
+        verification: Option<&verification::Verification>,
+    ) -> Result<score::ScoreBreakdown, UpgradeError> {
+        weights.validate()?;
+        let mut breakdown = score::ScoreBreakdown::default();
 
-        // Generate package.json change for npm
-        if request.ecosystem == "npm" {
-            changes.push(Change {
-                file_path: "package.json".to_string(),
-                change_type: ChangeType::Modify,
-                content: format!(
-                    r#"{{"dependencies": {{"{}": "{}"}}}}"#,
-                    request.package_name, request.target_version
+        // How upgrades of the ecosystem usually go
+        breakdown.add(
+            weights.ecosystem,
+            match ecosystems::ecosystem_for(&request.ecosystem) {
+                Some(ecosystem) => score::ScoreFactor::new(
+                    "ecosystem",
+                    ecosystem.compatibility(request),
+                    &format!("ecosystem:{}", ecosystem.name()),
+                    format!("{} upgrades", ecosystem.name()),
                 ),
-                metadata: HashMap::new(),
-            });
-        }
-
-        // Generate Cargo.toml change for Rust
-        if request.ecosystem == "cargo" {
-            changes.push(Change {
-                file_path: "Cargo.toml".to_string(),
-                change_type: ChangeType::Modify,
-                content: format!(
-                    r#"[dependencies]{} = "{}""#,
-                    request.package_name, request.target_version
+                None => score::ScoreFactor::new(
+                    "ecosystem",
+                    0.7,
+                    "worker",
+                    format!("'{}' has no ecosystem handler", request.ecosystem),
                 ),
-                metadata: HashMap::new(),
-            });
-        }
+            },
+        );
+        breakdown.add(
+            weights.version_jump,
+            score::version_jump_factor(risk.version_jump, risk.breaking_changes),
+        );
+        breakdown.add(
+            weights.security,
+            score::security_factor(&risk.security_issues),
+        );
+        breakdown.add(
+            weights.popularity,
+            score::popularity_factor(
+                adoption
+                    .map(adoption::AdoptionSignals::adjustments)
+                    .unwrap_or_default(),
+            ),
+        );
+        breakdown.add(
+            weights.verification,
+            score::verification_factor(verification),
+        );
 
-        Ok(changes)
+        Ok(breakdown)
     }
 
-    fn assess_risk(&self, request: &UpgradeRequest, changes: &[Change]) -> Result<RiskAssessment, UpgradeError> {
-        let mut risk_level = RiskLevel::Low;
-        let mut breaking_changes = false;
-        let mut security_issues = Vec::new();
-        let mut performance_impact = PerformanceImpact::None;
+    fn generate_changes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<Vec<Change>, UpgradeError> {
+        let mut changes = Vec::new();
 
-        // Assess version jump
-        if self.is_major_version_jump(&request.current_version, &request.target_version) {
-            risk_level = RiskLevel::High;
-            breaking_changes = true;
+        // Delegate to the ecosystem's manifest handler
+        if let Some(ecosystem) = ecosystems::ecosystem_for(&request.ecosystem) {
+            changes.extend(ecosystem.generate_changes(request, versions)?);
         }
 
-        // Check for known security issues
-        if self.has_known_vulnerabilities(&request.package_name, &request.target_version) {
-            security_issues.push("Known security vulnerability detected".to_string());
-            risk_level = RiskLevel::Critical;
+        // Record what a tag-based target resolved to
+        if let Some(tag) = self
+            .parse_spec(request, "target", &request.target_version)?
+            .dist_tag()
+            .filter(|_| request.ecosystem == "npm")
+        {
+            for change in changes
+                .iter_mut()
+                .filter(|change| !change.metadata.contains_key("lockfile_refresh"))
+            {
+                change
+                    .metadata
+                    .insert("dist_tag".to_string(), serde_json::json!(tag));
+                change.metadata.insert(
+                    "resolved_version".to_string(),
+                    serde_json::json!(versions.target.to_string()),
+                );
+            }
         }
 
-        // Assess performance impact
-        if changes.len() > 5 {
-            performance_impact = PerformanceImpact::Medium;
-        }
+        // Config files added or removed by the new major release
+        changes.extend(migrations::migration_changes(request, versions));
 
-        Ok(RiskAssessment {
-            risk_level,
-            breaking_changes,
-            security_issues,
-            performance_impact,
-        })
+        Ok(changes)
     }
 
-    fn is_major_version_jump(&self, current: &str, target: &str) -> bool {
-        let current_parts: Vec<&str> = current.split('.').collect();
-        let target_parts: Vec<&str> = target.split('.').collect();
+    // Whether the ecosystem's handler finds the package declared in the
+    // supplied manifests.
+    fn declares(&self, request: &UpgradeRequest) -> bool {
+        ecosystems::ecosystem_for(&request.ecosystem)
+            .is_some_and(|ecosystem| ecosystem.declares(request))
+    }
 
-        if current_parts.is_empty() || target_parts.is_empty() {
-            return false;
-        }
+    fn assess_risk(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        changes: &[Change],
+    ) -> Result<RiskAssessment, UpgradeError> {
+        let mut assessment = RiskAssessment {
+            version_jump: VersionJump::classify(&versions.current, &versions.target),
+            ..Default::default()
+        };
 
-        let current_major = current_parts[0].parse::<u32>().unwrap_or(0);
-        let target_major = target_parts[0].parse::<u32>().unwrap_or(0);
+        let context = risk::RiskContext {
+            request,
+            versions,
+            changes,
+        };
+        for rule in &self.risk_rules {
+            rule.evaluate(&context, &mut assessment);
+        }
 
-        target_major > current_major
+        Ok(assessment)
     }
+}
 
-    fn has_known_vulnerabilities(&self, package_name: &str, version: &str) -> bool {
-        // Simulate vulnerability check
-        // In a real implementation, this would query a vulnerability database
-        package_name.contains("vulnerable") || version.contains("0.0.0")
-    }
+/// Package names may not contain whitespace or control characters, nor
+/// start with `-` where a command would read them as a flag.
+pub(crate) fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// The stable release of the same version as a pre-release if it has been
+/// published, otherwise the newest stable release preceding it.
+fn nearest_stable(
+    published: &[ParsedVersion],
+    prerelease: &ParsedVersion,
+) -> Option<ParsedVersion> {
+    let stable = || published.iter().filter(|v| !v.is_prerelease());
+
+    stable()
+        .filter(|v| v.release() == prerelease.release())
+        .min()
+        .or_else(|| stable().filter(|v| *v < prerelease).max())
+        .cloned()
 }
 
 #[cfg(test)]
@@ -302,14 +1411,41 @@ mod tests {
 
     #[test]
     fn test_version_validation() {
+        let is_valid = |version: &str| version::SemanticScheme.parse(version).is_ok();
+
+        assert!(is_valid("1.0.0"));
+        assert!(is_valid("2.1.3"));
+        assert!(is_valid("0.5.10"));
+        assert!(!is_valid("1.0"));
+        assert!(!is_valid("invalid"));
+        assert!(!is_valid(""));
+    }
+
+    #[test]
+    fn test_version_validation_prerelease_and_build() {
         let worker = UpgradeWorker::new(None);
-        
-        assert!(worker.is_valid_version("1.0.0"));
-        assert!(worker.is_valid_version("2.1.3"));
-        assert!(worker.is_valid_version("0.5.10"));
-        assert!(!worker.is_valid_version("1.0"));
-        assert!(!worker.is_valid_version("invalid"));
-        assert!(!worker.is_valid_version(""));
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "1.0.0".to_string(),
+            target_version: "a.b".to_string(),
+            metadata: HashMap::new(),
+            ..Default::default()
+        };
+        let parse = |version: &str| worker.parse_spec(&request, "target", version);
+
+        assert!(matches!(
+            parse("1.0.0-beta.1+build.5"),
+            Ok(VersionSpec::Exact(_))
+        ));
+        assert!(matches!(parse("2.0.0-rc.1"), Ok(VersionSpec::Exact(_))));
+        assert!(parse("a.b").is_err());
+        assert!(parse("1.a.0").is_err());
+
+        let err = worker.validate_request(&request).unwrap_err();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+        assert!(err.message.contains("Invalid target version 'a.b'"));
     }
 
     #[test]
@@ -323,6 +1459,7 @@ mod tests {
             current_version: "1.0.0".to_string(),
             target_version: "2.0.0".to_string(),
             metadata: HashMap::new(),
+            ..Default::default()
         };
 
         assert!(worker.validate_request(&valid_request).is_ok());
@@ -334,9 +1471,24 @@ mod tests {
             current_version: "1.0.0".to_string(),
             target_version: "2.0.0".to_string(),
             metadata: HashMap::new(),
+            ..Default::default()
         };
 
         assert!(worker.validate_request(&invalid_request).is_err());
+
+        for name in ["x && cargo install evil", "--config=evil", "left\npad"] {
+            let request = UpgradeRequest {
+                package_name: name.to_string(),
+                ..valid_request.clone()
+            };
+            let err = worker.validate_request(&request).unwrap_err();
+            assert!(err.message.starts_with("Invalid package name"));
+        }
+        let request = UpgradeRequest {
+            replacement_package: Some("-lodash".to_string()),
+            ..valid_request.clone()
+        };
+        assert!(worker.validate_request(&request).is_err());
     }
 
     #[test]
@@ -350,29 +1502,42 @@ mod tests {
             current_version: "1.0.0".to_string(),
             target_version: "2.0.0".to_string(),
             metadata: HashMap::new(),
+            ..Default::default()
         };
 
-        let score = worker.assess_compatibility(&request).unwrap();
+        let versions = ResolvedVersions {
+            current: version::SemanticScheme.parse("1.0.0").unwrap(),
+            target: version::SemanticScheme.parse("2.0.0").unwrap(),
+        };
+        let risk = worker.assess_risk(&request, &versions, &[]).unwrap();
+        let weights = score::ScoreWeights::default();
+        let score = worker
+            .assess_compatibility(&request, &weights, &risk, None, None)
+            .unwrap()
+            .score;
         assert!(score >= 0.0 && score <= 1.0);
+        // Default weights: npm 0.3, a breaking major 0.125, no advisory 0.2,
+        // no adoption data 0.12 and no verification 0.05
+        assert!((score - 0.795).abs() < 1e-9);
+
+        let invalid = score::ScoreWeights {
+            verification: 0.5,
+            ..weights
+        };
+        assert!(worker
+            .assess_compatibility(&request, &invalid, &risk, None, None)
+            .is_err());
     }
 
     #[test]
     fn test_major_version_jump_detection() {
-        let worker = UpgradeWorker::new(None);
-        
-        assert!(worker.is_major_version_jump("1.0.0", "2.0.0"));
-        assert!(worker.is_major_version_jump("1.5.0", "2.0.0"));
-        assert!(!worker.is_major_version_jump("1.0.0", "1.5.0"));
-        assert!(!worker.is_major_version_jump("2.0.0", "1.0.0"));
-    }
+        let v = |s: &str| version::SemanticScheme.parse(s).unwrap();
 
-    #[test]
-    fn test_vulnerability_detection() {
-        let worker = UpgradeWorker::new(None);
-        
-        assert!(worker.has_known_vulnerabilities("vulnerable-package", "1.0.0"));
-        assert!(worker.has_known_vulnerabilities("normal-package", "0.0.0"));
-        assert!(!worker.has_known_vulnerabilities("normal-package", "1.0.0"));
+        assert!(risk::is_major_version_jump(&v("1.0.0"), &v("2.0.0")));
+        assert!(risk::is_major_version_jump(&v("1.5.0"), &v("2.0.0")));
+        assert!(!risk::is_major_version_jump(&v("1.0.0"), &v("1.5.0")));
+        assert!(!risk::is_major_version_jump(&v("2.0.0"), &v("1.0.0")));
+        assert!(risk::is_major_version_jump(&v("1.9.0"), &v("2.0.0-beta.1")));
     }
 
     #[tokio::test]
@@ -386,6 +1551,11 @@ mod tests {
             current_version: "1.0.0".to_string(),
             target_version: "2.0.0".to_string(),
             metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "1.0.0"}}"#.to_string(),
+            )]),
+            ..Default::default()
         };
 
         let response = worker.process_upgrade(request).await.unwrap();
@@ -393,5 +1563,471 @@ mod tests {
         assert!(response.success);
         assert!(response.compatibility_score > 0.0);
         assert!(!response.changes.is_empty());
+        assert_eq!(response.changeset_id.len(), 36);
+        assert!(!response.no_change);
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_target_already_applied() {
+        let worker = UpgradeWorker::new(None);
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.21".to_string(),
+            target_version: "4.17.21".to_string(),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request.clone()).await.unwrap();
+        assert!(response.success);
+        assert!(response.no_change);
+        assert!(response.changes.is_empty());
+
+        let declared = UpgradeRequest {
+            current_version: "4.17.20".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "^4.17.21"}}"#.to_string(),
+            )]),
+            ..request.clone()
+        };
+        let response = worker.process_upgrade(declared).await.unwrap();
+        assert!(response.no_change);
+        assert!(response.message.starts_with("No changes needed"));
+
+        let undeclared = UpgradeRequest {
+            current_version: "4.17.20".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash-es": "^4.17.21"}}"#.to_string(),
+            )]),
+            ..request
+        };
+        let error = worker.process_upgrade(undeclared).await.unwrap_err();
+        assert!(matches!(error.error_type, ErrorType::Validation));
+        assert_eq!(
+            error.message,
+            "Package 'lodash' not found in the supplied manifests"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_with_version_requirements() {
+        let registry = registry::StaticRegistry::new().with_releases(
+            "cargo",
+            "serde",
+            ["1.0.100", "1.0.190", "1.0.195", "2.0.0"]
+                .iter()
+                .map(|v| registry::ReleaseInfo::new(v))
+                .collect(),
+        );
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "cargo".to_string(),
+            package_name: "serde".to_string(),
+            current_version: "~1.0.100".to_string(),
+            target_version: ">=1.0.150, <2".to_string(),
+            metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "Cargo.toml".to_string(),
+                "[dependencies]\nserde = \"~1.0.100\"\n".to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert!(response.changes[0].content.contains("\"~1.0.195\""));
+        assert!(!response.risk_assessment.breaking_changes);
+    }
+
+    #[tokio::test]
+    async fn test_version_requirement_without_registry() {
+        let worker = UpgradeWorker::new(None);
+        
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "1.0.0".to_string(),
+            target_version: "^4.17".to_string(),
+            metadata: HashMap::new(),
+            ..Default::default()
+        };
+
+        let err = worker.process_upgrade(request).await.unwrap_err();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+    }
+
+    #[test]
+    fn test_risk_reflects_version_jump() {
+        let worker = UpgradeWorker::new(None);
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "cargo".to_string(),
+            package_name: "serde".to_string(),
+            current_version: "1.2.3".to_string(),
+            target_version: "1.2.4".to_string(),
+            metadata: HashMap::new(),
+            ..Default::default()
+        };
+        let assess = |current: &str, target: &str| {
+            let versions = ResolvedVersions {
+                current: version::SemanticScheme.parse(current).unwrap(),
+                target: version::SemanticScheme.parse(target).unwrap(),
+            };
+            worker.assess_risk(&request, &versions, &[]).unwrap()
+        };
+
+        let patch = assess("1.2.3", "1.2.4");
+        assert_eq!(patch.version_jump, VersionJump::Patch);
+        assert!(matches!(patch.risk_level, RiskLevel::Low));
+
+        let minor = assess("1.2.0", "1.9.0");
+        assert_eq!(minor.version_jump, VersionJump::Minor);
+        assert!(matches!(minor.risk_level, RiskLevel::Medium));
+
+        let major = assess("1.2.0", "2.0.0");
+        assert_eq!(major.version_jump, VersionJump::Major);
+        assert!(matches!(major.risk_level, RiskLevel::High));
+        assert!(major.breaking_changes);
+
+        let zero_minor = assess("0.3.2", "0.4.0");
+        assert_eq!(zero_minor.version_jump, VersionJump::Minor);
+        assert!(matches!(zero_minor.risk_level, RiskLevel::High));
+        assert!(zero_minor.breaking_changes);
+        assert!(!assess("0.3.2", "0.3.5").breaking_changes);
+    }
+
+    #[test]
+    fn test_dev_dependency_risk() {
+        let worker = UpgradeWorker::new(None);
+        let mut request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "jest".to_string(),
+            dependency_kind: Some(DependencyKind::Dev),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: version::SemanticScheme.parse("28.1.3").unwrap(),
+            target: version::SemanticScheme.parse("29.7.0").unwrap(),
+        };
+
+        let dev = worker.assess_risk(&request, &versions, &[]).unwrap();
+        assert_eq!(dev.risk_level, RiskLevel::Medium);
+        assert!(dev.breaking_changes);
+        assert_eq!(
+            dev.explanations,
+            ["jest is a development dependency and is not shipped"]
+        );
+
+        request.dependency_kind = Some(DependencyKind::Normal);
+        let runtime = worker.assess_risk(&request, &versions, &[]).unwrap();
+        assert_eq!(runtime.risk_level, RiskLevel::High);
+    }
+
+    #[tokio::test]
+    async fn test_recommended_action() {
+        let worker = UpgradeWorker::new(None);
+        let request = |target: &str| UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.20".to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "4.17.20"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let patch = worker.process_upgrade(request("4.17.21")).await.unwrap();
+        assert_eq!(patch.recommended_action, RecommendedAction::AutoMerge);
+        let major = worker.process_upgrade(request("5.0.0")).await.unwrap();
+        assert_eq!(major.recommended_action, RecommendedAction::ReviewRequired);
+
+        let mut risk = patch.risk_assessment;
+        let recommend =
+            |risk: &RiskAssessment| RecommendedAction::recommend(risk, None, &patch.changes, false);
+        risk.security_issues.push(advisories::SecurityIssue {
+            fixed_by_upgrade: true,
+            ..advisories::SecurityIssue::from("Prototype pollution".to_string())
+        });
+        assert_eq!(recommend(&risk), RecommendedAction::SecurityFastTrack);
+        risk.risk_level = RiskLevel::Critical;
+        assert_eq!(recommend(&risk), RecommendedAction::Block);
+        assert_eq!(
+            RecommendedAction::recommend(&major.risk_assessment, None, &[], true),
+            RecommendedAction::Block
+        );
+    }
+
+    #[tokio::test]
+    async fn test_downgrade_policy() {
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.21".to_string(),
+            target_version: "4.17.15".to_string(),
+            metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "4.17.21"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+        let worker_with = |downgrade_policy| {
+            UpgradeWorker::new(Some(WorkerConfig {
+                downgrade_policy,
+                ..WorkerConfig::default()
+            }))
+        };
+
+        let err = worker_with(DowngradePolicy::Reject)
+            .process_upgrade(request.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+
+        let warned = worker_with(DowngradePolicy::Warn)
+            .process_upgrade(request.clone())
+            .await
+            .unwrap();
+        assert_eq!(warned.warnings.len(), 1);
+        assert_eq!(warned.risk_assessment.version_jump, VersionJump::Downgrade);
+
+        let allowed = worker_with(DowngradePolicy::Allow)
+            .process_upgrade(request)
+            .await
+            .unwrap();
+        assert!(allowed.warnings.is_empty());
+        assert_eq!(allowed.risk_assessment.version_jump, VersionJump::Downgrade);
+    }
+
+    #[tokio::test]
+    async fn test_calendar_versions() {
+        let worker = UpgradeWorker::new(None);
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "internal-tools".to_string(),
+            current_version: "2023.11.2".to_string(),
+            target_version: "2024.03.1".to_string(),
+            metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"internal-tools": "2023.11.2"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert_eq!(response.version_scheme, "calver");
+        assert_eq!(response.risk_assessment.version_jump, VersionJump::Minor);
+        assert!(!response.risk_assessment.breaking_changes);
+    }
+
+    #[test]
+    fn test_validation_uses_ecosystem_version_scheme() {
+        let worker = UpgradeWorker::new(None);
+        let request = |ecosystem: &str, current: &str, target: &str| UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: ecosystem.to_string(),
+            package_name: "pkg".to_string(),
+            current_version: current.to_string(),
+            target_version: target.to_string(),
+            metadata: HashMap::new(),
+            ..Default::default()
+        };
+
+        let (current, target) = worker
+            .validate_request(&request("pip", "1!2.0.post1", "1!2.1rc1"))
+            .unwrap();
+        assert!(current.is_exact() && target.is_exact());
+        assert!(worker
+            .validate_request(&request("maven", "5.9.0", "5.10.0-SNAPSHOT"))
+            .is_ok());
+        assert!(worker
+            .validate_request(&request("pip", "1.0", "1.0-SNAPSHOT"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_latest_target_resolution() {
+        let registry = registry::StaticRegistry::new().with_releases(
+            "npm",
+            "react",
+            ["17.0.2", "18.2.0", "18.3.1", "19.0.0-rc.1"]
+                .iter()
+                .map(|v| registry::ReleaseInfo::new(v))
+                .collect(),
+        );
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+        let request = |target: &str| UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "react".to_string(),
+            current_version: "17.0.2".to_string(),
+            target_version: target.to_string(),
+            metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"react": "17.0.2"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let latest = worker.process_upgrade(request("latest")).await.unwrap();
+        assert_eq!(latest.resolved_version, "18.3.1");
+
+        let next = worker.process_upgrade(request("next")).await.unwrap();
+        assert_eq!(next.resolved_version, "19.0.0-rc.1");
+
+        let mut invalid = request("18.3.1");
+        invalid.current_version = "latest".to_string();
+        assert!(worker.process_upgrade(invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_npm_dist_tag_targets() {
+        let registry = registry::StaticRegistry::new()
+            .with_releases(
+                "npm",
+                "node-fetch",
+                ["2.6.9", "2.7.0", "3.3.2", "4.0.0-beta.1"]
+                    .iter()
+                    .map(|v| registry::ReleaseInfo::new(v))
+                    .collect(),
+            )
+            .with_dist_tag("npm", "node-fetch", "latest", "3.3.2")
+            .with_dist_tag("npm", "node-fetch", "lts", "2.7.0");
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+        let request = |target: &str| UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "node-fetch".to_string(),
+            current_version: "2.6.9".to_string(),
+            target_version: target.to_string(),
+            metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"node-fetch": "2.6.9"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let lts = worker.process_upgrade(request("lts")).await.unwrap();
+        assert_eq!(lts.resolved_version, "2.7.0");
+        assert_eq!(lts.changes[0].metadata["dist_tag"], "lts");
+        assert_eq!(lts.changes[0].metadata["resolved_version"], "2.7.0");
+
+        let latest = worker.process_upgrade(request("latest")).await.unwrap();
+        assert_eq!(latest.resolved_version, "3.3.2");
+
+        // No `next` tag published: fall back to the newest release
+        let next = worker.process_upgrade(request("next")).await.unwrap();
+        assert_eq!(next.resolved_version, "4.0.0-beta.1");
+
+        let err = worker.process_upgrade(request("canary")).await.unwrap_err();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_targets_are_rejected() {
+        let registry = registry::StaticRegistry::new().with_releases(
+            "cargo",
+            "tokio",
+            vec![
+                registry::ReleaseInfo::new("1.35.0"),
+                registry::ReleaseInfo {
+                    yanked: true,
+                    ..registry::ReleaseInfo::new("1.35.1")
+                },
+            ],
+        );
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+        let request = |target: &str| UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "cargo".to_string(),
+            package_name: "tokio".to_string(),
+            current_version: "1.34.0".to_string(),
+            target_version: target.to_string(),
+            metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "Cargo.toml".to_string(),
+                "[dependencies]\ntokio = \"1.34.0\"\n".to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let yanked = worker.process_upgrade(request("1.35.1")).await.unwrap_err();
+        assert!(matches!(yanked.error_type, ErrorType::Registry));
+        assert!(yanked.message.contains("yanked"));
+
+        let missing = worker.process_upgrade(request("1.99.0")).await.unwrap_err();
+        assert!(matches!(missing.error_type, ErrorType::Registry));
+
+        assert!(worker.process_upgrade(request("1.35.0")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_prerelease_policy() {
+        let registry = registry::StaticRegistry::new().with_releases(
+            "cargo",
+            "axum",
+            ["0.6.20", "0.7.0-rc.1", "0.7.0", "0.7.5"]
+                .iter()
+                .map(|v| registry::ReleaseInfo::new(v))
+                .collect(),
+        );
+        let request = |policy: Option<&str>| {
+            let mut metadata = HashMap::new();
+            if let Some(policy) = policy {
+                metadata.insert(
+                    PRERELEASE_POLICY_METADATA_KEY.to_string(),
+                    serde_json::json!(policy),
+                );
+            }
+            UpgradeRequest {
+                repository: "test/repo".to_string(),
+                ecosystem: "cargo".to_string(),
+                package_name: "axum".to_string(),
+                current_version: "0.6.20".to_string(),
+                target_version: "0.7.0-rc.1".to_string(),
+                metadata,
+                manifests: HashMap::from([(
+                    "Cargo.toml".to_string(),
+                    "[dependencies]\naxum = \"0.6.20\"\n".to_string(),
+                )]),
+                ..Default::default()
+            }
+        };
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            prerelease_policy: PrereleasePolicy::Reject,
+            ..WorkerConfig::default()
+        }))
+        .with_registry(Arc::new(registry));
+
+        let rejected = worker.process_upgrade(request(None)).await.unwrap_err();
+        assert!(matches!(rejected.error_type, ErrorType::Validation));
+
+        let skipped = worker.process_upgrade(request(Some("skip"))).await.unwrap();
+        assert_eq!(skipped.resolved_version, "0.7.0");
+        assert_eq!(skipped.warnings.len(), 1);
+
+        let accepted = worker
+            .process_upgrade(request(Some("accept")))
+            .await
+            .unwrap();
+        assert_eq!(accepted.resolved_version, "0.7.0-rc.1");
+
+        assert!(worker
+            .process_upgrade(request(Some("sometimes")))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,401 @@
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{RiskAssessment, RiskLevel, UpgradeRequest, UpgradeWorker};
+use serde::{Deserialize, Serialize};
+
+/// SPDX identifiers recognised in license fields, with the spellings
+/// packages commonly use instead.
+const LICENSES: &[(&str, &[&str])] = &[
+    ("0BSD", &[]),
+    ("AGPL-3.0-only", &["agpl-3.0", "agplv3", "agpl 3", "agpl"]),
+    ("AGPL-3.0-or-later", &["agpl-3.0+"]),
+    (
+        "Apache-2.0",
+        &[
+            "apache 2.0",
+            "apache2",
+            "apache-2",
+            "apache 2",
+            "apache license 2.0",
+            "apache license, version 2.0",
+            "apache software license",
+        ],
+    ),
+    ("Artistic-2.0", &[]),
+    ("BSD-2-Clause", &["simplified bsd", "freebsd"]),
+    ("BSD-3-Clause", &["new bsd", "bsd license", "modified bsd"]),
+    ("BSL-1.0", &["boost", "boost software license 1.0"]),
+    ("BUSL-1.1", &["business source license 1.1"]),
+    ("CC-BY-4.0", &[]),
+    ("CC0-1.0", &["cc0"]),
+    ("EPL-2.0", &["eclipse public license 2.0"]),
+    ("GPL-2.0-only", &["gpl-2.0", "gplv2", "gpl 2", "gpl2"]),
+    ("GPL-2.0-or-later", &["gpl-2.0+", "gplv2+"]),
+    (
+        "GPL-3.0-only",
+        &["gpl-3.0", "gplv3", "gpl 3", "gpl3", "gpl"],
+    ),
+    ("GPL-3.0-or-later", &["gpl-3.0+", "gplv3+"]),
+    ("ISC", &["isc license"]),
+    ("LGPL-2.1-only", &["lgpl-2.1", "lgplv2.1"]),
+    ("LGPL-2.1-or-later", &["lgpl-2.1+"]),
+    ("LGPL-3.0-only", &["lgpl-3.0", "lgplv3", "lgpl"]),
+    ("LGPL-3.0-or-later", &["lgpl-3.0+"]),
+    ("MIT", &["mit license", "expat"]),
+    ("MIT-0", &[]),
+    ("MPL-2.0", &["mpl 2.0", "mozilla public license 2.0"]),
+    ("PSF-2.0", &["psf", "python software foundation license"]),
+    ("SSPL-1.0", &["server side public license"]),
+    ("Unicode-DFS-2016", &[]),
+    ("Unlicense", &["the unlicense", "unlicensed"]),
+    ("WTFPL", &[]),
+    ("Zlib", &["zlib license"]),
+];
+
+/// Which licenses dependencies may be distributed under. Entries match
+/// an SPDX identifier and its `-only`/`-or-later` variants, so `AGPL-3.0`
+/// covers both.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LicensePolicy {
+    /// Licenses that are accepted; any license is when empty.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Licenses that are never accepted.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// The licenses of the current and target versions, in
+/// [`crate::RiskAssessment::license`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LicenseCheck {
+    /// SPDX expression of the current version, or its license field as
+    /// published when it cannot be normalized.
+    pub current: Option<String>,
+    pub target: Option<String>,
+    /// The target is distributed under another license than the current
+    /// version.
+    pub changed: bool,
+    /// Why the target's license breaks the configured policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub violation: Option<String>,
+}
+
+fn canonical(license: &str) -> Option<&'static str> {
+    let license = license.trim().to_ascii_lowercase();
+    LICENSES
+        .iter()
+        .find(|(id, aliases)| {
+            id.to_ascii_lowercase() == license || aliases.contains(&license.as_str())
+        })
+        .map(|(id, _)| *id)
+}
+
+fn tokens(expression: &str) -> Vec<String> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace('/', " OR ")
+        .split_whitespace()
+        .map(|token| match token.to_ascii_uppercase().as_str() {
+            keyword @ ("OR" | "AND" | "WITH") => keyword.to_string(),
+            _ => token.to_string(),
+        })
+        .collect()
+}
+
+/// Normalizes a license field to an SPDX expression: `MIT License` becomes
+/// `MIT`, `MIT/Apache-2.0` becomes `MIT OR Apache-2.0`. `None` when a
+/// license in it is not recognised.
+pub fn normalize(license: &str) -> Option<String> {
+    if let Some(id) = canonical(license) {
+        return Some(id.to_string());
+    }
+    let mut normalized = Vec::new();
+    let mut exception = false;
+    for token in tokens(license) {
+        match token.as_str() {
+            "OR" | "AND" | "WITH" | "(" | ")" => {
+                exception = token == "WITH";
+                normalized.push(token);
+            }
+            // Exceptions (`Classpath-exception-2.0`) are kept as written
+            _ if exception => {
+                exception = false;
+                normalized.push(token);
+            }
+            _ => normalized.push(canonical(&token)?.to_string()),
+        }
+    }
+    Some(normalized.join(" ").replace("( ", "(").replace(" )", ")"))
+}
+
+// The expression as alternatives, each a set of licenses that all apply
+fn alternatives(tokens: &[String], position: &mut usize) -> Vec<Vec<String>> {
+    let mut any = Vec::new();
+    loop {
+        let mut all: Vec<Vec<String>> = vec![Vec::new()];
+        loop {
+            let term = match tokens.get(*position).map(String::as_str) {
+                Some("(") => {
+                    *position += 1;
+                    let nested = alternatives(tokens, position);
+                    *position += 1;
+                    nested
+                }
+                Some(id) => {
+                    *position += 1;
+                    if tokens.get(*position).map(String::as_str) == Some("WITH") {
+                        *position += 2;
+                    }
+                    vec![vec![id.to_string()]]
+                }
+                None => vec![Vec::new()],
+            };
+            all = all
+                .iter()
+                .flat_map(|left| {
+                    term.iter()
+                        .map(move |right| [left.clone(), right.clone()].concat())
+                })
+                .collect();
+            if tokens.get(*position).map(String::as_str) != Some("AND") {
+                break;
+            }
+            *position += 1;
+        }
+        any.extend(all);
+        if tokens.get(*position).map(String::as_str) != Some("OR") {
+            break;
+        }
+        *position += 1;
+    }
+    any
+}
+
+fn covers(entry: &str, license: &str) -> bool {
+    let entry = entry.to_ascii_lowercase();
+    let license = license.to_ascii_lowercase();
+    license == entry
+        || license == format!("{}-only", entry)
+        || license == format!("{}-or-later", entry)
+        || license == format!("{}+", entry)
+}
+
+impl LicensePolicy {
+    fn permits(&self, license: &str) -> bool {
+        !self.deny.iter().any(|entry| covers(entry, license))
+            && (self.allow.is_empty() || self.allow.iter().any(|entry| covers(entry, license)))
+    }
+
+    /// Why an SPDX `expression` breaks the policy; `None` when one of its
+    /// alternatives is made of permitted licenses only.
+    pub fn violation(&self, expression: &str) -> Option<String> {
+        let alternatives = alternatives(&tokens(expression), &mut 0);
+        if alternatives
+            .iter()
+            .any(|all| all.iter().all(|license| self.permits(license)))
+        {
+            return None;
+        }
+        let refused: Vec<&str> = alternatives
+            .iter()
+            .flatten()
+            .filter(|license| !self.permits(license))
+            .map(String::as_str)
+            .collect();
+        Some(format!(
+            "{} is not permitted by the license policy",
+            refused.join(", ")
+        ))
+    }
+}
+
+impl UpgradeWorker {
+    // License of a release, normalized to SPDX when possible
+    async fn release_license(
+        &self,
+        request: &UpgradeRequest,
+        version: &ParsedVersion,
+        warnings: &mut Vec<String>,
+    ) -> Option<String> {
+        let releases = match self.registry_releases(request).await {
+            Ok(releases) => releases?,
+            Err(e) => {
+                warnings.push(format!("License check skipped: {}", e.message));
+                return None;
+            }
+        };
+        let scheme = self.version_scheme(request);
+        let license = releases
+            .iter()
+            .find(|release| scheme.parse(&release.version).ok().as_ref() == Some(version))?
+            .license
+            .clone()?;
+        Some(normalize(&license).unwrap_or(license))
+    }
+
+    /// Compares the licenses of the current and target versions and checks
+    /// the target against `license_policy`. A change of license makes the
+    /// upgrade at least medium risk; a policy violation, or a license the
+    /// policy cannot be checked against, makes it critical.
+    pub(crate) async fn check_license(
+        &self,
+        request: &UpgradeRequest,
+        target_request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        let current = self
+            .release_license(request, &versions.current, warnings)
+            .await;
+        let target = self
+            .release_license(target_request, &versions.target, warnings)
+            .await;
+        if current.is_none() && target.is_none() {
+            return;
+        }
+
+        let package = &target_request.package_name;
+        let changed = current.is_some() && target.is_some() && current != target;
+        if changed {
+            risk.risk_level = risk.risk_level.max(RiskLevel::Medium);
+            risk.explanations.push(format!(
+                "{} {} changes its license from {} to {}",
+                package,
+                versions.target,
+                current.as_deref().unwrap_or_default(),
+                target.as_deref().unwrap_or_default()
+            ));
+        }
+
+        let violation = self.config.license_policy.as_ref().and_then(|policy| {
+            match target.as_deref().and_then(normalize) {
+                Some(expression) => policy.violation(&expression),
+                None => Some(format!(
+                    "License '{}' cannot be checked against the license policy",
+                    target.as_deref().unwrap_or("unknown")
+                )),
+            }
+        });
+        if let Some(violation) = &violation {
+            risk.risk_level = RiskLevel::Critical;
+            risk.explanations
+                .push(format!("{} {}: {}", package, versions.target, violation));
+        }
+
+        risk.license = Some(LicenseCheck {
+            current,
+            target,
+            changed,
+            violation,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use crate::WorkerConfig;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_normalize_licenses() {
+        assert_eq!(normalize("MIT License").as_deref(), Some("MIT"));
+        assert_eq!(normalize("Apache 2.0").as_deref(), Some("Apache-2.0"));
+        assert_eq!(
+            normalize("MIT/Apache-2.0").as_deref(),
+            Some("MIT OR Apache-2.0")
+        );
+        assert_eq!(
+            normalize("(mit or apache-2.0) and bsd-3-clause").as_deref(),
+            Some("(MIT OR Apache-2.0) AND BSD-3-Clause")
+        );
+        assert_eq!(
+            normalize("GPL-2.0 WITH Classpath-exception-2.0").as_deref(),
+            Some("GPL-2.0-only WITH Classpath-exception-2.0")
+        );
+        assert_eq!(normalize("SEE LICENSE IN LICENSE.txt"), None);
+    }
+
+    #[test]
+    fn test_license_policy() {
+        let policy = LicensePolicy {
+            allow: Vec::new(),
+            deny: vec!["AGPL-3.0".to_string(), "SSPL-1.0".to_string()],
+        };
+        assert_eq!(policy.violation("MIT"), None);
+        assert_eq!(policy.violation("AGPL-3.0-only OR MIT"), None);
+        assert_eq!(
+            policy.violation("AGPL-3.0-or-later").as_deref(),
+            Some("AGPL-3.0-or-later is not permitted by the license policy")
+        );
+        assert!(policy
+            .violation("(MIT OR Apache-2.0) AND SSPL-1.0")
+            .is_some());
+
+        let allow = LicensePolicy {
+            allow: vec!["MIT".to_string(), "Apache-2.0".to_string()],
+            deny: Vec::new(),
+        };
+        assert_eq!(
+            allow.violation("(MIT OR GPL-3.0-only) AND Apache-2.0"),
+            None
+        );
+        assert!(allow.violation("MIT AND GPL-3.0-only").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_license_change_and_violation() {
+        let release = |version: &str, license: &str| ReleaseInfo {
+            license: Some(license.to_string()),
+            ..ReleaseInfo::new(version)
+        };
+        let registry = StaticRegistry::new().with_releases(
+            "npm",
+            "mongodb-memory",
+            vec![release("1.0.0", "MIT"), release("2.0.0", "AGPL-3.0")],
+        );
+        let config = WorkerConfig {
+            license_policy: Some(LicensePolicy {
+                allow: Vec::new(),
+                deny: vec!["AGPL-3.0".to_string()],
+            }),
+            ..WorkerConfig::default()
+        };
+        let worker = UpgradeWorker::new(Some(config)).with_registry(Arc::new(registry));
+        let request = UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "mongodb-memory".to_string(),
+            current_version: "1.0.0".to_string(),
+            target_version: "2.0.0".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"mongodb-memory": "1.0.0"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let risk = worker
+            .process_upgrade(request)
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::Critical);
+        assert_eq!(
+            risk.license,
+            Some(LicenseCheck {
+                current: Some("MIT".to_string()),
+                target: Some("AGPL-3.0-only".to_string()),
+                changed: true,
+                violation: Some("AGPL-3.0-only is not permitted by the license policy".to_string()),
+            })
+        );
+        assert!(risk.explanations.contains(
+            &"mongodb-memory 2.0.0 changes its license from MIT to AGPL-3.0-only".to_string()
+        ));
+    }
+}
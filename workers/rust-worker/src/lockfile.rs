@@ -0,0 +1,273 @@
+use crate::ecosystems::gitlink;
+use crate::verification::{run_step, StepStatus};
+use crate::{Change, ChangeType, ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker};
+use std::path::{Component, Path};
+use std::time::Duration;
+
+/// Package managers whose lockfile refresh commands may be executed.
+/// Commands are run directly, never through a shell.
+const LOCKFILE_TOOLS: &[&str] = &[
+    "bundle",
+    "cargo",
+    "composer",
+    "dart",
+    "deno",
+    "flutter",
+    "go",
+    "helm",
+    "lake",
+    "mix",
+    "npm",
+    "pip-compile",
+    "pipenv",
+    "pnpm",
+    "pod",
+    "poetry",
+    "swift",
+    "terraform",
+    "yarn",
+];
+
+/// Splits a configured command (`cargo build && cargo test`) into the
+/// argument vectors of its steps.
+pub(crate) fn command_steps(command: &str) -> Vec<Vec<&str>> {
+    command
+        .split("&&")
+        .map(|step| step.split_whitespace().collect::<Vec<_>>())
+        .filter(|argv| !argv.is_empty())
+        .collect()
+}
+
+pub(crate) fn is_lockfile_tool(program: &str) -> bool {
+    LOCKFILE_TOOLS.contains(&program)
+}
+
+/// Argument vectors of the steps regenerating the lockfile of `change`, as
+/// planned by its ecosystem. They are run as given, never re-split.
+fn refresh_steps(change: &Change) -> Vec<Vec<String>> {
+    change
+        .metadata
+        .get("argv")
+        .and_then(|steps| serde_json::from_value(steps.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Refuses refreshes without steps or with a step that is not a lockfile
+/// tool.
+fn check_tools(steps: &[Vec<String>]) -> Result<(), String> {
+    if steps.iter().all(|argv| argv.is_empty()) {
+        return Err("no refresh command was planned".to_string());
+    }
+    match steps
+        .iter()
+        .find_map(|argv| argv.first().filter(|program| !is_lockfile_tool(program)))
+    {
+        Some(program) => Err(format!("'{}' is not an allowed lockfile tool", program)),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn is_safe_path(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn internal_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Internal,
+    }
+}
+
+/// Writes the repository files with the generated edits applied.
+fn materialize(
+    root: &Path,
+    request: &UpgradeRequest,
+    changes: &[Change],
+) -> Result<(), UpgradeError> {
+    let is_deleted = |path: &str| {
+        changes.iter().any(|change| {
+            matches!(change.change_type, ChangeType::Delete) && change.file_path == path
+        })
+    };
+    let edited = changes
+        .iter()
+        .filter(|change| !change.metadata.contains_key("lockfile_refresh"))
+        .filter(|change| gitlink(change).is_none())
+        .filter(|change| !matches!(change.change_type, ChangeType::Delete))
+        .map(|change| (change.file_path.as_str(), change.content.as_str()));
+    let files = request
+        .manifests
+        .iter()
+        .map(|(path, content)| (path.as_str(), content.as_str()))
+        .filter(|(path, _)| !is_deleted(path))
+        .chain(edited);
+
+    for (path, content) in files {
+        if !is_safe_path(path) {
+            return Err(internal_error(format!(
+                "Refusing to write '{}' outside the checkout",
+                path
+            )));
+        }
+        let target = root.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| internal_error(format!("Failed to create {}: {}", path, e)))?;
+        }
+        std::fs::write(&target, content)
+            .map_err(|e| internal_error(format!("Failed to write {}: {}", path, e)))?;
+    }
+
+    // Cargo refuses manifests without a target, and sources are rarely supplied
+    for (path, content) in &request.manifests {
+        if path.rsplit('/').next() != Some("Cargo.toml") || !content.contains("[package]") {
+            continue;
+        }
+        let src = root.join(path).with_file_name("src");
+        if !src.join("lib.rs").exists() && !src.join("main.rs").exists() {
+            std::fs::create_dir_all(&src)
+                .and_then(|_| std::fs::write(src.join("lib.rs"), ""))
+                .map_err(|e| internal_error(format!("Failed to stub {}: {}", path, e)))?;
+        }
+    }
+    Ok(())
+}
+
+impl UpgradeWorker {
+    /// Runs the refresh command of every lockfile change in a temporary
+    /// checkout and replaces the placeholder with the regenerated lockfile.
+    /// Failures leave the refresh entry in place and are returned as
+    /// warnings.
+    pub(crate) async fn regenerate_lockfiles(
+        &self,
+        request: &UpgradeRequest,
+        changes: &mut [Change],
+    ) -> Result<Vec<String>, UpgradeError> {
+        let pending: Vec<usize> = (0..changes.len())
+            .filter(|&i| changes[i].metadata.contains_key("lockfile_refresh"))
+            .collect();
+        if pending.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let checkout = tempfile::tempdir()
+            .map_err(|e| internal_error(format!("Failed to create checkout: {}", e)))?;
+        materialize(checkout.path(), request, changes)?;
+
+        let mut warnings = Vec::new();
+        for i in pending {
+            let path = changes[i].file_path.clone();
+            let steps = refresh_steps(&changes[i]);
+            let command = changes[i]
+                .metadata
+                .get("command")
+                .and_then(|command| command.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let lockfile = checkout.path().join(&path);
+            let directory = lockfile.parent().unwrap_or(checkout.path());
+
+            match self.run_steps(directory, &steps).await {
+                Ok(()) => match std::fs::read_to_string(&lockfile) {
+                    Ok(content) => {
+                        let change = &mut changes[i];
+                        change.content = content;
+                        change.metadata.remove("lockfile_refresh");
+                        change
+                            .metadata
+                            .insert("regenerated".to_string(), serde_json::json!(true));
+                    }
+                    Err(e) => {
+                        warnings.push(format!("`{}` did not produce {}: {}", command, path, e))
+                    }
+                },
+                Err(reason) => warnings.push(format!(
+                    "Could not regenerate {} with `{}`: {}",
+                    path, command, reason
+                )),
+            }
+        }
+        Ok(warnings)
+    }
+
+    // Runs in the sandbox like builds do: package managers execute code of
+    // the repository and of the packages it resolves
+    async fn run_steps(&self, directory: &Path, steps: &[Vec<String>]) -> Result<(), String> {
+        check_tools(steps)?;
+        let isolation = self.isolation()?;
+        let timeout = Duration::from_secs(self.config.max_execution_time);
+        for argv in steps.iter().filter(|argv| !argv.is_empty()) {
+            let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+            let step = run_step(&isolation, &argv, directory, timeout, 1).await?;
+            match step.status {
+                StepStatus::Passed => {}
+                StepStatus::TimedOut => {
+                    return Err(format!("timed out after {}s", timeout.as_secs()))
+                }
+                _ if step.log.is_empty() => return Err("command failed".to_string()),
+                _ => return Err(step.log),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecosystems::lockfile_refresh;
+    use crate::WorkerConfig;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_command_steps() {
+        assert_eq!(
+            command_steps("go get x@v2.0.0 && go mod tidy"),
+            vec![vec!["go", "get", "x@v2.0.0"], vec!["go", "mod", "tidy"]]
+        );
+        assert!(!is_safe_path("../Cargo.lock"));
+        assert!(!is_safe_path("/etc/passwd"));
+        assert!(is_safe_path("crates/app/Cargo.lock"));
+    }
+
+    #[tokio::test]
+    async fn test_regenerate_cargo_lock() {
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            regenerate_lockfiles: true,
+            sandbox_enabled: false,
+            ..WorkerConfig::default()
+        }));
+        let request = UpgradeRequest {
+            ecosystem: "cargo".to_string(),
+            manifests: HashMap::from([
+                (
+                    "app/Cargo.toml".to_string(),
+                    "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"
+                        .to_string(),
+                ),
+                ("app/Cargo.lock".to_string(), "# stale\n".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let mut changes = vec![
+            lockfile_refresh(
+                "app/Cargo.lock",
+                &[&["cargo", "generate-lockfile", "--offline"]],
+            ),
+            lockfile_refresh("app/Cargo.lock", &[&["sh", "-c", "true"]]),
+        ];
+        let warnings = worker
+            .regenerate_lockfiles(&request, &mut changes)
+            .await
+            .unwrap();
+
+        assert!(changes[0].content.contains("name = \"app\""));
+        assert_eq!(changes[0].metadata["regenerated"], true);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("not an allowed lockfile tool"));
+        assert!(changes[1].metadata.contains_key("lockfile_refresh"));
+    }
+}
@@ -1,26 +1,139 @@
-use crate::lib::{UpgradeWorker, UpgradeRequest, WorkerConfig};
-use actix_web::{web, App, HttpServer, HttpResponse, Responder};
+use actix_web::{web, App, HttpRequest, HttpServer, HttpResponse, Responder};
+use ring::hmac;
 use serde_json::json;
-use std::collections::HashMap;
+use speccursor_rust_worker::adoption::GitHubIssueActivity;
+use speccursor_rust_worker::advisories::{
+    AdvisoryCache, AdvisoryCacheConfig, EpssClient, GhsaClient, GhsaConfig, NpmAuditClient,
+    OsvClient, SecurityUpgradeRequest,
+};
+use speccursor_rust_worker::compare::VersionComparisonRequest;
+use speccursor_rust_worker::credentials::Secret;
+use speccursor_rust_worker::group::GroupUpgradeRequest;
+use speccursor_rust_worker::image_scan::TrivyScanner;
+use speccursor_rust_worker::outcomes::{FileOutcomeStore, OutcomeReport};
+use speccursor_rust_worker::policy::Policy;
+use speccursor_rust_worker::provenance::{NpmProvenance, TrustRoot};
+use speccursor_rust_worker::registry::HttpRegistry;
+use speccursor_rust_worker::release_notes::GitHubReleaseNotes;
+use speccursor_rust_worker::sandbox::SandboxConfig;
+use speccursor_rust_worker::sbom::SbomRequest;
+use speccursor_rust_worker::semver_checks::{SemverChecks, TypeDeclarations};
+use speccursor_rust_worker::{UpgradeWorker, UpgradeRequest, WorkerConfig};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // Risk levels are adjusted and upgrades blocked by an optional policy
+    // file, read again whenever it changes
+    let policy_file = std::env::var_os("RISK_POLICY").map(std::path::PathBuf::from);
+    if let Some(path) = &policy_file {
+        Policy::load(path)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.message))?;
+    }
+    // Builds, codemods and lockfile refreshes run in containers of this
+    // image, and are refused without one
+    let sandbox = std::env::var("SANDBOX_IMAGE").ok().map(|image| SandboxConfig {
+        runtime: std::env::var("SANDBOX_RUNTIME").unwrap_or_else(|_| "docker".to_string()),
+        ..SandboxConfig::new(&image)
+    });
     let config = WorkerConfig {
         max_execution_time: 300,
         memory_limit: 1024 * 1024 * 1024, // 1GB
         sandbox_enabled: true,
+        sandbox,
         log_level: "info".to_string(),
+        policy_file,
+        ..WorkerConfig::default()
+    };
+
+    // OSV and npm are queried on every job; keep their advisories on disk
+    let cache_config = AdvisoryCacheConfig {
+        directory: std::env::var_os("ADVISORY_CACHE_DIR").map(Into::into),
+        ..AdvisoryCacheConfig::default()
     };
+    let osv = Arc::new(AdvisoryCache::new(Arc::new(OsvClient::new()), cache_config.clone()));
+    let npm_audit = Arc::new(AdvisoryCache::new(Arc::new(NpmAuditClient::new()), cache_config));
+    osv.spawn_refresh();
+    npm_audit.spawn_refresh();
+
+    // Provenance attestations are only verified against Sigstore's trust
+    // root (`trusted_root.json`, as distributed through its TUF repository)
+    let mut provenance = NpmProvenance::new();
+    if let Some(path) = std::env::var_os("SIGSTORE_TRUSTED_ROOT") {
+        let document = std::fs::read(&path)?;
+        let trust_root = serde_json::from_slice(&document)
+            .map_err(|e| e.to_string())
+            .and_then(|document| TrustRoot::from_json(&document))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        provenance = provenance.with_trust_root(trust_root);
+    }
 
-    let worker = UpgradeWorker::new(Some(config));
+    let mut worker = UpgradeWorker::new(Some(config))
+        .with_registry(Arc::new(HttpRegistry::new()))
+        .with_advisory_database(osv)
+        .with_advisory_database(npm_audit)
+        .with_provenance_verifier(Arc::new(provenance))
+        .with_exploit_scores(Arc::new(EpssClient::new()));
+    // Release notes are read unauthenticated, at a lower rate limit, without
+    // a GitHub token
+    let github_token = std::env::var_os("GITHUB_TOKEN")
+        .is_some()
+        .then(|| Secret::Env("GITHUB_TOKEN".to_string()));
+    let github = Arc::new(GitHubReleaseNotes::new(github_token, Duration::from_secs(30)));
+    worker = worker
+        .with_release_notes(github.clone())
+        .with_issue_activity(Arc::new(GitHubIssueActivity::new(github)));
+
+    // The GitHub Advisory Database needs a token
+    if std::env::var_os("GITHUB_TOKEN").is_some() {
+        let ghsa = GhsaConfig {
+            token: Secret::Env("GITHUB_TOKEN".to_string()),
+            api_url: None,
+            cache_ttl: Duration::from_secs(3600),
+        };
+        worker = worker.with_advisory_database(Arc::new(GhsaClient::new(
+            ghsa,
+            Duration::from_secs(30),
+        )));
+    }
+
+    // Base images are scanned against a shared Trivy server
+    if let Ok(server) = std::env::var("TRIVY_SERVER_URL") {
+        worker = worker.with_image_scanner(Arc::new(
+            TrivyScanner::new(Duration::from_secs(300)).with_server(&server),
+        ));
+    }
+
+    // Diffing crate APIs needs cargo-semver-checks installed
+    if std::env::var_os("SEMVER_CHECKS").is_some() {
+        worker = worker.with_api_diff(Arc::new(SemverChecks::new(Duration::from_secs(600))));
+    }
+    worker = worker.with_api_diff(Arc::new(TypeDeclarations::new(Duration::from_secs(120))));
+
+    // Reported outcomes feed the risk of later upgrades of the same package
+    let outcomes_path =
+        std::env::var_os("OUTCOMES_PATH").unwrap_or_else(|| "outcomes.jsonl".into());
+    let outcomes = FileOutcomeStore::open(outcomes_path.into())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.message))?;
+    worker = worker.with_outcome_store(Arc::new(outcomes));
+
+    let admin_token = AdminToken::from_env();
 
     println!("🚀 SpecCursor Rust Worker starting on port 8080...");
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(worker.clone()))
+            .app_data(web::Data::new(admin_token.clone()))
             .route("/health", web::get().to(health_check))
             .route("/upgrade", web::post().to(process_upgrade))
+            .route("/upgrade/security", web::post().to(security_upgrade))
+            .route("/upgrade/group", web::post().to(group_upgrade))
+            .route("/versions/compare", web::post().to(compare_versions))
+            .route("/sbom", web::post().to(generate_sbom))
+            .route("/outcomes", web::post().to(report_outcome))
+            .route("/admin/advisories/refresh", web::post().to(refresh_advisories))
             .route("/metrics", web::get().to(metrics))
     })
     .bind("0.0.0.0:8080")?
@@ -28,6 +141,43 @@ async fn main() -> std::io::Result<()> {
     .await
 }
 
+/// Token admin endpoints and outcome reports require, from `ADMIN_TOKEN`;
+/// while it is unset they refuse every request.
+#[derive(Clone)]
+struct AdminToken(Option<String>);
+
+impl AdminToken {
+    fn from_env() -> Self {
+        Self(std::env::var("ADMIN_TOKEN").ok().filter(|token| !token.is_empty()))
+    }
+
+    /// The response refusing `request`, unless it bears the token; tokens
+    /// are compared in constant time.
+    fn refusal(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        let Some(token) = &self.0 else {
+            return Some(HttpResponse::ServiceUnavailable().json(json!({
+                "error": "Admin endpoints are disabled until ADMIN_TOKEN is set"
+            })));
+        };
+        let presented = request
+            .headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // Verifying a MAC compares without exiting at the first difference
+        let key = hmac::Key::new(hmac::HMAC_SHA256, token.as_bytes());
+        let expected = hmac::sign(&key, token.as_bytes());
+        hmac::verify(&key, presented.as_bytes(), expected.as_ref())
+            .err()
+            .map(|_| {
+                HttpResponse::Unauthorized().json(json!({
+                    "error": "Missing or invalid admin token"
+                }))
+            })
+    }
+}
+
 async fn health_check() -> impl Responder {
     HttpResponse::Ok().json(json!({
         "status": "healthy",
@@ -49,6 +199,89 @@ async fn process_upgrade(
     }
 }
 
+async fn security_upgrade(
+    worker: web::Data<UpgradeWorker>,
+    request: web::Json<SecurityUpgradeRequest>,
+) -> impl Responder {
+    match worker.minimal_security_upgrade(&request).await {
+        Ok(upgrade) => HttpResponse::Ok().json(upgrade),
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_type": format!("{:?}", e.error_type)
+        }))
+    }
+}
+
+async fn group_upgrade(
+    worker: web::Data<UpgradeWorker>,
+    request: web::Json<GroupUpgradeRequest>,
+) -> impl Responder {
+    match worker.process_group(request.into_inner()).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_type": format!("{:?}", e.error_type)
+        }))
+    }
+}
+
+async fn compare_versions(
+    worker: web::Data<UpgradeWorker>,
+    request: web::Json<VersionComparisonRequest>,
+) -> impl Responder {
+    match worker.compare_versions(&request).await {
+        Ok(comparison) => HttpResponse::Ok().json(comparison),
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_type": format!("{:?}", e.error_type)
+        }))
+    }
+}
+
+async fn generate_sbom(
+    worker: web::Data<UpgradeWorker>,
+    request: web::Json<SbomRequest>,
+) -> impl Responder {
+    match worker.generate_sbom(&request).await {
+        Ok(sbom) => HttpResponse::Ok().json(sbom),
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_type": format!("{:?}", e.error_type)
+        }))
+    }
+}
+
+async fn report_outcome(
+    worker: web::Data<UpgradeWorker>,
+    admin_token: web::Data<AdminToken>,
+    request: HttpRequest,
+    report: web::Json<OutcomeReport>,
+) -> impl Responder {
+    // Outcomes raise the risk of later upgrades, so only trusted callers
+    // may report them
+    if let Some(refusal) = admin_token.refusal(&request) {
+        return refusal;
+    }
+    match worker.record_outcome(&report) {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(e) => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_type": format!("{:?}", e.error_type)
+        }))
+    }
+}
+
+async fn refresh_advisories(
+    worker: web::Data<UpgradeWorker>,
+    admin_token: web::Data<AdminToken>,
+    request: HttpRequest,
+) -> impl Responder {
+    if let Some(refusal) = admin_token.refusal(&request) {
+        return refusal;
+    }
+    HttpResponse::Ok().json(worker.refresh_advisories().await)
+}
+
 async fn metrics() -> impl Responder {
     HttpResponse::Ok().json(json!({
         "worker": {
@@ -64,6 +297,8 @@ async fn metrics() -> impl Responder {
 mod tests {
     use super::*;
     use actix_web::test;
+    use speccursor_rust_worker::outcomes::MemoryOutcomeStore;
+    use std::collections::HashMap;
 
     #[actix_web::test]
     async fn test_health_check() {
@@ -85,6 +320,7 @@ mod tests {
             memory_limit: 1024 * 1024 * 1024,
             sandbox_enabled: true,
             log_level: "info".to_string(),
+            ..WorkerConfig::default()
         };
 
         let worker = UpgradeWorker::new(Some(config));
@@ -101,6 +337,11 @@ mod tests {
             current_version: "1.0.0".to_string(),
             target_version: "2.0.0".to_string(),
             metadata: HashMap::new(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "1.0.0"}}"#.to_string(),
+            )]),
+            ..Default::default()
         };
 
         let req = test::TestRequest::post()
@@ -110,6 +351,188 @@ mod tests {
 
         let resp = test::call_service(&app, req).await;
         assert!(resp.status().is_success());
+
+        let req = test::TestRequest::post()
+            .uri("/upgrade")
+            .set_json(UpgradeRequest { manifests: HashMap::new(), ..request })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error());
+    }
+
+    #[actix_web::test]
+    async fn test_group_upgrade() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(UpgradeWorker::new(None)))
+                .route("/upgrade/group", web::post().to(group_upgrade))
+        ).await;
+
+        let upgrade = |package: &str, target: &str| json!({
+            "repository": "test/repo",
+            "ecosystem": "npm",
+            "package_name": package,
+            "current_version": "1.0.0",
+            "target_version": target,
+            "metadata": {},
+            "manifests": {"package.json": format!(r#"{{"dependencies": {{"{}": "1.0.0"}}}}"#, package)}
+        });
+        let req = test::TestRequest::post()
+            .uri("/upgrade/group")
+            .set_json(json!({"upgrades": [upgrade("lodash", "1.0.1"), upgrade("express", "2.0.0")]}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["upgrades"].as_array().unwrap().len(), 2);
+        assert_eq!(body["risk_assessment"]["risk_level"], "High");
+        assert_eq!(body["risk_assessment"]["explanations"][0], "High risk of express");
+
+        let req = test::TestRequest::post()
+            .uri("/upgrade/group")
+            .set_json(json!({"upgrades": []}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error());
+    }
+
+    #[actix_web::test]
+    async fn test_compare_versions() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(UpgradeWorker::new(None)))
+                .route("/versions/compare", web::post().to(compare_versions))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/versions/compare")
+            .set_json(json!({"ecosystem": "cargo", "from": "1.2.3", "to": "1.3.0"}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["ordering"], "less");
+        assert_eq!(body["jump"], "Minor");
+
+        let req = test::TestRequest::post()
+            .uri("/versions/compare")
+            .set_json(json!({"ecosystem": "cargo", "from": "1.2", "to": "1.3.0"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error());
+    }
+
+    #[actix_web::test]
+    async fn test_generate_sbom() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(UpgradeWorker::new(None)))
+                .route("/sbom", web::post().to(generate_sbom))
+        ).await;
+
+        let lockfile = json!({"lockfileVersion": 3, "packages": {
+            "node_modules/lodash": {"version": "4.17.20"}
+        }});
+        let req = test::TestRequest::post()
+            .uri("/sbom")
+            .set_json(json!({
+                "repository": "acme/web",
+                "ecosystem": "npm",
+                "package_name": "lodash",
+                "current_version": "4.17.20",
+                "target_version": "4.17.21",
+                "metadata": {},
+                "manifests": {
+                    "package.json": "{\"dependencies\": {\"lodash\": \"4.17.20\"}}",
+                    "package-lock.json": lockfile.to_string()
+                },
+                "format": "cyclonedx"
+            }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["format"], "cyclonedx");
+        assert_eq!(body["before"]["components"][0]["purl"], "pkg:npm/lodash@4.17.20");
+    }
+
+    #[actix_web::test]
+    async fn test_report_outcome() {
+        let worker = UpgradeWorker::new(None)
+            .with_outcome_store(Arc::new(MemoryOutcomeStore::new()));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(worker))
+                .app_data(web::Data::new(AdminToken(Some("s3cret".to_string()))))
+                .route("/outcomes", web::post().to(report_outcome))
+        ).await;
+        let report = |package: &str| json!({
+            "ecosystem": "npm",
+            "package_name": package,
+            "to_version": "1.3.0",
+            "outcome": "broke_production"
+        });
+
+        let req = test::TestRequest::post()
+            .uri("/outcomes")
+            .insert_header(("Authorization", "Bearer s3cret"))
+            .set_json(report("left-pad"))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["broke_production"], 1);
+        assert_eq!(body["merged"], 0);
+
+        let req = test::TestRequest::post()
+            .uri("/outcomes")
+            .set_json(report("left-pad"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+        let req = test::TestRequest::post()
+            .uri("/outcomes")
+            .insert_header(("Authorization", "Bearer s3cret"))
+            .set_json(report(""))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_refresh_advisories() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(UpgradeWorker::new(None)))
+                .app_data(web::Data::new(AdminToken(Some("s3cret".to_string()))))
+                .route("/admin/advisories/refresh", web::post().to(refresh_advisories))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/admin/advisories/refresh")
+            .insert_header(("Authorization", "Bearer s3cret"))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["refreshed"], 0);
+
+        for authorization in [None, Some("Bearer s3cre"), Some("s3cret")] {
+            let mut req = test::TestRequest::post().uri("/admin/advisories/refresh");
+            if let Some(authorization) = authorization {
+                req = req.insert_header(("Authorization", authorization));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), 401);
+        }
+
+        // Without a configured token the endpoint stays closed
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(UpgradeWorker::new(None)))
+                .app_data(web::Data::new(AdminToken(None)))
+                .route("/admin/advisories/refresh", web::post().to(refresh_advisories))
+        ).await;
+        let req = test::TestRequest::post()
+            .uri("/admin/advisories/refresh")
+            .insert_header(("Authorization", "Bearer "))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 503);
     }
 
     #[actix_web::test]
@@ -0,0 +1,221 @@
+use crate::version::ResolvedVersions;
+use crate::{Change, ChangeType, UpgradeRequest};
+use serde_json::json;
+use std::collections::HashMap;
+
+mod codemods;
+
+pub use codemods::{builtin_codemods, edits, Codemod, CodemodConfig};
+
+/// Files an upgrade has to add or remove once it crosses the major release
+/// that requires them, such as ESLint 9 replacing `.eslintrc*` with a flat
+/// `eslint.config.mjs`.
+pub struct MigrationRecipe {
+    pub ecosystem: &'static str,
+    pub package: &'static str,
+    /// First major release needing the migration.
+    pub major: u64,
+    pub name: &'static str,
+    /// Builds the `Add`/`Delete` changes from the supplied repository files.
+    pub migrate: fn(&UpgradeRequest) -> Vec<Change>,
+}
+
+pub const RECIPES: &[MigrationRecipe] = &[MigrationRecipe {
+    ecosystem: "npm",
+    package: "eslint",
+    major: 9,
+    name: "eslint-flat-config",
+    migrate: eslint_flat_config,
+}];
+
+/// Changes from every recipe whose major release the upgrade crosses,
+/// tagged with the recipe name in `metadata.migration`.
+pub fn migration_changes(request: &UpgradeRequest, versions: &ResolvedVersions) -> Vec<Change> {
+    RECIPES
+        .iter()
+        .filter(|recipe| {
+            recipe.ecosystem == request.ecosystem
+                && recipe.package == request.package_name
+                && versions.current.major() < recipe.major
+                && versions.target.major() >= recipe.major
+        })
+        .flat_map(|recipe| {
+            let mut changes = (recipe.migrate)(request);
+            for change in &mut changes {
+                change
+                    .metadata
+                    .insert("migration".to_string(), json!(recipe.name));
+            }
+            changes
+        })
+        .collect()
+}
+
+fn added(file_path: String, content: String) -> Change {
+    Change {
+        file_path,
+        change_type: ChangeType::Add,
+        content,
+        metadata: HashMap::new(),
+    }
+}
+
+fn deleted(file_path: &str) -> Change {
+    Change {
+        file_path: file_path.to_string(),
+        change_type: ChangeType::Delete,
+        content: String::new(),
+        metadata: HashMap::new(),
+    }
+}
+
+const ESLINTRC_NAMES: &[&str] = &[
+    ".eslintrc",
+    ".eslintrc.json",
+    ".eslintrc.yaml",
+    ".eslintrc.yml",
+];
+const FLAT_CONFIG_NAMES: &[&str] = &[
+    "eslint.config.js",
+    "eslint.config.mjs",
+    "eslint.config.cjs",
+    "eslint.config.ts",
+];
+
+/// Replaces each JSON or YAML `.eslintrc*` (and its `.eslintignore`) with an
+/// `eslint.config.mjs` that loads the same settings through `FlatCompat`.
+/// JavaScript legacy configs cannot be carried over and are left alone.
+fn eslint_flat_config(request: &UpgradeRequest) -> Vec<Change> {
+    let mut legacy: Vec<(&str, &str, serde_json::Value)> = request
+        .manifests
+        .iter()
+        .filter_map(|(path, content)| {
+            let (directory, file_name) = match path.rsplit_once('/') {
+                Some((directory, file_name)) => (&path[..directory.len() + 1], file_name),
+                None => ("", path.as_str()),
+            };
+            if !ESLINTRC_NAMES.contains(&file_name) {
+                return None;
+            }
+            let config = match file_name {
+                ".eslintrc.yaml" | ".eslintrc.yml" => serde_yaml::from_str(content).ok()?,
+                _ => serde_json::from_str(content).ok()?,
+            };
+            Some((directory, path.as_str(), config))
+        })
+        .collect();
+    legacy.sort_by(|a, b| a.1.cmp(b.1));
+
+    let mut changes = Vec::new();
+    for (directory, path, config) in legacy {
+        let has_flat_config = FLAT_CONFIG_NAMES.iter().any(|name| {
+            request
+                .manifests
+                .contains_key(&format!("{}{}", directory, name))
+        });
+        if has_flat_config {
+            continue;
+        }
+
+        let ignore_path = format!("{}.eslintignore", directory);
+        let ignores: Vec<&str> = request
+            .manifests
+            .get(&ignore_path)
+            .map(|content| {
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut content = String::from(
+            "import { FlatCompat } from \"@eslint/eslintrc\";\n\
+             import js from \"@eslint/js\";\n\n\
+             const compat = new FlatCompat({\n  \
+             baseDirectory: import.meta.dirname,\n  \
+             recommendedConfig: js.configs.recommended,\n\
+             });\n\n\
+             export default [\n",
+        );
+        if !ignores.is_empty() {
+            content.push_str(&format!("  {{ ignores: {} }},\n", json!(ignores)));
+        }
+        let config = serde_json::to_string_pretty(&config).unwrap_or_default();
+        content.push_str(&format!(
+            "  ...compat.config({}),\n];\n",
+            config.replace('\n', "\n  ")
+        ));
+
+        let mut flat_config = added(format!("{}eslint.config.mjs", directory), content);
+        flat_config.metadata.insert(
+            "requires_packages".to_string(),
+            json!(["@eslint/eslintrc", "@eslint/js"]),
+        );
+        changes.push(flat_config);
+        changes.push(deleted(path));
+        if request.manifests.contains_key(&ignore_path) {
+            changes.push(deleted(&ignore_path));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+
+    fn upgrade(current: &str, target: &str, files: &[(&str, &str)]) -> Vec<Change> {
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "eslint".to_string(),
+            manifests: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse(current).unwrap(),
+            target: SemanticScheme.parse(target).unwrap(),
+        };
+        migration_changes(&request, &versions)
+    }
+
+    #[test]
+    fn test_eslint_flat_config_migration() {
+        let files = [
+            (
+                "web/.eslintrc.json",
+                r#"{"extends": ["eslint:recommended"], "rules": {"semi": "error"}}"#,
+            ),
+            ("web/.eslintignore", "# build output\ndist\n\ncoverage/\n"),
+            ("legacy/.eslintrc.js", "module.exports = {};"),
+        ];
+        let changes = upgrade("8.57.0", "9.0.0", &files);
+        assert_eq!(changes.len(), 3);
+
+        assert_eq!(changes[0].file_path, "web/eslint.config.mjs");
+        assert!(matches!(changes[0].change_type, ChangeType::Add));
+        assert!(changes[0]
+            .content
+            .contains(r#"  { ignores: ["dist","coverage/"] },"#));
+        assert!(changes[0].content.contains(
+            "  ...compat.config({\n    \"extends\": [\n      \"eslint:recommended\"\n    ],"
+        ));
+        assert_eq!(changes[0].metadata["migration"], "eslint-flat-config");
+
+        assert_eq!(changes[1].file_path, "web/.eslintrc.json");
+        assert!(matches!(changes[1].change_type, ChangeType::Delete));
+        assert_eq!(changes[2].file_path, "web/.eslintignore");
+
+        // Only when the upgrade crosses into 9.x
+        assert!(upgrade("9.0.0", "9.4.0", &files).is_empty());
+        assert!(upgrade("8.56.0", "8.57.0", &files).is_empty());
+        // Already migrated
+        let migrated = [files[0], ("web/eslint.config.js", "export default [];")];
+        assert!(upgrade("8.57.0", "9.0.0", &migrated).is_empty());
+    }
+}
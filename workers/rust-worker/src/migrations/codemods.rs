@@ -0,0 +1,325 @@
+use crate::lockfile::command_steps;
+use crate::repo::{self, Checkout};
+use crate::sandbox::Isolation;
+use crate::verification::{copy_tree, run_step, StepStatus};
+use crate::version::ResolvedVersions;
+use crate::{Change, ChangeType, ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// A source transform (a jscodeshift transform, a `cargo fix`-style fixer)
+/// run on the repository once an upgrade crosses the major release it
+/// migrates to.
+#[derive(Debug, Clone)]
+pub struct Codemod {
+    pub ecosystem: String,
+    pub package: String,
+    /// First major release the codemod migrates to.
+    pub major: u64,
+    pub name: String,
+    /// Run from the checkout root, or the request's `path_scope`, without a
+    /// shell; steps are separated by `&&`.
+    pub command: String,
+}
+
+impl Codemod {
+    pub fn new(ecosystem: &str, package: &str, major: u64, name: &str, command: &str) -> Self {
+        Self {
+            ecosystem: ecosystem.to_string(),
+            package: package.to_string(),
+            major,
+            name: name.to_string(),
+            command: command.to_string(),
+        }
+    }
+}
+
+/// Codemods shipped with the worker.
+pub fn builtin_codemods() -> Vec<Codemod> {
+    vec![
+        Codemod::new(
+            "npm",
+            "next",
+            13,
+            "next-new-link",
+            "npx --yes @next/codemod@latest new-link . --force",
+        ),
+        Codemod::new(
+            "npm",
+            "next",
+            15,
+            "next-async-request-apis",
+            "npx --yes @next/codemod@latest next-async-request-apis . --force",
+        ),
+    ]
+}
+
+/// Which codemods run when [`crate::WorkerConfig::codemods`] is set.
+#[derive(Debug, Clone)]
+pub struct CodemodConfig {
+    /// Codemods run next to [`builtin_codemods`].
+    pub codemods: Vec<Codemod>,
+    /// Seconds each codemod may take.
+    pub timeout: u64,
+}
+
+impl Default for CodemodConfig {
+    fn default() -> Self {
+        Self {
+            codemods: Vec::new(),
+            timeout: 600,
+        }
+    }
+}
+
+/// Changes turning the files of `before` into those of `after`, in path
+/// order.
+pub fn edits(before: &HashMap<String, String>, after: &HashMap<String, String>) -> Vec<Change> {
+    let mut paths: Vec<&String> = before.keys().chain(after.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let (change_type, content) = match (before.get(path), after.get(path)) {
+                (Some(old), Some(new)) if old != new => (ChangeType::Modify, new.clone()),
+                (None, Some(new)) => (ChangeType::Add, new.clone()),
+                (Some(_), None) => (ChangeType::Delete, String::new()),
+                _ => return None,
+            };
+            Some(Change {
+                file_path: path.clone(),
+                change_type,
+                content,
+                metadata: HashMap::new(),
+            })
+        })
+        .collect()
+}
+
+fn internal_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Internal,
+    }
+}
+
+impl UpgradeWorker {
+    /// Runs the codemods of every major release the upgrade crosses in a
+    /// copy of the checkout with `changes` applied, and adds their edits to
+    /// `changes`, tagged with the codemod in `metadata.migration` and
+    /// `metadata.codemod`. Codemods that fail are skipped with a warning.
+    pub(crate) async fn run_codemods(
+        &self,
+        checkout: Option<&Checkout>,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        changes: &mut Vec<Change>,
+        warnings: &mut Vec<String>,
+    ) -> Result<(), UpgradeError> {
+        let Some(config) = &self.config.codemods else {
+            return Ok(());
+        };
+        let mut codemods: Vec<Codemod> = builtin_codemods()
+            .into_iter()
+            .chain(config.codemods.iter().cloned())
+            .filter(|codemod| {
+                codemod.ecosystem == request.ecosystem
+                    && codemod.package == request.package_name
+                    && versions.current.major() < codemod.major
+                    && versions.target.major() >= codemod.major
+            })
+            .collect();
+        if codemods.is_empty() {
+            return Ok(());
+        }
+        codemods.sort_by_key(|codemod| codemod.major);
+        let sparse = self
+            .config
+            .checkout
+            .as_ref()
+            .is_some_and(|checkout| checkout.sparse);
+        let Some(checkout) = checkout.filter(|checkout| checkout.local || !sparse) else {
+            warnings.push(
+                "Codemods skipped: only full checkouts and local workspaces can be migrated"
+                    .to_string(),
+            );
+            return Ok(());
+        };
+        let isolation = match self.isolation() {
+            Ok(isolation) => isolation,
+            Err(reason) => {
+                warnings.push(format!("Codemods skipped: {}", reason));
+                return Ok(());
+            }
+        };
+
+        let sandbox = tempfile::Builder::new()
+            .prefix("speccursor-codemod-")
+            .tempdir()
+            .map_err(|e| internal_error(format!("Failed to create a sandbox: {}", e)))?;
+        let (source, root) = (checkout.path.clone(), sandbox.path().to_path_buf());
+        let applied = changes.clone();
+        tokio::task::spawn_blocking(move || {
+            copy_tree(&source, &root)?;
+            repo::write_changes(&root, &applied)
+        })
+        .await
+        .map_err(|e| internal_error(format!("Sandbox task failed: {}", e)))??;
+        let scope = request.path_scope.as_deref().unwrap_or_default();
+        let directory = sandbox.path().join(scope.trim_matches('/'));
+
+        let timeout = Duration::from_secs(config.timeout);
+        let mut before = repo::read_files(sandbox.path(), &[]);
+        for codemod in codemods {
+            if let Err(reason) = run_codemod(&isolation, &codemod, &directory, timeout).await {
+                warnings.push(format!("Codemod {} failed: {}", codemod.name, reason));
+                before = repo::read_files(sandbox.path(), &[]);
+                continue;
+            }
+            let after = repo::read_files(sandbox.path(), &[]);
+            let edits = edits(&before, &after);
+            if !edits.is_empty() {
+                warnings.push(format!(
+                    "Codemod {} edited {} file{}; review the automated migration",
+                    codemod.name,
+                    edits.len(),
+                    if edits.len() == 1 { "" } else { "s" }
+                ));
+            }
+            for mut edit in edits {
+                edit.metadata
+                    .insert("migration".to_string(), json!(codemod.name));
+                edit.metadata
+                    .insert("codemod".to_string(), json!(codemod.command));
+                // A file the upgrade already edits keeps one change
+                match changes
+                    .iter_mut()
+                    .find(|change| change.file_path == edit.file_path)
+                {
+                    Some(change) => {
+                        change.change_type = edit.change_type;
+                        change.content = edit.content;
+                        change.metadata.extend(edit.metadata);
+                    }
+                    None => changes.push(edit),
+                }
+            }
+            before = after;
+        }
+        Ok(())
+    }
+}
+
+async fn run_codemod(
+    isolation: &Isolation,
+    codemod: &Codemod,
+    directory: &Path,
+    timeout: Duration,
+) -> Result<(), String> {
+    for argv in command_steps(&codemod.command) {
+        let step = run_step(isolation, &argv, directory, timeout, 1).await?;
+        match step.status {
+            StepStatus::Passed => {}
+            StepStatus::TimedOut => return Err(format!("timed out after {}s", timeout.as_secs())),
+            _ if step.log.is_empty() => {
+                return Err(format!(
+                    "`{}` exited with {}",
+                    step.command,
+                    step.exit_code.unwrap_or(-1)
+                ))
+            }
+            _ => return Err(format!("`{}` failed: {}", step.command, step.log)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WorkerConfig;
+
+    #[test]
+    fn test_edits() {
+        let files = |entries: &[(&str, &str)]| -> HashMap<String, String> {
+            entries
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect()
+        };
+        let before = files(&[("a.js", "x"), ("b.js", "y"), ("c.js", "z")]);
+        let after = files(&[("a.js", "x"), ("b.js", "y2"), ("d.js", "w")]);
+
+        let changes = edits(&before, &after);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.file_path.as_str(),
+                    format!("{:?}", change.change_type),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("b.js", "Modify".to_string()),
+                ("c.js", "Delete".to_string()),
+                ("d.js", "Add".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_codemods_add_migration_edits() {
+        let root = tempfile::tempdir().unwrap();
+        crate::repo::tests::source_repository(root.path());
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            local_roots: vec![root.path().to_path_buf()],
+            codemods: Some(CodemodConfig {
+                codemods: vec![
+                    Codemod::new(
+                        "npm",
+                        "lodash",
+                        5,
+                        "lodash-es",
+                        "sed -i s/'lodash'/'lodash-es'/ apps/web/src/index.js",
+                    ),
+                    Codemod::new("npm", "lodash", 5, "broken", "false"),
+                    Codemod::new("npm", "lodash", 6, "future", "false"),
+                ],
+                ..CodemodConfig::default()
+            }),
+            sandbox_enabled: false,
+            ..WorkerConfig::default()
+        }));
+        let request = UpgradeRequest {
+            repository: "acme/app".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.20".to_string(),
+            target_version: "5.0.0".to_string(),
+            local_path: Some(root.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        let migrated = response
+            .changes
+            .iter()
+            .find(|change| change.file_path == "apps/web/src/index.js")
+            .unwrap();
+        assert_eq!(migrated.content, "import _ from 'lodash-es';");
+        assert_eq!(migrated.metadata["migration"], "lodash-es");
+        assert!(response
+            .warnings
+            .contains(&"Codemod broken failed: `false` exited with 1".to_string()));
+        assert_eq!(
+            std::fs::read_to_string(root.path().join("apps/web/src/index.js")).unwrap(),
+            "import _ from 'lodash-es';"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use crate::version::ResolvedVersions;
+use crate::{RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker};
+use std::cmp::Ordering;
+use toml_edit::DocumentMut;
+
+/// Minimum supported Rust version declared by a repository, and the file it
+/// was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositoryMsrv {
+    pub version: String,
+    pub source: String,
+}
+
+/// Reads the MSRV from `Cargo.toml` (`package.rust-version` or
+/// `workspace.package.rust-version`), falling back to `clippy.toml`'s `msrv`.
+pub fn repository_msrv(request: &UpgradeRequest) -> Option<RepositoryMsrv> {
+    let from_manifest = request
+        .manifests
+        .get("Cargo.toml")
+        .and_then(|content| content.parse::<DocumentMut>().ok())
+        .and_then(|doc| {
+            doc.get("package")
+                .and_then(|package| package.get("rust-version"))
+                .or_else(|| {
+                    doc.get("workspace")
+                        .and_then(|workspace| workspace.get("package"))
+                        .and_then(|package| package.get("rust-version"))
+                })
+                .and_then(|value| value.as_str())
+                .map(|version| RepositoryMsrv {
+                    version: version.to_string(),
+                    source: "Cargo.toml".to_string(),
+                })
+        });
+
+    from_manifest.or_else(|| {
+        ["clippy.toml", ".clippy.toml"].iter().find_map(|path| {
+            let doc = request.manifests.get(*path)?.parse::<DocumentMut>().ok()?;
+            let version = doc.get("msrv")?.as_str()?.to_string();
+            Some(RepositoryMsrv {
+                version,
+                source: path.to_string(),
+            })
+        })
+    })
+}
+
+/// Compares dotted toolchain versions (`1.70` vs `1.70.0`), treating missing
+/// components as zero. Returns `None` if either side is not numeric.
+pub fn compare_rust_versions(a: &str, b: &str) -> Option<Ordering> {
+    let parse = |v: &str| {
+        v.trim()
+            .split('.')
+            .map(|c| c.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()
+    };
+    let (a, b) = (parse(a)?, parse(b)?);
+    let len = a.len().max(b.len());
+    let component = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+
+    Some(
+        (0..len)
+            .map(|i| component(&a, i).cmp(&component(&b, i)))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal),
+    )
+}
+
+impl UpgradeWorker {
+    /// Raises risk when the target release requires a newer Rust toolchain
+    /// than the repository's declared MSRV.
+    pub(crate) async fn check_msrv(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+    ) -> Result<(), UpgradeError> {
+        let repository = match repository_msrv(request) {
+            Some(msrv) => msrv,
+            None => return Ok(()),
+        };
+
+        let releases = self.registry_releases(request).await?.unwrap_or_default();
+        let required = releases
+            .iter()
+            .find(|release| release.version == versions.target.as_str())
+            .and_then(|release| release.rust_version.clone());
+
+        if let Some(required) = required {
+            if compare_rust_versions(&required, &repository.version) == Some(Ordering::Greater) {
+                risk.risk_level = risk.risk_level.max(RiskLevel::High);
+                risk.explanations.push(format!(
+                    "{} {} requires Rust {}, but the repository declares MSRV {} in {}",
+                    request.package_name,
+                    versions.target,
+                    required,
+                    repository.version,
+                    repository.source
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use std::sync::Arc;
+
+    fn request_with(manifests: &[(&str, &str)]) -> UpgradeRequest {
+        UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "cargo".to_string(),
+            package_name: "tokio".to_string(),
+            current_version: "1.30.0".to_string(),
+            target_version: "1.40.0".to_string(),
+            manifests: manifests
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_repository_msrv_sources() {
+        let manifest = request_with(&[(
+            "Cargo.toml",
+            "[package]\nname = \"app\"\nrust-version = \"1.70\"\n",
+        )]);
+        assert_eq!(repository_msrv(&manifest).unwrap().version, "1.70");
+
+        let workspace = request_with(&[(
+            "Cargo.toml",
+            "[workspace.package]\nrust-version = \"1.74.1\"\n",
+        )]);
+        assert_eq!(repository_msrv(&workspace).unwrap().version, "1.74.1");
+
+        let clippy = request_with(&[
+            ("Cargo.toml", "[package]\nname = \"app\"\n"),
+            ("clippy.toml", "msrv = \"1.65\"\n"),
+        ]);
+        let msrv = repository_msrv(&clippy).unwrap();
+        assert_eq!(
+            (msrv.version.as_str(), msrv.source.as_str()),
+            ("1.65", "clippy.toml")
+        );
+
+        assert!(repository_msrv(&request_with(&[])).is_none());
+    }
+
+    #[test]
+    fn test_compare_rust_versions() {
+        assert_eq!(
+            compare_rust_versions("1.70", "1.70.0"),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            compare_rust_versions("1.74", "1.70"),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare_rust_versions("1.63.0", "1.70"),
+            Some(Ordering::Less)
+        );
+        assert_eq!(compare_rust_versions("stable", "1.70"), None);
+    }
+
+    #[tokio::test]
+    async fn test_msrv_bump_raises_risk() {
+        let registry = StaticRegistry::new().with_releases(
+            "cargo",
+            "tokio",
+            vec![
+                ReleaseInfo::new("1.30.0"),
+                ReleaseInfo {
+                    rust_version: Some("1.75".to_string()),
+                    ..ReleaseInfo::new("1.40.0")
+                },
+            ],
+        );
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+        let request = request_with(&[(
+            "Cargo.toml",
+            "[package]\nname = \"app\"\nrust-version = \"1.70\"\n\n[dependencies]\ntokio = \"1.30.0\"\n",
+        )]);
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert_eq!(response.risk_assessment.risk_level, RiskLevel::High);
+        assert!(response.risk_assessment.explanations[0].contains("requires Rust 1.75"));
+    }
+}
@@ -0,0 +1,384 @@
+use crate::ecosystems::ecosystem_for;
+use crate::risk::{RiskContext, RiskRule};
+use crate::version::scheme_for;
+use crate::{
+    is_valid_package_name, ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeWorker,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// What became of an upgrade once it left the worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeOutcome {
+    Merged,
+    Reverted,
+    BrokeProduction,
+}
+
+/// An outcome reported to `POST /outcomes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutcomeReport {
+    pub ecosystem: String,
+    pub package_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_version: Option<String>,
+    pub to_version: String,
+    pub outcome: UpgradeOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    #[serde(default = "Utc::now")]
+    pub reported_at: DateTime<Utc>,
+}
+
+/// Outcomes reported for one package.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutcomeHistory {
+    pub merged: u32,
+    pub reverted: u32,
+    pub broke_production: u32,
+}
+
+impl OutcomeHistory {
+    fn record(&mut self, outcome: UpgradeOutcome) {
+        match outcome {
+            UpgradeOutcome::Merged => self.merged += 1,
+            UpgradeOutcome::Reverted => self.reverted += 1,
+            UpgradeOutcome::BrokeProduction => self.broke_production += 1,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.merged + self.reverted + self.broke_production
+    }
+
+    /// Risk level the history alone warrants: high once an upgrade of the
+    /// package broke production or half of them were reverted, medium
+    /// after any revert.
+    pub fn prior(&self) -> Option<RiskLevel> {
+        if self.broke_production > 0 || (self.reverted > 0 && 2 * self.reverted >= self.total()) {
+            Some(RiskLevel::High)
+        } else if self.reverted > 0 {
+            Some(RiskLevel::Medium)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where reported outcomes are kept.
+pub trait OutcomeStore: Send + Sync {
+    fn record(&self, report: &OutcomeReport) -> Result<(), UpgradeError>;
+
+    fn history(&self, ecosystem: &str, package: &str) -> OutcomeHistory;
+}
+
+type Histories = HashMap<(String, String), OutcomeHistory>;
+
+fn tally(histories: &mut Histories, report: &OutcomeReport) {
+    histories
+        .entry((report.ecosystem.clone(), report.package_name.clone()))
+        .or_default()
+        .record(report.outcome);
+}
+
+/// Outcomes appended to a JSON lines file, and tallied in memory.
+pub struct FileOutcomeStore {
+    path: PathBuf,
+    histories: Mutex<Histories>,
+}
+
+impl FileOutcomeStore {
+    /// Reads the outcomes already in `path`, skipping lines that do not
+    /// parse; the file is created on the first report.
+    pub fn open(path: PathBuf) -> Result<Self, UpgradeError> {
+        let mut histories = Histories::new();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                for report in content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<OutcomeReport>(line).ok())
+                {
+                    tally(&mut histories, &report);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(UpgradeError {
+                    message: format!("Failed to read outcomes from {}: {}", path.display(), e),
+                    error_type: ErrorType::Internal,
+                })
+            }
+        }
+        Ok(Self {
+            path,
+            histories: Mutex::new(histories),
+        })
+    }
+}
+
+impl OutcomeStore for FileOutcomeStore {
+    fn record(&self, report: &OutcomeReport) -> Result<(), UpgradeError> {
+        let mut histories = self.histories.lock().unwrap_or_else(|e| e.into_inner());
+        let line = serde_json::to_string(report).unwrap_or_default();
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| UpgradeError {
+                message: format!("Failed to record outcome in {}: {}", self.path.display(), e),
+                error_type: ErrorType::Internal,
+            })?;
+        tally(&mut histories, report);
+        Ok(())
+    }
+
+    fn history(&self, ecosystem: &str, package: &str) -> OutcomeHistory {
+        let histories = self.histories.lock().unwrap_or_else(|e| e.into_inner());
+        histories
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Outcomes kept in memory only, for tests.
+#[derive(Default)]
+pub struct MemoryOutcomeStore {
+    histories: Mutex<Histories>,
+}
+
+impl MemoryOutcomeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OutcomeStore for MemoryOutcomeStore {
+    fn record(&self, report: &OutcomeReport) -> Result<(), UpgradeError> {
+        let mut histories = self.histories.lock().unwrap_or_else(|e| e.into_inner());
+        tally(&mut histories, report);
+        Ok(())
+    }
+
+    fn history(&self, ecosystem: &str, package: &str) -> OutcomeHistory {
+        let histories = self.histories.lock().unwrap_or_else(|e| e.into_inner());
+        histories
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Raises packages whose earlier upgrades were reverted or broke
+/// production to the risk their history warrants.
+pub struct OutcomeRule {
+    store: Arc<dyn OutcomeStore>,
+}
+
+impl OutcomeRule {
+    pub fn new(store: Arc<dyn OutcomeStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl RiskRule for OutcomeRule {
+    fn name(&self) -> &str {
+        "outcome_history"
+    }
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        let request = context.request;
+        let history = self
+            .store
+            .history(&request.ecosystem, &request.package_name);
+        let Some(prior) = history.prior() else {
+            return;
+        };
+        assessment.risk_level = assessment.risk_level.max(prior);
+        let plural = |count: u32| if count == 1 { "" } else { "s" };
+        if history.broke_production > 0 {
+            assessment.explanations.push(format!(
+                "{} earlier upgrade{} of {} broke production",
+                history.broke_production,
+                plural(history.broke_production),
+                request.package_name
+            ));
+        }
+        if history.reverted > 0 {
+            assessment.explanations.push(format!(
+                "{} of {} earlier upgrade{} of {} {} reverted",
+                history.reverted,
+                history.total(),
+                plural(history.total()),
+                request.package_name,
+                if history.reverted == 1 { "was" } else { "were" }
+            ));
+        }
+    }
+}
+
+impl UpgradeWorker {
+    /// Records the outcome of an upgrade, returning the package's history
+    /// with it.
+    pub fn record_outcome(&self, report: &OutcomeReport) -> Result<OutcomeHistory, UpgradeError> {
+        let Some(store) = &self.outcomes else {
+            return Err(UpgradeError {
+                message: "Outcome reporting is not enabled".to_string(),
+                error_type: ErrorType::Validation,
+            });
+        };
+        validate_report(report)?;
+        store.record(report)?;
+        Ok(store.history(&report.ecosystem, &report.package_name))
+    }
+}
+
+// Reports feed the risk of later upgrades, so only outcomes of packages an
+// upgrade could have been made for are kept.
+fn validate_report(report: &OutcomeReport) -> Result<(), UpgradeError> {
+    let invalid = |message: String| UpgradeError {
+        message,
+        error_type: ErrorType::Validation,
+    };
+    if ecosystem_for(&report.ecosystem).is_none() {
+        return Err(invalid(format!("Unknown ecosystem '{}'", report.ecosystem)));
+    }
+    let package = &report.package_name;
+    if !is_valid_package_name(package) {
+        return Err(invalid(format!("Invalid package name '{}'", package)));
+    }
+    let scheme = scheme_for(&report.ecosystem);
+    for version in report.from_version.iter().chain([&report.to_version]) {
+        scheme
+            .parse(version)
+            .map_err(|e| invalid(format!("Invalid version '{}': {}", version, e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UpgradeRequest;
+
+    fn report(package: &str, outcome: UpgradeOutcome) -> OutcomeReport {
+        OutcomeReport {
+            ecosystem: "npm".to_string(),
+            package_name: package.to_string(),
+            from_version: Some("1.2.0".to_string()),
+            to_version: "1.3.0".to_string(),
+            outcome,
+            repository: None,
+            reported_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_file_store_persists_outcomes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outcomes.jsonl");
+        let store = FileOutcomeStore::open(path.clone()).unwrap();
+        store
+            .record(&report("left-pad", UpgradeOutcome::Merged))
+            .unwrap();
+        store
+            .record(&report("left-pad", UpgradeOutcome::Reverted))
+            .unwrap();
+
+        let reopened = FileOutcomeStore::open(path).unwrap();
+        let history = reopened.history("npm", "left-pad");
+        assert_eq!((history.merged, history.reverted), (1, 1));
+        assert_eq!(history.prior(), Some(RiskLevel::High));
+        assert_eq!(reopened.history("npm", "lodash"), OutcomeHistory::default());
+    }
+
+    #[tokio::test]
+    async fn test_outcomes_raise_risk() {
+        let worker =
+            UpgradeWorker::new(None).with_outcome_store(Arc::new(MemoryOutcomeStore::new()));
+        let request = |package: &str| UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: package.to_string(),
+            current_version: "1.2.0".to_string(),
+            target_version: "1.2.1".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                format!(r#"{{"dependencies": {{"{}": "1.2.0"}}}}"#, package),
+            )]),
+            ..Default::default()
+        };
+        for outcome in [
+            UpgradeOutcome::Merged,
+            UpgradeOutcome::Merged,
+            UpgradeOutcome::Reverted,
+        ] {
+            worker.record_outcome(&report("left-pad", outcome)).unwrap();
+        }
+
+        let risk = worker
+            .process_upgrade(request("left-pad"))
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::Medium);
+        assert!(risk
+            .explanations
+            .contains(&"1 of 3 earlier upgrades of left-pad was reverted".to_string()));
+
+        let history = worker
+            .record_outcome(&report("left-pad", UpgradeOutcome::BrokeProduction))
+            .unwrap();
+        assert_eq!(history.total(), 4);
+        let risk = worker
+            .process_upgrade(request("left-pad"))
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::High);
+
+        let untouched = worker.process_upgrade(request("lodash")).await.unwrap();
+        assert_eq!(untouched.risk_assessment.risk_level, RiskLevel::Low);
+        assert!(UpgradeWorker::new(None)
+            .record_outcome(&report("lodash", UpgradeOutcome::Merged))
+            .is_err());
+    }
+
+    #[test]
+    fn test_reports_are_validated() {
+        let worker =
+            UpgradeWorker::new(None).with_outcome_store(Arc::new(MemoryOutcomeStore::new()));
+        let rejected = |report: OutcomeReport| {
+            let error = worker.record_outcome(&report).unwrap_err();
+            assert!(matches!(error.error_type, ErrorType::Validation));
+            error.message
+        };
+
+        let unknown = OutcomeReport {
+            ecosystem: "left-pad-registry".to_string(),
+            ..report("left-pad", UpgradeOutcome::Merged)
+        };
+        assert_eq!(rejected(unknown), "Unknown ecosystem 'left-pad-registry'");
+        assert!(rejected(report("", UpgradeOutcome::Merged)).starts_with("Invalid package name"));
+        assert!(rejected(report("left pad", UpgradeOutcome::Merged))
+            .starts_with("Invalid package name"));
+        let version = OutcomeReport {
+            to_version: "not a version".to_string(),
+            ..report("left-pad", UpgradeOutcome::Merged)
+        };
+        assert!(rejected(version).starts_with("Invalid version"));
+        assert_eq!(
+            worker
+                .record_outcome(&report("left-pad", UpgradeOutcome::Merged))
+                .unwrap()
+                .total(),
+            1
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use crate::commit::{commit_message, CommitConfig};
+use crate::diff::unified_diff;
+use crate::version::ResolvedVersions;
+use crate::{
+    Change, ChangeType, ErrorType, RiskAssessment, UpgradeError, UpgradeRequest, UpgradeWorker,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// `git format-patch --zero-commit` header line
+const FROM_LINE: &str = "From 0000000000000000000000000000000000000000 Mon Sep 17 00:00:00 2001";
+
+// Widest diffstat graph, as in `git format-patch`
+const STAT_WIDTH: usize = 50;
+
+/// One mail of a change set rendered by `git format-patch`, ready for
+/// `git am`. Concatenated in order, the patches form an mbox.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Patch {
+    /// `0001-Bump-lodash-from-4.17.20-to-4.17.21.patch`
+    pub file_name: String,
+    pub subject: String,
+    pub content: String,
+    /// Where the patch was written, when a patch directory is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+}
+
+/// A logical step of an upgrade, applied as one patch.
+struct Step<'a> {
+    subject: String,
+    body: String,
+    changes: Vec<&'a Change>,
+}
+
+/// Splits `changes` into the manifest edits, each migration recipe's
+/// config changes and the regenerated lockfiles. Lockfiles still waiting
+/// for their refresh command have no content to patch; the manifest patch
+/// lists the commands instead.
+fn steps<'a>(
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+    message: &str,
+    changes: &'a [Change],
+) -> Vec<Step<'a>> {
+    let (subject, body) = message.split_once('\n').unwrap_or((message, ""));
+    let mut manifest = Step {
+        subject: subject.to_string(),
+        body: body.trim().to_string(),
+        changes: Vec::new(),
+    };
+    let mut migrations: Vec<Step> = Vec::new();
+    let mut lockfiles = Step {
+        subject: format!(
+            "Regenerate lockfiles for {} {}",
+            request.package_name, versions.target
+        ),
+        body: String::new(),
+        changes: Vec::new(),
+    };
+
+    let mut pending = Vec::new();
+    for change in changes {
+        if change.metadata.contains_key("lockfile_refresh") {
+            let command = change.metadata.get("command").and_then(|c| c.as_str());
+            pending.push(format!(
+                "    {}: {}",
+                change.file_path,
+                command.unwrap_or("refresh with the package manager")
+            ));
+        } else if change.metadata.contains_key("regenerated") {
+            lockfiles.changes.push(change);
+        } else if let Some(recipe) = change.metadata.get("migration").and_then(|m| m.as_str()) {
+            let subject = format!(
+                "Apply the {} migration for {} {}",
+                recipe, request.package_name, versions.target
+            );
+            match migrations.iter_mut().find(|step| step.subject == subject) {
+                Some(step) => step.changes.push(change),
+                None => migrations.push(Step {
+                    subject,
+                    body: String::new(),
+                    changes: vec![change],
+                }),
+            }
+        } else {
+            manifest.changes.push(change);
+        }
+    }
+    if !pending.is_empty() {
+        if !manifest.body.is_empty() {
+            manifest.body.push_str("\n\n");
+        }
+        manifest
+            .body
+            .push_str("Lockfiles to refresh after applying:\n\n");
+        manifest.body.push_str(&pending.join("\n"));
+    }
+
+    std::iter::once(manifest)
+        .chain(migrations)
+        .chain(std::iter::once(lockfiles))
+        .filter(|step| !step.changes.is_empty())
+        .collect()
+}
+
+/// `diff --git` section for one change, empty when it changes nothing.
+fn file_diff(request: &UpgradeRequest, change: &Change) -> String {
+    let path = &change.file_path;
+    let original = match change.change_type {
+        ChangeType::Add => None,
+        _ => request.manifests.get(path).map(String::as_str),
+    };
+    let updated = match change.change_type {
+        ChangeType::Delete => None,
+        _ => Some(change.content.as_str()),
+    };
+    if original == updated {
+        return String::new();
+    }
+    let mode = match (original, updated) {
+        (None, _) => "new file mode 100644\n",
+        (_, None) => "deleted file mode 100644\n",
+        _ => "",
+    };
+    format!(
+        "diff --git a/{0} b/{0}\n{1}{2}",
+        path,
+        mode,
+        unified_diff(path, original, updated)
+    )
+}
+
+/// `git diff --stat` summary of the `diff --git` sections in `diffs`.
+fn diffstat(diffs: &[(&str, &str)]) -> String {
+    let counts: Vec<(&str, usize, usize)> = diffs
+        .iter()
+        .map(|(path, diff)| {
+            let lines = || {
+                diff.lines()
+                    .filter(|line| !line.starts_with("+++") && !line.starts_with("---"))
+            };
+            let insertions = lines().filter(|line| line.starts_with('+')).count();
+            let deletions = lines().filter(|line| line.starts_with('-')).count();
+            (*path, insertions, deletions)
+        })
+        .collect();
+    let path_width = counts
+        .iter()
+        .map(|(path, ..)| path.len())
+        .max()
+        .unwrap_or(0);
+    let widest = counts.iter().map(|(_, i, d)| i + d).max().unwrap_or(0);
+    let count_width = widest.to_string().len();
+
+    let mut stat = String::new();
+    for (path, insertions, deletions) in &counts {
+        let (plus, minus) = if widest > STAT_WIDTH {
+            let scale = |n: usize| (n * STAT_WIDTH).div_ceil(widest);
+            (scale(*insertions), scale(*deletions))
+        } else {
+            (*insertions, *deletions)
+        };
+        stat.push_str(&format!(
+            " {:<path_width$} | {:>count_width$} {}{}\n",
+            path,
+            insertions + deletions,
+            "+".repeat(plus),
+            "-".repeat(minus),
+        ));
+    }
+
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let insertions: usize = counts.iter().map(|(_, i, _)| i).sum();
+    let deletions: usize = counts.iter().map(|(_, _, d)| d).sum();
+    let mut summary = format!(" {} changed", plural(counts.len(), "file"));
+    if insertions > 0 {
+        summary.push_str(&format!(", {}(+)", plural(insertions, "insertion")));
+    }
+    if deletions > 0 {
+        summary.push_str(&format!(", {}(-)", plural(deletions, "deletion")));
+    }
+    stat.push_str(&summary);
+    stat.push('\n');
+    stat
+}
+
+// `git format-patch` file name: numbered, with the subject's punctuation
+// turned into dashes
+fn file_name(number: usize, subject: &str) -> String {
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_matches(['-', '.']).chars().take(52).collect();
+    format!("{:04}-{}.patch", number, slug.trim_end_matches(['-', '.']))
+}
+
+/// Renders `changes` as `git format-patch` mails, one per logical step:
+/// the manifest edits (with `message` as their commit message), each
+/// migration and the regenerated lockfiles. Diffs are taken against the
+/// files supplied with the request.
+pub fn format_patches(
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+    message: &str,
+    author: (&str, &str),
+    date: DateTime<Utc>,
+    changes: &[Change],
+) -> Vec<Patch> {
+    let steps: Vec<(Step, Vec<(&str, String)>)> = steps(request, versions, message, changes)
+        .into_iter()
+        .map(|step| {
+            let diffs = step
+                .changes
+                .iter()
+                .map(|change| (change.file_path.as_str(), file_diff(request, change)))
+                .filter(|(_, diff)| !diff.is_empty())
+                .collect();
+            (step, diffs)
+        })
+        .filter(|(_, diffs): &(Step, Vec<(&str, String)>)| !diffs.is_empty())
+        .collect();
+
+    let total = steps.len();
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, (step, diffs))| {
+            let prefix = if total == 1 {
+                "[PATCH]".to_string()
+            } else {
+                format!("[PATCH {}/{}]", i + 1, total)
+            };
+            let mut content = format!(
+                "{}\nFrom: {} <{}>\nDate: {}\nSubject: {} {}\n\n",
+                FROM_LINE,
+                author.0,
+                author.1,
+                date.to_rfc2822(),
+                prefix,
+                step.subject
+            );
+            if !step.body.is_empty() {
+                content.push_str(&step.body);
+                content.push('\n');
+            }
+            let stats: Vec<(&str, &str)> = diffs
+                .iter()
+                .map(|(path, diff)| (*path, diff.as_str()))
+                .collect();
+            content.push_str("---\n");
+            content.push_str(&diffstat(&stats));
+            content.push('\n');
+            for (_, diff) in diffs {
+                content.push_str(diff);
+            }
+            content.push_str(&format!(
+                "-- \nspeccursor-rust-worker {}\n\n",
+                env!("CARGO_PKG_VERSION")
+            ));
+            Patch {
+                file_name: file_name(i + 1, &step.subject),
+                subject: step.subject.clone(),
+                content,
+                artifact: None,
+            }
+        })
+        .collect()
+}
+
+/// Writes `patches` to `directory/changeset_id/`, recording where each went.
+pub fn write_patches(
+    directory: &Path,
+    changeset_id: &str,
+    patches: &mut [Patch],
+) -> Result<(), UpgradeError> {
+    let directory = directory.join(changeset_id);
+    let io_error = |e: std::io::Error| UpgradeError {
+        message: format!("Failed to write patches to {}: {}", directory.display(), e),
+        error_type: ErrorType::Internal,
+    };
+    std::fs::create_dir_all(&directory).map_err(io_error)?;
+    for patch in patches {
+        let path = directory.join(&patch.file_name);
+        std::fs::write(&path, &patch.content).map_err(io_error)?;
+        patch.artifact = Some(path.to_string_lossy().to_string());
+    }
+    Ok(())
+}
+
+impl UpgradeWorker {
+    /// The change set as `git format-patch` mails, committed with the
+    /// configured message and author, and stored under the patch directory
+    /// when one is configured.
+    pub(crate) fn format_patches(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk_assessment: &RiskAssessment,
+        changes: &[Change],
+        changeset_id: &str,
+    ) -> Result<Vec<Patch>, UpgradeError> {
+        let default_config = CommitConfig::default();
+        let config = self.config.commit.as_ref().unwrap_or(&default_config);
+        let message = commit_message(config, request, versions, risk_assessment);
+        let mut patches = format_patches(
+            request,
+            versions,
+            &message,
+            (&config.author_name, &config.author_email),
+            Utc::now(),
+            changes,
+        );
+        if let Some(directory) = &self.config.patch_directory {
+            write_patches(directory, changeset_id, &mut patches)?;
+        }
+        Ok(patches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use chrono::TimeZone;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn change(path: &str, change_type: ChangeType, content: &str) -> Change {
+        Change {
+            file_path: path.to_string(),
+            change_type,
+            content: content.to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_format_patch_series() {
+        let request = UpgradeRequest {
+            package_name: "eslint".to_string(),
+            manifests: HashMap::from([
+                (
+                    "package.json".to_string(),
+                    "{\n  \"devDependencies\": {\n    \"eslint\": \"^8.57.0\"\n  }\n}\n"
+                        .to_string(),
+                ),
+                (".eslintrc.json".to_string(), "{}\n".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("8.57.0").unwrap(),
+            target: SemanticScheme.parse("9.0.0").unwrap(),
+        };
+        let mut placeholder = change("package-lock.json", ChangeType::Modify, "");
+        placeholder
+            .metadata
+            .insert("lockfile_refresh".to_string(), json!(true));
+        placeholder
+            .metadata
+            .insert("command".to_string(), json!("npm install"));
+        let mut flat_config = change("eslint.config.mjs", ChangeType::Add, "export default [];\n");
+        let mut eslintrc = change(".eslintrc.json", ChangeType::Delete, "");
+        for migration in [&mut flat_config, &mut eslintrc] {
+            migration
+                .metadata
+                .insert("migration".to_string(), json!("eslint-flat-config"));
+        }
+        let changes = vec![
+            change(
+                "package.json",
+                ChangeType::Modify,
+                "{\n  \"devDependencies\": {\n    \"eslint\": \"^9.0.0\"\n  }\n}\n",
+            ),
+            placeholder,
+            flat_config,
+            eslintrc,
+        ];
+
+        let patches = format_patches(
+            &request,
+            &versions,
+            "Bump eslint from 8.57.0 to 9.0.0",
+            ("speccursor", "bot@example.com"),
+            Utc.with_ymd_and_hms(2024, 4, 8, 12, 0, 0).unwrap(),
+            &changes,
+        );
+        assert_eq!(patches.len(), 2);
+        assert_eq!(
+            patches[0].file_name,
+            "0001-Bump-eslint-from-8.57.0-to-9.0.0.patch"
+        );
+        assert_eq!(
+            patches[0].content,
+            format!(
+                "{}\n\
+                 From: speccursor <bot@example.com>\n\
+                 Date: Mon, 8 Apr 2024 12:00:00 +0000\n\
+                 Subject: [PATCH 1/2] Bump eslint from 8.57.0 to 9.0.0\n\
+                 \n\
+                 Lockfiles to refresh after applying:\n\
+                 \n    package-lock.json: npm install\n\
+                 ---\n \
+                 package.json | 2 +-\n \
+                 1 file changed, 1 insertion(+), 1 deletion(-)\n\
+                 \n\
+                 diff --git a/package.json b/package.json\n\
+                 --- a/package.json\n\
+                 +++ b/package.json\n\
+                 @@ -1,5 +1,5 @@\n \
+                 {{\n   \"devDependencies\": {{\n\
+                 -    \"eslint\": \"^8.57.0\"\n\
+                 +    \"eslint\": \"^9.0.0\"\n   \
+                 }}\n \
+                 }}\n\
+                 -- \nspeccursor-rust-worker {}\n\n",
+                FROM_LINE,
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+
+        let migration = &patches[1];
+        assert_eq!(
+            migration.subject,
+            "Apply the eslint-flat-config migration for eslint 9.0.0"
+        );
+        assert!(migration
+            .content
+            .contains("Subject: [PATCH 2/2] Apply the eslint-flat-config"));
+        assert!(migration.content.contains(
+            " eslint.config.mjs | 1 +\n .eslintrc.json    | 1 -\n 2 files changed, 1 insertion(+), 1 deletion(-)\n"
+        ));
+        assert!(migration
+            .content
+            .contains("diff --git a/eslint.config.mjs b/eslint.config.mjs\nnew file mode 100644\n--- /dev/null\n"));
+        assert!(migration
+            .content
+            .contains("diff --git a/.eslintrc.json b/.eslintrc.json\ndeleted file mode 100644\n"));
+
+        let directory = tempfile::tempdir().unwrap();
+        let mut written = patches.clone();
+        write_patches(directory.path(), "changeset", &mut written).unwrap();
+        let artifact = written[1].artifact.as_ref().unwrap();
+        assert!(artifact
+            .ends_with("changeset/0002-Apply-the-eslint-flat-config-migration-for-eslint-9.patch"));
+        assert_eq!(
+            std::fs::read_to_string(artifact).unwrap(),
+            migration.content
+        );
+    }
+}
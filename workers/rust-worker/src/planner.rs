@@ -0,0 +1,170 @@
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{RiskAssessment, UpgradeError, UpgradeRequest, UpgradeWorker};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Request metadata flag that enables upgrade path planning.
+pub const PLAN_METADATA_KEY: &str = "plan";
+
+/// An ordered chain of upgrades leading from the current to the target version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradePlan {
+    pub steps: Vec<UpgradeStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeStep {
+    pub from_version: String,
+    pub to_version: String,
+    pub risk_assessment: RiskAssessment,
+}
+
+pub fn planning_requested(request: &UpgradeRequest) -> bool {
+    request
+        .metadata
+        .get(PLAN_METADATA_KEY)
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+impl UpgradeWorker {
+    /// Splits a multi-major upgrade into one step per intermediate major,
+    /// each landing on the latest stable release of that major.
+    pub(crate) async fn plan_upgrade(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+    ) -> Result<UpgradePlan, UpgradeError> {
+        let published = self.published_versions(request).await?;
+        let waypoints = intermediate_versions(&published, &versions.current, &versions.target);
+
+        let mut steps = Vec::new();
+        let mut from = versions.current.clone();
+        for to in waypoints
+            .into_iter()
+            .chain(std::iter::once(versions.target.clone()))
+        {
+            let step_versions = ResolvedVersions {
+                current: from.clone(),
+                target: to.clone(),
+            };
+            steps.push(UpgradeStep {
+                from_version: from.to_string(),
+                to_version: to.to_string(),
+                risk_assessment: self.assess_risk(request, &step_versions, &[])?,
+            });
+            from = to;
+        }
+
+        Ok(UpgradePlan { steps })
+    }
+}
+
+/// Latest stable release of every major strictly between current and target.
+fn intermediate_versions(
+    published: &[ParsedVersion],
+    current: &ParsedVersion,
+    target: &ParsedVersion,
+) -> Vec<ParsedVersion> {
+    let mut latest_per_major: BTreeMap<u64, ParsedVersion> = BTreeMap::new();
+
+    for version in published {
+        if version.is_prerelease()
+            || version.major() <= current.major()
+            || version.major() >= target.major()
+        {
+            continue;
+        }
+        let entry = latest_per_major
+            .entry(version.major())
+            .or_insert_with(|| version.clone());
+        if version > entry {
+            *entry = version.clone();
+        }
+    }
+
+    latest_per_major.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{ReleaseInfo, StaticRegistry};
+    use crate::version::{SemanticScheme, VersionJump, VersionScheme};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn v(s: &str) -> ParsedVersion {
+        SemanticScheme.parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_intermediate_versions() {
+        let published: Vec<ParsedVersion> = [
+            "2.4.1",
+            "3.0.0",
+            "3.10.1",
+            "4.0.0",
+            "4.17.21",
+            "5.0.0-beta.1",
+            "5.0.0",
+        ]
+        .iter()
+        .map(|s| v(s))
+        .collect();
+
+        let waypoints = intermediate_versions(&published, &v("2.4.1"), &v("5.0.0"));
+        assert_eq!(waypoints, vec![v("3.10.1"), v("4.17.21")]);
+
+        assert!(intermediate_versions(&published, &v("4.0.0"), &v("5.0.0")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_planning_mode() {
+        let registry = StaticRegistry::new().with_releases(
+            "npm",
+            "lodash",
+            ["2.4.2", "3.10.1", "4.17.21", "5.0.0"]
+                .iter()
+                .map(|v| ReleaseInfo::new(v))
+                .collect(),
+        );
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+
+        let mut metadata = HashMap::new();
+        metadata.insert(PLAN_METADATA_KEY.to_string(), serde_json::json!(true));
+        let request = UpgradeRequest {
+            repository: "test/repo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "2.4.2".to_string(),
+            target_version: "5.0.0".to_string(),
+            metadata,
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"lodash": "2.4.2"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        let plan = response.plan.unwrap();
+        let hops: Vec<(&str, &str)> = plan
+            .steps
+            .iter()
+            .map(|s| (s.from_version.as_str(), s.to_version.as_str()))
+            .collect();
+        assert_eq!(
+            hops,
+            vec![
+                ("2.4.2", "3.10.1"),
+                ("3.10.1", "4.17.21"),
+                ("4.17.21", "5.0.0")
+            ]
+        );
+        assert!(plan
+            .steps
+            .iter()
+            .all(|s| s.risk_assessment.version_jump == VersionJump::Major));
+    }
+}
@@ -0,0 +1,365 @@
+use crate::advisories::Severity;
+use crate::score::ScoreWeights;
+use crate::version::VersionJump;
+use crate::{ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Rules mapping conditions on a scored upgrade to outcomes, evaluated in
+/// order once every check has run. Read from TOML or YAML:
+///
+/// ```toml
+/// [[rules]]
+/// name = "critical-advisories"
+/// when = { security_severity = "critical" }
+/// then = { block = true, reason = "critical advisories need a security review" }
+///
+/// [[rules]]
+/// name = "internal-majors"
+/// when = { packages = ["@acme/*"], version_jump = ["Major"] }
+/// then = { risk_level = "Medium" }
+///
+/// [weights]
+/// security = 0.4
+/// popularity = 0.05
+/// verification = 0.0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+    /// Replace `WorkerConfig::score_weights`; weights left out keep their
+    /// defaults.
+    #[serde(default)]
+    pub weights: Option<ScoreWeights>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub name: String,
+    #[serde(default)]
+    pub when: Conditions,
+    pub then: Outcome,
+}
+
+/// What a rule matches; every condition set must hold, so a rule without
+/// conditions matches every upgrade.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Conditions {
+    pub ecosystem: Option<String>,
+    /// Package names; entries ending in `*` match name prefixes.
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// The risk level is at least this high.
+    pub risk_level: Option<RiskLevel>,
+    /// An advisory the upgrade does not fix is at least this severe.
+    pub security_severity: Option<Severity>,
+    /// The version jump is one of these.
+    #[serde(default)]
+    pub version_jump: Vec<VersionJump>,
+    pub breaking_changes: Option<bool>,
+}
+
+/// What a matching rule does.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Outcome {
+    /// Risk level the upgrade is given, higher or lower than it was scored.
+    pub risk_level: Option<RiskLevel>,
+    /// Neither write, commit nor open a pull request for the upgrade.
+    #[serde(default)]
+    pub block: bool,
+    pub reason: Option<String>,
+}
+
+/// A rule that matched, in [`PolicyDecision::matched`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchedRule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk_level: Option<RiskLevel>,
+    #[serde(default)]
+    pub block: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// The policy's verdict on an upgrade, in `UpgradeResponse::policy`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    pub matched: Vec<MatchedRule>,
+    pub blocked: bool,
+}
+
+impl Conditions {
+    fn hold(&self, request: &UpgradeRequest, risk: &RiskAssessment) -> bool {
+        let package = &request.package_name;
+        self.ecosystem
+            .as_ref()
+            .is_none_or(|ecosystem| *ecosystem == request.ecosystem)
+            && (self.packages.is_empty()
+                || self
+                    .packages
+                    .iter()
+                    .any(|pattern| match pattern.strip_suffix('*') {
+                        Some(prefix) => package.starts_with(prefix),
+                        None => pattern == package,
+                    }))
+            && self.risk_level.is_none_or(|level| risk.risk_level >= level)
+            && self.security_severity.is_none_or(|severity| {
+                risk.security_issues.iter().any(|issue| {
+                    !issue.fixed_by_upgrade && issue.severity.is_some_and(|s| s >= severity)
+                })
+            })
+            && (self.version_jump.is_empty() || self.version_jump.contains(&risk.version_jump))
+            && self
+                .breaking_changes
+                .is_none_or(|breaking| risk.breaking_changes == breaking)
+    }
+}
+
+impl Policy {
+    /// Reads a policy from a `.toml`, `.yaml` or `.yml` file.
+    pub fn load(path: &Path) -> Result<Self, UpgradeError> {
+        let invalid = |reason: String| UpgradeError {
+            message: format!("Failed to load policy {}: {}", path.display(), reason),
+            error_type: ErrorType::Validation,
+        };
+        let content = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let policy: Self = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?
+            }
+            _ => return Err(invalid("expected a .toml, .yaml or .yml file".to_string())),
+        };
+        if let Some(weights) = &policy.weights {
+            weights.validate().map_err(|e| invalid(e.message))?;
+        }
+        Ok(policy)
+    }
+
+    /// Applies the outcome of every rule matching the upgrade, each rule
+    /// seeing the risk level the rules before it left, and records the
+    /// rules that matched in the explanations.
+    pub fn evaluate(&self, request: &UpgradeRequest, risk: &mut RiskAssessment) -> PolicyDecision {
+        let mut decision = PolicyDecision::default();
+        for rule in &self.rules {
+            if !rule.when.hold(request, risk) {
+                continue;
+            }
+            if let Some(level) = rule.then.risk_level {
+                risk.risk_level = level;
+            }
+            decision.blocked |= rule.then.block;
+            risk.explanations.push(match &rule.then.reason {
+                Some(reason) => format!("Policy rule {} applies: {}", rule.name, reason),
+                None => format!("Policy rule {} applies", rule.name),
+            });
+            decision.matched.push(MatchedRule {
+                name: rule.name.clone(),
+                risk_level: rule.then.risk_level,
+                block: rule.then.block,
+                reason: rule.then.reason.clone(),
+            });
+        }
+        decision
+    }
+}
+
+/// A policy file read again whenever it is modified, so that rules and
+/// weights are tuned without restarting the worker.
+pub struct PolicyFile {
+    path: PathBuf,
+    loaded: Mutex<Option<(SystemTime, Arc<Policy>)>>,
+}
+
+impl PolicyFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            loaded: Mutex::new(None),
+        }
+    }
+
+    /// The policy the file holds. An edit that does not load keeps the
+    /// policy last loaded, with a warning; the first load must succeed.
+    pub fn current(&self, warnings: &mut Vec<String>) -> Result<Arc<Policy>, UpgradeError> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((read_at, policy)) = loaded.as_ref() {
+            if modified == Some(*read_at) {
+                return Ok(policy.clone());
+            }
+        }
+        match Policy::load(&self.path) {
+            Ok(policy) => {
+                let policy = Arc::new(policy);
+                *loaded = Some((modified.unwrap_or(SystemTime::UNIX_EPOCH), policy.clone()));
+                Ok(policy)
+            }
+            Err(e) => match loaded.as_ref() {
+                Some((_, policy)) => {
+                    warnings.push(format!("{}; the previous policy applies", e.message));
+                    Ok(policy.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advisories::SecurityIssue;
+    use crate::{UpgradeWorker, WorkerConfig};
+    use std::collections::HashMap;
+
+    const POLICY: &str = r#"
+[[rules]]
+name = "critical-advisories"
+when = { security_severity = "critical" }
+then = { block = true, reason = "critical advisories need a security review" }
+
+[[rules]]
+name = "internal-majors"
+when = { packages = ["@acme/*"], version_jump = ["Major"] }
+then = { risk_level = "Medium" }
+"#;
+
+    #[test]
+    fn test_policy_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("policy.toml");
+        std::fs::write(&toml_path, POLICY).unwrap();
+        let yaml_path = dir.path().join("policy.yaml");
+        std::fs::write(
+            &yaml_path,
+            "rules:\n  - name: critical-advisories\n    when:\n      security_severity: critical\n    then:\n      block: true\n      reason: critical advisories need a security review\n  - name: internal-majors\n    when:\n      packages: ['@acme/*']\n      version_jump: [Major]\n    then:\n      risk_level: Medium\n",
+        )
+        .unwrap();
+
+        let policy = Policy::load(&toml_path).unwrap();
+        assert_eq!(policy.rules.len(), 2);
+        assert_eq!(policy, Policy::load(&yaml_path).unwrap());
+        assert!(Policy::load(&dir.path().join("policy.json")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_policy_outcomes_in_response() {
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            policy: Some(toml::from_str(POLICY).unwrap()),
+            ..WorkerConfig::default()
+        }));
+        let request = |package: &str| UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: package.to_string(),
+            current_version: "1.4.0".to_string(),
+            target_version: "2.0.0".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                format!(r#"{{"dependencies": {{"{}": "1.4.0"}}}}"#, package),
+            )]),
+            ..Default::default()
+        };
+
+        let internal = worker.process_upgrade(request("@acme/ui")).await.unwrap();
+        assert_eq!(internal.risk_assessment.risk_level, RiskLevel::Medium);
+        let decision = internal.policy.unwrap();
+        assert_eq!(decision.matched[0].name, "internal-majors");
+        assert!(!decision.blocked);
+
+        let external = worker.process_upgrade(request("react")).await.unwrap();
+        assert_eq!(external.risk_assessment.risk_level, RiskLevel::High);
+        assert!(external.policy.unwrap().matched.is_empty());
+
+        let mut risk = external.risk_assessment;
+        risk.security_issues.push(SecurityIssue {
+            severity: Some(Severity::Critical),
+            ..SecurityIssue::from("Prototype pollution".to_string())
+        });
+        let policy: Policy = toml::from_str(POLICY).unwrap();
+        let decision = policy.evaluate(&request("react"), &mut risk);
+        assert!(decision.blocked);
+        assert!(risk.explanations.contains(
+            &"Policy rule critical-advisories applies: critical advisories need a security review"
+                .to_string()
+        ));
+        risk.security_issues[0].fixed_by_upgrade = true;
+        assert!(!policy.evaluate(&request("react"), &mut risk).blocked);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_upgrade_is_not_written() {
+        let root = tempfile::tempdir().unwrap();
+        crate::repo::tests::source_repository(root.path());
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            local_roots: vec![root.path().to_path_buf()],
+            policy: Some(
+                toml::from_str(
+                    "[[rules]]\nname = \"frozen\"\nwhen = { packages = [\"lodash\"] }\nthen = { block = true }\n",
+                )
+                .unwrap(),
+            ),
+            ..WorkerConfig::default()
+        }));
+        let request = UpgradeRequest {
+            repository: "acme/app".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.20".to_string(),
+            target_version: "4.17.21".to_string(),
+            local_path: Some(root.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request).await.unwrap();
+        assert!(response.policy.unwrap().blocked);
+        assert!(!response.changes.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(root.path().join("package.json")).unwrap(),
+            r#"{"dependencies": {"lodash": "^4.17.20"}}"#
+        );
+    }
+
+    #[test]
+    fn test_policy_file_reloads_when_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.toml");
+        let write = |content: &str, seconds: u64| {
+            std::fs::write(&path, content).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+                .unwrap();
+        };
+        let file = PolicyFile::new(path.clone());
+        let mut warnings = Vec::new();
+
+        write(
+            "[weights]\nsecurity = 0.4\npopularity = 0.05\nverification = 0.0\n",
+            1,
+        );
+        let policy = file.current(&mut warnings).unwrap();
+        assert_eq!(policy.weights.unwrap().security, 0.4);
+
+        write("[weights]\nsecurity = 0.9\n", 2);
+        let kept = file.current(&mut warnings).unwrap();
+        assert_eq!(kept, policy);
+        assert!(warnings[0]
+            .ends_with("Score weights must add up to 1, not 1.7; the previous policy applies"));
+
+        write(POLICY, 3);
+        assert_eq!(file.current(&mut warnings).unwrap().rules.len(), 2);
+        assert!(PolicyFile::new(dir.path().join("missing.toml"))
+            .current(&mut warnings)
+            .is_err());
+    }
+}
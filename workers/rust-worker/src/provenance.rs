@@ -0,0 +1,841 @@
+use crate::version::ResolvedVersions;
+use crate::{ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use serde::{Deserialize, Serialize};
+
+mod sigstore;
+
+use sigstore::{verify_bundle, Rejection};
+pub use sigstore::{SignerIdentity, TrustRoot};
+
+/// DER header of a P-256 SubjectPublicKeyInfo, followed by the 65-byte
+/// uncompressed point.
+const P256_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+const SLSA_PROVENANCE_PREFIX: &str = "https://slsa.dev/provenance/";
+
+/// Outcome of verifying one kind of signature or attestation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProvenanceStatus {
+    Verified,
+    /// The release was published without it.
+    Missing,
+    /// It does not match the published artifact.
+    Invalid,
+    /// It is intact but cannot be traced to the expected signer: the
+    /// certificate is not from a trusted authority or names another
+    /// identity.
+    Unverified,
+    /// It could not be checked: the ecosystem publishes none, or the
+    /// registry could not be reached.
+    Unavailable,
+}
+
+/// A signature or attestation check of the target version, in
+/// [`crate::RiskAssessment::provenance`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceCheck {
+    /// What was checked: `registry_signature` or `provenance`.
+    pub kind: String,
+    pub status: ProvenanceStatus,
+    pub details: String,
+}
+
+impl ProvenanceCheck {
+    fn new(kind: &str, status: ProvenanceStatus, details: String) -> Self {
+        Self {
+            kind: kind.to_string(),
+            status,
+            details,
+        }
+    }
+}
+
+/// Verifies the signatures and build attestations published with a release.
+#[async_trait]
+pub trait ProvenanceVerifier: Send + Sync {
+    fn supports(&self, ecosystem: &str) -> bool;
+
+    async fn verify(
+        &self,
+        package: &str,
+        version: &str,
+    ) -> Result<Vec<ProvenanceCheck>, UpgradeError>;
+}
+
+/// Checks npm registry signatures and provenance attestations, as
+/// `npm audit signatures` does. Attestations must name the exact tarball
+/// that was published and carry a Sigstore signature by a workflow of the
+/// repository the package declares; they are only checked against a
+/// configured [`TrustRoot`].
+pub struct NpmProvenance {
+    client: reqwest::Client,
+    registry_url: String,
+    trust_root: Option<TrustRoot>,
+}
+
+impl Default for NpmProvenance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NpmProvenance {
+    pub fn new() -> Self {
+        Self::with_registry_url("https://registry.npmjs.org")
+    }
+
+    pub fn with_registry_url(registry_url: &str) -> Self {
+        let registry_url = registry_url.trim_end_matches('/').to_string();
+        // URLs read from registry documents must not lead anywhere else
+        let registry = reqwest::Url::parse(&registry_url).ok();
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if registry
+                    .as_ref()
+                    .is_some_and(|registry| same_origin(registry, attempt.url()))
+                {
+                    attempt.follow()
+                } else {
+                    attempt.stop()
+                }
+            }))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            registry_url,
+            trust_root: None,
+        }
+    }
+
+    /// Verifies provenance attestations against Sigstore's trust root;
+    /// without one they are reported unavailable.
+    pub fn with_trust_root(mut self, trust_root: TrustRoot) -> Self {
+        self.trust_root = Some(trust_root);
+        self
+    }
+
+    fn on_registry(&self, url: &str) -> bool {
+        match (
+            reqwest::Url::parse(&self.registry_url),
+            reqwest::Url::parse(url),
+        ) {
+            (Ok(registry), Ok(url)) => same_origin(&registry, &url),
+            _ => false,
+        }
+    }
+
+    async fn check_provenance(
+        &self,
+        package: &str,
+        version: &str,
+        manifest: &serde_json::Value,
+        url: &str,
+    ) -> Result<ProvenanceCheck, UpgradeError> {
+        let check = |status, details: String| ProvenanceCheck::new("provenance", status, details);
+        if !self.on_registry(url) {
+            return Ok(check(
+                ProvenanceStatus::Unavailable,
+                format!(
+                    "The attestations of {}@{} are served from {}, outside the registry",
+                    package, version, url
+                ),
+            ));
+        }
+        let Some(trust_root) = &self.trust_root else {
+            return Ok(check(
+                ProvenanceStatus::Unavailable,
+                format!(
+                    "No Sigstore trust root is configured to verify the provenance of {}@{}",
+                    package, version
+                ),
+            ));
+        };
+        let repository = manifest["repository"]["url"]
+            .as_str()
+            .or_else(|| manifest["repository"].as_str());
+        let Some(identity) = repository.and_then(SignerIdentity::for_repository) else {
+            return Ok(check(
+                ProvenanceStatus::Unverified,
+                format!(
+                    "{}@{} declares no repository to expect its provenance from",
+                    package, version
+                ),
+            ));
+        };
+
+        let attestations = self.fetch_json(url).await?;
+        Ok(check_attestations(
+            package,
+            version,
+            manifest["dist"]["integrity"].as_str().unwrap_or_default(),
+            &attestations,
+            trust_root,
+            &identity,
+        ))
+    }
+
+    async fn fetch_json(&self, url: &str) -> Result<serde_json::Value, UpgradeError> {
+        let network_error = |message: String| UpgradeError {
+            message: format!("Provenance request to {} failed: {}", url, message),
+            error_type: ErrorType::Network,
+        };
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| network_error(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(network_error(format!("status {}", response.status())));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| network_error(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl ProvenanceVerifier for NpmProvenance {
+    fn supports(&self, ecosystem: &str) -> bool {
+        matches!(ecosystem, "npm" | "pnpm")
+    }
+
+    async fn verify(
+        &self,
+        package: &str,
+        version: &str,
+    ) -> Result<Vec<ProvenanceCheck>, UpgradeError> {
+        let manifest = self
+            .fetch_json(&format!(
+                "{}/{}/{}",
+                self.registry_url,
+                package.replace('/', "%2F"),
+                version
+            ))
+            .await?;
+        let keys = self
+            .fetch_json(&format!("{}/-/npm/v1/keys", self.registry_url))
+            .await?;
+        let dist = &manifest["dist"];
+        let mut checks = vec![verify_registry_signature(package, version, dist, &keys)];
+
+        checks.push(match dist["attestations"]["url"].as_str() {
+            Some(url) => {
+                self.check_provenance(package, version, &manifest, url)
+                    .await?
+            }
+            None => ProvenanceCheck::new(
+                "provenance",
+                ProvenanceStatus::Missing,
+                format!("{}@{} was published without provenance", package, version),
+            ),
+        });
+        Ok(checks)
+    }
+}
+
+fn same_origin(a: &reqwest::Url, b: &reqwest::Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host_str() == b.host_str()
+        && a.port_or_known_default() == b.port_or_known_default()
+}
+
+fn p256_point(spki: &[u8]) -> Option<&[u8]> {
+    spki.strip_prefix(P256_SPKI_PREFIX)
+        .filter(|point| point.len() == 65)
+}
+
+/// Verifies the ECDSA signature the registry made over
+/// `{package}@{version}:{integrity}` with one of its public `keys`
+/// (the `/-/npm/v1/keys` document).
+pub fn verify_registry_signature(
+    package: &str,
+    version: &str,
+    dist: &serde_json::Value,
+    keys: &serde_json::Value,
+) -> ProvenanceCheck {
+    let check =
+        |status, details: String| ProvenanceCheck::new("registry_signature", status, details);
+    let (Some(integrity), Some(signatures)) =
+        (dist["integrity"].as_str(), dist["signatures"].as_array())
+    else {
+        return check(
+            ProvenanceStatus::Missing,
+            format!("{}@{} has no registry signature", package, version),
+        );
+    };
+    let message = format!("{}@{}:{}", package, version, integrity);
+
+    for signature in signatures {
+        let Some(key) = keys["keys"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|key| key["keyid"] == signature["keyid"])
+        else {
+            continue;
+        };
+        let spki = key["key"]
+            .as_str()
+            .and_then(|key| STANDARD.decode(key).ok());
+        let sig = signature["sig"]
+            .as_str()
+            .and_then(|sig| STANDARD.decode(sig).ok());
+        let (Some(spki), Some(sig)) = (spki, sig) else {
+            continue;
+        };
+        let Some(point) = p256_point(&spki) else {
+            continue;
+        };
+        let keyid = signature["keyid"].as_str().unwrap_or_default();
+        return match UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point)
+            .verify(message.as_bytes(), &sig)
+        {
+            Ok(()) => check(
+                ProvenanceStatus::Verified,
+                format!("Signed by the registry with key {}", keyid),
+            ),
+            Err(_) => check(
+                ProvenanceStatus::Invalid,
+                format!(
+                    "The registry signature of {}@{} does not match key {}",
+                    package, version, keyid
+                ),
+            ),
+        };
+    }
+    check(
+        ProvenanceStatus::Unavailable,
+        format!("None of the registry keys signed {}@{}", package, version),
+    )
+}
+
+// Hex SHA-512 digest of an `sha512-<base64>` Subresource Integrity string
+fn sha512_hex(integrity: &str) -> Option<String> {
+    let digest = STANDARD.decode(integrity.strip_prefix("sha512-")?).ok()?;
+    Some(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Checks that the SLSA provenance among the attestations of
+/// `{package}@{version}` is signed by `identity` under `trust_root` and
+/// names the tarball with the given `integrity`, and reports the
+/// repository that built it.
+pub fn check_attestations(
+    package: &str,
+    version: &str,
+    integrity: &str,
+    attestations: &serde_json::Value,
+    trust_root: &TrustRoot,
+    identity: &SignerIdentity,
+) -> ProvenanceCheck {
+    let check = |status, details: String| ProvenanceCheck::new("provenance", status, details);
+    let Some(provenance) = attestations["attestations"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|attestation| {
+            attestation["predicateType"]
+                .as_str()
+                .is_some_and(|predicate| predicate.starts_with(SLSA_PROVENANCE_PREFIX))
+        })
+    else {
+        return check(
+            ProvenanceStatus::Missing,
+            format!("{}@{} has no SLSA provenance attestation", package, version),
+        );
+    };
+    if let Err(rejection) = verify_bundle(&provenance["bundle"], trust_root, identity) {
+        let (status, reason) = match rejection {
+            Rejection::Invalid(reason) => (ProvenanceStatus::Invalid, reason),
+            Rejection::Unverified(reason) => (ProvenanceStatus::Unverified, reason),
+        };
+        return check(
+            status,
+            format!("The provenance of {}@{}: {}", package, version, reason),
+        );
+    }
+    let statement: Option<serde_json::Value> = provenance["bundle"]["dsseEnvelope"]["payload"]
+        .as_str()
+        .and_then(|payload| STANDARD.decode(payload).ok())
+        .and_then(|payload| serde_json::from_slice(&payload).ok());
+    let (Some(statement), Some(digest)) = (statement, sha512_hex(integrity)) else {
+        return check(
+            ProvenanceStatus::Invalid,
+            format!("The provenance of {}@{} cannot be read", package, version),
+        );
+    };
+
+    let purl = format!("pkg:npm/{}@{}", package.replace('@', "%40"), version);
+    let matches = statement["subject"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|subject| subject["name"] == purl.as_str() && subject["digest"]["sha512"] == digest);
+    if !matches {
+        return check(
+            ProvenanceStatus::Invalid,
+            format!(
+                "The provenance of {}@{} is for a different artifact",
+                package, version
+            ),
+        );
+    }
+
+    let workflow = &statement["predicate"]["buildDefinition"]["externalParameters"]["workflow"];
+    let details = match (workflow["repository"].as_str(), workflow["path"].as_str()) {
+        (Some(repository), Some(path)) => format!("Built by {} ({})", repository, path),
+        (Some(repository), None) => format!("Built by {}", repository),
+        _ => "Built with SLSA provenance".to_string(),
+    };
+    check(ProvenanceStatus::Verified, details)
+}
+
+impl UpgradeWorker {
+    /// Records the signature and provenance checks of the target version. An
+    /// invalid signature makes the upgrade critical; under
+    /// `require_provenance`, anything that could not be verified makes it at
+    /// least high risk.
+    pub(crate) async fn check_provenance(
+        &self,
+        target_request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        if self.provenance.is_empty() {
+            return;
+        }
+        let ecosystem = target_request.ecosystem.as_str();
+        let package = target_request.package_name.as_str();
+        let version = versions.target.to_string();
+
+        let mut checks = Vec::new();
+        for verifier in self.provenance.iter().filter(|v| v.supports(ecosystem)) {
+            match verifier.verify(package, &version).await {
+                Ok(found) => checks.extend(found),
+                Err(e) => {
+                    warnings.push(format!("Provenance check skipped: {}", e.message));
+                    checks.push(ProvenanceCheck::new(
+                        "provenance",
+                        ProvenanceStatus::Unavailable,
+                        e.message,
+                    ));
+                }
+            }
+        }
+        // crates.io and most other registries publish no signatures yet
+        if checks.is_empty() {
+            checks.push(ProvenanceCheck::new(
+                "provenance",
+                ProvenanceStatus::Unavailable,
+                format!("No signatures are verified for {} packages", ecosystem),
+            ));
+        }
+
+        for check in &checks {
+            match check.status {
+                ProvenanceStatus::Verified => continue,
+                ProvenanceStatus::Invalid => risk.risk_level = RiskLevel::Critical,
+                _ if self.config.require_provenance => {
+                    risk.risk_level = risk.risk_level.max(RiskLevel::High)
+                }
+                _ => continue,
+            }
+            risk.explanations.push(format!(
+                "{} {} {}: {}",
+                package,
+                version,
+                check.kind.replace('_', " "),
+                check.details
+            ));
+        }
+        risk.provenance = checks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+    use serde_json::json;
+
+    const INTEGRITY: &str = "sha512-ZjNHYMQ4hQCWxsuMITQxcBxdnQfWn/oubdK0v4Ksmrhe1DHxbFASmKtc5P3flbvsZfAdEQ+9K1sJlydq8Ya+CQ==";
+
+    #[test]
+    fn test_registry_signature() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        let spki = [P256_SPKI_PREFIX, key.public_key().as_ref()].concat();
+        let keys = json!({"keys": [{"keyid": "SHA256:test", "key": STANDARD.encode(spki)}]});
+        let sign =
+            |message: &str| STANDARD.encode(key.sign(&rng, message.as_bytes()).unwrap().as_ref());
+        let dist = |sig: String| json!({"integrity": INTEGRITY, "signatures": [{"keyid": "SHA256:test", "sig": sig}]});
+
+        let signed = dist(sign(&format!("left-pad@1.3.0:{}", INTEGRITY)));
+        let check = verify_registry_signature("left-pad", "1.3.0", &signed, &keys);
+        assert_eq!(check.status, ProvenanceStatus::Verified);
+
+        // A signature over another version does not carry over
+        let check = verify_registry_signature("left-pad", "1.3.1", &signed, &keys);
+        assert_eq!(check.status, ProvenanceStatus::Invalid);
+
+        let unknown = json!({"keys": []});
+        let check = verify_registry_signature("left-pad", "1.3.0", &signed, &unknown);
+        assert_eq!(check.status, ProvenanceStatus::Unavailable);
+        let check = verify_registry_signature("left-pad", "1.3.0", &json!({}), &keys);
+        assert_eq!(check.status, ProvenanceStatus::Missing);
+    }
+
+    // DER element with a short or two-byte length
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![tag];
+        match content.len() {
+            length @ 0..=0x7f => encoded.push(length as u8),
+            length @ 0x80..=0xff => encoded.extend([0x81, length as u8]),
+            length => encoded.extend([0x82, (length >> 8) as u8, length as u8]),
+        }
+        encoded.extend_from_slice(content);
+        encoded
+    }
+
+    fn sequence(elements: &[&[u8]]) -> Vec<u8> {
+        der(0x30, &elements.concat())
+    }
+
+    struct Signer {
+        key: EcdsaKeyPair,
+        spki: Vec<u8>,
+    }
+
+    impl Signer {
+        fn new() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            let key =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            let spki = [P256_SPKI_PREFIX, key.public_key().as_ref()].concat();
+            Self { key, spki }
+        }
+
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            let rng = SystemRandom::new();
+            self.key.sign(&rng, message).unwrap().as_ref().to_vec()
+        }
+
+        // Certificate for `subject`, valid 2024 to 2034, naming a workflow
+        // identity when `uri` is given
+        fn certify(&self, subject: &Signer, uri: Option<&str>) -> Vec<u8> {
+            let ecdsa_sha256 = sequence(&[&der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 4, 3, 2])]);
+            let validity = sequence(&[&der(0x17, b"240101000000Z"), &der(0x17, b"340101000000Z")]);
+            let mut tbs = vec![
+                der(0xa0, &der(0x02, &[2])),
+                der(0x02, &[1]),
+                ecdsa_sha256.clone(),
+                sequence(&[]),
+                validity,
+                sequence(&[]),
+                subject.spki.clone(),
+            ];
+            if let Some(uri) = uri {
+                let san = sequence(&[
+                    &der(0x06, &[0x55, 0x1d, 0x11]),
+                    &der(0x04, &sequence(&[&der(0x86, uri.as_bytes())])),
+                ]);
+                let issuer = sequence(&[
+                    &der(0x06, &[0x2b, 6, 1, 4, 1, 0x83, 0xbf, 0x30, 1, 8]),
+                    &der(
+                        0x04,
+                        &der(0x0c, b"https://token.actions.githubusercontent.com"),
+                    ),
+                ]);
+                tbs.push(der(0xa3, &sequence(&[&san, &issuer])));
+            }
+            let tbs = der(0x30, &tbs.concat());
+            let signature = [&[0][..], &self.sign(&tbs)].concat();
+            sequence(&[&tbs, &ecdsa_sha256, &der(0x03, &signature)])
+        }
+    }
+
+    struct Sigstore {
+        root: Signer,
+        log: Signer,
+    }
+
+    impl Sigstore {
+        fn trust_root(&self) -> TrustRoot {
+            TrustRoot::from_json(&json!({
+                "certificateAuthorities": [{
+                    "certChain": {"certificates": [
+                        {"rawBytes": STANDARD.encode(self.root.certify(&self.root, None))}
+                    ]},
+                    "validFor": {"start": "2024-01-01T00:00:00Z"}
+                }],
+                "tlogs": [{
+                    "logId": {"keyId": STANDARD.encode(self.key_id())},
+                    "publicKey": {"rawBytes": STANDARD.encode(&self.log.spki)}
+                }]
+            }))
+            .unwrap()
+        }
+
+        fn key_id(&self) -> Vec<u8> {
+            ring::digest::digest(&ring::digest::SHA256, &self.log.spki)
+                .as_ref()
+                .to_vec()
+        }
+
+        // Bundle of `statement` signed by a workflow at `uri`, logged in
+        // 2025
+        fn bundle(&self, statement: &serde_json::Value, uri: &str) -> serde_json::Value {
+            let signer = Signer::new();
+            let certificate = self.root.certify(&signer, Some(uri));
+            let payload = statement.to_string();
+            let payload_type = "application/vnd.in-toto+json";
+            let encoded = format!(
+                "DSSEv1 {} {} {} {}",
+                payload_type.len(),
+                payload_type,
+                payload.len(),
+                payload
+            );
+
+            let pem = format!(
+                "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+                STANDARD.encode(&certificate)
+            );
+            let body = STANDARD.encode(
+                json!({"kind": "dsse", "spec": {"signatures": [{"verifier": STANDARD.encode(pem)}]}})
+                    .to_string(),
+            );
+            let key_id = self.key_id();
+            let promise = json!({
+                "body": body,
+                "integratedTime": 1735689600,
+                "logID": key_id.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+                "logIndex": 42
+            });
+            json!({
+                "verificationMaterial": {
+                    "certificate": {"rawBytes": STANDARD.encode(&certificate)},
+                    "tlogEntries": [{
+                        "logIndex": "42",
+                        "logId": {"keyId": STANDARD.encode(&key_id)},
+                        "integratedTime": "1735689600",
+                        "inclusionPromise": {
+                            "signedEntryTimestamp": STANDARD.encode(self.log.sign(promise.to_string().as_bytes()))
+                        },
+                        "canonicalizedBody": body
+                    }]
+                },
+                "dsseEnvelope": {
+                    "payloadType": payload_type,
+                    "payload": STANDARD.encode(&payload),
+                    "signatures": [{"sig": STANDARD.encode(signer.sign(encoded.as_bytes()))}]
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn test_provenance_attestation() {
+        let sigstore = Sigstore {
+            root: Signer::new(),
+            log: Signer::new(),
+        };
+        let trust_root = sigstore.trust_root();
+        let identity =
+            SignerIdentity::for_repository("git+https://github.com/sigstore/sigstore-js.git")
+                .unwrap();
+        let workflow =
+            "https://github.com/sigstore/sigstore-js/.github/workflows/release.yml@refs/tags/v2.2.0";
+        let statement = |digest: &str| {
+            json!({
+                "_type": "https://in-toto.io/Statement/v1",
+                "subject": [{"name": "pkg:npm/%40sigstore/sign@2.2.0", "digest": {"sha512": digest}}],
+                "predicateType": "https://slsa.dev/provenance/v1",
+                "predicate": {"buildDefinition": {"externalParameters": {"workflow": {
+                    "repository": "https://github.com/sigstore/sigstore-js",
+                    "path": ".github/workflows/release.yml"
+                }}}}
+            })
+        };
+        let attestations = |bundle: serde_json::Value| {
+            json!({"attestations": [
+                {"predicateType": "https://github.com/npm/attestation/tree/main/specs/publish/v0.1"},
+                {"predicateType": "https://slsa.dev/provenance/v1", "bundle": bundle}
+            ]})
+        };
+        let check = |attestations: &serde_json::Value, trust_root: &TrustRoot| {
+            check_attestations(
+                "@sigstore/sign",
+                "2.2.0",
+                INTEGRITY,
+                attestations,
+                trust_root,
+                &identity,
+            )
+        };
+        let digest = sha512_hex(INTEGRITY).unwrap();
+        assert!(digest.starts_with("66334760c4388500"));
+
+        let signed = attestations(sigstore.bundle(&statement(&digest), workflow));
+        let verified = check(&signed, &trust_root);
+        assert_eq!(verified.status, ProvenanceStatus::Verified);
+        assert_eq!(
+            verified.details,
+            "Built by https://github.com/sigstore/sigstore-js (.github/workflows/release.yml)"
+        );
+
+        // A well-formed bundle proves nothing unless it chains to the trust
+        // root and names the package's repository
+        let other = Sigstore {
+            root: Signer::new(),
+            log: Signer::new(),
+        };
+        assert_eq!(
+            check(&signed, &other.trust_root()).status,
+            ProvenanceStatus::Unverified
+        );
+        let forked = attestations(sigstore.bundle(
+            &statement(&digest),
+            "https://github.com/attacker/sigstore-js/.github/workflows/release.yml@refs/heads/main",
+        ));
+        let unverified = check(&forked, &trust_root);
+        assert_eq!(unverified.status, ProvenanceStatus::Unverified);
+        assert!(unverified.details.contains("attacker/sigstore-js"));
+
+        let mut tampered = signed.clone();
+        tampered["attestations"][1]["bundle"]["dsseEnvelope"]["payload"] =
+            json!(STANDARD.encode(statement(&"00".repeat(64)).to_string()));
+        assert_eq!(
+            check(&tampered, &trust_root).status,
+            ProvenanceStatus::Invalid
+        );
+        let other_artifact = attestations(sigstore.bundle(&statement(&"00".repeat(64)), workflow));
+        assert_eq!(
+            check(&other_artifact, &trust_root).status,
+            ProvenanceStatus::Invalid
+        );
+        assert_eq!(
+            check(&json!({}), &trust_root).status,
+            ProvenanceStatus::Missing
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attestations_stay_on_the_registry() {
+        let npm = NpmProvenance::with_registry_url("https://registry.npmjs.org/");
+        assert!(npm.on_registry("https://registry.npmjs.org/-/npm/v1/attestations/left-pad@1.3.0"));
+        assert!(!npm.on_registry("https://attacker.example/-/npm/v1/attestations/left-pad@1.3.0"));
+        assert!(!npm.on_registry("http://registry.npmjs.org/-/npm/v1/attestations/left-pad@1.3.0"));
+
+        let manifest =
+            json!({"repository": {"url": "git+https://github.com/left-pad/left-pad.git"}});
+        let check = npm
+            .check_provenance("left-pad", "1.3.0", &manifest, "https://attacker.example/a")
+            .await
+            .unwrap();
+        assert_eq!(check.status, ProvenanceStatus::Unavailable);
+        // Without a trust root nothing is fetched or verified
+        let check = npm
+            .check_provenance(
+                "left-pad",
+                "1.3.0",
+                &manifest,
+                "https://registry.npmjs.org/-/npm/v1/attestations/left-pad@1.3.0",
+            )
+            .await
+            .unwrap();
+        assert_eq!(check.status, ProvenanceStatus::Unavailable);
+        assert!(check.details.starts_with("No Sigstore trust root"));
+    }
+
+    struct StaticVerifier(ProvenanceStatus);
+
+    #[async_trait]
+    impl ProvenanceVerifier for StaticVerifier {
+        fn supports(&self, _ecosystem: &str) -> bool {
+            true
+        }
+
+        async fn verify(
+            &self,
+            _package: &str,
+            _version: &str,
+        ) -> Result<Vec<ProvenanceCheck>, UpgradeError> {
+            Ok(vec![ProvenanceCheck::new(
+                "provenance",
+                self.0,
+                "static".to_string(),
+            )])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_provenance_risk_levels() {
+        use crate::version::{SemanticScheme, VersionScheme};
+        use std::sync::Arc;
+
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "left-pad".to_string(),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.2.0").unwrap(),
+            target: SemanticScheme.parse("1.3.0").unwrap(),
+        };
+        let risk_for = |status, require_provenance| {
+            let worker = UpgradeWorker::new(Some(crate::WorkerConfig {
+                require_provenance,
+                ..Default::default()
+            }))
+            .with_provenance_verifier(Arc::new(StaticVerifier(status)));
+            let (request, versions) = (request.clone(), versions.clone());
+            async move {
+                let mut risk = RiskAssessment::default();
+                worker
+                    .check_provenance(&request, &versions, &mut risk, &mut Vec::new())
+                    .await;
+                risk.risk_level
+            }
+        };
+
+        assert_eq!(
+            risk_for(ProvenanceStatus::Missing, false).await,
+            RiskLevel::Low
+        );
+        assert_eq!(
+            risk_for(ProvenanceStatus::Missing, true).await,
+            RiskLevel::High
+        );
+        assert_eq!(
+            risk_for(ProvenanceStatus::Unavailable, true).await,
+            RiskLevel::High
+        );
+        assert_eq!(
+            risk_for(ProvenanceStatus::Invalid, false).await,
+            RiskLevel::Critical
+        );
+    }
+}
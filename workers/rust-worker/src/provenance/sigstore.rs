@@ -0,0 +1,570 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+
+const EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const P384: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+const ECDSA_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const ECDSA_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+// Fulcio's OIDC issuer extensions, 1.3.6.1.4.1.57264.1.1 (raw string) and
+// 1.3.6.1.4.1.57264.1.8 (UTF8String)
+const FULCIO_ISSUER: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x01];
+const FULCIO_ISSUER_V2: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x08];
+
+/// Sigstore trust material: the Fulcio certificate authorities issuing
+/// signing certificates and the Rekor transparency logs recording their
+/// use, as published in Sigstore's `trusted_root.json`.
+#[derive(Debug, Clone)]
+pub struct TrustRoot {
+    authorities: Vec<Authority>,
+    logs: Vec<TransparencyLog>,
+}
+
+#[derive(Debug, Clone)]
+struct Authority {
+    /// DER certificates from the issuing intermediate up to the root.
+    chain: Vec<Vec<u8>>,
+    valid_from: i64,
+    valid_until: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct TransparencyLog {
+    key_id: Vec<u8>,
+    /// DER SubjectPublicKeyInfo.
+    public_key: Vec<u8>,
+}
+
+impl TrustRoot {
+    /// Reads a `trusted_root.json` document (media type
+    /// `application/vnd.dev.sigstore.trustedroot+json`).
+    pub fn from_json(document: &serde_json::Value) -> Result<Self, String> {
+        let bytes = |value: &serde_json::Value, what: &str| {
+            value
+                .as_str()
+                .and_then(|raw| STANDARD.decode(raw).ok())
+                .ok_or_else(|| format!("trusted root has an unreadable {}", what))
+        };
+        let time = |value: &serde_json::Value| {
+            value
+                .as_str()
+                .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+                .map(|time| time.timestamp())
+        };
+
+        let authorities = document["certificateAuthorities"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|authority| {
+                let chain = authority["certChain"]["certificates"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|certificate| bytes(&certificate["rawBytes"], "certificate"))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Authority {
+                    chain,
+                    valid_from: time(&authority["validFor"]["start"]).unwrap_or(i64::MIN),
+                    valid_until: time(&authority["validFor"]["end"]),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let logs = document["tlogs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|log| {
+                Ok(TransparencyLog {
+                    key_id: bytes(&log["logId"]["keyId"], "log ID")?,
+                    public_key: bytes(&log["publicKey"]["rawBytes"], "log key")?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if authorities
+            .iter()
+            .all(|authority| authority.chain.is_empty())
+            || logs.is_empty()
+        {
+            return Err("trusted root lists no certificate authority or transparency log".into());
+        }
+        Ok(Self { authorities, logs })
+    }
+}
+
+/// The signer an attestation must come from: a CI workflow of the
+/// package's repository, with a token of its OIDC issuer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignerIdentity {
+    /// `https://github.com/{owner}/{repo}`.
+    pub repository: String,
+    pub issuer: String,
+}
+
+impl SignerIdentity {
+    /// The identity expected for a package declaring `repository` (as
+    /// written in `package.json`), when it is hosted on a forge whose CI
+    /// Sigstore issues certificates for.
+    pub fn for_repository(repository: &str) -> Option<Self> {
+        let url = repository
+            .trim()
+            .trim_start_matches("git+")
+            .trim_end_matches('/')
+            .trim_end_matches(".git");
+        let url = if let Some(path) = url.strip_prefix("github:") {
+            format!("https://github.com/{}", path)
+        } else {
+            url.replacen("git://", "https://", 1)
+                .replacen("ssh://git@", "https://", 1)
+                .replacen("git@github.com:", "https://github.com/", 1)
+        };
+
+        let issuer = if url.starts_with("https://github.com/") {
+            "https://token.actions.githubusercontent.com"
+        } else if url.starts_with("https://gitlab.com/") {
+            "https://gitlab.com"
+        } else {
+            return None;
+        };
+        Some(Self {
+            repository: url,
+            issuer: issuer.to_string(),
+        })
+    }
+}
+
+/// Why a bundle was not accepted.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Rejection {
+    /// The signature does not match the signed content.
+    Invalid(String),
+    /// The signature holds, but cannot be traced to the expected signer.
+    Unverified(String),
+}
+
+/// Verifies a Sigstore bundle over a DSSE envelope: the envelope is signed
+/// by the key of its certificate, the certificate was issued by a trusted
+/// Fulcio authority to `identity` and was logged, while valid, in a trusted
+/// Rekor log.
+pub(crate) fn verify_bundle(
+    bundle: &serde_json::Value,
+    trust_root: &TrustRoot,
+    identity: &SignerIdentity,
+) -> Result<(), Rejection> {
+    let material = &bundle["verificationMaterial"];
+    let leaf_der = material["certificate"]["rawBytes"]
+        .as_str()
+        .or_else(|| material["x509CertificateChain"]["certificates"][0]["rawBytes"].as_str())
+        .and_then(|raw| STANDARD.decode(raw).ok())
+        .ok_or_else(|| Rejection::Unverified("the bundle carries no signing certificate".into()))?;
+    let leaf = Certificate::parse(&leaf_der)
+        .ok_or_else(|| Rejection::Invalid("the signing certificate cannot be read".into()))?;
+
+    // The envelope is signed over its pre-authentication encoding
+    let envelope = &bundle["dsseEnvelope"];
+    let payload_type = envelope["payloadType"].as_str().unwrap_or_default();
+    let payload = envelope["payload"]
+        .as_str()
+        .and_then(|raw| STANDARD.decode(raw).ok())
+        .unwrap_or_default();
+    let mut encoded = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    encoded.extend_from_slice(&payload);
+    let signed = envelope["signatures"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|signature| STANDARD.decode(signature["sig"].as_str()?).ok())
+        .any(|signature| verify_signature(leaf.public_key, None, &encoded, &signature));
+    if !signed {
+        return Err(Rejection::Invalid(
+            "the attestation signature does not match its certificate".into(),
+        ));
+    }
+
+    let logged_at = logged_time(material, trust_root, &leaf_der).ok_or_else(|| {
+        Rejection::Unverified("no trusted transparency log recorded the signature".into())
+    })?;
+    if !leaf.valid_at(logged_at) {
+        return Err(Rejection::Unverified(
+            "the signing certificate was not valid when the signature was logged".into(),
+        ));
+    }
+    if !trust_root
+        .authorities
+        .iter()
+        .any(|authority| authority.issued(&leaf, logged_at))
+    {
+        return Err(Rejection::Unverified(
+            "the signing certificate was not issued by a trusted authority".into(),
+        ));
+    }
+
+    let workflow_prefix = format!("{}/", identity.repository);
+    if leaf.issuer.as_deref() != Some(identity.issuer.as_str())
+        || !leaf
+            .uris
+            .iter()
+            .any(|uri| uri.starts_with(&workflow_prefix))
+    {
+        return Err(Rejection::Unverified(format!(
+            "signed by {} rather than a workflow of {}",
+            leaf.uris
+                .first()
+                .map_or("an unknown identity", String::as_str),
+            identity.repository
+        )));
+    }
+    Ok(())
+}
+
+impl Authority {
+    // Whether `leaf` chains up to this authority's root, with every
+    // certificate valid at `time`.
+    fn issued(&self, leaf: &Certificate, time: i64) -> bool {
+        if time < self.valid_from || self.valid_until.is_some_and(|end| time > end) {
+            return false;
+        }
+        let Some(chain) = self
+            .chain
+            .iter()
+            .map(|der| Certificate::parse(der))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+        let mut subject = leaf;
+        for issuer in &chain {
+            if !issuer.valid_at(time) || !issuer.signed(subject) {
+                return false;
+            }
+            subject = issuer;
+        }
+        !chain.is_empty()
+    }
+}
+
+// Time a trusted log integrated an entry recording `leaf_der`, proved by the
+// log's signed entry timestamp.
+fn logged_time(
+    material: &serde_json::Value,
+    trust_root: &TrustRoot,
+    leaf_der: &[u8],
+) -> Option<i64> {
+    material["tlogEntries"]
+        .as_array()?
+        .iter()
+        .find_map(|entry| {
+            let key_id = STANDARD.decode(entry["logId"]["keyId"].as_str()?).ok()?;
+            let log = trust_root.logs.iter().find(|log| log.key_id == key_id)?;
+            let number =
+                |value: &serde_json::Value| value.as_i64().or_else(|| value.as_str()?.parse().ok());
+            let integrated_time = number(&entry["integratedTime"])?;
+            let log_index = number(&entry["logIndex"])?;
+            let body = entry["canonicalizedBody"].as_str()?;
+            let timestamp = STANDARD
+                .decode(entry["inclusionPromise"]["signedEntryTimestamp"].as_str()?)
+                .ok()?;
+
+            // serde_json orders keys, which canonicalizes the promise
+            let promise = serde_json::json!({
+                "body": body,
+                "integratedTime": integrated_time,
+                "logID": key_id.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+                "logIndex": log_index,
+            })
+            .to_string();
+            let (curve, point) = public_key(&log.public_key)?;
+            let signed = verify_with(curve, None, point, promise.as_bytes(), &timestamp);
+            let body: serde_json::Value =
+                serde_json::from_slice(&STANDARD.decode(body).ok()?).ok()?;
+            (signed && mentions_certificate(&body, leaf_der)).then_some(integrated_time)
+        })
+}
+
+// Whether a log entry body names the certificate, which entries embed as
+// base64 of its PEM encoding.
+fn mentions_certificate(value: &serde_json::Value, der: &[u8]) -> bool {
+    match value {
+        serde_json::Value::String(raw) => STANDARD
+            .decode(raw)
+            .ok()
+            .and_then(|pem| String::from_utf8(pem).ok())
+            .and_then(|pem| {
+                let body: String = pem
+                    .strip_prefix("-----BEGIN CERTIFICATE-----")?
+                    .split("-----END CERTIFICATE-----")
+                    .next()?
+                    .split_whitespace()
+                    .collect();
+                STANDARD.decode(body).ok()
+            })
+            .is_some_and(|decoded| decoded == der),
+        serde_json::Value::Array(values) => values.iter().any(|v| mentions_certificate(v, der)),
+        serde_json::Value::Object(fields) => fields.values().any(|v| mentions_certificate(v, der)),
+        _ => false,
+    }
+}
+
+/// The fields of an X.509 certificate needed to verify Sigstore bundles.
+struct Certificate<'a> {
+    tbs: &'a [u8],
+    signature_algorithm: &'a [u8],
+    signature: &'a [u8],
+    /// DER SubjectPublicKeyInfo.
+    public_key: &'a [u8],
+    not_before: i64,
+    not_after: i64,
+    uris: Vec<String>,
+    issuer: Option<String>,
+}
+
+impl<'a> Certificate<'a> {
+    fn parse(der: &'a [u8]) -> Option<Self> {
+        let (_, certificate, _) = Der::new(der).expect(0x30)?;
+        let mut certificate = Der::new(certificate);
+        let (_, tbs_fields, tbs) = certificate.expect(0x30)?;
+        let signature_algorithm = algorithm(certificate.expect(0x30)?.1)?;
+        let signature = bit_string(certificate.expect(0x03)?.1)?;
+
+        let mut fields = Der::new(tbs_fields);
+        let (mut tag, _, _) = fields.next()?;
+        if tag == 0xa0 {
+            // Explicit version, then the serial number
+            (tag, _, _) = fields.next()?;
+        }
+        if tag != 0x02 {
+            return None;
+        }
+        fields.expect(0x30)?; // signature algorithm
+        fields.expect(0x30)?; // issuer
+        let mut validity = Der::new(fields.expect(0x30)?.1);
+        let not_before = time(validity.next()?)?;
+        let not_after = time(validity.next()?)?;
+        fields.expect(0x30)?; // subject
+        let public_key = fields.expect(0x30)?.2;
+
+        let mut uris = Vec::new();
+        let mut issuer = None;
+        while let Some((tag, content, _)) = fields.next() {
+            if tag != 0xa3 {
+                continue;
+            }
+            let mut extensions = Der::new(Der::new(content).expect(0x30)?.1);
+            while let Some((_, extension, _)) = extensions.next() {
+                let mut extension = Der::new(extension);
+                let id = extension.expect(0x06)?.1;
+                let (mut tag, mut value, _) = extension.next()?;
+                if tag == 0x01 {
+                    // Criticality
+                    (tag, value, _) = extension.next()?;
+                }
+                if tag != 0x04 {
+                    return None;
+                }
+                match id {
+                    SUBJECT_ALT_NAME => {
+                        let mut names = Der::new(Der::new(value).expect(0x30)?.1);
+                        while let Some((tag, name, _)) = names.next() {
+                            // [6] uniformResourceIdentifier
+                            if tag == 0x86 {
+                                uris.push(String::from_utf8_lossy(name).into_owned());
+                            }
+                        }
+                    }
+                    FULCIO_ISSUER_V2 => {
+                        let (_, name, _) = Der::new(value).expect(0x0c)?;
+                        issuer = Some(String::from_utf8_lossy(name).into_owned());
+                    }
+                    FULCIO_ISSUER if issuer.is_none() => {
+                        issuer = Some(String::from_utf8_lossy(value).into_owned());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Some(Self {
+            tbs,
+            signature_algorithm,
+            signature,
+            public_key,
+            not_before,
+            not_after,
+            uris,
+            issuer,
+        })
+    }
+
+    fn valid_at(&self, time: i64) -> bool {
+        (self.not_before..=self.not_after).contains(&time)
+    }
+
+    // Whether this certificate's key made the signature of `subject`.
+    fn signed(&self, subject: &Certificate) -> bool {
+        let hash = match subject.signature_algorithm {
+            ECDSA_SHA256 => Hash::Sha256,
+            ECDSA_SHA384 => Hash::Sha384,
+            _ => return false,
+        };
+        verify_signature(self.public_key, Some(hash), subject.tbs, subject.signature)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Curve {
+    P256,
+    P384,
+}
+
+#[derive(Clone, Copy)]
+enum Hash {
+    Sha256,
+    Sha384,
+}
+
+// Verifies an ECDSA signature with a SubjectPublicKeyInfo, hashing with
+// `hash` or the curve's own digest size.
+fn verify_signature(spki: &[u8], hash: Option<Hash>, message: &[u8], signature: &[u8]) -> bool {
+    public_key(spki)
+        .is_some_and(|(curve, point)| verify_with(curve, hash, point, message, signature))
+}
+
+fn verify_with(
+    curve: Curve,
+    hash: Option<Hash>,
+    point: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let algorithm: &'static dyn VerificationAlgorithm = match (curve, hash) {
+        (Curve::P256, None | Some(Hash::Sha256)) => &signature::ECDSA_P256_SHA256_ASN1,
+        (Curve::P256, Some(Hash::Sha384)) => &signature::ECDSA_P256_SHA384_ASN1,
+        (Curve::P384, Some(Hash::Sha256)) => &signature::ECDSA_P384_SHA256_ASN1,
+        (Curve::P384, None | Some(Hash::Sha384)) => &signature::ECDSA_P384_SHA384_ASN1,
+    };
+    UnparsedPublicKey::new(algorithm, point)
+        .verify(message, signature)
+        .is_ok()
+}
+
+// Curve and encoded point of an elliptic curve SubjectPublicKeyInfo.
+fn public_key(spki: &[u8]) -> Option<(Curve, &[u8])> {
+    let mut fields = Der::new(Der::new(spki).expect(0x30)?.1);
+    let mut algorithm = Der::new(fields.expect(0x30)?.1);
+    if algorithm.expect(0x06)?.1 != EC_PUBLIC_KEY {
+        return None;
+    }
+    let curve = match algorithm.expect(0x06)?.1 {
+        P256 => Curve::P256,
+        P384 => Curve::P384,
+        _ => return None,
+    };
+    Some((curve, bit_string(fields.expect(0x03)?.1)?))
+}
+
+// Object identifier of an AlgorithmIdentifier.
+fn algorithm(content: &[u8]) -> Option<&[u8]> {
+    Some(Der::new(content).expect(0x06)?.1)
+}
+
+fn bit_string(content: &[u8]) -> Option<&[u8]> {
+    content.strip_prefix(&[0])
+}
+
+// Seconds since the epoch of an UTCTime or GeneralizedTime.
+fn time((tag, content, _): (u8, &[u8], &[u8])) -> Option<i64> {
+    let raw = std::str::from_utf8(content).ok()?;
+    let format = match tag {
+        0x17 => "%y%m%d%H%M%SZ",
+        0x18 => "%Y%m%d%H%M%SZ",
+        _ => return None,
+    };
+    Some(
+        NaiveDateTime::parse_from_str(raw, format)
+            .ok()?
+            .and_utc()
+            .timestamp(),
+    )
+}
+
+/// Reader of consecutive DER elements.
+struct Der<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input }
+    }
+
+    /// The next element's tag, contents and whole encoding.
+    fn next(&mut self) -> Option<(u8, &'a [u8], &'a [u8])> {
+        let (&tag, rest) = self.input.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (length, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return None;
+            }
+            let length = rest[..count]
+                .iter()
+                .fold(0usize, |length, &byte| length << 8 | byte as usize);
+            (length, &rest[count..])
+        };
+        if rest.len() < length {
+            return None;
+        }
+        let header = self.input.len() - rest.len();
+        let whole = &self.input[..header + length];
+        let content = &rest[..length];
+        self.input = &rest[length..];
+        Some((tag, content, whole))
+    }
+
+    fn expect(&mut self, tag: u8) -> Option<(u8, &'a [u8], &'a [u8])> {
+        self.next().filter(|(found, _, _)| *found == tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signer_identity() {
+        let identity =
+            SignerIdentity::for_repository("git+https://github.com/sigstore/sigstore-js.git");
+        assert_eq!(
+            identity,
+            Some(SignerIdentity {
+                repository: "https://github.com/sigstore/sigstore-js".to_string(),
+                issuer: "https://token.actions.githubusercontent.com".to_string(),
+            })
+        );
+        assert_eq!(
+            SignerIdentity::for_repository("github:sigstore/sigstore-js")
+                .unwrap()
+                .repository,
+            "https://github.com/sigstore/sigstore-js"
+        );
+        assert_eq!(
+            SignerIdentity::for_repository("https://gitlab.com/acme/web")
+                .unwrap()
+                .issuer,
+            "https://gitlab.com"
+        );
+        assert!(SignerIdentity::for_repository("https://example.com/acme/web").is_none());
+    }
+}
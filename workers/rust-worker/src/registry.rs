@@ -0,0 +1,659 @@
+use crate::{ErrorType, UpgradeError};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// A single published release of a package.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+    pub version: String,
+    #[serde(default)]
+    pub yanked: bool,
+    /// Deprecation message published for this release (npm `deprecated`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// Minimum supported Rust version declared by a crate release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<String>,
+    /// Feature names a crate release publishes, when the registry lists them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// When the release was published, as an RFC 3339 timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+    /// npm lifecycle scripts run on install (`preinstall`, `install`,
+    /// `postinstall`) that the release declares.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_scripts: Option<Vec<String>>,
+    /// Account that published the release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    /// Accounts allowed to publish the package as of this release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintainers: Option<Vec<String>>,
+    /// Size of the published artifact in bytes (unpacked on npm).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// License as the release declares it, not necessarily an SPDX
+    /// expression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Peer dependencies the release declares, name to range (npm
+    /// `peerDependencies`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_dependencies: Option<BTreeMap<String, String>>,
+}
+
+impl ReleaseInfo {
+    pub fn new(version: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            yanked: false,
+            deprecated: None,
+            rust_version: None,
+            features: None,
+            published_at: None,
+            install_scripts: None,
+            publisher: None,
+            maintainers: None,
+            size: None,
+            license: None,
+            peer_dependencies: None,
+        }
+    }
+}
+
+/// Source of package metadata used to resolve requirements and vet targets.
+#[async_trait]
+pub trait Registry: Send + Sync {
+    /// Whether this registry can answer queries for the given ecosystem.
+    fn supports(&self, ecosystem: &str) -> bool;
+
+    async fn releases(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Vec<ReleaseInfo>, UpgradeError>;
+
+    /// Named tags pointing at releases (npm `dist-tags`). Registries without
+    /// the concept return an empty map.
+    async fn dist_tags(
+        &self,
+        _ecosystem: &str,
+        _package: &str,
+    ) -> Result<HashMap<String, String>, UpgradeError> {
+        Ok(HashMap::new())
+    }
+
+    /// Recent downloads of a package: the last week on npm, the last 90
+    /// days on crates.io. `None` when the registry does not publish them.
+    async fn downloads(
+        &self,
+        _ecosystem: &str,
+        _package: &str,
+    ) -> Result<Option<u64>, UpgradeError> {
+        Ok(None)
+    }
+
+    /// Downloads per version: of the last week on npm, all-time on
+    /// crates.io. Empty when the registry does not publish them.
+    async fn version_downloads(
+        &self,
+        _ecosystem: &str,
+        _package: &str,
+    ) -> Result<HashMap<String, u64>, UpgradeError> {
+        Ok(HashMap::new())
+    }
+
+    /// Deprecation marker on the package as a whole (a crates.io
+    /// maintenance badge), as a message. `None` when the registry has none.
+    async fn deprecation(
+        &self,
+        _ecosystem: &str,
+        _package: &str,
+    ) -> Result<Option<String>, UpgradeError> {
+        Ok(None)
+    }
+}
+
+/// Registry client for the public package indexes.
+pub struct HttpRegistry {
+    client: reqwest::Client,
+}
+
+impl Default for HttpRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpRegistry {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .unwrap_or_default();
+
+        Self { client }
+    }
+
+    async fn fetch_json(&self, url: &str) -> Result<serde_json::Value, UpgradeError> {
+        let response = self.client.get(url).send().await.map_err(network_error)?;
+
+        if !response.status().is_success() {
+            return Err(UpgradeError {
+                message: format!(
+                    "Registry request to {} failed with status {}",
+                    url,
+                    response.status()
+                ),
+                error_type: ErrorType::Network,
+            });
+        }
+
+        response.json().await.map_err(network_error)
+    }
+}
+
+#[async_trait]
+impl Registry for HttpRegistry {
+    fn supports(&self, ecosystem: &str) -> bool {
+        matches!(ecosystem, "cargo" | "npm" | "pip")
+    }
+
+    async fn releases(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Vec<ReleaseInfo>, UpgradeError> {
+        match ecosystem {
+            "cargo" => {
+                let url = format!("https://crates.io/api/v1/crates/{}/versions", package);
+                Ok(parse_crates_io_versions(&self.fetch_json(&url).await?))
+            }
+            "npm" => Ok(parse_npm_packument(
+                &self.fetch_json(&npm_packument_url(package)).await?,
+            )),
+            "pip" => {
+                let url = format!("https://pypi.org/pypi/{}/json", package);
+                Ok(parse_pypi_releases(&self.fetch_json(&url).await?))
+            }
+            _ => Err(UpgradeError {
+                message: format!("No registry client available for ecosystem '{}'", ecosystem),
+                error_type: ErrorType::Validation,
+            }),
+        }
+    }
+
+    async fn dist_tags(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<HashMap<String, String>, UpgradeError> {
+        match ecosystem {
+            "npm" => Ok(parse_npm_dist_tags(
+                &self.fetch_json(&npm_packument_url(package)).await?,
+            )),
+            _ => Ok(HashMap::new()),
+        }
+    }
+
+    async fn downloads(&self, ecosystem: &str, package: &str) -> Result<Option<u64>, UpgradeError> {
+        match ecosystem {
+            "cargo" => {
+                let url = format!("https://crates.io/api/v1/crates/{}", package);
+                Ok(self.fetch_json(&url).await?["crate"]["recent_downloads"].as_u64())
+            }
+            "npm" => {
+                let url = format!(
+                    "https://api.npmjs.org/downloads/point/last-week/{}",
+                    package
+                );
+                Ok(self.fetch_json(&url).await?["downloads"].as_u64())
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn version_downloads(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<HashMap<String, u64>, UpgradeError> {
+        let (url, downloads) = match ecosystem {
+            "cargo" => (
+                format!("https://crates.io/api/v1/crates/{}/versions", package),
+                "/versions",
+            ),
+            "npm" => (
+                format!(
+                    "https://api.npmjs.org/versions/{}/last-week",
+                    package.replace('/', "%2F")
+                ),
+                "/downloads",
+            ),
+            _ => return Ok(HashMap::new()),
+        };
+        Ok(parse_version_downloads(
+            self.fetch_json(&url).await?.pointer(downloads),
+        ))
+    }
+
+    async fn deprecation(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Option<String>, UpgradeError> {
+        match ecosystem {
+            "cargo" => {
+                let url = format!("https://crates.io/api/v1/crates/{}", package);
+                Ok(parse_crates_io_deprecation(&self.fetch_json(&url).await?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// In-memory registry, useful for tests and for callers that pre-fetch metadata.
+#[derive(Debug, Clone, Default)]
+pub struct StaticRegistry {
+    releases: HashMap<(String, String), Vec<ReleaseInfo>>,
+    dist_tags: HashMap<(String, String), HashMap<String, String>>,
+    downloads: HashMap<(String, String), u64>,
+    deprecations: HashMap<(String, String), String>,
+    version_downloads: HashMap<(String, String), HashMap<String, u64>>,
+}
+
+impl StaticRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_releases(
+        mut self,
+        ecosystem: &str,
+        package: &str,
+        releases: Vec<ReleaseInfo>,
+    ) -> Self {
+        self.releases
+            .insert((ecosystem.to_string(), package.to_string()), releases);
+        self
+    }
+
+    pub fn with_dist_tag(
+        mut self,
+        ecosystem: &str,
+        package: &str,
+        tag: &str,
+        version: &str,
+    ) -> Self {
+        self.dist_tags
+            .entry((ecosystem.to_string(), package.to_string()))
+            .or_default()
+            .insert(tag.to_string(), version.to_string());
+        self
+    }
+
+    pub fn with_downloads(mut self, ecosystem: &str, package: &str, downloads: u64) -> Self {
+        self.downloads
+            .insert((ecosystem.to_string(), package.to_string()), downloads);
+        self
+    }
+
+    pub fn with_version_downloads(
+        mut self,
+        ecosystem: &str,
+        package: &str,
+        version: &str,
+        downloads: u64,
+    ) -> Self {
+        self.version_downloads
+            .entry((ecosystem.to_string(), package.to_string()))
+            .or_default()
+            .insert(version.to_string(), downloads);
+        self
+    }
+
+    pub fn with_deprecation(mut self, ecosystem: &str, package: &str, message: &str) -> Self {
+        self.deprecations.insert(
+            (ecosystem.to_string(), package.to_string()),
+            message.to_string(),
+        );
+        self
+    }
+}
+
+#[async_trait]
+impl Registry for StaticRegistry {
+    fn supports(&self, ecosystem: &str) -> bool {
+        self.releases.keys().any(|(known, _)| known == ecosystem)
+    }
+
+    async fn releases(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Vec<ReleaseInfo>, UpgradeError> {
+        self.releases
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .cloned()
+            .ok_or_else(|| UpgradeError {
+                message: format!("Package '{}' not found in {} registry", package, ecosystem),
+                error_type: ErrorType::Network,
+            })
+    }
+
+    async fn dist_tags(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<HashMap<String, String>, UpgradeError> {
+        Ok(self
+            .dist_tags
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn downloads(&self, ecosystem: &str, package: &str) -> Result<Option<u64>, UpgradeError> {
+        Ok(self
+            .downloads
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .copied())
+    }
+
+    async fn version_downloads(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<HashMap<String, u64>, UpgradeError> {
+        Ok(self
+            .version_downloads
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn deprecation(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Option<String>, UpgradeError> {
+        Ok(self
+            .deprecations
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .cloned())
+    }
+}
+
+fn network_error(e: reqwest::Error) -> UpgradeError {
+    UpgradeError {
+        message: format!("Registry request failed: {}", e),
+        error_type: ErrorType::Network,
+    }
+}
+
+fn npm_packument_url(package: &str) -> String {
+    format!("https://registry.npmjs.org/{}", package.replace('/', "%2F"))
+}
+
+fn parse_crates_io_versions(body: &serde_json::Value) -> Vec<ReleaseInfo> {
+    body["versions"]
+        .as_array()
+        .map(|versions| {
+            versions
+                .iter()
+                .filter_map(|v| {
+                    Some(ReleaseInfo {
+                        version: v["num"].as_str()?.to_string(),
+                        yanked: v["yanked"].as_bool().unwrap_or(false),
+                        deprecated: None,
+                        rust_version: v["rust_version"].as_str().map(str::to_string),
+                        features: v["features"]
+                            .as_object()
+                            .map(|features| features.keys().cloned().collect()),
+                        published_at: v["created_at"].as_str().map(str::to_string),
+                        install_scripts: None,
+                        publisher: v["published_by"]["login"].as_str().map(str::to_string),
+                        maintainers: None,
+                        size: v["crate_size"].as_u64(),
+                        license: v["license"].as_str().map(str::to_string),
+                        peer_dependencies: None,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Downloads per version of crates.io's version list, or of npm's
+// `{"1.0.0": 123}` map
+fn parse_version_downloads(downloads: Option<&serde_json::Value>) -> HashMap<String, u64> {
+    match downloads {
+        Some(serde_json::Value::Array(versions)) => versions
+            .iter()
+            .filter_map(|v| Some((v["num"].as_str()?.to_string(), v["downloads"].as_u64()?)))
+            .collect(),
+        Some(serde_json::Value::Object(versions)) => versions
+            .iter()
+            .filter_map(|(version, downloads)| Some((version.clone(), downloads.as_u64()?)))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+// The maintenance badge of a crate, when it marks the crate deprecated
+fn parse_crates_io_deprecation(body: &serde_json::Value) -> Option<String> {
+    body["crate"]["badges"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|badge| {
+            badge["badge_type"] == "maintenance" && badge["attributes"]["status"] == "deprecated"
+        })
+        .map(|_| "its maintenance status on crates.io is deprecated".to_string())
+}
+
+fn parse_npm_packument(body: &serde_json::Value) -> Vec<ReleaseInfo> {
+    body["versions"]
+        .as_object()
+        .map(|versions| {
+            versions
+                .iter()
+                .map(|(version, manifest)| ReleaseInfo {
+                    version: version.clone(),
+                    yanked: false,
+                    deprecated: manifest["deprecated"].as_str().map(str::to_string),
+                    rust_version: None,
+                    features: None,
+                    published_at: body["time"][version].as_str().map(str::to_string),
+                    install_scripts: Some(npm_install_scripts(manifest)),
+                    publisher: manifest["_npmUser"]["name"].as_str().map(str::to_string),
+                    maintainers: manifest["maintainers"].as_array().map(|maintainers| {
+                        maintainers
+                            .iter()
+                            .filter_map(|maintainer| Some(maintainer["name"].as_str()?.to_string()))
+                            .collect()
+                    }),
+                    size: manifest["dist"]["unpackedSize"].as_u64(),
+                    // Old manifests use `{"type": "MIT", "url": ...}`
+                    license: manifest["license"]
+                        .as_str()
+                        .or_else(|| manifest["license"]["type"].as_str())
+                        .map(str::to_string),
+                    peer_dependencies: manifest["peerDependencies"].as_object().map(|peers| {
+                        peers
+                            .iter()
+                            .filter_map(|(name, range)| {
+                                Some((name.clone(), range.as_str()?.to_string()))
+                            })
+                            .collect()
+                    }),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Install lifecycle scripts of a version manifest; `hasInstallScript`
+// marks an implicit `node-gyp rebuild`
+fn npm_install_scripts(manifest: &serde_json::Value) -> Vec<String> {
+    let mut scripts: Vec<String> = ["preinstall", "install", "postinstall"]
+        .into_iter()
+        .filter(|script| manifest["scripts"][script].is_string())
+        .map(str::to_string)
+        .collect();
+    if scripts.is_empty() && manifest["hasInstallScript"] == true {
+        scripts.push("install".to_string());
+    }
+    scripts
+}
+
+fn parse_npm_dist_tags(body: &serde_json::Value) -> HashMap<String, String> {
+    body["dist-tags"]
+        .as_object()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|(tag, version)| Some((tag.clone(), version.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_pypi_releases(body: &serde_json::Value) -> Vec<ReleaseInfo> {
+    body["releases"]
+        .as_object()
+        .map(|releases| {
+            releases
+                .iter()
+                .map(|(version, files)| ReleaseInfo {
+                    version: version.clone(),
+                    deprecated: None,
+                    rust_version: None,
+                    features: None,
+                    published_at: files[0]["upload_time_iso_8601"]
+                        .as_str()
+                        .map(str::to_string),
+                    install_scripts: None,
+                    publisher: None,
+                    maintainers: None,
+                    size: None,
+                    license: None,
+                    peer_dependencies: None,
+                    // A release counts as yanked once every uploaded file is yanked
+                    yanked: files
+                        .as_array()
+                        .map(|files| {
+                            !files.is_empty()
+                                && files.iter().all(|f| f["yanked"].as_bool() == Some(true))
+                        })
+                        .unwrap_or(false),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_registry_payloads() {
+        let crates = json!({"versions": [{"num": "1.0.1", "yanked": true}, {"num": "1.0.0", "yanked": false, "rust_version": "1.70", "features": {"default": ["std"], "std": []}}]});
+        assert_eq!(
+            parse_crates_io_versions(&crates),
+            vec![
+                ReleaseInfo {
+                    yanked: true,
+                    ..ReleaseInfo::new("1.0.1")
+                },
+                ReleaseInfo {
+                    rust_version: Some("1.70".to_string()),
+                    features: Some(vec!["default".to_string(), "std".to_string()]),
+                    ..ReleaseInfo::new("1.0.0")
+                },
+            ]
+        );
+
+        let deprecated = json!({"crate": {"badges": [
+            {"badge_type": "maintenance", "attributes": {"status": "deprecated"}}
+        ]}});
+        assert!(parse_crates_io_deprecation(&deprecated).is_some());
+        let downloads = parse_version_downloads(
+            json!({"versions": [{"num": "1.0.0", "downloads": 1200}]}).pointer("/versions"),
+        );
+        assert_eq!(downloads["1.0.0"], 1200);
+        let downloads = parse_version_downloads(
+            json!({"downloads": {"4.17.21": 51234567}}).pointer("/downloads"),
+        );
+        assert_eq!(downloads["4.17.21"], 51234567);
+        assert!(parse_crates_io_deprecation(&json!({"crate": {"badges": []}})).is_none());
+
+        let npm = json!({
+            "versions": {
+                "4.17.20": {"deprecated": "use 4.17.21", "scripts": {"test": "jest"}},
+                "4.17.21": {
+                    "scripts": {"postinstall": "node setup.js"},
+                    "_npmUser": {"name": "bnjmnt4n", "email": "benjamin@example.com"},
+                    "maintainers": [{"name": "mathias"}, {"name": "bnjmnt4n"}],
+                    "dist": {"unpackedSize": 1412415},
+                    "license": "MIT",
+                    "peerDependencies": {"react": "^17.0.0 || ^18.0.0"}
+                },
+                "4.17.22": {"hasInstallScript": true}
+            },
+            "time": {"4.17.21": "2021-02-20T15:42:16.891Z"}
+        });
+        let releases = parse_npm_packument(&npm);
+        assert_eq!(releases.len(), 3);
+        assert_eq!(releases[0].deprecated.as_deref(), Some("use 4.17.21"));
+        assert_eq!(releases[0].install_scripts, Some(Vec::new()));
+        assert_eq!(
+            releases[1].published_at.as_deref(),
+            Some("2021-02-20T15:42:16.891Z")
+        );
+        assert_eq!(
+            releases[1].install_scripts,
+            Some(vec!["postinstall".to_string()])
+        );
+        assert_eq!(releases[1].publisher.as_deref(), Some("bnjmnt4n"));
+        assert_eq!(
+            releases[1].maintainers,
+            Some(vec!["mathias".to_string(), "bnjmnt4n".to_string()])
+        );
+        assert_eq!(releases[1].size, Some(1412415));
+        assert_eq!(releases[1].license.as_deref(), Some("MIT"));
+        assert_eq!(
+            releases[1].peer_dependencies.as_ref().unwrap()["react"],
+            "^17.0.0 || ^18.0.0"
+        );
+        assert_eq!(releases[0].peer_dependencies, None);
+        assert_eq!(
+            releases[2].install_scripts,
+            Some(vec!["install".to_string()])
+        );
+
+        let tags = json!({"dist-tags": {"latest": "20.11.1", "lts": "18.19.0"}});
+        let tags = parse_npm_dist_tags(&tags);
+        assert_eq!(tags.get("lts").map(String::as_str), Some("18.19.0"));
+
+        let pypi = json!({"releases": {"2.0.0": [{"yanked": true}], "2.0.1": [{"yanked": false}]}});
+        let releases = parse_pypi_releases(&pypi);
+        assert!(releases.iter().any(|r| r.version == "2.0.0" && r.yanked));
+        assert!(releases.iter().any(|r| r.version == "2.0.1" && !r.yanked));
+    }
+
+    #[tokio::test]
+    async fn test_static_registry() {
+        let registry =
+            StaticRegistry::new().with_releases("npm", "lodash", vec![ReleaseInfo::new("4.17.21")]);
+
+        assert_eq!(registry.releases("npm", "lodash").await.unwrap().len(), 1);
+        assert!(registry.releases("npm", "left-pad").await.is_err());
+    }
+}
@@ -0,0 +1,698 @@
+use crate::credentials::Secret;
+use crate::version::{ResolvedVersions, VersionScheme};
+use crate::{ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker};
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// Notes kept per section of the digest; the rest are only counted
+const MAX_NOTES: usize = 20;
+// Longer notes are cut at a word boundary
+const MAX_NOTE_LENGTH: usize = 300;
+// Files a changelog is looked up in when a project publishes no releases
+const CHANGELOG_FILES: [&str; 4] = ["CHANGELOG.md", "CHANGES.md", "HISTORY.md", "NEWS.md"];
+
+/// The notes a project published for one release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    /// Version or tag the notes are headed with (`v1.2.0`, `pkg@1.2.0`).
+    pub version: String,
+    /// Markdown body of the notes.
+    pub body: String,
+}
+
+/// Every release's notes of a package, from one source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Changelog {
+    /// Where the notes were read (a releases page or changelog file).
+    pub source: String,
+    pub releases: Vec<Release>,
+}
+
+/// One entry of a release notes section.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseNote {
+    pub version: String,
+    pub text: String,
+}
+
+/// What the releases from the current version up to the target say
+/// changed, in [`crate::UpgradeResponse::release_notes`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseNotes {
+    pub source: String,
+    /// Versions whose notes were read, oldest first.
+    pub versions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breaking: Vec<ReleaseNote>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deprecations: Vec<ReleaseNote>,
+    /// Upgrade and migration instructions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub migration: Vec<ReleaseNote>,
+    /// Notes left out of the sections above to keep the digest short.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub omitted: usize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+impl ReleaseNotes {
+    pub fn is_empty(&self) -> bool {
+        self.breaking.is_empty() && self.deprecations.is_empty() && self.migration.is_empty()
+    }
+}
+
+/// Where the release notes of packages are published.
+#[async_trait]
+pub trait ReleaseNotesSource: Send + Sync {
+    /// The package's release notes; `None` when the source does not know
+    /// where the package publishes them.
+    async fn changelog(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Option<Changelog>, UpgradeError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Breaking,
+    Deprecations,
+    Migration,
+}
+
+impl Section {
+    /// The section a heading starts, if it is one the digest keeps.
+    fn of_heading(heading: &str) -> Option<Self> {
+        let heading = heading.to_lowercase();
+        if heading.contains("breaking") {
+            Some(Section::Breaking)
+        } else if heading.contains("deprecat") {
+            Some(Section::Deprecations)
+        } else if heading.contains("migrat") || heading.contains("upgrad") {
+            Some(Section::Migration)
+        } else {
+            None
+        }
+    }
+
+    /// The section an entry outside of one belongs to by its own marker
+    /// (`BREAKING:`, `feat!:`, `Deprecated ...`).
+    fn of_entry(entry: &str) -> Option<Self> {
+        let lower = entry
+            .trim_start_matches(['*', '_', '[', '`'])
+            .to_lowercase();
+        if lower.starts_with("breaking")
+            || lower.contains("[breaking]")
+            || conventional_breaking().is_match(entry)
+        {
+            Some(Section::Breaking)
+        } else if lower.starts_with("deprecate") {
+            Some(Section::Deprecations)
+        } else {
+            None
+        }
+    }
+}
+
+fn conventional_breaking() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^\w+(\([^)]*\))?!:").expect("valid pattern"))
+}
+
+fn heading_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^(#{1,6})\s+(.*?)\s*#*$").expect("valid pattern"))
+}
+
+fn version_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^\[?(?:\S*@|[\w-]+-)?v?(\d+(?:\.\d+)*(?:[-+.][0-9A-Za-z.-]+)?)\]?")
+            .expect("valid pattern")
+    })
+}
+
+/// The version a release tag or changelog heading names (`v1.2.0`,
+/// `@scope/pkg@1.2.0`, `[1.2.0] - 2024-01-31`).
+pub fn tag_version(tag: &str) -> Option<&str> {
+    let caps = version_pattern().captures(tag.trim())?;
+    Some(caps.get(1)?.as_str())
+}
+
+/// Splits a changelog file into its releases, at the headings naming a
+/// version. Headings of the same level that name none, like
+/// `## Unreleased`, end a release without starting one.
+pub fn parse_changelog(text: &str) -> Vec<Release> {
+    let mut releases: Vec<Release> = Vec::new();
+    let mut level = None;
+    let mut open = false;
+    for line in text.lines() {
+        if let Some(caps) = heading_pattern().captures(line) {
+            let depth = caps[1].len();
+            if level.is_none_or(|level| depth <= level) {
+                match tag_version(&caps[2]) {
+                    Some(version) => {
+                        level = Some(depth);
+                        releases.push(Release {
+                            version: version.to_string(),
+                            body: String::new(),
+                        });
+                        open = true;
+                        continue;
+                    }
+                    None if level == Some(depth) => open = false,
+                    None => {}
+                }
+            }
+        }
+        if let Some(release) = releases.last_mut().filter(|_| open) {
+            release.body.push_str(line);
+            release.body.push('\n');
+        }
+    }
+    releases
+}
+
+// Cuts a note at a word boundary so digests stay readable
+fn shorten(text: &str) -> String {
+    if text.len() <= MAX_NOTE_LENGTH {
+        return text.to_string();
+    }
+    let mut end = MAX_NOTE_LENGTH;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = text[..end].rfind(' ').unwrap_or(end);
+    format!("{}…", text[..cut].trim_end())
+}
+
+/// Breaking changes, deprecations and migration notes of a release's body:
+/// the entries under headings naming them, and entries marked as such
+/// anywhere else.
+fn extract(body: &str) -> Vec<(Section, String)> {
+    let mut notes: Vec<(Section, String)> = Vec::new();
+    let mut section = None;
+    let mut continues = false;
+    for line in body.lines() {
+        let trimmed = line.trim();
+        let heading = heading_pattern()
+            .captures(trimmed)
+            .map(|caps| caps[2].to_string())
+            .or_else(|| {
+                // Bold lines stand in for headings (`**Breaking changes**`)
+                let bold = trimmed.strip_prefix("**")?.strip_suffix("**")?;
+                Some(bold.trim_end_matches(':').to_string())
+            });
+        if let Some(heading) = heading {
+            section = Section::of_heading(&heading);
+            continues = false;
+            continue;
+        }
+        if trimmed.is_empty() {
+            continues = false;
+            continue;
+        }
+
+        let entry = trimmed
+            .strip_prefix(['-', '*', '+'])
+            .map(str::trim_start)
+            .filter(|_| !trimmed.starts_with("**"));
+        match (entry, notes.last_mut()) {
+            // Wrapped lines and nested text extend the previous entry
+            (None, Some((_, text))) if continues && line.starts_with(char::is_whitespace) => {
+                text.push(' ');
+                text.push_str(trimmed);
+            }
+            _ => {
+                let entry = entry.unwrap_or(trimmed);
+                continues = false;
+                if let Some(kind) = section.or_else(|| Section::of_entry(entry)) {
+                    notes.push((kind, entry.to_string()));
+                    continues = true;
+                }
+            }
+        }
+    }
+    notes
+}
+
+/// The digest of the releases after `versions.current` up to and including
+/// `versions.target`. Releases whose versions `scheme` cannot parse are
+/// skipped.
+pub fn digest(
+    changelog: &Changelog,
+    scheme: &dyn VersionScheme,
+    versions: &ResolvedVersions,
+) -> ReleaseNotes {
+    let mut releases: Vec<_> = changelog
+        .releases
+        .iter()
+        .filter_map(|release| {
+            let version = scheme.parse(tag_version(&release.version)?).ok()?;
+            (version > versions.current && version <= versions.target).then_some((version, release))
+        })
+        .collect();
+    releases.sort_by(|a, b| a.0.cmp(&b.0));
+    releases.dedup_by(|a, b| a.0 == b.0);
+
+    let mut notes = ReleaseNotes {
+        source: changelog.source.clone(),
+        ..Default::default()
+    };
+    for (version, release) in releases {
+        let version = version.to_string();
+        for (section, text) in extract(&release.body) {
+            let entries = match section {
+                Section::Breaking => &mut notes.breaking,
+                Section::Deprecations => &mut notes.deprecations,
+                Section::Migration => &mut notes.migration,
+            };
+            if entries.len() < MAX_NOTES {
+                entries.push(ReleaseNote {
+                    version: version.clone(),
+                    text: shorten(&text),
+                });
+            } else {
+                notes.omitted += 1;
+            }
+        }
+        notes.versions.push(version);
+    }
+    notes
+}
+
+/// The `owner/repo` of a GitHub repository URL as registries publish them
+/// (`git+https://github.com/lodash/lodash.git`, `github:owner/repo`,
+/// `https://github.com/owner/repo/tree/main/packages/x`).
+pub fn github_repository(url: &str) -> Option<String> {
+    let path = url
+        .strip_prefix("github:")
+        .or_else(|| url.split_once("github.com").map(|(_, path)| path))?;
+    let mut parts = path
+        .trim_start_matches([':', '/'])
+        .split('/')
+        .filter(|part| !part.is_empty());
+    let owner = parts.next()?;
+    let repo = parts.next()?.trim_end_matches(".git");
+    (!repo.is_empty()).then(|| format!("{}/{}", owner, repo))
+}
+
+/// Reads release notes from the GitHub releases of a package's repository,
+/// or its changelog file when it publishes none. The repository is found in
+/// the npm, crates.io and PyPI metadata of the package unless it is given.
+pub struct GitHubReleaseNotes {
+    client: reqwest::Client,
+    api_url: String,
+    token: Option<Secret>,
+    repositories: HashMap<String, String>,
+}
+
+impl GitHubReleaseNotes {
+    pub fn new(token: Option<Secret>, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            api_url: "https://api.github.com".to_string(),
+            token,
+            repositories: HashMap::new(),
+        }
+    }
+
+    /// Reads the release notes of `package` from the GitHub repository
+    /// `owner/repo`.
+    pub fn with_repository(mut self, package: &str, repository: &str) -> Self {
+        self.repositories
+            .insert(package.to_string(), repository.to_string());
+        self
+    }
+
+    pub(crate) fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// The body of `url`, `None` when it is not found. Requests to the
+    /// GitHub API carry the token.
+    pub(crate) async fn get(
+        &self,
+        url: &str,
+        accept: &str,
+    ) -> Result<Option<String>, UpgradeError> {
+        let mut request = self.client.get(url).header("Accept", accept);
+        if url.starts_with(&self.api_url) {
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token.read()?);
+            }
+        }
+        let response = request.send().await.map_err(|e| UpgradeError {
+            message: format!("Release notes request to {} failed: {}", url, e),
+            error_type: ErrorType::Network,
+        })?;
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                response.text().await.map(Some).map_err(|e| UpgradeError {
+                    message: format!("Release notes request to {} failed: {}", url, e),
+                    error_type: ErrorType::Network,
+                })
+            }
+            status => Err(UpgradeError {
+                message: format!(
+                    "Release notes request to {} failed with status {}",
+                    url, status
+                ),
+                error_type: ErrorType::Network,
+            }),
+        }
+    }
+
+    /// The GitHub repository (`owner/repo`) the package's registry metadata
+    /// links to.
+    pub(crate) async fn repository(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Option<String>, UpgradeError> {
+        if let Some(repository) = self.repositories.get(package) {
+            return Ok(Some(repository.clone()));
+        }
+        let (url, fields): (String, &[&str]) = match ecosystem {
+            "npm" | "pnpm" => (
+                format!("https://registry.npmjs.org/{}", package),
+                &["/repository/url", "/repository", "/homepage"],
+            ),
+            "cargo" => (
+                format!("https://crates.io/api/v1/crates/{}", package),
+                &["/crate/repository", "/crate/homepage"],
+            ),
+            "pip" | "pipenv" => (
+                format!("https://pypi.org/pypi/{}/json", package),
+                &[
+                    "/info/project_urls/Source",
+                    "/info/project_urls/Repository",
+                    "/info/project_urls/Homepage",
+                    "/info/home_page",
+                ],
+            ),
+            _ => return Ok(None),
+        };
+        let Some(body) = self.get(&url, "application/json").await? else {
+            return Ok(None);
+        };
+        let metadata: serde_json::Value =
+            serde_json::from_str(&body).unwrap_or(serde_json::Value::Null);
+        Ok(fields
+            .iter()
+            .filter_map(|field| metadata.pointer(field)?.as_str())
+            .find_map(github_repository))
+    }
+}
+
+#[async_trait]
+impl ReleaseNotesSource for GitHubReleaseNotes {
+    async fn changelog(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Option<Changelog>, UpgradeError> {
+        let Some(repository) = self.repository(ecosystem, package).await? else {
+            return Ok(None);
+        };
+
+        let url = format!(
+            "{}/repos/{}/releases?per_page=100",
+            self.api_url, repository
+        );
+        let releases: Vec<Release> = match self.get(&url, "application/vnd.github+json").await? {
+            Some(body) => serde_json::from_str::<Vec<serde_json::Value>>(&body)
+                .unwrap_or_default()
+                .iter()
+                .filter(|release| release["draft"] != true)
+                .filter_map(|release| {
+                    Some(Release {
+                        version: release["tag_name"].as_str()?.to_string(),
+                        body: release["body"].as_str()?.to_string(),
+                    })
+                })
+                .filter(|release| !release.body.trim().is_empty())
+                .collect(),
+            None => return Ok(None),
+        };
+        if !releases.is_empty() {
+            return Ok(Some(Changelog {
+                source: format!("https://github.com/{}/releases", repository),
+                releases,
+            }));
+        }
+
+        for file in CHANGELOG_FILES {
+            let url = format!("{}/repos/{}/contents/{}", self.api_url, repository, file);
+            if let Some(text) = self.get(&url, "application/vnd.github.raw").await? {
+                return Ok(Some(Changelog {
+                    source: format!("https://github.com/{}/blob/HEAD/{}", repository, file),
+                    releases: parse_changelog(&text),
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// In-memory release notes, useful for tests.
+#[derive(Debug, Clone, Default)]
+pub struct StaticReleaseNotes {
+    changelogs: HashMap<(String, String), Changelog>,
+}
+
+impl StaticReleaseNotes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_changelog(mut self, ecosystem: &str, package: &str, changelog: Changelog) -> Self {
+        self.changelogs
+            .insert((ecosystem.to_string(), package.to_string()), changelog);
+        self
+    }
+}
+
+#[async_trait]
+impl ReleaseNotesSource for StaticReleaseNotes {
+    async fn changelog(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Option<Changelog>, UpgradeError> {
+        Ok(self
+            .changelogs
+            .get(&(ecosystem.to_string(), package.to_string()))
+            .cloned())
+    }
+}
+
+impl UpgradeWorker {
+    /// Digests the release notes between the two versions. Breaking changes
+    /// they list make the upgrade breaking and high risk; notes that cannot
+    /// be fetched are reported as a warning.
+    pub(crate) async fn check_release_notes(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) -> Option<ReleaseNotes> {
+        let source = self.release_notes.as_ref()?;
+        if request.replacement_package.is_some() || versions.target <= versions.current {
+            return None;
+        }
+        let changelog = match source
+            .changelog(&request.ecosystem, &request.package_name)
+            .await
+        {
+            Ok(changelog) => changelog?,
+            Err(e) => {
+                warnings.push(format!("Release notes skipped: {}", e.message));
+                return None;
+            }
+        };
+        let notes = digest(&changelog, self.version_scheme(request), versions);
+        if notes.versions.is_empty() {
+            return None;
+        }
+
+        let count = |notes: &[ReleaseNote], what: &str| {
+            format!(
+                "{} {}{}",
+                notes.len(),
+                what,
+                if notes.len() == 1 { "" } else { "s" }
+            )
+        };
+        if !notes.breaking.is_empty() {
+            risk.breaking_changes = true;
+            risk.risk_level = risk.risk_level.max(RiskLevel::High);
+            risk.explanations.push(format!(
+                "Release notes of {} {} list {}",
+                request.package_name,
+                versions.target,
+                count(&notes.breaking, "breaking change")
+            ));
+        }
+        if !notes.migration.is_empty() {
+            risk.explanations.push(format!(
+                "Release notes of {} {} include {}",
+                request.package_name,
+                versions.target,
+                count(&notes.migration, "migration note")
+            ));
+        }
+        Some(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::SemanticScheme;
+    use std::sync::Arc;
+
+    const CHANGELOG: &str = "# Changelog
+
+## [Unreleased]
+### Breaking Changes
+- Not released yet
+
+## [2.0.0] - 2024-03-01
+### ⚠ BREAKING CHANGES
+* `connect()` now returns a promise
+  instead of taking a callback
+* Node 16 is no longer supported
+
+### Features
+* feat!: drop the `legacy` export
+* Add `retry` option
+
+## [1.5.0] - 2024-01-15
+### Deprecated
+- `Client#query` in favour of `Client#execute`
+
+## 1.4.0 (2023-12-01)
+**Migration guide**
+Replace `new Pool(url)` with `Pool.connect(url)`.
+";
+
+    #[test]
+    fn test_parse_changelog() {
+        let releases = parse_changelog(CHANGELOG);
+        let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+        assert_eq!(versions, ["2.0.0", "1.5.0", "1.4.0"]);
+        assert!(!releases[0].body.contains("Not released yet"));
+        assert_eq!(tag_version("@scope/pkg@1.2.0-rc.1"), Some("1.2.0-rc.1"));
+        assert_eq!(tag_version("tokio-1.38.0"), Some("1.38.0"));
+        assert_eq!(tag_version("Unreleased"), None);
+        assert_eq!(
+            github_repository("git+https://github.com/lodash/lodash.git").as_deref(),
+            Some("lodash/lodash")
+        );
+        assert_eq!(
+            github_repository("https://github.com/tokio-rs/tokio/tree/master/tokio").as_deref(),
+            Some("tokio-rs/tokio")
+        );
+        assert_eq!(github_repository("https://gitlab.com/acme/app"), None);
+    }
+
+    #[test]
+    fn test_digest_between_versions() {
+        let changelog = Changelog {
+            source: "https://github.com/acme/db/blob/HEAD/CHANGELOG.md".to_string(),
+            releases: parse_changelog(CHANGELOG),
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("1.4.0").unwrap(),
+            target: SemanticScheme.parse("2.0.0").unwrap(),
+        };
+        let notes = digest(&changelog, &SemanticScheme, &versions);
+        assert_eq!(notes.versions, ["1.5.0", "2.0.0"]);
+        let breaking: Vec<&str> = notes.breaking.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(
+            breaking,
+            [
+                "`connect()` now returns a promise instead of taking a callback",
+                "Node 16 is no longer supported",
+                "feat!: drop the `legacy` export",
+            ]
+        );
+        assert_eq!(
+            notes.deprecations,
+            [ReleaseNote {
+                version: "1.5.0".to_string(),
+                text: "`Client#query` in favour of `Client#execute`".to_string(),
+            }]
+        );
+        // 1.4.0 is the current version, so its migration guide is not news
+        assert!(notes.migration.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_release_notes_in_response() {
+        let changelog = Changelog {
+            source: "https://github.com/acme/db/releases".to_string(),
+            releases: vec![
+                Release {
+                    version: "v2.0.0".to_string(),
+                    body: "## Breaking\n- Drops Node 16\n\n## Upgrading\nCall `init()` first.\n"
+                        .to_string(),
+                },
+                Release {
+                    version: "v1.9.0".to_string(),
+                    body: "- Faster queries\n".to_string(),
+                },
+            ],
+        };
+        let worker = UpgradeWorker::new(None).with_release_notes(Arc::new(
+            StaticReleaseNotes::new().with_changelog("npm", "acme-db", changelog),
+        ));
+        let request = |target: &str| UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "acme-db".to_string(),
+            current_version: "1.8.0".to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"acme-db": "1.8.0"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request("2.0.0")).await.unwrap();
+        let notes = response.release_notes.unwrap();
+        assert_eq!(notes.breaking[0].text, "Drops Node 16");
+        assert_eq!(notes.migration[0].text, "Call `init()` first.");
+        let risk = response.risk_assessment;
+        assert!(risk.breaking_changes);
+        assert!(risk
+            .explanations
+            .contains(&"Release notes of acme-db 2.0.0 list 1 breaking change".to_string()));
+
+        let response = worker.process_upgrade(request("1.9.0")).await.unwrap();
+        let notes = response.release_notes.unwrap();
+        assert_eq!(notes.versions, ["1.9.0"]);
+        assert!(notes.is_empty());
+        assert_eq!(response.risk_assessment.risk_level, RiskLevel::Medium);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest};
+use regex::Regex;
+use std::borrow::Cow;
+
+/// Ecosystems whose manifest handlers can swap one package for another.
+const SUPPORTED_ECOSYSTEMS: &[&str] = &["npm", "cargo"];
+
+const NPM_SOURCE_EXTENSIONS: &[&str] = &[
+    "js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts", "vue", "svelte",
+];
+
+/// Rejects renames the ecosystem cannot express, and renames to the same
+/// package.
+pub(crate) fn validate(request: &UpgradeRequest) -> Result<(), UpgradeError> {
+    let Some(replacement) = &request.replacement_package else {
+        return Ok(());
+    };
+    let problem = if replacement.trim().is_empty() || *replacement == request.package_name {
+        "must name a different package"
+    } else if !SUPPORTED_ECOSYSTEMS.contains(&request.ecosystem.as_str()) {
+        "is not supported for this ecosystem"
+    } else {
+        return Ok(());
+    };
+    Err(UpgradeError {
+        message: format!(
+            "Replacement package '{}' for '{}' {}",
+            replacement, request.package_name, problem
+        ),
+        error_type: ErrorType::Validation,
+    })
+}
+
+/// The request as seen from the target side: for a rename the target version
+/// is resolved and vetted against the replacement package.
+pub fn target_request(request: &UpgradeRequest) -> Cow<'_, UpgradeRequest> {
+    match &request.replacement_package {
+        Some(replacement) => Cow::Owned(UpgradeRequest {
+            package_name: replacement.clone(),
+            replacement_package: None,
+            ..request.clone()
+        }),
+        None => Cow::Borrowed(request),
+    }
+}
+
+/// Supplied source files importing the package being replaced
+/// (`require("babel-core")`, `use structopt::StructOpt`), in path order.
+pub fn affected_imports(request: &UpgradeRequest) -> Vec<&str> {
+    let package = regex::escape(&request.package_name);
+    let (pattern, extensions): (String, &[&str]) = match request.ecosystem.as_str() {
+        "npm" => (
+            format!(
+                r#"(?:\bfrom\s*|\brequire\(\s*|\bimport\(\s*|\bimport\s+)["']{}(?:/[^"']*)?["']"#,
+                package
+            ),
+            NPM_SOURCE_EXTENSIONS,
+        ),
+        "cargo" => {
+            let crate_name = regex::escape(&request.package_name.replace('-', "_"));
+            (
+                format!(r"\b{0}\s*::|\bextern\s+crate\s+{0}\b", crate_name),
+                &["rs"],
+            )
+        }
+        _ => return Vec::new(),
+    };
+    let pattern = Regex::new(&pattern).expect("valid pattern");
+
+    let mut files: Vec<&str> = request
+        .manifests
+        .iter()
+        .filter(|(path, _)| {
+            path.rsplit_once('.')
+                .is_some_and(|(_, extension)| extensions.contains(&extension))
+        })
+        .filter(|(_, content)| pattern.is_match(content))
+        .map(|(path, _)| path.as_str())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Replacing a package is a breaking change: its API and import path move.
+pub(crate) fn assess_risk(request: &UpgradeRequest, assessment: &mut RiskAssessment) {
+    let Some(replacement) = &request.replacement_package else {
+        return;
+    };
+    assessment.breaking_changes = true;
+    assessment.risk_level = assessment.risk_level.max(RiskLevel::High);
+    assessment.explanations.push(format!(
+        "{} is replaced by {}",
+        request.package_name, replacement
+    ));
+
+    let files = affected_imports(request);
+    if !files.is_empty() {
+        assessment.explanations.push(format!(
+            "Imports of {} must be updated to {} in {}",
+            request.package_name,
+            replacement,
+            files.join(", ")
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request(ecosystem: &str, package: &str, files: &[(&str, &str)]) -> UpgradeRequest {
+        UpgradeRequest {
+            ecosystem: ecosystem.to_string(),
+            package_name: package.to_string(),
+            replacement_package: Some("replacement".to_string()),
+            manifests: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_affected_imports() {
+        let npm = request(
+            "npm",
+            "babel-core",
+            &[
+                ("src/a.js", "const babel = require('babel-core');"),
+                (
+                    "src/b.ts",
+                    "import { transform } from \"babel-core/lib/api\";",
+                ),
+                ("src/c.ts", "import x from \"babel-core-extra\";"),
+                ("README.md", "require('babel-core')"),
+            ],
+        );
+        assert_eq!(affected_imports(&npm), ["src/a.js", "src/b.ts"]);
+
+        let cargo = request(
+            "cargo",
+            "structopt",
+            &[
+                ("src/main.rs", "use structopt::StructOpt;\n"),
+                ("src/cli.rs", "#[derive(structopt::StructOpt)]\n"),
+                ("src/lib.rs", "use my_structopt::Thing;\n"),
+            ],
+        );
+        assert_eq!(affected_imports(&cargo), ["src/cli.rs", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&request("npm", "babel-core", &[])).is_ok());
+        assert!(validate(&request("pip", "pycrypto", &[])).is_err());
+        let mut same = request("npm", "babel-core", &[]);
+        same.replacement_package = Some("babel-core".to_string());
+        assert!(validate(&same).is_err());
+    }
+}
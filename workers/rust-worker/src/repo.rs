@@ -0,0 +1,1023 @@
+use crate::credentials::{GitCredentials, RepositoryCredentials};
+use crate::ecosystems::{gitlink, glob_match};
+use crate::lockfile::is_safe_path;
+use crate::scm::remote_host;
+use crate::{Change, ChangeType, ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker};
+use git2::build::CheckoutBuilder;
+use git2::{
+    AutotagOption, Cred, CredentialType, Direction, FetchOptions, ObjectType, PushOptions,
+    RemoteCallbacks, Repository, Tree, TreeWalkMode, TreeWalkResult,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use walkdir::WalkDir;
+
+/// Directories never read from a checkout: VCS metadata, installed
+/// dependencies and build output.
+const SKIPPED_DIRECTORIES: &[&str] = &[
+    ".git",
+    ".terraform",
+    ".venv",
+    "__pycache__",
+    "node_modules",
+    "target",
+    "vendor",
+];
+
+/// Files larger than this (bundles, fixtures, binaries) are not read.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Files checked out by a sparse checkout without `include` globs: the
+/// manifests, lockfiles and configuration the ecosystems read.
+pub const MANIFEST_GLOBS: &[&str] = &[
+    "**/.eslintignore",
+    "**/.eslintrc*",
+    "**/.github/workflows/*.yaml",
+    "**/.github/workflows/*.yml",
+    "**/.terraform.lock.hcl",
+    "**/.yarnrc.yml",
+    "**/*.csproj",
+    "**/*.fsproj",
+    "**/*.gemspec",
+    "**/*.tf",
+    "**/*.vbproj",
+    "**/Cargo.lock",
+    "**/Cargo.toml",
+    "**/Chart.lock",
+    "**/Chart.yaml",
+    "**/Directory.Packages.props",
+    "**/Dockerfile*",
+    "**/Gemfile",
+    "**/Gemfile.lock",
+    "**/Package.resolved",
+    "**/Package.swift",
+    "**/Pipfile",
+    "**/Pipfile.lock",
+    "**/Podfile",
+    "**/Podfile.lock",
+    "**/build.gradle",
+    "**/build.gradle.kts",
+    "**/composer.json",
+    "**/composer.lock",
+    "**/conanfile.py",
+    "**/conanfile.txt",
+    "**/deno.json",
+    "**/deno.jsonc",
+    "**/deno.lock",
+    "**/environment.yaml",
+    "**/environment.yml",
+    "**/eslint.config.*",
+    "**/go.mod",
+    "**/go.sum",
+    "**/import_map.json",
+    "**/lake-manifest.json",
+    "**/lakefile.lean",
+    "**/lakefile.toml",
+    "**/lean-toolchain",
+    "**/libs.versions.toml",
+    "**/mix.exs",
+    "**/mix.lock",
+    "**/package-lock.json",
+    "**/package.json",
+    "**/pnpm-lock.yaml",
+    "**/pnpm-workspace.yaml",
+    "**/poetry.lock",
+    "**/pom.xml",
+    "**/pubspec.lock",
+    "**/pubspec.yaml",
+    "**/pyproject.toml",
+    "**/requirements*.in",
+    "**/requirements*.lock",
+    "**/requirements*.txt",
+    "**/requirements.yaml",
+    "**/rust-toolchain",
+    "**/rust-toolchain.toml",
+    "**/vcpkg.json",
+    "**/yarn.lock",
+];
+
+// Local ref the requested branch, tag or commit is fetched into
+const CHECKOUT_REF: &str = "refs/remotes/origin/speccursor-checkout";
+
+// Local ref the head of the default branch is fetched into
+const DEFAULT_BRANCH_REF: &str = "refs/remotes/origin/speccursor-default";
+
+/// How `request.repository` is cloned when [`crate::WorkerConfig::checkout`]
+/// is set.
+#[derive(Debug, Clone)]
+pub struct CheckoutConfig {
+    /// Commits of history to fetch; `0` fetches all of it.
+    pub depth: u32,
+    /// Branch, tag, full ref or commit SHA to check out; the remote's
+    /// default branch when unset.
+    pub reference: Option<String>,
+    /// Token for HTTPS remotes without [`CheckoutConfig::credentials`].
+    /// SSH remotes authenticate through the agent.
+    pub token: Option<String>,
+    /// Credentials per repository, the first matching one winning.
+    pub credentials: Vec<RepositoryCredentials>,
+    /// Where checkouts are created; the system temp directory when unset.
+    pub directory: Option<PathBuf>,
+    /// Leave checkouts on disk once the upgrade is processed.
+    pub keep: bool,
+    /// Only write files matching `include` (or [`MANIFEST_GLOBS`] when it
+    /// is empty) to the working tree. History is still fetched in full up
+    /// to `depth`, but large repositories check out in a fraction of the
+    /// time.
+    pub sparse: bool,
+    /// Globs (`services/*/package.json`, `crates/**`) a sparse checkout is
+    /// limited to.
+    pub include: Vec<String>,
+    /// Globs (`legacy/**`, `**/testdata/**`) left out of manifest discovery
+    /// next to [`SKIPPED_DIRECTORIES`] and gitignored paths, in checkouts
+    /// and local workspaces alike.
+    pub exclude: Vec<String>,
+}
+
+impl Default for CheckoutConfig {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            reference: None,
+            token: None,
+            credentials: Vec::new(),
+            directory: None,
+            keep: false,
+            sparse: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// A working tree of the requested repository, removed on drop unless
+/// [`CheckoutConfig::keep`] is set.
+pub struct Checkout {
+    pub path: PathBuf,
+    /// Commit the working tree is at; empty for a local workspace that is
+    /// not a git repository.
+    pub commit: String,
+    /// A working tree of the caller's, edited in place.
+    pub local: bool,
+    _directory: Option<tempfile::TempDir>,
+}
+
+fn checkout_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Network,
+    }
+}
+
+fn internal_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Internal,
+    }
+}
+
+/// The git URL for `repository`: `owner/repo` is shorthand for GitHub over
+/// HTTPS, anything else (HTTPS, SSH, paths) is used as given.
+pub fn remote_url(repository: &str) -> String {
+    let shorthand = repository.matches('/').count() == 1
+        && !repository.contains(':')
+        && !repository.starts_with(['.', '/']);
+    if shorthand {
+        format!(
+            "https://github.com/{}.git",
+            repository.trim_end_matches(".git")
+        )
+    } else {
+        repository.to_string()
+    }
+}
+
+/// Refuses to clone `url` unless it is an HTTPS or SSH URL on one of
+/// `hosts`. Local paths and `file://` URLs would read outside
+/// [`crate::WorkerConfig::local_roots`].
+pub fn check_remote(url: &str, hosts: &[String]) -> Result<(), UpgradeError> {
+    let transport = url.starts_with("https://")
+        || url.starts_with("ssh://")
+        || !url.contains("://") && url.contains('@');
+    let allowed = transport
+        && remote_host(url).is_some_and(|host| {
+            hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&host))
+        });
+    if allowed {
+        return Ok(());
+    }
+    Err(UpgradeError {
+        message: format!(
+            "Refusing to check out {}: only `owner/repo` shorthand and HTTPS or SSH URLs on {} are cloned",
+            url,
+            hosts.join(", ")
+        ),
+        error_type: ErrorType::Validation,
+    })
+}
+
+fn callbacks(credentials: GitCredentials) -> RemoteCallbacks<'static> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |_url, username, allowed| {
+        let username = username.unwrap_or("git");
+        match &credentials {
+            GitCredentials::SshKey {
+                private_key,
+                passphrase,
+            } if allowed.contains(CredentialType::SSH_KEY) => {
+                Cred::ssh_key_from_memory(username, None, private_key, passphrase.as_deref())
+            }
+            _ if allowed.contains(CredentialType::SSH_KEY) => Cred::ssh_key_from_agent(username),
+            GitCredentials::Token { username, token }
+                if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) =>
+            {
+                Cred::userpass_plaintext(username, token)
+            }
+            _ => Cred::default(),
+        }
+    });
+    callbacks
+}
+
+/// Fetches `config.reference` of `url` into a new directory and checks it
+/// out, authenticating with `credentials`.
+pub fn checkout(
+    url: &str,
+    config: &CheckoutConfig,
+    credentials: &GitCredentials,
+) -> Result<Checkout, UpgradeError> {
+    let root = config.directory.clone().unwrap_or_else(std::env::temp_dir);
+    let io_error = |e: std::io::Error| {
+        internal_error(format!(
+            "Failed to create a checkout in {}: {}",
+            root.display(),
+            e
+        ))
+    };
+    std::fs::create_dir_all(&root).map_err(io_error)?;
+    let (path, directory) = if config.keep {
+        let path = root.join(format!("speccursor-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).map_err(io_error)?;
+        (path, None)
+    } else {
+        let directory = tempfile::Builder::new()
+            .prefix("speccursor-")
+            .tempdir_in(&root)
+            .map_err(io_error)?;
+        (directory.path().to_path_buf(), Some(directory))
+    };
+
+    let git_error = |e: git2::Error| checkout_error(format!("Failed to check out {}: {}", url, e));
+    let repo = Repository::init(&path).map_err(git_error)?;
+    let mut remote = repo.remote("origin", url).map_err(git_error)?;
+
+    let source = {
+        let connection = remote
+            .connect_auth(Direction::Fetch, Some(callbacks(credentials.clone())), None)
+            .map_err(git_error)?;
+        let heads = connection.list().map_err(git_error)?;
+        select_ref(
+            heads.iter().map(|head| (head.name(), head.symref_target())),
+            config.reference.as_deref(),
+        )
+        .ok_or_else(|| {
+            checkout_error(format!(
+                "{} has no branch, tag or ref named '{}'",
+                url,
+                config.reference.as_deref().unwrap_or("HEAD")
+            ))
+        })?
+    };
+
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(callbacks(credentials.clone()))
+        .download_tags(AutotagOption::None);
+    if config.depth > 0 {
+        options.depth(config.depth as i32);
+    }
+    remote
+        .fetch(
+            &[format!("+{}:{}", source, CHECKOUT_REF)],
+            Some(&mut options),
+            None,
+        )
+        .map_err(git_error)?;
+
+    let commit = repo
+        .find_reference(CHECKOUT_REF)
+        .and_then(|reference| reference.peel_to_commit())
+        .map_err(git_error)?;
+    let mut builder = CheckoutBuilder::new();
+    builder.force();
+    let mut skip_checkout = false;
+    if config.sparse {
+        let tree = commit.tree().map_err(git_error)?;
+        let paths = sparse_paths(&tree, &config.include, &config.exclude);
+        // A checkout without paths would write the whole tree
+        skip_checkout = paths.is_empty();
+        builder.disable_pathspec_match(true);
+        for path in &paths {
+            builder.path(path);
+        }
+    }
+    if !skip_checkout {
+        repo.checkout_tree(commit.as_object(), Some(&mut builder))
+            .map_err(git_error)?;
+    }
+    repo.set_head_detached(commit.id()).map_err(git_error)?;
+
+    Ok(Checkout {
+        path,
+        commit: commit.id().to_string(),
+        local: false,
+        _directory: directory,
+    })
+}
+
+/// The working tree at `path`, which must lie under one of `roots`.
+pub fn open_local(path: &Path, roots: &[PathBuf]) -> Result<Checkout, UpgradeError> {
+    let refused = |reason: &str| UpgradeError {
+        message: format!("Local workspace {} {}", path.display(), reason),
+        error_type: ErrorType::Validation,
+    };
+    // Resolving links and `..` first keeps the workspace from escaping
+    let path = path.canonicalize().map_err(|_| refused("does not exist"))?;
+    let allowed = roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| path.starts_with(root));
+    if !allowed {
+        return Err(refused("is not under an allowed root"));
+    }
+    if !path.is_dir() {
+        return Err(refused("is not a directory"));
+    }
+
+    let commit = Repository::open(&path)
+        .and_then(|repo| Ok(repo.head()?.peel_to_commit()?.id().to_string()))
+        .unwrap_or_default();
+    Ok(Checkout {
+        path,
+        commit,
+        local: true,
+        _directory: None,
+    })
+}
+
+/// Writes `changes` to the working tree at `root`. Lockfiles still waiting
+/// for their refresh command and submodule commits are left out.
+pub fn write_changes(root: &Path, changes: &[Change]) -> Result<(), UpgradeError> {
+    for change in changes.iter().filter(|change| {
+        !change.metadata.contains_key("lockfile_refresh") && gitlink(change).is_none()
+    }) {
+        if !is_safe_path(&change.file_path) || through_symlink(root, &change.file_path) {
+            return Err(UpgradeError {
+                message: format!(
+                    "Refusing to write outside the workspace: {}",
+                    change.file_path
+                ),
+                error_type: ErrorType::Validation,
+            });
+        }
+        let io_error = |e: std::io::Error| {
+            internal_error(format!("Failed to write {}: {}", change.file_path, e))
+        };
+        let path = root.join(&change.file_path);
+        if matches!(change.change_type, ChangeType::Delete) {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(io_error(e)),
+                _ => {}
+            }
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(io_error)?;
+            }
+            std::fs::write(&path, &change.content).map_err(io_error)?;
+        }
+    }
+    Ok(())
+}
+
+// Whether `relative`, or a directory leading to it, is a symlink below
+// `root`; writing through it could land anywhere
+fn through_symlink(root: &Path, relative: &str) -> bool {
+    let mut path = root.to_path_buf();
+    relative.split('/').any(|component| {
+        path.push(component);
+        std::fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+    })
+}
+
+/// Pushes `branch` of the checkout at `path` to its `origin`, replacing the
+/// remote branch if it exists.
+pub fn push(path: &Path, branch: &str, credentials: &GitCredentials) -> Result<(), UpgradeError> {
+    let git_error = |e: git2::Error| checkout_error(format!("Failed to push {}: {}", branch, e));
+    let repo = Repository::open(path).map_err(git_error)?;
+    let mut remote = repo.find_remote("origin").map_err(git_error)?;
+
+    let rejection = std::cell::RefCell::new(None);
+    let mut callbacks = callbacks(credentials.clone());
+    callbacks.push_update_reference(|_reference, status| {
+        *rejection.borrow_mut() = status.map(str::to_string);
+        Ok(())
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    remote
+        .push(
+            &[format!("+refs/heads/{0}:refs/heads/{0}", branch)],
+            Some(&mut options),
+        )
+        .map_err(git_error)?;
+    drop(options);
+
+    match rejection.into_inner() {
+        Some(status) => Err(checkout_error(format!(
+            "The remote rejected {}: {}",
+            branch, status
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Fetches the default branch of the checkout's `origin` and returns the
+/// commit at its head, or `None` when the working tree has no `origin`.
+pub fn fetch_default_branch(
+    path: &Path,
+    credentials: &GitCredentials,
+) -> Result<Option<String>, UpgradeError> {
+    let git_error =
+        |e: git2::Error| checkout_error(format!("Failed to fetch the default branch: {}", e));
+    let repo = Repository::open(path).map_err(git_error)?;
+    let Ok(mut remote) = repo.find_remote("origin") else {
+        return Ok(None);
+    };
+
+    let head = {
+        let connection = remote
+            .connect_auth(Direction::Fetch, Some(callbacks(credentials.clone())), None)
+            .map_err(git_error)?;
+        let heads = connection.list().map_err(git_error)?;
+        select_ref(
+            heads.iter().map(|head| (head.name(), head.symref_target())),
+            None,
+        )
+        .ok_or_else(|| checkout_error("The remote has no default branch".to_string()))?
+    };
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(callbacks(credentials.clone()))
+        .download_tags(AutotagOption::None);
+    // Deepening a shallow checkout would fetch the branch's whole history
+    if repo.is_shallow() {
+        options.depth(1);
+    }
+    remote
+        .fetch(
+            &[format!("+{}:{}", head, DEFAULT_BRANCH_REF)],
+            Some(&mut options),
+            None,
+        )
+        .map_err(git_error)?;
+    let commit = repo
+        .find_reference(DEFAULT_BRANCH_REF)
+        .and_then(|reference| reference.peel_to_commit())
+        .map_err(git_error)?;
+    Ok(Some(commit.id().to_string()))
+}
+
+/// Paths of the files in `tree` matching `include`, or [`MANIFEST_GLOBS`]
+/// when no globs are given, that [`is_excluded`] keeps.
+fn sparse_paths(tree: &Tree, include: &[String], exclude: &[String]) -> Vec<String> {
+    let patterns: Vec<&str> = if include.is_empty() {
+        MANIFEST_GLOBS.to_vec()
+    } else {
+        include.iter().map(String::as_str).collect()
+    };
+    let mut paths = Vec::new();
+    let _ = tree.walk(TreeWalkMode::PreOrder, |directory, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            let path = format!("{}{}", directory, entry.name().unwrap_or_default());
+            if patterns.iter().any(|pattern| glob_match(pattern, &path))
+                && !is_excluded(&path, exclude)
+            {
+                paths.push(path);
+            }
+        }
+        TreeWalkResult::Ok
+    });
+    paths
+}
+
+/// Picks the ref to fetch among those the remote advertises: the target of
+/// `HEAD` when no reference is requested, otherwise a full ref, branch or
+/// tag of that name. Commit SHAs are fetched as they are.
+fn select_ref<'a>(
+    mut heads: impl Iterator<Item = (&'a str, Option<&'a str>)> + Clone,
+    reference: Option<&str>,
+) -> Option<String> {
+    let Some(reference) = reference else {
+        return heads
+            .find(|(name, _)| *name == "HEAD")
+            .map(|(name, target)| target.unwrap_or(name).to_string());
+    };
+    let candidates = [
+        reference.to_string(),
+        format!("refs/heads/{}", reference),
+        format!("refs/tags/{}", reference),
+    ];
+    for candidate in &candidates {
+        if heads.clone().any(|(name, _)| name == candidate) {
+            return Some(candidate.clone());
+        }
+    }
+    let is_commit = reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit());
+    is_commit.then(|| reference.to_string())
+}
+
+/// Whether manifest discovery leaves `path` (relative, `/`-separated) out:
+/// it lies in one of [`SKIPPED_DIRECTORIES`] or matches an `exclude` glob.
+pub fn is_excluded(path: &str, exclude: &[String]) -> bool {
+    path.split('/')
+        .any(|component| SKIPPED_DIRECTORIES.contains(&component))
+        || exclude.iter().any(|pattern| glob_match(pattern, path))
+}
+
+/// Text files of a working tree keyed by their `/`-separated path relative
+/// to `root`, leaving out [`SKIPPED_DIRECTORIES`], paths git ignores, paths
+/// matching `exclude` and large or binary files.
+pub fn read_files(root: &Path, exclude: &[String]) -> HashMap<String, String> {
+    // Ignored paths are build output and installed or vendored copies
+    let repo = Repository::open(root).ok();
+    let entries = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        let Some(relative) = relative_path(root, entry.path()) else {
+            return true;
+        };
+        let ignored = repo
+            .as_ref()
+            .is_some_and(|repo| repo.is_path_ignored(&relative).unwrap_or(false));
+        !(ignored || is_excluded(&relative, exclude))
+    });
+
+    let mut files = HashMap::new();
+    for entry in entries.filter_map(Result::ok) {
+        if !entry.file_type().is_file()
+            || entry
+                .metadata()
+                .map_or(true, |metadata| metadata.len() > MAX_FILE_SIZE)
+        {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let Some(relative) = relative_path(root, entry.path()) else {
+            continue;
+        };
+        files.insert(relative, content);
+    }
+    files
+}
+
+// `/`-separated path of `path` below `root`; `None` for `root` itself
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative: Vec<_> = path
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    (!relative.is_empty()).then(|| relative.join("/"))
+}
+
+impl UpgradeWorker {
+    /// Opens the requested local workspace, or clones the requested
+    /// repository when checkouts are enabled, and replaces the supplied
+    /// files with its actual content. The checkout is returned so that it
+    /// outlives the upgrade.
+    pub(crate) async fn load_repository(
+        &self,
+        request: &mut UpgradeRequest,
+    ) -> Result<Option<Checkout>, UpgradeError> {
+        if let Some(path) = &request.local_path {
+            let checkout = open_local(Path::new(path), &self.config.local_roots)?;
+            let exclude = match &self.config.checkout {
+                Some(config) => config.exclude.as_slice(),
+                None => &[],
+            };
+            request.manifests = read_files(&checkout.path, exclude);
+            return Ok(Some(checkout));
+        }
+        let Some(config) = self.config.checkout.clone() else {
+            return Ok(None);
+        };
+        let timeout = Duration::from_secs(self.config.max_execution_time);
+        // The configured provider knows its host and can lend its token
+        let provider = self
+            .config
+            .scm
+            .as_ref()
+            .map(|scm| crate::scm::provider_for(scm, timeout));
+        let url = match &provider {
+            Some(provider) => provider.remote_url(&request.repository),
+            None => remote_url(&request.repository),
+        };
+        let mut hosts = vec![provider
+            .as_ref()
+            .map_or_else(|| "github.com".to_string(), |provider| provider.host())];
+        hosts.extend(
+            config
+                .credentials
+                .iter()
+                .filter_map(|entry| entry.host.clone()),
+        );
+        check_remote(&url, &hosts)?;
+        let credentials = self
+            .git_credentials(&request.repository, provider.as_deref())
+            .await?;
+        let url = credentials.remote_url(&url);
+
+        let task = tokio::task::spawn_blocking(move || {
+            let checkout = checkout(&url, &config, &credentials)?;
+            let files = read_files(&checkout.path, &config.exclude);
+            Ok::<_, UpgradeError>((checkout, files))
+        });
+        let (checkout, files) = tokio::time::timeout(timeout, task)
+            .await
+            .map_err(|_| {
+                checkout_error(format!(
+                    "Checking out {} timed out after {}s",
+                    request.repository,
+                    timeout.as_secs()
+                ))
+            })?
+            .map_err(|e| internal_error(format!("Checkout task failed: {}", e)))??;
+
+        request.manifests = files;
+        Ok(Some(checkout))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use git2::Signature;
+
+    // A repository with one commit on `main`, tagged `v1`
+    pub(crate) fn source_repository(root: &Path) -> String {
+        let repo = Repository::init(root).unwrap();
+        for (path, content) in [
+            (
+                "package.json",
+                r#"{"dependencies": {"lodash": "^4.17.20"}}"#,
+            ),
+            ("node_modules/lodash/package.json", "{}"),
+            ("apps/web/package.json", "{}"),
+            ("apps/web/src/index.js", "import _ from 'lodash';"),
+        ] {
+            let file = root.join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        }
+
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let author = Signature::now("Test", "test@example.com").unwrap();
+        let commit = repo
+            .commit(
+                Some("refs/heads/main"),
+                &author,
+                &author,
+                "Initial",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        repo.tag_lightweight("v1", &repo.find_object(commit, None).unwrap(), false)
+            .unwrap();
+        commit.to_string()
+    }
+
+    #[test]
+    fn test_remote_url() {
+        assert_eq!(remote_url("acme/app"), "https://github.com/acme/app.git");
+        assert_eq!(
+            remote_url("git@gitlab.example.com:acme/app.git"),
+            "git@gitlab.example.com:acme/app.git"
+        );
+        assert_eq!(
+            remote_url("https://bitbucket.org/acme/app"),
+            "https://bitbucket.org/acme/app"
+        );
+    }
+
+    #[test]
+    fn test_check_remote() {
+        let hosts = ["github.com".to_string(), "git.example.com".to_string()];
+        for allowed in [
+            "https://github.com/acme/app.git",
+            "https://GitHub.com/acme/app",
+            "ssh://git@git.example.com:7999/acme/app.git",
+            "git@git.example.com:acme/app.git",
+        ] {
+            assert!(check_remote(allowed, &hosts).is_ok(), "{}", allowed);
+        }
+        for refused in [
+            "/srv/repos/app",
+            "../app",
+            "acme/sub/app",
+            "file:///srv/repos/app",
+            "http://github.com/acme/app.git",
+            "git://github.com/acme/app.git",
+            "ext::sh -c touch% /tmp/pwned",
+            "https://attacker.example/acme/app.git",
+            "git@attacker.example:acme/app.git",
+        ] {
+            let err = check_remote(refused, &hosts).unwrap_err();
+            assert!(
+                matches!(err.error_type, ErrorType::Validation),
+                "{}",
+                refused
+            );
+        }
+    }
+
+    #[test]
+    fn test_checkout_reads_repository_files() {
+        let source = tempfile::tempdir().unwrap();
+        let commit = source_repository(source.path());
+        let checkouts = tempfile::tempdir().unwrap();
+        let url = source.path().to_string_lossy().to_string();
+
+        for reference in [None, Some("main"), Some("v1"), Some(commit.as_str())] {
+            let config = CheckoutConfig {
+                depth: 0,
+                reference: reference.map(str::to_string),
+                directory: Some(checkouts.path().to_path_buf()),
+                ..CheckoutConfig::default()
+            };
+            let checkout = checkout(&url, &config, &GitCredentials::Default).unwrap();
+            assert_eq!(checkout.commit, commit);
+
+            let files = read_files(&checkout.path, &[]);
+            let mut paths: Vec<&String> = files.keys().collect();
+            paths.sort();
+            assert_eq!(
+                paths,
+                [
+                    "apps/web/package.json",
+                    "apps/web/src/index.js",
+                    "package.json"
+                ]
+            );
+            assert!(files["package.json"].contains("lodash"));
+        }
+        // Temporary checkouts are removed on drop
+        assert_eq!(std::fs::read_dir(checkouts.path()).unwrap().count(), 0);
+
+        let missing = CheckoutConfig {
+            depth: 0,
+            reference: Some("release".to_string()),
+            directory: Some(checkouts.path().to_path_buf()),
+            ..CheckoutConfig::default()
+        };
+        let err = checkout(&url, &missing, &GitCredentials::Default)
+            .err()
+            .unwrap();
+        assert!(err
+            .message
+            .contains("no branch, tag or ref named 'release'"));
+    }
+
+    #[test]
+    fn test_checkout_symlinks_are_not_written_through() {
+        let source = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("package.json"), "{}").unwrap();
+        std::os::unix::fs::symlink(outside.path(), source.path().join("vendor")).unwrap();
+        source_repository(source.path());
+        let checkouts = tempfile::tempdir().unwrap();
+        let config = CheckoutConfig {
+            depth: 0,
+            directory: Some(checkouts.path().to_path_buf()),
+            ..CheckoutConfig::default()
+        };
+        let url = source.path().to_string_lossy().to_string();
+        let checkout = checkout(&url, &config, &GitCredentials::Default).unwrap();
+        assert!(checkout.path.join("vendor").is_symlink());
+        assert!(!read_files(&checkout.path, &[]).contains_key("vendor/package.json"));
+
+        let change = Change {
+            file_path: "vendor/package.json".to_string(),
+            change_type: ChangeType::Modify,
+            content: r#"{"dependencies": {"lodash": "4.17.21"}}"#.to_string(),
+            metadata: HashMap::new(),
+        };
+        let err = write_changes(&checkout.path, &[change]).unwrap_err();
+        assert!(err
+            .message
+            .starts_with("Refusing to write outside the workspace"));
+        assert_eq!(
+            std::fs::read_to_string(outside.path().join("package.json")).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_push_branch() {
+        let source = tempfile::tempdir().unwrap();
+        let base = source_repository(source.path());
+        let url = source.path().to_string_lossy().to_string();
+        let config = CheckoutConfig {
+            depth: 0,
+            ..CheckoutConfig::default()
+        };
+        let checkout = checkout(&url, &config, &GitCredentials::Default).unwrap();
+
+        // Pushes need a bare remote
+        let remote = tempfile::tempdir().unwrap();
+        Repository::init_bare(remote.path()).unwrap();
+        let repo = Repository::open(&checkout.path).unwrap();
+        repo.remote_set_url("origin", &remote.path().to_string_lossy())
+            .unwrap();
+        let commit = repo
+            .find_commit(git2::Oid::from_str(&base).unwrap())
+            .unwrap();
+        repo.branch("speccursor/lodash-4.17.21", &commit, false)
+            .unwrap();
+        push(
+            &checkout.path,
+            "speccursor/lodash-4.17.21",
+            &GitCredentials::Default,
+        )
+        .unwrap();
+
+        let pushed = Repository::open(remote.path())
+            .unwrap()
+            .find_reference("refs/heads/speccursor/lodash-4.17.21")
+            .unwrap()
+            .target()
+            .unwrap();
+        assert_eq!(pushed.to_string(), base);
+        assert!(push(&checkout.path, "missing", &GitCredentials::Default).is_err());
+    }
+
+    #[test]
+    fn test_sparse_checkout() {
+        let source = tempfile::tempdir().unwrap();
+        source_repository(source.path());
+        let url = source.path().to_string_lossy().to_string();
+        let sparse = |include: &[&str]| {
+            let config = CheckoutConfig {
+                depth: 0,
+                sparse: true,
+                include: include.iter().map(|glob| glob.to_string()).collect(),
+                ..CheckoutConfig::default()
+            };
+            let checkout = checkout(&url, &config, &GitCredentials::Default).unwrap();
+            let mut paths: Vec<String> = read_files(&checkout.path, &[]).into_keys().collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(sparse(&[]), ["apps/web/package.json", "package.json"]);
+        assert_eq!(
+            sparse(&["apps/**"]),
+            ["apps/web/package.json", "apps/web/src/index.js"]
+        );
+        assert!(sparse(&["docs/**"]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_workspace() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = root.path().join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        let base = source_repository(&workspace);
+        let worker = UpgradeWorker::new(Some(crate::WorkerConfig {
+            local_roots: vec![root.path().to_path_buf()],
+            ..crate::WorkerConfig::default()
+        }));
+        let request = UpgradeRequest {
+            repository: "acme/app".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.20".to_string(),
+            target_version: "4.17.21".to_string(),
+            local_path: Some(workspace.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let response = worker.process_upgrade(request.clone()).await.unwrap();
+        assert_eq!(response.base_commit, Some(base));
+        let manifest = std::fs::read_to_string(workspace.join("package.json")).unwrap();
+        assert!(manifest.contains("^4.17.21"));
+
+        // Only the workspace's own files are edited, never through a symlink
+        let outside = tempfile::tempdir().unwrap();
+        let outside_manifest = r#"{"dependencies": {"lodash": "4.17.20"}}"#;
+        std::fs::write(outside.path().join("package.json"), outside_manifest).unwrap();
+        std::os::unix::fs::symlink(outside.path(), workspace.join("link")).unwrap();
+        let supplied = UpgradeRequest {
+            target_version: "4.17.22".to_string(),
+            manifests: HashMap::from([(
+                "link/package.json".to_string(),
+                outside_manifest.to_string(),
+            )]),
+            ..request.clone()
+        };
+        let response = worker.process_upgrade(supplied).await.unwrap();
+        assert!(response
+            .changes
+            .iter()
+            .all(|change| change.file_path != "link/package.json"));
+        assert_eq!(
+            std::fs::read_to_string(outside.path().join("package.json")).unwrap(),
+            outside_manifest
+        );
+        let through_link = Change {
+            file_path: "link/package.json".to_string(),
+            change_type: ChangeType::Modify,
+            content: "{}".to_string(),
+            metadata: HashMap::new(),
+        };
+        let err = write_changes(&workspace, &[through_link]).unwrap_err();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+        assert_eq!(
+            std::fs::read_to_string(outside.path().join("package.json")).unwrap(),
+            outside_manifest
+        );
+        std::fs::remove_file(workspace.join("link")).unwrap();
+
+        // Workspaces must lie under an allowed root
+        let escape = UpgradeRequest {
+            local_path: Some(format!("{}/../..", workspace.display())),
+            ..request.clone()
+        };
+        let err = worker.process_upgrade(escape).await.err().unwrap();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+        let disabled = UpgradeWorker::new(None);
+        assert!(disabled.process_upgrade(request.clone()).await.is_err());
+
+        // Cloning the workspace would skip the `local_roots` check
+        let cloning = UpgradeWorker::new(Some(crate::WorkerConfig {
+            checkout: Some(CheckoutConfig::default()),
+            ..crate::WorkerConfig::default()
+        }));
+        for repository in [
+            workspace.display().to_string(),
+            format!("file://{}", workspace.display()),
+        ] {
+            let clone = UpgradeRequest {
+                repository,
+                local_path: None,
+                ..request.clone()
+            };
+            let err = cloning.process_upgrade(clone).await.err().unwrap();
+            assert!(matches!(err.error_type, ErrorType::Validation));
+            assert!(err.message.starts_with("Refusing to check out"));
+        }
+    }
+
+    #[test]
+    fn test_discovery_exclusions() {
+        let root = tempfile::tempdir().unwrap();
+        Repository::init(root.path()).unwrap();
+        for (path, content) in [
+            (".gitignore", "dist/\n*.generated.json\n"),
+            ("package.json", "{}"),
+            ("apps/web/package.json", "{}"),
+            ("apps/web/schema.generated.json", "{}"),
+            ("dist/package.json", "{}"),
+            ("node_modules/lodash/package.json", "{}"),
+            (
+                "go/vendor/golang.org/x/net/go.mod",
+                "module golang.org/x/net",
+            ),
+            ("legacy/package.json", "{}"),
+        ] {
+            let path = root.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let mut paths: Vec<String> = read_files(root.path(), &["legacy/**".to_string()])
+            .into_keys()
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [".gitignore", "apps/web/package.json", "package.json"]
+        );
+        assert!(is_excluded("crates/app/target/Cargo.toml", &[]));
+        assert!(!is_excluded("crates/target-spec/Cargo.toml", &[]));
+    }
+}
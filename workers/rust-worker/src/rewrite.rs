@@ -0,0 +1,167 @@
+use crate::version::ParsedVersion;
+use crate::DependencyKind;
+
+// Operators whose meaning carries over unchanged when the version they anchor
+// is replaced. Upper bounds and exclusions (`<`, `<=`, `>`, `!=`) do not.
+const PRESERVED_OPERATORS: &[&str] = &["===", "==", ">=", "~=", "~>", "=", "^", "~"];
+
+const NPM_DEPENDENCY_TABLES: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+const CARGO_DEPENDENCY_TABLES: &[&str] =
+    &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// `package.json` sections declaring dependencies of `kind`, all of them
+/// when unset. Empty for kinds npm does not have.
+pub fn npm_sections(kind: Option<DependencyKind>) -> &'static [&'static str] {
+    match kind {
+        None => NPM_DEPENDENCY_TABLES,
+        Some(DependencyKind::Normal) => &["dependencies"],
+        Some(DependencyKind::Dev) => &["devDependencies"],
+        Some(DependencyKind::Peer) => &["peerDependencies"],
+        Some(DependencyKind::Optional) => &["optionalDependencies"],
+        Some(DependencyKind::Build) => &[],
+    }
+}
+
+/// `Cargo.toml` tables declaring dependencies of `kind`, all of them when
+/// unset. Optional dependencies live in `[dependencies]`; Cargo has no
+/// peer dependencies.
+pub fn cargo_tables(kind: Option<DependencyKind>) -> &'static [&'static str] {
+    match kind {
+        None => CARGO_DEPENDENCY_TABLES,
+        Some(DependencyKind::Normal | DependencyKind::Optional) => &["dependencies"],
+        Some(DependencyKind::Dev) => &["dev-dependencies"],
+        Some(DependencyKind::Build) => &["build-dependencies"],
+        Some(DependencyKind::Peer) => &[],
+    }
+}
+
+/// Rewrites `declared` so that it points at `target` while keeping its style:
+/// the leading operator, wildcard precision (`1.x` -> `2.x`) and any build
+/// metadata. Compound ranges fall back to the ecosystem's default style.
+pub fn rewrite_requirement(ecosystem: &str, declared: &str, target: &ParsedVersion) -> String {
+    let declared = declared.trim();
+    let target_raw = target.as_str();
+
+    let (operator, version) = split_operator(declared);
+    let compound = version.contains("||")
+        || version.contains(',')
+        || version.contains(" - ")
+        || version.split_whitespace().count() > 1;
+    let preserved = operator.is_empty() || PRESERVED_OPERATORS.contains(&operator.trim_end());
+    if compound || !preserved {
+        return default_requirement(ecosystem, target);
+    }
+
+    if let Some(wildcard) = rewrite_wildcard(version, target) {
+        return format!("{}{}", operator, wildcard);
+    }
+
+    // Pessimistic operators bound the last written component (`~> 2.2`
+    // allows `< 3`, `~> 2.2.1` only `< 2.3`), so keep the precision
+    if is_pessimistic(ecosystem, operator.trim_end()) {
+        let precision = version
+            .split(['+', '-'])
+            .next()
+            .unwrap_or(version)
+            .split('.')
+            .count();
+        return format!("{}{}", operator, truncate(target, precision));
+    }
+
+    let build = match (version.split_once('+'), target_raw.contains('+')) {
+        (Some((_, metadata)), false) => format!("+{}", metadata),
+        _ => String::new(),
+    };
+
+    format!("{}{}{}", operator, target_raw, build)
+}
+
+fn default_requirement(ecosystem: &str, target: &ParsedVersion) -> String {
+    match ecosystem {
+        "npm" | "composer" | "poetry" | "deno" | "pub" => format!("^{}", target),
+        "pip" => format!("~={}", truncate(target, 2)),
+        "conda" => format!("={}", truncate(target, 2)),
+        "rubygems" | "cocoapods" | "hex" | "terraform" => format!("~> {}", truncate(target, 2)),
+        _ => target.to_string(),
+    }
+}
+
+// Conda's `=1.26` is a prefix match, so it bounds the last component too
+fn is_pessimistic(ecosystem: &str, operator: &str) -> bool {
+    matches!(operator, "~>" | "~=")
+        || operator == "~" && matches!(ecosystem, "composer" | "poetry")
+        || operator == "=" && ecosystem == "conda"
+}
+
+fn truncate(target: &ParsedVersion, precision: usize) -> String {
+    (0..precision.max(1))
+        .map(|i| target.component(i).to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Splits a single comparator into its operator (including any whitespace that
+// follows it) and the version text.
+fn split_operator(comparator: &str) -> (&str, &str) {
+    let version_start = comparator
+        .find(|c: char| c.is_ascii_alphanumeric() || c == '*')
+        .unwrap_or(comparator.len());
+    comparator.split_at(version_start)
+}
+
+fn rewrite_wildcard(version: &str, target: &ParsedVersion) -> Option<String> {
+    let components: Vec<&str> = version.split('.').collect();
+    let wildcard_at = components
+        .iter()
+        .position(|c| matches!(*c, "x" | "X" | "*"))?;
+    if wildcard_at == 0 {
+        return None;
+    }
+
+    let mut rewritten: Vec<String> = (0..wildcard_at)
+        .map(|i| target.component(i).to_string())
+        .collect();
+    rewritten.extend(components[wildcard_at..].iter().map(|c| c.to_string()));
+    Some(rewritten.join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+
+    fn rewrite(ecosystem: &str, declared: &str, target: &str) -> String {
+        rewrite_requirement(ecosystem, declared, &SemanticScheme.parse(target).unwrap())
+    }
+
+    #[test]
+    fn test_rewrite_preserves_style() {
+        assert_eq!(rewrite("npm", "^1.2.3", "2.0.0"), "^2.0.0");
+        assert_eq!(rewrite("npm", "~1.2.3", "1.3.0"), "~1.3.0");
+        assert_eq!(rewrite("npm", ">= 1.2.3", "2.0.0"), ">= 2.0.0");
+        assert_eq!(rewrite("npm", "1.x", "2.4.1"), "2.x");
+        assert_eq!(rewrite("npm", "1.2.x", "2.4.1"), "2.4.x");
+        assert_eq!(rewrite("npm", ">=1.0.0 <2.0.0", "2.1.0"), "^2.1.0");
+        assert_eq!(rewrite("npm", "<2.0.0", "2.1.0"), "^2.1.0");
+        assert_eq!(
+            rewrite("cargo", "=1.0.0+vendored", "1.1.0"),
+            "=1.1.0+vendored"
+        );
+        assert_eq!(
+            rewrite("cargo", "1.0.0+vendored", "1.1.0+upstream"),
+            "1.1.0+upstream"
+        );
+        assert_eq!(rewrite("cargo", ">=1.0, <2", "2.0.1"), "2.0.1");
+        assert_eq!(rewrite("rubygems", "~> 2.2", "3.0.8"), "~> 3.0");
+        assert_eq!(rewrite("pip", "~=1.4.2", "1.6.0"), "~=1.6.0");
+        assert_eq!(rewrite("composer", "~2.9", "3.5.0"), "~3.5");
+        assert_eq!(rewrite("pip", ">=2.28,<3", "3.1.0"), "~=3.1");
+        assert_eq!(rewrite("rubygems", ">= 0.18, < 2.0", "2.1.0"), "~> 2.1");
+    }
+}
@@ -0,0 +1,371 @@
+use crate::advisories::{native_packages, resolved_packages};
+use crate::version::{self, ParsedVersion, ResolvedVersions, VersionJump};
+use crate::{ecosystems, rename};
+use crate::{Change, DependencyKind, PerformanceImpact, RiskAssessment, RiskLevel, UpgradeRequest};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// What a rule may look at: the upgrade and the changes it makes.
+pub struct RiskContext<'a> {
+    pub request: &'a UpgradeRequest,
+    pub versions: &'a ResolvedVersions,
+    pub changes: &'a [Change],
+}
+
+/// One step of the risk assessment. Rules run in the order they were
+/// registered, each adjusting the assessment the previous ones left, before
+/// advisories, API diffs and release notes are consulted.
+pub trait RiskRule: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment);
+}
+
+/// The rules every worker starts with, in order.
+pub fn builtin_rules() -> Vec<Arc<dyn RiskRule>> {
+    vec![
+        Arc::new(VersionJumpRule),
+        Arc::new(BreakingRangeRule),
+        Arc::new(DependencyFootprintRule),
+        Arc::new(DevDependencyRule),
+        Arc::new(ReplacementRule),
+        Arc::new(EcosystemRule),
+    ]
+}
+
+pub fn is_major_version_jump(current: &ParsedVersion, target: &ParsedVersion) -> bool {
+    VersionJump::classify(current, target) == VersionJump::Major
+}
+
+/// Minor and pre-release bumps and downgrades are medium risk, major bumps
+/// high.
+pub struct VersionJumpRule;
+
+impl RiskRule for VersionJumpRule {
+    fn name(&self) -> &str {
+        "version_jump"
+    }
+
+    fn evaluate(&self, _context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        let level = match assessment.version_jump {
+            VersionJump::Major => RiskLevel::High,
+            VersionJump::Minor | VersionJump::Prerelease | VersionJump::Downgrade => {
+                RiskLevel::Medium
+            }
+            VersionJump::Patch | VersionJump::None => RiskLevel::Low,
+        };
+        assessment.risk_level = assessment.risk_level.max(level);
+    }
+}
+
+/// Targets outside the range the current version is declared with are
+/// breaking: caret ecosystems treat 0.x minor bumps as breaking, the others
+/// major bumps.
+pub struct BreakingRangeRule;
+
+impl RiskRule for BreakingRangeRule {
+    fn name(&self) -> &str {
+        "breaking_range"
+    }
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        let versions = context.versions;
+        let breaking = match context.request.ecosystem.as_str() {
+            "cargo" | "npm" | "composer" => {
+                version::breaks_caret_range(&versions.current, &versions.target)
+            }
+            _ => is_major_version_jump(&versions.current, &versions.target),
+        };
+        if breaking {
+            assessment.risk_level = assessment.risk_level.max(RiskLevel::High);
+            assessment.breaking_changes = true;
+        }
+    }
+}
+
+/// Impact of packages added to the resolution: up to 5 low, up to 20
+/// medium, more high.
+pub fn dependency_impact(added: usize) -> PerformanceImpact {
+    match added {
+        0 => PerformanceImpact::None,
+        1..=5 => PerformanceImpact::Low,
+        6..=20 => PerformanceImpact::Medium,
+        _ => PerformanceImpact::High,
+    }
+}
+
+/// Estimates the performance impact from the lockfiles the upgrade
+/// rewrites: the change in the number of resolved packages, and any added
+/// package that compiles native code, which is at least medium. Measured
+/// builds, when verification runs, replace the estimate.
+pub struct DependencyFootprintRule;
+
+impl RiskRule for DependencyFootprintRule {
+    fn name(&self) -> &str {
+        "dependency_footprint"
+    }
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        let (mut before_count, mut after_count) = (0, 0);
+        let mut native = BTreeSet::new();
+        for change in context.changes {
+            let path = change.file_path.as_str();
+            let Some(original) = context.request.manifests.get(path) else {
+                continue;
+            };
+            let (Some(before), Some(after)) = (
+                resolved_packages(path, original),
+                resolved_packages(path, &change.content),
+            ) else {
+                continue;
+            };
+            before_count += before.len();
+            after_count += after.len();
+            let already_native = native_packages(path, original);
+            native.extend(
+                native_packages(path, &change.content)
+                    .into_iter()
+                    .filter(|name| !already_native.contains(name)),
+            );
+        }
+
+        let added = after_count.saturating_sub(before_count);
+        let mut impact = dependency_impact(added);
+        if before_count != after_count {
+            assessment.explanations.push(format!(
+                "The lockfiles resolve {} packages instead of {} ({:+}): {:?} performance impact (up to 5 added packages is low, up to 20 medium, more high)",
+                after_count,
+                before_count,
+                after_count as i64 - before_count as i64,
+                impact
+            ));
+        }
+        if !native.is_empty() {
+            impact = impact.max(PerformanceImpact::Medium);
+            assessment.explanations.push(format!(
+                "The upgrade adds packages that compile native code ({}): at least Medium performance impact, as native builds lengthen clean builds",
+                native.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        assessment.performance_impact = assessment.performance_impact.max(impact);
+    }
+}
+
+/// Dev-only packages never ship, so breakage stays in CI: they are at most
+/// medium risk, short of critical.
+pub struct DevDependencyRule;
+
+impl RiskRule for DevDependencyRule {
+    fn name(&self) -> &str {
+        "dev_dependency"
+    }
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        let request = context.request;
+        if request.dependency_kind == Some(DependencyKind::Dev)
+            && assessment.risk_level < RiskLevel::Critical
+        {
+            assessment.risk_level = assessment.risk_level.min(RiskLevel::Medium);
+            assessment.explanations.push(format!(
+                "{} is a development dependency and is not shipped",
+                request.package_name
+            ));
+        }
+    }
+}
+
+/// Replacing a package breaks its imports.
+pub struct ReplacementRule;
+
+impl RiskRule for ReplacementRule {
+    fn name(&self) -> &str {
+        "replacement"
+    }
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        rename::assess_risk(context.request, assessment);
+    }
+}
+
+/// Ecosystem-specific rules, e.g. declared constraint semantics.
+pub struct EcosystemRule;
+
+impl RiskRule for EcosystemRule {
+    fn name(&self) -> &str {
+        "ecosystem"
+    }
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        if let Some(ecosystem) = ecosystems::ecosystem_for(&context.request.ecosystem) {
+            ecosystem.assess_risk(context.request, context.versions, assessment);
+        }
+    }
+}
+
+/// Raises upgrades of the listed packages to at least `level`, e.g. any
+/// upgrade of a crypto crate to high. Entries ending in `*` match package
+/// name prefixes.
+pub struct PackageRule {
+    pub name: String,
+    /// Every ecosystem when unset.
+    pub ecosystem: Option<String>,
+    pub packages: Vec<String>,
+    pub level: RiskLevel,
+    /// Recorded in the explanations when the rule raises the risk.
+    pub reason: String,
+}
+
+impl PackageRule {
+    pub fn new(name: &str, packages: &[&str], level: RiskLevel, reason: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ecosystem: None,
+            packages: packages.iter().map(|package| package.to_string()).collect(),
+            level,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn for_ecosystem(mut self, ecosystem: &str) -> Self {
+        self.ecosystem = Some(ecosystem.to_string());
+        self
+    }
+
+    fn matches(&self, request: &UpgradeRequest) -> bool {
+        if self
+            .ecosystem
+            .as_ref()
+            .is_some_and(|ecosystem| *ecosystem != request.ecosystem)
+        {
+            return false;
+        }
+        self.packages
+            .iter()
+            .any(|package| match package.strip_suffix('*') {
+                Some(prefix) => request.package_name.starts_with(prefix),
+                None => *package == request.package_name,
+            })
+    }
+}
+
+impl RiskRule for PackageRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn evaluate(&self, context: &RiskContext<'_>, assessment: &mut RiskAssessment) {
+        if self.matches(context.request) && assessment.risk_level < self.level {
+            assessment.risk_level = self.level;
+            assessment
+                .explanations
+                .push(format!("{}: {}", context.request.package_name, self.reason));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionScheme};
+    use crate::{ChangeType, UpgradeWorker};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_custom_rules_run_after_builtin_rules() {
+        let worker = UpgradeWorker::new(None).with_risk_rule(Arc::new(
+            PackageRule::new(
+                "crypto",
+                &["ring", "rustls*"],
+                RiskLevel::High,
+                "cryptography upgrades need a security review",
+            )
+            .for_ecosystem("cargo"),
+        ));
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("0.23.1").unwrap(),
+            target: SemanticScheme.parse("0.23.4").unwrap(),
+        };
+        let assess = |ecosystem: &str, package: &str| {
+            let request = UpgradeRequest {
+                ecosystem: ecosystem.to_string(),
+                package_name: package.to_string(),
+                ..Default::default()
+            };
+            worker.assess_risk(&request, &versions, &[]).unwrap()
+        };
+
+        let rustls = assess("cargo", "rustls-pemfile");
+        assert_eq!(rustls.risk_level, RiskLevel::High);
+        assert_eq!(
+            rustls.explanations,
+            ["rustls-pemfile: cryptography upgrades need a security review"]
+        );
+        assert_eq!(assess("cargo", "serde").risk_level, RiskLevel::Low);
+        assert_eq!(assess("npm", "ring").risk_level, RiskLevel::Low);
+    }
+
+    #[test]
+    fn test_dependency_footprint() {
+        let lockfile = |packages: &[(&str, bool)]| {
+            let entries: Vec<String> = packages
+                .iter()
+                .map(|(name, native)| {
+                    format!(
+                        r#""node_modules/{}": {{"version": "1.0.0", "hasInstallScript": {}}}"#,
+                        name, native
+                    )
+                })
+                .collect();
+            format!(r#"{{"packages": {{{}}}}}"#, entries.join(", "))
+        };
+        let request = UpgradeRequest {
+            ecosystem: "npm".to_string(),
+            package_name: "sqlite3".to_string(),
+            manifests: HashMap::from([(
+                "package-lock.json".to_string(),
+                lockfile(&[("sqlite3", false), ("ms", false)]),
+            )]),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("4.2.0").unwrap(),
+            target: SemanticScheme.parse("4.2.1").unwrap(),
+        };
+        let upgraded: Vec<(&str, bool)> = ["a", "b", "c", "d", "e", "f", "g", "ms"]
+            .iter()
+            .map(|name| (*name, false))
+            .chain([("sqlite3", true)])
+            .collect();
+        let changes = [Change {
+            file_path: "package-lock.json".to_string(),
+            change_type: ChangeType::Modify,
+            content: lockfile(&upgraded),
+            metadata: HashMap::new(),
+        }];
+
+        let mut assessment = UpgradeWorker::new(None)
+            .assess_risk(&request, &versions, &changes)
+            .unwrap();
+        assert_eq!(assessment.performance_impact, PerformanceImpact::Medium);
+        assert_eq!(
+            assessment.explanations,
+            [
+                "The lockfiles resolve 9 packages instead of 2 (+7): Medium performance impact (up to 5 added packages is low, up to 20 medium, more high)",
+                "The upgrade adds packages that compile native code (sqlite3): at least Medium performance impact, as native builds lengthen clean builds"
+            ]
+        );
+
+        assessment.performance_impact = PerformanceImpact::None;
+        DependencyFootprintRule.evaluate(
+            &RiskContext {
+                request: &request,
+                versions: &versions,
+                changes: &[],
+            },
+            &mut assessment,
+        );
+        assert_eq!(assessment.performance_impact, PerformanceImpact::None);
+        assert_eq!(dependency_impact(3), PerformanceImpact::Low);
+        assert_eq!(dependency_impact(21), PerformanceImpact::High);
+    }
+}
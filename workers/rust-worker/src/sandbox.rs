@@ -0,0 +1,227 @@
+use crate::UpgradeWorker;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Variables passed on to commands run on the host; tokens and credentials
+/// of the worker never reach build scripts.
+const PASSED_ENVIRONMENT: &[&str] = &[
+    "CARGO_HOME",
+    "GOMODCACHE",
+    "GOPATH",
+    "HOME",
+    "JAVA_HOME",
+    "LANG",
+    "PATH",
+    "RUSTUP_HOME",
+    "TMPDIR",
+];
+
+/// Variables every command runs with, keeping tools quiet and
+/// non-interactive.
+const COMMAND_ENVIRONMENT: &[(&str, &str)] = &[
+    ("CI", "true"),
+    ("NO_COLOR", "1"),
+    ("CARGO_TERM_COLOR", "never"),
+];
+
+/// Container the commands of checked out repositories run in (builds,
+/// tests, benchmarks, codemods and lockfile refreshes) while
+/// [`crate::WorkerConfig::sandbox_enabled`] is set.
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    /// Container runtime with a Docker-compatible CLI: `docker` or
+    /// `podman`.
+    pub runtime: String,
+    /// Image holding the toolchains of the ecosystems built.
+    pub image: String,
+    /// Let commands reach the network, which installing dependencies needs.
+    pub network: bool,
+    /// Processes a command may run at once.
+    pub pids_limit: u32,
+}
+
+impl SandboxConfig {
+    pub fn new(image: &str) -> Self {
+        Self {
+            runtime: "docker".to_string(),
+            image: image.to_string(),
+            network: true,
+            pids_limit: 1024,
+        }
+    }
+}
+
+/// Where the commands of a checkout run.
+#[derive(Debug, Clone)]
+pub enum Isolation {
+    /// On the worker host with a scrubbed environment, only when the
+    /// sandbox is disabled.
+    Host,
+    /// In a throwaway container that mounts nothing but the checkout,
+    /// without capabilities and limited to `memory_limit` bytes.
+    Container {
+        config: SandboxConfig,
+        memory_limit: u64,
+    },
+}
+
+impl Isolation {
+    /// The command running `argv` in `directory`, as the container `name`
+    /// when sandboxed.
+    pub(crate) fn command(&self, argv: &[&str], directory: &Path, name: &str) -> Command {
+        let mut command = match self {
+            Isolation::Host => {
+                let mut command = Command::new(argv[0]);
+                command
+                    .args(&argv[1..])
+                    .current_dir(directory)
+                    .env_clear()
+                    .envs(
+                        PASSED_ENVIRONMENT
+                            .iter()
+                            .filter_map(|name| std::env::var_os(name).map(|value| (name, value))),
+                    )
+                    .envs(COMMAND_ENVIRONMENT.iter().copied());
+                command
+            }
+            Isolation::Container {
+                config,
+                memory_limit,
+            } => {
+                let directory = directory.to_string_lossy();
+                let memory = memory_limit.to_string();
+                let mut command = Command::new(&config.runtime);
+                command.args(["run", "--rm", "--init", "--name", name]);
+                if !config.network {
+                    command.args(["--network", "none"]);
+                }
+                command
+                    .args(["--memory", &memory, "--memory-swap", &memory])
+                    .args(["--pids-limit", &config.pids_limit.to_string()])
+                    .args(["--cap-drop", "ALL", "--security-opt", "no-new-privileges"]);
+                // Files the command writes stay owned by the worker
+                #[cfg(unix)]
+                if let Ok(metadata) = std::fs::metadata(directory.as_ref()) {
+                    use std::os::unix::fs::MetadataExt;
+                    command.args(["--user", &format!("{}:{}", metadata.uid(), metadata.gid())]);
+                }
+                // Mounted at the same path, so that paths in `argv` hold
+                command
+                    .args(["--volume", &format!("{}:{}", directory, directory)])
+                    .args(["--workdir", &directory])
+                    .args(["--env", "HOME=/tmp"]);
+                for (variable, value) in COMMAND_ENVIRONMENT {
+                    command.args(["--env", &format!("{}={}", variable, value)]);
+                }
+                command.arg(&config.image).args(argv);
+                command
+            }
+        };
+        command.stdin(Stdio::null()).kill_on_drop(true);
+        command
+    }
+
+    /// Removes the container of a command that timed out, which killing
+    /// the runtime's client leaves running.
+    pub(crate) async fn stop(&self, name: &str) {
+        if let Isolation::Container { config, .. } = self {
+            let _ = Command::new(&config.runtime)
+                .args(["rm", "--force", name])
+                .stdin(Stdio::null())
+                .output()
+                .await;
+        }
+    }
+}
+
+impl UpgradeWorker {
+    /// Where the commands of checkouts run: in the configured container
+    /// while the sandbox is enabled, which refuses them without one.
+    pub(crate) fn isolation(&self) -> Result<Isolation, String> {
+        if !self.config.sandbox_enabled {
+            return Ok(Isolation::Host);
+        }
+        match &self.config.sandbox {
+            Some(config) => Ok(Isolation::Container {
+                config: config.clone(),
+                memory_limit: self.config.memory_limit,
+            }),
+            None => Err(
+                "the sandbox is enabled but no container is configured to run commands in"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WorkerConfig;
+
+    #[test]
+    fn test_container_command() {
+        let directory = tempfile::tempdir().unwrap();
+        let isolation = Isolation::Container {
+            config: SandboxConfig {
+                network: false,
+                ..SandboxConfig::new("speccursor/toolchains")
+            },
+            memory_limit: 512,
+        };
+        let command = isolation.command(&["npm", "test"], directory.path(), "job-1");
+        let command = command.as_std();
+        assert_eq!(command.get_program(), "docker");
+        let args: Vec<String> = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let path = directory.path().to_string_lossy();
+        let flag = |name: &str| {
+            args.iter()
+                .position(|arg| arg == name)
+                .map(|i| args[i + 1].as_str())
+        };
+        assert_eq!(flag("--name"), Some("job-1"));
+        assert_eq!(flag("--network"), Some("none"));
+        assert_eq!(flag("--memory"), Some("512"));
+        assert_eq!(flag("--memory-swap"), Some("512"));
+        assert_eq!(flag("--cap-drop"), Some("ALL"));
+        assert_eq!(
+            flag("--volume"),
+            Some(format!("{}:{}", path, path).as_str())
+        );
+        assert_eq!(
+            args[args.len() - 3..],
+            ["speccursor/toolchains", "npm", "test"]
+        );
+    }
+
+    #[test]
+    fn test_enabled_sandbox_needs_a_container() {
+        let worker = UpgradeWorker::new(None);
+        assert!(worker
+            .isolation()
+            .unwrap_err()
+            .contains("sandbox is enabled"));
+
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            sandbox: Some(SandboxConfig::new("speccursor/toolchains")),
+            memory_limit: 2048,
+            ..WorkerConfig::default()
+        }));
+        assert!(matches!(
+            worker.isolation(),
+            Ok(Isolation::Container {
+                memory_limit: 2048,
+                ..
+            })
+        ));
+        let worker = UpgradeWorker::new(Some(WorkerConfig {
+            sandbox_enabled: false,
+            ..WorkerConfig::default()
+        }));
+        assert!(matches!(worker.isolation(), Ok(Isolation::Host)));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::advisories::{lockfile_ecosystem, resolved_packages};
+use crate::{ChangeFormat, ErrorType, UpgradeError, UpgradeRequest, UpgradeWorker, WorkerConfig};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeSet;
+
+const TOOL: &str = concat!("speccursor-rust-worker-", env!("CARGO_PKG_VERSION"));
+
+/// Document format of a software bill of materials.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON.
+    #[default]
+    CycloneDx,
+    /// SPDX 2.3 JSON.
+    Spdx,
+}
+
+/// An upgrade whose dependency inventory is wanted, for `POST /sbom`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SbomRequest {
+    #[serde(flatten)]
+    pub upgrade: UpgradeRequest,
+    #[serde(default)]
+    pub format: SbomFormat,
+}
+
+/// SBOMs of the resolved dependencies before and after an upgrade.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SbomResponse {
+    pub format: SbomFormat,
+    pub resolved_version: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+    pub warnings: Vec<String>,
+}
+
+/// A resolved package, as `(ecosystem, name, version)`.
+pub type Component = (&'static str, String, String);
+
+/// Package URL of a component, e.g. `pkg:npm/%40babel/core@7.23.2`.
+pub fn purl((ecosystem, name, version): &Component) -> String {
+    let kind = match *ecosystem {
+        "pip" => "pypi",
+        "rubygems" => "gem",
+        ecosystem => ecosystem,
+    };
+    format!("pkg:{}/{}@{}", kind, name.replace('@', "%40"), version)
+}
+
+/// The packages resolved by every lockfile among `files`, in path order.
+pub fn components<'a>(files: impl Iterator<Item = (&'a str, &'a str)>) -> BTreeSet<Component> {
+    let mut components = BTreeSet::new();
+    for (path, content) in files {
+        let (Some(ecosystem), Some(packages)) =
+            (lockfile_ecosystem(path), resolved_packages(path, content))
+        else {
+            continue;
+        };
+        components.extend(
+            packages
+                .into_iter()
+                .map(|(name, version)| (ecosystem, name, version)),
+        );
+    }
+    components
+}
+
+/// A CycloneDX 1.5 document listing `components` of `repository`.
+pub fn cyclonedx(
+    repository: &str,
+    components: &BTreeSet<Component>,
+    timestamp: DateTime<Utc>,
+) -> serde_json::Value {
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "tools": {"components": [{
+                "type": "application",
+                "name": "speccursor-rust-worker",
+                "version": env!("CARGO_PKG_VERSION"),
+            }]},
+            "component": {"type": "application", "name": repository, "bom-ref": repository},
+        },
+        "components": components
+            .iter()
+            .map(|component| json!({
+                "type": "library",
+                "bom-ref": purl(component),
+                "name": component.1,
+                "version": component.2,
+                "purl": purl(component),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// An SPDX 2.3 document listing `components` of `repository`.
+pub fn spdx(
+    repository: &str,
+    components: &BTreeSet<Component>,
+    timestamp: DateTime<Utc>,
+) -> serde_json::Value {
+    let packages: Vec<serde_json::Value> = components
+        .iter()
+        .enumerate()
+        .map(|(i, component)| {
+            json!({
+                "name": component.1,
+                "SPDXID": format!("SPDXRef-Package-{}", i + 1),
+                "versionInfo": component.2,
+                "downloadLocation": "NOASSERTION",
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl(component),
+                }],
+            })
+        })
+        .collect();
+    let relationships: Vec<serde_json::Value> = (1..=packages.len())
+        .map(|i| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": format!("SPDXRef-Package-{}", i),
+            })
+        })
+        .collect();
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": repository,
+        "documentNamespace": format!(
+            "https://speccursor.dev/spdx/{}-{}",
+            repository.replace(|c: char| !c.is_ascii_alphanumeric(), "-"),
+            uuid::Uuid::new_v4()
+        ),
+        "creationInfo": {
+            "created": timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "creators": [format!("Tool: {}", TOOL)],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+impl UpgradeWorker {
+    /// Resolves the upgrade without committing, pushing or editing anything
+    /// and describes the dependencies its lockfiles resolve before and
+    /// after. Lockfiles are only current after the upgrade when
+    /// `regenerate_lockfiles` is enabled.
+    pub async fn generate_sbom(&self, request: &SbomRequest) -> Result<SbomResponse, UpgradeError> {
+        let mut upgrade = request.upgrade.clone();
+        self.validate_request(&upgrade)?;
+        // Read the repository once, then work from its files alone
+        self.load_repository(&mut upgrade).await?;
+        upgrade.local_path = None;
+        upgrade.change_format = ChangeFormat::Content;
+        let preview = UpgradeWorker {
+            config: WorkerConfig {
+                checkout: None,
+                commit: None,
+                scm: None,
+                patch_directory: None,
+                ..self.config.clone()
+            },
+            ..self.clone()
+        };
+
+        let before_files = || {
+            upgrade
+                .manifests
+                .iter()
+                .map(|(path, content)| (path.as_str(), content.as_str()))
+        };
+        let before = components(before_files());
+        if before.is_empty() {
+            return Err(UpgradeError {
+                message: "No lockfile to build an SBOM from: supply Cargo.lock, \
+                          package-lock.json, yarn.lock, poetry.lock or Gemfile.lock"
+                    .to_string(),
+                error_type: ErrorType::Validation,
+            });
+        }
+
+        let response = preview.process_upgrade(upgrade.clone()).await?;
+        let mut warnings = response.warnings;
+        let mut stale: Vec<&str> = response
+            .changes
+            .iter()
+            .filter(|change| change.metadata.contains_key("lockfile_refresh"))
+            .map(|change| change.file_path.as_str())
+            .collect();
+        stale.sort();
+        for path in &stale {
+            warnings.push(format!(
+                "{} was not regenerated; the SBOM after the upgrade lists it unchanged",
+                path
+            ));
+        }
+        let after = components(before_files().map(|(path, content)| {
+            let changed = response.changes.iter().find(|change| {
+                change.file_path == path && !change.metadata.contains_key("lockfile_refresh")
+            });
+            (
+                path,
+                changed.map_or(content, |change| change.content.as_str()),
+            )
+        }));
+
+        let now = Utc::now();
+        let document = match request.format {
+            SbomFormat::CycloneDx => cyclonedx,
+            SbomFormat::Spdx => spdx,
+        };
+        Ok(SbomResponse {
+            format: request.format,
+            resolved_version: response.resolved_version,
+            before: document(&upgrade.repository, &before, now),
+            after: document(&upgrade.repository, &after, now),
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const LOCKFILE: &str = r#"{"lockfileVersion": 3, "packages": {
+        "": {"name": "web"},
+        "node_modules/@babel/core": {"version": "7.23.2"},
+        "node_modules/lodash": {"version": "4.17.20"}
+    }}"#;
+
+    #[test]
+    fn test_sbom_documents() {
+        let components = components([("web/package-lock.json", LOCKFILE)].into_iter());
+        let timestamp = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let bom = cyclonedx("acme/web", &components, timestamp);
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["metadata"]["timestamp"], "2024-03-01T12:00:00Z");
+        assert_eq!(bom["components"][0]["purl"], "pkg:npm/%40babel/core@7.23.2");
+        assert_eq!(bom["components"][1]["name"], "lodash");
+
+        let document = spdx("acme/web", &components, timestamp);
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["packages"][1]["SPDXID"], "SPDXRef-Package-2");
+        assert_eq!(
+            document["packages"][1]["externalRefs"][0]["referenceLocator"],
+            "pkg:npm/lodash@4.17.20"
+        );
+        assert_eq!(
+            document["relationships"][1]["relatedSpdxElement"],
+            "SPDXRef-Package-2"
+        );
+        assert_eq!(
+            purl(&("pip", "requests".to_string(), "2.31.0".to_string())),
+            "pkg:pypi/requests@2.31.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_sbom() {
+        let request = |manifests: HashMap<String, String>| SbomRequest {
+            upgrade: UpgradeRequest {
+                repository: "acme/web".to_string(),
+                ecosystem: "npm".to_string(),
+                package_name: "lodash".to_string(),
+                current_version: "4.17.20".to_string(),
+                target_version: "4.17.21".to_string(),
+                manifests,
+                ..Default::default()
+            },
+            format: SbomFormat::Spdx,
+        };
+        let worker = UpgradeWorker::new(None);
+        let response = worker
+            .generate_sbom(&request(HashMap::from([
+                (
+                    "package.json".to_string(),
+                    r#"{"dependencies": {"lodash": "4.17.20"}}"#.to_string(),
+                ),
+                ("package-lock.json".to_string(), LOCKFILE.to_string()),
+            ])))
+            .await
+            .unwrap();
+        assert_eq!(response.resolved_version, "4.17.21");
+        assert_eq!(response.before["packages"].as_array().unwrap().len(), 2);
+        // Without regeneration the lockfile is unchanged, and says so
+        assert_eq!(response.after["packages"], response.before["packages"]);
+        assert!(response
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("package-lock.json was not regenerated")));
+
+        let err = worker
+            .generate_sbom(&request(HashMap::new()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.error_type, ErrorType::Validation));
+    }
+}
@@ -0,0 +1,690 @@
+mod bitbucket;
+mod github;
+mod gitlab;
+
+pub use bitbucket::{Bitbucket, BitbucketConfig};
+pub use github::{GitHub, GitHubConfig};
+pub use gitlab::{GitLab, GitLabConfig};
+
+use crate::commit::UpgradeCommit;
+use crate::release_notes::ReleaseNotes;
+use crate::repo::{self, Checkout};
+use crate::version::ResolvedVersions;
+use crate::{
+    Change, ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Status context upgrade commits are reported under.
+pub const STATUS_CONTEXT: &str = "speccursor/risk";
+
+/// A hosting service upgrade branches are pushed to and proposed on.
+#[async_trait]
+pub trait ScmProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Clone URL of `repository`, expanding `owner/repo` shorthand to this
+    /// provider's host.
+    fn remote_url(&self, repository: &str) -> String;
+
+    /// Username and token HTTPS pushes authenticate with.
+    fn push_credentials(&self) -> (String, String);
+
+    /// Host the token belongs to; it is never lent to remotes elsewhere.
+    fn host(&self) -> String;
+
+    /// Opens a pull request for `proposal.branch`, or returns the one
+    /// already open for it.
+    async fn open_pull_request(
+        &self,
+        repository: &str,
+        proposal: &Proposal,
+        warnings: &mut Vec<String>,
+    ) -> Result<PullRequest, UpgradeError>;
+
+    /// Attaches `status` to commit `sha`.
+    async fn report_status(
+        &self,
+        repository: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> Result<(), UpgradeError>;
+}
+
+/// Where upgrade branches are pushed and proposed when
+/// [`crate::WorkerConfig::scm`] is set.
+#[derive(Debug, Clone)]
+pub enum ScmConfig {
+    GitHub(GitHubConfig),
+    GitLab(GitLabConfig),
+    Bitbucket(BitbucketConfig),
+}
+
+/// Returns the provider for `config`, whose API requests time out after
+/// `timeout`.
+pub fn provider_for(config: &ScmConfig, timeout: Duration) -> Box<dyn ScmProvider> {
+    match config {
+        ScmConfig::GitHub(config) => Box::new(GitHub::new(config.clone(), timeout)),
+        ScmConfig::GitLab(config) => Box::new(GitLab::new(config.clone(), timeout)),
+        ScmConfig::Bitbucket(config) => Box::new(Bitbucket::new(config.clone(), timeout)),
+    }
+}
+
+/// A pull request (merge request on GitLab) opened or found open for an
+/// upgrade branch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequest {
+    /// `github`, `gitlab` or `bitbucket`.
+    pub provider: String,
+    /// Repository-scoped number (GitLab `iid`).
+    pub number: u64,
+    pub url: String,
+    /// Approvals suggested for the assessed risk.
+    pub approvals_required: u32,
+}
+
+/// What is proposed for an upgrade branch.
+#[derive(Debug, Clone)]
+pub struct Proposal {
+    pub branch: String,
+    pub title: String,
+    pub description: String,
+    pub risk_level: RiskLevel,
+}
+
+impl Proposal {
+    pub fn approvals_required(&self) -> u32 {
+        approvals_required(self.risk_level)
+    }
+
+    /// `low`, `medium`, `high` or `critical`.
+    pub fn risk(&self) -> String {
+        format!("{:?}", self.risk_level).to_lowercase()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusState {
+    Pending,
+    Success,
+    Failure,
+}
+
+/// A commit status (build status on Bitbucket) summarising an upgrade.
+#[derive(Debug, Clone)]
+pub struct CommitStatus {
+    pub state: StatusState,
+    pub description: String,
+    /// Page the status links to.
+    pub target_url: Option<String>,
+}
+
+impl CommitStatus {
+    /// Low and medium risk upgrades pass, high risk ones wait for review
+    /// and critical ones fail.
+    pub fn for_risk(risk_level: RiskLevel, target_url: Option<String>) -> Self {
+        let state = match risk_level {
+            RiskLevel::Low | RiskLevel::Medium => StatusState::Success,
+            RiskLevel::High => StatusState::Pending,
+            RiskLevel::Critical => StatusState::Failure,
+        };
+        Self {
+            state,
+            description: format!(
+                "{} risk upgrade",
+                format!("{:?}", risk_level).to_lowercase()
+            ),
+            target_url,
+        }
+    }
+}
+
+/// Approvals an upgrade of `risk_level` should get before it is merged.
+pub fn approvals_required(risk_level: RiskLevel) -> u32 {
+    match risk_level {
+        RiskLevel::Low => 0,
+        RiskLevel::Medium => 1,
+        RiskLevel::High => 2,
+        RiskLevel::Critical => 3,
+    }
+}
+
+/// API usages listed in pull request descriptions; the rest are counted.
+const MAX_LISTED_USAGES: usize = 20;
+
+/// Markdown description of the pull request: what is bumped, the assessed
+/// risk with its approval hint, known advisories, the digest of the release
+/// notes, the lines to review and the files changed.
+pub fn description(
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+    risk_assessment: &RiskAssessment,
+    release_notes: Option<&ReleaseNotes>,
+    changes: &[Change],
+) -> String {
+    let risk = format!("{:?}", risk_assessment.risk_level).to_lowercase();
+    let approvals = approvals_required(risk_assessment.risk_level);
+    let mut description = format!(
+        "Bumps `{}` from {} to {}.\n\n**Risk:** {}",
+        request.package_name, versions.current, versions.target, risk
+    );
+    if approvals > 0 {
+        description.push_str(&format!(
+            " ({} approval{} suggested)",
+            approvals,
+            if approvals == 1 { "" } else { "s" }
+        ));
+    }
+    description.push('\n');
+    for explanation in &risk_assessment.explanations {
+        description.push_str(&format!("- {}\n", explanation));
+    }
+
+    // Advisories the upgrade fixes are what reviewers look for first
+    if !risk_assessment.security_issues.is_empty() {
+        description.push_str("\n**Security:**\n");
+        for issue in &risk_assessment.security_issues {
+            description.push_str(&format!("- {}\n", issue));
+        }
+    }
+
+    if let Some(notes) = release_notes.filter(|notes| !notes.is_empty()) {
+        description.push_str(&format!(
+            "\n**Release notes** ([source]({})):\n",
+            notes.source
+        ));
+        for (title, entries) in [
+            ("Breaking changes", &notes.breaking),
+            ("Deprecations", &notes.deprecations),
+            ("Migration", &notes.migration),
+        ] {
+            if !entries.is_empty() {
+                description.push_str(&format!("\n_{}_\n", title));
+            }
+            for note in entries {
+                description.push_str(&format!("- {} ({})\n", note.text, note.version));
+            }
+        }
+        if notes.omitted > 0 {
+            description.push_str(&format!("\n{} more notes omitted\n", notes.omitted));
+        }
+    }
+
+    // Where the repository uses what the target breaks, to review first
+    if !risk_assessment.api_usages.is_empty() {
+        description.push_str("\n**Review:**\n");
+        for usage in risk_assessment.api_usages.iter().take(MAX_LISTED_USAGES) {
+            description.push_str(&format!(
+                "- `{}:{}` uses `{}`: {}\n",
+                usage.file, usage.line, usage.name, usage.reason
+            ));
+        }
+        let omitted = risk_assessment
+            .api_usages
+            .len()
+            .saturating_sub(MAX_LISTED_USAGES);
+        if omitted > 0 {
+            description.push_str(&format!("\n{} more usages omitted\n", omitted));
+        }
+    }
+
+    description.push_str("\n**Files changed:**\n");
+    let mut paths: Vec<&str> = changes
+        .iter()
+        .map(|change| change.file_path.as_str())
+        .collect();
+    paths.sort_unstable();
+    paths.dedup();
+    for path in paths {
+        // Codemod edits are flagged for a closer look
+        let codemod = changes
+            .iter()
+            .filter(|change| change.file_path == path && change.metadata.contains_key("codemod"))
+            .find_map(|change| change.metadata.get("migration")?.as_str());
+        match codemod {
+            Some(name) => {
+                description.push_str(&format!("- `{}` (automated migration `{}`)\n", path, name))
+            }
+            None => description.push_str(&format!("- `{}`\n", path)),
+        }
+    }
+    description
+}
+
+/// Splits `repository` into the web URL of its host and the repository's
+/// path there. HTTPS and SSH URLs are understood; `owner/repo` shorthand
+/// needs `url`, which also gives the base of instances served under a path
+/// (`https://git.example.com/gitlab`). With `url`, repositories on other
+/// hosts are refused, so API tokens never leave the configured instance.
+pub fn split_repository(repository: &str, url: Option<&str>) -> Option<(String, String)> {
+    let repository = repository.trim_end_matches('/').trim_end_matches(".git");
+    if let Some(url) = url {
+        let url = url.trim_end_matches('/');
+        let path = match repository.strip_prefix(url) {
+            Some(path) => path.trim_start_matches('/'),
+            None if !repository.contains(':') => repository,
+            None => {
+                let (host, path) = split_repository(repository, None)?;
+                if remote_host(&host).is_none() || remote_host(&host) != remote_host(url) {
+                    return None;
+                }
+                return Some((url.to_string(), path));
+            }
+        };
+        return Some((url.to_string(), path.to_string()));
+    }
+
+    let (host, path) = if let Some(rest) = repository
+        .strip_prefix("https://")
+        .or_else(|| repository.strip_prefix("http://"))
+    {
+        let (host, path) = rest.split_once('/')?;
+        let scheme = &repository[..repository.len() - rest.len()];
+        (format!("{}{}", scheme, host), path)
+    } else if let Some(rest) = repository.strip_prefix("ssh://") {
+        let (host, path) = rest.split_once('/')?;
+        let host = host.rsplit('@').next()?.split(':').next()?;
+        (format!("https://{}", host), path)
+    } else {
+        let (host, path) = repository.split_once(':')?;
+        (format!("https://{}", host.rsplit('@').next()?), path)
+    };
+    path.contains('/').then(|| (host, path.to_string()))
+}
+
+/// Lowercased host name of an HTTP(S), `ssh://` or scp-style
+/// (`git@host:owner/repo`) URL, without user or port; `None` for shorthand
+/// and local paths.
+pub fn remote_host(url: &str) -> Option<String> {
+    let authority = match url.split_once("://") {
+        Some(("https" | "http" | "ssh" | "git", rest)) => rest.split('/').next()?,
+        Some(_) => return None,
+        None => {
+            let (authority, _) = url.split_once(':')?;
+            if authority.contains('/') {
+                return None;
+            }
+            authority
+        }
+    };
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// The clone URL of `owner/repo` shorthand under `base`; `None` for full
+/// URLs and paths.
+pub fn shorthand_url(base: &str, repository: &str) -> Option<String> {
+    let shorthand = repository.contains('/')
+        && !repository.contains(':')
+        && !repository.starts_with(['.', '/']);
+    shorthand.then(|| {
+        format!(
+            "{}/{}.git",
+            base.trim_end_matches('/'),
+            repository.trim_end_matches(".git")
+        )
+    })
+}
+
+pub(crate) fn scm_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Network,
+    }
+}
+
+fn unknown_repository(provider: &str, repository: &str) -> UpgradeError {
+    UpgradeError {
+        message: format!(
+            "Cannot tell the {} repository of '{}'; set the instance URL",
+            provider, repository
+        ),
+        error_type: ErrorType::Validation,
+    }
+}
+
+pub(crate) enum Auth {
+    Header(&'static str, String),
+    Bearer(String),
+    Basic(String, String),
+}
+
+/// A JSON REST client for one provider's API.
+pub(crate) struct Client {
+    provider: &'static str,
+    client: reqwest::Client,
+    auth: Auth,
+}
+
+impl Client {
+    pub(crate) fn new(provider: &'static str, auth: Auth, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        Self {
+            provider,
+            client,
+            auth,
+        }
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<(reqwest::StatusCode, serde_json::Value), UpgradeError> {
+        let request = match &self.auth {
+            Auth::Header(name, value) => request.header(*name, value),
+            Auth::Bearer(token) => request.bearer_auth(token),
+            Auth::Basic(username, password) => request.basic_auth(username, Some(password)),
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|e| scm_error(format!("{} request failed: {}", self.provider, e)))?;
+        let status = response.status();
+        let body = response.json().await.unwrap_or(serde_json::Value::Null);
+        Ok((status, body))
+    }
+
+    pub(crate) async fn get(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<serde_json::Value, UpgradeError> {
+        let (status, body) = self.send(self.client.get(url).query(query)).await?;
+        if !status.is_success() {
+            return Err(self.error(url, status, &body));
+        }
+        Ok(body)
+    }
+
+    pub(crate) async fn post(
+        &self,
+        url: &str,
+        payload: &serde_json::Value,
+    ) -> Result<(reqwest::StatusCode, serde_json::Value), UpgradeError> {
+        self.send(self.client.post(url).json(payload)).await
+    }
+
+    /// Like [`Client::post`], failing unless the request succeeds.
+    pub(crate) async fn create(
+        &self,
+        url: &str,
+        payload: &serde_json::Value,
+    ) -> Result<serde_json::Value, UpgradeError> {
+        let (status, body) = self.post(url, payload).await?;
+        if !status.is_success() {
+            return Err(self.error(url, status, &body));
+        }
+        Ok(body)
+    }
+
+    // The message of the error formats the providers return
+    pub(crate) fn error(
+        &self,
+        url: &str,
+        status: reqwest::StatusCode,
+        body: &serde_json::Value,
+    ) -> UpgradeError {
+        let detail = [
+            &body["message"],
+            &body["error"]["message"],
+            &body["errors"][0]["message"],
+            &body["error"],
+        ]
+        .into_iter()
+        .find(|detail| !detail.is_null())
+        .map(|detail| detail.as_str().map_or(detail.to_string(), str::to_string))
+        .unwrap_or_default();
+        scm_error(format!(
+            "{} request to {} failed with status {}: {}",
+            self.provider, url, status, detail
+        ))
+    }
+}
+
+impl UpgradeWorker {
+    /// Pushes the upgrade branch, opens a pull request for it with the
+    /// given [`description`] and reports the assessed risk as a commit
+    /// status when a provider is configured.
+    pub(crate) async fn open_pull_request(
+        &self,
+        committed: Option<(&Checkout, &UpgradeCommit)>,
+        request: &UpgradeRequest,
+        risk_assessment: &RiskAssessment,
+        description: String,
+        changes: &[Change],
+        warnings: &mut Vec<String>,
+    ) -> Result<Option<PullRequest>, UpgradeError> {
+        let Some(config) = &self.config.scm else {
+            return Ok(None);
+        };
+        let Some((checkout, commit)) = committed else {
+            if !changes.is_empty() {
+                warnings.push("No pull request opened: the changes were not committed".to_string());
+            }
+            return Ok(None);
+        };
+        let provider = provider_for(config, Duration::from_secs(self.config.max_execution_time));
+
+        let path = checkout.path.clone();
+        let branch = commit.branch.clone();
+        let credentials = self
+            .git_credentials(&request.repository, Some(provider.as_ref()))
+            .await?;
+        tokio::task::spawn_blocking(move || repo::push(&path, &branch, &credentials))
+            .await
+            .map_err(|e| scm_error(format!("Push task failed: {}", e)))??;
+
+        let proposal = Proposal {
+            branch: commit.branch.clone(),
+            title: commit
+                .message
+                .lines()
+                .next()
+                .unwrap_or(&commit.branch)
+                .to_string(),
+            description,
+            risk_level: risk_assessment.risk_level,
+        };
+        let pull_request = provider
+            .open_pull_request(&request.repository, &proposal, warnings)
+            .await?;
+
+        let status =
+            CommitStatus::for_risk(risk_assessment.risk_level, Some(pull_request.url.clone()));
+        if let Err(e) = provider
+            .report_status(&request.repository, &commit.sha, &status)
+            .await
+        {
+            warnings.push(format!("Commit status not reported: {}", e));
+        }
+        Ok(Some(pull_request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{SemanticScheme, VersionJump, VersionScheme};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_split_repository() {
+        let split = |repository, url| split_repository(repository, url).unwrap();
+        assert_eq!(
+            split("https://gitlab.example.com/platform/api/worker.git", None),
+            (
+                "https://gitlab.example.com".to_string(),
+                "platform/api/worker".to_string()
+            )
+        );
+        assert_eq!(
+            split("git@gitlab.example.com:platform/worker.git", None).1,
+            "platform/worker"
+        );
+        assert_eq!(
+            split("ssh://git@bitbucket.example.com:7999/plat/worker.git", None).0,
+            "https://bitbucket.example.com"
+        );
+        assert_eq!(
+            split(
+                "https://git.example.com/gitlab/platform/worker",
+                Some("https://git.example.com/gitlab/")
+            ),
+            (
+                "https://git.example.com/gitlab".to_string(),
+                "platform/worker".to_string()
+            )
+        );
+        assert_eq!(
+            split("platform/worker", Some("https://gitlab.example.com")).1,
+            "platform/worker"
+        );
+        assert!(split_repository("platform/worker", None).is_none());
+        assert_eq!(
+            split(
+                "git@git.example.com:platform/worker.git",
+                Some("https://git.example.com/gitlab")
+            ),
+            (
+                "https://git.example.com/gitlab".to_string(),
+                "platform/worker".to_string()
+            )
+        );
+        for foreign in [
+            "https://evil.example/a/b",
+            "http://evil.example/a/b",
+            "git@evil.example:a/b",
+            "ssh://git@evil.example/a/b",
+        ] {
+            assert!(split_repository(foreign, Some("https://github.com")).is_none());
+        }
+
+        assert_eq!(
+            shorthand_url("https://gitlab.example.com/", "platform/api/worker").as_deref(),
+            Some("https://gitlab.example.com/platform/api/worker.git")
+        );
+        assert!(shorthand_url("https://gitlab.example.com", "git@host:a/b.git").is_none());
+
+        assert_eq!(
+            remote_host("https://token@GitHub.com:443/acme/app.git").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            remote_host("ssh://git@bitbucket.example.com:7999/plat/worker.git").as_deref(),
+            Some("bitbucket.example.com")
+        );
+        assert_eq!(
+            remote_host("git@gitlab.example.com:platform/worker.git").as_deref(),
+            Some("gitlab.example.com")
+        );
+        for local in [
+            "acme/app",
+            "/srv/repos/app",
+            "./app",
+            "file:///srv/repos/app",
+        ] {
+            assert!(remote_host(local).is_none(), "{}", local);
+        }
+    }
+
+    #[test]
+    fn test_description_carries_approval_hint() {
+        let request = UpgradeRequest {
+            package_name: "rails".to_string(),
+            ..Default::default()
+        };
+        let versions = ResolvedVersions {
+            current: SemanticScheme.parse("6.1.7").unwrap(),
+            target: SemanticScheme.parse("7.1.3").unwrap(),
+        };
+        let risk_assessment = RiskAssessment {
+            risk_level: RiskLevel::High,
+            breaking_changes: true,
+            version_jump: VersionJump::Major,
+            explanations: vec!["Major version upgrade".to_string()],
+            ..Default::default()
+        };
+        let changes = vec![
+            Change {
+                file_path: "Gemfile".to_string(),
+                change_type: crate::ChangeType::Modify,
+                content: String::new(),
+                metadata: HashMap::new(),
+            };
+            2
+        ];
+
+        assert_eq!(
+            description(&request, &versions, &risk_assessment, None, &changes),
+            "Bumps `rails` from 6.1.7 to 7.1.3.\n\n\
+             **Risk:** high (2 approvals suggested)\n\
+             - Major version upgrade\n\n\
+             **Files changed:**\n\
+             - `Gemfile`\n"
+        );
+        let fixing = RiskAssessment {
+            security_issues: vec![crate::advisories::SecurityIssue {
+                id: "GHSA-xxxx".to_string(),
+                aliases: vec!["CVE-2024-0001".to_string()],
+                version: "6.1.7".to_string(),
+                fixed_by_upgrade: true,
+                ..Default::default()
+            }],
+            ..risk_assessment
+        };
+        assert!(
+            description(&request, &versions, &fixing, None, &changes).contains(
+                "\n**Security:**\n- GHSA-xxxx (CVE-2024-0001) affects 6.1.7, fixed by the upgrade\n"
+            )
+        );
+        let notes = ReleaseNotes {
+            source: "https://github.com/rails/rails/releases".to_string(),
+            versions: vec!["7.0.0".to_string()],
+            breaking: vec![crate::release_notes::ReleaseNote {
+                version: "7.0.0".to_string(),
+                text: "Remove deprecated `ActiveRecord::Base.default_scope` block".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(
+            description(&request, &versions, &fixing, Some(&notes), &changes).contains(
+                "\n**Release notes** ([source](https://github.com/rails/rails/releases)):\n\n\
+                 _Breaking changes_\n\
+                 - Remove deprecated `ActiveRecord::Base.default_scope` block (7.0.0)\n"
+            )
+        );
+        let using = RiskAssessment {
+            api_usages: vec![crate::usages::ApiUsage {
+                file: "app/models/post.rb".to_string(),
+                line: 3,
+                column: 3,
+                name: "default_scope".to_string(),
+                source: crate::usages::BreakSource::ReleaseNotes,
+                reason: "Remove deprecated `ActiveRecord::Base.default_scope` block".to_string(),
+                text: "default_scope { where(published: true) }".to_string(),
+            }],
+            ..fixing
+        };
+        assert!(
+            description(&request, &versions, &using, None, &changes).contains(
+                "\n**Review:**\n- `app/models/post.rb:3` uses `default_scope`: \
+                 Remove deprecated `ActiveRecord::Base.default_scope` block\n"
+            )
+        );
+        assert_eq!(approvals_required(RiskLevel::Low), 0);
+        assert_eq!(approvals_required(RiskLevel::Critical), 3);
+
+        let status = CommitStatus::for_risk(RiskLevel::High, None);
+        assert_eq!(status.state, StatusState::Pending);
+        assert_eq!(status.description, "high risk upgrade");
+    }
+}
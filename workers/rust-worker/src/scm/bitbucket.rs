@@ -0,0 +1,309 @@
+use super::{
+    remote_host, repo, shorthand_url, split_repository, unknown_repository, Auth, Client,
+    CommitStatus, Proposal, PullRequest, ScmProvider, StatusState,
+};
+use crate::UpgradeError;
+use async_trait::async_trait;
+use serde_json::json;
+use std::time::Duration;
+
+// Username Bitbucket expects with repository, project and workspace access
+// tokens
+const TOKEN_USER: &str = "x-token-auth";
+
+const CLOUD_URL: &str = "https://bitbucket.org";
+const CLOUD_API: &str = "https://api.bitbucket.org/2.0";
+
+/// A Bitbucket Cloud repository, or one on a Bitbucket Server or Data
+/// Center instance when `url` is set.
+#[derive(Debug, Clone, Default)]
+pub struct BitbucketConfig {
+    /// Base URL of a Bitbucket Server or Data Center instance; Bitbucket
+    /// Cloud when unset.
+    pub url: Option<String>,
+    /// Access token, or app password (Cloud) or user password (Server) when
+    /// `username` is set.
+    pub token: String,
+    /// Account `token` belongs to, for app password and basic
+    /// authentication.
+    pub username: Option<String>,
+    /// Branch pull requests target; the repository's default branch when
+    /// unset.
+    pub target_branch: Option<String>,
+    /// Delete the upgrade branch once the pull request is merged (Cloud).
+    pub close_source_branch: bool,
+}
+
+pub struct Bitbucket {
+    config: BitbucketConfig,
+    client: Client,
+}
+
+/// Where a repository's REST resources live.
+#[derive(Debug, PartialEq)]
+enum Location {
+    /// `{api}/repositories/{workspace}/{slug}`
+    Cloud { repository: String },
+    /// `{base}/rest/api/1.0/projects/{key}/repos/{slug}` and the build
+    /// status API of the instance
+    Server { base: String, repository: String },
+}
+
+impl Bitbucket {
+    pub fn new(config: BitbucketConfig, timeout: Duration) -> Self {
+        let auth = match &config.username {
+            Some(username) => Auth::Basic(username.clone(), config.token.clone()),
+            None => Auth::Bearer(config.token.clone()),
+        };
+        let client = Client::new("Bitbucket", auth, timeout);
+        Self { config, client }
+    }
+
+    fn locate(&self, repository: &str) -> Result<Location, UpgradeError> {
+        let unknown = || unknown_repository("Bitbucket", repository);
+        match &self.config.url {
+            None => {
+                let (_, path) = split_repository(repository, Some(CLOUD_URL))
+                    .filter(|(_, path)| path.matches('/').count() == 1)
+                    .ok_or_else(unknown)?;
+                Ok(Location::Cloud {
+                    repository: format!("{}/repositories/{}", CLOUD_API, path),
+                })
+            }
+            Some(url) => {
+                let (base, path) = split_repository(repository, Some(url)).ok_or_else(unknown)?;
+                // HTTP clone URLs live under `/scm`
+                let path = path.strip_prefix("scm/").unwrap_or(&path);
+                let (project, slug) = path
+                    .split_once('/')
+                    .filter(|(_, slug)| !slug.contains('/'))
+                    .ok_or_else(unknown)?;
+                Ok(Location::Server {
+                    repository: format!(
+                        "{}/rest/api/1.0/projects/{}/repos/{}",
+                        base, project, slug
+                    ),
+                    base,
+                })
+            }
+        }
+    }
+
+    async fn target_branch(&self, location: &Location) -> Result<String, UpgradeError> {
+        if let Some(branch) = &self.config.target_branch {
+            return Ok(branch.clone());
+        }
+        let branch = match location {
+            Location::Cloud { repository } => {
+                self.client.get(repository, &[]).await?["mainbranch"]["name"].clone()
+            }
+            Location::Server { repository, .. } => {
+                let url = format!("{}/default-branch", repository);
+                self.client.get(&url, &[]).await?["displayId"].clone()
+            }
+        };
+        Ok(branch.as_str().unwrap_or("main").to_string())
+    }
+}
+
+fn pull_request(body: &serde_json::Value, approvals_required: u32) -> Option<PullRequest> {
+    // Cloud links to the page under `html`, Server under `self`
+    let url = body["links"]["html"]["href"]
+        .as_str()
+        .or_else(|| body["links"]["self"][0]["href"].as_str())?;
+    Some(PullRequest {
+        provider: "bitbucket".to_string(),
+        number: body["id"].as_u64()?,
+        url: url.to_string(),
+        approvals_required,
+    })
+}
+
+#[async_trait]
+impl ScmProvider for Bitbucket {
+    fn name(&self) -> &'static str {
+        "bitbucket"
+    }
+
+    fn remote_url(&self, repository: &str) -> String {
+        let base = match &self.config.url {
+            Some(url) => format!("{}/scm", url.trim_end_matches('/')),
+            None => CLOUD_URL.to_string(),
+        };
+        shorthand_url(&base, repository).unwrap_or_else(|| repo::remote_url(repository))
+    }
+
+    fn push_credentials(&self) -> (String, String) {
+        let user = self.config.username.as_deref().unwrap_or(TOKEN_USER);
+        (user.to_string(), self.config.token.clone())
+    }
+
+    fn host(&self) -> String {
+        remote_host(self.config.url.as_deref().unwrap_or(CLOUD_URL)).unwrap_or_default()
+    }
+
+    async fn open_pull_request(
+        &self,
+        repository: &str,
+        proposal: &Proposal,
+        _warnings: &mut Vec<String>,
+    ) -> Result<PullRequest, UpgradeError> {
+        let location = self.locate(repository)?;
+        let target_branch = self.target_branch(&location).await?;
+
+        let (url, payload, existing) = match &location {
+            Location::Cloud { repository } => (
+                format!("{}/pullrequests", repository),
+                json!({
+                    "title": proposal.title,
+                    "description": proposal.description,
+                    "source": {"branch": {"name": proposal.branch}},
+                    "destination": {"branch": {"name": target_branch}},
+                    "close_source_branch": self.config.close_source_branch,
+                }),
+                vec![(
+                    "q",
+                    format!(
+                        "source.branch.name=\"{}\" AND state=\"OPEN\"",
+                        proposal.branch
+                    ),
+                )],
+            ),
+            Location::Server { repository, .. } => (
+                format!("{}/pull-requests", repository),
+                json!({
+                    "title": proposal.title,
+                    "description": proposal.description,
+                    "fromRef": {"id": format!("refs/heads/{}", proposal.branch)},
+                    "toRef": {"id": format!("refs/heads/{}", target_branch)},
+                }),
+                vec![
+                    ("at", format!("refs/heads/{}", proposal.branch)),
+                    ("direction", "OUTGOING".to_string()),
+                    ("state", "OPEN".to_string()),
+                ],
+            ),
+        };
+
+        let (status, body) = self.client.post(&url, &payload).await?;
+        let created = if status.is_success() {
+            body
+        } else if status == reqwest::StatusCode::CONFLICT
+            || (status == reqwest::StatusCode::BAD_REQUEST
+                && matches!(location, Location::Cloud { .. }))
+        {
+            // Server answers a duplicate with 409, Cloud with 400
+            let query: Vec<(&str, &str)> = existing
+                .iter()
+                .map(|(key, value)| (*key, value.as_str()))
+                .collect();
+            // Both page their results under `values`
+            let open = self.client.get(&url, &query).await?["values"][0].clone();
+            if open.is_null() {
+                return Err(self.client.error(&url, status, &body));
+            }
+            open
+        } else {
+            return Err(self.client.error(&url, status, &body));
+        };
+        pull_request(&created, proposal.approvals_required()).ok_or_else(|| {
+            super::scm_error(format!(
+                "Bitbucket returned no pull request for {}",
+                proposal.branch
+            ))
+        })
+    }
+
+    async fn report_status(
+        &self,
+        repository: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> Result<(), UpgradeError> {
+        let state = match status.state {
+            StatusState::Pending => "INPROGRESS",
+            StatusState::Success => "SUCCESSFUL",
+            StatusState::Failure => "FAILED",
+        };
+        let url = match self.locate(repository)? {
+            Location::Cloud { repository } => {
+                format!("{}/commit/{}/statuses/build", repository, sha)
+            }
+            Location::Server { base, .. } => {
+                format!("{}/rest/build-status/1.0/commits/{}", base, sha)
+            }
+        };
+        let payload = json!({
+            "key": super::STATUS_CONTEXT,
+            "name": super::STATUS_CONTEXT,
+            "state": state,
+            "description": status.description,
+            "url": status.target_url,
+        });
+        self.client.create(&url, &payload).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitbucket_locations() {
+        let cloud = Bitbucket::new(BitbucketConfig::default(), Duration::from_secs(1));
+        assert_eq!(
+            cloud.locate("acme/app").unwrap(),
+            Location::Cloud {
+                repository: "https://api.bitbucket.org/2.0/repositories/acme/app".to_string()
+            }
+        );
+        assert_eq!(
+            cloud.locate("git@bitbucket.org:acme/app.git").unwrap(),
+            cloud.locate("acme/app").unwrap()
+        );
+        assert_eq!(
+            cloud.remote_url("acme/app"),
+            "https://bitbucket.org/acme/app.git"
+        );
+        assert_eq!(cloud.push_credentials().0, "x-token-auth");
+
+        let server = Bitbucket::new(
+            BitbucketConfig {
+                url: Some("https://bitbucket.example.com".to_string()),
+                username: Some("svc-upgrades".to_string()),
+                ..BitbucketConfig::default()
+            },
+            Duration::from_secs(1),
+        );
+        let expected = Location::Server {
+            base: "https://bitbucket.example.com".to_string(),
+            repository: "https://bitbucket.example.com/rest/api/1.0/projects/PLAT/repos/worker"
+                .to_string(),
+        };
+        assert_eq!(
+            server
+                .locate("https://bitbucket.example.com/scm/PLAT/worker.git")
+                .unwrap(),
+            expected
+        );
+        assert_eq!(server.locate("PLAT/worker").unwrap(), expected);
+        assert_eq!(
+            server.remote_url("PLAT/worker"),
+            "https://bitbucket.example.com/scm/PLAT/worker.git"
+        );
+        assert_eq!(server.push_credentials().0, "svc-upgrades");
+
+        let cloud_pull_request = pull_request(
+            &json!({"id": 3, "links": {"html": {"href": "https://bitbucket.org/acme/app/pull-requests/3"}}}),
+            0,
+        )
+        .unwrap();
+        assert_eq!(cloud_pull_request.number, 3);
+        let server_pull_request = pull_request(
+            &json!({"id": 12, "links": {"self": [{"href": "https://bitbucket.example.com/projects/PLAT/repos/worker/pull-requests/12"}]}}),
+            2,
+        )
+        .unwrap();
+        assert!(server_pull_request.url.ends_with("/pull-requests/12"));
+    }
+}
@@ -0,0 +1,233 @@
+use super::{
+    remote_host, repo, shorthand_url, split_repository, unknown_repository, Auth, Client,
+    CommitStatus, Proposal, PullRequest, ScmProvider, StatusState,
+};
+use crate::UpgradeError;
+use async_trait::async_trait;
+use serde_json::json;
+use std::time::Duration;
+
+// Username GitHub expects with installation and personal access tokens
+const TOKEN_USER: &str = "x-access-token";
+
+const GITHUB_URL: &str = "https://github.com";
+
+/// A github.com or GitHub Enterprise Server repository.
+#[derive(Debug, Clone, Default)]
+pub struct GitHubConfig {
+    /// Web URL of a GitHub Enterprise Server instance; github.com when
+    /// unset.
+    pub url: Option<String>,
+    /// Installation or personal access token allowed to push and to write
+    /// pull requests and statuses.
+    pub token: String,
+    /// Branch pull requests target; the repository's default branch when
+    /// unset.
+    pub target_branch: Option<String>,
+    pub labels: Vec<String>,
+}
+
+pub struct GitHub {
+    config: GitHubConfig,
+    client: Client,
+}
+
+impl GitHub {
+    pub fn new(config: GitHubConfig, timeout: Duration) -> Self {
+        let client = Client::new("GitHub", Auth::Bearer(config.token.clone()), timeout);
+        Self { config, client }
+    }
+
+    fn web_url(&self) -> &str {
+        self.config.url.as_deref().unwrap_or(GITHUB_URL)
+    }
+
+    /// `{api}/repos/{owner}/{repo}` and the owner of `repository`.
+    fn repository_url(&self, repository: &str) -> Result<(String, String), UpgradeError> {
+        let (base, path) = split_repository(repository, Some(self.web_url()))
+            .filter(|(_, path)| path.matches('/').count() == 1)
+            .ok_or_else(|| unknown_repository("GitHub", repository))?;
+        let api = if base == GITHUB_URL {
+            "https://api.github.com".to_string()
+        } else {
+            format!("{}/api/v3", base)
+        };
+        let owner = path.split('/').next().unwrap_or_default().to_string();
+        Ok((format!("{}/repos/{}", api, path), owner))
+    }
+}
+
+fn pull_request(body: &serde_json::Value, approvals_required: u32) -> Option<PullRequest> {
+    Some(PullRequest {
+        provider: "github".to_string(),
+        number: body["number"].as_u64()?,
+        url: body["html_url"].as_str()?.to_string(),
+        approvals_required,
+    })
+}
+
+#[async_trait]
+impl ScmProvider for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn remote_url(&self, repository: &str) -> String {
+        match &self.config.url {
+            Some(url) => {
+                shorthand_url(url, repository).unwrap_or_else(|| repo::remote_url(repository))
+            }
+            None => repo::remote_url(repository),
+        }
+    }
+
+    fn push_credentials(&self) -> (String, String) {
+        (TOKEN_USER.to_string(), self.config.token.clone())
+    }
+
+    fn host(&self) -> String {
+        remote_host(self.web_url()).unwrap_or_default()
+    }
+
+    async fn open_pull_request(
+        &self,
+        repository: &str,
+        proposal: &Proposal,
+        warnings: &mut Vec<String>,
+    ) -> Result<PullRequest, UpgradeError> {
+        let (repository_url, owner) = self.repository_url(repository)?;
+        let base = match &self.config.target_branch {
+            Some(branch) => branch.clone(),
+            None => self.client.get(&repository_url, &[]).await?["default_branch"]
+                .as_str()
+                .unwrap_or("main")
+                .to_string(),
+        };
+
+        let url = format!("{}/pulls", repository_url);
+        let payload = json!({
+            "head": proposal.branch,
+            "base": base,
+            "title": proposal.title,
+            "body": proposal.description,
+        });
+        let (status, body) = self.client.post(&url, &payload).await?;
+        let created = if status.is_success() {
+            body
+        } else if status == reqwest::StatusCode::UNPROCESSABLE_ENTITY {
+            // Raised, among others, for a pull request already open
+            let head = format!("{}:{}", owner, proposal.branch);
+            let open = self
+                .client
+                .get(&url, &[("head", &head), ("state", "open")])
+                .await?;
+            if open[0].is_null() {
+                return Err(self.client.error(&url, status, &body));
+            }
+            open[0].clone()
+        } else {
+            return Err(self.client.error(&url, status, &body));
+        };
+        let pull_request =
+            pull_request(&created, proposal.approvals_required()).ok_or_else(|| {
+                super::scm_error(format!(
+                    "GitHub returned no pull request for {}",
+                    proposal.branch
+                ))
+            })?;
+
+        if !self.config.labels.is_empty() {
+            let labels = format!("{}/issues/{}/labels", repository_url, pull_request.number);
+            let payload = json!({ "labels": self.config.labels });
+            if let Err(e) = self.client.create(&labels, &payload).await {
+                warnings.push(format!(
+                    "Labels not added to #{}: {}",
+                    pull_request.number, e
+                ));
+            }
+        }
+        Ok(pull_request)
+    }
+
+    async fn report_status(
+        &self,
+        repository: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> Result<(), UpgradeError> {
+        let state = match status.state {
+            StatusState::Pending => "pending",
+            StatusState::Success => "success",
+            StatusState::Failure => "failure",
+        };
+        let (repository_url, _) = self.repository_url(repository)?;
+        let url = format!("{}/statuses/{}", repository_url, sha);
+        let payload = json!({
+            "state": state,
+            "context": super::STATUS_CONTEXT,
+            "description": status.description,
+            "target_url": status.target_url,
+        });
+        self.client.create(&url, &payload).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_repositories() {
+        let github = GitHub::new(GitHubConfig::default(), Duration::from_secs(1));
+        assert_eq!(
+            github.repository_url("acme/app").unwrap(),
+            (
+                "https://api.github.com/repos/acme/app".to_string(),
+                "acme".to_string()
+            )
+        );
+        assert_eq!(
+            github
+                .repository_url("git@github.com:acme/app.git")
+                .unwrap()
+                .0,
+            "https://api.github.com/repos/acme/app"
+        );
+        assert!(github.repository_url("acme/app/extra").is_err());
+        // The token is only sent to the configured instance
+        for foreign in [
+            "https://evil.example/acme/app",
+            "http://evil.example/acme/app",
+            "git@evil.example:acme/app",
+        ] {
+            assert!(github.repository_url(foreign).is_err());
+        }
+
+        let enterprise = GitHub::new(
+            GitHubConfig {
+                url: Some("https://github.example.com".to_string()),
+                ..GitHubConfig::default()
+            },
+            Duration::from_secs(1),
+        );
+        assert_eq!(
+            enterprise.repository_url("acme/app").unwrap().0,
+            "https://github.example.com/api/v3/repos/acme/app"
+        );
+        assert!(enterprise
+            .repository_url("https://github.com/acme/app")
+            .is_err());
+        assert_eq!(
+            enterprise.remote_url("acme/app"),
+            "https://github.example.com/acme/app.git"
+        );
+
+        let pull_request = pull_request(
+            &json!({"number": 7, "html_url": "https://github.com/acme/app/pull/7"}),
+            1,
+        )
+        .unwrap();
+        assert_eq!(pull_request.number, 7);
+        assert_eq!(pull_request.approvals_required, 1);
+    }
+}
@@ -0,0 +1,248 @@
+use super::{
+    remote_host, repo, shorthand_url, split_repository, unknown_repository, Auth, Client,
+    CommitStatus, Proposal, PullRequest, ScmProvider, StatusState,
+};
+use crate::UpgradeError;
+use async_trait::async_trait;
+use serde_json::json;
+use std::time::Duration;
+
+// Username GitLab expects with OAuth, personal and project access tokens
+const TOKEN_USER: &str = "oauth2";
+
+const GITLAB_URL: &str = "https://gitlab.com";
+
+/// A GitLab.com or self-managed project, proposed on through merge
+/// requests.
+#[derive(Debug, Clone)]
+pub struct GitLabConfig {
+    /// Instance URL, including any path it is served under
+    /// (`https://git.example.com/gitlab`); GitLab.com when unset. The token
+    /// is only sent to this instance.
+    pub url: Option<String>,
+    /// Token with the `api` and `write_repository` scopes.
+    pub token: String,
+    /// Branch merge requests target; the project's default branch when unset.
+    pub target_branch: Option<String>,
+    /// Labels added next to the scoped `risk::<level>` label.
+    pub labels: Vec<String>,
+    /// Delete the upgrade branch once the merge request is merged.
+    pub remove_source_branch: bool,
+    /// Add an approval rule requiring [`super::approvals_required`]
+    /// approvals. Approval rules need GitLab Premium; without it the hint is
+    /// only written to the description.
+    pub approval_rules: bool,
+}
+
+impl Default for GitLabConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            token: String::new(),
+            target_branch: None,
+            labels: Vec::new(),
+            remove_source_branch: true,
+            approval_rules: false,
+        }
+    }
+}
+
+pub struct GitLab {
+    config: GitLabConfig,
+    client: Client,
+}
+
+impl GitLab {
+    pub fn new(config: GitLabConfig, timeout: Duration) -> Self {
+        let client = Client::new(
+            "GitLab",
+            Auth::Header("PRIVATE-TOKEN", config.token.clone()),
+            timeout,
+        );
+        Self { config, client }
+    }
+
+    fn web_url(&self) -> &str {
+        self.config.url.as_deref().unwrap_or(GITLAB_URL)
+    }
+
+    /// `{api}/projects/{id}` for `repository`.
+    fn project_url(&self, repository: &str) -> Result<String, UpgradeError> {
+        let (base, path) = split_repository(repository, Some(self.web_url()))
+            .ok_or_else(|| unknown_repository("GitLab", repository))?;
+        Ok(format!(
+            "{}/api/v4/projects/{}",
+            base,
+            path.replace('/', "%2F")
+        ))
+    }
+}
+
+fn merge_request(body: &serde_json::Value, approvals_required: u32) -> Option<PullRequest> {
+    Some(PullRequest {
+        provider: "gitlab".to_string(),
+        number: body["iid"].as_u64()?,
+        url: body["web_url"].as_str()?.to_string(),
+        approvals_required,
+    })
+}
+
+#[async_trait]
+impl ScmProvider for GitLab {
+    fn name(&self) -> &'static str {
+        "gitlab"
+    }
+
+    fn remote_url(&self, repository: &str) -> String {
+        self.config
+            .url
+            .as_deref()
+            .and_then(|url| shorthand_url(url, repository))
+            .unwrap_or_else(|| repo::remote_url(repository))
+    }
+
+    fn push_credentials(&self) -> (String, String) {
+        (TOKEN_USER.to_string(), self.config.token.clone())
+    }
+
+    fn host(&self) -> String {
+        self.config
+            .url
+            .as_deref()
+            .and_then(remote_host)
+            .unwrap_or_else(|| "gitlab.com".to_string())
+    }
+
+    async fn open_pull_request(
+        &self,
+        repository: &str,
+        proposal: &Proposal,
+        warnings: &mut Vec<String>,
+    ) -> Result<PullRequest, UpgradeError> {
+        let project = self.project_url(repository)?;
+        let target_branch = match &self.config.target_branch {
+            Some(branch) => branch.clone(),
+            None => self.client.get(&project, &[]).await?["default_branch"]
+                .as_str()
+                .unwrap_or("main")
+                .to_string(),
+        };
+
+        let approvals = proposal.approvals_required();
+        let mut labels = self.config.labels.clone();
+        labels.push(format!("risk::{}", proposal.risk()));
+        let payload = json!({
+            "source_branch": proposal.branch,
+            "target_branch": target_branch,
+            "title": proposal.title,
+            "description": proposal.description,
+            "labels": labels.join(","),
+            "remove_source_branch": self.config.remove_source_branch,
+        });
+
+        let url = format!("{}/merge_requests", project);
+        let (status, body) = self.client.post(&url, &payload).await?;
+        let created = if status.is_success() {
+            body
+        } else if status == reqwest::StatusCode::CONFLICT {
+            let open = self
+                .client
+                .get(
+                    &url,
+                    &[("source_branch", &proposal.branch), ("state", "opened")],
+                )
+                .await?;
+            open[0].clone()
+        } else {
+            return Err(self.client.error(&url, status, &body));
+        };
+        let merge_request = merge_request(&created, approvals).ok_or_else(|| {
+            super::scm_error(format!(
+                "GitLab returned no merge request for {}",
+                proposal.branch
+            ))
+        })?;
+
+        if self.config.approval_rules && approvals > 0 {
+            let rule = json!({
+                "name": format!("speccursor {} risk", proposal.risk()),
+                "approvals_required": approvals,
+            });
+            let rules = format!("{}/{}/approval_rules", url, merge_request.number);
+            if let Err(e) = self.client.create(&rules, &rule).await {
+                warnings.push(format!(
+                    "Approval rule not added to !{}: {}",
+                    merge_request.number, e
+                ));
+            }
+        }
+        Ok(merge_request)
+    }
+
+    async fn report_status(
+        &self,
+        repository: &str,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> Result<(), UpgradeError> {
+        let state = match status.state {
+            StatusState::Pending => "pending",
+            StatusState::Success => "success",
+            StatusState::Failure => "failed",
+        };
+        let url = format!("{}/statuses/{}", self.project_url(repository)?, sha);
+        let payload = json!({
+            "state": state,
+            "name": super::STATUS_CONTEXT,
+            "description": status.description,
+            "target_url": status.target_url,
+        });
+        self.client.create(&url, &payload).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitlab_projects() {
+        let gitlab = GitLab::new(
+            GitLabConfig {
+                url: Some("https://git.example.com/gitlab".to_string()),
+                ..GitLabConfig::default()
+            },
+            Duration::from_secs(1),
+        );
+        assert_eq!(
+            gitlab.project_url("platform/worker").unwrap(),
+            "https://git.example.com/gitlab/api/v4/projects/platform%2Fworker"
+        );
+        assert_eq!(
+            gitlab.remote_url("platform/api/worker"),
+            "https://git.example.com/gitlab/platform/api/worker.git"
+        );
+        assert!(gitlab
+            .project_url("https://evil.example/platform/worker")
+            .is_err());
+
+        let hosted = GitLab::new(GitLabConfig::default(), Duration::from_secs(1));
+        assert_eq!(
+            hosted
+                .project_url("git@gitlab.com:platform/worker.git")
+                .unwrap(),
+            "https://gitlab.com/api/v4/projects/platform%2Fworker"
+        );
+        assert!(hosted
+            .project_url("git@evil.example:platform/worker")
+            .is_err());
+
+        let merge_request = merge_request(
+            &json!({"iid": 42, "web_url": "https://git.example.com/gitlab/platform/worker/-/merge_requests/42"}),
+            2,
+        )
+        .unwrap();
+        assert_eq!(merge_request.number, 42);
+        assert_eq!(merge_request.provider, "gitlab");
+    }
+}
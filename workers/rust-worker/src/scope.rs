@@ -0,0 +1,134 @@
+use crate::{Change, ErrorType, UpgradeError, UpgradeRequest};
+use std::borrow::Cow;
+
+/// The request's `path_scope` as a repository-relative directory prefix
+/// ending in `/`; `None` when unset or the repository root.
+pub fn prefix(request: &UpgradeRequest) -> Result<Option<String>, UpgradeError> {
+    let Some(scope) = &request.path_scope else {
+        return Ok(None);
+    };
+    let mut components = Vec::new();
+    for component in scope.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                return Err(UpgradeError {
+                    message: format!("Path scope '{}' must stay inside the repository", scope),
+                    error_type: ErrorType::Validation,
+                })
+            }
+            component => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(format!("{}/", components.join("/"))))
+}
+
+fn directory(path: &str) -> &str {
+    &path[..path.rfind('/').map_or(0, |i| i + 1)]
+}
+
+/// The request as seen from the scoped component: only the files under the
+/// scope, so its risk is assessed on its own.
+pub fn scoped_request(request: &UpgradeRequest) -> Cow<'_, UpgradeRequest> {
+    match prefix(request) {
+        Ok(Some(prefix)) => Cow::Owned(UpgradeRequest {
+            manifests: request
+                .manifests
+                .iter()
+                .filter(|(path, _)| path.starts_with(&prefix))
+                .map(|(path, content)| (path.clone(), content.clone()))
+                .collect(),
+            ..request.clone()
+        }),
+        _ => Cow::Borrowed(request),
+    }
+}
+
+/// Drops the changes outside the scope. Lockfiles in a parent directory are
+/// kept, as they govern the scoped component too (workspace lockfiles live
+/// at the root).
+pub(crate) fn retain_changes(
+    request: &UpgradeRequest,
+    changes: &mut Vec<Change>,
+) -> Result<(), UpgradeError> {
+    let Some(prefix) = prefix(request)? else {
+        return Ok(());
+    };
+    let lockfile = |change: &Change| change.metadata.contains_key("lockfile_refresh");
+    let generated = !changes.is_empty();
+    changes.retain(|change| {
+        change.file_path.starts_with(&prefix)
+            || (lockfile(change) && prefix.starts_with(directory(&change.file_path)))
+    });
+    if generated && changes.iter().all(lockfile) {
+        return Err(UpgradeError {
+            message: format!(
+                "No manifest under '{}' declares '{}'",
+                prefix, request.package_name
+            ),
+            error_type: ErrorType::Validation,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UpgradeWorker;
+    use std::collections::HashMap;
+
+    fn request(path_scope: &str) -> UpgradeRequest {
+        let manifest = r#"{"dependencies": {"lodash": "^4.17.20"}}"#.to_string();
+        UpgradeRequest {
+            repository: "https://github.com/acme/monorepo".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "lodash".to_string(),
+            current_version: "4.17.20".to_string(),
+            target_version: "5.0.0".to_string(),
+            manifests: HashMap::from([
+                ("package.json".to_string(), manifest.clone()),
+                ("package-lock.json".to_string(), "{}".to_string()),
+                (
+                    "services/payments/package.json".to_string(),
+                    manifest.clone(),
+                ),
+                ("services/web/package.json".to_string(), manifest.clone()),
+                ("services/web/yarn.lock".to_string(), String::new()),
+            ]),
+            path_scope: Some(path_scope.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_path_scope() {
+        let worker = UpgradeWorker::new(None);
+        let response = worker
+            .process_upgrade(request("./services/payments"))
+            .await
+            .unwrap();
+        let mut paths: Vec<&str> = response
+            .changes
+            .iter()
+            .map(|change| change.file_path.as_str())
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            ["package-lock.json", "services/payments/package.json"]
+        );
+
+        // The root scope is the whole repository
+        let response = worker.process_upgrade(request("/")).await.unwrap();
+        assert_eq!(response.changes.len(), 5);
+
+        for scope in ["services/../../etc", "services/billing/"] {
+            let err = worker.process_upgrade(request(scope)).await.unwrap_err();
+            assert!(matches!(err.error_type, ErrorType::Validation));
+        }
+    }
+}
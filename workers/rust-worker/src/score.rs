@@ -0,0 +1,341 @@
+use crate::advisories::{SecurityIssue, Severity};
+use crate::verification::Verification;
+use crate::version::VersionJump;
+use crate::{ErrorType, UpgradeError};
+use serde::{Deserialize, Serialize};
+
+/// Signal given to popularity when the registry publishes no adoption
+/// data, and scaled by the adoption adjustments otherwise.
+pub const NEUTRAL_POPULARITY: f64 = 0.8;
+
+/// Weights the signals are combined with into the compatibility score;
+/// they must add up to 1. Set in `WorkerConfig::score_weights` or the
+/// `weights` table of a policy file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreWeights {
+    /// How well upgrades of the ecosystem usually go.
+    pub ecosystem: f64,
+    pub version_jump: f64,
+    /// Advisories of the target version.
+    pub security: f64,
+    /// Adoption of the target version.
+    pub popularity: f64,
+    /// Whether the upgraded checkout builds and passes its tests.
+    pub verification: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            ecosystem: 0.3,
+            version_jump: 0.25,
+            security: 0.2,
+            popularity: 0.15,
+            verification: 0.1,
+        }
+    }
+}
+
+impl ScoreWeights {
+    pub fn validate(&self) -> Result<(), UpgradeError> {
+        let weights = [
+            self.ecosystem,
+            self.version_jump,
+            self.security,
+            self.popularity,
+            self.verification,
+        ];
+        let invalid = |message: String| UpgradeError {
+            message,
+            error_type: ErrorType::Validation,
+        };
+        if weights.iter().any(|weight| !(0.0..=1.0).contains(weight)) {
+            return Err(invalid(
+                "Score weights must lie between 0 and 1".to_string(),
+            ));
+        }
+        let sum: f64 = weights.iter().sum();
+        if (sum - 1.0).abs() > 1e-6 {
+            return Err(invalid(format!(
+                "Score weights must add up to 1, not {}",
+                (sum * 1e6).round() / 1e6
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// An adjustment of a signal, e.g. the target's age scaling popularity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Adjustment {
+    /// `target_age`, `target_share`, `downloads` or `issue_spike`.
+    pub name: String,
+    pub multiplier: f64,
+    /// Where the adjustment's input came from: `registry` or
+    /// `issue_tracker`.
+    pub source: String,
+    pub detail: String,
+}
+
+impl Adjustment {
+    pub fn new(name: &str, multiplier: f64, source: &str, detail: String) -> Self {
+        Self {
+            name: name.to_string(),
+            multiplier,
+            source: source.to_string(),
+            detail,
+        }
+    }
+}
+
+/// One weighted signal of the compatibility score.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreFactor {
+    /// `ecosystem`, `version_jump`, `security`, `popularity` or
+    /// `verification`.
+    pub name: String,
+    pub weight: f64,
+    /// The signal, from 0 (certain to break) to 1 (safe).
+    pub signal: f64,
+    /// `weight * signal`: what the factor adds to the score.
+    pub contribution: f64,
+    /// Where the signal came from: `ecosystem:npm`, `risk_assessment`,
+    /// `advisories`, `registry`, `verification` or `worker`.
+    pub source: String,
+    pub detail: String,
+    /// What scaled the signal, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adjustments: Vec<Adjustment>,
+}
+
+impl ScoreFactor {
+    pub fn new(name: &str, signal: f64, source: &str, detail: String) -> Self {
+        Self {
+            name: name.to_string(),
+            weight: 0.0,
+            signal: signal.clamp(0.0, 1.0),
+            contribution: 0.0,
+            source: source.to_string(),
+            detail,
+            adjustments: Vec::new(),
+        }
+    }
+}
+
+/// How `UpgradeResponse::compatibility_score` was computed, in
+/// `UpgradeResponse::score_breakdown`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub factors: Vec<ScoreFactor>,
+    /// Sources of the factors and their adjustments, each listed once.
+    pub sources: Vec<String>,
+    pub score: f64,
+}
+
+impl ScoreBreakdown {
+    /// Adds `factor` with `weight`, recording its contribution.
+    pub fn add(&mut self, weight: f64, mut factor: ScoreFactor) {
+        factor.weight = weight;
+        factor.contribution = weight * factor.signal;
+        self.score += factor.contribution;
+        let sources = std::iter::once(&factor.source).chain(
+            factor
+                .adjustments
+                .iter()
+                .map(|adjustment| &adjustment.source),
+        );
+        for source in sources {
+            if !self.sources.contains(source) {
+                self.sources.push(source.clone());
+            }
+        }
+        self.factors.push(factor);
+    }
+}
+
+/// Patches are safe, minor and pre-release bumps mostly so, downgrades and
+/// major bumps least; breaking upgrades never score above a major bump.
+pub fn version_jump_factor(jump: VersionJump, breaking: bool) -> ScoreFactor {
+    let signal: f64 = match jump {
+        VersionJump::None | VersionJump::Patch => 1.0,
+        VersionJump::Minor => 0.85,
+        VersionJump::Prerelease => 0.8,
+        VersionJump::Downgrade => 0.6,
+        VersionJump::Major => 0.5,
+    };
+    let (signal, detail) = if breaking {
+        (
+            signal.min(0.5),
+            format!("{:?} upgrade with breaking changes", jump),
+        )
+    } else {
+        (signal, format!("{:?} upgrade", jump))
+    };
+    ScoreFactor::new("version_jump", signal, "risk_assessment", detail)
+}
+
+/// Advisories the upgrade does not fix lower the signal by the worst of
+/// their severities.
+pub fn security_factor(issues: &[SecurityIssue]) -> ScoreFactor {
+    let unfixed: Vec<&SecurityIssue> = issues
+        .iter()
+        .filter(|issue| !issue.fixed_by_upgrade)
+        .collect();
+    if unfixed.is_empty() {
+        return ScoreFactor::new(
+            "security",
+            1.0,
+            "advisories",
+            "no known advisory affects the target".to_string(),
+        );
+    }
+    let worst = unfixed.iter().filter_map(|issue| issue.severity).max();
+    let signal = match worst {
+        Some(Severity::Critical) => 0.0,
+        Some(Severity::High) => 0.25,
+        Some(Severity::Medium) => 0.5,
+        Some(Severity::Low) | None => 0.75,
+    };
+    ScoreFactor::new(
+        "security",
+        signal,
+        "advisories",
+        format!(
+            "{} advisor{} not fixed by the upgrade{}",
+            unfixed.len(),
+            if unfixed.len() == 1 { "y" } else { "ies" },
+            worst.map_or(String::new(), |severity| format!(
+                ", at worst {:?}",
+                severity
+            ))
+        ),
+    )
+}
+
+/// [`NEUTRAL_POPULARITY`] scaled by the adoption adjustments.
+pub fn popularity_factor(adjustments: Vec<Adjustment>) -> ScoreFactor {
+    let signal = adjustments
+        .iter()
+        .fold(NEUTRAL_POPULARITY, |signal, adjustment| {
+            signal * adjustment.multiplier
+        });
+    let detail = if adjustments.is_empty() {
+        "no adoption signal moves it".to_string()
+    } else {
+        adjustments
+            .iter()
+            .map(|adjustment| adjustment.detail.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+    ScoreFactor {
+        adjustments,
+        ..ScoreFactor::new("popularity", signal, "registry", detail)
+    }
+}
+
+/// A passing verification is safe and a failing one certain to break; an
+/// upgrade that was not verified is in between.
+pub fn verification_factor(verification: Option<&Verification>) -> ScoreFactor {
+    let (signal, detail) = match verification {
+        Some(verification) if verification.passed => {
+            (1.0, "the upgraded checkout builds and passes its tests")
+        }
+        Some(_) => (0.0, "the upgraded checkout fails to build or test"),
+        None => (0.5, "the upgrade was not verified"),
+    };
+    ScoreFactor::new("verification", signal, "verification", detail.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_must_add_up_to_one() {
+        assert!(ScoreWeights::default().validate().is_ok());
+        let heavy = ScoreWeights {
+            security: 0.5,
+            ..ScoreWeights::default()
+        };
+        assert_eq!(
+            heavy.validate().unwrap_err().message,
+            "Score weights must add up to 1, not 1.3"
+        );
+        let negative = ScoreWeights {
+            ecosystem: 0.6,
+            verification: -0.2,
+            ..ScoreWeights::default()
+        };
+        assert!(negative.validate().is_err());
+    }
+
+    #[test]
+    fn test_default_score_of_unverified_patch() {
+        let weights = ScoreWeights::default();
+        let mut breakdown = ScoreBreakdown::default();
+        breakdown.add(
+            weights.ecosystem,
+            ScoreFactor::new(
+                "ecosystem",
+                0.9,
+                "ecosystem:cargo",
+                "cargo upgrades".to_string(),
+            ),
+        );
+        breakdown.add(
+            weights.version_jump,
+            version_jump_factor(VersionJump::Patch, false),
+        );
+        breakdown.add(weights.security, security_factor(&[]));
+        breakdown.add(
+            weights.popularity,
+            popularity_factor(vec![Adjustment::new(
+                "target_share",
+                1.1,
+                "registry",
+                "25% of downloads go to the target".to_string(),
+            )]),
+        );
+        breakdown.add(weights.verification, verification_factor(None));
+
+        // 0.27 + 0.25 + 0.2 + 0.132 + 0.05
+        assert!((breakdown.score - 0.902).abs() < 1e-9);
+        assert_eq!(
+            breakdown.sources,
+            [
+                "ecosystem:cargo",
+                "risk_assessment",
+                "advisories",
+                "registry",
+                "verification"
+            ]
+        );
+        let total: f64 = breakdown.factors.iter().map(|f| f.contribution).sum();
+        assert!((total - breakdown.score).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_security_factor() {
+        let issue = |severity: Option<Severity>, fixed_by_upgrade: bool| SecurityIssue {
+            severity,
+            fixed_by_upgrade,
+            ..SecurityIssue::default()
+        };
+        assert_eq!(
+            security_factor(&[issue(Some(Severity::Critical), true)]).signal,
+            1.0
+        );
+        let factor = security_factor(&[
+            issue(Some(Severity::Medium), false),
+            issue(Some(Severity::High), false),
+        ]);
+        assert_eq!(factor.signal, 0.25);
+        assert_eq!(
+            factor.detail,
+            "2 advisories not fixed by the upgrade, at worst High"
+        );
+        assert_eq!(security_factor(&[issue(None, false)]).signal, 0.75);
+    }
+}
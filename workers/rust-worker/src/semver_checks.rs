@@ -0,0 +1,454 @@
+use crate::version::ResolvedVersions;
+use crate::{ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+mod typescript;
+
+pub use typescript::{collect, declarations, diff_declarations, Declaration, TypeDeclarations};
+
+/// A breaking change of a crate's public API, in
+/// [`crate::RiskAssessment::api_breaks`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiBreak {
+    /// cargo-semver-checks lint that found it (`function_missing`).
+    pub lint: String,
+    /// What the lint checks (`pub fn removed or renamed`).
+    pub summary: String,
+    /// The item concerned (`function tokio::spawn_local, previously in file
+    /// src/task.rs:12`).
+    pub item: String,
+}
+
+/// Diffs the public APIs of two versions of a package.
+#[async_trait]
+pub trait ApiDiff: Send + Sync {
+    /// Whether packages of the ecosystem can be diffed.
+    fn supports(&self, ecosystem: &str) -> bool;
+
+    /// Breaking changes of `target` relative to `current`.
+    async fn breaking_changes(
+        &self,
+        package: &str,
+        current: &str,
+        target: &str,
+    ) -> Result<Vec<ApiBreak>, UpgradeError>;
+}
+
+/// Runs `cargo semver-checks` on the target's published source, with the
+/// current version from crates.io as the baseline, in a temporary directory.
+pub struct SemverChecks {
+    client: reqwest::Client,
+    registry_url: String,
+    timeout: Duration,
+}
+
+impl SemverChecks {
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_registry_url("https://crates.io", timeout)
+    }
+
+    pub fn with_registry_url(registry_url: &str, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            registry_url: registry_url.trim_end_matches('/').to_string(),
+            timeout,
+        }
+    }
+
+    // Downloads and unpacks the `.crate` archive of a release under
+    // `directory`, returning the path of its manifest
+    async fn unpack(
+        &self,
+        directory: &Path,
+        package: &str,
+        version: &str,
+    ) -> Result<std::path::PathBuf, String> {
+        let url = format!(
+            "{}/api/v1/crates/{}/{}/download",
+            self.registry_url, package, version
+        );
+        unpack_archive(&self.client, &url, directory, self.timeout).await?;
+        Ok(directory
+            .join(format!("{}-{}", package, version))
+            .join("Cargo.toml"))
+    }
+}
+
+/// Runs `command`, killing it once `timeout` passes.
+pub(crate) async fn run(
+    command: &mut Command,
+    timeout: Duration,
+) -> Result<std::process::Output, String> {
+    let output = command.kill_on_drop(true).output();
+    tokio::time::timeout(timeout, output)
+        .await
+        .map_err(|_| format!("timed out after {}s", timeout.as_secs()))?
+        .map_err(|e| e.to_string())
+}
+
+/// Downloads the gzipped tarball at `url` and unpacks it into `directory`.
+pub(crate) async fn unpack_archive(
+    client: &reqwest::Client,
+    url: &str,
+    directory: &Path,
+    timeout: Duration,
+) -> Result<(), String> {
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("{} returned {}", url, response.status()));
+    }
+    let archive = directory.join("archive.tar.gz");
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    std::fs::write(&archive, &bytes).map_err(|e| e.to_string())?;
+    let output = run(
+        Command::new("tar")
+            .arg("-xzf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(directory),
+        timeout,
+    )
+    .await?;
+    if !output.status.success() {
+        return Err(format!("failed to unpack {}", url));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl ApiDiff for SemverChecks {
+    fn supports(&self, ecosystem: &str) -> bool {
+        ecosystem == "cargo"
+    }
+
+    async fn breaking_changes(
+        &self,
+        package: &str,
+        current: &str,
+        target: &str,
+    ) -> Result<Vec<ApiBreak>, UpgradeError> {
+        let failed = |reason: String| UpgradeError {
+            message: format!(
+                "cargo semver-checks of {} {} against {} failed: {}",
+                package, target, current, reason
+            ),
+            error_type: ErrorType::Internal,
+        };
+        let directory = tempfile::tempdir().map_err(|e| failed(e.to_string()))?;
+        let manifest = self
+            .unpack(directory.path(), package, target)
+            .await
+            .map_err(failed)?;
+
+        let output = run(
+            Command::new("cargo")
+                .args(["semver-checks", "check-release", "--color", "never"])
+                .arg("--manifest-path")
+                .arg(&manifest)
+                .args(["--baseline-version", current])
+                .current_dir(directory.path()),
+            self.timeout,
+        )
+        .await
+        .map_err(failed)?;
+        let report = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let breaks = parse_report(&report);
+        if !output.status.success() && breaks.is_empty() {
+            return Err(failed(
+                report
+                    .lines()
+                    .rfind(|line| !line.trim().is_empty())
+                    .unwrap_or("command failed")
+                    .trim()
+                    .to_string(),
+            ));
+        }
+        Ok(breaks)
+    }
+}
+
+/// The breaking changes a `cargo semver-checks` report lists, one per item
+/// of each failed lint:
+///
+/// ```text
+/// --- failure function_missing: pub fn removed or renamed ---
+/// ...
+/// Failed in:
+///   function tokio::spawn_local, previously in file src/task.rs:12
+/// ```
+pub fn parse_report(report: &str) -> Vec<ApiBreak> {
+    let mut breaks = Vec::new();
+    let mut lint: Option<(&str, &str)> = None;
+    let mut in_items = false;
+    for line in report.lines() {
+        if let Some(header) = line
+            .strip_prefix("--- failure ")
+            .and_then(|rest| rest.strip_suffix(" ---"))
+        {
+            lint = header.split_once(": ");
+            in_items = false;
+        } else if line.trim() == "Failed in:" {
+            in_items = lint.is_some();
+        } else if in_items && line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            let (name, summary) = lint.unwrap_or_default();
+            breaks.push(ApiBreak {
+                lint: name.to_string(),
+                summary: summary.to_string(),
+                item: line.trim().to_string(),
+            });
+        } else {
+            in_items = false;
+        }
+    }
+    breaks
+}
+
+/// Canned API diffs, useful for tests.
+#[derive(Debug, Clone, Default)]
+pub struct StaticApiDiff {
+    ecosystem: String,
+    breaks: HashMap<(String, String, String), Vec<ApiBreak>>,
+}
+
+impl StaticApiDiff {
+    /// Diffs of packages of `ecosystem`.
+    pub fn new(ecosystem: &str) -> Self {
+        Self {
+            ecosystem: ecosystem.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_breaks(
+        mut self,
+        package: &str,
+        current: &str,
+        target: &str,
+        breaks: Vec<ApiBreak>,
+    ) -> Self {
+        self.breaks.insert(
+            (package.to_string(), current.to_string(), target.to_string()),
+            breaks,
+        );
+        self
+    }
+}
+
+#[async_trait]
+impl ApiDiff for StaticApiDiff {
+    fn supports(&self, ecosystem: &str) -> bool {
+        ecosystem == self.ecosystem
+    }
+
+    async fn breaking_changes(
+        &self,
+        package: &str,
+        current: &str,
+        target: &str,
+    ) -> Result<Vec<ApiBreak>, UpgradeError> {
+        self.breaks
+            .get(&(package.to_string(), current.to_string(), target.to_string()))
+            .cloned()
+            .ok_or_else(|| UpgradeError {
+                message: format!("No API diff of {} {} to {}", package, current, target),
+                error_type: ErrorType::Internal,
+            })
+    }
+}
+
+impl UpgradeWorker {
+    /// Replaces the version-number guess at whether an upgrade breaks its
+    /// dependents with an API diff of the ecosystem, when one is configured.
+    /// The guess stands, with a warning, when the diff cannot be run, and
+    /// when an npm diff finds no breaks: type declarations leave runtime
+    /// behaviour out.
+    pub(crate) async fn check_api_breaks(
+        &self,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        let Some(diff) = self
+            .api_diffs
+            .iter()
+            .find(|diff| diff.supports(&request.ecosystem))
+        else {
+            return;
+        };
+        if request.replacement_package.is_some() || versions.target <= versions.current {
+            return;
+        }
+        let (current, target) = (versions.current.to_string(), versions.target.to_string());
+        let breaks = match diff
+            .breaking_changes(&request.package_name, &current, &target)
+            .await
+        {
+            Ok(breaks) => breaks,
+            Err(e) => {
+                warnings.push(format!("API diff skipped: {}", e.message));
+                return;
+            }
+        };
+
+        if breaks.is_empty() {
+            if risk.breaking_changes && request.ecosystem == "cargo" {
+                risk.breaking_changes = false;
+                risk.risk_level = risk.risk_level.min(RiskLevel::Medium);
+                risk.explanations.push(format!(
+                    "cargo-semver-checks found no breaking API changes from {} {} to {}",
+                    request.package_name, current, target
+                ));
+            }
+        } else {
+            risk.breaking_changes = true;
+            risk.risk_level = risk.risk_level.max(RiskLevel::High);
+            risk.explanations.push(format!(
+                "{} {} breaks {} public API item{} of {}",
+                request.package_name,
+                target,
+                breaks.len(),
+                if breaks.len() == 1 { "" } else { "s" },
+                current
+            ));
+        }
+        risk.api_breaks = Some(breaks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const REPORT: &str = "     Parsing tokio v1.40.0 (current)
+     Checking tokio v1.30.0 -> v1.40.0 (minor change)
+   Completed [   0.451s] 85 checks; 83 passed, 2 failed, 0 unnecessary
+
+--- failure function_missing: pub fn removed or renamed ---
+
+Description:
+A publicly-visible function cannot be imported by its prior path. A `pub use` may have been removed, or the function itself may have been renamed or removed entirely.
+        ref: https://doc.rust-lang.org/cargo/reference/semver.html#item-remove
+       impl: https://github.com/obi1kenobi/cargo-semver-checks/tree/v0.35.0/src/lints/function_missing.ron
+
+Failed in:
+  function tokio::task::spawn_local_on, previously in file /tmp/tokio-1.30.0/src/task/local.rs:321
+  function tokio::time::sleep_ms, previously in file /tmp/tokio-1.30.0/src/time/sleep.rs:80
+
+--- failure enum_variant_added: enum variant added on exhaustive enum ---
+
+Description:
+A publicly-visible enum without #[non_exhaustive] has a new variant.
+        ref: https://doc.rust-lang.org/cargo/reference/semver.html#enum-variant-new
+       impl: https://github.com/obi1kenobi/cargo-semver-checks/tree/v0.35.0/src/lints/enum_variant_added.ron
+
+Failed in:
+  variant RuntimeFlavor:MultiThreadAlt in /tmp/tokio-1.40.0/src/runtime/runtime.rs:110
+
+     Summary semver requires new major version: 2 major and 0 minor checks failed
+";
+
+    #[test]
+    fn test_parse_report() {
+        let breaks = parse_report(REPORT);
+        assert_eq!(breaks.len(), 3);
+        assert_eq!(
+            breaks[0],
+            ApiBreak {
+                lint: "function_missing".to_string(),
+                summary: "pub fn removed or renamed".to_string(),
+                item: "function tokio::task::spawn_local_on, previously in file /tmp/tokio-1.30.0/src/task/local.rs:321"
+                    .to_string(),
+            }
+        );
+        assert_eq!(breaks[2].lint, "enum_variant_added");
+        assert!(parse_report("   Completed [   0.451s] 85 checks; 85 passed\n").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_breaks_replace_version_heuristic() {
+        let request = |current: &str, target: &str| UpgradeRequest {
+            repository: "acme/service".to_string(),
+            ecosystem: "cargo".to_string(),
+            package_name: "tokio".to_string(),
+            current_version: current.to_string(),
+            target_version: target.to_string(),
+            manifests: HashMap::from([(
+                "Cargo.toml".to_string(),
+                format!("[dependencies]\ntokio = \"{}\"\n", current),
+            )]),
+            ..Default::default()
+        };
+        let diff = StaticApiDiff::new("cargo")
+            .with_breaks("tokio", "0.3.7", "0.4.0", Vec::new())
+            .with_breaks("tokio", "1.30.0", "1.40.0", parse_report(REPORT));
+        let worker = UpgradeWorker::new(None).with_api_diff(Arc::new(diff));
+
+        // A 0.x minor bump is only assumed to break
+        let risk = worker
+            .process_upgrade(request("0.3.7", "0.4.0"))
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert!(!risk.breaking_changes);
+        assert_eq!(risk.risk_level, RiskLevel::Medium);
+        assert_eq!(risk.api_breaks, Some(Vec::new()));
+
+        let risk = worker
+            .process_upgrade(request("1.30.0", "1.40.0"))
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert!(risk.breaking_changes);
+        assert_eq!(risk.risk_level, RiskLevel::High);
+        assert_eq!(risk.api_breaks.map(|breaks| breaks.len()), Some(3));
+        assert!(risk
+            .explanations
+            .contains(&"tokio 1.40.0 breaks 3 public API items of 1.30.0".to_string()));
+
+        let response = worker
+            .process_upgrade(request("1.30.0", "1.41.0"))
+            .await
+            .unwrap();
+        assert!(response.risk_assessment.api_breaks.is_none());
+        assert!(response.warnings[0].starts_with("API diff skipped"));
+
+        // Declarations leave runtime behaviour out, so an empty npm diff
+        // keeps the major bump high
+        let diff = StaticApiDiff::new("npm").with_breaks("chalk", "4.1.2", "5.0.0", Vec::new());
+        let worker = UpgradeWorker::new(None).with_api_diff(Arc::new(diff));
+        let risk = worker
+            .process_upgrade(UpgradeRequest {
+                ecosystem: "npm".to_string(),
+                package_name: "chalk".to_string(),
+                manifests: HashMap::from([(
+                    "package.json".to_string(),
+                    r#"{"dependencies": {"chalk": "4.1.2"}}"#.to_string(),
+                )]),
+                ..request("4.1.2", "5.0.0")
+            })
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::High);
+        assert_eq!(risk.api_breaks, Some(Vec::new()));
+    }
+}
@@ -0,0 +1,487 @@
+use super::{unpack_archive, ApiBreak, ApiDiff};
+use crate::{ErrorType, UpgradeError};
+use async_trait::async_trait;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+// Signatures quoted in a break are cut to this many characters
+const MAX_SIGNATURE_LENGTH: usize = 120;
+
+/// An exported declaration of a package's type declarations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    /// `function`, `class`, `member`, ...
+    pub kind: String,
+    /// The declaration with its whitespace collapsed; overloads are joined
+    /// by newlines.
+    pub signature: String,
+    /// `.d.ts` file declaring it, relative to the package root.
+    pub file: String,
+}
+
+/// Diffs the exported declarations of the `.d.ts` files two versions of an
+/// npm package ship, reporting removed and changed ones.
+pub struct TypeDeclarations {
+    client: reqwest::Client,
+    registry_url: String,
+    timeout: Duration,
+}
+
+impl TypeDeclarations {
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_registry_url("https://registry.npmjs.org", timeout)
+    }
+
+    pub fn with_registry_url(registry_url: &str, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                "speccursor-rust-worker/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            registry_url: registry_url.trim_end_matches('/').to_string(),
+            timeout,
+        }
+    }
+
+    // Unpacks a release into `directory` and reads its declarations
+    async fn declarations_of(
+        &self,
+        directory: &Path,
+        package: &str,
+        version: &str,
+    ) -> Result<BTreeMap<String, Declaration>, String> {
+        let url = format!(
+            "{}/{}/{}",
+            self.registry_url,
+            package.replace('/', "%2F"),
+            version
+        );
+        let manifest: serde_json::Value = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let tarball = manifest["dist"]["tarball"]
+            .as_str()
+            .ok_or_else(|| format!("{} lists no tarball", url))?;
+        unpack_archive(&self.client, tarball, directory, self.timeout).await?;
+
+        let files: Vec<(String, String)> = WalkDir::new(directory)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != "node_modules")
+            .filter_map(Result::ok)
+            .filter(|entry| is_declaration_file(&entry.file_name().to_string_lossy()))
+            .filter_map(|entry| {
+                // Paths are taken below the tarball's top directory
+                let relative = entry.path().strip_prefix(directory).ok()?;
+                let relative: Vec<_> = relative.components().skip(1).collect();
+                let path = relative
+                    .iter()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Some((path, std::fs::read_to_string(entry.path()).ok()?))
+            })
+            .collect();
+        if files.is_empty() {
+            return Err(format!(
+                "{} {} ships no type declarations",
+                package, version
+            ));
+        }
+        Ok(collect(
+            files
+                .iter()
+                .map(|(path, text)| (path.as_str(), text.as_str())),
+        ))
+    }
+}
+
+#[async_trait]
+impl ApiDiff for TypeDeclarations {
+    fn supports(&self, ecosystem: &str) -> bool {
+        matches!(ecosystem, "npm" | "pnpm")
+    }
+
+    async fn breaking_changes(
+        &self,
+        package: &str,
+        current: &str,
+        target: &str,
+    ) -> Result<Vec<ApiBreak>, UpgradeError> {
+        let failed = |reason: String| UpgradeError {
+            message: format!(
+                "Type declarations of {} {} and {} could not be diffed: {}",
+                package, current, target, reason
+            ),
+            error_type: ErrorType::Internal,
+        };
+        let directory = tempfile::tempdir().map_err(|e| failed(e.to_string()))?;
+        let (before, after) = (
+            directory.path().join("current"),
+            directory.path().join("target"),
+        );
+        for path in [&before, &after] {
+            std::fs::create_dir(path).map_err(|e| failed(e.to_string()))?;
+        }
+        let before = self
+            .declarations_of(&before, package, current)
+            .await
+            .map_err(failed)?;
+        let after = self
+            .declarations_of(&after, package, target)
+            .await
+            .map_err(failed)?;
+        Ok(diff_declarations(&before, &after))
+    }
+}
+
+fn is_declaration_file(name: &str) -> bool {
+    [".d.ts", ".d.mts", ".d.cts"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+}
+
+fn declaration_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^export\s+(?:declare\s+)?(?:default\s+)?(?:abstract\s+)?(?:async\s+)?(const\s+enum|function|const|let|var|class|interface|type|enum|namespace|module)\s+([\w$]+)",
+        )
+        .expect("valid pattern")
+    })
+}
+
+fn reexport_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"^export\s+(?:type\s+)?\{([^}]*)\}(?:\s*from\s*(['"][^'"]+['"]))?"#)
+            .expect("valid pattern")
+    })
+}
+
+fn block_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(?:export\s+)?(?:declare\s+)?(?:default\s+)?(?:abstract\s+)?(?:const\s+)?(?:interface|class|namespace|module|enum|global)\b")
+            .expect("valid pattern")
+    })
+}
+
+fn member_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"^(?:(?:export|declare|public|static|readonly|abstract|async|get|set|function|const|let|var|class|interface|type|enum|namespace)\s+)*(new\b|[\w$]+|\[[^\]]*\]|"[^"]*"|'[^']*')"#)
+            .expect("valid pattern")
+    })
+}
+
+/// Top-level statements of declarations text, split at `separator` and at
+/// the end of interface, class, namespace and enum blocks, with comments
+/// dropped and whitespace collapsed.
+fn statements(text: &str, separator: char) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0i32;
+    let mut chars = text.chars().peekable();
+    let mut finish = |current: &mut String| {
+        let statement = current.split_whitespace().collect::<Vec<_>>().join(" ");
+        let statement = statement.trim_end_matches(separator).trim();
+        if !statement.is_empty() {
+            statements.push(statement.to_string());
+        }
+        current.clear();
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|next| *next != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+                current.push(' ');
+                continue;
+            }
+            '"' | '\'' | '`' => {
+                current.push(c);
+                while let Some(next) = chars.next() {
+                    current.push(next);
+                    if next == '\\' {
+                        current.extend(chars.next());
+                    } else if next == c {
+                        break;
+                    }
+                }
+                continue;
+            }
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        current.push(c);
+        if depth == 0
+            && (c == separator || (c == '}' && block_pattern().is_match(current.trim_start())))
+        {
+            finish(&mut current);
+        }
+    }
+    finish(&mut current);
+    statements
+}
+
+/// The header and body of a block statement (`class A extends B { ... }`).
+fn split_block(statement: &str) -> Option<(&str, &str)> {
+    let open = statement.find('{')?;
+    let body = statement[open + 1..].strip_suffix('}')?;
+    Some((statement[..open].trim(), body))
+}
+
+/// Exported declarations of one `.d.ts` file, by name. Members of exported
+/// classes, interfaces, enums and namespaces are named `Outer.member`.
+pub fn declarations(file: &str, text: &str) -> Vec<(String, Declaration)> {
+    let declaration = |kind: &str, signature: &str| Declaration {
+        kind: kind.to_string(),
+        signature: signature.to_string(),
+        file: file.to_string(),
+    };
+    let mut found = Vec::new();
+    for statement in statements(text, ';') {
+        if let Some(caps) = declaration_pattern().captures(&statement) {
+            let kind = caps[1].split_whitespace().last().unwrap_or_default();
+            let name = caps[2].to_string();
+            let Some((header, body)) = split_block(&statement).filter(|_| {
+                matches!(
+                    kind,
+                    "class" | "interface" | "enum" | "namespace" | "module"
+                )
+            }) else {
+                found.push((name, declaration(kind, &statement)));
+                continue;
+            };
+            found.push((name.clone(), declaration(kind, header)));
+            let separator = if kind == "enum" { ',' } else { ';' };
+            for member in statements(body, separator) {
+                if member.starts_with("private ") || member.starts_with('#') {
+                    continue;
+                }
+                let member_name = member_pattern()
+                    .captures(&member)
+                    .map_or("()", |caps| caps.get(1).map_or("()", |m| m.as_str()));
+                found.push((
+                    format!("{}.{}", name, member_name),
+                    declaration("member", &member),
+                ));
+            }
+        } else if let Some(caps) = reexport_pattern().captures(&statement) {
+            let from = caps.get(2).map(|from| from.as_str());
+            for specifier in caps[1].split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                let specifier = specifier.trim_start_matches("type ");
+                let (local, exported) = specifier
+                    .split_once(" as ")
+                    .unwrap_or((specifier, specifier));
+                let signature = match from {
+                    Some(from) => format!("{} from {}", local.trim(), from),
+                    None => local.trim().to_string(),
+                };
+                found.push((
+                    exported.trim().to_string(),
+                    declaration("export", &signature),
+                ));
+            }
+        } else if let Some(rest) = statement.strip_prefix("export default ") {
+            found.push(("default".to_string(), declaration("default export", rest)));
+        } else if let Some(rest) = statement.strip_prefix("export =") {
+            found.push(("export=".to_string(), declaration("export", rest.trim())));
+        }
+    }
+    found
+}
+
+/// The declarations of a package's `.d.ts` files, by name. Overloads in a
+/// file are merged; a name exported from several files keeps the
+/// declaration of the first file by path.
+pub fn collect<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> BTreeMap<String, Declaration> {
+    let mut files: Vec<(&str, &str)> = files.into_iter().collect();
+    files.sort();
+    let mut all: BTreeMap<String, Declaration> = BTreeMap::new();
+    for (file, text) in files {
+        for (name, declaration) in declarations(file, text) {
+            match all.get_mut(&name) {
+                Some(existing) if existing.file == declaration.file => {
+                    existing.signature.push('\n');
+                    existing.signature.push_str(&declaration.signature);
+                }
+                Some(_) => {}
+                None => {
+                    all.insert(name, declaration);
+                }
+            }
+        }
+    }
+    all
+}
+
+fn shorten(signature: &str) -> String {
+    let signature = signature.replace('\n', " | ");
+    match signature.char_indices().nth(MAX_SIGNATURE_LENGTH) {
+        Some((end, _)) => format!("{}…", &signature[..end]),
+        None => signature,
+    }
+}
+
+/// Declarations of `current` that `target` removes or changes.
+pub fn diff_declarations(
+    current: &BTreeMap<String, Declaration>,
+    target: &BTreeMap<String, Declaration>,
+) -> Vec<ApiBreak> {
+    current
+        .iter()
+        .filter_map(|(name, before)| match target.get(name) {
+            None => Some(ApiBreak {
+                lint: "export_removed".to_string(),
+                summary: format!("exported {} removed", before.kind),
+                item: format!("{} {}, previously in {}", before.kind, name, before.file),
+            }),
+            Some(after) if after.signature != before.signature => Some(ApiBreak {
+                lint: "export_changed".to_string(),
+                summary: format!("exported {} changed", before.kind),
+                item: format!(
+                    "{} {} in {}: `{}` is now `{}`",
+                    before.kind,
+                    name,
+                    after.file,
+                    shorten(&before.signature),
+                    shorten(&after.signature)
+                ),
+            }),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENT: &str = r#"/**
+ * Creates a debounced function.
+ */
+export declare function debounce<T extends (...args: any[]) => any>(func: T, wait?: number): T;
+export declare function throttle(func: () => void, wait: number): () => void;
+export declare const VERSION: string;
+export interface Options {
+    leading?: boolean;
+    trailing?: boolean; // Defaults to true
+    maxWait?: number;
+}
+export declare class Queue<T> {
+    private items;
+    constructor(limit: number);
+    push(item: T): void;
+    pop(): T | undefined;
+}
+export declare enum Level { Low = 0, High = 1 }
+export type Handler = (event: string) => void;
+export { debounce as default };
+"#;
+
+    const TARGET: &str = r#"export declare function debounce<T extends (...args: any[]) => any>(func: T, wait?: number): T;
+export declare function throttle(func: () => void, wait: number, options?: Options): () => void;
+export declare const VERSION: string;
+export interface Options {
+    leading?: boolean;
+    trailing?: boolean;
+    signal?: AbortSignal;
+}
+export declare class Queue<T> {
+    private items;
+    private size;
+    constructor(limit: number);
+    push(item: T): void;
+}
+export declare enum Level { Low = 0, High = 1, Critical = 2 }
+export type Handler = (event: string) => void;
+export { debounce as default };
+"#;
+
+    #[test]
+    fn test_declarations() {
+        let declarations = collect([("index.d.ts", CURRENT)]);
+        let names: Vec<&str> = declarations.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "Handler",
+                "Level",
+                "Level.High",
+                "Level.Low",
+                "Options",
+                "Options.leading",
+                "Options.maxWait",
+                "Options.trailing",
+                "Queue",
+                "Queue.constructor",
+                "Queue.pop",
+                "Queue.push",
+                "VERSION",
+                "debounce",
+                "default",
+                "throttle",
+            ]
+        );
+        assert_eq!(
+            declarations["Queue"].signature,
+            "export declare class Queue<T>"
+        );
+        assert_eq!(
+            declarations["Options.trailing"].signature,
+            "trailing?: boolean"
+        );
+        assert_eq!(declarations["Level.High"].signature, "High = 1");
+    }
+
+    #[test]
+    fn test_diff_declarations() {
+        let breaks = diff_declarations(
+            &collect([("index.d.ts", CURRENT)]),
+            &collect([("index.d.ts", TARGET)]),
+        );
+        let items: Vec<&str> = breaks.iter().map(|b| b.item.as_str()).collect();
+        assert_eq!(
+            items,
+            [
+                "member Options.maxWait, previously in index.d.ts",
+                "member Queue.pop, previously in index.d.ts",
+                "function throttle in index.d.ts: `export declare function throttle(func: () => void, wait: number): () => void` \
+                 is now `export declare function throttle(func: () => void, wait: number, options?: Options): () => void`",
+            ]
+        );
+        assert_eq!(breaks[0].lint, "export_removed");
+        assert_eq!(breaks[2].summary, "exported function changed");
+    }
+}
@@ -0,0 +1,192 @@
+use crate::credentials::Secret;
+use crate::{ErrorType, UpgradeError};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// How upgrade commits are signed, for branches that only accept signed
+/// commits. Signatures are made with `gpg` or `ssh-keygen`, as git does.
+#[derive(Debug, Clone)]
+pub struct SigningConfig {
+    pub format: SigningFormat,
+    /// The private key: an ASCII armored secret key for GPG, an OpenSSH
+    /// private key for SSH.
+    pub key: Secret,
+    /// Key to sign with when the GPG key material holds several; the
+    /// first secret key otherwise.
+    pub key_id: Option<String>,
+    /// Passphrase of the GPG key. SSH keys must not be passphrase
+    /// protected.
+    pub passphrase: Option<Secret>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigningFormat {
+    /// OpenPGP signature of an ASCII armored secret key.
+    Gpg,
+    /// SSH signature (`gpg.format = ssh`) of an OpenSSH private key.
+    Ssh,
+}
+
+fn signing_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Internal,
+    }
+}
+
+// Runs `command` with `input` on stdin and returns its stdout
+fn run(mut command: Command, input: &[u8]) -> Result<Vec<u8>, UpgradeError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| signing_error(format!("Failed to run {}: {}", program, e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input)
+            .map_err(|e| signing_error(format!("Failed to write to {}: {}", program, e)))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| signing_error(format!("Failed to run {}: {}", program, e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(signing_error(format!(
+            "{} failed to sign: {}",
+            program,
+            stderr.lines().last().unwrap_or("no output")
+        )));
+    }
+    Ok(output.stdout)
+}
+
+// Writes a secret readable only by the worker
+fn write_secret(path: &Path, content: &str) -> Result<(), UpgradeError> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| signing_error(format!("Failed to stage the signing key: {}", e)))
+}
+
+fn sign_ssh(key: &str, payload: &[u8], directory: &Path) -> Result<Vec<u8>, UpgradeError> {
+    let key_file = directory.join("signing_key");
+    write_secret(&key_file, key)?;
+    let mut command = Command::new("ssh-keygen");
+    command
+        .args(["-Y", "sign", "-n", "git", "-f"])
+        .arg(&key_file);
+    run(command, payload)
+}
+
+fn sign_gpg(
+    config: &SigningConfig,
+    key: &str,
+    payload: &[u8],
+    directory: &Path,
+) -> Result<Vec<u8>, UpgradeError> {
+    // A keyring of its own keeps the key out of the worker's
+    let home = directory.join("gnupg");
+    std::fs::create_dir(&home)
+        .map_err(|e| signing_error(format!("Failed to create a GPG home: {}", e)))?;
+    #[cfg(unix)]
+    std::fs::set_permissions(&home, std::os::unix::fs::PermissionsExt::from_mode(0o700))
+        .map_err(|e| signing_error(format!("Failed to create a GPG home: {}", e)))?;
+
+    // The passphrase goes through a file to stay out of the process list
+    let passphrase = config.passphrase.as_ref().map(Secret::read).transpose()?;
+    let passphrase_file = directory.join("passphrase");
+    if let Some(passphrase) = &passphrase {
+        write_secret(&passphrase_file, passphrase)?;
+    }
+    let gpg = || {
+        let mut command = Command::new("gpg");
+        command.arg("--homedir").arg(&home).args([
+            "--batch",
+            "--quiet",
+            "--pinentry-mode",
+            "loopback",
+        ]);
+        if passphrase.is_some() {
+            command.arg("--passphrase-file").arg(&passphrase_file);
+        }
+        command
+    };
+    let mut import = gpg();
+    import.arg("--import");
+    run(import, key.as_bytes())?;
+
+    let mut sign = gpg();
+    sign.args(["--armor", "--detach-sign"]);
+    if let Some(key_id) = &config.key_id {
+        sign.args(["--local-user", key_id]);
+    }
+    run(sign, payload)
+}
+
+/// Signs a commit object made by `git2::Repository::commit_create_buffer`,
+/// returning the armored signature for its `gpgsig` header.
+pub fn sign_commit(config: &SigningConfig, payload: &str) -> Result<String, UpgradeError> {
+    // ssh-keygen insists on the final newline
+    let key = format!("{}\n", config.key.read()?);
+    let directory = tempfile::Builder::new()
+        .prefix("speccursor-signing-")
+        .tempdir()
+        .map_err(|e| signing_error(format!("Failed to stage the signing key: {}", e)))?;
+    let signature = match config.format {
+        SigningFormat::Ssh => sign_ssh(&key, payload.as_bytes(), directory.path())?,
+        SigningFormat::Gpg => sign_gpg(config, &key, payload.as_bytes(), directory.path())?,
+    };
+    String::from_utf8(signature)
+        .map_err(|_| signing_error("The signature is not ASCII armored".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssh_signature() {
+        let directory = tempfile::tempdir().unwrap();
+        let key = directory.path().join("id_ed25519");
+        let generated = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "speccursor", "-f"])
+            .arg(&key)
+            .status();
+        if !generated.is_ok_and(|status| status.success()) {
+            // ssh-keygen is not installed
+            return;
+        }
+
+        let config = SigningConfig {
+            format: SigningFormat::Ssh,
+            key: Secret::Inline(std::fs::read_to_string(&key).unwrap().trim().to_string()),
+            key_id: None,
+            passphrase: None,
+        };
+        let payload = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\nBump lodash\n";
+        let signature = sign_commit(&config, payload).unwrap();
+        assert!(signature.starts_with("-----BEGIN SSH SIGNATURE-----"));
+
+        let signature_file = directory.path().join("signature");
+        std::fs::write(&signature_file, &signature).unwrap();
+        let mut check = Command::new("ssh-keygen");
+        check
+            .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+            .arg(&signature_file);
+        run(check, payload.as_bytes()).unwrap();
+
+        let missing = SigningConfig {
+            key: Secret::File(directory.path().join("missing")),
+            ..config
+        };
+        let error = sign_commit(&missing, payload).unwrap_err();
+        assert!(matches!(error.error_type, ErrorType::Validation));
+    }
+}
@@ -0,0 +1,445 @@
+mod publish;
+
+pub use publish::{publish_signals, PublishSignal};
+
+use crate::registry::ReleaseInfo;
+use crate::version::{ParsedVersion, ResolvedVersions};
+use crate::{RiskAssessment, RiskLevel, UpgradeRequest, UpgradeWorker};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Packages first published this recently are treated as brand new.
+const NEW_PACKAGE_DAYS: i64 = 30;
+
+/// Recent downloads under which a brand-new package is suspicious.
+const FEW_DOWNLOADS: u64 = 1000;
+
+// Names shorter than this are too close to too many others to compare
+const MIN_TYPOSQUAT_LEN: usize = 4;
+
+/// Widely used packages of each ecosystem, the usual typosquatting targets.
+const POPULAR_PACKAGES: &[(&str, &[&str])] = &[
+    (
+        "npm",
+        &[
+            "axios",
+            "babel-core",
+            "body-parser",
+            "chalk",
+            "commander",
+            "cross-env",
+            "debug",
+            "dotenv",
+            "eslint",
+            "express",
+            "glob",
+            "jquery",
+            "lodash",
+            "moment",
+            "mongoose",
+            "nodemon",
+            "prettier",
+            "react",
+            "react-dom",
+            "redux",
+            "request",
+            "rimraf",
+            "semver",
+            "typescript",
+            "underscore",
+            "uuid",
+            "webpack",
+            "yargs",
+        ],
+    ),
+    (
+        "cargo",
+        &[
+            "anyhow",
+            "base64",
+            "bytes",
+            "chrono",
+            "clap",
+            "crossbeam",
+            "futures",
+            "hyper",
+            "itertools",
+            "lazy_static",
+            "libc",
+            "log",
+            "rand",
+            "rayon",
+            "regex",
+            "reqwest",
+            "serde",
+            "serde_json",
+            "syn",
+            "thiserror",
+            "tokio",
+            "tracing",
+            "url",
+            "uuid",
+        ],
+    ),
+    (
+        "pip",
+        &[
+            "beautifulsoup4",
+            "boto3",
+            "certifi",
+            "cryptography",
+            "django",
+            "flask",
+            "jinja2",
+            "matplotlib",
+            "numpy",
+            "pandas",
+            "pillow",
+            "pytest",
+            "python-dateutil",
+            "pyyaml",
+            "requests",
+            "scikit-learn",
+            "scipy",
+            "setuptools",
+            "six",
+            "urllib3",
+        ],
+    ),
+];
+
+/// Signs that a package may be malicious, in
+/// [`crate::RiskAssessment::supply_chain_flags`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SupplyChainFlag {
+    /// The name is one edit away from a popular package.
+    PossibleTyposquat { package: String, similar_to: String },
+    /// First published recently and barely downloaded since.
+    NewPackage {
+        package: String,
+        first_published: String,
+        downloads: Option<u64>,
+    },
+    /// The target version runs scripts on install that the current one did
+    /// not.
+    InstallScripts {
+        package: String,
+        version: String,
+        scripts: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for SupplyChainFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SupplyChainFlag::PossibleTyposquat {
+                package,
+                similar_to,
+            } => write!(
+                f,
+                "{} is one edit away from the popular package {}; it may be a typosquat",
+                package, similar_to
+            ),
+            SupplyChainFlag::NewPackage {
+                package,
+                first_published,
+                downloads,
+            } => {
+                write!(f, "{} was first published {}", package, first_published)?;
+                match downloads {
+                    Some(downloads) => write!(f, " and has {} recent downloads", downloads),
+                    None => Ok(()),
+                }
+            }
+            SupplyChainFlag::InstallScripts {
+                package,
+                version,
+                scripts,
+            } => write!(
+                f,
+                "{} {} adds install scripts: {}",
+                package,
+                version,
+                scripts.join(", ")
+            ),
+        }
+    }
+}
+
+// Registries treat `-`, `_` and `.` alike in some ecosystems (PyPI) and
+// typosquats swap them in the others
+fn normalize(name: &str) -> String {
+    name.to_ascii_lowercase().replace(['_', '.'], "-")
+}
+
+/// Edits (insertions, deletions, substitutions and swaps of adjacent
+/// characters) turning one name into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(rows[i - 1][j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The popular package `name` is one edit away from, unless it is that
+/// package.
+pub fn similar_popular_package(ecosystem: &str, name: &str) -> Option<&'static str> {
+    let ecosystem = match ecosystem {
+        "pnpm" => "npm",
+        "pipenv" => "pip",
+        ecosystem => ecosystem,
+    };
+    let (_, popular) = POPULAR_PACKAGES
+        .iter()
+        .find(|(known, _)| *known == ecosystem)?;
+    let name = normalize(name);
+    if name.len() < MIN_TYPOSQUAT_LEN || popular.iter().any(|known| normalize(known) == name) {
+        return None;
+    }
+    popular
+        .iter()
+        .find(|known| edit_distance(&normalize(known), &name) == 1)
+        .copied()
+}
+
+/// When the package was first published, if that was within
+/// [`NEW_PACKAGE_DAYS`] of `now` and it has at most [`FEW_DOWNLOADS`]
+/// recent downloads (or an unknown count).
+pub fn first_published_recently(
+    releases: &[ReleaseInfo],
+    downloads: Option<u64>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let first = releases
+        .iter()
+        .filter_map(|release| DateTime::parse_from_rfc3339(release.published_at.as_deref()?).ok())
+        .map(|published| published.with_timezone(&Utc))
+        .min()?;
+    let recent = now - first < Duration::days(NEW_PACKAGE_DAYS);
+    (recent && downloads.is_none_or(|downloads| downloads <= FEW_DOWNLOADS)).then_some(first)
+}
+
+/// Install scripts of `target` that `current` does not declare.
+pub fn added_install_scripts(current: Option<&ReleaseInfo>, target: &ReleaseInfo) -> Vec<String> {
+    let before = current
+        .and_then(|release| release.install_scripts.clone())
+        .unwrap_or_default();
+    target
+        .install_scripts
+        .iter()
+        .flatten()
+        .filter(|script| !before.contains(script))
+        .cloned()
+        .collect()
+}
+
+impl UpgradeWorker {
+    /// Flags typosquatting names, brand-new barely used packages and npm
+    /// targets that add install scripts, each making the upgrade high risk,
+    /// and scores anomalies in how the target was published. Registry
+    /// lookups that fail are reported as warnings.
+    pub(crate) async fn check_supply_chain(
+        &self,
+        request: &UpgradeRequest,
+        target_request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) {
+        let package = &target_request.package_name;
+        let mut flags = Vec::new();
+        let mut signals = Vec::new();
+        if let Some(similar_to) = similar_popular_package(&request.ecosystem, package) {
+            flags.push(SupplyChainFlag::PossibleTyposquat {
+                package: package.clone(),
+                similar_to: similar_to.to_string(),
+            });
+        }
+
+        let releases = match self.registry_releases(target_request).await {
+            Ok(releases) => releases,
+            Err(e) => {
+                warnings.push(format!("Supply chain check skipped: {}", e.message));
+                None
+            }
+        };
+        if let (Some(releases), Some(registry)) = (releases, &self.registry) {
+            let downloads = registry
+                .downloads(&request.ecosystem, package)
+                .await
+                .unwrap_or_default();
+            if let Some(first) = first_published_recently(&releases, downloads, Utc::now()) {
+                flags.push(SupplyChainFlag::NewPackage {
+                    package: package.clone(),
+                    first_published: first.format("%Y-%m-%d").to_string(),
+                    downloads,
+                });
+            }
+
+            let scheme = self.version_scheme(request);
+            let find = |releases: &[ReleaseInfo], version: &ParsedVersion| {
+                releases
+                    .iter()
+                    .find(|release| scheme.parse(&release.version).ok().as_ref() == Some(version))
+                    .cloned()
+            };
+            let same_package = request.package_name == *package;
+            let current_releases = if same_package {
+                Some(releases.clone())
+            } else {
+                self.registry_releases(request).await.ok().flatten()
+            };
+            let current = current_releases.and_then(|current| find(&current, &versions.current));
+            let target = find(&releases, &versions.target);
+
+            if let (Some(target), true) = (
+                &target,
+                matches!(request.ecosystem.as_str(), "npm" | "pnpm"),
+            ) {
+                let scripts = added_install_scripts(current.as_ref(), target);
+                if !scripts.is_empty() {
+                    flags.push(SupplyChainFlag::InstallScripts {
+                        package: package.clone(),
+                        version: versions.target.to_string(),
+                        scripts,
+                    });
+                }
+            }
+            // Publish anomalies only mean something within one package
+            if let (true, Some(current), Some(target)) = (same_package, &current, &target) {
+                signals = publish_signals(&releases, current, target);
+            }
+        }
+
+        for flag in flags {
+            risk.risk_level = risk.risk_level.max(RiskLevel::High);
+            risk.explanations.push(flag.to_string());
+            risk.supply_chain_flags.push(flag);
+        }
+
+        // One publish anomaly is worth a look; an ownership transfer or
+        // several together are a red flag
+        let level = if signals.len() > 1
+            || signals
+                .iter()
+                .any(|signal| matches!(signal, PublishSignal::OwnershipTransferred { .. }))
+        {
+            RiskLevel::High
+        } else {
+            RiskLevel::Medium
+        };
+        for signal in signals {
+            risk.risk_level = risk.risk_level.max(level);
+            risk.explanations
+                .push(format!("{} {} {}", package, versions.target, signal));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::StaticRegistry;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_typosquat_names() {
+        assert_eq!(edit_distance("lodash", "lodahs"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(similar_popular_package("npm", "lodahs"), Some("lodash"));
+        assert_eq!(similar_popular_package("npm", "expres"), Some("express"));
+        assert_eq!(similar_popular_package("pip", "request"), Some("requests"));
+        assert_eq!(similar_popular_package("cargo", "serde-json"), None);
+        assert_eq!(similar_popular_package("npm", "lodash"), None);
+        assert_eq!(similar_popular_package("npm", "left-pad"), None);
+        assert_eq!(similar_popular_package("go", "cobra"), None);
+    }
+
+    #[test]
+    fn test_new_packages() {
+        let release = |published_at: &str| ReleaseInfo {
+            published_at: Some(published_at.to_string()),
+            ..ReleaseInfo::new("1.0.0")
+        };
+        let now = DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let fresh = [release("2024-02-20T10:00:00.000Z")];
+        assert!(first_published_recently(&fresh, Some(12), now).is_some());
+        assert!(first_published_recently(&fresh, None, now).is_some());
+        assert!(first_published_recently(&fresh, Some(50_000), now).is_none());
+        let established = [
+            release("2015-06-01T00:00:00Z"),
+            release("2024-02-20T10:00:00Z"),
+        ];
+        assert!(first_published_recently(&established, Some(12), now).is_none());
+        assert!(first_published_recently(&[ReleaseInfo::new("1.0.0")], Some(12), now).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_install_scripts_and_publisher() {
+        let release = |version: &str, publisher: &str, scripts: &[&str]| ReleaseInfo {
+            published_at: Some(format!("2018-09-0{}T00:00:00Z", &version[4..])),
+            publisher: Some(publisher.to_string()),
+            install_scripts: Some(scripts.iter().map(|s| s.to_string()).collect()),
+            ..ReleaseInfo::new(version)
+        };
+        let registry = StaticRegistry::new()
+            .with_releases(
+                "npm",
+                "event-stream",
+                vec![
+                    release("3.3.5", "dominictarr", &[]),
+                    release("3.3.6", "right9ctrl", &["postinstall"]),
+                ],
+            )
+            .with_downloads("npm", "event-stream", 2_000_000);
+        let worker = UpgradeWorker::new(None).with_registry(Arc::new(registry));
+        let request = UpgradeRequest {
+            repository: "acme/web".to_string(),
+            ecosystem: "npm".to_string(),
+            package_name: "event-stream".to_string(),
+            current_version: "3.3.5".to_string(),
+            target_version: "3.3.6".to_string(),
+            manifests: HashMap::from([(
+                "package.json".to_string(),
+                r#"{"dependencies": {"event-stream": "3.3.5"}}"#.to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let risk = worker
+            .process_upgrade(request)
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.risk_level, RiskLevel::High);
+        assert_eq!(
+            risk.supply_chain_flags,
+            [SupplyChainFlag::InstallScripts {
+                package: "event-stream".to_string(),
+                version: "3.3.6".to_string(),
+                scripts: vec!["postinstall".to_string()],
+            }]
+        );
+        assert!(risk
+            .explanations
+            .contains(&"event-stream 3.3.6 adds install scripts: postinstall".to_string()));
+        assert!(risk.explanations.contains(
+            &"event-stream 3.3.6 is the first release published by right9ctrl".to_string()
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::registry::ReleaseInfo;
+use chrono::DateTime;
+use std::fmt;
+
+/// A target more than this many times the size of the current version is
+/// unusually large.
+const LARGE_SIZE_RATIO: f64 = 2.0;
+
+/// Growth in bytes below which the size ratio is not considered, as small
+/// packages double easily.
+const MIN_SIZE_DELTA: u64 = 100_000;
+
+/// Something unusual about how the target version was published, compared
+/// to the current one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublishSignal {
+    /// Maintainers were added or removed since the current version.
+    MaintainersChanged {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// None of the current version's maintainers maintain the target.
+    OwnershipTransferred { from: Vec<String>, to: Vec<String> },
+    /// The published artifact grew much more than releases usually do.
+    LargePublishDelta { current_size: u64, target_size: u64 },
+    /// The target is the first release its publisher made of the package.
+    NewPublisher { publisher: String },
+}
+
+impl fmt::Display for PublishSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishSignal::MaintainersChanged { added, removed } => {
+                let mut changes = Vec::new();
+                if !added.is_empty() {
+                    changes.push(format!("added {}", added.join(", ")));
+                }
+                if !removed.is_empty() {
+                    changes.push(format!("removed {}", removed.join(", ")));
+                }
+                write!(f, "changes maintainers ({})", changes.join("; "))
+            }
+            PublishSignal::OwnershipTransferred { from, to } => write!(
+                f,
+                "was published after ownership moved from {} to {}",
+                from.join(", "),
+                to.join(", ")
+            ),
+            PublishSignal::LargePublishDelta {
+                current_size,
+                target_size,
+            } => write!(
+                f,
+                "is {:.1} times the size of the current version ({} to {} bytes)",
+                *target_size as f64 / *current_size as f64,
+                current_size,
+                target_size
+            ),
+            PublishSignal::NewPublisher { publisher } => {
+                write!(f, "is the first release published by {}", publisher)
+            }
+        }
+    }
+}
+
+/// Compares how `target` was published with `current`, two releases of the
+/// same package among its `releases`. Signals whose metadata the registry
+/// does not publish are not raised.
+pub fn publish_signals(
+    releases: &[ReleaseInfo],
+    current: &ReleaseInfo,
+    target: &ReleaseInfo,
+) -> Vec<PublishSignal> {
+    let mut signals = Vec::new();
+
+    if let (Some(before), Some(after)) = (&current.maintainers, &target.maintainers) {
+        let added: Vec<String> = after
+            .iter()
+            .filter(|name| !before.contains(name))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = before
+            .iter()
+            .filter(|name| !after.contains(name))
+            .cloned()
+            .collect();
+        if !before.is_empty() && !after.is_empty() && removed.len() == before.len() {
+            signals.push(PublishSignal::OwnershipTransferred {
+                from: before.clone(),
+                to: after.clone(),
+            });
+        } else if !added.is_empty() || !removed.is_empty() {
+            signals.push(PublishSignal::MaintainersChanged { added, removed });
+        }
+    }
+
+    if let (Some(current_size), Some(target_size)) = (current.size, target.size) {
+        if current_size > 0
+            && target_size as f64 > current_size as f64 * LARGE_SIZE_RATIO
+            && target_size - current_size >= MIN_SIZE_DELTA
+        {
+            signals.push(PublishSignal::LargePublishDelta {
+                current_size,
+                target_size,
+            });
+        }
+    }
+
+    let published =
+        |release: &ReleaseInfo| DateTime::parse_from_rfc3339(release.published_at.as_deref()?).ok();
+    if let (Some(publisher), Some(target_published)) = (&target.publisher, published(target)) {
+        let earlier: Vec<&ReleaseInfo> = releases
+            .iter()
+            .filter(|release| published(release).is_some_and(|at| at < target_published))
+            .filter(|release| release.publisher.is_some())
+            .collect();
+        if !earlier.is_empty()
+            && earlier
+                .iter()
+                .all(|release| release.publisher.as_ref() != Some(publisher))
+        {
+            signals.push(PublishSignal::NewPublisher {
+                publisher: publisher.clone(),
+            });
+        }
+    }
+
+    signals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str, published_at: &str, publisher: &str, size: u64) -> ReleaseInfo {
+        ReleaseInfo {
+            published_at: Some(published_at.to_string()),
+            publisher: Some(publisher.to_string()),
+            maintainers: Some(vec![publisher.to_string()]),
+            size: Some(size),
+            ..ReleaseInfo::new(version)
+        }
+    }
+
+    #[test]
+    fn test_publish_signals() {
+        let releases = [
+            release("3.3.4", "2016-09-01T00:00:00Z", "dominictarr", 30_000),
+            release("3.3.5", "2018-09-01T00:00:00Z", "dominictarr", 32_000),
+            release("3.3.6", "2018-09-09T00:00:00Z", "right9ctrl", 250_000),
+        ];
+        assert_eq!(
+            publish_signals(&releases, &releases[1], &releases[2]),
+            [
+                PublishSignal::OwnershipTransferred {
+                    from: vec!["dominictarr".to_string()],
+                    to: vec!["right9ctrl".to_string()],
+                },
+                PublishSignal::LargePublishDelta {
+                    current_size: 32_000,
+                    target_size: 250_000,
+                },
+                PublishSignal::NewPublisher {
+                    publisher: "right9ctrl".to_string(),
+                },
+            ]
+        );
+        assert!(publish_signals(&releases, &releases[0], &releases[1]).is_empty());
+
+        let shared = ReleaseInfo {
+            maintainers: Some(vec!["dominictarr".to_string(), "right9ctrl".to_string()]),
+            ..releases[1].clone()
+        };
+        assert_eq!(
+            publish_signals(&releases, &releases[0], &shared),
+            [PublishSignal::MaintainersChanged {
+                added: vec!["right9ctrl".to_string()],
+                removed: Vec::new(),
+            }]
+        );
+        assert_eq!(
+            publish_signals(&releases, &releases[1], &releases[2])[1].to_string(),
+            "is 7.8 times the size of the current version (32000 to 250000 bytes)"
+        );
+
+        // Registries without publish metadata raise nothing
+        let bare = [ReleaseInfo::new("1.0.0"), ReleaseInfo::new("2.0.0")];
+        assert!(publish_signals(&bare, &bare[0], &bare[1]).is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+use crate::deprecations::quoted_names;
+use crate::release_notes::ReleaseNotes;
+use crate::version::ResolvedVersions;
+use crate::{rename, RiskAssessment, RiskLevel, UpgradeRequest};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Names too common to be searched for without the type or module they
+/// belong to.
+const COMMON_NAMES: &[&str] = &[
+    "build", "call", "clone", "close", "create", "default", "delete", "from", "get", "init",
+    "into", "iter", "len", "map", "new", "next", "open", "read", "run", "send", "set", "start",
+    "stop", "update", "with", "write",
+];
+
+/// What reported an API as broken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakSource {
+    /// An API diff, in [`crate::RiskAssessment::api_breaks`].
+    ApiDiff,
+    /// A breaking change the release notes list.
+    ReleaseNotes,
+}
+
+/// A line of the repository using an API the target removes or changes, in
+/// [`crate::RiskAssessment::api_usages`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiUsage {
+    pub file: String,
+    /// 1-based line and column of the name.
+    pub line: usize,
+    pub column: usize,
+    pub name: String,
+    pub source: BreakSource,
+    /// The break: the API diff's summary or the release note.
+    pub reason: String,
+    /// The line, trimmed.
+    pub text: String,
+}
+
+/// An API broken by the target, searched for as `name` or, for common
+/// names, `qualifier::name`/`qualifier.name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenApi {
+    pub qualifier: Option<String>,
+    pub name: String,
+    pub source: BreakSource,
+    pub reason: String,
+}
+
+impl BrokenApi {
+    fn pattern(&self) -> Option<Regex> {
+        let name = regex::escape(&self.name);
+        let ambiguous = self.name.len() < 3 || COMMON_NAMES.contains(&self.name.as_str());
+        let pattern = match &self.qualifier {
+            Some(qualifier) if ambiguous => {
+                format!(r"\b{}\s*(?:::|\.|#)\s*{}\b", regex::escape(qualifier), name)
+            }
+            None if ambiguous => return None,
+            _ => format!(r"\b{}\b", name),
+        };
+        Regex::new(&pattern).ok()
+    }
+}
+
+/// The API an API diff item names (`function tokio::time::sleep_ms,
+/// previously in ...`, `variant RuntimeFlavor:MultiThreadAlt in ...`,
+/// `interface Options.timeout in ...`).
+pub fn broken_item(item: &str) -> Option<(Option<&str>, &str)> {
+    let path = item.split_whitespace().nth(1)?.trim_end_matches([',', ':']);
+    let mut segments = path
+        .split([':', '.', '#'])
+        .filter(|segment| !segment.is_empty())
+        .rev();
+    let name = segments.next()?;
+    Some((segments.next(), name))
+}
+
+/// The APIs the API diff and the release notes' breaking changes report.
+pub fn broken_apis(risk: &RiskAssessment, release_notes: Option<&ReleaseNotes>) -> Vec<BrokenApi> {
+    let mut apis = Vec::new();
+    for api_break in risk.api_breaks.iter().flatten() {
+        if let Some((qualifier, name)) = broken_item(&api_break.item) {
+            apis.push(BrokenApi {
+                qualifier: qualifier.map(str::to_string),
+                name: name.to_string(),
+                source: BreakSource::ApiDiff,
+                reason: api_break.summary.clone(),
+            });
+        }
+    }
+    for note in release_notes.iter().flat_map(|notes| &notes.breaking) {
+        for name in quoted_names(&note.text) {
+            apis.push(BrokenApi {
+                qualifier: None,
+                name: name.to_string(),
+                source: BreakSource::ReleaseNotes,
+                reason: note.text.clone(),
+            });
+        }
+    }
+    apis
+}
+
+/// Lines of `files` using any of `apis`, skipping comment lines, in file
+/// and line order.
+pub fn find_usages(files: &[(&str, &str)], apis: &[BrokenApi]) -> Vec<ApiUsage> {
+    let patterns: Vec<(&BrokenApi, Regex)> = apis
+        .iter()
+        .filter_map(|api| Some((api, api.pattern()?)))
+        .collect();
+    let mut usages = Vec::new();
+    for (file, content) in files {
+        for (index, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("//") || trimmed.starts_with("/*") || trimmed.starts_with('*') {
+                continue;
+            }
+            let mut names = Vec::new();
+            for (api, pattern) in &patterns {
+                let Some(found) = pattern.find(line) else {
+                    continue;
+                };
+                if names.contains(&api.name) {
+                    continue;
+                }
+                names.push(api.name.clone());
+                usages.push(ApiUsage {
+                    file: file.to_string(),
+                    line: index + 1,
+                    column: line[..found.start()].chars().count() + 1,
+                    name: api.name.clone(),
+                    source: api.source,
+                    reason: api.reason.clone(),
+                    text: trimmed.to_string(),
+                });
+            }
+        }
+    }
+    usages
+}
+
+/// Records where the supplied files importing the package use APIs the
+/// target breaks; any usage makes the upgrade breaking and high risk.
+pub(crate) fn check_api_usages(
+    request: &UpgradeRequest,
+    versions: &ResolvedVersions,
+    release_notes: Option<&ReleaseNotes>,
+    risk: &mut RiskAssessment,
+) {
+    let apis = broken_apis(risk, release_notes);
+    let importing = rename::affected_imports(request);
+    if apis.is_empty() || importing.is_empty() {
+        return;
+    }
+    let files: Vec<(&str, &str)> = importing
+        .iter()
+        .filter_map(|path| Some((*path, request.manifests.get(*path)?.as_str())))
+        .collect();
+    let usages = find_usages(&files, &apis);
+
+    let package = &request.package_name;
+    if usages.is_empty() {
+        risk.explanations.push(format!(
+            "The files importing {} use none of the APIs {} breaks",
+            package, versions.target
+        ));
+        return;
+    }
+    let mut affected: Vec<&str> = usages.iter().map(|usage| usage.file.as_str()).collect();
+    affected.dedup();
+    risk.breaking_changes = true;
+    risk.risk_level = risk.risk_level.max(RiskLevel::High);
+    risk.explanations.push(format!(
+        "{} line{} in {} file{}{} APIs {} {} breaks",
+        usages.len(),
+        if usages.len() == 1 { "" } else { "s" },
+        affected.len(),
+        if affected.len() == 1 { "" } else { "s" },
+        if usages.len() == 1 { " uses" } else { " use" },
+        package,
+        versions.target
+    ));
+    risk.api_usages = usages;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broken_item() {
+        assert_eq!(
+            broken_item("function tokio::time::sleep_ms, previously in file src/time.rs:80"),
+            Some((Some("time"), "sleep_ms"))
+        );
+        assert_eq!(
+            broken_item("variant RuntimeFlavor:MultiThreadAlt in src/runtime.rs:110"),
+            Some((Some("RuntimeFlavor"), "MultiThreadAlt"))
+        );
+        assert_eq!(
+            broken_item("function render, previously in index.d.ts"),
+            Some((None, "render"))
+        );
+    }
+
+    #[test]
+    fn test_find_usages() {
+        let api = |qualifier: Option<&str>, name: &str| BrokenApi {
+            qualifier: qualifier.map(str::to_string),
+            name: name.to_string(),
+            source: BreakSource::ApiDiff,
+            reason: "pub fn removed or renamed".to_string(),
+        };
+        let apis = [
+            api(Some("time"), "sleep_ms"),
+            api(Some("Builder"), "new"),
+            api(None, "build"),
+        ];
+        let source = "use tokio::time::sleep_ms;\n\
+                      // sleep_ms is gone\n\
+                      fn main() {\n    let rt = Builder::new().build();\n    let v = Vec::new();\n    sleep_ms(10);\n}\n";
+
+        let usages = find_usages(&[("src/main.rs", source)], &apis);
+        let locations: Vec<_> = usages
+            .iter()
+            .map(|usage| (usage.line, usage.column, usage.name.as_str()))
+            .collect();
+        assert_eq!(
+            locations,
+            [(1, 18, "sleep_ms"), (4, 14, "new"), (6, 5, "sleep_ms")]
+        );
+        assert_eq!(usages[1].text, "let rt = Builder::new().build();");
+    }
+
+    #[tokio::test]
+    async fn test_api_usages_in_risk_assessment() {
+        use crate::semver_checks::{ApiBreak, StaticApiDiff};
+        use crate::UpgradeWorker;
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        let diff = StaticApiDiff::new("cargo").with_breaks(
+            "tokio",
+            "1.30.0",
+            "1.40.0",
+            vec![ApiBreak {
+                lint: "function_missing".to_string(),
+                summary: "pub fn removed or renamed".to_string(),
+                item: "function tokio::time::sleep_ms, previously in file src/time.rs:80"
+                    .to_string(),
+            }],
+        );
+        let worker = UpgradeWorker::new(None).with_api_diff(Arc::new(diff));
+        let request = UpgradeRequest {
+            repository: "acme/service".to_string(),
+            ecosystem: "cargo".to_string(),
+            package_name: "tokio".to_string(),
+            current_version: "1.30.0".to_string(),
+            target_version: "1.40.0".to_string(),
+            manifests: HashMap::from([
+                (
+                    "Cargo.toml".to_string(),
+                    "[dependencies]\ntokio = \"1.30.0\"\n".to_string(),
+                ),
+                (
+                    "src/main.rs".to_string(),
+                    "fn main() {\n    tokio::time::sleep_ms(10);\n}\n".to_string(),
+                ),
+                (
+                    "src/lib.rs".to_string(),
+                    "pub fn sleep_ms() {}\n".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let risk = worker
+            .process_upgrade(request)
+            .await
+            .unwrap()
+            .risk_assessment;
+        assert_eq!(risk.api_usages.len(), 1);
+        assert_eq!(
+            (risk.api_usages[0].file.as_str(), risk.api_usages[0].line),
+            ("src/main.rs", 2)
+        );
+        assert!(risk
+            .explanations
+            .contains(&"1 line in 1 file uses APIs tokio 1.40.0 breaks".to_string()));
+    }
+}
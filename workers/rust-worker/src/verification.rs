@@ -0,0 +1,472 @@
+use crate::lockfile::command_steps;
+use crate::repo::{self, Checkout};
+use crate::sandbox::Isolation;
+use crate::version::ResolvedVersions;
+use crate::{
+    Change, ErrorType, RiskAssessment, RiskLevel, UpgradeError, UpgradeRequest, UpgradeWorker,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+mod benchmarks;
+mod measure;
+pub use benchmarks::{parse_results, BenchmarkChange, BenchmarkComparison, Estimate};
+pub use measure::{artifact_sizes, impact, BuildMeasurement, BuildMetrics, BUILD_COMMAND};
+
+/// Directories left out of the copy verification builds in: VCS metadata,
+/// installed dependencies and build output, which the commands recreate.
+const BUILD_DIRECTORIES: &[&str] = &[".git", ".venv", "__pycache__", "node_modules", "target"];
+
+/// How upgrades are built and tested when
+/// [`crate::WorkerConfig::verification`] is set.
+#[derive(Debug, Clone)]
+pub struct VerificationConfig {
+    /// Commands per ecosystem (`cargo` → `cargo build && cargo test`),
+    /// replacing [`default_command`].
+    pub commands: HashMap<String, String>,
+    /// Seconds every step together may take.
+    pub timeout: u64,
+    /// Lines kept from the end of each step's output.
+    pub log_lines: usize,
+    /// Time clean release builds of cargo projects before and after the
+    /// upgrade, and weigh their binaries, once the verification passed.
+    pub measure_builds: bool,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            commands: HashMap::new(),
+            timeout: 1800,
+            log_lines: 40,
+            measure_builds: true,
+        }
+    }
+}
+
+/// Outcome of one step of a verification command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    TimedOut,
+    /// Not run, an earlier step having failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationStep {
+    pub command: String,
+    pub status: StepStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// The last lines of the step's output, stdout before stderr.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub log: String,
+}
+
+/// Result of building and testing the checkout with the changes applied,
+/// in [`crate::UpgradeResponse::verification`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Verification {
+    pub passed: bool,
+    pub duration_ms: u64,
+    pub steps: Vec<VerificationStep>,
+    /// Build time and binary size of the current and target versions, for
+    /// cargo projects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildMeasurement>,
+    /// The requested benchmarks of the current and target versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmarks: Option<BenchmarkComparison>,
+}
+
+/// The build and test command of an ecosystem, picking the package
+/// manager of npm projects by their lockfile.
+pub fn default_command(ecosystem: &str, directory: &Path) -> Option<&'static str> {
+    let command = match ecosystem {
+        "cargo" => "cargo check --all-targets && cargo test",
+        "npm" if directory.join("pnpm-lock.yaml").exists() => {
+            "pnpm install --frozen-lockfile && pnpm test"
+        }
+        "npm" if directory.join("yarn.lock").exists() => {
+            "yarn install --frozen-lockfile && yarn test"
+        }
+        "npm" => "npm ci && npm test",
+        "go" => "go build ./... && go test ./...",
+        "gradle" if directory.join("gradlew").exists() => "./gradlew build",
+        "gradle" => "gradle build",
+        "hex" => "mix deps.get && mix test",
+        "maven" => "mvn -B verify",
+        "nuget" => "dotnet test",
+        "pub" => "dart pub get && dart test",
+        "swiftpm" => "swift build && swift test",
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// The last `lines` lines of `output`.
+pub fn excerpt(output: &str, lines: usize) -> String {
+    let all: Vec<&str> = output.trim_end().lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+fn internal_error(message: String) -> UpgradeError {
+    UpgradeError {
+        message,
+        error_type: ErrorType::Internal,
+    }
+}
+
+/// Copies the working tree at `from` to `to`, without
+/// [`BUILD_DIRECTORIES`] and symlinks.
+pub(crate) fn copy_tree(from: &Path, to: &Path) -> Result<(), UpgradeError> {
+    let entries = WalkDir::new(from).into_iter().filter_entry(|entry| {
+        entry.depth() == 0
+            || !(entry.file_type().is_dir()
+                && BUILD_DIRECTORIES.contains(&entry.file_name().to_string_lossy().as_ref()))
+    });
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| internal_error(format!("Failed to read the checkout: {}", e)))?;
+        let Ok(relative) = entry.path().strip_prefix(from) else {
+            continue;
+        };
+        let target = to.join(relative);
+        let io_error = |e: std::io::Error| {
+            internal_error(format!("Failed to copy {}: {}", relative.display(), e))
+        };
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target).map_err(io_error)?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &target).map_err(io_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs one step in `directory`, isolated as `isolation` says; `Err` when
+/// it cannot be started.
+pub(crate) async fn run_step(
+    isolation: &Isolation,
+    argv: &[&str],
+    directory: &Path,
+    timeout: Duration,
+    log_lines: usize,
+) -> Result<VerificationStep, String> {
+    let name = format!("speccursor-{}", uuid::Uuid::new_v4());
+    let mut command = isolation.command(argv, directory, &name);
+
+    let started = Instant::now();
+    let output = tokio::time::timeout(timeout, command.output()).await;
+    if output.is_err() {
+        isolation.stop(&name).await;
+    }
+    let mut step = VerificationStep {
+        command: argv.join(" "),
+        status: StepStatus::TimedOut,
+        exit_code: None,
+        duration_ms: started.elapsed().as_millis() as u64,
+        log: String::new(),
+    };
+    if let Ok(output) = output {
+        let output = output.map_err(|e| format!("`{}` could not be run: {}", argv[0], e))?;
+        let log = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout).trim_end(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        step.status = if output.status.success() {
+            StepStatus::Passed
+        } else {
+            StepStatus::Failed
+        };
+        step.exit_code = output.status.code();
+        step.log = excerpt(log.trim_start(), log_lines);
+    }
+    Ok(step)
+}
+
+impl UpgradeWorker {
+    /// Builds and tests a copy of the checkout with `changes` applied when
+    /// verification is enabled. A failing build makes the upgrade breaking
+    /// and high risk; verifications that cannot run are skipped with a
+    /// warning.
+    pub(crate) async fn verify_upgrade(
+        &self,
+        checkout: Option<&Checkout>,
+        request: &UpgradeRequest,
+        versions: &ResolvedVersions,
+        changes: &[Change],
+        risk: &mut RiskAssessment,
+        warnings: &mut Vec<String>,
+    ) -> Result<Option<Verification>, UpgradeError> {
+        let Some(config) = &self.config.verification else {
+            if request.benchmark_command.is_some() {
+                warnings.push("Benchmarks skipped: verification is disabled".to_string());
+            }
+            return Ok(None);
+        };
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        let isolation = match self.isolation() {
+            Ok(isolation) => isolation,
+            Err(reason) => {
+                warnings.push(format!("Verification skipped: {}", reason));
+                return Ok(None);
+            }
+        };
+        let sparse = self
+            .config
+            .checkout
+            .as_ref()
+            .is_some_and(|checkout| checkout.sparse);
+        let Some(checkout) = checkout.filter(|checkout| checkout.local || !sparse) else {
+            warnings.push(
+                "Verification skipped: only full checkouts and local workspaces can be built"
+                    .to_string(),
+            );
+            return Ok(None);
+        };
+        if let Some(pending) = changes
+            .iter()
+            .find(|change| change.metadata.contains_key("lockfile_refresh"))
+        {
+            warnings.push(format!(
+                "Verification skipped: {} was not regenerated",
+                pending.file_path
+            ));
+            return Ok(None);
+        }
+
+        let sandbox = tempfile::Builder::new()
+            .prefix("speccursor-verify-")
+            .tempdir()
+            .map_err(|e| internal_error(format!("Failed to create a sandbox: {}", e)))?;
+        let (source, root) = (checkout.path.clone(), sandbox.path().to_path_buf());
+        let owned = changes.to_vec();
+        tokio::task::spawn_blocking(move || {
+            copy_tree(&source, &root)?;
+            repo::write_changes(&root, &owned)
+        })
+        .await
+        .map_err(|e| internal_error(format!("Sandbox task failed: {}", e)))??;
+
+        let scope = request.path_scope.as_deref().unwrap_or_default();
+        let directory = sandbox.path().join(scope.trim_matches('/'));
+        let command = match config.commands.get(&request.ecosystem) {
+            Some(command) => command.as_str(),
+            None => match default_command(&request.ecosystem, &directory) {
+                Some(command) => command,
+                None => {
+                    warnings.push(format!(
+                        "Verification skipped: no build command for {}",
+                        request.ecosystem
+                    ));
+                    return Ok(None);
+                }
+            },
+        };
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_secs(config.timeout);
+        let mut steps: Vec<VerificationStep> = Vec::new();
+        for argv in command_steps(command) {
+            if steps.iter().any(|step| step.status != StepStatus::Passed) {
+                steps.push(VerificationStep {
+                    command: argv.join(" "),
+                    status: StepStatus::Skipped,
+                    exit_code: None,
+                    duration_ms: 0,
+                    log: String::new(),
+                });
+                continue;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match run_step(&isolation, &argv, &directory, remaining, config.log_lines).await {
+                Ok(step) => steps.push(step),
+                Err(reason) => {
+                    warnings.push(format!("Verification skipped: {}", reason));
+                    return Ok(None);
+                }
+            }
+        }
+        let mut verification = Verification {
+            passed: steps.iter().all(|step| step.status == StepStatus::Passed),
+            duration_ms: started.elapsed().as_millis() as u64,
+            steps,
+            build: None,
+            benchmarks: None,
+        };
+
+        let package = &request.package_name;
+        if let Some(failed) = verification
+            .steps
+            .iter()
+            .find(|step| step.status == StepStatus::Failed)
+        {
+            risk.breaking_changes = true;
+            risk.risk_level = risk.risk_level.max(RiskLevel::High);
+            risk.explanations.push(format!(
+                "`{}` fails with {} {}",
+                failed.command, package, versions.target
+            ));
+        } else if verification.passed {
+            risk.explanations.push(format!(
+                "`{}` passes with {} {}",
+                command, package, versions.target
+            ));
+        } else {
+            warnings.push(format!("Verification timed out after {}s", config.timeout));
+        }
+
+        // Measurements compare against a copy of the checkout as it is
+        let measure_build =
+            verification.passed && config.measure_builds && request.ecosystem == "cargo";
+        let benchmark = request
+            .benchmark_command
+            .as_deref()
+            .filter(|_| verification.passed);
+        if !measure_build && benchmark.is_none() {
+            return Ok(Some(verification));
+        }
+        let baseline = tempfile::Builder::new()
+            .prefix("speccursor-baseline-")
+            .tempdir()
+            .map_err(|e| internal_error(format!("Failed to create a sandbox: {}", e)))?;
+        let (source, root) = (checkout.path.clone(), baseline.path().to_path_buf());
+        tokio::task::spawn_blocking(move || copy_tree(&source, &root))
+            .await
+            .map_err(|e| internal_error(format!("Sandbox task failed: {}", e)))??;
+        let before = baseline.path().join(scope.trim_matches('/'));
+
+        // Measured build costs replace the estimate from the lockfiles
+        if measure_build {
+            match measure::compare(&isolation, &before, &directory, deadline).await {
+                Ok(measurement) => {
+                    risk.performance_impact = measurement.performance_impact();
+                    risk.explanations.push(format!(
+                        "Clean release builds with {} {} change by {:+.1}% in time and {:+.1}% in binary size: {:?} performance impact (under 2% is none, under 10% low, under 25% medium, more high)",
+                        package,
+                        versions.target,
+                        measurement.duration_change() * 100.0,
+                        measurement.size_change() * 100.0,
+                        risk.performance_impact
+                    ));
+                    verification.build = Some(measurement);
+                }
+                Err(reason) => warnings.push(format!("Build measurement skipped: {}", reason)),
+            }
+        }
+
+        if let Some(command) = benchmark {
+            match benchmarks::compare(&isolation, command, &before, &directory, deadline).await {
+                Ok(comparison) => {
+                    if let Some(worst) = comparison.worst_regression() {
+                        risk.performance_impact = risk.performance_impact.max(impact(worst.change));
+                        risk.explanations.push(format!(
+                            "{} benchmark{} with {} {}, `{}` by {:+.1}%",
+                            comparison.regressions,
+                            if comparison.regressions == 1 {
+                                " regresses"
+                            } else {
+                                "s regress"
+                            },
+                            package,
+                            versions.target,
+                            worst.name,
+                            worst.change * 100.0
+                        ));
+                    }
+                    verification.benchmarks = Some(comparison);
+                }
+                Err(reason) => warnings.push(format!("Benchmarks skipped: {}", reason)),
+            }
+        }
+        Ok(Some(verification))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WorkerConfig;
+
+    #[test]
+    fn test_default_command() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(
+            default_command("npm", root.path()),
+            Some("npm ci && npm test")
+        );
+        std::fs::write(root.path().join("pnpm-lock.yaml"), "").unwrap();
+        assert_eq!(
+            default_command("npm", root.path()),
+            Some("pnpm install --frozen-lockfile && pnpm test")
+        );
+        assert!(default_command("terraform", root.path()).is_none());
+        assert_eq!(excerpt("a\nb\nc\n", 2), "b\nc");
+        assert_eq!(excerpt("a", 5), "a");
+    }
+
+    #[tokio::test]
+    async fn test_verification_of_local_workspace() {
+        let root = tempfile::tempdir().unwrap();
+        let worker = |command: &str| {
+            UpgradeWorker::new(Some(WorkerConfig {
+                local_roots: vec![root.path().to_path_buf()],
+                verification: Some(VerificationConfig {
+                    commands: HashMap::from([("npm".to_string(), command.to_string())]),
+                    ..VerificationConfig::default()
+                }),
+                sandbox_enabled: false,
+                ..WorkerConfig::default()
+            }))
+        };
+        let request = |workspace: &str| {
+            let path = root.path().join(workspace);
+            std::fs::create_dir(&path).unwrap();
+            crate::repo::tests::source_repository(&path);
+            UpgradeRequest {
+                repository: "acme/app".to_string(),
+                ecosystem: "npm".to_string(),
+                package_name: "lodash".to_string(),
+                current_version: "4.17.20".to_string(),
+                target_version: "4.17.21".to_string(),
+                local_path: Some(path.to_string_lossy().to_string()),
+                ..Default::default()
+            }
+        };
+
+        let response = worker("grep -c 4.17.21 package.json")
+            .process_upgrade(request("passing"))
+            .await
+            .unwrap();
+        let verification = response.verification.unwrap();
+        assert!(verification.passed);
+        assert_eq!(verification.steps[0].log, "1");
+        assert_eq!(response.risk_assessment.risk_level, RiskLevel::Low);
+
+        // Installed dependencies are left out of the sandbox
+        let response = worker("test -d node_modules && grep -q 4.17.21 package.json")
+            .process_upgrade(request("failing"))
+            .await
+            .unwrap();
+        let verification = response.verification.unwrap();
+        assert!(!verification.passed);
+        assert_eq!(verification.steps[0].status, StepStatus::Failed);
+        assert_eq!(verification.steps[0].exit_code, Some(1));
+        assert_eq!(verification.steps[1].status, StepStatus::Skipped);
+        assert!(response.risk_assessment.breaking_changes);
+        assert_eq!(response.risk_assessment.risk_level, RiskLevel::High);
+        assert_eq!(response.recommended_action, crate::RecommendedAction::Block);
+        assert!(root.path().join("failing/node_modules/lodash").exists());
+    }
+}
@@ -0,0 +1,286 @@
+use super::{run_step, StepStatus};
+use crate::lockfile::command_steps;
+use crate::sandbox::Isolation;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Tools a requested benchmark command may run. Commands are run directly,
+/// never through a shell.
+const BENCHMARK_TOOLS: &[&str] = &["cargo", "go", "npm", "pnpm", "yarn"];
+
+/// Slowdown below which a significant change is not a regression.
+const REGRESSION_THRESHOLD: f64 = 0.05;
+
+/// Time per iteration in nanoseconds, with the bounds of its confidence
+/// interval.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    pub value: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkChange {
+    pub name: String,
+    pub before: Estimate,
+    pub after: Estimate,
+    /// Relative change of the time per iteration; positive is slower.
+    pub change: f64,
+    /// The confidence intervals do not overlap.
+    pub significant: bool,
+    /// Significantly slower by more than 5%.
+    pub regression: bool,
+}
+
+/// Benchmarks run before and after the upgrade, in
+/// [`super::Verification::benchmarks`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    pub command: String,
+    /// Benchmarks reported by both runs.
+    pub benchmarks: Vec<BenchmarkChange>,
+    /// Geometric mean of the changes of every benchmark.
+    pub mean_change: f64,
+    pub regressions: usize,
+}
+
+impl BenchmarkComparison {
+    /// Pairs the benchmarks both runs report by name.
+    pub fn new(
+        command: &str,
+        before: &BTreeMap<String, Estimate>,
+        after: &BTreeMap<String, Estimate>,
+    ) -> Self {
+        let benchmarks: Vec<BenchmarkChange> = before
+            .iter()
+            .filter_map(|(name, before)| {
+                let after = after.get(name)?;
+                let change = after.value / before.value - 1.0;
+                let significant = after.lower > before.upper || after.upper < before.lower;
+                Some(BenchmarkChange {
+                    name: name.clone(),
+                    before: *before,
+                    after: *after,
+                    change,
+                    significant,
+                    regression: significant && change > REGRESSION_THRESHOLD,
+                })
+            })
+            .collect();
+        let mean_change = if benchmarks.is_empty() {
+            0.0
+        } else {
+            let log_sum: f64 = benchmarks.iter().map(|b| (1.0 + b.change).ln()).sum();
+            (log_sum / benchmarks.len() as f64).exp() - 1.0
+        };
+        Self {
+            command: command.to_string(),
+            regressions: benchmarks.iter().filter(|b| b.regression).count(),
+            benchmarks,
+            mean_change,
+        }
+    }
+
+    /// The regression slowing down the most.
+    pub fn worst_regression(&self) -> Option<&BenchmarkChange> {
+        self.benchmarks
+            .iter()
+            .filter(|b| b.regression)
+            .max_by(|a, b| a.change.total_cmp(&b.change))
+    }
+}
+
+fn criterion_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(.*?)\s*time:\s+\[([\d.]+)\s*(\S+)\s+([\d.]+)\s*(\S+)\s+([\d.]+)\s*(\S+)\]")
+            .expect("valid pattern")
+    })
+}
+
+fn benchmark_js_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(.+?) x ([\d,]+(?:\.\d+)?) ops/sec ±([\d.]+)% \(\d+ runs? sampled\)")
+            .expect("valid pattern")
+    })
+}
+
+fn nanoseconds(value: &str, unit: &str) -> Option<f64> {
+    let scale = match unit {
+        "ps" => 1e-3,
+        "ns" => 1.0,
+        "µs" | "μs" | "us" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return None,
+    };
+    Some(value.parse::<f64>().ok()? * scale)
+}
+
+/// Benchmark results in the output of criterion (`cargo bench`) and
+/// benchmark.js (`npm run bench`), by name.
+pub fn parse_results(output: &str) -> BTreeMap<String, Estimate> {
+    let mut results = BTreeMap::new();
+    let mut previous = "";
+    for line in output
+        .lines()
+        .map(|line| line.rsplit('\r').next().unwrap_or(line))
+    {
+        if let Some(caps) = criterion_pattern().captures(line) {
+            // Long names are printed on a line of their own
+            let name = match caps[1].trim() {
+                "" => previous,
+                name => name,
+            };
+            let estimate = (|| {
+                Some(Estimate {
+                    lower: nanoseconds(&caps[2], &caps[3])?,
+                    value: nanoseconds(&caps[4], &caps[5])?,
+                    upper: nanoseconds(&caps[6], &caps[7])?,
+                })
+            })();
+            if let Some(estimate) = estimate.filter(|_| !name.is_empty()) {
+                results.insert(name.to_string(), estimate);
+            }
+        } else if let Some(caps) = benchmark_js_pattern().captures(line.trim()) {
+            let ops: f64 = caps[2].replace(',', "").parse().unwrap_or_default();
+            let margin: f64 = caps[3].parse::<f64>().unwrap_or_default() / 100.0;
+            if ops > 0.0 {
+                let value = 1e9 / ops;
+                results.insert(
+                    caps[1].to_string(),
+                    Estimate {
+                        value,
+                        lower: value * (1.0 - margin),
+                        upper: value * (1.0 + margin),
+                    },
+                );
+            }
+        }
+        if !line.trim().is_empty() {
+            previous = line.trim();
+        }
+    }
+    results
+}
+
+// Runs `command` in `directory`, returning the results it prints
+async fn run(
+    isolation: &Isolation,
+    command: &str,
+    directory: &Path,
+    deadline: Instant,
+) -> Result<BTreeMap<String, Estimate>, String> {
+    let mut output = String::new();
+    for argv in command_steps(command) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let step = run_step(isolation, &argv, directory, remaining, usize::MAX).await?;
+        match step.status {
+            StepStatus::Passed => output.push_str(&step.log),
+            StepStatus::TimedOut => return Err(format!("`{}` timed out", step.command)),
+            _ => {
+                let last = step.log.lines().last().unwrap_or_default();
+                return Err(format!("`{}` failed: {}", step.command, last));
+            }
+        }
+        output.push('\n');
+    }
+    Ok(parse_results(&output))
+}
+
+/// Runs the requested benchmark command in `before`, then in `after`.
+pub(crate) async fn compare(
+    isolation: &Isolation,
+    command: &str,
+    before: &Path,
+    after: &Path,
+    deadline: Instant,
+) -> Result<BenchmarkComparison, String> {
+    if let Some(argv) = command_steps(command)
+        .into_iter()
+        .find(|argv| !BENCHMARK_TOOLS.contains(&argv[0]))
+    {
+        return Err(format!("'{}' is not an allowed benchmark tool", argv[0]));
+    }
+    let current = run(isolation, command, before, deadline).await?;
+    if current.is_empty() {
+        return Err(format!(
+            "`{}` printed no criterion or benchmark.js results",
+            command
+        ));
+    }
+    let target = run(isolation, command, after, deadline).await?;
+    Ok(BenchmarkComparison::new(command, &current, &target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRITERION: &str = "\
+Benchmarking fib 20: Warming up for 3.0000 s\r\
+Benchmarking fib 20: Analyzing
+fib 20                  time:   [26.029 µs 26.251 µs 26.505 µs]
+                        change: [-2.1% +0.5% +3.0%] (p = 0.63 > 0.05)
+parse/a-rather-long-benchmark-name-that-wraps
+                        time:   [1.2001 ms 1.2100 ms 1.2203 ms]
+";
+
+    #[test]
+    fn test_parse_results() {
+        let results = parse_results(CRITERION);
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results["fib 20"],
+            Estimate {
+                value: 26_251.0,
+                lower: 26_029.0,
+                upper: 26_505.0
+            }
+        );
+        assert_eq!(
+            results["parse/a-rather-long-benchmark-name-that-wraps"].value,
+            1_210_000.0
+        );
+
+        let results = parse_results(
+            "> bench\nRegExp#test x 4,000,000 ops/sec ±1.00% (93 runs sampled)\nFastest is RegExp#test\n",
+        );
+        assert_eq!(results["RegExp#test"].value, 250.0);
+        assert_eq!(results["RegExp#test"].upper, 252.5);
+    }
+
+    #[test]
+    fn test_benchmark_comparison() {
+        let estimate = |value: f64, margin: f64| Estimate {
+            value,
+            lower: value - margin,
+            upper: value + margin,
+        };
+        let before = BTreeMap::from([
+            ("decode".to_string(), estimate(100.0, 2.0)),
+            ("encode".to_string(), estimate(100.0, 2.0)),
+            ("hash".to_string(), estimate(100.0, 10.0)),
+            ("removed".to_string(), estimate(100.0, 2.0)),
+        ]);
+        let after = BTreeMap::from([
+            ("decode".to_string(), estimate(121.0, 2.0)),
+            ("encode".to_string(), estimate(100.0, 2.0)),
+            ("hash".to_string(), estimate(110.0, 10.0)),
+        ]);
+
+        let comparison = BenchmarkComparison::new("cargo bench", &before, &after);
+        assert_eq!(comparison.benchmarks.len(), 3);
+        assert_eq!(comparison.regressions, 1);
+        assert_eq!(comparison.worst_regression().unwrap().name, "decode");
+        // Within its noise, `hash` is not a regression
+        assert!(!comparison.benchmarks[2].significant);
+        assert!((comparison.mean_change - (1.21f64 * 1.1).cbrt() + 1.0).abs() < 1e-9);
+    }
+}
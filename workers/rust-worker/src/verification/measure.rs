@@ -0,0 +1,256 @@
+use super::{run_step, StepStatus};
+use crate::sandbox::Isolation;
+use crate::PerformanceImpact;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Instant;
+
+/// Build whose wall time and artifacts are measured.
+pub const BUILD_COMMAND: &str = "cargo build --release";
+
+/// File extensions of final artifacts next to extensionless binaries;
+/// `rlib`s only feed other builds.
+const ARTIFACT_EXTENSIONS: &[&str] = &["dll", "dylib", "exe", "so", "wasm"];
+
+/// One clean release build.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildMetrics {
+    pub duration_ms: u64,
+    /// Size in bytes of each binary, shared library and wasm module built,
+    /// by path below the target directory.
+    pub artifacts: BTreeMap<String, u64>,
+}
+
+impl BuildMetrics {
+    pub fn size(&self) -> u64 {
+        self.artifacts.values().sum()
+    }
+}
+
+/// Clean release builds of the current and target versions, in
+/// [`super::Verification::build`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildMeasurement {
+    pub command: String,
+    pub before: BuildMetrics,
+    pub after: BuildMetrics,
+    pub duration_delta_ms: i64,
+    pub size_delta_bytes: i64,
+}
+
+// Relative change from `before` to `after`; none from nothing
+fn relative(before: u64, after: u64) -> f64 {
+    if before == 0 {
+        0.0
+    } else {
+        after as f64 / before as f64 - 1.0
+    }
+}
+
+impl BuildMeasurement {
+    pub fn new(before: BuildMetrics, after: BuildMetrics) -> Self {
+        Self {
+            command: BUILD_COMMAND.to_string(),
+            duration_delta_ms: after.duration_ms as i64 - before.duration_ms as i64,
+            size_delta_bytes: after.size() as i64 - before.size() as i64,
+            before,
+            after,
+        }
+    }
+
+    /// Relative change of the build time.
+    pub fn duration_change(&self) -> f64 {
+        relative(self.before.duration_ms, self.after.duration_ms)
+    }
+
+    /// Relative change of the artifacts' total size.
+    pub fn size_change(&self) -> f64 {
+        relative(self.before.size(), self.after.size())
+    }
+
+    /// Impact by the larger growth of build time and size.
+    pub fn performance_impact(&self) -> PerformanceImpact {
+        impact(self.duration_change().max(self.size_change()))
+    }
+}
+
+/// Impact of a relative slowdown or growth: under 2% is noise, under 10%
+/// low and under 25% medium.
+pub fn impact(growth: f64) -> PerformanceImpact {
+    if growth < 0.02 {
+        PerformanceImpact::None
+    } else if growth < 0.10 {
+        PerformanceImpact::Low
+    } else if growth < 0.25 {
+        PerformanceImpact::Medium
+    } else {
+        PerformanceImpact::High
+    }
+}
+
+/// Final artifacts of release builds in `target`, for the host
+/// (`release/app`) and cross targets (`wasm32-unknown-unknown/release/app.wasm`)
+/// alike.
+pub fn artifact_sizes(target: &Path) -> BTreeMap<String, u64> {
+    let mut directories = vec![target.join("release")];
+    if let Ok(entries) = std::fs::read_dir(target) {
+        directories.extend(entries.flatten().map(|entry| entry.path().join("release")));
+    }
+    let mut artifacts = BTreeMap::new();
+    for directory in directories {
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let artifact = match path.extension() {
+                Some(extension) => {
+                    ARTIFACT_EXTENSIONS.contains(&extension.to_string_lossy().as_ref())
+                }
+                None => !entry.file_name().to_string_lossy().starts_with('.'),
+            };
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !artifact || !metadata.is_file() {
+                continue;
+            }
+            if let Ok(relative) = path.strip_prefix(target) {
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                artifacts.insert(relative, metadata.len());
+            }
+        }
+    }
+    artifacts
+}
+
+/// Target directory of the measured builds, below the copy built: a
+/// sandboxed build sees nothing else.
+const MEASURE_TARGET: &str = "target/measure";
+
+/// Builds `directory` from scratch into [`MEASURE_TARGET`], after fetching
+/// its dependencies so that downloads are not timed.
+async fn build(
+    isolation: &Isolation,
+    directory: &Path,
+    deadline: Instant,
+) -> Result<BuildMetrics, String> {
+    let target = directory.join(MEASURE_TARGET);
+    if target.exists() {
+        std::fs::remove_dir_all(&target)
+            .map_err(|e| format!("{} could not be cleaned: {}", MEASURE_TARGET, e))?;
+    }
+    let fetch = ["cargo", "fetch"];
+    let build = [
+        "cargo",
+        "build",
+        "--release",
+        "--target-dir",
+        MEASURE_TARGET,
+    ];
+    let mut duration_ms = 0;
+    for argv in [&fetch[..], &build[..]] {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let step = run_step(isolation, argv, directory, remaining, 1).await?;
+        match step.status {
+            StepStatus::Passed => duration_ms = step.duration_ms,
+            StepStatus::TimedOut => return Err(format!("`{}` timed out", step.command)),
+            _ => return Err(format!("`{}` failed: {}", step.command, step.log)),
+        }
+    }
+    Ok(BuildMetrics {
+        duration_ms,
+        artifacts: artifact_sizes(&target),
+    })
+}
+
+/// Measures clean release builds of `before` and `after`, in that order.
+pub(crate) async fn compare(
+    isolation: &Isolation,
+    before: &Path,
+    after: &Path,
+    deadline: Instant,
+) -> Result<BuildMeasurement, String> {
+    let current = build(isolation, before, deadline)
+        .await
+        .map_err(|reason| format!("the current version does not build: {}", reason))?;
+    let target = build(isolation, after, deadline).await?;
+    Ok(BuildMeasurement::new(current, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(duration_ms: u64, size: u64) -> BuildMetrics {
+        BuildMetrics {
+            duration_ms,
+            artifacts: BTreeMap::from([("release/app".to_string(), size)]),
+        }
+    }
+
+    #[test]
+    fn test_performance_impact() {
+        let measurement =
+            BuildMeasurement::new(metrics(10_000, 4_000_000), metrics(10_100, 3_900_000));
+        assert_eq!(measurement.duration_delta_ms, 100);
+        assert_eq!(measurement.size_delta_bytes, -100_000);
+        assert!(matches!(
+            measurement.performance_impact(),
+            PerformanceImpact::None
+        ));
+
+        let slower = BuildMeasurement::new(metrics(10_000, 4_000_000), metrics(11_500, 4_000_000));
+        assert!(matches!(
+            slower.performance_impact(),
+            PerformanceImpact::Medium
+        ));
+        let larger = BuildMeasurement::new(metrics(10_000, 4_000_000), metrics(10_000, 6_000_000));
+        assert!(matches!(
+            larger.performance_impact(),
+            PerformanceImpact::High
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compare_builds() {
+        let root = tempfile::tempdir().unwrap();
+        for (name, main) in [
+            ("before", "fn main() {}\n"),
+            (
+                "after",
+                "static TABLE: [u8; 1 << 18] = [7; 1 << 18];\n\
+                 fn main() { println!(\"{}\", TABLE[std::env::args().count()]); }\n",
+            ),
+        ] {
+            let crate_root = root.path().join(name);
+            std::fs::create_dir_all(crate_root.join("src")).unwrap();
+            std::fs::write(
+                crate_root.join("Cargo.toml"),
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+            )
+            .unwrap();
+            std::fs::write(crate_root.join("src/main.rs"), main).unwrap();
+        }
+
+        let deadline = Instant::now() + std::time::Duration::from_secs(300);
+        let measurement = compare(
+            &Isolation::Host,
+            &root.path().join("before"),
+            &root.path().join("after"),
+            deadline,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            measurement.after.artifacts.keys().collect::<Vec<_>>(),
+            ["release/app"]
+        );
+        assert!(measurement.size_delta_bytes >= 1 << 18);
+        assert!(matches!(
+            measurement.performance_impact(),
+            PerformanceImpact::High
+        ));
+    }
+}
@@ -0,0 +1,925 @@
+mod calver;
+mod conan;
+mod debian;
+mod maven;
+mod nuget;
+mod pep440;
+mod rubygems;
+mod semantic;
+
+pub use calver::CalVerScheme;
+pub use conan::ConanScheme;
+pub use debian::DebianScheme;
+pub use maven::MavenScheme;
+pub use nuget::NuGetScheme;
+pub use pep440::Pep440Scheme;
+pub use rubygems::RubyGemsScheme;
+pub use semantic::SemanticScheme;
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Versioning rules for an ecosystem: how versions are parsed and ordered.
+pub trait VersionScheme: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String>;
+
+    /// Whether a partial version in a comparator is padded with zeros
+    /// (`==1.2` is `1.2.0` alone) rather than standing for every release
+    /// it prefixes, as npm and Cargo read `=1.2`. Wildcards (`1.2.*`) are
+    /// prefixes either way.
+    fn pads_partial_versions(&self) -> bool {
+        false
+    }
+}
+
+/// Returns the versioning scheme used by the given ecosystem.
+pub fn scheme_for(ecosystem: &str) -> &'static dyn VersionScheme {
+    match ecosystem {
+        "pip" | "pipenv" | "conda" => &Pep440Scheme,
+        "maven" | "gradle" => &MavenScheme,
+        "nuget" => &NuGetScheme,
+        "conan" | "vcpkg" | "docker" => &ConanScheme,
+        "rubygems" | "cocoapods" => &RubyGemsScheme,
+        "debian" | "os-packages" => &DebianScheme,
+        _ => &SemanticScheme,
+    }
+}
+
+/// Like [`scheme_for`], but switches to [`CalVerScheme`] when every sample
+/// (versions or requirements as written in a request) is a calendar version.
+/// Only SemVer and PEP 440 ecosystems are considered; Maven and Debian
+/// versions are left to their own schemes.
+pub fn detect_scheme(ecosystem: &str, samples: &[&str]) -> &'static dyn VersionScheme {
+    let default = scheme_for(ecosystem);
+    if !matches!(default.name(), "semver" | "pep440") {
+        return default;
+    }
+
+    let calendar = |sample: &&str| {
+        let version = sample.trim_start_matches(|c: char| "^~=<>! ".contains(c));
+        CalVerScheme.parse(version).is_ok()
+    };
+    if !samples.is_empty() && samples.iter().all(calendar) {
+        &CalVerScheme
+    } else {
+        default
+    }
+}
+
+/// A version parsed by a [`VersionScheme`]. Ordering and equality use a
+/// scheme-specific sort key, so versions are only comparable within a scheme.
+#[derive(Debug, Clone)]
+pub struct ParsedVersion {
+    raw: String,
+    release: Vec<u64>,
+    prerelease: bool,
+    calendar: bool,
+    key: Vec<KeyPart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum KeyPart {
+    Num(u64),
+    Text(String),
+    Weights(Vec<u32>),
+}
+
+impl ParsedVersion {
+    fn new(raw: &str, release: Vec<u64>, prerelease: bool, key: Vec<KeyPart>) -> Self {
+        Self {
+            raw: raw.trim().to_string(),
+            release,
+            prerelease,
+            calendar: false,
+            key,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// Numeric release components, e.g. `[1, 2, 3]` for `1.2.3-rc.1`.
+    pub fn release(&self) -> &[u64] {
+        &self.release
+    }
+
+    pub fn component(&self, index: usize) -> u64 {
+        self.release.get(index).copied().unwrap_or(0)
+    }
+
+    pub fn major(&self) -> u64 {
+        self.component(0)
+    }
+
+    pub fn minor(&self) -> u64 {
+        self.component(1)
+    }
+
+    pub fn patch(&self) -> u64 {
+        self.component(2)
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        self.prerelease
+    }
+
+    /// Whether the leading components are a calendar date rather than a
+    /// compatibility signal.
+    pub fn is_calendar(&self) -> bool {
+        self.calendar
+    }
+}
+
+impl PartialEq for ParsedVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for ParsedVersion {}
+
+impl PartialOrd for ParsedVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ParsedVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl Hash for ParsedVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl fmt::Display for ParsedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+/// A version as written in an upgrade request: either a concrete release or a
+/// requirement that has to be resolved against the registry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionSpec {
+    Exact(ParsedVersion),
+    Requirement(Requirement),
+    /// Newest published release: `latest`/`*` (stable only) or `next`
+    /// (pre-releases included).
+    Latest {
+        include_prereleases: bool,
+    },
+    /// Any other npm dist-tag (`lts`, `beta`, ...); only the registry can
+    /// say which version it points at.
+    Tag(String),
+}
+
+// npm tag names are URL-safe and must not look like a version or range, so
+// dotted strings such as `a.b` stay invalid versions rather than tags.
+fn is_dist_tag_name(raw: &str) -> bool {
+    raw.starts_with(|c: char| c.is_ascii_alphabetic())
+        && raw
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// A parsed version requirement. Unions (`||` for npm, comma-separated
+/// ranges for Maven) are kept as alternatives; a version matches if any
+/// alternative matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    pub raw: String,
+    alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Alternative {
+    bounds: Vec<Bound>,
+    // Release components of pre-release versions named in this alternative;
+    // other pre-releases never match (npm/cargo semantics).
+    prerelease_releases: Vec<Vec<u64>>,
+    allow_prereleases: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Bound {
+    Eq(ParsedVersion),
+    Ne(ParsedVersion),
+    Gt(ParsedVersion),
+    Ge(ParsedVersion),
+    Lt(ParsedVersion),
+    Le(ParsedVersion),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Caret,
+    Tilde,
+    Compatible,
+}
+
+// Longest operators first so `>=` is not read as `>`.
+const OPERATORS: &[(&str, Operator)] = &[
+    ("===", Operator::Eq),
+    ("==", Operator::Eq),
+    ("!=", Operator::Ne),
+    (">=", Operator::Ge),
+    ("<=", Operator::Le),
+    ("~=", Operator::Compatible),
+    ("~>", Operator::Compatible),
+    (">", Operator::Gt),
+    ("<", Operator::Lt),
+    ("=", Operator::Eq),
+    ("^", Operator::Caret),
+    ("~", Operator::Tilde),
+];
+
+impl VersionSpec {
+    pub fn parse(ecosystem: &str, raw: &str) -> Result<Self, String> {
+        Self::parse_with(ecosystem, scheme_for(ecosystem), raw)
+    }
+
+    /// Parses `raw` with an explicit scheme, e.g. one picked by
+    /// [`detect_scheme`]. The ecosystem still decides requirement syntax.
+    pub fn parse_with(
+        ecosystem: &str,
+        scheme: &dyn VersionScheme,
+        raw: &str,
+    ) -> Result<Self, String> {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return Err("version cannot be empty".to_string());
+        }
+
+        match raw {
+            "latest" | "*" => {
+                return Ok(VersionSpec::Latest {
+                    include_prereleases: false,
+                })
+            }
+            "next" => {
+                return Ok(VersionSpec::Latest {
+                    include_prereleases: true,
+                })
+            }
+            _ => {}
+        }
+
+        if let Ok(version) = scheme.parse(raw) {
+            return Ok(VersionSpec::Exact(version));
+        }
+
+        Requirement::parse(ecosystem, scheme, raw)
+            .map(VersionSpec::Requirement)
+            .or_else(|e| {
+                if ecosystem == "npm" && is_dist_tag_name(raw) {
+                    Ok(VersionSpec::Tag(raw.to_string()))
+                } else {
+                    Err(e)
+                }
+            })
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, VersionSpec::Exact(_))
+    }
+
+    pub fn matches(&self, version: &ParsedVersion) -> bool {
+        match self {
+            VersionSpec::Exact(exact) => exact == version,
+            VersionSpec::Requirement(req) => req.matches(version),
+            VersionSpec::Latest {
+                include_prereleases,
+            } => *include_prereleases || !version.is_prerelease(),
+            VersionSpec::Tag(_) => false,
+        }
+    }
+
+    /// The npm dist-tag this spec names, if it is one.
+    pub fn dist_tag(&self) -> Option<&str> {
+        match self {
+            VersionSpec::Latest {
+                include_prereleases: false,
+            } => Some("latest"),
+            VersionSpec::Latest {
+                include_prereleases: true,
+            } => Some("next"),
+            VersionSpec::Tag(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Picks the highest candidate satisfying this spec.
+    pub fn resolve<'a, I>(&self, candidates: I) -> Option<ParsedVersion>
+    where
+        I: IntoIterator<Item = &'a ParsedVersion>,
+    {
+        candidates
+            .into_iter()
+            .filter(|candidate| self.matches(candidate))
+            .max()
+            .cloned()
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSpec::Exact(version) => write!(f, "{}", version),
+            VersionSpec::Requirement(req) => write!(f, "{}", req.raw),
+            VersionSpec::Latest {
+                include_prereleases: false,
+            } => write!(f, "latest"),
+            VersionSpec::Latest {
+                include_prereleases: true,
+            } => write!(f, "next"),
+            VersionSpec::Tag(tag) => write!(f, "{}", tag),
+        }
+    }
+}
+
+impl Requirement {
+    pub fn parse(ecosystem: &str, scheme: &dyn VersionScheme, raw: &str) -> Result<Self, String> {
+        // Elixir spells out unions and intersections: `>= 1.0.0 and < 2.0.0 or ~> 3.0`
+        let normalized = match ecosystem {
+            "hex" => raw.replace(" or ", " || ").replace(" and ", " "),
+            _ => raw.to_string(),
+        };
+
+        let alternatives = if raw.starts_with('[') || raw.starts_with('(') {
+            parse_interval_union(scheme, raw)?
+        } else {
+            normalized
+                .split("||")
+                .map(|alternative| parse_alternative(ecosystem, scheme, alternative.trim()))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        Ok(Requirement {
+            raw: raw.to_string(),
+            alternatives,
+        })
+    }
+
+    pub fn matches(&self, version: &ParsedVersion) -> bool {
+        self.alternatives.iter().any(|alternative| {
+            if version.is_prerelease()
+                && !alternative.allow_prereleases
+                && !alternative
+                    .prerelease_releases
+                    .iter()
+                    .any(|release| same_release(release, version.release()))
+            {
+                return false;
+            }
+
+            alternative.bounds.iter().all(|bound| match bound {
+                Bound::Eq(v) => version == v,
+                Bound::Ne(v) => version != v,
+                Bound::Gt(v) => version > v,
+                Bound::Ge(v) => version >= v,
+                Bound::Lt(v) => version < v,
+                Bound::Le(v) => version <= v,
+            })
+        })
+    }
+}
+
+fn parse_alternative(
+    ecosystem: &str,
+    scheme: &dyn VersionScheme,
+    raw: &str,
+) -> Result<Alternative, String> {
+    let mut alternative = Alternative::default();
+
+    // npm hyphen ranges: `1.2.3 - 2.3.4`
+    if let Some((low, high)) = raw.split_once(" - ") {
+        let (low, _) = parse_partial(scheme, low.trim())?;
+        let (high, precision) = parse_partial(scheme, high.trim())?;
+        push_prerelease(&mut alternative, &low);
+        alternative.bounds.push(Bound::Ge(low));
+        alternative
+            .bounds
+            .push(upper_inclusive(scheme, high, precision)?);
+        return Ok(alternative);
+    }
+
+    // Comparators are separated by commas (cargo, pip) or whitespace (npm)
+    let mut comparators: Vec<String> = Vec::new();
+    for token in raw.split(|c: char| c == ',' || c.is_whitespace()) {
+        if token.is_empty() {
+            continue;
+        }
+        match comparators.last_mut() {
+            // Re-attach versions separated from their operator (`>= 1.2`)
+            Some(last) if last.chars().all(|c| "<>=~^!".contains(c)) => last.push_str(token),
+            _ => comparators.push(token.to_string()),
+        }
+    }
+
+    for comparator in comparators {
+        let (operator, written) = split_operator(ecosystem, &comparator);
+        let version = written.trim_end_matches(".*").trim_end_matches(".x");
+        if matches!(version, "*" | "x" | "X" | "") {
+            continue;
+        }
+        let wildcard = version.len() < written.len();
+
+        let (version, precision) = parse_partial(scheme, version)?;
+        push_prerelease(&mut alternative, &version);
+        let bounds = expand(scheme, operator, version, precision, wildcard)?;
+        alternative.bounds.extend(bounds);
+    }
+
+    Ok(alternative)
+}
+
+fn split_operator<'a>(ecosystem: &str, comparator: &'a str) -> (Operator, &'a str) {
+    for (symbol, operator) in OPERATORS {
+        if let Some(rest) = comparator.strip_prefix(symbol) {
+            return (*operator, rest.trim());
+        }
+    }
+
+    // A bare requirement means caret for cargo and an exact (or wildcard
+    // prefix) match everywhere else.
+    let operator = match ecosystem {
+        "cargo" => Operator::Caret,
+        _ => Operator::Eq,
+    };
+    (operator, comparator)
+}
+
+/// Parses a possibly partial version (`1`, `1.2`) and returns it along with
+/// the number of release components that were actually specified.
+fn parse_partial(scheme: &dyn VersionScheme, raw: &str) -> Result<(ParsedVersion, usize), String> {
+    let raw = raw.trim_start_matches(['v', 'V']);
+    let numeric = raw
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .next()
+        .unwrap_or("");
+    let suffix = &raw[numeric.len()..];
+    let components: Vec<&str> = numeric.split('.').filter(|c| !c.is_empty()).collect();
+
+    if !suffix.is_empty() || components.is_empty() {
+        let version = scheme
+            .parse(raw)
+            .map_err(|e| format!("invalid requirement '{}': {}", raw, e))?;
+        let precision = version.release().len();
+        return Ok((version, precision));
+    }
+
+    let version = scheme
+        .parse(&components.join("."))
+        .or_else(|_| scheme.parse(&pad_release(&components)))
+        .map_err(|e| format!("invalid requirement '{}': {}", raw, e))?;
+
+    Ok((version, components.len()))
+}
+
+fn pad_release(components: &[&str]) -> String {
+    let mut padded: Vec<&str> = components.to_vec();
+    while padded.len() < 3 {
+        padded.push("0");
+    }
+    padded.join(".")
+}
+
+fn expand(
+    scheme: &dyn VersionScheme,
+    operator: Operator,
+    version: ParsedVersion,
+    precision: usize,
+    wildcard: bool,
+) -> Result<Vec<Bound>, String> {
+    let partial =
+        precision < 3 && !version.is_prerelease() && (wildcard || !scheme.pads_partial_versions());
+
+    let bounds = match operator {
+        Operator::Eq if partial => vec![
+            Bound::Ge(version.clone()),
+            Bound::Lt(bump(scheme, &version, precision.saturating_sub(1))?),
+        ],
+        Operator::Eq => vec![Bound::Eq(version)],
+        Operator::Ne => vec![Bound::Ne(version)],
+        Operator::Gt if partial => vec![Bound::Ge(bump(
+            scheme,
+            &version,
+            precision.saturating_sub(1),
+        )?)],
+        Operator::Gt => vec![Bound::Gt(version)],
+        Operator::Ge => vec![Bound::Ge(version)],
+        Operator::Lt => vec![Bound::Lt(version)],
+        Operator::Le if partial => vec![upper_inclusive(scheme, version, precision)?],
+        Operator::Le => vec![Bound::Le(version)],
+        Operator::Caret => {
+            let significant = (0..precision)
+                .find(|&i| version.component(i) != 0)
+                .unwrap_or(precision.saturating_sub(1));
+            let upper = bump(scheme, &version, significant)?;
+            vec![Bound::Ge(version), Bound::Lt(upper)]
+        }
+        Operator::Tilde => {
+            let index = if precision >= 2 { 1 } else { 0 };
+            let upper = bump(scheme, &version, index)?;
+            vec![Bound::Ge(version), Bound::Lt(upper)]
+        }
+        Operator::Compatible => {
+            let upper = bump(scheme, &version, precision.saturating_sub(2))?;
+            vec![Bound::Ge(version), Bound::Lt(upper)]
+        }
+    };
+
+    Ok(bounds)
+}
+
+fn upper_inclusive(
+    scheme: &dyn VersionScheme,
+    version: ParsedVersion,
+    precision: usize,
+) -> Result<Bound, String> {
+    if precision < 3 && !version.is_prerelease() {
+        Ok(Bound::Lt(bump(
+            scheme,
+            &version,
+            precision.saturating_sub(1),
+        )?))
+    } else {
+        Ok(Bound::Le(version))
+    }
+}
+
+/// Smallest version whose component at `index` is one higher, with all later
+/// components zeroed (`bump(1.4.2, 1) == 1.5.0`).
+fn bump(
+    scheme: &dyn VersionScheme,
+    version: &ParsedVersion,
+    index: usize,
+) -> Result<ParsedVersion, String> {
+    let mut components: Vec<u64> = (0..=index.max(2)).map(|i| version.component(i)).collect();
+    components[index] += 1;
+    for component in components.iter_mut().skip(index + 1) {
+        *component = 0;
+    }
+
+    let raw = components
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(".");
+    scheme.parse(&raw)
+}
+
+fn push_prerelease(alternative: &mut Alternative, version: &ParsedVersion) {
+    if version.is_prerelease() {
+        alternative
+            .prerelease_releases
+            .push(version.release().to_vec());
+    }
+}
+
+/// Maven-style interval unions: `[1.0,2.0)`, `(,1.5]`, `[1.2]`, `[1,2),[3,4)`.
+fn parse_interval_union(scheme: &dyn VersionScheme, raw: &str) -> Result<Vec<Alternative>, String> {
+    let mut alternatives = Vec::new();
+    let mut rest = raw.trim();
+
+    while !rest.is_empty() {
+        let close = rest
+            .find([']', ')'])
+            .ok_or_else(|| format!("unterminated range in '{}'", raw))?;
+        let (interval, remainder) = rest.split_at(close + 1);
+        alternatives.push(parse_interval(scheme, interval)?);
+        rest = remainder.trim_start_matches(',').trim();
+    }
+
+    Ok(alternatives)
+}
+
+fn parse_interval(scheme: &dyn VersionScheme, interval: &str) -> Result<Alternative, String> {
+    if interval.len() < 2 || !interval.starts_with(['[', '(']) {
+        return Err(format!("invalid range '{}'", interval));
+    }
+    let lower_inclusive = interval.starts_with('[');
+    let upper_inclusive = interval.ends_with(']');
+    let inner = &interval[1..interval.len() - 1];
+    let parse = |raw: &str| {
+        scheme
+            .parse(raw.trim())
+            .map_err(|e| format!("invalid range '{}': {}", interval, e))
+    };
+
+    // Maven ranges include qualified versions such as snapshots
+    let mut alternative = Alternative {
+        allow_prereleases: true,
+        ..Alternative::default()
+    };
+
+    match inner.split_once(',') {
+        None => alternative.bounds.push(Bound::Eq(parse(inner)?)),
+        Some((low, high)) => {
+            if !low.trim().is_empty() {
+                let low = parse(low)?;
+                alternative.bounds.push(if lower_inclusive {
+                    Bound::Ge(low)
+                } else {
+                    Bound::Gt(low)
+                });
+            }
+            if !high.trim().is_empty() {
+                let high = parse(high)?;
+                alternative.bounds.push(if upper_inclusive {
+                    Bound::Le(high)
+                } else {
+                    Bound::Lt(high)
+                });
+            }
+        }
+    }
+
+    Ok(alternative)
+}
+
+fn same_release(a: &[u64], b: &[u64]) -> bool {
+    let len = a.len().max(b.len());
+    (0..len).all(|i| a.get(i).copied().unwrap_or(0) == b.get(i).copied().unwrap_or(0))
+}
+
+/// How far apart two versions are, from the perspective of the upgrade.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VersionJump {
+    #[default]
+    None,
+    Patch,
+    Prerelease,
+    Minor,
+    Major,
+    Downgrade,
+}
+
+impl VersionJump {
+    pub fn classify(current: &ParsedVersion, target: &ParsedVersion) -> Self {
+        match target.cmp(current) {
+            Ordering::Less => return VersionJump::Downgrade,
+            Ordering::Equal => return VersionJump::None,
+            Ordering::Greater => {}
+        }
+
+        let len = current.release().len().max(target.release().len());
+        match (0..len).find(|&i| current.component(i) != target.component(i)) {
+            // A new year or period is just the passage of time under CalVer
+            Some(0 | 1) if current.is_calendar() && target.is_calendar() => VersionJump::Minor,
+            Some(0) => VersionJump::Major,
+            Some(1) => VersionJump::Minor,
+            Some(_) => VersionJump::Patch,
+            None if current.is_prerelease() || target.is_prerelease() => VersionJump::Prerelease,
+            // Post releases, build revisions and epochs with equal releases
+            None => VersionJump::Patch,
+        }
+    }
+}
+
+/// Whether `target` falls outside the caret range of `current` under cargo
+/// and npm rules: the leftmost non-zero component is the breaking one, so
+/// `0.3 -> 0.4` and `0.0.3 -> 0.0.4` are breaking while `1.3 -> 1.4` is not.
+pub fn breaks_caret_range(current: &ParsedVersion, target: &ParsedVersion) -> bool {
+    if target <= current || current.is_calendar() && target.is_calendar() {
+        return false;
+    }
+
+    let significant = (0..2).find(|&i| current.component(i) != 0).unwrap_or(2);
+    (0..=significant).any(|i| current.component(i) != target.component(i))
+}
+
+/// Concrete versions an upgrade operates on after requirement resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedVersions {
+    pub current: ParsedVersion,
+    pub target: ParsedVersion,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> ParsedVersion {
+        SemanticScheme.parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_exact_and_requirements() {
+        assert_eq!(
+            VersionSpec::parse("cargo", "1.2.3").unwrap(),
+            VersionSpec::Exact(v("1.2.3"))
+        );
+        assert!(!VersionSpec::parse("cargo", "^1.2").unwrap().is_exact());
+        assert!(!VersionSpec::parse("npm", ">=2 <3").unwrap().is_exact());
+        assert!(!VersionSpec::parse("pip", ">=2,<3").unwrap().is_exact());
+        assert!(VersionSpec::parse("npm", "not a version").is_err());
+        assert!(VersionSpec::parse("npm", "").is_err());
+    }
+
+    #[test]
+    fn test_requirement_matching() {
+        let caret = VersionSpec::parse("cargo", "^1.2").unwrap();
+        assert!(caret.matches(&v("1.9.0")));
+        assert!(!caret.matches(&v("2.0.0")));
+        assert!(!caret.matches(&v("2.0.0-alpha.1")));
+
+        let tilde = VersionSpec::parse("npm", "~0.9").unwrap();
+        assert!(tilde.matches(&v("0.9.4")));
+        assert!(!tilde.matches(&v("0.10.0")));
+
+        let range = VersionSpec::parse("npm", ">= 2 < 3").unwrap();
+        assert!(range.matches(&v("2.5.0")));
+        assert!(!range.matches(&v("3.0.0")));
+
+        let union = VersionSpec::parse("npm", "^1.0.0 || ^3.0.0").unwrap();
+        assert!(union.matches(&v("3.1.0")));
+        assert!(!union.matches(&v("2.0.0")));
+
+        let hyphen = VersionSpec::parse("npm", "1.2.3 - 2.3.4").unwrap();
+        assert!(hyphen.matches(&v("2.3.4")));
+        assert!(!hyphen.matches(&v("2.3.5")));
+
+        let wildcard = VersionSpec::parse("npm", "1.2.x").unwrap();
+        assert!(wildcard.matches(&v("1.2.9")));
+        assert!(!wildcard.matches(&v("1.3.0")));
+
+        let prerelease = VersionSpec::parse("npm", ">=2.0.0-rc.1").unwrap();
+        assert!(prerelease.matches(&v("2.0.0-rc.2")));
+        assert!(!prerelease.matches(&v("2.1.0-beta.1")));
+    }
+
+    #[test]
+    fn test_scheme_specific_requirements() {
+        let pep440 = |s: &str| Pep440Scheme.parse(s).unwrap();
+        let compatible = VersionSpec::parse("pip", "~=1.4.2").unwrap();
+        assert!(compatible.matches(&pep440("1.4.9")));
+        assert!(!compatible.matches(&pep440("1.5")));
+        let elixir = VersionSpec::parse("hex", ">= 1.0.0 and < 1.5.0 or ~> 2.1").unwrap();
+        assert!(elixir.matches(&v("1.4.9")));
+        assert!(!elixir.matches(&v("1.5.0")));
+        assert!(elixir.matches(&v("2.9.0")));
+        assert!(!elixir.matches(&v("3.0.0")));
+
+        let pinned = VersionSpec::parse("pip", "==2.0.*").unwrap();
+        assert!(pinned.matches(&pep440("2.0.post1")));
+        assert!(!pinned.matches(&pep440("2.1")));
+
+        // PEP 440 pads partial versions with zeros instead of reading them
+        // as prefixes
+        let pip = |raw: &str| VersionSpec::parse("pip", raw).unwrap();
+        assert!(pip("==1.2").matches(&pep440("1.2.0")));
+        assert!(!pip("==1.2").matches(&pep440("1.2.5")));
+        assert!(pip(">1.2").matches(&pep440("1.2.5")));
+        assert!(!pip("<=1.2").matches(&pep440("1.2.5")));
+        assert!(pip("<=1.2").matches(&pep440("1.2")));
+        assert!(pip("~=1.2").matches(&pep440("1.9")));
+        assert!(!VersionSpec::parse("npm", "=1.2")
+            .unwrap()
+            .matches(&v("1.3.0")));
+        assert!(VersionSpec::parse("npm", "=1.2")
+            .unwrap()
+            .matches(&v("1.2.5")));
+
+        let maven = |s: &str| MavenScheme.parse(s).unwrap();
+        let range = VersionSpec::parse("maven", "[1.0,2.0)").unwrap();
+        assert!(range.matches(&maven("1.5")));
+        assert!(!range.matches(&maven("2.0")));
+        let union = VersionSpec::parse("maven", "(,1.0],[1.2,)").unwrap();
+        assert!(union.matches(&maven("0.9")));
+        assert!(!union.matches(&maven("1.1")));
+        assert!(union.matches(&maven("1.3")));
+        for malformed in ["[1.0,2.0),)", "[1.0,2.0),1.0]", "(1.0]]"] {
+            assert!(
+                VersionSpec::parse("maven", malformed).is_err(),
+                "{}",
+                malformed
+            );
+        }
+        assert!(parse_interval(&MavenScheme, "1.0]").is_err());
+    }
+
+    #[test]
+    fn test_version_jump_classification() {
+        let jump = |a: &str, b: &str| VersionJump::classify(&v(a), &v(b));
+
+        assert_eq!(jump("1.2.3", "2.0.0"), VersionJump::Major);
+        assert_eq!(jump("1.2.0", "1.9.0"), VersionJump::Minor);
+        assert_eq!(jump("1.2.3", "1.2.4"), VersionJump::Patch);
+        assert_eq!(jump("2.0.0-beta.1", "2.0.0-rc.1"), VersionJump::Prerelease);
+        assert_eq!(jump("2.0.0-rc.1", "2.0.0"), VersionJump::Prerelease);
+        assert_eq!(jump("1.2.4", "1.2.3"), VersionJump::Downgrade);
+        assert_eq!(jump("1.2.3", "1.2.3+build.7"), VersionJump::None);
+
+        let pep440 = |s: &str| Pep440Scheme.parse(s).unwrap();
+        assert_eq!(
+            VersionJump::classify(&pep440("1.0"), &pep440("1.0.post1")),
+            VersionJump::Patch
+        );
+        assert_eq!(
+            VersionJump::classify(&pep440("1!1.0"), &pep440("2.0")),
+            VersionJump::Downgrade
+        );
+
+        let calver = |s: &str| CalVerScheme.parse(s).unwrap();
+        assert_eq!(
+            VersionJump::classify(&calver("2023.12.4"), &calver("2024.01.0")),
+            VersionJump::Minor
+        );
+        assert_eq!(
+            VersionJump::classify(&calver("2024.03.1"), &calver("2024.03.2")),
+            VersionJump::Patch
+        );
+    }
+
+    #[test]
+    fn test_caret_breaking_ranges() {
+        let breaks = |a: &str, b: &str| breaks_caret_range(&v(a), &v(b));
+
+        assert!(breaks("0.3.2", "0.4.0"));
+        assert!(breaks("0.0.3", "0.0.4"));
+        assert!(breaks("1.9.0", "2.0.0"));
+        assert!(!breaks("0.3.2", "0.3.9"));
+        assert!(!breaks("1.3.0", "1.4.0"));
+        assert!(!breaks("0.4.0", "0.3.2"));
+    }
+
+    #[test]
+    fn test_detect_calendar_scheme() {
+        assert_eq!(
+            detect_scheme("npm", &["2024.03.1", "^2024.04"]).name(),
+            "calver"
+        );
+        assert_eq!(
+            detect_scheme("pip", &["2023.7.22", "2024.2.2"]).name(),
+            "calver"
+        );
+        assert_eq!(
+            detect_scheme("npm", &["2024.03.1", "latest"]).name(),
+            "semver"
+        );
+        assert_eq!(detect_scheme("cargo", &["1.2.3", "2.0.0"]).name(), "semver");
+        assert_eq!(
+            detect_scheme("debian", &["2024.03.1", "2024.04"]).name(),
+            "debian"
+        );
+
+        let spec = VersionSpec::parse_with("npm", &CalVerScheme, "^2024.03").unwrap();
+        assert!(spec.matches(&CalVerScheme.parse("2024.11.2").unwrap()));
+        assert!(!spec.matches(&CalVerScheme.parse("2025.01.0").unwrap()));
+    }
+
+    #[test]
+    fn test_latest_keywords() {
+        let published = [v("1.9.1"), v("2.0.0"), v("2.1.0-beta.1")];
+
+        let latest = VersionSpec::parse("cargo", "latest").unwrap();
+        assert_eq!(latest.resolve(&published), Some(v("2.0.0")));
+        assert_eq!(VersionSpec::parse("npm", "*").unwrap(), latest);
+
+        assert_eq!(
+            VersionSpec::parse("npm", "lts").unwrap(),
+            VersionSpec::Tag("lts".to_string())
+        );
+        assert!(VersionSpec::parse("cargo", "lts").is_err());
+
+        let next = VersionSpec::parse("npm", "next").unwrap();
+        assert_eq!(next.resolve(&published), Some(v("2.1.0-beta.1")));
+    }
+
+    #[test]
+    fn test_resolve_picks_highest_match() {
+        let published = [
+            v("1.0.0"),
+            v("1.4.2"),
+            v("1.9.1"),
+            v("2.0.0"),
+            v("2.1.0-beta.1"),
+        ];
+
+        let spec = VersionSpec::parse("cargo", ">=2,<3").unwrap();
+        assert_eq!(spec.resolve(&published), Some(v("2.0.0")));
+
+        let spec = VersionSpec::parse("cargo", "^1.2").unwrap();
+        assert_eq!(spec.resolve(&published), Some(v("1.9.1")));
+
+        let spec = VersionSpec::parse("cargo", "^4").unwrap();
+        assert_eq!(spec.resolve(&published), None);
+    }
+}
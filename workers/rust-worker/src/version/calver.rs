@@ -0,0 +1,82 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+
+/// Calendar versioning with a four-digit year first (`2024.03.1`,
+/// `2024.3-rc.1`). Components may be zero-padded, unlike SemVer.
+pub struct CalVerScheme;
+
+// Shorter versions are padded so `2024.03` and `2024.3.0` compare equal.
+const RELEASE_WIDTH: usize = 3;
+
+impl VersionScheme for CalVerScheme {
+    fn name(&self) -> &'static str {
+        "calver"
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        let unprefixed = trimmed.strip_prefix('v').unwrap_or(trimmed);
+        let without_build = unprefixed.split('+').next().unwrap_or_default();
+        let (release, pre) = match without_build.split_once('-') {
+            Some((release, pre)) => (release, Some(pre)),
+            None => (without_build, None),
+        };
+
+        let invalid = || format!("'{}' is not a calendar version", trimmed);
+        let components = release
+            .split('.')
+            .map(|c| {
+                if !c.is_empty() && c.chars().all(|ch| ch.is_ascii_digit()) {
+                    c.parse::<u64>().map_err(|_| invalid())
+                } else {
+                    Err(invalid())
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let year = release.split('.').next().unwrap_or_default();
+        if components.len() < 2 || year.len() != 4 || !(1990..=2999).contains(&components[0]) {
+            return Err(invalid());
+        }
+        if pre.is_some_and(str::is_empty) {
+            return Err(invalid());
+        }
+
+        let mut key: Vec<KeyPart> = (0..components.len().max(RELEASE_WIDTH))
+            .map(|i| KeyPart::Num(components.get(i).copied().unwrap_or(0)))
+            .collect();
+        match pre {
+            None => key.push(KeyPart::Num(1)),
+            Some(pre) => {
+                key.push(KeyPart::Num(0));
+                key.extend(pre.split('.').map(|identifier| match identifier.parse() {
+                    Ok(number) => KeyPart::Num(number),
+                    Err(_) => KeyPart::Text(identifier.to_string()),
+                }));
+            }
+        }
+
+        Ok(ParsedVersion {
+            calendar: true,
+            ..ParsedVersion::new(trimmed, components, pre.is_some(), key)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calver_ordering() {
+        let v = |s: &str| CalVerScheme.parse(s).unwrap();
+
+        assert!(v("2023.12.5") < v("2024.01.0"));
+        assert!(v("2024.03.1") < v("2024.10"));
+        assert!(v("2024.3-rc.1") < v("2024.3"));
+        assert_eq!(v("2024.03"), v("v2024.3.0"));
+        assert!(v("2024.03.1").is_calendar());
+        assert!(CalVerScheme.parse("1.2.3").is_err());
+        assert!(CalVerScheme.parse("2024").is_err());
+        assert!(CalVerScheme.parse("2024.x").is_err());
+    }
+}
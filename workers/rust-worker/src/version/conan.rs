@@ -0,0 +1,77 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+
+/// Conan 2 versions: any number of dot-separated items compared numerically
+/// when both are numbers (`1.83.0`, `20230802.1`, `cci.20230101`), with a
+/// `-` pre-release and an ignored `+` build suffix. vcpkg port versions
+/// and Docker image tags follow the same relaxed rules.
+pub struct ConanScheme;
+
+const RELEASE_WIDTH: usize = 4;
+
+fn item_key(item: &str) -> KeyPart {
+    match item.parse() {
+        Ok(number) => KeyPart::Num(number),
+        Err(_) => KeyPart::Text(item.to_ascii_lowercase()),
+    }
+}
+
+impl VersionScheme for ConanScheme {
+    fn name(&self) -> &'static str {
+        "conan"
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        let invalid = || format!("'{}' is not a valid Conan version", trimmed);
+
+        let without_build = trimmed.split('+').next().unwrap_or_default();
+        let (version, pre) = match without_build.split_once('-') {
+            Some((version, pre)) => (version, Some(pre)),
+            None => (without_build, None),
+        };
+        let valid_items = |text: &str| {
+            text.split('.').all(|item| {
+                !item.is_empty() && item.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            })
+        };
+        if !valid_items(version) || !pre.is_none_or(valid_items) {
+            return Err(invalid());
+        }
+
+        let items: Vec<&str> = version.split('.').collect();
+        let release: Vec<u64> = items
+            .iter()
+            .map_while(|item| item.parse::<u64>().ok())
+            .collect();
+
+        let mut key: Vec<KeyPart> = items.iter().map(|item| item_key(item)).collect();
+        key.resize(key.len().max(RELEASE_WIDTH), KeyPart::Num(0));
+        match pre {
+            None => key.push(KeyPart::Num(1)),
+            Some(pre) => {
+                key.push(KeyPart::Num(0));
+                key.extend(pre.split('.').map(item_key));
+            }
+        }
+
+        Ok(ParsedVersion::new(trimmed, release, pre.is_some(), key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conan_ordering() {
+        let v = |s: &str| ConanScheme.parse(s).unwrap();
+
+        assert!(v("1.2.13") < v("1.3"));
+        assert!(v("1.83.0") < v("1.84.0"));
+        assert!(v("20230802.1") < v("20240116.1"));
+        assert!(v("2.0-pre") < v("2.0"));
+        assert_eq!(v("2.0"), v("2.0.0+build1"));
+        assert_eq!(v("cci.20230101").release(), &[] as &[u64]);
+        assert!(ConanScheme.parse("1..2").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+
+/// Debian package versions (`[epoch:]upstream[-revision]`) ordered the way
+/// `dpkg --compare-versions` does, including `~` sorting before everything.
+pub struct DebianScheme;
+
+// Number of (non-digit, digit) pairs each part is padded to, so that a
+// missing trailing part compares as empty rather than as "shorter".
+const PART_PAIRS: usize = 12;
+
+impl VersionScheme for DebianScheme {
+    fn name(&self) -> &'static str {
+        "debian"
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        let invalid = || format!("'{}' is not a valid Debian version", trimmed);
+
+        let (epoch, rest) = match trimmed.split_once(':') {
+            Some((epoch, rest)) => (epoch.parse::<u64>().map_err(|_| invalid())?, rest),
+            None => (0, trimmed),
+        };
+        let (upstream, revision) = match rest.rsplit_once('-') {
+            Some((upstream, revision)) => (upstream, revision),
+            None => (rest, ""),
+        };
+
+        let allowed = |c: char| c.is_ascii_alphanumeric() || ".+~-:".contains(c);
+        if !upstream.starts_with(|c: char| c.is_ascii_digit()) || !rest.chars().all(allowed) {
+            return Err(invalid());
+        }
+
+        let release = upstream
+            .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .filter_map(|c| c.parse::<u64>().ok())
+            .collect();
+
+        let mut key = vec![KeyPart::Num(epoch)];
+        encode_part(upstream, &mut key);
+        encode_part(revision, &mut key);
+
+        Ok(ParsedVersion::new(
+            trimmed,
+            release,
+            upstream.contains('~'),
+            key,
+        ))
+    }
+}
+
+fn char_weight(c: char) -> u32 {
+    match c {
+        '~' => 0,
+        c if c.is_ascii_alphabetic() => c as u32 + 2,
+        c => c as u32 + 258,
+    }
+}
+
+// End of a non-digit run sorts after `~` but before any other character
+const END_WEIGHT: u32 = 1;
+
+fn encode_part(part: &str, key: &mut Vec<KeyPart>) {
+    let mut rest = part;
+    let mut pairs = 0;
+
+    while !rest.is_empty() || pairs < PART_PAIRS {
+        let letters_end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (letters, remainder) = rest.split_at(letters_end);
+        let digits_end = remainder
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(remainder.len());
+        let (digits, remainder) = remainder.split_at(digits_end);
+
+        let mut weights: Vec<u32> = letters.chars().map(char_weight).collect();
+        weights.push(END_WEIGHT);
+        key.push(KeyPart::Weights(weights));
+        key.push(KeyPart::Num(digits.parse().unwrap_or(0)));
+
+        rest = remainder;
+        pairs += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debian_ordering() {
+        let v = |s: &str| DebianScheme.parse(s).unwrap();
+
+        assert!(v("1.0~rc1") < v("1.0"));
+        assert!(v("1.0") < v("1.0a"));
+        assert!(v("1.0-1") < v("1.0-2"));
+        assert!(v("1.0-9") < v("1.0-10"));
+        assert!(v("2.30-1ubuntu1") < v("2.30-1ubuntu2"));
+        assert!(v("9.9") < v("1:0.1"));
+        assert!(v("1.2.3~beta1-1").is_prerelease());
+        assert!(DebianScheme.parse("not-a-version").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+
+/// Maven `ComparableVersion` ordering, including qualifiers such as
+/// `-SNAPSHOT`, `-alpha-1` and `.RELEASE`.
+pub struct MavenScheme;
+
+const RELEASE_WIDTH: usize = 6;
+
+// Rank of a plain release (`1.0`, `1.0-ga`, `1.0.Final`)
+const RELEASE_RANK: u64 = 6;
+
+fn qualifier_rank(qualifier: &str) -> Option<u64> {
+    match qualifier {
+        "alpha" | "a" => Some(1),
+        "beta" | "b" => Some(2),
+        "milestone" | "m" => Some(3),
+        "rc" | "cr" => Some(4),
+        "snapshot" => Some(5),
+        "" | "ga" | "final" | "release" => Some(RELEASE_RANK),
+        "sp" => Some(7),
+        _ => None,
+    }
+}
+
+impl VersionScheme for MavenScheme {
+    fn name(&self) -> &'static str {
+        "maven"
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || !trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!("'{}' is not a valid Maven version", trimmed));
+        }
+        if trimmed.contains(|c: char| c.is_whitespace() || "[](),".contains(c)) {
+            return Err(format!("'{}' is a version range, not a version", trimmed));
+        }
+
+        let lowered = trimmed.to_ascii_lowercase();
+        let numeric_end = lowered
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(lowered.len());
+        let release = lowered[..numeric_end]
+            .split('.')
+            .filter(|c| !c.is_empty())
+            .map(|c| c.parse::<u64>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Qualifier: `-SNAPSHOT`, `.RELEASE`, `-beta-2`, `-1` (build number)
+        let qualifier = lowered[numeric_end..].trim_start_matches(['-', '.']);
+        let label: String = qualifier
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        let qualifier_number = qualifier[label.len()..]
+            .trim_start_matches(['-', '.'])
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .and_then(|n| n.parse::<u64>().ok())
+            .unwrap_or(0);
+
+        let mut key: Vec<KeyPart> = (0..RELEASE_WIDTH.max(release.len()))
+            .map(|i| KeyPart::Num(release.get(i).copied().unwrap_or(0)))
+            .collect();
+        let rank = qualifier_rank(&label);
+        // Unknown qualifiers sort after all known ones, lexically
+        key.push(KeyPart::Num(rank.unwrap_or(8)));
+        key.push(KeyPart::Text(if rank.is_some() {
+            String::new()
+        } else {
+            label
+        }));
+        key.push(KeyPart::Num(qualifier_number));
+
+        Ok(ParsedVersion::new(
+            trimmed,
+            release,
+            rank.map(|rank| rank < RELEASE_RANK).unwrap_or(false),
+            key,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maven_ordering() {
+        let v = |s: &str| MavenScheme.parse(s).unwrap();
+
+        assert!(v("1.0-alpha-1") < v("1.0-beta"));
+        assert!(v("1.0-beta-2") < v("1.0-beta-10"));
+        assert!(v("1.0-rc1") < v("1.0-SNAPSHOT"));
+        assert!(v("1.0-SNAPSHOT") < v("1.0"));
+        assert_eq!(v("1.0"), v("1.0.0.RELEASE"));
+        assert!(v("1.0") < v("1.0-sp1"));
+        assert!(v("1.0-sp1") < v("1.0.1"));
+        assert!(v("1.0-SNAPSHOT").is_prerelease());
+        assert!(MavenScheme.parse("[1.0,2.0)").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+
+/// NuGet versions: SemVer 2 with an optional fourth (revision) component,
+/// case-insensitive release labels, and normalization so `1.0` equals
+/// `1.0.0.0`.
+pub struct NuGetScheme;
+
+const RELEASE_WIDTH: usize = 4;
+
+impl VersionScheme for NuGetScheme {
+    fn name(&self) -> &'static str {
+        "nuget"
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        let invalid = || format!("'{}' is not a valid NuGet version", trimmed);
+
+        let without_metadata = trimmed.split('+').next().unwrap_or_default();
+        let (release, label) = match without_metadata.split_once('-') {
+            Some((release, label)) => (release, Some(label)),
+            None => (without_metadata, None),
+        };
+
+        let components = release
+            .split('.')
+            .map(|c| {
+                if !c.is_empty() && c.chars().all(|ch| ch.is_ascii_digit()) {
+                    c.parse::<u64>().map_err(|_| invalid())
+                } else {
+                    Err(invalid())
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if components.is_empty() || components.len() > RELEASE_WIDTH {
+            return Err(invalid());
+        }
+
+        let mut key: Vec<KeyPart> = (0..RELEASE_WIDTH)
+            .map(|i| KeyPart::Num(components.get(i).copied().unwrap_or(0)))
+            .collect();
+        match label {
+            None => key.push(KeyPart::Num(1)),
+            Some(label) => {
+                let identifiers: Vec<&str> = label.split('.').collect();
+                if identifiers.iter().any(|identifier| {
+                    identifier.is_empty()
+                        || !identifier
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+                }) {
+                    return Err(invalid());
+                }
+                key.push(KeyPart::Num(0));
+                key.extend(
+                    identifiers
+                        .iter()
+                        .map(|identifier| match identifier.parse() {
+                            Ok(number) => KeyPart::Num(number),
+                            Err(_) => KeyPart::Text(identifier.to_ascii_lowercase()),
+                        }),
+                );
+            }
+        }
+
+        Ok(ParsedVersion::new(
+            trimmed,
+            components,
+            label.is_some(),
+            key,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nuget_ordering() {
+        let v = |s: &str| NuGetScheme.parse(s).unwrap();
+
+        assert_eq!(v("1.0"), v("1.0.0.0"));
+        assert!(v("1.0.0.1") > v("1.0.0"));
+        assert_eq!(v("2.0.0-Beta"), v("2.0.0-beta"));
+        assert!(v("2.0.0-beta.2") < v("2.0.0-beta.10"));
+        assert!(v("2.0.0-rc.1") < v("2.0.0"));
+        assert_eq!(v("6.0.1+sha.5a2c"), v("6.0.1"));
+        assert!(NuGetScheme.parse("1.0.0.0.0").is_err());
+        assert!(NuGetScheme.parse("1.0-").is_err());
+    }
+}
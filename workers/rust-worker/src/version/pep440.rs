@@ -0,0 +1,127 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// PEP 440 versions used by pip: epochs, pre/post/dev releases and local
+/// version labels.
+pub struct Pep440Scheme;
+
+// Release segments are zero-padded so versions of different lengths compare
+// correctly (`1.0 == 1.0.0`).
+const RELEASE_WIDTH: usize = 8;
+
+fn pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"(?ix)^v?
+            (?:(?P<epoch>[0-9]+)!)?
+            (?P<release>[0-9]+(?:\.[0-9]+)*)
+            (?:[-_.]?(?P<pre_l>alpha|beta|preview|pre|rc|a|b|c)[-_.]?(?P<pre_n>[0-9]+)?)?
+            (?:-(?P<post_n1>[0-9]+)|[-_.]?(?P<post_l>post|rev|r)[-_.]?(?P<post_n2>[0-9]+)?)?
+            (?:[-_.]?(?P<dev_l>dev)[-_.]?(?P<dev_n>[0-9]+)?)?
+            (?:\+(?P<local>[a-z0-9]+(?:[-_.][a-z0-9]+)*))?$",
+        )
+        .expect("valid PEP 440 pattern")
+    })
+}
+
+impl VersionScheme for Pep440Scheme {
+    fn name(&self) -> &'static str {
+        "pep440"
+    }
+
+    fn pads_partial_versions(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        let captures = pattern()
+            .captures(trimmed)
+            .ok_or_else(|| format!("'{}' is not a valid PEP 440 version", trimmed))?;
+        let number = |name: &str| -> Result<Option<u64>, String> {
+            captures
+                .name(name)
+                .map(|m| m.as_str().parse::<u64>().map_err(|e| e.to_string()))
+                .transpose()
+        };
+
+        let epoch = number("epoch")?.unwrap_or(0);
+        let release = captures["release"]
+            .split('.')
+            .map(|c| c.parse::<u64>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pre_phase = captures.name("pre_l").map(|label| {
+            match label.as_str().to_ascii_lowercase().as_str() {
+                "a" | "alpha" => 1,
+                "b" | "beta" => 2,
+                _ => 3,
+            }
+        });
+        let pre_number = number("pre_n")?.unwrap_or(0);
+        let post = match number("post_n1")? {
+            Some(n) => Some(n),
+            None if captures.name("post_l").is_some() => Some(number("post_n2")?.unwrap_or(0)),
+            None => None,
+        };
+        let dev = if captures.name("dev_l").is_some() {
+            Some(number("dev_n")?.unwrap_or(0))
+        } else {
+            None
+        };
+
+        let mut key = vec![KeyPart::Num(epoch)];
+        key.extend(
+            (0..RELEASE_WIDTH.max(release.len()))
+                .map(|i| KeyPart::Num(release.get(i).copied().unwrap_or(0))),
+        );
+        // A bare dev release sorts before every pre-release of the same version
+        let phase = match (pre_phase, post, dev) {
+            (Some(phase), _, _) => phase,
+            (None, None, Some(_)) => 0,
+            _ => 4,
+        };
+        key.push(KeyPart::Num(phase));
+        key.push(KeyPart::Num(pre_number));
+        key.push(KeyPart::Num(post.map(|n| n + 1).unwrap_or(0)));
+        key.push(KeyPart::Num(dev.unwrap_or(u64::MAX)));
+        key.push(KeyPart::Text(
+            captures
+                .name("local")
+                .map(|m| m.as_str().to_ascii_lowercase())
+                .unwrap_or_default(),
+        ));
+
+        Ok(ParsedVersion::new(
+            trimmed,
+            release,
+            pre_phase.is_some() || dev.is_some(),
+            key,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pep440_ordering() {
+        let v = |s: &str| Pep440Scheme.parse(s).unwrap();
+
+        assert!(v("1.0.dev1") < v("1.0a1"));
+        assert!(v("1.0a1") < v("1.0b2"));
+        assert!(v("1.0b2") < v("1.0rc1"));
+        assert!(v("1.0rc1") < v("1.0"));
+        assert!(v("1.0") < v("1.0.post1"));
+        assert!(v("1.0.post1.dev1") < v("1.0.post1"));
+        assert!(v("1.0.post1") < v("1.1"));
+        assert!(v("2.0") < v("1!1.0"));
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert!(v("2.0.0rc1").is_prerelease());
+        assert!(!v("2.0.post3").is_prerelease());
+        assert!(Pep440Scheme.parse("1.0-SNAPSHOT").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+
+/// `Gem::Version` ordering: dot-separated segments where any letter makes
+/// the version a pre-release (`7.1.0.rc2`, `1.0.0-beta` is `1.0.0.pre.beta`).
+pub struct RubyGemsScheme;
+
+const RELEASE_WIDTH: usize = 6;
+
+impl VersionScheme for RubyGemsScheme {
+    fn name(&self) -> &'static str {
+        "rubygems"
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        let invalid = || format!("'{}' is not a valid gem version", trimmed);
+        if !trimmed.starts_with(|c: char| c.is_ascii_digit())
+            || !trimmed
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        {
+            return Err(invalid());
+        }
+
+        // Split into segments at dots and at letter/digit boundaries
+        let normalized = trimmed.replace('-', ".pre.");
+        let mut segments: Vec<String> = Vec::new();
+        for part in normalized.split('.') {
+            if part.is_empty() {
+                return Err(invalid());
+            }
+            let mut current = String::new();
+            for c in part.chars() {
+                if !current.is_empty()
+                    && current.ends_with(|last: char| last.is_ascii_digit()) != c.is_ascii_digit()
+                {
+                    segments.push(std::mem::take(&mut current));
+                }
+                current.push(c);
+            }
+            segments.push(current);
+        }
+
+        let release_len = segments
+            .iter()
+            .position(|segment| !segment.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or(segments.len());
+        let release = segments[..release_len]
+            .iter()
+            .map(|segment| segment.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let prerelease = release_len < segments.len();
+
+        let mut key: Vec<KeyPart> = (0..RELEASE_WIDTH.max(release.len()))
+            .map(|i| KeyPart::Num(release.get(i).copied().unwrap_or(0)))
+            .collect();
+        if prerelease {
+            // Letters sort before numbers within the pre-release part
+            key.push(KeyPart::Num(0));
+            for segment in &segments[release_len..] {
+                match segment.parse::<u64>() {
+                    Ok(number) => key.extend([KeyPart::Num(1), KeyPart::Num(number)]),
+                    Err(_) => key.extend([KeyPart::Num(0), KeyPart::Text(segment.clone())]),
+                }
+            }
+        } else {
+            key.push(KeyPart::Num(1));
+        }
+
+        Ok(ParsedVersion::new(trimmed, release, prerelease, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rubygems_ordering() {
+        let v = |s: &str| RubyGemsScheme.parse(s).unwrap();
+
+        assert!(v("7.1.0.beta1") < v("7.1.0.rc1"));
+        assert!(v("7.1.0.rc1") < v("7.1.0.rc2"));
+        assert!(v("7.1.0.rc2") < v("7.1.0"));
+        assert!(v("7.0.8.1") > v("7.0.8"));
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert!(v("1.0.0-beta").is_prerelease());
+        assert!(RubyGemsScheme.parse("v1.0").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use super::{KeyPart, ParsedVersion, VersionScheme};
+use semver::{Prerelease, Version};
+
+/// Semantic Versioning 2.0 (cargo, npm, Go modules). A leading `v` is
+/// accepted since Go and many git tags use it.
+pub struct SemanticScheme;
+
+impl VersionScheme for SemanticScheme {
+    fn name(&self) -> &'static str {
+        "semver"
+    }
+
+    fn parse(&self, raw: &str) -> Result<ParsedVersion, String> {
+        let trimmed = raw.trim();
+        let version = Version::parse(trimmed.strip_prefix('v').unwrap_or(trimmed))
+            .map_err(|e| e.to_string())?;
+
+        let mut key = vec![
+            KeyPart::Num(version.major),
+            KeyPart::Num(version.minor),
+            KeyPart::Num(version.patch),
+        ];
+        if version.pre == Prerelease::EMPTY {
+            key.push(KeyPart::Num(1));
+        } else {
+            key.push(KeyPart::Num(0));
+            // Numeric identifiers sort below alphanumeric ones
+            key.extend(
+                version
+                    .pre
+                    .split('.')
+                    .map(|identifier| match identifier.parse() {
+                        Ok(number) => KeyPart::Num(number),
+                        Err(_) => KeyPart::Text(identifier.to_string()),
+                    }),
+            );
+        }
+
+        Ok(ParsedVersion::new(
+            trimmed,
+            vec![version.major, version.minor, version.patch],
+            !version.pre.is_empty(),
+            key,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semver_ordering() {
+        let v = |s: &str| SemanticScheme.parse(s).unwrap();
+
+        assert!(v("1.0.0-alpha") < v("1.0.0-alpha.1"));
+        assert!(v("1.0.0-alpha.1") < v("1.0.0-alpha.beta"));
+        assert!(v("1.0.0-beta.2") < v("1.0.0-beta.11"));
+        assert!(v("1.0.0-rc.1") < v("1.0.0"));
+        assert_eq!(v("v1.2.3"), v("1.2.3+build.5"));
+        assert!(SemanticScheme.parse("1.0").is_err());
+        assert!(SemanticScheme.parse("a.b").is_err());
+    }
+}